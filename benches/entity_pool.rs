@@ -0,0 +1,95 @@
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+
+use bevy_ecs::prelude::*;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use scene_editor::components::{Mesh, Position, TransformBundle};
+use scene_editor::entity_pool;
+
+/// Objects spawned and despawned each frame
+const CHURN: usize = 1000;
+
+fn mesh() -> Mesh {
+    Mesh {
+        vao_id: glow::NativeVertexArray(NonZeroU32::new(1).unwrap()),
+        indices_len: 36,
+        aabb: Default::default(),
+        has_tangents: false,
+    }
+}
+
+fn transform(i: usize) -> TransformBundle {
+    TransformBundle { position: Position::new(i as f32, 0.0, 0.0), ..Default::default() }
+}
+
+/// A frame that replaces every object, like a burst of particles
+fn spawn_despawn_frame(world: &mut World, live: &mut Vec<Entity>) {
+    for entity in live.drain(..) {
+        world.despawn(entity);
+    }
+    for i in 0..CHURN {
+        live.push(world.spawn((mesh(), transform(i))).id());
+    }
+}
+
+/// The same frame with the objects taken from and given back to the pool
+fn pooled_frame(world: &mut World, live: &mut Vec<Entity>) {
+    for entity in live.drain(..) {
+        entity_pool::release(entity, world);
+    }
+    for i in 0..CHURN {
+        live.push(entity_pool::spawn(world, mesh(), transform(i)));
+    }
+}
+
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn");
+    group.bench_function("spawn_despawn", |b| {
+        b.iter_batched_ref(
+            || (World::new(), Vec::new()),
+            |(world, live)| {
+                for _ in 0..10 {
+                    spawn_despawn_frame(world, live);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("pooled", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut world = World::new();
+                entity_pool::reserve(&mut world, mesh(), CHURN);
+                (world, Vec::new())
+            },
+            |(world, live)| {
+                for _ in 0..10 {
+                    pooled_frame(world, live);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+
+    // Criterion reports averages, while frame spikes show in the slowest frame
+    let slowest = |frame: fn(&mut World, &mut Vec<Entity>), world: &mut World| {
+        let mut live = Vec::new();
+        (0..200)
+            .map(|_| {
+                let start = Instant::now();
+                frame(world, &mut live);
+                start.elapsed()
+            })
+            .max()
+            .unwrap_or(Duration::ZERO)
+    };
+    let spawn_despawn = slowest(spawn_despawn_frame, &mut World::new());
+    let mut pooled_world = World::new();
+    entity_pool::reserve(&mut pooled_world, mesh(), CHURN);
+    let pooled = slowest(pooled_frame, &mut pooled_world);
+    println!("slowest frame: spawn_despawn {spawn_despawn:?}, pooled {pooled:?}");
+}
+
+criterion_group!(benches, churn);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+#version 410 core
+
+layout(location = 0) in vec3 in_pos;
+
+uniform mat4 vp;
+
+void main() {
+    gl_Position = vp * vec4(in_pos, 1.0);
+}
@@ -1,72 +1,88 @@
 #version 410 core
 
-in vec2 tex_coords;
-
 out vec4 out_frag_color;
 
-struct DirLight {
-    vec3 direction;
-
-    vec3 ambient;
-    vec3 diffuse;
-    vec3 specular;
-};
-
-struct PointLight {
-    vec3 position;
-
-    vec3 ambient;
-    vec3 diffuse;
-    vec3 specular;
-
-    float constant;
-    float linear;
-    float quadratic;
-};
+#include "lighting.glsl"
 
+// SAMPLES is defined when the G-buffer is multisampled, and each sample is lit on its own
+#ifdef SAMPLES
+uniform sampler2DMS position_tx;
+uniform sampler2DMS normal_tx;
+uniform sampler2DMS albedo_spec_tx;
+uniform sampler2DMS velocity_tx;
+#else
+#define SAMPLES 1
 uniform sampler2D position_tx;
 uniform sampler2D normal_tx;
 uniform sampler2D albedo_spec_tx;
+uniform sampler2D velocity_tx;
+#endif
+uniform bool show_motion_vectors;
+uniform bool show_light_heatmap;
+uniform bool shadows_enabled;
+uniform bool lighting_enabled;
 
 uniform vec3 view_pos;
+uniform vec3 selection_color;
 uniform mat4 light_space_matrix;
+uniform float exposure;
+uniform vec3 background;
+// Draw a sky lit by the sun in place of the background color
+uniform bool sky_enabled;
+// Equirectangular HDR image drawn in place of the sky and the background color, which also lights
+// the scene in place of the ambient light of the sun
+uniform bool environment_enabled;
+uniform sampler2D environment_tx;
+// Turns a pixel of the view back into the direction it looks in
+uniform mat4 inverse_view_projection;
+
+struct Fog {
+    bool enabled;
+    vec3 color;
+    float density;
+    float start;
+    float height;
+    float height_falloff;
+};
+uniform Fog fog;
+// How far away the background is taken to be when it is fogged
+uniform float sky_distance;
 
 uniform DirLight dir_light;
-#define MAX_POINT_LIGHTS 128
-uniform int point_lights_size;
-uniform PointLight point_lights[MAX_POINT_LIGHTS];
-
-uniform sampler2DShadow shadow_map_tx;
-
-vec3 calculate_general_light(vec3 light_ambient, vec3 light_diffuse, vec3 light_specular, vec3 light_dir, vec3 normal, vec3 albedo, float specular_strength, vec3 view_dir, float shadow) {
-    float diff = max(dot(normal, light_dir), 0.0);
-    vec3 halfway_dir = normalize(light_dir + view_dir);
-    float spec = pow(max(dot(normal, halfway_dir), 0.0), 16.0);
 
-    vec3 ambient = light_ambient * albedo;
-    vec3 diffuse = light_diffuse * diff * albedo;
-    vec3 specular = light_specular * spec * specular_strength;
+// Point lights are sorted on the CPU into clusters of the view, split into columns and rows on
+// screen and into slices by depth. Each light takes a row of four texels: its position and
+// constant attenuation, then its ambient, diffuse and specular colors with the linear and
+// quadratic attenuation.
+uniform sampler2D light_tx;
+// Start and count of the light indices of each cluster, with a row per depth slice
+uniform usampler2D cluster_tx;
+uniform usampler2D light_index_tx;
+uniform int light_index_row;
+uniform vec3 cluster_count;
+// Near and far distance the depth slices are spread between
+uniform vec2 cluster_depth;
+// Whether the depth slices are all as deep, as for orthographic views, rather than deeper further
+// from the camera
+uniform bool linear_slices;
+uniform mat4 view;
+// Position and size of the view in the window
+uniform vec4 view_rect;
 
-    return ambient + shadow * (diffuse + specular);
-}
-
-vec3 calculate_dir_light(vec3 normal, vec3 albedo, float specular_strength, vec3 view_dir, float shadow) {
+uniform sampler2DShadow shadow_map_tx;
+// Width of the square of shadow map samples blended at shadow edges
+uniform int shadow_kernel_size;
+// Largest depth bias, reached by surfaces at a grazing angle to the sun
+uniform float shadow_depth_bias;
+// Distance surfaces are moved along their normal before looking up the shadow map
+uniform float shadow_normal_offset;
+
+float calculate_shadow(vec3 frag_pos, vec3 normal) {
     vec3 light_dir = normalize(-dir_light.direction);
-    return calculate_general_light(dir_light.ambient, dir_light.diffuse, dir_light.specular, light_dir, normal, albedo, specular_strength, view_dir, shadow);
-}
-
-vec3 calculate_point_light(PointLight light, vec3 frag_pos, vec3 normal, vec3 albedo, float specular_strength, vec3 view_dir) {
-    vec3 light_dir = normalize(light.position - frag_pos);
-    float distance = length(light.position - frag_pos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance + light.quadratic * (distance * distance));
-
-    vec3 color = calculate_general_light(light.ambient, light.diffuse, light.specular, light_dir, normal, albedo, specular_strength, view_dir, 1.0);
-    color *= attenuation;
-
-    return color;
-}
-
-float calculate_shadow(vec4 frag_pos_light_space, vec3 normal) {
+    float n_dot_l = max(dot(normal, light_dir), 0.0);
+    // Surfaces facing away from the sun are moved further, where they are most prone to acne
+    vec3 offset_pos = frag_pos + normal * shadow_normal_offset * (1.0 - n_dot_l);
+    vec4 frag_pos_light_space = light_space_matrix * vec4(offset_pos, 1.0);
     vec3 proj_coords = frag_pos_light_space.xyz / frag_pos_light_space.w;
     proj_coords = proj_coords * 0.5 + 0.5;
 
@@ -75,50 +91,175 @@ float calculate_shadow(vec4 frag_pos_light_space, vec3 normal) {
     }
 
     float current_depth = proj_coords.z;
-    vec3 light_dir = normalize(-dir_light.direction);
-    float bias = clamp(0.005 * tan(acos(max(dot(normal, light_dir), 0.0))), 0.0, 0.01);
+    float bias = clamp(0.5 * shadow_depth_bias * tan(acos(n_dot_l)), 0.0, shadow_depth_bias);
 
+    // Each sample is already blended between texels by the comparison filter
     float shadow = 0.0;
     vec2 texel_size = 1.0 / textureSize(shadow_map_tx, 0);
-    for (float y = -1.5; y <= 1.5; y += 1.0) {
-        for (float x = -1.5; x <= 1.5; x += 1.0) {
-            shadow += texture(shadow_map_tx, vec3(proj_coords.xy + vec2(x, y) * texel_size, current_depth - bias));
+    int radius = shadow_kernel_size / 2;
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            vec2 coords = proj_coords.xy + vec2(x, y) * texel_size;
+            shadow += texture(shadow_map_tx, vec3(coords, current_depth - bias));
         }
     }
-    shadow /= 16.0;
+    float kernel_size = float(2 * radius + 1);
+    shadow /= kernel_size * kernel_size;
 
     return shadow;
 }
 
-void main() {
-    vec4 pos = texture(position_tx, tex_coords);
+PointLight fetch_point_light(uint index) {
+    int row = int(index);
+    vec4 a = texelFetch(light_tx, ivec2(0, row), 0);
+    vec4 b = texelFetch(light_tx, ivec2(1, row), 0);
+    vec4 c = texelFetch(light_tx, ivec2(2, row), 0);
+    vec4 d = texelFetch(light_tx, ivec2(3, row), 0);
+    return PointLight(a.xyz, b.xyz, c.xyz, d.xyz, a.w, b.w, c.w);
+}
+
+// Start and count of the lights of the cluster a fragment is in
+uvec2 light_cluster(ivec2 coords, vec3 frag_pos) {
+    vec2 screen = (vec2(coords) + 0.5 - view_rect.xy) / view_rect.zw;
+    float depth = max(-(view * vec4(frag_pos, 1.0)).z, cluster_depth.x);
+    float slice = linear_slices
+        ? (depth - cluster_depth.x) / (cluster_depth.y - cluster_depth.x)
+        : log(depth / cluster_depth.x) / log(cluster_depth.y / cluster_depth.x);
+    ivec3 cluster = ivec3(vec3(screen, slice) * cluster_count);
+    cluster = clamp(cluster, ivec3(0), ivec3(cluster_count) - 1);
+    return texelFetch(cluster_tx, ivec2(cluster.y * int(cluster_count.x) + cluster.x, cluster.z), 0).rg;
+}
+
+// Direction the camera looks in through a pixel
+vec3 view_ray(ivec2 coords) {
+    vec2 ndc = (vec2(coords) + 0.5 - view_rect.xy) / view_rect.zw * 2.0 - 1.0;
+    vec4 near = inverse_view_projection * vec4(ndc, -1.0, 1.0);
+    vec4 far = inverse_view_projection * vec4(ndc, 1.0, 1.0);
+    return normalize(far.xyz / far.w - near.xyz / near.w);
+}
+
+// Gradient from the horizon to the zenith that turns red and dark as the sun sets, with a glow
+// around the sun
+vec3 sky(vec3 dir) {
+    vec3 to_sun = normalize(-dir_light.direction);
+    float day = clamp(to_sun.y * 3.0 + 0.2, 0.0, 1.0);
+    vec3 zenith = mix(vec3(0.02, 0.03, 0.08), vec3(0.15, 0.35, 0.8), day);
+    vec3 horizon = mix(vec3(0.8, 0.35, 0.15), vec3(0.65, 0.75, 0.9), day);
+    horizon *= clamp(to_sun.y * 4.0 + 1.0, 0.15, 1.0);
+
+    vec3 color = mix(horizon, zenith, sqrt(max(dir.y, 0.0)));
+    // Below the horizon the sky fades to a darker ground color
+    color = mix(color, horizon * 0.3, clamp(-dir.y * 4.0, 0.0, 1.0));
+
+    float sun = max(dot(dir, to_sun), 0.0);
+    color += dir_light.diffuse * (0.4 * pow(sun, 8.0) + 4.0 * smoothstep(0.9995, 0.9998, sun));
+    return color;
+}
+
+// Where a direction is seen in the environment map, with +Y at the top and -Z in the middle
+vec2 environment_uv(vec3 dir) {
+    const float PI = 3.14159265;
+    return vec2(atan(dir.x, -dir.z) / (2.0 * PI) + 0.5, acos(clamp(dir.y, -1.0, 1.0)) / PI);
+}
+
+// Light arriving at a surface from the environment, from a mip level blurred to a few texels
+// across
+vec3 environment_light(vec3 normal) {
+    float level = max(log2(float(textureSize(environment_tx, 0).x)) - 3.0, 0.0);
+    return textureLod(environment_tx, environment_uv(normal), level).rgb;
+}
+
+// Fog along the ray from the camera to a position, which is denser lower down
+vec3 apply_fog(vec3 color, vec3 frag_pos) {
+    float dist = max(distance(view_pos, frag_pos) - fog.start, 0.0);
+    float amount = fog.density * dist;
+    if (fog.height_falloff > 0.0) {
+        // Integral of the density along the ray, which falls off exponentially with height
+        float camera = min(-fog.height_falloff * (view_pos.y - fog.height), 80.0);
+        float climb = fog.height_falloff * (frag_pos.y - view_pos.y);
+        float average = abs(climb) > 1e-4 ? (1.0 - exp(-climb)) / climb : 1.0;
+        amount *= exp(camera) * average;
+    }
+    return mix(color, fog.color, 1.0 - exp(-max(amount, 0.0)));
+}
+
+// Blue for no lights, through green, to red for 16 or more
+vec3 heatmap(uint count) {
+    float t = clamp(float(count) / 16.0, 0.0, 1.0);
+    return clamp(vec3(2.0 * t - 1.0, 1.0 - abs(2.0 * t - 1.0), 1.0 - 2.0 * t), 0.0, 1.0);
+}
+
+// Color of one sample of the G-buffer, which is the mip level for textures that are not
+// multisampled
+vec3 shade(ivec2 coords, int s) {
+    vec4 pos = texelFetch(position_tx, coords, s);
     vec3 frag_pos = pos.rgb;
 
+    if (show_motion_vectors) {
+        // Scaled up so slow motion is visible, with gray for no motion
+        vec2 velocity = texelFetch(velocity_tx, coords, s).rg;
+        return vec3(vec2(0.5) + velocity * 20.0, 0.5);
+    }
+
     if (pos.a == 1.0) {
-        out_frag_color = vec4(1.0, 0.5, 0.0, 1.0);
-        return;
+        return selection_color;
     }
 
-    vec3 normal = texture(normal_tx, tex_coords).rgb;
+    vec3 normal = texelFetch(normal_tx, coords, s).rgb;
 
     if (normal == vec3(0.0, 0.0, 0.0)) {
-        out_frag_color = vec4(0.4, 0.4, 1.0, 1.0);
-        return;
+        if (!sky_enabled && !environment_enabled && !fog.enabled) {
+            return background;
+        }
+        vec3 dir = view_ray(coords);
+        vec3 color = background;
+        if (environment_enabled) {
+            color = textureLod(environment_tx, environment_uv(dir), 0.0).rgb;
+        } else if (sky_enabled) {
+            color = sky(dir);
+        }
+        return fog.enabled ? apply_fog(color, view_pos + dir * sky_distance) : color;
     }
 
-    vec3 albedo = texture(albedo_spec_tx, tex_coords).rgb;
-    float specular = texture(albedo_spec_tx, tex_coords).a;
+    vec4 albedo_spec = texelFetch(albedo_spec_tx, coords, s);
+    vec3 albedo = albedo_spec.rgb;
+    float specular = albedo_spec.a;
+
+    uvec2 cluster = light_cluster(coords, frag_pos);
+    if (show_light_heatmap) {
+        return mix(heatmap(cluster.y), albedo, 0.25);
+    }
+
+    if (!lighting_enabled) {
+        return albedo;
+    }
 
     vec3 view_dir = normalize(view_pos - frag_pos);
     vec3 result = vec3(0.0);
 
-    float shadow = calculate_shadow(light_space_matrix * vec4(frag_pos, 1.0), normal);
-    result += calculate_dir_light(normal, albedo, specular, view_dir, shadow);
+    float shadow = shadows_enabled ? calculate_shadow(frag_pos, normal) : 1.0;
+    result += calculate_dir_light(dir_light, normal, albedo, specular, view_dir, shadow);
+    if (environment_enabled) {
+        result += environment_light(normal) * albedo;
+    }
 
-    int size = min(point_lights_size, MAX_POINT_LIGHTS);
-    for (int i = 0; i < size; i++) {
-        result += calculate_point_light(point_lights[i], frag_pos, normal, albedo, specular, view_dir);
+    for (uint i = cluster.x; i < cluster.x + cluster.y; i++) {
+        ivec2 texel = ivec2(int(i) % light_index_row, int(i) / light_index_row);
+        PointLight light = fetch_point_light(texelFetch(light_index_tx, texel, 0).r);
+        result += calculate_point_light(light, frag_pos, normal, albedo, specular, view_dir);
     }
 
-    out_frag_color = vec4(result, 1.0);
+    result *= exposure;
+    return fog.enabled ? apply_fog(result, frag_pos) : result;
+}
+
+void main() {
+    // The G-buffer covers the whole window, so look it up by window position to support views
+    // that only cover part of it
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec3 color = vec3(0.0);
+    for (int s = 0; s < SAMPLES; s++) {
+        color += shade(coords, s);
+    }
+    out_frag_color = vec4(color / float(SAMPLES), 1.0);
 }
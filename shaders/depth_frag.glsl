@@ -1,5 +1,16 @@
 #version 410 core
 
+#ifdef ALPHA_TEST
+in vec2 tex_coords;
+
+uniform sampler2D diffuse_tx;
+#endif
+
 void main() {
-    // gl_FragDepth = gl_FragCoord.z;
+    // Cut out the same holes as the geometry pass, so light shines through them
+#ifdef ALPHA_TEST
+    if (texture(diffuse_tx, tex_coords).a < 0.5) {
+        discard;
+    }
+#endif
 }
@@ -0,0 +1,69 @@
+#version 410 core
+
+out vec4 out_frag_color;
+
+// SAMPLES is defined when the G-buffer is multisampled, and the depth of each pixel is read from
+// its first sample
+#ifdef SAMPLES
+uniform sampler2DMS position_tx;
+uniform sampler2DMS normal_tx;
+#else
+uniform sampler2D position_tx;
+uniform sampler2D normal_tx;
+#endif
+// The scene with reflections, which is what gets blurred
+uniform sampler2D color_tx;
+uniform bool depth_of_field_enabled;
+
+uniform vec3 view_pos;
+// Position and size of the view in the window
+uniform vec4 view_rect;
+uniform float focus_distance;
+uniform float aperture;
+// Largest blur radius, as a fraction of the height of the view
+uniform float max_blur;
+uniform int samples;
+
+const float GOLDEN_ANGLE = 2.39996323;
+
+ivec2 clamp_to_view(ivec2 coords) {
+    return clamp(coords, ivec2(view_rect.xy), ivec2(view_rect.xy + view_rect.zw) - 1);
+}
+
+// Radius in pixels of the circle a point at a pixel is blurred into
+float blur_radius(ivec2 coords) {
+    vec3 normal = texelFetch(normal_tx, coords, 0).rgb;
+    // The background is as far away as it gets
+    float ratio = 1.0;
+    if (normal != vec3(0.0)) {
+        float depth = max(distance(view_pos, texelFetch(position_tx, coords, 0).xyz), 0.001);
+        ratio = abs(depth - focus_distance) / depth;
+    }
+    return min(aperture * ratio, 1.0) * max_blur * view_rect.w;
+}
+
+void main() {
+    // The G-buffer covers the whole window, so look it up by window position to support views
+    // that only cover part of it
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec3 color = texelFetch(color_tx, coords, 0).rgb;
+    if (!depth_of_field_enabled) {
+        out_frag_color = vec4(color, 1.0);
+        return;
+    }
+
+    float radius = blur_radius(coords);
+    vec3 sum = color;
+    float total = 1.0;
+    // Samples spiral out over the circle of the pixel, and each only counts if its own blur
+    // reaches this pixel, so sharp surfaces do not bleed into the blur around them
+    for (int i = 1; i < samples; i++) {
+        float offset = radius * sqrt(float(i) / float(samples));
+        float angle = float(i) * GOLDEN_ANGLE;
+        ivec2 texel = clamp_to_view(coords + ivec2(round(offset * vec2(cos(angle), sin(angle)))));
+        float weight = clamp(blur_radius(texel) - offset + 1.0, 0.0, 1.0);
+        sum += texelFetch(color_tx, texel, 0).rgb * weight;
+        total += weight;
+    }
+    out_frag_color = vec4(sum / total, 1.0);
+}
@@ -1,10 +1,40 @@
 #version 410 core
 
+#define MAX_JOINTS 64
+
 layout(location = 0) in vec3 in_pos;
+layout(location = 2) in vec2 in_tex_coords;
+layout(location = 3) in vec4 in_joints;
+layout(location = 4) in vec4 in_weights;
+
+#ifdef ALPHA_TEST
+out vec2 tex_coords;
+#endif
 
 uniform mat4 light_space_matrix;
 uniform mat4 model;
 
+#if defined(SKINNED)
+const bool skinned = true;
+#elif defined(VARIANT)
+const bool skinned = false;
+#else
+// Custom shaders are compiled without feature defines, so they check at runtime
+uniform bool skinned;
+#endif
+uniform mat4 joint_matrices[MAX_JOINTS];
+
 void main() {
-    gl_Position = light_space_matrix * model * vec4(in_pos, 1.0);
+    mat4 skin = mat4(1.0);
+    if (skinned) {
+        skin = in_weights.x * joint_matrices[int(in_joints.x)]
+             + in_weights.y * joint_matrices[int(in_joints.y)]
+             + in_weights.z * joint_matrices[int(in_joints.z)]
+             + in_weights.w * joint_matrices[int(in_joints.w)];
+    }
+
+    gl_Position = light_space_matrix * model * skin * vec4(in_pos, 1.0);
+#ifdef ALPHA_TEST
+    tex_coords = in_tex_coords;
+#endif
 }
@@ -3,18 +3,53 @@
 in vec3 frag_pos;
 in vec3 normal;
 in vec2 tex_coords;
+in vec4 tangent;
+in vec4 splat;
+in vec4 clip_pos;
+in vec4 previous_clip_pos;
 
 layout(location = 0) out vec4 out_position;
 layout(location = 1) out vec3 out_normal;
 layout(location = 2) out vec4 out_albedo_spec;
+layout(location = 3) out vec2 out_velocity;
+layout(location = 4) out uint out_entity_id;
 
 uniform sampler2D diffuse_tx;
 uniform sampler2D specular_tx;
+uniform sampler2D normal_tx;
+// Layer textures of a terrain
+uniform sampler2D layer_tx[4];
 uniform float selected;
+// Index of the entity plus one, read back when picking
+uniform uint entity_id;
+
+#include "normal_map.glsl"
 
 void main() {
+#ifdef ALPHA_TEST
+    if (texture(diffuse_tx, tex_coords).a < 0.5) {
+        discard;
+    }
+#endif
+
     out_position = vec4(frag_pos, selected);
+#ifdef NORMAL_MAP
+    vec3 mapped = texture(normal_tx, tex_coords).rgb * 2.0 - 1.0;
+    out_normal = perturb_normal(normal, tangent, mapped);
+#else
     out_normal = normalize(normal);
+#endif
+#ifdef SPLAT
+    vec4 weights = splat / max(dot(splat, vec4(1.0)), 0.0001);
+    out_albedo_spec.rgb = texture(layer_tx[0], tex_coords).rgb * weights.x
+        + texture(layer_tx[1], tex_coords).rgb * weights.y
+        + texture(layer_tx[2], tex_coords).rgb * weights.z
+        + texture(layer_tx[3], tex_coords).rgb * weights.w;
+#else
     out_albedo_spec.rgb = texture(diffuse_tx, tex_coords).rgb;
+#endif
     out_albedo_spec.a = texture(specular_tx, tex_coords).r;
+    // Screen-space motion since the previous frame, in texture coordinates
+    out_velocity = (clip_pos.xy / clip_pos.w - previous_clip_pos.xy / previous_clip_pos.w) * 0.5;
+    out_entity_id = entity_id;
 }
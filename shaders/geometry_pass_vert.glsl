@@ -1,21 +1,58 @@
 #version 410 core
 
+#define MAX_JOINTS 64
+
 layout(location = 0) in vec3 in_pos;
 layout(location = 1) in vec3 in_normal;
 layout(location = 2) in vec2 in_tex_coords;
+layout(location = 3) in vec4 in_joints;
+layout(location = 4) in vec4 in_weights;
+layout(location = 5) in vec4 in_tangent;
+// Weight of each terrain layer
+layout(location = 6) in vec4 in_splat;
 
 out vec3 frag_pos;
 out vec3 normal;
 out vec2 tex_coords;
+// World-space tangent, with the handedness of the bitangent in w
+out vec4 tangent;
+out vec4 splat;
+out vec4 clip_pos;
+out vec4 previous_clip_pos;
 
 uniform mat4 mvp;
+// Model-view-projection matrix of the previous frame, for motion vectors
+uniform mat4 previous_mvp;
 uniform mat4 model;
 uniform mat3 normal_mat;
 
+#if defined(SKINNED)
+const bool skinned = true;
+#elif defined(VARIANT)
+const bool skinned = false;
+#else
+// Custom shaders are compiled without feature defines, so they check at runtime
+uniform bool skinned;
+#endif
+uniform mat4 joint_matrices[MAX_JOINTS];
+
 void main() {
-    frag_pos = vec3(model * vec4(in_pos, 1.0));
-    normal = normal_mat * in_normal;
+    mat4 skin = mat4(1.0);
+    if (skinned) {
+        skin = in_weights.x * joint_matrices[int(in_joints.x)]
+             + in_weights.y * joint_matrices[int(in_joints.y)]
+             + in_weights.z * joint_matrices[int(in_joints.z)]
+             + in_weights.w * joint_matrices[int(in_joints.w)];
+    }
+    vec4 pos = skin * vec4(in_pos, 1.0);
+
+    frag_pos = vec3(model * pos);
+    normal = normal_mat * mat3(skin) * in_normal;
     tex_coords = in_tex_coords;
+    tangent = vec4(mat3(model) * mat3(skin) * in_tangent.xyz, in_tangent.w);
+    splat = in_splat;
 
-    gl_Position = mvp * vec4(in_pos, 1.0);
+    clip_pos = mvp * pos;
+    previous_clip_pos = previous_mvp * pos;
+    gl_Position = clip_pos;
 }
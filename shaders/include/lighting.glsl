@@ -0,0 +1,49 @@
+// Blinn-Phong lighting, used by the deferred pass
+
+struct DirLight {
+    vec3 direction;
+
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+struct PointLight {
+    vec3 position;
+
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+
+    float constant;
+    float linear;
+    float quadratic;
+};
+
+vec3 calculate_general_light(vec3 light_ambient, vec3 light_diffuse, vec3 light_specular, vec3 light_dir, vec3 normal, vec3 albedo, float specular_strength, vec3 view_dir, float shadow) {
+    float diff = max(dot(normal, light_dir), 0.0);
+    vec3 halfway_dir = normalize(light_dir + view_dir);
+    float spec = pow(max(dot(normal, halfway_dir), 0.0), 16.0);
+
+    vec3 ambient = light_ambient * albedo;
+    vec3 diffuse = light_diffuse * diff * albedo;
+    vec3 specular = light_specular * spec * specular_strength;
+
+    return ambient + shadow * (diffuse + specular);
+}
+
+vec3 calculate_dir_light(DirLight light, vec3 normal, vec3 albedo, float specular_strength, vec3 view_dir, float shadow) {
+    vec3 light_dir = normalize(-light.direction);
+    return calculate_general_light(light.ambient, light.diffuse, light.specular, light_dir, normal, albedo, specular_strength, view_dir, shadow);
+}
+
+vec3 calculate_point_light(PointLight light, vec3 frag_pos, vec3 normal, vec3 albedo, float specular_strength, vec3 view_dir) {
+    vec3 light_dir = normalize(light.position - frag_pos);
+    float distance = length(light.position - frag_pos);
+    float attenuation = 1.0 / (light.constant + light.linear * distance + light.quadratic * (distance * distance));
+
+    vec3 color = calculate_general_light(light.ambient, light.diffuse, light.specular, light_dir, normal, albedo, specular_strength, view_dir, 1.0);
+    color *= attenuation;
+
+    return color;
+}
@@ -0,0 +1,9 @@
+// Tangent-space normal mapping, used by the geometry pass
+
+vec3 perturb_normal(vec3 normal, vec4 tangent, vec3 mapped) {
+    // MikkTSpace: the bitangent is rebuilt per pixel from the interpolated normal and tangent
+    vec3 n = normalize(normal);
+    vec3 t = normalize(tangent.xyz - n * dot(n, tangent.xyz));
+    vec3 b = tangent.w * cross(n, t);
+    return normalize(mat3(t, b, n) * mapped);
+}
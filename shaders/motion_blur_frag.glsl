@@ -0,0 +1,49 @@
+#version 410 core
+
+out vec4 out_frag_color;
+
+// SAMPLES is defined when the G-buffer is multisampled, and the motion of each pixel is read
+// from its first sample
+#ifdef SAMPLES
+uniform sampler2DMS velocity_tx;
+#else
+uniform sampler2D velocity_tx;
+#endif
+// The scene after depth of field, which is what gets blurred
+uniform sampler2D color_tx;
+uniform bool motion_blur_enabled;
+
+// Position and size of the view in the window
+uniform vec4 view_rect;
+// Part of the motion over a frame that is blurred
+uniform float strength;
+uniform int samples;
+
+void main() {
+    // The G-buffer covers the whole window, so look it up by window position to support views
+    // that only cover part of it
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec3 color = texelFetch(color_tx, coords, 0).rgb;
+    if (!motion_blur_enabled) {
+        out_frag_color = vec4(color, 1.0);
+        return;
+    }
+
+    // The velocity is how far the pixel moved on screen since the last frame, as a fraction of
+    // the view, and the blur is centered on where it is now
+    vec2 motion = texelFetch(velocity_tx, coords, 0).rg * view_rect.zw * strength;
+    if (dot(motion, motion) < 0.25) {
+        out_frag_color = vec4(color, 1.0);
+        return;
+    }
+
+    vec3 sum = vec3(0.0);
+    ivec2 view_min = ivec2(view_rect.xy);
+    ivec2 view_max = ivec2(view_rect.xy + view_rect.zw) - 1;
+    for (int i = 0; i < samples; i++) {
+        float t = (float(i) + 0.5) / float(samples) - 0.5;
+        ivec2 texel = clamp(coords - ivec2(round(motion * t)), view_min, view_max);
+        sum += texelFetch(color_tx, texel, 0).rgb;
+    }
+    out_frag_color = vec4(sum / float(samples), 1.0);
+}
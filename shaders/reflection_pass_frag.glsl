@@ -0,0 +1,91 @@
+#version 410 core
+
+out vec4 out_frag_color;
+
+// SAMPLES is defined when the G-buffer is multisampled, and reflections are traced from its
+// first sample
+#ifdef SAMPLES
+uniform sampler2DMS position_tx;
+uniform sampler2DMS normal_tx;
+uniform sampler2DMS albedo_spec_tx;
+#else
+uniform sampler2D position_tx;
+uniform sampler2D normal_tx;
+uniform sampler2D albedo_spec_tx;
+#endif
+// The lit scene, which is what the reflections show
+uniform sampler2D color_tx;
+uniform bool reflections_enabled;
+
+uniform vec3 view_pos;
+uniform mat4 view_projection;
+// Position and size of the view in the window
+uniform vec4 view_rect;
+// How far a reflected ray is followed, in steps of `max_distance / steps`
+uniform float max_distance;
+uniform int steps;
+// How far behind a surface the ray can be and still hit it
+uniform float thickness;
+
+// Pixel of the view a world position is drawn at, and how far it is from the edge of the view
+// from 0 at the edge to 1 in the middle
+vec3 project(vec3 world_pos) {
+    vec4 clip = view_projection * vec4(world_pos, 1.0);
+    if (clip.w <= 0.0) {
+        return vec3(-1.0);
+    }
+    vec2 ndc = clip.xy / clip.w;
+    vec2 edge = 1.0 - abs(ndc);
+    vec2 pixel = view_rect.xy + (ndc * 0.5 + 0.5) * view_rect.zw;
+    return vec3(pixel, min(edge.x, edge.y));
+}
+
+vec3 reflection(ivec2 coords, vec3 color) {
+    vec4 pos = texelFetch(position_tx, coords, 0);
+    vec3 normal = texelFetch(normal_tx, coords, 0).rgb;
+    float specular = texelFetch(albedo_spec_tx, coords, 0).a;
+    // The background, selection silhouettes and matte surfaces reflect nothing
+    if (normal == vec3(0.0) || pos.a == 1.0 || specular == 0.0) {
+        return color;
+    }
+
+    vec3 view_dir = normalize(pos.xyz - view_pos);
+    vec3 dir = reflect(view_dir, normalize(normal));
+    float step_length = max_distance / float(steps);
+    for (int i = 1; i <= steps; i++) {
+        vec3 ray = pos.xyz + dir * step_length * float(i);
+        vec3 screen = project(ray);
+        if (screen.z <= 0.0) {
+            break;
+        }
+
+        ivec2 texel = ivec2(screen.xy);
+        vec3 surface_normal = texelFetch(normal_tx, texel, 0).rgb;
+        if (surface_normal == vec3(0.0)) {
+            continue;
+        }
+        vec3 surface = texelFetch(position_tx, texel, 0).xyz;
+        float behind = distance(view_pos, ray) - distance(view_pos, surface);
+        if (behind > 0.0 && behind < thickness + step_length) {
+            // Fade out toward the edges of the view and the end of the ray, where the reflected
+            // surface would suddenly go missing
+            float fade = smoothstep(0.0, 0.1, screen.z) * (1.0 - float(i) / float(steps));
+            // Surfaces reflect more at grazing angles
+            float fresnel = mix(0.25, 1.0, pow(1.0 - max(dot(-view_dir, normal), 0.0), 5.0));
+            vec3 reflected = texelFetch(color_tx, texel, 0).rgb;
+            return mix(color, reflected, clamp(specular * fresnel * fade, 0.0, 1.0));
+        }
+    }
+    return color;
+}
+
+void main() {
+    // The G-buffer covers the whole window, so look it up by window position to support views
+    // that only cover part of it
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    vec3 color = texelFetch(color_tx, coords, 0).rgb;
+    if (reflections_enabled) {
+        color = reflection(coords, color);
+    }
+    out_frag_color = vec4(color, 1.0);
+}
@@ -0,0 +1,229 @@
+use egui::emath::RectTransform;
+use egui::epaint::text::FontDefinitions;
+use egui::epaint::{
+    tessellate_shapes, ClippedShape, FontImage, Fonts, ImageData, Primitive, TessellationOptions,
+    Vertex,
+};
+use egui::{pos2, vec2, Align2, Color32, FontId, Pos2, Rect, Shape, Stroke};
+
+/// Side of the font atlas used when drawing marks into an image
+const FONT_ATLAS_SIZE: usize = 4096;
+
+/// Gamma egui applies to font coverage, so text looks the same in the saved image as on screen
+const COVERAGE_GAMMA: f32 = 0.55;
+
+/// Tools for marking up a screenshot
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum MarkupTool {
+    #[default]
+    Arrow,
+    Box,
+    Text,
+}
+
+impl MarkupTool {
+    pub const ALL: [MarkupTool; 3] = [MarkupTool::Arrow, MarkupTool::Box, MarkupTool::Text];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MarkupTool::Arrow => "↗ Arrow",
+            MarkupTool::Box => "⬜ Box",
+            MarkupTool::Text => "🔤 Text",
+        }
+    }
+}
+
+/// What a mark is, in pixels from the top left of the screenshot
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Arrow { from: Pos2, to: Pos2 },
+    Box { rect: Rect },
+    Text { pos: Pos2, text: String },
+}
+
+/// An arrow, box or text drawn on a screenshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    pub annotation: Annotation,
+    pub color: Color32,
+    /// Line width of arrows and boxes, or the height of text, in pixels of the screenshot
+    pub size: f32,
+}
+
+impl Mark {
+    /// Shapes that draw the mark, where `to_screen` maps pixels of the screenshot to where it is
+    /// painted
+    pub fn shapes(&self, fonts: &Fonts, to_screen: RectTransform) -> Vec<Shape> {
+        let scale = to_screen.scale().x;
+        let stroke = Stroke::new(self.size * scale, self.color);
+        match &self.annotation {
+            Annotation::Arrow { from, to } => {
+                let (from, to) = (to_screen * *from, to_screen * *to);
+                let direction = (to - from).normalized();
+                let head = direction * (self.size * 4.0).max(12.0) * scale;
+                let left = to - head + head.rot90() * 0.5;
+                let right = to - head - head.rot90() * 0.5;
+                vec![
+                    Shape::line_segment([from, to], stroke),
+                    Shape::line(vec![left, to, right], stroke),
+                ]
+            }
+            Annotation::Box { rect } => {
+                vec![Shape::rect_stroke(to_screen.transform_rect(*rect), 0.0, stroke)]
+            }
+            Annotation::Text { pos, text } => {
+                let font = FontId::proportional(self.size * scale);
+                vec![Shape::text(fonts, to_screen * *pos, Align2::LEFT_TOP, text, font, self.color)]
+            }
+        }
+    }
+}
+
+/// A screenshot of the scene view that is being marked up
+pub struct Markup {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA8 pixels, starting with the top row
+    pub pixels: Vec<u8>,
+    pub marks: Vec<Mark>,
+    pub tool: MarkupTool,
+    pub color: Color32,
+    pub line_width: f32,
+    pub text_size: f32,
+    /// Text placed by the text tool
+    pub text: String,
+    pub path: String,
+    /// Where the arrow or box being dragged out starts
+    pub drag_start: Option<Pos2>,
+    pub texture: Option<egui::TextureHandle>,
+}
+
+impl Markup {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+            marks: Vec::new(),
+            tool: MarkupTool::default(),
+            color: Color32::from_rgb(255, 64, 64),
+            line_width: 4.0,
+            text_size: 24.0,
+            text: String::new(),
+            path: String::new(),
+            drag_start: None,
+            texture: None,
+        }
+    }
+
+    /// The mark the current tool makes when dragged from `start` to `end`
+    pub fn mark_between(&self, start: Pos2, end: Pos2) -> Option<Mark> {
+        let annotation = match self.tool {
+            MarkupTool::Arrow => Annotation::Arrow { from: start, to: end },
+            MarkupTool::Box => Annotation::Box { rect: Rect::from_two_pos(start, end) },
+            MarkupTool::Text => return None,
+        };
+        Some(Mark { annotation, color: self.color, size: self.line_width })
+    }
+
+    /// The screenshot with the marks drawn into it
+    pub fn flatten(&self) -> Vec<u8> {
+        draw_marks(&self.pixels, self.width, self.height, &self.marks)
+    }
+}
+
+/// Draw marks into RGBA8 pixels, starting with the top row, the same way egui paints them
+pub fn draw_marks(pixels: &[u8], width: u32, height: u32, marks: &[Mark]) -> Vec<u8> {
+    let fonts = Fonts::new(1.0, FONT_ATLAS_SIZE, FontDefinitions::default());
+    let image_rect = Rect::from_min_size(Pos2::ZERO, vec2(width as f32, height as f32));
+    let to_image = RectTransform::identity(image_rect);
+    let shapes = marks
+        .iter()
+        .flat_map(|mark| mark.shapes(&fonts, to_image))
+        .map(|shape| ClippedShape(image_rect, shape))
+        .collect();
+
+    let (font_size, discs) = {
+        let atlas = fonts.texture_atlas();
+        let atlas = atlas.lock();
+        (atlas.size(), atlas.prepared_discs())
+    };
+    let primitives =
+        tessellate_shapes(1.0, TessellationOptions::default(), font_size, discs, shapes);
+    // Text is laid out while building the shapes, so the atlas has every glyph by now
+    let font_image = match fonts.font_image_delta().map(|delta| delta.image) {
+        Some(ImageData::Font(image)) => image,
+        _ => FontImage::new(font_size),
+    };
+
+    let mut pixels = pixels.to_vec();
+    for primitive in primitives {
+        let Primitive::Mesh(mesh) = primitive.primitive else {
+            continue;
+        };
+        for triangle in mesh.indices.chunks_exact(3) {
+            let vertices = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+            fill_triangle(&mut pixels, width, height, vertices, &font_image);
+        }
+    }
+    pixels
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b - a).x * (p - a).y - (b - a).y * (p - a).x
+}
+
+/// Blend a triangle from an egui mesh over the pixels it covers
+fn fill_triangle(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    [a, b, c]: [&Vertex; 3],
+    font_image: &FontImage,
+) {
+    let area = edge(a.pos, b.pos, c.pos);
+    if area.abs() <= f32::EPSILON {
+        return;
+    }
+
+    let min = a.pos.min(b.pos).min(c.pos);
+    let max = a.pos.max(b.pos).max(c.pos);
+    let (x0, y0) = (min.x.floor().max(0.0) as u32, min.y.floor().max(0.0) as u32);
+    let (x1, y1) = (max.x.ceil().max(0.0) as u32, max.y.ceil().max(0.0) as u32);
+    let [font_width, font_height] = font_image.size;
+    for y in y0..y1.min(height) {
+        for x in x0..x1.min(width) {
+            let p = pos2(x as f32 + 0.5, y as f32 + 0.5);
+            let weights = [
+                edge(b.pos, c.pos, p) / area,
+                edge(c.pos, a.pos, p) / area,
+                edge(a.pos, b.pos, p) / area,
+            ];
+            if weights.iter().any(|&w| w < 0.0) {
+                continue;
+            }
+
+            let uv = weights
+                .iter()
+                .zip([a, b, c])
+                .fold(vec2(0.0, 0.0), |uv, (w, vertex)| uv + vertex.uv.to_vec2() * *w);
+            let tx = ((uv.x * font_width as f32) as usize).min(font_width - 1);
+            let ty = ((uv.y * font_height as f32) as usize).min(font_height - 1);
+            let coverage = font_image.pixels[ty * font_width + tx].powf(COVERAGE_GAMMA);
+
+            // Vertex colors are premultiplied, so the source is blended with "over"
+            let mut source = [0.0; 4];
+            for (w, vertex) in weights.iter().zip([a, b, c]) {
+                for (s, channel) in source.iter_mut().zip(vertex.color.to_array()) {
+                    *s += channel as f32 / 255.0 * w * coverage;
+                }
+            }
+            let i = (y as usize * width as usize + x as usize) * 4;
+            for (destination, s) in pixels[i..i + 4].iter_mut().zip(source) {
+                let blended = s + *destination as f32 / 255.0 * (1.0 - source[3]);
+                *destination = (blended.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+}
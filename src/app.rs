@@ -0,0 +1,226 @@
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::{apply_system_buffers, ExecutorKind, ScheduleLabel};
+use color_eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::info;
+
+use crate::editor::{self, StartupOptions};
+use crate::plugin::{self, Plugin, PluginPanels};
+use crate::registry::{ComponentRegistry, Inspect};
+use crate::resources::ModelImporter;
+
+/// Stages of a frame, run in the order they are declared
+#[derive(SystemSet, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EditorSet {
+    /// Key and mouse events are applied to `Input`
+    Input,
+    /// The UI is built and the editor tools and camera react to input
+    EditorUi,
+    /// The simulation catches up with the time that has passed, in fixed steps
+    Simulation,
+    /// The scene and UI are drawn
+    Render,
+    /// Input and events of the frame are cleared and the clock advances
+    Cleanup,
+}
+
+impl EditorSet {
+    const ORDER: [EditorSet; 5] = [
+        EditorSet::Input,
+        EditorSet::EditorUi,
+        EditorSet::Simulation,
+        EditorSet::Render,
+        EditorSet::Cleanup,
+    ];
+
+    /// Run the sets of `schedule` one after another, applying the commands of each set before the
+    /// next one starts
+    pub fn configure(schedule: &mut Schedule) {
+        let [input, editor_ui, simulation, render, cleanup] = Self::ORDER;
+        schedule.configure_sets((input, editor_ui, simulation, render, cleanup).chain());
+        for pair in Self::ORDER.windows(2) {
+            schedule.add_system(apply_system_buffers.after(pair[0]).before(pair[1]));
+        }
+    }
+}
+
+/// Schedule run for each fixed simulation step, by the systems in `EditorSet::Simulation`
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationSchedule;
+
+/// Editor with resources, systems and plugins of your own added to it
+///
+/// ```no_run
+/// # use bevy_ecs::prelude::*;
+/// # use scene_editor::{EditorSet, SceneEditorApp};
+/// #[derive(Resource, Default)]
+/// struct Score(u32);
+///
+/// fn count(mut score: ResMut<Score>) {
+///     score.0 += 1;
+/// }
+///
+/// SceneEditorApp::new()
+///     .init_resource::<Score>()
+///     .add_systems(EditorSet::Simulation, count)
+///     .run()?;
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+pub struct SceneEditorApp {
+    pub(crate) options: StartupOptions,
+    pub(crate) registry: ComponentRegistry,
+    /// Holds the resources added before running, the editor adds its own to it
+    pub(crate) world: World,
+    /// Systems run once per frame, with the editor's own added to them
+    pub(crate) schedule: Schedule,
+    /// Systems run for each simulation step
+    pub(crate) simulation_schedule: Schedule,
+    /// Importers for model files, by file extension
+    pub(crate) model_importers: Vec<(String, ModelImporter)>,
+    plugins: Vec<Box<dyn Plugin>>,
+    /// Names of the plugins that have been built
+    built_plugins: Vec<&'static str>,
+}
+
+impl SceneEditorApp {
+    pub fn new() -> Self {
+        let mut schedule = Schedule::default();
+        // Systems drawing with OpenGL have to run on the thread the context is current on
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        EditorSet::configure(&mut schedule);
+        let mut world = World::new();
+        world.init_resource::<PluginPanels>();
+
+        Self {
+            options: StartupOptions::default(),
+            registry: ComponentRegistry::default(),
+            world,
+            schedule,
+            simulation_schedule: Schedule::default(),
+            model_importers: Vec::new(),
+            plugins: Vec::new(),
+            built_plugins: Vec::new(),
+        }
+    }
+
+    /// Start with the given scene, asset directories and window settings
+    pub fn set_options(&mut self, options: StartupOptions) -> &mut Self {
+        self.options = options;
+        self
+    }
+
+    /// Save custom components in scenes and edit them in the inspector
+    pub fn set_registry(&mut self, registry: ComponentRegistry) -> &mut Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Register a component under a unique name, see [`ComponentRegistry::register`]
+    pub fn register_component<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: Component + Default + Serialize + DeserializeOwned,
+    {
+        self.registry.register::<T>(name);
+        self
+    }
+
+    /// Register a component with widgets of its own in the inspector
+    pub fn register_inspectable<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: Component + Default + Serialize + DeserializeOwned + Inspect,
+    {
+        self.registry.register_inspectable::<T>(name);
+        self
+    }
+
+    /// Add a resource, which the editor keeps instead of creating its own default of the type, as
+    /// it does for `Camera` and `Lighting`
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    pub fn init_resource<R: Resource + FromWorld>(&mut self) -> &mut Self {
+        self.world.init_resource::<R>();
+        self
+    }
+
+    /// World the editor starts with, holding the resources added so far
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Add systems to a stage of the frame
+    ///
+    /// Systems in `EditorSet::Simulation` run for each fixed step instead of each frame, with the
+    /// step length in the `FixedTime` resource.
+    pub fn add_systems<M>(
+        &mut self,
+        set: EditorSet,
+        systems: impl IntoSystemConfigs<M>,
+    ) -> &mut Self {
+        match set {
+            EditorSet::Simulation => {
+                self.simulation_schedule.add_systems(systems.in_set(set));
+            }
+            _ => {
+                self.schedule.add_systems(systems.in_set(set));
+            }
+        }
+        self
+    }
+
+    /// Add a panel that is toggled from the toolbar and shown in a window of its own
+    pub fn add_panel<F>(&mut self, title: &'static str, show: F) -> &mut Self
+    where
+        F: FnMut(&mut egui::Ui, &mut World) + Send + Sync + 'static,
+    {
+        self.world.resource_mut::<PluginPanels>().add(title, show);
+        self
+    }
+
+    /// Load model files with an extension the editor does not read itself, such as `"ply"`
+    pub fn add_model_importer(&mut self, extension: &str, importer: ModelImporter) -> &mut Self {
+        self.model_importers.push((extension.to_owned(), importer));
+        self
+    }
+
+    /// Add a plugin, which is built when the editor starts
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Build the plugins added so far, each after its dependencies
+    ///
+    /// Plugins added while building are built too. This is done by [`run`](Self::run), so it only
+    /// needs to be called to inspect the app before it runs.
+    pub fn build_plugins(&mut self) -> Result<()> {
+        while !self.plugins.is_empty() {
+            let plugins = std::mem::take(&mut self.plugins);
+            for plugin in plugin::load_order(plugins, &self.built_plugins)? {
+                info!("building plugin {}", plugin.name());
+                plugin.build(self);
+                self.built_plugins.push(plugin.name());
+            }
+        }
+        Ok(())
+    }
+
+    /// Open the editor window and run until it is closed
+    pub fn run(&mut self) -> Result<()> {
+        self.build_plugins()?;
+        editor::run_app(std::mem::take(self))
+    }
+}
+
+impl Default for SceneEditorApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::{fs, mem};
+
+use bytemuck::Pod;
+use tracing::warn;
+
+/// Data derived from source assets, such as generated tangents, stored on disk between runs
+///
+/// Entries are keyed by the path, size and modification time of the source, so they are computed
+/// again when the source changes.
+#[derive(Debug, Clone)]
+pub struct DerivedCache {
+    dir: PathBuf,
+}
+
+impl DerivedCache {
+    /// Directory the editor keeps its cache in
+    pub const DEFAULT_DIR: &'static str = ".cache/derived";
+
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Load the data called `name` derived from `source`, or compute and store it
+    pub fn get_or_insert_with<T: Pod>(
+        &self,
+        source: &Path,
+        name: &str,
+        compute: impl FnOnce() -> Vec<T>,
+    ) -> Vec<T> {
+        let Some(path) = self.entry_path(source, name) else {
+            return compute();
+        };
+        if let Ok(bytes) = fs::read(&path) {
+            if bytes.len() % mem::size_of::<T>() == 0 {
+                return bytemuck::pod_collect_to_vec(&bytes);
+            }
+        }
+
+        let data = compute();
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, bytemuck::cast_slice::<T, u8>(&data)));
+        if let Err(e) = written {
+            warn!("could not write {} to the asset cache: {e}", path.display());
+        }
+        data
+    }
+
+    fn entry_path(&self, source: &Path, name: &str) -> Option<PathBuf> {
+        let metadata = fs::metadata(source).ok()?;
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        name.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        metadata.modified().ok()?.hash(&mut hasher);
+        Some(self.dir.join(format!("{:016x}.bin", hasher.finish())))
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+
+/// Folder next to a scene that its backups are kept in
+pub const BACKUP_DIR: &str = ".backups";
+
+/// How many backups of each scene are kept unless configured otherwise
+pub const DEFAULT_KEEP: usize = 10;
+
+/// Folder the backups of a scene are kept in
+pub fn backup_dir(scene: &Path) -> PathBuf {
+    scene.parent().unwrap_or(Path::new("")).join(BACKUP_DIR)
+}
+
+/// `YYYY-MM-DD_HH-MM-SS` in UTC, which sorts in the order the times are in
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Days since 1970-01-01 to a date, from Howard Hinnant's `civil_from_days`
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let (hour, minute, second) = (secs / 3600, secs / 60 % 60, secs % 60);
+    format!("{year:04}-{month:02}-{day:02}_{hour:02}-{minute:02}-{second:02}")
+}
+
+/// A backup of a scene, named after the scene and when it was made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub path: PathBuf,
+    /// When the backup was made, as written by [`timestamp`]
+    pub timestamp: String,
+}
+
+/// Backups of a scene, newest first
+pub fn list(scene: &Path) -> Vec<Backup> {
+    let (Some(stem), Some(extension)) = (file_stem(scene), scene.extension()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(backup_dir(scene)) else {
+        return Vec::new();
+    };
+    let prefix = format!("{stem}.");
+    let suffix = format!(".{}", extension.to_string_lossy());
+
+    let mut backups: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let timestamp = name.strip_prefix(&prefix)?.strip_suffix(&suffix)?;
+            // Other scenes can share the start of the name, such as `level.old.ron`
+            let is_timestamp = timestamp.len() == 19
+                && timestamp.chars().all(|c| c.is_ascii_digit() || "-_".contains(c));
+            is_timestamp.then(|| Backup { path: entry.path(), timestamp: timestamp.to_owned() })
+        })
+        .collect();
+    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    backups
+}
+
+/// Copy a scene into its backup folder before it is overwritten, and remove the oldest backups so
+/// at most `keep` are left
+///
+/// Does nothing if the scene does not exist yet or `keep` is 0.
+pub fn back_up(scene: &Path, keep: usize) -> Result<Option<PathBuf>> {
+    if keep == 0 || !scene.is_file() {
+        return Ok(None);
+    }
+    let (Some(stem), Some(extension)) = (file_stem(scene), scene.extension()) else {
+        return Err(eyre!("{} has no file extension", scene.display()));
+    };
+
+    let dir = backup_dir(scene);
+    fs::create_dir_all(&dir)?;
+    let name = format!("{stem}.{}.{}", timestamp(SystemTime::now()), extension.to_string_lossy());
+    let backup = dir.join(name);
+    fs::copy(scene, &backup)
+        .map_err(|e| eyre!("could not back up {} to {}: {e}", scene.display(), backup.display()))?;
+
+    for old in list(scene).into_iter().skip(keep) {
+        fs::remove_file(&old.path)?;
+    }
+    Ok(Some(backup))
+}
+
+/// Put a backup in place of its scene, backing up the scene first so the restore can be undone
+pub fn restore(backup: &Path, scene: &Path, keep: usize) -> Result<()> {
+    // Read first, as backing up the scene can remove the oldest backup
+    let contents =
+        fs::read(backup).map_err(|e| eyre!("could not read {}: {e}", backup.display()))?;
+    back_up(scene, keep.max(1))?;
+    fs::write(scene, contents)?;
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> Option<String> {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned())
+}
@@ -0,0 +1,353 @@
+//! Export of scenes as Bevy `DynamicScene` files, so the editor can lay out levels for Bevy games
+//!
+//! The written `.scn.ron` files follow the scene format of Bevy 0.10, the version of `bevy_ecs`
+//! the editor is built on, and are loaded with `SceneSpawner` or a `DynamicSceneBundle`. Bevy looks
+//! up every component by its type name in the type registry of the game, so the components are:
+//!
+//! - position, rotation and scale as `bevy_transform::components::transform::Transform`, and the
+//!   matrix they make as `bevy_transform::components::global_transform::GlobalTransform`
+//! - hidden as `bevy_render::view::visibility::Visibility`, which is `Hidden` or `Inherited`, with
+//!   the `bevy_render::view::visibility::ComputedVisibility` Bevy fills in every frame
+//! - a point light as `bevy_pbr::light::PointLight`, with the `CubemapVisibleEntities` and
+//!   `CubemapFrusta` of `bevy_pbr::bundle` and `bevy_render::primitives` it is rendered with
+//! - the model, textures and material as `{module}::SceneModel`
+//! - a scene camera as `{module}::SceneCamera`
+//! - tags as `{module}::SceneTags`
+//! - properties as `{module}::SceneProperties`
+//!
+//! The last four only exist in the game, so they are written only when
+//! [`BevyExportOptions::module`] names the module the game declares them in, with
+//! `#[derive(Component, Reflect, Default)]`, `#[reflect(Component)]` and the fields of
+//! [`SceneModel`], [`SceneCamera`], [`SceneTags`] and [`SceneProperties`]. Terrains, scripts,
+//! animations and custom shaders have no counterpart and are left out, and entities in groups are
+//! written where the groups put them in the world, without the groups. The scene has no resources.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use nalgebra_glm as glm;
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+
+use crate::components::{CameraComponent, PointLight, PropertyValue};
+use crate::scene::{Scene, SceneEntity};
+use crate::transform;
+
+/// File name ending of Bevy scenes, which its asset server loads as a `DynamicScene`
+pub const BEVY_SCENE_EXTENSION: &str = "scn.ron";
+
+/// Brightness of a Bevy point light in lumens, for a light with a full diffuse color
+const LUMENS_AT_FULL_DIFFUSE: f32 = 800.0;
+
+/// Range of a Bevy point light, for lights that do not fade out
+const DEFAULT_LIGHT_RANGE: f32 = 20.0;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BevyExportOptions {
+    /// Module path of the game that declares `SceneModel`, `SceneCamera` and `SceneTags`, such as
+    /// `my_game::level`, or `None` to write only Bevy's own components
+    pub module: Option<String>,
+}
+
+/// Model of an entity, for the game to load and attach its mesh and material
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SceneModel {
+    pub model: String,
+    pub diffuse: Option<String>,
+    pub specular: Option<String>,
+    pub normal: Option<String>,
+    pub material: Option<String>,
+}
+
+/// Lens of a scene camera, for the game to give a `Camera3dBundle`
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct SceneCamera {
+    /// Vertical field of view in radians, as in Bevy's `PerspectiveProjection`
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+/// Labels of an entity, for the game to find it by
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SceneTags {
+    pub tags: Vec<String>,
+}
+
+/// Free-form properties of an entity, for the game to read its own data from
+///
+/// The game declares `properties` as a `HashMap`, as Bevy 0.10 can not reflect a `BTreeMap`. Both
+/// are written the same, and this one keeps the file in the same order every time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SceneProperties {
+    pub properties: BTreeMap<String, SceneProperty>,
+}
+
+/// Value of a property, declared in the game with `#[derive(Reflect, FromReflect)]`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SceneProperty {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl From<&PropertyValue> for SceneProperty {
+    fn from(value: &PropertyValue) -> Self {
+        match value {
+            PropertyValue::Bool(b) => Self::Bool(*b),
+            PropertyValue::Number(n) => Self::Number(*n),
+            PropertyValue::String(s) => Self::String(s.clone()),
+        }
+    }
+}
+
+/// `DynamicScene` as Bevy 0.10 reads it, with entities by their index in the scene
+#[derive(Serialize)]
+struct DynamicScene {
+    /// Resources by their type name, which Bevy requires even when there are none
+    resources: BTreeMap<String, Component>,
+    entities: BTreeMap<usize, DynamicEntity>,
+}
+
+#[derive(Serialize)]
+struct DynamicEntity {
+    /// Components by their type name in the Bevy type registry
+    components: BTreeMap<String, Component>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Component {
+    Transform(Transform),
+    GlobalTransform(GlobalTransform),
+    Visibility(Visibility),
+    ComputedVisibility(ComputedVisibility),
+    PointLight(BevyPointLight),
+    /// Component whose fields Bevy does not save, such as the caches lights are rendered with
+    Unit(Unit),
+    Model(SceneModel),
+    Camera(SceneCamera),
+    Tags(SceneTags),
+    Properties(SceneProperties),
+}
+
+#[derive(Serialize)]
+struct Vec3 {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+impl From<glm::Vec3> for Vec3 {
+    fn from(v: glm::Vec3) -> Self {
+        Self { x: v.x, y: v.y, z: v.z }
+    }
+}
+
+#[derive(Serialize)]
+struct Transform {
+    translation: Vec3,
+    /// Quaternion as `(x, y, z, w)`
+    rotation: (f32, f32, f32, f32),
+    scale: Vec3,
+}
+
+/// `Affine3A` as Bevy reflects it, with the columns of the rotation and scale
+#[derive(Serialize)]
+struct Affine3A {
+    matrix3: Mat3A,
+    translation: Vec3,
+}
+
+#[derive(Serialize)]
+struct Mat3A {
+    x_axis: Vec3,
+    y_axis: Vec3,
+    z_axis: Vec3,
+}
+
+#[derive(Serialize)]
+struct GlobalTransform(Affine3A);
+
+impl From<&glm::Mat4> for GlobalTransform {
+    fn from(m: &glm::Mat4) -> Self {
+        let column = |i: usize| Vec3 { x: m[(0, i)], y: m[(1, i)], z: m[(2, i)] };
+        Self(Affine3A {
+            matrix3: Mat3A { x_axis: column(0), y_axis: column(1), z_axis: column(2) },
+            translation: column(3),
+        })
+    }
+}
+
+#[derive(Serialize)]
+enum Visibility {
+    Inherited,
+    Hidden,
+}
+
+/// Whether an entity is visible, which Bevy works out every frame, starting out as not visible
+#[derive(Serialize)]
+struct ComputedVisibility {
+    flags: VisibilityFlags,
+}
+
+#[derive(Serialize)]
+struct VisibilityFlags {
+    bits: u8,
+}
+
+#[derive(Serialize)]
+struct Unit {}
+
+#[derive(Serialize)]
+enum Color {
+    Rgba { red: f32, green: f32, blue: f32, alpha: f32 },
+}
+
+#[derive(Serialize)]
+struct BevyPointLight {
+    color: Color,
+    intensity: f32,
+    range: f32,
+    radius: f32,
+    shadows_enabled: bool,
+    shadow_depth_bias: f32,
+    shadow_normal_bias: f32,
+}
+
+impl From<&PointLight> for BevyPointLight {
+    fn from(light: &PointLight) -> Self {
+        // Bevy splits the light into a color and a brightness
+        let brightness = light.diffuse.max();
+        let hue = light.diffuse / brightness.max(f32::EPSILON);
+        Self {
+            color: Color::Rgba { red: hue.x, green: hue.y, blue: hue.z, alpha: 1.0 },
+            intensity: brightness * LUMENS_AT_FULL_DIFFUSE,
+            range: light.range().unwrap_or(DEFAULT_LIGHT_RANGE),
+            radius: 0.0,
+            shadows_enabled: false,
+            shadow_depth_bias: 0.02,
+            shadow_normal_bias: 0.6,
+        }
+    }
+}
+
+impl From<&CameraComponent> for SceneCamera {
+    fn from(camera: &CameraComponent) -> Self {
+        Self { fov: camera.fov_degrees.to_radians(), near: camera.near, far: camera.far }
+    }
+}
+
+/// Write a scene as a Bevy scene file
+pub fn export(scene: &Scene, path: &Path, options: &BevyExportOptions) -> Result<()> {
+    let ron = to_ron(scene, options)?;
+    fs::write(path, ron).map_err(|e| eyre!("could not write {}: {e}", path.display()))
+}
+
+/// A scene in the RON format of a Bevy `DynamicScene`
+pub fn to_ron(scene: &Scene, options: &BevyExportOptions) -> Result<String> {
+    if let Some(module) = &options.module {
+        let valid = module.split("::").all(|part| {
+            let mut chars = part.chars();
+            chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                && chars.all(|c| c.is_alphanumeric() || c == '_')
+        });
+        if !valid {
+            return Err(eyre!("{module:?} is not a Rust module path"));
+        }
+    }
+
+    let entities = scene
+        .entities
+        .iter()
+        .enumerate()
+        .map(|(index, entity)| {
+            (index, DynamicEntity { components: components(scene, entity, options) })
+        })
+        .collect();
+    let scene = DynamicScene { resources: BTreeMap::new(), entities };
+    ron::ser::to_string_pretty(&scene, PrettyConfig::default())
+        .map_err(|e| eyre!("could not serialize Bevy scene: {e}"))
+}
+
+/// Position, rotation and scale of an entity in the world, with the groups it is in applied
+fn world_transform(scene: &Scene, entity: &SceneEntity) -> Transform {
+    let (mut position, mut rotation, mut scale) = (entity.position, entity.rotation, entity.scale);
+    if entity.group.is_some() {
+        (position, rotation, scale) = transform::decompose(&scene.world_matrix(entity));
+    }
+    let quat = rotation.quat().coords;
+    Transform {
+        translation: glm::Vec3::from(position).into(),
+        rotation: (quat.x, quat.y, quat.z, quat.w),
+        scale: glm::Vec3::from(scale).into(),
+    }
+}
+
+fn components(
+    scene: &Scene,
+    entity: &SceneEntity,
+    options: &BevyExportOptions,
+) -> BTreeMap<String, Component> {
+    let mut components = BTreeMap::new();
+    let transform = world_transform(scene, entity);
+    components.insert(
+        "bevy_transform::components::transform::Transform".to_owned(),
+        Component::Transform(transform),
+    );
+    components.insert(
+        "bevy_transform::components::global_transform::GlobalTransform".to_owned(),
+        Component::GlobalTransform((&scene.world_matrix(entity)).into()),
+    );
+    let visibility = if entity.hidden { Visibility::Hidden } else { Visibility::Inherited };
+    components.insert(
+        "bevy_render::view::visibility::Visibility".to_owned(),
+        Component::Visibility(visibility),
+    );
+    components.insert(
+        "bevy_render::view::visibility::ComputedVisibility".to_owned(),
+        Component::ComputedVisibility(ComputedVisibility { flags: VisibilityFlags { bits: 0 } }),
+    );
+    if let Some(light) = &entity.point_light {
+        components
+            .insert("bevy_pbr::light::PointLight".to_owned(), Component::PointLight(light.into()));
+        for name in [
+            "bevy_pbr::bundle::CubemapVisibleEntities",
+            "bevy_render::primitives::CubemapFrusta",
+        ] {
+            components.insert(name.to_owned(), Component::Unit(Unit {}));
+        }
+    }
+
+    let Some(module) = &options.module else {
+        return components;
+    };
+    if !entity.model.is_empty() {
+        let model = SceneModel {
+            model: entity.model.clone(),
+            diffuse: entity.diffuse.clone(),
+            specular: entity.specular.clone(),
+            normal: entity.normal.clone(),
+            material: entity.material.clone(),
+        };
+        components.insert(format!("{module}::SceneModel"), Component::Model(model));
+    }
+    if let Some(camera) = &entity.camera {
+        components.insert(format!("{module}::SceneCamera"), Component::Camera(camera.into()));
+    }
+    if !entity.tags.is_empty() {
+        let tags = entity.tags.iter().cloned().collect();
+        components.insert(format!("{module}::SceneTags"), Component::Tags(SceneTags { tags }));
+    }
+    if !entity.properties.is_empty() {
+        let properties = entity.properties.iter().map(|(k, v)| (k.clone(), v.into())).collect();
+        components.insert(
+            format!("{module}::SceneProperties"),
+            Component::Properties(SceneProperties { properties }),
+        );
+    }
+    components
+}
@@ -0,0 +1,110 @@
+use nalgebra_glm as glm;
+
+/// Corners of [`Aabb::corners`] joined by the twelve edges of a box, along X, Y and then Z
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// Axis-aligned bounding box
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: glm::Vec3, max: glm::Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box containing all the points, or an empty box at the origin if there are none
+    pub fn from_points(points: &[glm::Vec3]) -> Self {
+        let Some((first, rest)) = points.split_first() else {
+            return Self::default();
+        };
+        rest.iter().fold(Self::new(*first, *first), |aabb, p| Self {
+            min: glm::min2(&aabb.min, p),
+            max: glm::max2(&aabb.max, p),
+        })
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> glm::Vec3 {
+        self.max - self.min
+    }
+
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &Self) -> Self {
+        Self { min: glm::min2(&self.min, &other.min), max: glm::max2(&self.max, &other.max) }
+    }
+
+    pub fn corners(&self) -> [glm::Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            glm::vec3(a.x, a.y, a.z),
+            glm::vec3(b.x, a.y, a.z),
+            glm::vec3(a.x, b.y, a.z),
+            glm::vec3(b.x, b.y, a.z),
+            glm::vec3(a.x, a.y, b.z),
+            glm::vec3(b.x, a.y, b.z),
+            glm::vec3(a.x, b.y, b.z),
+            glm::vec3(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Axis-aligned box containing this box after it is transformed
+    pub fn transformed(&self, matrix: &glm::Mat4) -> Self {
+        let corners = self.corners().map(|c| glm::vec4_to_vec3(&(matrix * c.push(1.0))));
+        Self::from_points(&corners)
+    }
+
+    /// Smallest rectangle around the box on screen, in normalized device coordinates, or `None` if
+    /// it is entirely behind the near plane
+    ///
+    /// The box is clipped against the near plane, so the rectangle of a box the camera is inside
+    /// of covers the part in front, which can reach past the edges of the screen.
+    pub fn ndc_rect(&self, mvp: &glm::Mat4) -> Option<(glm::Vec2, glm::Vec2)> {
+        let clip = self.corners().map(|c| mvp * c.push(1.0));
+        // How far in front of the near plane, where z is -w, a point in clip space is
+        let front = |p: &glm::Vec4| p.z + p.w;
+        let mut points: Vec<_> = clip.iter().filter(|p| front(p) >= 0.0).copied().collect();
+        for (a, b) in EDGES.map(|(a, b)| (clip[a], clip[b])) {
+            if (front(&a) >= 0.0) != (front(&b) >= 0.0) {
+                let t = front(&a) / (front(&a) - front(&b));
+                points.push(a + (b - a) * t);
+            }
+        }
+        let points: Vec<_> =
+            points.iter().filter(|p| p.w > 0.0).map(|p| glm::vec2(p.x / p.w, p.y / p.w)).collect();
+        let (first, rest) = points.split_first()?;
+        let rect = rest.iter().fold((*first, *first), |(min, max), p| {
+            (glm::min2(&min, p), glm::max2(&max, p))
+        });
+        Some(rect)
+    }
+
+    /// The twelve edges of the box, as pairs of points for drawing lines
+    pub fn edges(&self) -> [glm::Vec3; 24] {
+        let c = self.corners();
+        let mut points = [glm::Vec3::zeros(); 24];
+        for (i, (a, b)) in EDGES.into_iter().enumerate() {
+            points[2 * i] = c[a];
+            points[2 * i + 1] = c[b];
+        }
+        points
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::editor::StartupOptions;
+use crate::frame_limiter::VsyncMode;
+use crate::game_logic::{MATERIALS_DIR, MODELS_DIR, TEXTURES_DIR};
+use crate::golden::{GOLDEN_DIR, REPORT_DIR};
+use crate::headless::{GoldenOptions, HeadlessOptions};
+
+/// Command-line arguments of the `scene-editor` binary
+#[derive(Debug, Parser)]
+#[command(
+    name = "scene-editor",
+    version,
+    about = "Edit and render 3D scenes",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Scene file to open on startup
+    pub scene: Option<PathBuf>,
+    /// Directory to load models from
+    #[arg(long, value_name = "DIR", default_value = MODELS_DIR)]
+    pub models: PathBuf,
+    /// Directory to load textures from
+    #[arg(long, value_name = "DIR", default_value = TEXTURES_DIR)]
+    pub textures: PathBuf,
+    /// Directory to load materials from and save them to
+    #[arg(long, value_name = "DIR", default_value = MATERIALS_DIR)]
+    pub materials: PathBuf,
+    /// Width of the window, or of the image in headless mode
+    #[arg(
+        long,
+        value_name = "PX",
+        requires = "height",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub width: Option<u32>,
+    /// Height of the window, or of the image in headless mode
+    #[arg(
+        long,
+        value_name = "PX",
+        requires = "width",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub height: Option<u32>,
+    /// When frames wait for vertical sync [default: as in Preferences, or on]
+    #[arg(long, value_enum)]
+    pub vsync: Option<Vsync>,
+    /// Render the scene to images without opening a window
+    #[arg(long, requires = "scene")]
+    pub headless: bool,
+    /// Image file for a single frame, or directory for a numbered PNG sequence
+    #[arg(long, value_name = "PATH", default_value = "render.png", requires = "headless")]
+    pub output: PathBuf,
+    /// Number of frames to render in headless mode
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        requires = "headless",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub frames: u32,
+    /// Numbered camera view to render from in headless mode, instead of the first one
+    #[arg(long, value_name = "SLOT", requires = "headless")]
+    pub view: Option<u8>,
+    /// Scene variant to render in headless mode, instead of the base scene
+    #[arg(long, value_name = "NAME", requires = "headless")]
+    pub variant: Option<String>,
+    /// Render the golden-image cases in DIR and compare them with their reference images
+    #[arg(
+        long,
+        value_name = "DIR",
+        num_args = 0..=1,
+        default_missing_value = GOLDEN_DIR,
+        conflicts_with_all = ["headless", "scene"]
+    )]
+    pub golden: Option<PathBuf>,
+    /// Save the renders of the golden-image cases as their new reference images
+    #[arg(long, requires = "golden")]
+    pub approve: bool,
+    /// Directory to write the HTML diff report to when golden-image cases fail
+    #[arg(long, value_name = "DIR", default_value = REPORT_DIR, requires = "golden")]
+    pub report: PathBuf,
+    /// Reload the built-in shaders when their files in shaders/ change (debug builds only)
+    #[arg(long, conflicts_with_all = ["headless", "golden"])]
+    pub watch_shaders: bool,
+    /// Open the scene for viewing only, with editing turned off
+    #[arg(long, conflicts_with_all = ["headless", "golden"])]
+    pub view_only: bool,
+}
+
+/// Commands run instead of opening the editor
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Write sample models, textures, materials and a showcase scene to DIR
+    GenerateDemo {
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+    /// Write SCENE as a Bevy `DynamicScene` to OUTPUT, which should end in `.scn.ron`
+    ExportBevy {
+        #[arg(value_name = "SCENE")]
+        scene: PathBuf,
+        #[arg(value_name = "OUTPUT")]
+        output: PathBuf,
+        /// Module of the game that declares `SceneModel`, `SceneCamera` and `SceneTags`
+        #[arg(long, value_name = "PATH")]
+        module: Option<String>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Vsync {
+    On,
+    Off,
+    /// Wait for vertical sync, except after frames that missed a refresh of the display
+    Adaptive,
+}
+
+impl Cli {
+    /// What the windowed editor starts with
+    pub fn startup_options(&self) -> StartupOptions {
+        StartupOptions {
+            scene: self.scene.clone(),
+            models_dir: self.models.clone(),
+            textures_dir: self.textures.clone(),
+            materials_dir: self.materials.clone(),
+            window_size: self.width.zip(self.height),
+            vsync: self.vsync.map(|vsync| match vsync {
+                Vsync::On => VsyncMode::On,
+                Vsync::Off => VsyncMode::Off,
+                Vsync::Adaptive => VsyncMode::Adaptive,
+            }),
+            watch_shaders: self.watch_shaders,
+            view_only: self.view_only,
+        }
+    }
+
+    /// What to render, if headless mode was asked for
+    pub fn headless_options(&self) -> Option<HeadlessOptions> {
+        let scene = self.scene.clone().filter(|_| self.headless)?;
+        Some(HeadlessOptions {
+            scene,
+            output: self.output.clone(),
+            models_dir: self.models.clone(),
+            textures_dir: self.textures.clone(),
+            materials_dir: self.materials.clone(),
+            frames: self.frames,
+            width: self.width.unwrap_or(1920),
+            height: self.height.unwrap_or(1080),
+            view: self.view,
+            variant: self.variant.clone(),
+        })
+    }
+
+    /// Which golden-image cases to check, if that was asked for
+    pub fn golden_options(&self) -> Option<GoldenOptions> {
+        Some(GoldenOptions {
+            dir: self.golden.clone()?,
+            approve: self.approve,
+            report: self.report.clone(),
+            ..GoldenOptions::default()
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use egui::epaint::text::cursor::PCursor;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{vec2, Align2, Color32, FontId, Stroke, TextStyle};
+
+use crate::glsl::{tokenize, TokenKind};
+use crate::shader::{Severity, ShaderDiagnostic};
+
+fn token_color(kind: TokenKind, dark_mode: bool) -> Color32 {
+    let (dark, light) = match kind {
+        TokenKind::Comment => (Color32::from_rgb(110, 150, 100), Color32::from_rgb(60, 120, 50)),
+        TokenKind::Preprocessor => {
+            (Color32::from_rgb(200, 140, 220), Color32::from_rgb(140, 60, 160))
+        }
+        TokenKind::Keyword => (Color32::from_rgb(240, 120, 110), Color32::from_rgb(180, 40, 40)),
+        TokenKind::Type => (Color32::from_rgb(100, 180, 240), Color32::from_rgb(20, 100, 180)),
+        TokenKind::Builtin => (Color32::from_rgb(230, 200, 110), Color32::from_rgb(140, 100, 0)),
+        TokenKind::Number => (Color32::from_rgb(180, 220, 160), Color32::from_rgb(20, 130, 110)),
+        TokenKind::Identifier | TokenKind::Other => {
+            (Color32::from_gray(215), Color32::from_gray(30))
+        }
+    };
+    if dark_mode { dark } else { light }
+}
+
+fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => Color32::from_rgb(240, 70, 70),
+        Severity::Warning => Color32::from_rgb(230, 160, 40),
+    }
+}
+
+/// The worst diagnostic on a line, counting lines from 1
+fn worst_on_line(diagnostics: &[ShaderDiagnostic], line: u32) -> Option<&ShaderDiagnostic> {
+    let on_line = diagnostics.iter().filter(|d| d.line == Some(line));
+    on_line.min_by_key(|d| match d.severity {
+        Severity::Error => 0,
+        Severity::Warning => 1,
+    })
+}
+
+/// Highlighted source, with the lines that have diagnostics underlined
+fn layout_job(
+    source: &str,
+    font_id: FontId,
+    dark_mode: bool,
+    diagnostics: &[ShaderDiagnostic],
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let mut line = 1;
+    for token in tokenize(source) {
+        for text in source[token.range].split_inclusive('\n') {
+            let underline = worst_on_line(diagnostics, line)
+                .map_or(Stroke::NONE, |d| Stroke::new(1.0, severity_color(d.severity)));
+            let format = TextFormat {
+                font_id: font_id.clone(),
+                color: token_color(token.kind, dark_mode),
+                underline,
+                ..Default::default()
+            };
+            job.append(text, 0.0, format);
+            if text.ends_with('\n') {
+                line += 1;
+            }
+        }
+    }
+    job
+}
+
+/// Multiline GLSL editor with highlighting, line numbers and the messages of the compiler shown
+/// next to the lines they are about
+pub fn code_editor(
+    ui: &mut egui::Ui,
+    source: &mut String,
+    diagnostics: &[ShaderDiagnostic],
+) -> egui::Response {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let dark_mode = ui.visuals().dark_mode;
+    let line_count = source.lines().count().max(1);
+    let digit_width = ui.fonts(|fonts| fonts.glyph_width(&font_id, '0'));
+    let gutter_width = (line_count.to_string().len() as f32 + 1.0) * digit_width;
+
+    let mut layouter = |ui: &egui::Ui, source: &str, wrap_width: f32| {
+        let mut job = layout_job(source, font_id.clone(), dark_mode, diagnostics);
+        job.wrap.max_width = wrap_width;
+        ui.fonts(|fonts| fonts.layout_job(job))
+    };
+    let output = egui::TextEdit::multiline(source)
+        .code_editor()
+        .desired_width(f32::INFINITY)
+        .margin(vec2(gutter_width + 8.0, 2.0))
+        .layouter(&mut layouter)
+        .show(ui);
+
+    let painter = ui.painter_at(output.response.rect);
+    let origin = output.text_draw_pos;
+    let number_color = ui.visuals().weak_text_color();
+    for (paragraph, text) in output.galley.text().split('\n').enumerate() {
+        let line = paragraph as u32 + 1;
+        let diagnostic = worst_on_line(diagnostics, line);
+        let start = output.galley.pos_from_pcursor(PCursor {
+            paragraph,
+            offset: 0,
+            prefer_next_row: false,
+        });
+        painter.text(
+            origin + vec2(-8.0, start.min.y),
+            Align2::RIGHT_TOP,
+            line.to_string(),
+            font_id.clone(),
+            diagnostic.map_or(number_color, |d| severity_color(d.severity)),
+        );
+
+        if let Some(diagnostic) = diagnostic {
+            let end = output.galley.pos_from_pcursor(PCursor {
+                paragraph,
+                offset: text.chars().count(),
+                prefer_next_row: false,
+            });
+            painter.text(
+                origin + vec2(end.max.x + 4.0 * digit_width, end.min.y),
+                Align2::LEFT_TOP,
+                format!("◀ {}", diagnostic.message),
+                font_id.clone(),
+                severity_color(diagnostic.severity),
+            );
+        }
+    }
+    output.response
+}
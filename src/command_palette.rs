@@ -0,0 +1,148 @@
+//! Ctrl+Shift+P palette that lists what the editor can do, found by typing part of its name
+
+use crate::input_map::{Action, Binding, InputMap};
+use crate::settings::Panel;
+
+/// Something run from the command palette
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteCommand {
+    /// Run an action as if its binding was pressed
+    Action(Action),
+    /// Open or close a panel
+    TogglePanel(Panel),
+    NewScene,
+    SaveScene,
+    /// Put the selected entities in a new group
+    GroupSelected,
+    /// Take the entities out of the selected groups
+    Ungroup,
+    /// Add a scene camera where the editor camera is
+    AddCamera,
+    /// Spawn a model at the origin
+    Spawn(String),
+}
+
+/// A command as listed in the palette
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    pub label: String,
+    /// Key or mouse button that runs the command without the palette
+    pub binding: Option<Binding>,
+    pub command: PaletteCommand,
+}
+
+/// Actions that only do something while their binding is held, or that need the pointer
+fn is_held(action: Action) -> bool {
+    matches!(
+        action,
+        Action::MoveForward
+            | Action::MoveBackward
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::MoveUp
+            | Action::MoveDown
+            | Action::MoveFast
+            | Action::LookAround
+            | Action::LookLeft
+            | Action::LookRight
+            | Action::LookUp
+            | Action::LookDown
+            | Action::Select
+            | Action::CommandPalette
+    )
+}
+
+/// Every command in the palette, with a spawn command for each of the models
+pub fn entries<'a>(
+    input_map: &InputMap,
+    models: impl IntoIterator<Item = &'a str>,
+) -> Vec<PaletteEntry> {
+    let mut entries = vec![
+        PaletteEntry {
+            label: "New scene".to_owned(),
+            binding: None,
+            command: PaletteCommand::NewScene,
+        },
+        PaletteEntry {
+            label: "Save scene".to_owned(),
+            binding: None,
+            command: PaletteCommand::SaveScene,
+        },
+        PaletteEntry {
+            label: "Group selected".to_owned(),
+            binding: None,
+            command: PaletteCommand::GroupSelected,
+        },
+        PaletteEntry {
+            label: "Ungroup".to_owned(),
+            binding: None,
+            command: PaletteCommand::Ungroup,
+        },
+        PaletteEntry {
+            label: "Add camera".to_owned(),
+            binding: None,
+            command: PaletteCommand::AddCamera,
+        },
+    ];
+    // The inspector is shown while something is selected
+    let panels = Panel::ALL.into_iter().filter(|&panel| panel != Panel::Inspector);
+    entries.extend(panels.map(|panel| PaletteEntry {
+        label: format!("Toggle {} panel", panel.label()),
+        binding: None,
+        command: PaletteCommand::TogglePanel(panel),
+    }));
+    entries.extend(Action::all().filter(|&action| !is_held(action)).map(|action| PaletteEntry {
+        label: action.label().into_owned(),
+        binding: Some(input_map.get(action)),
+        command: PaletteCommand::Action(action),
+    }));
+    let mut models: Vec<_> = models.into_iter().collect();
+    models.sort_unstable();
+    entries.extend(models.into_iter().map(|model| PaletteEntry {
+        label: format!("Spawn {model}"),
+        binding: None,
+        command: PaletteCommand::Spawn(model.to_owned()),
+    }));
+    entries
+}
+
+/// How well the query matches the text, or `None` if its characters are not all in the text in
+/// the same order
+///
+/// Case and spaces in the query are ignored. Characters at the start of a word and runs of
+/// characters in a row count for more, so `sv` puts "Save view 1" above "Select previous".
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().collect();
+    let same = |a: char, b: char| a.to_lowercase().eq(b.to_lowercase());
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..text.len()).find(|&i| same(text[i], q))?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match previous {
+            Some(previous) if previous + 1 == found => score += 4,
+            // Characters skipped between two matches
+            Some(_) => score -= (found - next).min(8) as i32,
+            None => {}
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// Entries that match the query, best match first, and in the order they are listed for equally
+/// good matches
+pub fn search<'a>(entries: &'a [PaletteEntry], query: &str) -> Vec<&'a PaletteEntry> {
+    let mut matches: Vec<_> = entries
+        .iter()
+        .filter_map(|entry| Some((fuzzy_score(query, &entry.label)?, entry)))
+        .collect();
+    matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    matches.into_iter().map(|(_, entry)| entry).collect()
+}
@@ -1,25 +1,102 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
 use glow::Context;
+#[cfg(feature = "editor")]
+use glow::HasContext;
+#[cfg(feature = "editor")]
+use nalgebra_glm as glm;
 use tracing::{debug, info, warn};
 
-use crate::components::CustomShader;
-use crate::shader::{ShaderBuilder, ShaderType};
+#[cfg(feature = "editor")]
+use crate::annotation::Markup;
+use crate::bevy_export::{self, BevyExportOptions};
+use crate::components::{
+    AnimationPlayer, CustomShader, Group, Mesh, Position, ShaderParams, Skeleton, TransformBundle,
+};
+#[cfg(feature = "editor")]
+use crate::components::{CameraComponent, Parent, Rotation, Selected};
+#[cfg(feature = "editor")]
+use crate::config::EditorConfig;
+use crate::entity_pool::EntityPool;
+#[cfg(feature = "editor")]
+use crate::entity_pool::{self, Pooled};
+#[cfg(feature = "editor")]
+use crate::environment_map::EnvironmentMap;
+#[cfg(feature = "editor")]
+use crate::gl_debug::GlErrorChecks;
+#[cfg(feature = "editor")]
+use crate::issue_report::{self, IssueReport};
+use crate::material::{self, MaterialHandle, MaterialId};
+use crate::mesh_cleanup::MeshCleanup;
+#[cfg(feature = "editor")]
+use crate::profiler::Profiler;
+use crate::renderer::{self, ImageSettings};
+#[cfg(feature = "editor")]
+use crate::resources::{
+    Camera, DebugDraw, EguiGlowRes, LookDev, LookDevEnvironment, RenderState, SceneView,
+    TextureDetails, TextureLoader, Time, UiState, ViewLayout, Viewport, SCENE_COLOR,
+};
+use crate::resources::{
+    Lighting, ModelLoader, NotificationLevel, Notifications, RenderSettings, ShaderGlobals,
+    ViewBookmarks,
+};
+use crate::scene::{LightingRig, MergeOptions, Scene};
+use crate::shader::{self, CompileError, ShaderType};
+use crate::shader_cache::ShaderCache;
+use crate::shader_limits::{self, ShaderLimits};
+use crate::terrain::Terrain;
+#[cfg(feature = "editor")]
+use crate::terrain::{Heightmap, DEFAULT_QUADS, DEFAULT_SIZE};
+#[cfg(feature = "editor")]
+use crate::texture_import::{self, TextureQuality, TextureSettings};
+use crate::transform;
+#[cfg(feature = "editor")]
+use crate::usage::UsageLog;
+use crate::variables::SceneVariables;
+use crate::variants::{self, SceneVariants};
+use crate::{backup, image_export};
+
+/// Show a notification in the editor, runs without the editor only log
+fn notify(world: &mut World, level: NotificationLevel, message: impl Into<String>) {
+    if let Some(mut notifications) = world.get_resource_mut::<Notifications>() {
+        notifications.push(level, message);
+    }
+}
 
 /// Despawn an entity and destroy its OpenGL resources
+///
+/// The children of the entity, such as the members of a group, go into the parent it was in.
 pub fn despawn_and_destroy(entity: Entity, world: &mut World) {
-    let gl = world.non_send_resource::<Arc<Context>>().clone();
-    if let Some(mut cs) = world.entity_mut(entity).get_mut::<CustomShader>() {
+    transform::unparent_children(world, entity);
+    if let Some(mut cs) = world.entity_mut(entity).take::<CustomShader>() {
         if let Ok(ref mut shader) = cs.shader {
+            let gl = world.non_send_resource::<Arc<Context>>();
             unsafe {
-                shader.destroy(&gl);
+                shader.destroy(gl);
             }
         }
     }
     world.despawn(entity);
 }
 
+/// Remove every mesh entity and group, giving the mesh entities back to the pool
+#[cfg(feature = "editor")]
+pub fn despawn_all(world: &mut World) {
+    // Groups go first, so the entities in them are no longer children and can be pooled
+    let mut groups = world.query_filtered::<Entity, With<Group>>();
+    for group in groups.iter(world).collect::<Vec<_>>() {
+        despawn_and_destroy(group, world);
+    }
+    let mut meshes = world.query_filtered::<Entity, (With<Mesh>, Without<Pooled>)>();
+    for entity in meshes.iter(world).collect::<Vec<_>>() {
+        entity_pool::release(entity, world);
+    }
+}
+
 /// Add a custom shader component to an entity
 pub fn add_custom_shader(entity: Entity, world: &mut World) {
     let gl = world.non_send_resource::<Arc<Context>>().clone();
@@ -28,8 +105,46 @@ pub fn add_custom_shader(entity: Entity, world: &mut World) {
 
 /// Compile the shader in the custom shader component of an entity
 pub fn compile_custom_shader(entity: Entity, world: &mut World) {
+    match compile_and_check(entity, world) {
+        Some(Ok(())) => notify(
+            world,
+            NotificationLevel::Success,
+            format!("Compiled the custom shader of entity {}", entity.index()),
+        ),
+        Some(Err(e)) => notify(world, NotificationLevel::Error, e),
+        None => {}
+    }
+}
+
+/// Compile the custom shader of an entity loaded with a scene or material
+///
+/// Only shaders that do not compile are notified, so loading a scene does not show a toast for
+/// every shader in it.
+pub(crate) fn load_custom_shader(entity: Entity, world: &mut World) {
+    if let Some(Err(e)) = compile_and_check(entity, world) {
+        notify(world, NotificationLevel::Error, e);
+    }
+}
+
+/// Compile the custom shader of an entity, returning why it did not compile
+fn compile_and_check(entity: Entity, world: &mut World) -> Option<Result<(), String>> {
+    world.init_resource::<ShaderCache>();
+    world.resource_scope(|world, mut cache: Mut<ShaderCache>| {
+        compile_with_cache(entity, world, &mut cache);
+    });
+    let error = world.get::<CustomShader>(entity)?.error.as_ref();
+    let index = entity.index();
+    Some(match error {
+        Some(e) => Err(format!("Custom shader of entity {index} did not compile: {e}")),
+        None => Ok(()),
+    })
+}
+
+fn compile_with_cache(entity: Entity, world: &mut World, cache: &mut ShaderCache) {
     let gl = world.non_send_resource::<Arc<Context>>().clone();
-    if let Some(mut cs) = world.entity_mut(entity).get_mut::<CustomShader>() {
+    let limits = world.get_resource::<ShaderLimits>().copied().unwrap_or_default();
+    let mut entity_mut = world.entity_mut(entity);
+    if let Some(mut cs) = entity_mut.get_mut::<CustomShader>() {
         // Delete the existing shader program
         if let Ok(ref mut shader) = cs.shader {
             unsafe {
@@ -37,20 +152,89 @@ pub fn compile_custom_shader(entity: Entity, world: &mut World) {
             }
         }
 
-        cs.shader = ShaderBuilder::new(&gl)
-            .add_shader_source(&cs.vert_source, ShaderType::Vertex)
-            .and_then(|b| {
-                b.add_shader_source(&cs.frag_source, ShaderType::Fragment).and_then(|b| b.link())
-            });
+        // Shaders that could hang the GPU are not handed to the driver at all. Includes that can
+        // not be resolved are reported when compiling.
+        let check_limits = |source: &str| match shader::preprocess(source) {
+            Ok(source) => source.remap(shader_limits::check_limits(&source.source, &limits)),
+            Err(_) => Vec::new(),
+        };
+        let vert_limits = check_limits(&cs.vert_source);
+        let frag_limits = check_limits(&cs.frag_source);
+        if !vert_limits.is_empty() || !frag_limits.is_empty() {
+            cs.shader = Err(eyre!("custom shader is over the limits for custom shaders"));
+            cs.vert_diagnostics = vert_limits;
+            cs.frag_diagnostics = frag_limits;
+            cs.error = Some("the shader is over the limits for custom shaders".to_owned());
+            warn!("custom shader is over the limits for custom shaders, not compiling it");
+            return;
+        }
+
+        // Entities with the same shader, such as duplicates, share one program
+        let uniforms = match cache.get_or_link(&gl, &cs.vert_source, &cs.frag_source) {
+            Ok(cached) => {
+                info!("custom shader compilation successful");
+                cs.shader = Ok(cached.shader);
+                cs.vert_diagnostics = cached.vert_diagnostics;
+                cs.frag_diagnostics = cached.frag_diagnostics;
+                cs.error = None;
+                cs.last_working = (cs.vert_source.clone(), cs.frag_source.clone());
+                cached.uniforms
+            }
+            Err(e) => {
+                warn!("custom shader error: {}", e);
+                // Stages after the one that failed were not compiled, so they have nothing to show
+                let (vert, frag) = match e.downcast_ref::<CompileError>() {
+                    Some(failed) if failed.shader_type == ShaderType::Vertex => {
+                        (failed.diagnostics.clone(), Vec::new())
+                    }
+                    Some(failed) => (Vec::new(), failed.diagnostics.clone()),
+                    None => (Vec::new(), Vec::new()),
+                };
+                cs.vert_diagnostics = vert;
+                cs.frag_diagnostics = frag;
+                cs.error = Some(e.to_string());
+                cs.shader = Err(e);
+                return;
+            }
+        };
+
+        let mut params = entity_mut.take::<ShaderParams>().unwrap_or_default();
+        params.sync(uniforms);
+        entity_mut.insert(params);
+    }
+}
+
+/// Go back to the source of the last custom shader that compiled, and compile it
+pub fn revert_custom_shader(entity: Entity, world: &mut World) {
+    if let Some(mut cs) = world.entity_mut(entity).get_mut::<CustomShader>() {
+        let (vert_source, frag_source) = cs.last_working.clone();
+        cs.vert_source = vert_source;
+        cs.frag_source = frag_source;
+        compile_custom_shader(entity, world);
+    }
+}
 
-        if let Err(e) = &cs.shader {
-            warn!("custom shader error: {}", e);
-        } else {
-            info!("custom shader compilation successful");
+/// Stop drawing an entity with its custom shader, keeping the source so it can be fixed
+pub fn disable_custom_shader(entity: Entity, world: &mut World) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    if let Some(mut cs) = world.entity_mut(entity).get_mut::<CustomShader>() {
+        if let Ok(ref mut shader) = cs.shader {
+            unsafe {
+                shader.destroy(&gl);
+            }
         }
+        cs.shader = Err(eyre!("disabled, compile the shader to enable it again"));
+        info!("disabled the custom shader of entity {}", entity.index());
     }
 }
 
+/// Draw custom shaders again after they were switched off because a frame stalled
+#[cfg(feature = "editor")]
+pub fn resume_custom_shaders(world: &mut World) {
+    world.resource_mut::<RenderState>().custom_shaders = true;
+    world.resource_mut::<UiState>().stalled_frame = None;
+}
+
 /// Remove the custom shader component of an entity
 pub fn remove_custom_shader(entity: Entity, world: &mut World) {
     let gl = world.non_send_resource::<Arc<Context>>().clone();
@@ -61,7 +245,742 @@ pub fn remove_custom_shader(entity: Entity, world: &mut World) {
             }
         }
 
-        world.entity_mut(entity).remove::<CustomShader>();
+        world.entity_mut(entity).remove::<(CustomShader, ShaderParams)>();
         debug!("custom shader removed for entity {}", entity.index());
     }
 }
+
+/// Save every mesh entity in the world to a scene file
+pub fn save_scene(world: &mut World, path: &Path) {
+    let keep = backups_to_keep(world);
+    // A scene that can not be backed up is still saved, to not lose the changes
+    if let Err(e) = backup::back_up(path, keep) {
+        warn!("could not back up scene: {e}");
+    }
+
+    // Variants are stored as changes to the base scene
+    match variants::with_base(world, Scene::from_world).save(path) {
+        Ok(()) => {
+            info!("saved scene to {}", path.display());
+            notify(world, NotificationLevel::Success, format!("Saved {}", path.display()));
+            #[cfg(feature = "editor")]
+            if let Some(mut config) = world.get_resource_mut::<EditorConfig>() {
+                config.add_recent_file(path);
+            }
+        }
+        Err(e) => {
+            warn!("could not save scene: {}", e);
+            notify(world, NotificationLevel::Error, format!("Could not save the scene: {e}"));
+        }
+    }
+}
+
+/// How many backups of a scene to keep, as set in the editor configuration
+#[cfg_attr(not(feature = "editor"), allow(unused_variables))]
+fn backups_to_keep(world: &World) -> usize {
+    #[cfg(feature = "editor")]
+    if let Some(config) = world.get_resource::<EditorConfig>() {
+        return config.backups;
+    }
+    backup::DEFAULT_KEEP
+}
+
+/// Put a backup in place of a scene file and open it
+pub fn restore_backup(world: &mut World, backup: &Path, scene: &Path) {
+    let keep = backups_to_keep(world);
+    match backup::restore(backup, scene, keep) {
+        Ok(()) => {
+            info!("restored {} from {}", scene.display(), backup.display());
+            load_scene(world, scene);
+        }
+        Err(e) => {
+            warn!("could not restore backup: {e}");
+            notify(world, NotificationLevel::Error, format!("Could not restore the backup: {e}"));
+        }
+    }
+}
+
+/// Replace the mesh entities in the world with the ones stored in a scene file
+pub fn load_scene(world: &mut World, path: &Path) {
+    let scene = match Scene::load(path) {
+        Ok(scene) => scene,
+        Err(e) => {
+            warn!("could not load scene: {}", e);
+            notify(world, NotificationLevel::Error, format!("Could not load the scene: {e}"));
+            return;
+        }
+    };
+
+    let mut query = world.query_filtered::<Entity, Or<(With<Mesh>, With<Terrain>, With<Group>)>>();
+    let old_entities: Vec<_> = query.iter(world).collect();
+
+    // Spawn before despawning so a broken scene file leaves the current scene intact
+    match scene.spawn(world) {
+        Ok(spawned) => {
+            // Puts back the lighting a variant replaced, so scenes saved without their lighting
+            // keep the one that is there
+            variants::switch(world, None);
+            if let Some(lighting) = scene.lighting {
+                world.insert_resource(lighting);
+            }
+            world.insert_resource(scene.render_settings);
+            for entity in old_entities {
+                despawn_and_destroy(entity, world);
+            }
+            // Pooled entities are mesh entities too, and went with the old scene
+            if let Some(mut pool) = world.get_resource_mut::<EntityPool>() {
+                pool.clear();
+            }
+            world.insert_resource(ViewBookmarks { views: scene.views });
+            world.get_resource_or_insert_with(ShaderGlobals::default).params = scene.shader_params;
+            world.insert_resource(SceneVariables { values: scene.variables });
+            world.insert_resource(SceneVariants::from_scene(&scene.variants, &spawned));
+            info!("loaded {} entities from {}", spawned.len(), path.display());
+            let message = format!("Loaded {} entities from {}", spawned.len(), path.display());
+            notify(world, NotificationLevel::Success, message);
+            #[cfg(feature = "editor")]
+            if let Some(mut usage) = world.get_resource_mut::<UsageLog>() {
+                usage.scene_opened(path);
+            }
+            #[cfg(feature = "editor")]
+            if let Some(mut config) = world.get_resource_mut::<EditorConfig>() {
+                config.add_recent_file(path);
+            }
+        }
+        Err(e) => {
+            warn!("could not load scene: {}", e);
+            notify(world, NotificationLevel::Error, format!("Could not load the scene: {e}"));
+        }
+    }
+}
+
+/// Capture the scene view as it was last rendered and open it in the markup window
+#[cfg(feature = "editor")]
+pub fn capture_screenshot(world: &mut World) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let Viewport { width, height } = *world.resource::<Viewport>();
+    let pixels = renderer::read_scene_color(&gl, world.resource::<RenderState>(), width, height);
+    world.resource_mut::<UiState>().markup = Some(Markup::new(width, height, pixels));
+    debug!("captured a {width}x{height} screenshot");
+}
+
+/// Bundle diagnostics, recent logs, render settings, a screenshot and optionally the scene into a
+/// zip file for attaching to a bug report
+#[cfg(feature = "editor")]
+pub fn report_issue(world: &mut World, path: &Path, include_scene: bool) {
+    let result = build_issue_report(world, include_scene).and_then(|report| report.save(path));
+    match &result {
+        Ok(()) => info!("saved issue report to {}", path.display()),
+        Err(e) => warn!("could not save issue report: {e}"),
+    }
+    match result {
+        Ok(()) => {
+            let message = format!("Saved {}, attach it to the issue", path.display());
+            notify(world, NotificationLevel::Success, message);
+        }
+        Err(e) => {
+            notify(world, NotificationLevel::Error, format!("Could not save issue report: {e}"));
+        }
+    }
+}
+
+#[cfg(feature = "editor")]
+fn build_issue_report(world: &mut World, include_scene: bool) -> Result<IssueReport> {
+    use std::fmt::Write as _;
+
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let Viewport { width, height } = *world.resource::<Viewport>();
+    let entity_count =
+        world.query_filtered::<(), (With<Mesh>, Without<Pooled>)>().iter(world).count();
+
+    let mut diagnostics = String::new();
+    writeln!(diagnostics, "Version: {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(diagnostics, "System: {} {}", std::env::consts::OS, std::env::consts::ARCH)?;
+    unsafe {
+        writeln!(diagnostics, "Vendor: {}", gl.get_parameter_string(glow::VENDOR))?;
+        writeln!(diagnostics, "Renderer: {}", gl.get_parameter_string(glow::RENDERER))?;
+        writeln!(diagnostics, "OpenGL version: {}", gl.get_parameter_string(glow::VERSION))?;
+        let glsl = gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION);
+        writeln!(diagnostics, "GLSL version: {glsl}")?;
+    }
+    writeln!(diagnostics, "Viewport: {width}x{height}")?;
+    writeln!(diagnostics, "Entities: {entity_count}")?;
+    let frame_time = world.resource::<Time>().avg_frame_time_ms();
+    writeln!(diagnostics, "Average frame time: {frame_time:.2} ms")?;
+    let profiler = world.resource::<Profiler>();
+    let stats = profiler.stats();
+    writeln!(diagnostics, "Draw calls: {}, triangles: {}", stats.draw_calls, stats.triangles)?;
+    for pass in profiler.pass_timings() {
+        let gpu = pass.gpu_ms.map_or("-".to_owned(), |ms| format!("{ms:.2} ms"));
+        writeln!(diagnostics, "  {}: CPU {:.2} ms, GPU {gpu}", pass.name, pass.cpu_ms)?;
+    }
+    writeln!(diagnostics, "\nAsset errors:")?;
+    let model_errors = world.resource::<ModelLoader>().errors().iter();
+    let material_errors = world.get_resource::<material::MaterialLoader>().map(|l| l.errors());
+    let errors = model_errors
+        .chain(world.resource::<TextureLoader>().errors())
+        .chain(material_errors.unwrap_or_default());
+    for error in errors {
+        writeln!(diagnostics, "  {error}")?;
+    }
+    writeln!(diagnostics, "\nGL errors:")?;
+    for failure in world.resource::<GlErrorChecks>().failures() {
+        writeln!(diagnostics, "  {failure}")?;
+    }
+
+    let mut render_settings = String::new();
+    let lighting = *world.resource::<Lighting>();
+    let pretty = ron::ser::PrettyConfig::default();
+    writeln!(render_settings, "Lighting: {}", ron::ser::to_string_pretty(&lighting, pretty)?)?;
+    writeln!(render_settings, "Debug draw: {:?}", world.resource::<DebugDraw>())?;
+    writeln!(render_settings, "GL error checks: {:?}", world.resource::<GlErrorChecks>().mode)?;
+    writeln!(render_settings, "Projection: {:?}", world.resource::<Camera>().mode)?;
+    let layout = world.resource::<ViewLayout>();
+    writeln!(render_settings, "Quad view: {}", layout.quad)?;
+    for (view, name) in ViewLayout::NAMES.iter().enumerate() {
+        writeln!(render_settings, "{name} passes: {:?}", layout.passes(view))?;
+    }
+
+    let pixels = renderer::read_scene_color(&gl, world.resource::<RenderState>(), width, height);
+    let scene = include_scene
+        .then(|| variants::with_base(world, Scene::from_world).to_ron())
+        .transpose()?;
+
+    Ok(IssueReport {
+        diagnostics,
+        render_settings,
+        logs: issue_report::recent_logs(),
+        screenshot: Some((width, height, pixels)),
+        scene,
+    })
+}
+
+/// Replace the scene with an empty one
+pub fn new_scene(world: &mut World) {
+    variants::switch(world, None);
+    let mut query = world.query_filtered::<Entity, Or<(With<Mesh>, With<Terrain>, With<Group>)>>();
+    let entities: Vec<_> = query.iter(world).collect();
+    for entity in entities {
+        despawn_and_destroy(entity, world);
+    }
+    if let Some(mut pool) = world.get_resource_mut::<EntityPool>() {
+        pool.clear();
+    }
+    world.insert_resource(ViewBookmarks::default());
+    world.get_resource_or_insert_with(ShaderGlobals::default).params = Default::default();
+    world.insert_resource(SceneVariables::default());
+    world.insert_resource(SceneVariants::default());
+    world.insert_resource(RenderSettings::default());
+    info!("started a new scene");
+}
+
+/// Spawn the entities and terrains of a scene file into the open scene, along with its variables
+/// and variants
+///
+/// The open scene keeps its lighting, render settings, views and shader parameters, and the
+/// variables it has a value for. The imported entities are selected.
+pub fn import_scene(world: &mut World, path: &Path, options: &MergeOptions) {
+    let result = try_import_scene(world, path, options);
+    match &result {
+        Ok(message) => info!("{message}"),
+        Err(e) => warn!("could not import scene: {e}"),
+    }
+    match result {
+        Ok(message) => notify(world, NotificationLevel::Success, message),
+        Err(e) => notify(world, NotificationLevel::Error, format!("Could not import scene: {e}")),
+    }
+}
+
+fn try_import_scene(world: &mut World, path: &Path, options: &MergeOptions) -> Result<String> {
+    let mut scene = Scene::load(path)?;
+    let variable_names = world.get_resource::<SceneVariables>();
+    let variable_names = variable_names.map(|v| v.values.keys().cloned().collect());
+    let variant_names = world.get_resource::<SceneVariants>();
+    let variant_names = variant_names.map(|v| v.variants.keys().cloned().collect());
+    scene.prepare_merge(
+        &variable_names.unwrap_or_default(),
+        &variant_names.unwrap_or_default(),
+        options,
+    );
+
+    // Spawned while the base scene is shown, so the variant that is shown applies to them after
+    let spawned = variants::with_base(world, |world| {
+        let spawned = scene.spawn(world)?;
+        let mut variables = world.get_resource_or_insert_with(SceneVariables::default);
+        for (name, value) in &scene.variables {
+            variables.values.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+        let imported = SceneVariants::from_scene(&scene.variants, &spawned).variants;
+        world.get_resource_or_insert_with(SceneVariants::default).merge(imported);
+        Ok::<_, color_eyre::Report>(spawned)
+    })?;
+
+    #[cfg(feature = "editor")]
+    {
+        let mut selected = world.query_filtered::<Entity, With<Selected>>();
+        for previous in selected.iter(world).collect::<Vec<_>>() {
+            world.entity_mut(previous).remove::<Selected>();
+        }
+        for &entity in &spawned {
+            world.entity_mut(entity).insert(Selected);
+        }
+    }
+    Ok(format!("imported {} entities from {}", spawned.len(), path.display()))
+}
+
+/// Save a scene variant as a scene of its own
+pub fn export_variant(world: &mut World, name: &str, path: &Path) {
+    match variants::export(world, name, path) {
+        Ok(()) => {
+            info!("exported variant {name:?} to {}", path.display());
+            notify(world, NotificationLevel::Success, format!("Saved {}", path.display()));
+        }
+        Err(e) => {
+            warn!("could not export variant: {e}");
+            notify(world, NotificationLevel::Error, format!("Could not export the variant: {e}"));
+        }
+    }
+}
+
+/// Save the base scene as a Bevy `DynamicScene`
+pub fn export_bevy_scene(world: &mut World, path: &Path, options: &BevyExportOptions) {
+    let scene = variants::with_base(world, Scene::from_world);
+    match bevy_export::export(&scene, path, options) {
+        Ok(()) => {
+            info!("exported the scene for Bevy to {}", path.display());
+            notify(world, NotificationLevel::Success, format!("Saved {}", path.display()));
+        }
+        Err(e) => {
+            warn!("could not export the scene for Bevy: {e}");
+            notify(world, NotificationLevel::Error, format!("Could not export for Bevy: {e}"));
+        }
+    }
+}
+
+/// Give an entity a material, or for `None` keep its textures and shader as its own
+pub fn set_material(world: &mut World, entity: Entity, material: Option<MaterialId>) {
+    match material {
+        Some(id) => material::apply(world, entity, id),
+        None => {
+            world.entity_mut(entity).remove::<MaterialHandle>();
+        }
+    }
+}
+
+/// Save the textures and shader of an entity as a material that other entities can use
+pub fn save_material(world: &mut World, entity: Entity, name: &str) {
+    match material::save_from_entity(world, entity, name) {
+        Ok(_) => info!("saved material {name:?}"),
+        Err(e) => warn!("could not save material: {e}"),
+    }
+}
+
+/// Select only the given entity
+#[cfg(feature = "editor")]
+pub fn select(world: &mut World, entity: Entity) {
+    let mut selected = world.query_filtered::<Entity, With<Selected>>();
+    for previous in selected.iter(world).collect::<Vec<_>>() {
+        world.entity_mut(previous).remove::<Selected>();
+    }
+    if let Some(mut entity) = world.get_entity_mut(entity) {
+        entity.insert(Selected);
+        debug!("selected entity {}", entity.id().index());
+    }
+}
+
+/// Spawn a scene camera where the editor camera is, looking the same way, and select it
+///
+/// The camera has no model, so only its frustum is drawn.
+#[cfg(feature = "editor")]
+pub fn add_camera(world: &mut World) {
+    let camera = world.resource::<Camera>();
+    let position = Position::new(camera.pos.x, camera.pos.y, camera.pos.z);
+    // Scene cameras look along -Z of their entity, which the look-at rotation turns the other way
+    let look_at = glm::quat_look_at(&camera.front, &camera.up);
+    let rotation = Rotation::from_quat(glm::quat_inverse(&look_at));
+    let transform = TransformBundle { position, rotation, ..Default::default() };
+    let entity = world.spawn((CameraComponent::default(), transform)).id();
+    info!("added camera {}", entity.index());
+    select(world, entity);
+}
+
+/// Put the selected entities in a new group at their center, and select the group instead
+///
+/// The entities keep where they are in the world. The group is put in the group the entities
+/// were in when they were all in the same one, and selected entities in a selected group stay in
+/// it.
+#[cfg(feature = "editor")]
+pub fn group_selected(world: &mut World) {
+    let mut query =
+        world.query_filtered::<Entity, (With<Selected>, With<Position>, Without<Pooled>)>();
+    let mut selected: Vec<_> = query.iter(world).collect();
+    selected.sort_by_key(|entity| entity.index());
+    let parent_of = |entity: Entity| world.get::<Parent>(entity).map(|p| p.0);
+    let roots: Vec<_> = selected
+        .iter()
+        .copied()
+        .filter(|&entity| parent_of(entity).map_or(true, |parent| !selected.contains(&parent)))
+        .collect();
+    let Some(&first) = roots.first() else {
+        return;
+    };
+    let parent = parent_of(first).filter(|&p| roots.iter().all(|&e| parent_of(e) == Some(p)));
+
+    let center = roots.iter().fold(glm::Vec3::zeros(), |sum, &entity| {
+        sum + glm::vec4_to_vec3(&transform::world_matrix_of(world, entity).column(3).into_owned())
+    }) / roots.len() as f32;
+    let parent_model =
+        parent.map_or_else(glm::Mat4::identity, |p| transform::world_matrix_of(world, p));
+    let local = glm::inverse(&parent_model) * glm::vec4(center.x, center.y, center.z, 1.0);
+    let position = Position::new(local.x, local.y, local.z);
+
+    let mut names = world.query::<&Group>();
+    let names: Vec<_> = names.iter(world).map(|group| group.name.as_str()).collect();
+    let name = (1..).map(|n| format!("Group {n}")).find(|name| !names.contains(&name.as_str()));
+    let name = name.expect("some number is free");
+    info!("grouped {} entities as {name:?}", roots.len());
+    let mut group =
+        world.spawn((Group { name }, TransformBundle { position, ..Default::default() }));
+    if let Some(parent) = parent {
+        group.insert(Parent(parent));
+    }
+    let group = group.id();
+
+    for &entity in &roots {
+        transform::set_parent(world, entity, Some(group));
+    }
+    for entity in selected {
+        world.entity_mut(entity).remove::<Selected>();
+    }
+    world.entity_mut(group).insert(Selected);
+}
+
+/// Take the entities out of the selected groups and remove the groups, selecting the entities
+/// instead
+///
+/// The entities keep where they are in the world, and go in the group the group was in.
+#[cfg(feature = "editor")]
+pub fn ungroup_selected(world: &mut World) {
+    let mut query = world.query_filtered::<Entity, (With<Selected>, With<Group>)>();
+    let groups: Vec<_> = query.iter(world).collect();
+    let mut members = Vec::new();
+    for group in groups {
+        let parent = world.get::<Parent>(group).map(|p| p.0);
+        let mut children = world.query::<(Entity, &Parent)>();
+        let children: Vec<_> =
+            children.iter(world).filter(|(_, p)| p.0 == group).map(|(e, _)| e).collect();
+        for &child in &children {
+            transform::set_parent(world, child, parent);
+        }
+        info!("ungrouped {} entities", children.len());
+        members.extend(children);
+        world.despawn(group);
+    }
+    // Groups selected along with a group they were in are gone by now
+    for entity in members {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            entity.insert(Selected);
+        }
+    }
+}
+
+/// Read a texture back from the GPU and open it in the texture details window
+#[cfg(feature = "editor")]
+pub fn inspect_texture(world: &mut World, name: &str) {
+    let texture_loader = world.resource::<TextureLoader>();
+    let (Some(&texture), Some(info)) = (texture_loader.get(name), texture_loader.info(name)) else {
+        warn!("texture {name} was not loaded from a file");
+        return;
+    };
+    let info = info.clone();
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let pixels = texture_import::read_pixels(&gl, texture, info.width, info.height);
+    let channel = match world.resource::<UiState>().texture_details.as_ref() {
+        Some(details) if details.name == name => details.channel,
+        _ => Default::default(),
+    };
+    world.resource_mut::<UiState>().texture_details = Some(TextureDetails {
+        name: name.to_owned(),
+        settings: info.settings,
+        info,
+        pixels,
+        channel,
+        max_anisotropy: texture_import::max_anisotropy(&gl),
+        quality: world.resource::<TextureLoader>().quality(),
+        preview: None,
+    });
+}
+
+/// Save the import settings of a texture next to its file, and load it again with them
+#[cfg(feature = "editor")]
+pub fn set_texture_settings(world: &mut World, name: &str, settings: TextureSettings) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let result = world.resource_mut::<TextureLoader>().set_settings(&gl, name, settings);
+    match result {
+        Ok(()) => info!("saved import settings of texture {name}"),
+        Err(e) => {
+            warn!("could not apply import settings of texture {name}: {e}");
+            let message = format!("Could not apply the import settings: {e}");
+            notify(world, NotificationLevel::Error, message);
+        }
+    }
+    inspect_texture(world, name);
+}
+
+/// Limit the filtering of every texture
+#[cfg(feature = "editor")]
+pub fn set_texture_quality(world: &mut World, quality: TextureQuality) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    world.resource_mut::<TextureLoader>().set_quality(&gl, quality);
+    if let Some(details) = &mut world.resource_mut::<UiState>().texture_details {
+        details.quality = quality;
+    }
+    debug!("set texture quality to {}", quality.name());
+}
+
+/// Multisample the scene with the given samples per pixel, or as many as the driver supports
+#[cfg(feature = "editor")]
+pub fn set_msaa_samples(world: &mut World, samples: u32) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    match world.resource_mut::<RenderState>().set_samples(&gl, samples) {
+        Ok(applied) => {
+            debug!("set MSAA samples to {applied}");
+            if applied < samples {
+                let message = format!("Only {applied} samples per pixel are supported for MSAA");
+                notify(world, NotificationLevel::Warning, message);
+            }
+        }
+        Err(e) => {
+            warn!("could not set MSAA samples to {samples}: {e}");
+            let message = format!("Could not turn on {samples}× MSAA: {e}");
+            notify(world, NotificationLevel::Error, message);
+            return;
+        }
+    }
+    replace_scene_texture(world);
+}
+
+/// Show the texture the scene is rendered to in the scene view, after the frame graph is rebuilt
+#[cfg(feature = "editor")]
+pub fn replace_scene_texture(world: &mut World) {
+    let scene_texture = world.resource::<RenderState>().frame_graph.texture(SCENE_COLOR);
+    let view_texture = world.get_resource::<SceneView>().and_then(|view| view.texture);
+    if let (Some(id), Some(texture)) = (view_texture, scene_texture) {
+        if let Some(mut egui_glow) = world.get_resource_mut::<EguiGlowRes>() {
+            egui_glow.painter.replace_native_texture(id, texture);
+        }
+    }
+}
+
+/// Load a texture from its file again, with the import settings next to it
+#[cfg(feature = "editor")]
+pub fn reload_texture(world: &mut World, name: &str) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    match world.resource_mut::<TextureLoader>().reload(&gl, name) {
+        Ok(()) => {
+            info!("reloaded texture {name}");
+            notify(world, NotificationLevel::Success, format!("Reloaded texture {name}"));
+        }
+        Err(e) => {
+            warn!("could not reload texture {name}: {e}");
+            notify(world, NotificationLevel::Error, format!("Could not reload {name}: {e}"));
+        }
+    }
+    inspect_texture(world, name);
+}
+
+/// Load an OpenEXR file as the HDRI environment of look-dev mode, and switch to it
+#[cfg(feature = "editor")]
+pub fn load_hdri(world: &mut World, path: &Path) {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let texture = EnvironmentMap::load(path).and_then(|map| unsafe { map.upload(&gl) });
+    let texture = match texture {
+        Ok(texture) => texture,
+        Err(e) => {
+            warn!("could not load HDRI: {e}");
+            notify(world, NotificationLevel::Error, format!("Could not load the HDRI: {e}"));
+            return;
+        }
+    };
+
+    let mut look_dev = world.resource_mut::<LookDev>();
+    let previous = look_dev.hdri.replace(texture);
+    look_dev.environment = LookDevEnvironment::Hdri;
+    if let Some(previous) = previous {
+        // Look-dev may be showing the old one, until it picks up the new one next frame
+        let mut render_state = world.resource_mut::<RenderState>();
+        if render_state.environment_map == Some(previous) {
+            render_state.environment_map = Some(texture);
+        }
+        unsafe { gl.delete_texture(previous) };
+    }
+    info!("loaded HDRI {}", path.display());
+}
+
+/// Save the lights and scene-wide lighting to a file
+pub fn export_lighting(world: &mut World, path: &Path) {
+    match LightingRig::from_world(world).save(path) {
+        Ok(()) => {
+            info!("saved lighting to {}", path.display());
+            notify(world, NotificationLevel::Success, format!("Saved {}", path.display()));
+        }
+        Err(e) => {
+            warn!("could not save lighting: {}", e);
+            notify(world, NotificationLevel::Error, format!("Could not save the lighting: {e}"));
+        }
+    }
+}
+
+/// Replace the lights and scene-wide lighting with the ones stored in a file
+pub fn import_lighting(world: &mut World, path: &Path) {
+    match LightingRig::load(path).and_then(|rig| rig.apply(world)) {
+        Ok(spawned) => {
+            info!("loaded {} lights from {}", spawned.len(), path.display());
+            let message = format!("Loaded {} lights from {}", spawned.len(), path.display());
+            notify(world, NotificationLevel::Success, message);
+        }
+        Err(e) => {
+            warn!("could not load lighting: {}", e);
+            notify(world, NotificationLevel::Error, format!("Could not load the lighting: {e}"));
+        }
+    }
+}
+
+/// Render the scene from the camera and save it as a PNG or OpenEXR image
+pub fn render_image(world: &mut World, path: &Path, settings: &ImageSettings) {
+    let (width, height) = (settings.width, settings.height);
+    let result = renderer::render_offscreen(world, settings)
+        .and_then(|pixels| image_export::save_hdr_image(path, width, height, &pixels));
+    match &result {
+        Ok(()) => info!("rendered {width}x{height} image to {}", path.display()),
+        Err(e) => warn!("could not render image: {}", e),
+    }
+    match result {
+        Ok(()) => notify(world, NotificationLevel::Success, format!("Saved {}", path.display())),
+        Err(e) => notify(world, NotificationLevel::Error, format!("Could not render image: {e}")),
+    }
+}
+
+/// Clean up the mesh of an entity, adding the result as a new model that the entity then uses
+///
+/// The original model is kept, so other entities using it are not changed.
+pub fn clean_up_mesh(world: &mut World, entity: Entity, cleanup: MeshCleanup) {
+    let result = try_clean_up_mesh(world, entity, cleanup);
+    match &result {
+        Ok(message) => info!("{message}"),
+        Err(e) => warn!("could not clean up mesh: {e}"),
+    }
+    match result {
+        Ok(message) => notify(world, NotificationLevel::Success, message),
+        Err(e) => notify(world, NotificationLevel::Error, format!("Could not clean up mesh: {e}")),
+    }
+}
+
+/// Load a model file at runtime and spawn an entity for each of its meshes
+///
+/// Meshes that only differ in where they are share one model, see [`ModelLoader::import`].
+pub fn import_model(world: &mut World, path: &Path) {
+    let result = try_import_model(world, path);
+    match &result {
+        Ok(message) => info!("{message}"),
+        Err(e) => warn!("could not import model: {e}"),
+    }
+    match result {
+        Ok(message) => notify(world, NotificationLevel::Success, message),
+        Err(e) => notify(world, NotificationLevel::Error, format!("Could not import model: {e}")),
+    }
+}
+
+fn try_import_model(world: &mut World, path: &Path) -> Result<String> {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let (parts, report) = world.resource_mut::<ModelLoader>().import(&gl, path)?;
+
+    for part in &parts {
+        let model_loader = world.resource::<ModelLoader>();
+        let mesh = model_loader
+            .get(&part.model)
+            .map(Mesh::from)
+            .ok_or_else(|| eyre!("{} is not loaded", part.model))?;
+        let skin = model_loader.skin(&part.model).cloned();
+        let position = Position::new(part.offset.x, part.offset.y, part.offset.z);
+        let mut entity = world.spawn((mesh, TransformBundle { position, ..Default::default() }));
+        if let Some(skin) = skin {
+            entity.insert((Skeleton::new(skin), AnimationPlayer::default()));
+        }
+        if !part.properties.values.is_empty() {
+            entity.insert(part.properties.clone());
+        }
+    }
+
+    Ok(format!(
+        "imported {} meshes from {}, {} as instances saving {:.1} KiB",
+        report.meshes,
+        path.display(),
+        report.instances,
+        report.bytes_saved as f64 / 1024.0
+    ))
+}
+
+/// Spawn a terrain in front of the camera, flat or with its heights read from a texture
+#[cfg(feature = "editor")]
+pub fn add_terrain(world: &mut World, heightmap: Option<&str>) {
+    let result = try_add_terrain(world, heightmap);
+    match &result {
+        Ok(entity) => info!("added terrain {}", entity.index()),
+        Err(e) => warn!("could not add terrain: {e}"),
+    }
+    match result {
+        Ok(entity) => select(world, entity),
+        Err(e) => notify(world, NotificationLevel::Error, format!("Could not add terrain: {e}")),
+    }
+}
+
+#[cfg(feature = "editor")]
+fn try_add_terrain(world: &mut World, heightmap: Option<&str>) -> Result<Entity> {
+    let terrain = match heightmap {
+        Some(name) => {
+            let info = world.resource::<TextureLoader>().info(name);
+            let path = info.ok_or_else(|| eyre!("texture {name} is not loaded"))?.path.clone();
+            let heightmap = Heightmap::load(&path)?;
+            Terrain::from_heightmap(&heightmap, DEFAULT_QUADS, DEFAULT_SIZE, DEFAULT_SIZE / 8.0)
+        }
+        None => Terrain::flat(DEFAULT_QUADS, DEFAULT_SIZE),
+    };
+    // Under the camera, so the new terrain is in view
+    let camera = world.resource::<Camera>();
+    let position = Position::new(camera.pos.x, camera.pos.y - 2.0, camera.pos.z);
+    Ok(world.spawn((terrain, TransformBundle { position, ..Default::default() })).id())
+}
+
+fn try_clean_up_mesh(world: &mut World, entity: Entity, cleanup: MeshCleanup) -> Result<String> {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let vao_id = world
+        .get::<Mesh>(entity)
+        .ok_or_else(|| eyre!("entity {} has no mesh", entity.index()))?
+        .vao_id;
+
+    let mut model_loader = world.resource_mut::<ModelLoader>();
+    let name = model_loader
+        .name_of(vao_id)
+        .ok_or_else(|| eyre!("the mesh is not a loaded model"))?
+        .to_owned();
+    if model_loader.skin(&name).is_some() {
+        return Err(eyre!("{name} is skinned, and skinned meshes can not be cleaned up"));
+    }
+    let before = model_loader
+        .mesh_data(&name)
+        .ok_or_else(|| eyre!("the vertices of {name} are not available"))?;
+    let after = cleanup.apply(before);
+    let message = format!(
+        "{name}: {} to {} vertices, {} to {} triangles",
+        before.vertex_count(),
+        after.vertex_count(),
+        before.triangle_count(),
+        after.triangle_count()
+    );
+
+    let new_name = model_loader.unique_name(&format!("{name}.{}", cleanup.suffix()));
+    let mesh = Mesh::from(model_loader.insert(&gl, new_name.clone(), after)?);
+    world.entity_mut(entity).insert(mesh);
+    Ok(format!("{message}, saved as {new_name}"))
+}
@@ -1,12 +1,19 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
 use bevy_ecs::prelude::*;
 use color_eyre::Result;
 use glow::{Context, Texture, VertexArray};
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 
-use crate::shader::{Shader, ShaderBuilder, ShaderType};
+use crate::bounds::Aabb;
+use crate::resources::{Camera, CameraPose};
+use crate::shader::{Shader, ShaderBuilder, ShaderDiagnostic, ShaderType, UniformValue};
+use crate::skin::SkinData;
 use crate::vao::VertexArrayObject;
 
-#[derive(Component, Default, Debug, Copy, Clone)]
+#[derive(Component, Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
@@ -25,21 +32,110 @@ impl From<Position> for glm::Vec3 {
     }
 }
 
-/// Rotation in degrees
-#[derive(Component, Default, Debug, Copy, Clone)]
+/// Rotation of an entity, kept as a quaternion
+///
+/// Euler angles are applied around the Y axis, then X, then Z. The angles a rotation was last set
+/// from are kept alongside the quaternion, so the inspector shows the angles that were typed in
+/// rather than an equal rotation with other angles.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "EulerAngles", into = "EulerAngles")]
 pub struct Rotation {
+    quat: glm::Quat,
+    /// In degrees
+    euler: glm::Vec3,
+}
+
+impl Rotation {
+    /// Rotation from Euler angles in degrees
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self::from_euler(glm::vec3(x, y, z))
+    }
+
+    /// Rotation from a vector of Euler angles in degrees
+    pub fn from_euler(degrees: glm::Vec3) -> Self {
+        let turn = |angle: f32, axis: glm::Vec3| glm::quat_angle_axis(angle.to_radians(), &axis);
+        let quat = turn(degrees.y, glm::Vec3::y())
+            * turn(degrees.x, glm::Vec3::x())
+            * turn(degrees.z, glm::Vec3::z());
+        Self { quat, euler: degrees }
+    }
+
+    pub fn from_quat(quat: glm::Quat) -> Self {
+        let quat = glm::quat_normalize(&quat);
+        Self { quat, euler: quat_to_euler(&quat) }
+    }
+
+    /// Euler angles in degrees
+    pub fn euler(&self) -> glm::Vec3 {
+        self.euler
+    }
+
+    pub fn quat(&self) -> glm::Quat {
+        self.quat
+    }
+
+    pub fn matrix(&self) -> glm::Mat4 {
+        glm::quat_to_mat4(&self.quat)
+    }
+
+    /// Rotation `t` of the way from this one to `to`, turning the short way
+    pub fn slerp(&self, to: &Rotation, t: f32) -> Rotation {
+        let (from, mut to) = (self.quat, to.quat);
+        // A quaternion and its negation are the same rotation, from opposite sides
+        if glm::quat_dot(&from, &to) < 0.0 {
+            to = -to;
+        }
+        // Slerp divides by the sine of the angle between the two, so close ones are blended
+        let quat = if glm::quat_dot(&from, &to) > 0.9995 {
+            glm::quat_normalize(&glm::quat_lerp(&from, &to, t))
+        } else {
+            glm::quat_slerp(&from, &to, t)
+        };
+        Rotation::from_quat(quat)
+    }
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self { quat: glm::quat_identity(), euler: glm::Vec3::zeros() }
+    }
+}
+
+/// Euler angles in degrees, applied in Y-X-Z order, of the quaternion
+fn quat_to_euler(quat: &glm::Quat) -> glm::Vec3 {
+    let m = glm::quat_to_mat3(quat);
+    let x = (-m[(1, 2)]).clamp(-1.0, 1.0).asin();
+    // Looking straight up or down, the Y and Z axes line up and only their sum is known
+    let (y, z) = if m[(1, 2)].abs() < 0.9999 {
+        (m[(0, 2)].atan2(m[(2, 2)]), m[(1, 0)].atan2(m[(1, 1)]))
+    } else {
+        ((-m[(2, 0)]).atan2(m[(0, 0)]), 0.0)
+    };
+    glm::vec3(x.to_degrees(), y.to_degrees(), z.to_degrees())
+}
+
+/// Euler angles in degrees, which is how rotations are saved in scenes
+#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EulerAngles {
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
-impl From<Rotation> for glm::Vec3 {
+impl From<EulerAngles> for Rotation {
+    fn from(value: EulerAngles) -> Self {
+        Self::new(value.x, value.y, value.z)
+    }
+}
+
+impl From<Rotation> for EulerAngles {
     fn from(value: Rotation) -> Self {
-        glm::vec3(value.x.to_radians(), value.y.to_radians(), value.z.to_radians())
+        let euler = value.euler;
+        Self { x: euler.x, y: euler.y, z: euler.z }
     }
 }
 
-#[derive(Component, Debug, Copy, Clone)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Scale {
     pub x: f32,
     pub y: f32,
@@ -69,33 +165,132 @@ pub struct TransformBundle {
     pub position: Position,
     pub rotation: Rotation,
     pub scale: Scale,
+    pub global_transform: GlobalTransform,
 }
 
-#[derive(Component)]
+/// Entity the transform of this entity is relative to
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+/// An empty transform that the entities in it move with, by having it as their [`Parent`]
+#[derive(Component, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Group {
+    pub name: String,
+}
+
+/// Model matrix of an entity in the world, with the transforms of its parents applied
+///
+/// Updated by [`update_global_transforms`](crate::transform::update_global_transforms) before the
+/// scene is drawn, for the entities whose transform or parents changed.
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct GlobalTransform(pub glm::Mat4);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(glm::Mat4::identity())
+    }
+}
+
+/// Model matrix an entity was drawn with in the previous frame, used for motion vectors
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct PreviousTransform(pub glm::Mat4);
+
+/// Transform of an animated entity before the last simulation step, and the model matrix it is
+/// drawn with, blended between that transform and the current one
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct Interpolation {
+    pub position: Position,
+    pub rotation: Rotation,
+    pub scale: Scale,
+    pub model: glm::Mat4,
+}
+
+#[derive(Component, Copy, Clone)]
 pub struct Mesh {
     pub vao_id: VertexArray,
     pub indices_len: usize,
+    /// Bounds of the mesh in model space
+    pub aabb: Aabb,
+    /// Whether the mesh can be drawn with a normal map
+    pub has_tangents: bool,
 }
 
 impl From<&VertexArrayObject> for Mesh {
     fn from(vao: &VertexArrayObject) -> Self {
         let vao_id = vao.vao_id;
         let indices_len = vao.indices_len;
-        Self { vao_id, indices_len }
+        let aabb = vao.aabb;
+        let has_tangents = vao.has_tangents;
+        Self { vao_id, indices_len, aabb, has_tangents }
     }
 }
 
-#[derive(Component)]
-pub struct StencilId(pub usize);
+/// Simpler meshes drawn in place of the mesh of the entity when it is far from the camera
+///
+/// Level 0 is the mesh of the entity, and level `n` is `meshes[n - 1]`, drawn from
+/// `distances[n - 1]` away.
+#[derive(Component, Clone, Default)]
+pub struct Lod {
+    /// Camera distance each level after the first is drawn from, from the nearest
+    pub distances: Vec<f32>,
+    /// Simpler meshes of the model, from the most detailed
+    pub meshes: Vec<Mesh>,
+    /// Level drawn at every distance, for checking the levels in the editor
+    pub forced: Option<usize>,
+}
+
+impl Lod {
+    /// The level to draw at a distance from the camera
+    pub fn level(&self, distance: f32) -> usize {
+        let level = match self.forced {
+            Some(level) => level,
+            None => self.distances.iter().take_while(|&&d| distance >= d).count(),
+        };
+        level.min(self.meshes.len())
+    }
+
+    /// The mesh to draw for `mesh`, the mesh of the entity, with the model matrix `model` seen
+    /// from `eye`
+    pub fn select<'a>(
+        lod: Option<&'a Self>,
+        mesh: &'a Mesh,
+        model: &glm::Mat4,
+        eye: &glm::Vec3,
+    ) -> &'a Mesh {
+        let Some(lod) = lod else {
+            return mesh;
+        };
+        let center = glm::vec4_to_vec3(&(model * mesh.aabb.center().push(1.0)));
+        match lod.level(glm::distance(&center, eye)) {
+            0 => mesh,
+            level => &lod.meshes[level - 1],
+        }
+    }
+}
 
 #[derive(Component)]
 pub struct Selected;
 
+/// Not drawn, and does not light the scene if it has a point light
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct Hidden;
+
+/// Not selected by clicking it in the scene view, so it does not get in the way of what is in front
+#[derive(Component, Debug, Default, Copy, Clone)]
+pub struct Locked;
+
 #[derive(Component)]
 pub struct CustomShader {
     pub shader: Result<Shader>,
     pub vert_source: String,
     pub frag_source: String,
+    /// What the compiler reported for each stage the last time the shader was compiled
+    pub vert_diagnostics: Vec<ShaderDiagnostic>,
+    pub frag_diagnostics: Vec<ShaderDiagnostic>,
+    /// Why the shader did not compile the last time it was compiled
+    pub error: Option<String>,
+    /// Vertex and fragment source of the last shader that compiled
+    pub last_working: (String, String),
 }
 
 impl CustomShader {
@@ -110,7 +305,126 @@ impl CustomShader {
             .link()
             .unwrap());
 
-        Self { shader, vert_source, frag_source }
+        Self {
+            shader,
+            last_working: (vert_source.clone(), frag_source.clone()),
+            vert_source,
+            frag_source,
+            vert_diagnostics: Vec::new(),
+            frag_diagnostics: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+/// Values of the uniforms of a custom shader, edited in the inspector and set when drawing
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct ShaderParams {
+    pub values: BTreeMap<String, UniformValue>,
+}
+
+impl ShaderParams {
+    /// Match the values to the uniforms of a newly compiled shader
+    ///
+    /// Values of uniforms that are still there with the same type are kept, new uniforms start
+    /// with the value the shader gives them and the rest are dropped.
+    pub fn sync(&mut self, uniforms: Vec<(String, UniformValue)>) {
+        let mut values = BTreeMap::new();
+        for (name, value) in uniforms {
+            let kept = self.values.get(&name).filter(|old| old.same_type(&value));
+            values.insert(name, kept.copied().unwrap_or(value));
+        }
+        self.values = values;
+    }
+}
+
+/// Value of a custom property, written in scene files as a plain string, number or boolean
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl PropertyValue {
+    /// A glTF extras value as a property, with arrays and objects kept as their JSON text
+    pub fn from_json(value: &serde_json::Value) -> Option<Self> {
+        Some(match value {
+            serde_json::Value::Null => return None,
+            serde_json::Value::Bool(b) => PropertyValue::Bool(*b),
+            serde_json::Value::Number(n) => PropertyValue::Number(n.as_f64()?),
+            serde_json::Value::String(s) => PropertyValue::String(s.clone()),
+            other => PropertyValue::String(other.to_string()),
+        })
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(value: bool) -> Self {
+        PropertyValue::Bool(value)
+    }
+}
+
+impl From<f64> for PropertyValue {
+    fn from(value: f64) -> Self {
+        PropertyValue::Number(value)
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.to_owned())
+    }
+}
+
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+
+/// Free-form properties of an entity, for tagging it with data other tools use
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct Properties {
+    pub values: BTreeMap<String, PropertyValue>,
+}
+
+impl Properties {
+    /// The `extras` of a glTF node, if they are a JSON object
+    pub fn from_gltf_extras(extras: &str) -> Option<Self> {
+        let serde_json::Value::Object(object) = serde_json::from_str(extras).ok()? else {
+            return None;
+        };
+        let values = object
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), PropertyValue::from_json(value)?)))
+            .collect();
+        Some(Self { values })
+    }
+}
+
+/// Labels for finding an entity by category in the outliner, such as `light` or `background`
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct Tags {
+    pub values: BTreeSet<String>,
+}
+
+impl Tags {
+    pub fn new<S: Into<String>>(tags: impl IntoIterator<Item = S>) -> Self {
+        Self { values: tags.into_iter().map(Into::into).collect() }
+    }
+}
+
+/// Behavior written in Rhai, run each frame by the editor
+#[derive(Component, Default, Debug, Clone, PartialEq)]
+pub struct Script {
+    pub source: String,
+}
+
+impl Script {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self { source: source.into() }
     }
 }
 
@@ -118,9 +432,23 @@ impl CustomShader {
 pub struct CustomTexture {
     pub diffuse: Option<Texture>,
     pub specular: Option<Texture>,
+    /// Tangent-space normal map, used on meshes with tangents
+    pub normal: Option<Texture>,
+    /// Cut out the mostly transparent parts of the diffuse texture
+    pub alpha_test: bool,
 }
 
-#[derive(Component)]
+/// Share of the brightest diffuse channel below which a point light counts as out of range
+pub const LIGHT_CUTOFF: f32 = 1.0 / 64.0;
+
+/// Share of the attenuation at the range that comes from the linear term, the rest being
+/// quadratic, as in the usual attenuation tables
+const LINEAR_SHARE: f32 = 4.5 / 79.5;
+
+/// Shortest range a point light can be given
+const RANGE_EPSILON: f32 = 1e-3;
+
+#[derive(Component, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PointLight {
     pub ambient: glm::Vec3,
     pub diffuse: glm::Vec3,
@@ -141,4 +469,354 @@ impl PointLight {
     ) -> Self {
         Self { ambient, diffuse, specular, constant, linear, quadratic }
     }
+
+    /// Distance at which the diffuse light has faded below [`LIGHT_CUTOFF`], or `None` if it never
+    /// does
+    pub fn range(&self) -> Option<f32> {
+        let (linear, quadratic) = (self.linear, self.quadratic);
+        // Solve quadratic * d^2 + linear * d = target for the distance d
+        let target = self.attenuation_at_range();
+        if target <= 0.0 {
+            Some(0.0)
+        } else if quadratic > 0.0 {
+            Some(
+                (-linear + (linear * linear + 4.0 * quadratic * target).sqrt()) / (2.0 * quadratic),
+            )
+        } else if linear > 0.0 {
+            Some(target / linear)
+        } else {
+            None
+        }
+    }
+
+    /// Set the linear and quadratic attenuation so the light reaches `range`, keeping the constant
+    /// attenuation and the color
+    pub fn set_range(&mut self, range: f32) {
+        let range = range.max(RANGE_EPSILON);
+        let target = self.attenuation_at_range().max(0.0);
+        self.linear = target * LINEAR_SHARE / range;
+        self.quadratic = target * (1.0 - LINEAR_SHARE) / (range * range);
+    }
+
+    /// What the linear and quadratic terms add up to at the range
+    fn attenuation_at_range(&self) -> f32 {
+        self.diffuse.max() / LIGHT_CUTOFF - self.constant
+    }
+}
+
+/// A camera that is part of the scene, looking along the -Z axis of its entity
+///
+/// Scene cameras are separate from the editor camera that flies through the scene. The view can
+/// be looked through or previewed in a corner while the entity is selected.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraComponent {
+    /// Vertical field of view in degrees
+    pub fov_degrees: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl CameraComponent {
+    /// Camera at the entity with the given model matrix, projecting onto a view of the given
+    /// size
+    ///
+    /// Roll is left out, as the editor camera keeps the horizon level.
+    pub fn camera(&self, model: &glm::Mat4, width: u32, height: u32) -> Camera {
+        let position = model.column(3).xyz();
+        let front = (model * glm::vec4(0.0, 0.0, -1.0, 0.0)).xyz();
+        let front = if front == glm::Vec3::zeros() { -glm::Vec3::z() } else { front.normalize() };
+        let pitch = (front.y.clamp(-1.0, 1.0) as f64).asin().to_degrees();
+        let yaw = (front.z as f64).atan2(front.x as f64).to_degrees();
+
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        let near = self.near.max(f32::EPSILON);
+        let far = self.far.max(near * 2.0);
+        let projection = glm::perspective(aspect, self.fov_degrees.to_radians(), near, far);
+        let front = CameraPose { position, yaw, pitch }.front();
+        Camera::new(projection, position, front, glm::Vec3::y(), yaw, pitch)
+    }
+}
+
+impl Default for CameraComponent {
+    fn default() -> Self {
+        Self { fov_degrees: Camera::FOV_DEGREES, near: Camera::NEAR, far: Camera::FAR }
+    }
+}
+
+/// Continuously rotates an entity, in degrees per second around each axis
+#[derive(Component, Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rotator {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Rotator {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// Moves an entity back and forth along an axis, relative to where it currently is
+#[derive(Component, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Oscillator {
+    pub axis: glm::Vec3,
+    pub amplitude: f32,
+    /// Oscillations per second
+    pub frequency: f32,
+    #[serde(skip)]
+    pub elapsed: f32,
+}
+
+impl Oscillator {
+    pub fn new(axis: glm::Vec3, amplitude: f32, frequency: f32) -> Self {
+        Self { axis, amplitude, frequency, elapsed: 0.0 }
+    }
+
+    /// Offset from the center of the oscillation at the given time
+    pub fn offset_at(&self, time: f32) -> glm::Vec3 {
+        let axis = if self.axis == glm::Vec3::zeros() { self.axis } else { self.axis.normalize() };
+        axis * self.amplitude * (std::f32::consts::TAU * self.frequency * time).sin()
+    }
+}
+
+impl Default for Oscillator {
+    fn default() -> Self {
+        Self::new(glm::vec3(0.0, 1.0, 0.0), 1.0, 0.5)
+    }
+}
+
+/// Moves an entity along a sequence of points at a constant speed
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FollowPath {
+    pub points: Vec<glm::Vec3>,
+    /// Units per second
+    pub speed: f32,
+    pub looping: bool,
+    /// Distance travelled along the path
+    #[serde(skip)]
+    pub distance: f32,
+}
+
+impl FollowPath {
+    pub fn new(points: Vec<glm::Vec3>, speed: f32, looping: bool) -> Self {
+        Self { points, speed, looping, distance: 0.0 }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (glm::Vec3, glm::Vec3)> + '_ {
+        let closing = if self.looping && self.points.len() > 2 {
+            Some((*self.points.last().unwrap(), self.points[0]))
+        } else {
+            None
+        };
+        self.points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(a, b)| glm::distance(&a, &b)).sum()
+    }
+
+    /// Position on the path after travelling the given distance
+    pub fn sample(&self, distance: f32) -> Option<glm::Vec3> {
+        let first = *self.points.first()?;
+        let length = self.length();
+        if length <= 0.0 {
+            return Some(first);
+        }
+
+        let mut remaining =
+            if self.looping { distance.rem_euclid(length) } else { distance.clamp(0.0, length) };
+        for (a, b) in self.segments() {
+            let segment_length = glm::distance(&a, &b);
+            if remaining <= segment_length {
+                let t = if segment_length > 0.0 { remaining / segment_length } else { 0.0 };
+                return Some(glm::lerp(&a, &b, t));
+            }
+            remaining -= segment_length;
+        }
+
+        self.segments().last().map(|(_, b)| b)
+    }
+}
+
+impl Default for FollowPath {
+    fn default() -> Self {
+        Self::new(Vec::new(), 2.0, true)
+    }
+}
+
+/// Joint matrices of a skinned mesh, updated from its `AnimationPlayer`
+#[derive(Component, Clone)]
+pub struct Skeleton {
+    pub skin: Arc<SkinData>,
+    pub joint_matrices: Vec<glm::Mat4>,
+}
+
+impl Skeleton {
+    pub fn new(skin: Arc<SkinData>) -> Self {
+        let mut joint_matrices = Vec::new();
+        skin.joint_matrices(None, &mut joint_matrices);
+        Self { skin, joint_matrices }
+    }
+}
+
+#[derive(Component, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationPlayer {
+    /// Index of the animation in the skin
+    pub animation: Option<usize>,
+    pub speed: f32,
+    pub looping: bool,
+    #[serde(skip)]
+    pub time: f32,
+    #[serde(skip)]
+    pub playing: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self { animation: None, speed: 1.0, looping: true, time: 0.0, playing: false }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Cubic,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f32,
+    pub position: Position,
+    pub rotation: Rotation,
+    pub scale: Scale,
+}
+
+impl Keyframe {
+    fn to_vecs(self) -> [glm::Vec3; 3] {
+        let Self { position: p, rotation: r, scale: s, .. } = self;
+        [glm::vec3(p.x, p.y, p.z), r.euler(), glm::vec3(s.x, s.y, s.z)]
+    }
+
+    fn from_vecs(time: f32, [p, r, s]: [glm::Vec3; 3]) -> Self {
+        Self {
+            time,
+            position: Position::new(p.x, p.y, p.z),
+            rotation: Rotation::from_euler(r),
+            scale: Scale::new(s.x, s.y, s.z),
+        }
+    }
+}
+
+/// Transform keyframes recorded on the timeline, sorted by time
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub keyframes: Vec<Keyframe>,
+    pub interpolation: Interpolation,
+    pub looping: bool,
+    /// Length of the timeline in seconds
+    pub length: f32,
+    #[serde(skip)]
+    pub time: f32,
+    #[serde(skip)]
+    pub playing: bool,
+}
+
+impl AnimationClip {
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Time playback loops or stops at: the length of the timeline, or the last keyframe when it
+    /// is later
+    pub fn end(&self) -> f32 {
+        self.length.max(self.duration())
+    }
+
+    /// Move the playhead forward while playing, holding the last keyframe until the end
+    pub fn advance(&mut self, delta_seconds: f32) {
+        if !self.playing {
+            return;
+        }
+        let end = self.end();
+        self.time += delta_seconds;
+        if self.time > end {
+            if self.looping && end > 0.0 {
+                self.time %= end;
+            } else {
+                self.time = end;
+                self.playing = false;
+            }
+        }
+    }
+
+    /// Insert a keyframe, replacing any existing keyframe at the same time
+    pub fn insert_keyframe(&mut self, keyframe: Keyframe) {
+        const EPSILON: f32 = 1e-3;
+        match self.keyframes.iter().position(|k| (k.time - keyframe.time).abs() < EPSILON) {
+            Some(i) => self.keyframes[i] = keyframe,
+            None => {
+                let i = self.keyframes.partition_point(|k| k.time < keyframe.time);
+                self.keyframes.insert(i, keyframe);
+            }
+        }
+    }
+
+    pub fn sample(&self, time: f32) -> Option<Keyframe> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(*first);
+        }
+        if time >= last.time {
+            return Some(*last);
+        }
+
+        let i = self.keyframes.partition_point(|k| k.time <= time);
+        let (a, b) = (self.keyframes[i - 1], self.keyframes[i]);
+        let t = (time - a.time) / (b.time - a.time);
+        let (va, vb) = (a.to_vecs(), b.to_vecs());
+
+        let values = match self.interpolation {
+            Interpolation::Linear => [0, 1, 2].map(|j| glm::lerp(&va[j], &vb[j], t)),
+            Interpolation::Cubic => {
+                let before = self.keyframes[i.saturating_sub(2)].to_vecs();
+                let after = self.keyframes[(i + 1).min(self.keyframes.len() - 1)].to_vecs();
+                [0, 1, 2].map(|j| catmull_rom(&before[j], &va[j], &vb[j], &after[j], t))
+            }
+        };
+
+        Some(Keyframe::from_vecs(time, values))
+    }
+}
+
+impl Default for AnimationClip {
+    fn default() -> Self {
+        Self {
+            keyframes: Vec::new(),
+            interpolation: Interpolation::default(),
+            looping: true,
+            length: 5.0,
+            time: 0.0,
+            playing: false,
+        }
+    }
+}
+
+fn catmull_rom(
+    p0: &glm::Vec3,
+    p1: &glm::Vec3,
+    p2: &glm::Vec3,
+    p3: &glm::Vec3,
+    t: f32,
+) -> glm::Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2.0 * p1)
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
 }
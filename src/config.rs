@@ -0,0 +1,425 @@
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use bevy_ecs::system::Resource;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use egui::Color32;
+use toml_edit::{Array, Document, Item, Table};
+use tracing::{info, warn};
+
+use crate::backup;
+use crate::frame_limiter::{VsyncMode, FPS_LIMIT_RANGE};
+use crate::palette::PalettePreset;
+use crate::resources::MSAA_SAMPLES;
+use crate::settings::{self, DockLayout, Theme};
+use crate::snapping::Snapping;
+use crate::texture_import::TextureQuality;
+
+/// How many recently opened or saved scenes are remembered
+pub const MAX_RECENT_FILES: usize = 10;
+/// Sizes the editor interface can be scaled to, relative to the scale factor of the display
+pub const UI_SCALE_RANGE: RangeInclusive<f32> = 0.75..=2.0;
+
+/// Where the per-user editor configuration is kept
+///
+/// This is `scene-editor/config.toml` in `$XDG_CONFIG_HOME`, or in `~/.config` if it is not set.
+/// On Windows it is in `%APPDATA%`. Falls back to the working directory if none of them are set.
+pub fn config_path() -> PathBuf {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    base.map_or_else(|| PathBuf::from("config.toml"), |dir| dir.join("scene-editor/config.toml"))
+}
+
+/// Read a TOML document, or an empty one if the file does not exist yet
+pub(crate) fn read_document(path: &Path) -> Result<Document> {
+    if !path.exists() {
+        return Ok(Document::new());
+    }
+    let source = fs::read_to_string(path)?;
+    source.parse().map_err(|e| eyre!("could not parse {}: {e}", path.display()))
+}
+
+/// Change some of the tables in a TOML file, keeping the others and their comments as they are
+pub(crate) fn update_document(path: &Path, update: impl FnOnce(&mut Document)) -> Result<()> {
+    let mut document = read_document(path)?;
+    update(&mut document);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Size and position of the editor window, in physical pixels
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WindowConfig {
+    pub size: Option<(u32, u32)>,
+    pub position: Option<(i32, i32)>,
+}
+
+/// How the camera responds to input
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraConfig {
+    /// Units per second
+    pub move_speed: f32,
+    /// Degrees per pixel of mouse movement
+    pub look_sensitivity: f64,
+    pub invert_y: bool,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self { move_speed: 5.0, look_sensitivity: 0.3, invert_y: false }
+    }
+}
+
+/// Per-user editor configuration that is kept between sessions
+///
+/// The key bindings are kept in the same file, in the `bindings` table written by
+/// [`InputMap::save`](crate::input_map::InputMap::save).
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct EditorConfig {
+    pub window: WindowConfig,
+    /// When frames wait for vertical sync
+    pub vsync: VsyncMode,
+    /// Most frames drawn per second, within [`FPS_LIMIT_RANGE`], or `None` for no limit
+    pub fps_limit: Option<u32>,
+    /// Draw few frames while the window is in the background, to save power
+    pub idle_when_unfocused: bool,
+    /// Samples per pixel of the scene, one of [`MSAA_SAMPLES`]
+    pub msaa_samples: u32,
+    pub camera: CameraConfig,
+    /// Scenes that were opened or saved, most recent first
+    pub recent_files: Vec<PathBuf>,
+    pub theme: Theme,
+    /// Color of selections and links in place of the one of the theme
+    pub accent: Option<Color32>,
+    /// Size of the interface relative to the scale factor of the display, within
+    /// [`UI_SCALE_RANGE`]
+    pub ui_scale: f32,
+    /// Show the start screen when the editor is started without a scene
+    pub start_screen: bool,
+    /// Backups kept of each scene when it is saved, or 0 to not make any
+    pub backups: usize,
+    /// Keep linked custom shaders on disk, so they load faster the next time they are used
+    pub shader_binaries: bool,
+    /// Limit on the filtering of every texture
+    pub texture_quality: TextureQuality,
+    /// Ask the releases feed for a newer version on startup
+    pub check_for_updates: bool,
+    /// Version that was running last time, used to show what's new after upgrading
+    pub last_seen_version: Option<String>,
+    /// Record usage statistics to a local file
+    pub usage_log: bool,
+    /// Steps transforms are rounded to while Ctrl is held, and the distance of each nudge
+    pub snapping: Snapping,
+    /// Colors for the selection outline, bounding boxes and axes
+    pub palette: PalettePreset,
+    pub layout: DockLayout,
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig::default(),
+            vsync: VsyncMode::default(),
+            fps_limit: None,
+            idle_when_unfocused: true,
+            msaa_samples: 1,
+            camera: CameraConfig::default(),
+            recent_files: Vec::new(),
+            theme: Theme::default(),
+            accent: None,
+            ui_scale: 1.0,
+            start_screen: true,
+            backups: backup::DEFAULT_KEEP,
+            shader_binaries: true,
+            texture_quality: TextureQuality::default(),
+            check_for_updates: false,
+            last_seen_version: None,
+            usage_log: false,
+            snapping: Snapping::default(),
+            palette: PalettePreset::default(),
+            layout: DockLayout::default(),
+        }
+    }
+}
+
+impl EditorConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let document = read_document(path)?;
+
+        let mut config = Self::default();
+        if let Some(window) = document.get("window").and_then(Item::as_table_like) {
+            let int = |key| window.get(key).and_then(Item::as_integer);
+            if let (Some(width), Some(height)) = (int("width"), int("height")) {
+                if let (Ok(width @ 1..), Ok(height @ 1..)) = (width.try_into(), height.try_into()) {
+                    config.window.size = Some((width, height));
+                }
+            }
+            if let (Some(x), Some(y)) = (int("x"), int("y")) {
+                if let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) {
+                    config.window.position = Some((x, y));
+                }
+            }
+            // Older configurations turn vsync on or off with a boolean
+            if let Some(vsync) = window.get("vsync") {
+                let mode = match (vsync.as_bool(), vsync.as_str()) {
+                    (Some(on), _) => Some(if on { VsyncMode::On } else { VsyncMode::Off }),
+                    (_, Some(name)) => VsyncMode::ALL.into_iter().find(|m| m.name() == name),
+                    _ => None,
+                };
+                match mode {
+                    Some(mode) => config.vsync = mode,
+                    None => warn!("unknown vsync mode {} in {}", vsync, path.display()),
+                }
+            }
+            if let Some(fps) = window.get("fps_limit").and_then(Item::as_integer) {
+                let (start, end) = (*FPS_LIMIT_RANGE.start(), *FPS_LIMIT_RANGE.end());
+                let clamped = fps.clamp(start.into(), end.into());
+                if clamped != fps {
+                    warn!("fps_limit {fps} in {} is out of range, clamping it", path.display());
+                }
+                config.fps_limit = u32::try_from(clamped).ok();
+            }
+            if let Some(idle) = window.get("idle_when_unfocused").and_then(Item::as_bool) {
+                config.idle_when_unfocused = idle;
+            }
+        }
+        if let Some(camera) = document.get("camera").and_then(Item::as_table_like) {
+            if let Some(speed) = camera.get("move_speed").and_then(Item::as_float) {
+                config.camera.move_speed = speed as f32;
+            }
+            if let Some(sensitivity) = camera.get("look_sensitivity").and_then(Item::as_float) {
+                config.camera.look_sensitivity = sensitivity;
+            }
+            if let Some(invert_y) = camera.get("invert_y").and_then(Item::as_bool) {
+                config.camera.invert_y = invert_y;
+            }
+        }
+        if let Some(files) = document.get("recent").and_then(|recent| recent.get("files")) {
+            let files = files.as_array().into_iter().flatten().filter_map(|f| f.as_str());
+            config.recent_files = files.map(PathBuf::from).take(MAX_RECENT_FILES).collect();
+        }
+        if let Some(start_screen) =
+            document.get("startup").and_then(|startup| startup.get("start_screen"))
+        {
+            config.start_screen = start_screen.as_bool().unwrap_or(config.start_screen);
+        }
+        if let Some(keep) = document.get("backups").and_then(|backups| backups.get("keep")) {
+            match keep.as_integer().map(usize::try_from) {
+                Some(Ok(keep)) => config.backups = keep,
+                _ => warn!("invalid number of backups {} in {}", keep, path.display()),
+            }
+        }
+        if let Some(binaries) = document.get("shaders").and_then(|s| s.get("cache_binaries")) {
+            config.shader_binaries = binaries.as_bool().unwrap_or(config.shader_binaries);
+        }
+        if let Some(quality) = document.get("textures").and_then(|t| t.get("quality")) {
+            let name = quality.as_str().unwrap_or_default();
+            match TextureQuality::ALL.into_iter().find(|q| q.name() == name) {
+                Some(quality) => config.texture_quality = quality,
+                None => warn!("unknown texture quality {} in {}", quality, path.display()),
+            }
+        }
+        if let Some(msaa) = document.get("rendering").and_then(|r| r.get("msaa_samples")) {
+            match msaa.as_integer().and_then(|n| u32::try_from(n).ok()) {
+                Some(samples) if MSAA_SAMPLES.contains(&samples) => config.msaa_samples = samples,
+                _ => warn!("invalid MSAA samples {} in {}", msaa, path.display()),
+            }
+        }
+        if let Some(appearance) = document.get("appearance").and_then(Item::as_table_like) {
+            if let Some(name) = appearance.get("theme").and_then(Item::as_str) {
+                match Theme::ALL.into_iter().find(|t| t.name() == name) {
+                    Some(theme) => config.theme = theme,
+                    None => warn!("unknown theme {:?} in {}", name, path.display()),
+                }
+            }
+            if let Some(accent) = appearance.get("accent").and_then(Item::as_str) {
+                match settings::parse_color(accent) {
+                    Some(color) => config.accent = Some(color),
+                    None => warn!("invalid accent color {:?} in {}", accent, path.display()),
+                }
+            }
+            if let Some(scale) = appearance.get("ui_scale").and_then(Item::as_float) {
+                let scale = scale as f32;
+                if !UI_SCALE_RANGE.contains(&scale) {
+                    warn!("ui_scale {scale} in {} is out of range, clamping it", path.display());
+                }
+                config.ui_scale = scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+            }
+        }
+        config.load_settings(&document, path);
+
+        Ok(config)
+    }
+
+    /// Read the tables that were kept in the settings file before they moved here
+    fn load_settings(&mut self, document: &Document, path: &Path) {
+        if let Some(updates) = document.get("updates").and_then(Item::as_table_like) {
+            if let Some(check) = updates.get("check_for_updates").and_then(Item::as_bool) {
+                self.check_for_updates = check;
+            }
+            if let Some(version) = updates.get("last_seen_version").and_then(Item::as_str) {
+                self.last_seen_version = Some(version.to_owned());
+            }
+        }
+        if let Some(usage) = document.get("usage").and_then(Item::as_table_like) {
+            if let Some(enabled) = usage.get("keep_local_log").and_then(Item::as_bool) {
+                self.usage_log = enabled;
+            }
+        }
+        // Older versions only had a nudge distance, under [editing]
+        if let Some(editing) = document.get("editing").and_then(Item::as_table_like) {
+            if let Some(snap) = editing.get("snap_increment").and_then(Item::as_float) {
+                self.snapping.position = snap as f32;
+            }
+        }
+        if let Some(snapping) = document.get("snapping").and_then(Item::as_table_like) {
+            let steps = [
+                ("position", &mut self.snapping.position),
+                ("rotation", &mut self.snapping.rotation),
+                ("scale", &mut self.snapping.scale),
+            ];
+            for (key, step) in steps {
+                if let Some(value) = snapping.get(key).and_then(Item::as_float) {
+                    *step = value as f32;
+                }
+            }
+        }
+        let palette = document.get("appearance").and_then(|a| a.get("palette"));
+        if let Some(name) = palette.and_then(Item::as_str) {
+            match PalettePreset::ALL.into_iter().find(|p| p.name() == name) {
+                Some(palette) => self.palette = palette,
+                None => warn!("unknown palette {:?} in {}", name, path.display()),
+            }
+        }
+        if let Some(layout) = document.get("layout").and_then(Item::as_table_like) {
+            self.layout.load(layout, path);
+        }
+    }
+
+    /// Load the configuration, falling back to the defaults if it is missing or invalid
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        Self::load(path).unwrap_or_else(|e| {
+            warn!("could not load editor configuration, using defaults: {e}");
+            Self::default()
+        })
+    }
+
+    /// Load the configuration, and the settings in `legacy` if it does not have them yet
+    ///
+    /// Panels, snapping, the palette and the update and usage choices were kept in a settings
+    /// file in the working directory before they moved here. They are taken from it until the
+    /// configuration is saved with a `layout` table.
+    pub fn load_or_migrate(path: impl AsRef<Path>, legacy: impl AsRef<Path>) -> Self {
+        let (path, legacy) = (path.as_ref(), legacy.as_ref());
+        let mut config = Self::load_or_default(path);
+        let migrated = read_document(path).is_ok_and(|document| document.contains_key("layout"));
+        if !migrated && legacy.exists() {
+            info!("moving settings from {} to {}", legacy.display(), path.display());
+            match read_document(legacy) {
+                Ok(document) => config.load_settings(&document, legacy),
+                Err(e) => warn!("could not load settings, using defaults: {e}"),
+            }
+        }
+        config
+    }
+
+    /// Write the configuration, keeping the key bindings and anything else in the file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut window = Table::new();
+        if let Some((width, height)) = self.window.size {
+            window["width"] = toml_edit::value(i64::from(width));
+            window["height"] = toml_edit::value(i64::from(height));
+        }
+        if let Some((x, y)) = self.window.position {
+            window["x"] = toml_edit::value(i64::from(x));
+            window["y"] = toml_edit::value(i64::from(y));
+        }
+        window["vsync"] = toml_edit::value(self.vsync.name());
+        if let Some(fps) = self.fps_limit {
+            window["fps_limit"] = toml_edit::value(i64::from(fps));
+        }
+        window["idle_when_unfocused"] = toml_edit::value(self.idle_when_unfocused);
+        let mut camera = Table::new();
+        camera["move_speed"] = toml_edit::value(self.camera.move_speed as f64);
+        camera["look_sensitivity"] = toml_edit::value(self.camera.look_sensitivity);
+        camera["invert_y"] = toml_edit::value(self.camera.invert_y);
+        let mut recent = Table::new();
+        let files: Array =
+            self.recent_files.iter().map(|f| f.to_string_lossy().into_owned()).collect();
+        recent["files"] = toml_edit::value(files);
+        let mut startup = Table::new();
+        startup["start_screen"] = toml_edit::value(self.start_screen);
+        let mut rendering = Table::new();
+        rendering["msaa_samples"] = toml_edit::value(i64::from(self.msaa_samples));
+        let mut appearance = Table::new();
+        appearance["theme"] = toml_edit::value(self.theme.name());
+        if let Some(accent) = self.accent {
+            appearance["accent"] = toml_edit::value(settings::color_hex(accent));
+        }
+        appearance["ui_scale"] = toml_edit::value(self.ui_scale as f64);
+        appearance["palette"] = toml_edit::value(self.palette.name());
+        let mut backups = Table::new();
+        backups["keep"] = toml_edit::value(self.backups as i64);
+        let mut shaders = Table::new();
+        shaders["cache_binaries"] = toml_edit::value(self.shader_binaries);
+        let mut textures = Table::new();
+        textures["quality"] = toml_edit::value(self.texture_quality.name());
+        let mut updates = Table::new();
+        updates["check_for_updates"] = toml_edit::value(self.check_for_updates);
+        if let Some(version) = &self.last_seen_version {
+            updates["last_seen_version"] = toml_edit::value(version.as_str());
+        }
+        let mut usage = Table::new();
+        usage["keep_local_log"] = toml_edit::value(self.usage_log);
+        let mut snapping = Table::new();
+        snapping["position"] = toml_edit::value(self.snapping.position as f64);
+        snapping["rotation"] = toml_edit::value(self.snapping.rotation as f64);
+        snapping["scale"] = toml_edit::value(self.snapping.scale as f64);
+
+        update_document(path.as_ref(), |document| {
+            document["window"] = Item::Table(window);
+            document["camera"] = Item::Table(camera);
+            document["recent"] = Item::Table(recent);
+            document["startup"] = Item::Table(startup);
+            document["rendering"] = Item::Table(rendering);
+            document["appearance"] = Item::Table(appearance);
+            document["backups"] = Item::Table(backups);
+            document["shaders"] = Item::Table(shaders);
+            document["textures"] = Item::Table(textures);
+            document["updates"] = Item::Table(updates);
+            document["usage"] = Item::Table(usage);
+            document["snapping"] = Item::Table(snapping);
+            document["layout"] = Item::Table(self.layout.save());
+        })
+    }
+
+    /// Pixels per point of the editor interface on a display with the given scale factor
+    pub fn pixels_per_point(&self, scale_factor: f64) -> f32 {
+        scale_factor as f32 * self.ui_scale
+    }
+
+    /// Move a scene to the top of the recent files
+    pub fn add_recent_file(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        self.recent_files.retain(|file| *file != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Forget the recent files that have been moved or deleted
+    pub fn remove_missing_recent_files(&mut self) {
+        self.recent_files.retain(|file| file.exists());
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use bevy_ecs::prelude::*;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+use crate::components::{Mesh, Position, TransformBundle};
+use crate::config::EditorConfig;
+use crate::entity_pool::{self, Pooled};
+use crate::resources::{Lighting, ModelLoader};
+
+/// Lines kept in the console, the oldest are dropped first
+const CAPACITY: usize = 1000;
+/// Target of the lines the console writes itself, for the commands typed into it and their results
+pub const CONSOLE_TARGET: &str = "console";
+
+/// A log event, or a line written by the console
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub level: Level,
+    /// Module the event came from, or [`CONSOLE_TARGET`]
+    pub target: String,
+    pub message: String,
+}
+
+impl LogLine {
+    /// The line as the console shows it, after the module it came from unless the console wrote it
+    pub fn text(&self) -> String {
+        match self.target.as_str() {
+            CONSOLE_TARGET => self.message.clone(),
+            target => format!("{target}: {}", self.message),
+        }
+    }
+}
+
+/// Lines shown in the console, shared with the `tracing` layer that writes the log events to it
+#[derive(Resource, Clone, Default)]
+pub struct ConsoleLog {
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+}
+
+impl ConsoleLog {
+    pub fn push(&self, line: LogLine) {
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        if lines.len() == CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Write a line of the console's own
+    pub fn reply(&self, level: Level, message: impl Into<String>) {
+        self.push(LogLine { level, target: CONSOLE_TARGET.to_owned(), message: message.into() });
+    }
+
+    /// Copy of the lines, oldest first
+    ///
+    /// The lines are copied so nothing holds the lock while they are drawn, as drawing can log.
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.lock().map(|lines| lines.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// The lines the console shows, leaving out debug and trace lines unless `debug` is set
+    pub fn shown_lines(&self, debug: bool) -> Vec<LogLine> {
+        let mut lines = self.lines();
+        lines.retain(|line| debug || line.level <= Level::INFO);
+        lines
+    }
+
+    /// The lines the console shows as text to copy, each after its level
+    pub fn to_text(&self, debug: bool) -> String {
+        let lines = self.shown_lines(debug).into_iter();
+        lines.map(|line| format!("{} {}\n", line.level, line.text())).collect()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.clear();
+        }
+    }
+
+    /// Layer for the `tracing` subscriber that writes every event it is given to this log
+    pub fn layer(&self) -> ConsoleLayer {
+        ConsoleLayer { log: self.clone() }
+    }
+}
+
+/// Writes log events to a [`ConsoleLog`]
+pub struct ConsoleLayer {
+    log: ConsoleLog,
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        let metadata = event.metadata();
+        self.log.push(LogLine {
+            level: *metadata.level(),
+            target: metadata.target().to_owned(),
+            message: message.0,
+        });
+    }
+}
+
+/// Formats the message of an event followed by its other fields, as the terminal output does
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        let _ = match field.name() {
+            "message" => write!(self.0, "{value:?}"),
+            name => write!(self.0, "{name}={value:?}"),
+        };
+    }
+}
+
+/// A value that can be changed with `set`
+struct Setting {
+    name: &'static str,
+    get: fn(&World) -> Option<f32>,
+    set: fn(&mut World, f32),
+}
+
+static SETTINGS: [Setting; 3] = [
+    Setting {
+        name: "camera.speed",
+        get: |world| Some(world.get_resource::<EditorConfig>()?.camera.move_speed),
+        set: |world, value| {
+            if let Some(mut config) = world.get_resource_mut::<EditorConfig>() {
+                config.camera.move_speed = value;
+            }
+        },
+    },
+    Setting {
+        name: "camera.sensitivity",
+        get: |world| Some(world.get_resource::<EditorConfig>()?.camera.look_sensitivity as f32),
+        set: |world, value| {
+            if let Some(mut config) = world.get_resource_mut::<EditorConfig>() {
+                config.camera.look_sensitivity = value as f64;
+            }
+        },
+    },
+    Setting {
+        name: "lighting.exposure",
+        get: |world| Some(world.get_resource::<Lighting>()?.exposure),
+        set: |world, value| {
+            if let Some(mut lighting) = world.get_resource_mut::<Lighting>() {
+                lighting.exposure = value;
+            }
+        },
+    },
+];
+
+/// A command typed into the console
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    /// `spawn <model> [x y z]`, at the origin when no position is given
+    Spawn { model: String, position: Position },
+    /// `despawn <entity>`, by the index shown in the outliner
+    Despawn(u32),
+    /// `set <name> <value>`
+    Set { name: String, value: f32 },
+    /// `get <name>`
+    Get(String),
+    Clear,
+    Help,
+}
+
+const HELP: &str = "\
+spawn <model> [x y z]  spawn a model, at the origin when no position is given
+despawn <entity>       despawn an entity by the number it has in the outliner
+set <name> <value>     change a setting
+get <name>             show the value of a setting
+clear                  clear the console
+help                   show this list
+";
+
+fn number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("{word:?} is not a number"))
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<_> = line.split_whitespace().collect();
+        let command = match words.as_slice() {
+            ["spawn", model] => {
+                ConsoleCommand::Spawn { model: model.to_string(), position: Position::default() }
+            }
+            ["spawn", model, x, y, z] => ConsoleCommand::Spawn {
+                model: model.to_string(),
+                position: Position::new(number(x)?, number(y)?, number(z)?),
+            },
+            ["despawn", entity] => ConsoleCommand::Despawn(number(entity)?),
+            ["set", name, value] => {
+                ConsoleCommand::Set { name: name.to_string(), value: number(value)? }
+            }
+            ["get", name] => ConsoleCommand::Get(name.to_string()),
+            ["clear"] => ConsoleCommand::Clear,
+            ["help"] => ConsoleCommand::Help,
+            [] => return Err("type a command, or help to list them".to_owned()),
+            ["spawn" | "despawn" | "set" | "get" | "clear" | "help", ..] => {
+                return Err(format!("wrong arguments for {}, see help", words[0]));
+            }
+            [name, ..] => return Err(format!("unknown command {name:?}, see help")),
+        };
+        Ok(command)
+    }
+
+    /// Run the command, returning what to show in the console
+    pub fn execute(self, world: &mut World) -> Result<String, String> {
+        match self {
+            ConsoleCommand::Spawn { model, position } => {
+                let model_loader = world.resource::<ModelLoader>();
+                let Some(vao) = model_loader.get(&model) else {
+                    return Err(format!("no model is called {model:?}"));
+                };
+                let mesh = Mesh::from(vao);
+                let transform = TransformBundle { position, ..Default::default() };
+                let entity = entity_pool::spawn(world, mesh, transform);
+                Ok(format!("spawned {model} {}", entity.index()))
+            }
+            ConsoleCommand::Despawn(index) => {
+                let entity = world
+                    .entities()
+                    .resolve_from_id(index)
+                    .filter(|&entity| {
+                        world.get_entity(entity).is_some_and(|e| {
+                            e.contains::<Mesh>() && !e.contains::<Pooled>()
+                        })
+                    })
+                    .ok_or_else(|| format!("there is no entity {index}"))?;
+                entity_pool::release(entity, world);
+                Ok(format!("despawned {index}"))
+            }
+            ConsoleCommand::Set { name, value } => {
+                let setting = setting(&name)?;
+                (setting.set)(world, value);
+                Ok(format!("{name} = {value}"))
+            }
+            ConsoleCommand::Get(name) => {
+                let value = (setting(&name)?.get)(world)
+                    .ok_or_else(|| format!("{name} is not available"))?;
+                Ok(format!("{name} = {value}"))
+            }
+            ConsoleCommand::Clear => {
+                if let Some(log) = world.get_resource::<ConsoleLog>() {
+                    log.clear();
+                }
+                Ok(String::new())
+            }
+            ConsoleCommand::Help => {
+                let names: Vec<_> = SETTINGS.iter().map(|s| s.name).collect();
+                Ok(format!("{HELP}settings: {}", names.join(", ")))
+            }
+        }
+    }
+}
+
+fn setting(name: &str) -> Result<&'static Setting, String> {
+    SETTINGS.iter().find(|s| s.name == name).ok_or_else(|| format!("there is no setting {name:?}"))
+}
+
+/// Echo a line typed into the console, then run it and write what it returns
+pub fn run_line(world: &mut World, line: &str) {
+    let log = world.get_resource_or_insert_with(ConsoleLog::default).clone();
+    log.reply(Level::INFO, format!("> {line}"));
+    match ConsoleCommand::parse(line).and_then(|command| command.execute(world)) {
+        Ok(reply) => {
+            for line in reply.lines() {
+                log.reply(Level::INFO, line);
+            }
+        }
+        Err(e) => log.reply(Level::WARN, e),
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use nalgebra_glm as glm;
+
+use crate::components::{FollowPath, Oscillator, PointLight, Rotator};
+use crate::image_export;
+use crate::material::{Material, MATERIAL_EXTENSION};
+use crate::mesh_cleanup::MeshData;
+use crate::resources::CameraPose;
+use crate::scene::{Scene, ShaderSources};
+use crate::shader::{UniformValue, GEOMETRY_PASS_VERT};
+use crate::texture_import::{Filter, TextureSettings};
+
+/// File name of the showcase scene in the demo folder
+pub const SCENE_FILE: &str = "showcase.ron";
+
+/// Width and height of the generated textures
+const TEXTURE_SIZE: u32 = 256;
+
+/// Tiles across each side of the tile textures
+const TILES: u32 = 4;
+
+/// Fragment shader of the `glow` material, with bands of its tint scrolling up the surface
+const GLOW_FRAG: &str = r#"#version 410 core
+
+in vec3 frag_pos;
+in vec3 normal;
+in vec4 clip_pos;
+in vec4 previous_clip_pos;
+
+layout(location = 0) out vec4 out_position;
+layout(location = 1) out vec3 out_normal;
+layout(location = 2) out vec4 out_albedo_spec;
+layout(location = 3) out vec2 out_velocity;
+layout(location = 4) out uint out_entity_id;
+
+uniform float time;
+uniform float selected;
+uniform uint entity_id;
+uniform vec3 tint = vec3(0.2, 0.8, 1.0);
+uniform float bands = 12.0;
+
+void main() {
+    float band = 0.5 + 0.5 * sin(frag_pos.y * bands - time * 3.0);
+    out_position = vec4(frag_pos, selected);
+    out_normal = normalize(normal);
+    out_albedo_spec = vec4(mix(vec3(0.05), tint, band), band);
+    out_velocity = (clip_pos.xy / clip_pos.w - previous_clip_pos.xy / previous_clip_pos.w) * 0.5;
+    out_entity_id = entity_id;
+}
+"#;
+
+/// Where the demo content was written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DemoContent {
+    pub scene: PathBuf,
+    pub models_dir: PathBuf,
+    pub textures_dir: PathBuf,
+    pub materials_dir: PathBuf,
+}
+
+impl DemoContent {
+    /// Arguments that open the showcase scene with the demo assets
+    pub fn args(&self) -> Vec<String> {
+        vec![
+            self.scene.display().to_string(),
+            "--models".to_owned(),
+            self.models_dir.display().to_string(),
+            "--textures".to_owned(),
+            self.textures_dir.display().to_string(),
+            "--materials".to_owned(),
+            self.materials_dir.display().to_string(),
+        ]
+    }
+}
+
+/// Write sample models, textures and materials, and a scene showing them off, to a folder
+///
+/// The content is the same on every run, so it can be used to reproduce issues and compare
+/// renders. Files of the same name in the folder are replaced.
+pub fn generate(dir: &Path) -> Result<DemoContent> {
+    let content = DemoContent {
+        scene: dir.join(SCENE_FILE),
+        models_dir: dir.join("models"),
+        textures_dir: dir.join("textures"),
+        materials_dir: dir.join("materials"),
+    };
+    for dir in [&content.models_dir, &content.textures_dir, &content.materials_dir] {
+        fs::create_dir_all(dir).map_err(|e| eyre!("could not create {}: {e}", dir.display()))?;
+    }
+
+    // The built-in models are written too, so the demo opens without asset errors
+    write_model(&content.models_dir, "Cube", &MeshData::cube())?;
+    write_model(&content.models_dir, "Plane", &MeshData::plane())?;
+    write_model(&content.models_dir, "Sphere", &MeshData::sphere(32, 16))?;
+    write_model(&content.models_dir, "Torus", &MeshData::torus(48, 24, 0.3))?;
+    write_model(&content.models_dir, "Column", &MeshData::cylinder(32))?;
+
+    write_texture(&content.textures_dir, "checker", checker)?;
+    write_texture(&content.textures_dir, "tiles", tiles)?;
+    write_texture(&content.textures_dir, "tiles_specular", tiles_specular)?;
+    write_texture(&content.textures_dir, "tiles_normal", tiles_normal)?;
+    // The floor is seen at a grazing angle, where anisotropic filtering keeps it sharp
+    let floor =
+        TextureSettings { filter: Filter::Trilinear, anisotropy: 8.0, ..Default::default() };
+    floor.save_for(&content.textures_dir.join("tiles.png"))?;
+
+    let tiles = Material {
+        diffuse: Some("tiles".to_owned()),
+        specular: Some("tiles_specular".to_owned()),
+        normal: Some("tiles_normal".to_owned()),
+        ..Default::default()
+    };
+    write_material(&content.materials_dir, "tiles", &tiles)?;
+    let glow = Material {
+        custom_shader: Some(ShaderSources {
+            vert: GEOMETRY_PASS_VERT.to_owned(),
+            frag: GLOW_FRAG.to_owned(),
+            params: BTreeMap::from([("tint".to_owned(), UniformValue::Vec3([1.0, 0.45, 0.1]))]),
+        }),
+        ..Default::default()
+    };
+    write_material(&content.materials_dir, "glow", &glow)?;
+
+    showcase().save(&content.scene)?;
+    Ok(content)
+}
+
+/// A tiled floor with a spinning torus, columns, a glowing sphere and three colored lights, one of
+/// them circling the others so their shadows move
+fn showcase() -> Scene {
+    let light = |color: glm::Vec3| PointLight::new(color * 0.05, color, color, 1.0, 0.09, 0.032);
+    let circle: Vec<_> = (0..8)
+        .map(|i| {
+            let (sin, cos) = (i as f32 / 8.0 * std::f32::consts::TAU).sin_cos();
+            glm::vec3(cos * 4.0, 2.5, sin * 4.0)
+        })
+        .collect();
+
+    let mut scene = Scene::new()
+        .spawn_model("Plane")
+        .scaled(10.0, 1.0, 10.0)
+        .material("tiles")
+        .locked()
+        .spawn_model("Torus")
+        .at(0.0, 1.5, 0.0)
+        .rotated(60.0, 0.0, 0.0)
+        .diffuse("checker")
+        .rotator(Rotator::new(0.0, 30.0, 0.0))
+        .spawn_model("Column")
+        .at(-3.0, 1.0, -2.0)
+        .scaled(0.4, 1.0, 0.4)
+        .diffuse("checker")
+        .spawn_model("Column")
+        .at(3.0, 1.0, -2.0)
+        .scaled(0.4, 1.0, 0.4)
+        .diffuse("checker")
+        .spawn_model("Sphere")
+        .at(0.0, 1.0, 3.0)
+        .scaled(0.6, 0.6, 0.6)
+        .material("glow")
+        .oscillator(Oscillator::new(glm::vec3(0.0, 1.0, 0.0), 0.4, 0.25))
+        .spawn_model("Cube")
+        .at(2.0, 0.5, 2.0)
+        .scaled(0.5, 0.5, 0.5)
+        .rotated(0.0, 30.0, 0.0)
+        .spawn_model("Sphere")
+        .at(-4.0, 3.0, 2.0)
+        .scaled(0.1, 0.1, 0.1)
+        .point_light(light(glm::vec3(1.0, 0.3, 0.2)))
+        .tag("light")
+        .spawn_model("Sphere")
+        .at(4.0, 3.0, 2.0)
+        .scaled(0.1, 0.1, 0.1)
+        .point_light(light(glm::vec3(0.2, 0.4, 1.0)))
+        .tag("light")
+        .spawn_model("Sphere")
+        .at(4.0, 2.5, 0.0)
+        .scaled(0.1, 0.1, 0.1)
+        .point_light(light(glm::vec3(0.3, 1.0, 0.4)))
+        .tag("light")
+        .follow_path(FollowPath::new(circle, 2.0, true))
+        .finish();
+    scene.views = BTreeMap::from([
+        (1, CameraPose { position: glm::vec3(0.0, 3.5, 8.0), yaw: -90.0, pitch: -18.0 }),
+        (2, CameraPose { position: glm::vec3(5.0, 2.0, 5.0), yaw: -135.0, pitch: -10.0 }),
+    ]);
+    scene
+}
+
+/// Write a mesh as an OBJ file with a single object of the given name
+fn write_model(dir: &Path, name: &str, mesh: &MeshData) -> Result<()> {
+    let mut obj = format!("o {name}\n");
+    for p in &mesh.positions {
+        writeln!(obj, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    for t in &mesh.texture_coords {
+        writeln!(obj, "vt {} {}", t.x, t.y)?;
+    }
+    for n in &mesh.normals {
+        writeln!(obj, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+        writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+    let path = dir.join(format!("{}.obj", name.to_lowercase()));
+    fs::write(&path, obj).map_err(|e| eyre!("could not write {}: {e}", path.display()))
+}
+
+/// Write a PNG texture with the color of each texel given by its coordinates from 0 to 1
+fn write_texture(dir: &Path, name: &str, texel: fn(f32, f32) -> [u8; 4]) -> Result<()> {
+    let size = TEXTURE_SIZE;
+    let pixels: Vec<u8> = (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size, i / size);
+            texel((x as f32 + 0.5) / size as f32, (y as f32 + 0.5) / size as f32)
+        })
+        .collect();
+    image_export::save_png(&dir.join(format!("{name}.png")), size, size, &pixels)
+}
+
+fn write_material(dir: &Path, name: &str, material: &Material) -> Result<()> {
+    material.save(dir.join(name).with_extension(MATERIAL_EXTENSION))
+}
+
+fn checker(u: f32, v: f32) -> [u8; 4] {
+    if ((u * 8.0) as u32 + (v * 8.0) as u32) % 2 == 0 {
+        [230, 120, 40, 255]
+    } else {
+        [240, 230, 210, 255]
+    }
+}
+
+/// Distance from a point to the nearest grout line, in tiles, and which tile it is on
+fn tile_edge(u: f32, v: f32) -> (f32, u32) {
+    let (x, y) = (u * TILES as f32, v * TILES as f32);
+    let edge = [x.fract(), 1.0 - x.fract(), y.fract(), 1.0 - y.fract()]
+        .into_iter()
+        .fold(f32::MAX, f32::min);
+    (edge, x as u32 + y as u32 * TILES)
+}
+
+/// Grout lines are this far from the middle of the line to its edge, in tiles
+const GROUT: f32 = 0.03;
+
+/// Tiles round off over this distance from the grout, in tiles
+const BEVEL: f32 = 0.06;
+
+fn tiles(u: f32, v: f32) -> [u8; 4] {
+    let (edge, tile) = tile_edge(u, v);
+    if edge < GROUT {
+        return [60, 58, 55, 255];
+    }
+    // Each tile is a slightly different shade, the same on every run
+    let shade = (tile.wrapping_mul(2_654_435_761) >> 28) as u8;
+    [150 + shade * 3, 150 + shade * 3, 160 + shade * 3, 255]
+}
+
+fn tiles_specular(u: f32, v: f32) -> [u8; 4] {
+    let gloss = if tile_edge(u, v).0 < GROUT { 20 } else { 200 };
+    [gloss, gloss, gloss, 255]
+}
+
+/// Tangent-space normals of the tiles, sloping down at their beveled edges
+fn tiles_normal(u: f32, v: f32) -> [u8; 4] {
+    let height = |u: f32, v: f32| ((tile_edge(u, v).0 - GROUT) / BEVEL).clamp(0.0, 1.0);
+    let step = 1.0 / TEXTURE_SIZE as f32;
+    let dx = (height(u + step, v) - height(u - step, v)) / (2.0 * step * TILES as f32);
+    let dy = (height(u, v + step) - height(u, v - step)) / (2.0 * step * TILES as f32);
+    // Rows go down the image while the v coordinate goes up
+    let normal = glm::normalize(&glm::vec3(-dx * BEVEL, dy * BEVEL, 1.0));
+    let encode = |c: f32| ((c * 0.5 + 0.5) * 255.0).round() as u8;
+    [encode(normal.x), encode(normal.y), encode(normal.z), 255]
+}
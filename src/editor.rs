@@ -0,0 +1,323 @@
+use std::cell::Cell;
+use std::ffi::CString;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use egui_glow::EguiGlow;
+#[cfg(feature = "accesskit")]
+use egui_winit::accesskit_winit::ActionRequestEvent;
+use glow::{Context, HasContext as _};
+use glutin::config::{Config, ConfigTemplateBuilder};
+use glutin::context::{
+    ContextApi, ContextAttributesBuilder, GlProfile, PossiblyCurrentContext, Robustness, Version,
+};
+use glutin::display::GetGlDisplay;
+use glutin::prelude::*;
+use glutin_winit::{DisplayBuilder, GlWindow};
+use raw_window_handle::HasRawWindowHandle;
+use tracing::{info, warn, Level};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::FmtSubscriber;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, Event, WindowEvent};
+use winit::event_loop::{EventLoop, EventLoopBuilder};
+use winit::window::{Window, WindowBuilder};
+
+use crate::app::SceneEditorApp;
+use crate::config::{self, EditorConfig};
+use crate::console::ConsoleLog;
+use crate::frame_limiter::VsyncMode;
+use crate::issue_report::LogWriter;
+use crate::registry::ComponentRegistry;
+use crate::settings::LEGACY_SETTINGS_PATH;
+use crate::{game_logic, gl_debug};
+
+pub const WINDOW_TITLE: &str = "Scene Editor";
+
+pub enum WinitEvent {
+    WindowEvent(WindowEvent<'static>),
+    ScaleFactorChanged {
+        scale_factor: f64,
+        new_size: PhysicalSize<u32>,
+    },
+    MouseMotion((f64, f64)),
+    #[cfg(feature = "accesskit")]
+    AccessKitActionRequest(egui::accesskit::ActionRequest),
+    LoopDestroyed,
+}
+
+/// Events sent to the event loop from other threads
+#[derive(Debug)]
+pub enum UserEvent {
+    /// Sent when the quit action is pressed
+    Quit,
+    /// Sent by AccessKit when assistive technology asks to interact with the UI
+    #[cfg(feature = "accesskit")]
+    AccessKit(ActionRequestEvent),
+}
+
+#[cfg(feature = "accesskit")]
+impl From<ActionRequestEvent> for UserEvent {
+    fn from(event: ActionRequestEvent) -> Self {
+        UserEvent::AccessKit(event)
+    }
+}
+
+/// Scene, asset directories and window settings the editor starts with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupOptions {
+    /// Scene to open instead of the default one
+    pub scene: Option<PathBuf>,
+    pub models_dir: PathBuf,
+    pub textures_dir: PathBuf,
+    pub materials_dir: PathBuf,
+    /// Initial window size in physical pixels, instead of the size it had last time
+    pub window_size: Option<(u32, u32)>,
+    /// When frames wait for vertical sync, until it is changed in Preferences, instead of what
+    /// the configuration says
+    pub vsync: Option<VsyncMode>,
+    /// Reload the built-in shaders when their files in `shaders/` change, in debug builds
+    pub watch_shaders: bool,
+    /// Open the scene for viewing, with the tools that change it turned off
+    pub view_only: bool,
+}
+
+impl Default for StartupOptions {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            models_dir: PathBuf::from(game_logic::MODELS_DIR),
+            textures_dir: PathBuf::from(game_logic::TEXTURES_DIR),
+            materials_dir: PathBuf::from(game_logic::MATERIALS_DIR),
+            window_size: None,
+            vsync: None,
+            watch_shaders: false,
+            view_only: false,
+        }
+    }
+}
+
+pub fn run() -> Result<()> {
+    run_with_registry(ComponentRegistry::default())
+}
+
+/// Run the editor with custom components registered by user code
+pub fn run_with_registry(registry: ComponentRegistry) -> Result<()> {
+    run_with_options(StartupOptions::default(), registry)
+}
+
+/// Run the editor with the given startup scene, asset directories and window settings
+pub fn run_with_options(options: StartupOptions, registry: ComponentRegistry) -> Result<()> {
+    SceneEditorApp::new().set_options(options).set_registry(registry).run()
+}
+
+pub(crate) fn run_app(mut app: SceneEditorApp) -> Result<()> {
+    let console_log = ConsoleLog::default();
+    init_tracing(Some(&console_log))?;
+    app.world.insert_resource(console_log);
+
+    let config = EditorConfig::load_or_migrate(config::config_path(), LEGACY_SETTINGS_PATH);
+    let window_size = app.options.window_size.or(config.window.size);
+    let (gl, gl_context, gl_config, window, event_loop) =
+        create_glutin_window(window_size, config.window.position, config.msaa_samples);
+
+    let gl = Arc::new(gl);
+    let window = Arc::new(window);
+    // On macOS, needed to avoid program hanging after game loop thread stops
+    let _wc = window.clone();
+
+    unsafe {
+        info!("Vendor: {}", gl.get_parameter_string(glow::VENDOR));
+        info!("Renderer: {}", gl.get_parameter_string(glow::RENDERER));
+        info!("OpenGL Version: {}", gl.get_parameter_string(glow::VERSION));
+        info!("GLSL Version: {}", gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION));
+
+        if gl_debug::enable_debug_output(&gl) {
+            info!("GL debug output enabled");
+        } else {
+            info!("GL debug output is not supported, falling back to error checks");
+        }
+    }
+
+    #[cfg_attr(not(feature = "accesskit"), allow(unused_mut))]
+    let mut egui_glow = EguiGlow::new(&event_loop, gl.clone(), None);
+    let pixels_per_point = config.pixels_per_point(window.scale_factor());
+    egui_glow.egui_ctx.set_pixels_per_point(pixels_per_point);
+    info!("set egui pixels per point to {pixels_per_point}");
+
+    #[cfg(feature = "accesskit")]
+    {
+        let egui_ctx = egui_glow.egui_ctx.clone();
+        egui_glow.egui_winit.init_accesskit(&window, event_loop.create_proxy(), move || {
+            // Called when assistive technology first connects, so egui only builds the
+            // accessibility tree when someone needs it
+            egui_ctx.enable_accesskit();
+            egui_ctx.request_repaint();
+            egui_ctx.accesskit_placeholder_tree_update()
+        });
+        // The AccessKit adapter has to be created before the window is shown
+        window.set_visible(true);
+    }
+
+    let not_current_gl_context = gl_context.make_not_current()?;
+    let (event_sender, event_receiver) = mpsc::channel();
+    let event_loop_proxy = event_loop.create_proxy();
+
+    let game_loop_thread = thread::spawn(move || {
+        game_logic::run_game_loop(
+            gl,
+            window,
+            not_current_gl_context,
+            gl_config,
+            egui_glow,
+            app,
+            config,
+            event_receiver,
+            event_loop_proxy,
+        )
+    });
+    let game_loop_thread = Cell::new(Some(game_loop_thread));
+
+    event_loop.run(move |event, _, control_flow| {
+        control_flow.set_wait();
+
+        match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                control_flow.set_exit();
+            }
+            Event::WindowEvent { event: WindowEvent::Destroyed, .. } => {
+                control_flow.set_exit();
+            }
+            Event::UserEvent(UserEvent::Quit) => {
+                control_flow.set_exit();
+            }
+            #[cfg(feature = "accesskit")]
+            Event::UserEvent(UserEvent::AccessKit(ActionRequestEvent { request, .. })) => {
+                let res = event_sender.send(WinitEvent::AccessKitActionRequest(request));
+                if res.is_err() {
+                    get_thread_result(&game_loop_thread).unwrap();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size },
+                ..
+            } => {
+                let res = event_sender.send(WinitEvent::ScaleFactorChanged {
+                    scale_factor,
+                    new_size: *new_inner_size,
+                });
+                if res.is_err() {
+                    get_thread_result(&game_loop_thread).unwrap();
+                }
+            }
+            Event::WindowEvent { event, .. } => {
+                let res = event_sender.send(WinitEvent::WindowEvent(event.to_static().unwrap()));
+                if res.is_err() {
+                    get_thread_result(&game_loop_thread).unwrap();
+                }
+            }
+            Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
+                let res = event_sender.send(WinitEvent::MouseMotion(delta));
+                if res.is_err() {
+                    get_thread_result(&game_loop_thread).unwrap();
+                }
+            }
+            Event::LoopDestroyed => {
+                let _ = event_sender.send(WinitEvent::LoopDestroyed);
+                if let Some(thread) = game_loop_thread.take() {
+                    thread.join().unwrap().unwrap();
+                }
+            }
+            _ => (),
+        }
+    });
+}
+
+/// Log to the terminal, and to the console panel when given its log
+pub(crate) fn init_tracing(console_log: Option<&ConsoleLog>) -> Result<()> {
+    let subscriber = FmtSubscriber::builder()
+        .with_max_level(if cfg!(debug_assertions) { Level::DEBUG } else { Level::WARN })
+        .with_writer(LogWriter::default)
+        .finish()
+        .with(console_log.map(ConsoleLog::layer));
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|_| eyre!("setting default subscriber failed"))
+}
+
+/// Create the window and its GL context, with a default framebuffer multisampled as close to
+/// `samples` as the driver allows
+fn create_glutin_window(
+    size: Option<(u32, u32)>,
+    position: Option<(i32, i32)>,
+    samples: u32,
+) -> (Context, PossiblyCurrentContext, Config, Window, EventLoop<UserEvent>) {
+    let event_loop = EventLoopBuilder::with_user_event().build();
+    // With AccessKit the window is shown once the adapter is created
+    let mut window_builder =
+        WindowBuilder::new().with_title(WINDOW_TITLE).with_visible(!cfg!(feature = "accesskit"));
+    if let Some((width, height)) = size {
+        window_builder = window_builder.with_inner_size(PhysicalSize::new(width, height));
+    }
+    if let Some((x, y)) = position {
+        window_builder = window_builder.with_position(PhysicalPosition::new(x, y));
+    }
+    let template = ConfigTemplateBuilder::new().with_stencil_size(8);
+    let display_builder = DisplayBuilder::new().with_window_builder(Some(window_builder));
+
+    let (window, gl_config) = display_builder
+        .build(&event_loop, template, |configs| {
+            // Without MSAA the config with the fewest samples is picked
+            let samples = if samples > 1 { samples } else { 0 };
+            configs.min_by_key(|cfg| u32::from(cfg.num_samples()).abs_diff(samples)).unwrap()
+        })
+        .unwrap();
+
+    info!("Picked a config with {} samples", gl_config.num_samples());
+    info!("Picked a config with {} stencil size", gl_config.stencil_size());
+    info!("Picked a config with {} depth size", gl_config.depth_size());
+
+    let window = window.unwrap();
+    let raw_window_handle = window.raw_window_handle();
+
+    let gl_display = gl_config.display();
+
+    let context_attributes = || {
+        ContextAttributesBuilder::new()
+            .with_profile(GlProfile::Core)
+            .with_debug(cfg!(debug_assertions))
+            // Maximum supported version on macOS
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(4, 1))))
+    };
+    // A robust context lets the driver reset a GPU hung by a custom shader instead of freezing
+    let robust_attributes = context_attributes()
+        .with_robustness(Robustness::RobustLoseContextOnReset)
+        .build(Some(raw_window_handle));
+    let not_current_gl_context =
+        unsafe { gl_display.create_context(&gl_config, &robust_attributes) }.unwrap_or_else(|e| {
+            warn!("Could not create a robust OpenGL context, using a regular one: {e}");
+            let attributes = context_attributes().build(Some(raw_window_handle));
+            unsafe { gl_display.create_context(&gl_config, &attributes).unwrap() }
+        });
+
+    let attrs = window.build_surface_attributes(Default::default());
+    let gl_surface =
+        unsafe { gl_config.display().create_window_surface(&gl_config, &attrs).unwrap() };
+
+    let gl_context = not_current_gl_context.make_current(&gl_surface).unwrap();
+
+    let gl = unsafe {
+        glow::Context::from_loader_function(|s| {
+            let s = CString::new(s).expect("failed to construct C string for gl proc address");
+            gl_display.get_proc_address(&s)
+        })
+    };
+
+    (gl, gl_context, gl_config, window, event_loop)
+}
+
+fn get_thread_result(cell: &Cell<Option<thread::JoinHandle<Result<()>>>>) -> Result<()> {
+    if let Some(thread) = cell.take() { thread.join().unwrap() } else { Ok(()) }
+}
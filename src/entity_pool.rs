@@ -0,0 +1,116 @@
+use bevy_ecs::component::ComponentId;
+use bevy_ecs::prelude::*;
+
+use crate::commands;
+use crate::components::{
+    GlobalTransform, Mesh, Position, PreviousTransform, Rotation, Scale, TransformBundle,
+};
+use crate::renderer::model_matrix;
+use crate::transform;
+
+/// Marks an entity that is kept in the `EntityPool` to be reused, and is not part of the scene
+///
+/// Stored in a sparse set, so marking and unmarking an entity does not move its other components.
+#[derive(Component, Debug, Default, Copy, Clone)]
+#[component(storage = "SparseSet")]
+pub struct Pooled;
+
+/// Mesh entities that were released, kept to be spawned again without allocating
+///
+/// Pooled entities keep the components a drawn mesh has, so they stay in the same table and
+/// reusing one only overwrites its components. This avoids the frame spikes of scenes that spawn
+/// and despawn many objects each frame, such as particles or stress tests, at the cost of more
+/// work per object on average.
+#[derive(Resource, Debug, Default)]
+pub struct EntityPool {
+    free: Vec<Entity>,
+    /// Components a pooled entity has, looked up the first time they are needed
+    components: Option<[ComponentId; 6]>,
+}
+
+impl EntityPool {
+    /// Number of entities waiting to be reused
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    /// Forget the pooled entities, for when they were despawned along with the scene
+    pub fn clear(&mut self) {
+        self.free.clear();
+    }
+}
+
+/// Spawn pooled entities ahead of time, so their storage is allocated before it is needed
+pub fn reserve(world: &mut World, mesh: Mesh, count: usize) {
+    let model = model_matrix(Default::default(), Default::default(), Default::default());
+    let bundles = (0..count).map(|_| {
+        (mesh, TransformBundle::default(), PreviousTransform(model), Pooled)
+    });
+    let spawned: Vec<_> = world.spawn_batch(bundles).collect();
+    world.get_resource_or_insert_with(EntityPool::default).free.extend(spawned);
+}
+
+/// Spawn a mesh entity, reusing a pooled entity when there is one
+pub fn spawn(world: &mut World, mesh: Mesh, transform: TransformBundle) -> Entity {
+    let model = model_matrix(transform.position, transform.rotation, transform.scale);
+    let transform = TransformBundle { global_transform: GlobalTransform(model), ..transform };
+    let bundle = (mesh, transform, PreviousTransform(model));
+
+    while let Some(entity) = world.get_resource_mut::<EntityPool>().and_then(|mut p| p.free.pop()) {
+        // Entities can be despawned while pooled, such as when a scene is loaded
+        if let Some(mut pooled) = world.get_entity_mut(entity).filter(|e| e.contains::<Pooled>()) {
+            pooled.insert(bundle).remove::<Pooled>();
+            return entity;
+        }
+    }
+    world.spawn(bundle).id()
+}
+
+/// Give an entity back to the pool, or despawn it if it has components a pooled entity can not
+/// keep
+pub fn release(entity: Entity, world: &mut World) {
+    let Some(entity_ref) = world.get_entity(entity) else {
+        return;
+    };
+    if entity_ref.contains::<Pooled>() {
+        return;
+    }
+    if !is_poolable(world, entity) {
+        commands::despawn_and_destroy(entity, world);
+        return;
+    }
+
+    // Pooled entities are not drawn, so they can not be picked either
+    transform::unparent_children(world, entity);
+    world.entity_mut(entity).insert(Pooled);
+    world.get_resource_or_insert_with(EntityPool::default).free.push(entity);
+}
+
+/// Whether an entity is a mesh with no components other than the ones pooled entities have
+fn is_poolable(world: &mut World, entity: Entity) -> bool {
+    let pooled = pooled_components(world);
+    let Some(entity) = world.get_entity(entity) else {
+        return false;
+    };
+    entity.contains::<Mesh>() && entity.archetype().components().all(|c| pooled.contains(&c))
+}
+
+fn pooled_components(world: &mut World) -> [ComponentId; 6] {
+    if let Some(components) = world.get_resource::<EntityPool>().and_then(|p| p.components) {
+        return components;
+    }
+    let components = [
+        world.init_component::<Mesh>(),
+        world.init_component::<Position>(),
+        world.init_component::<Rotation>(),
+        world.init_component::<Scale>(),
+        world.init_component::<GlobalTransform>(),
+        world.init_component::<PreviousTransform>(),
+    ];
+    world.get_resource_or_insert_with(EntityPool::default).components = Some(components);
+    components
+}
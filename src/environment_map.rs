@@ -0,0 +1,109 @@
+//! HDR images of an environment, shown around the scene and lighting it in look-dev mode
+//!
+//! Environment maps are equirectangular, with the full circle around the horizon across the width
+//! and straight up to straight down across the height, as HDRIs are usually shared. They are read
+//! from OpenEXR files.
+
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext, Texture};
+use nalgebra_glm as glm;
+
+use crate::gl_debug::gl_check;
+
+/// An equirectangular HDR image, with linear colors that can be brighter than 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    /// RGB of each pixel, starting with the top row
+    pub pixels: Vec<f32>,
+}
+
+impl EnvironmentMap {
+    /// Read the first RGB layer of an OpenEXR file, where alpha is ignored
+    pub fn load(path: &Path) -> Result<Self> {
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            path,
+            |resolution, _| EnvironmentMap {
+                width: resolution.width(),
+                height: resolution.height(),
+                pixels: vec![0.0; resolution.width() * resolution.height() * 3],
+            },
+            |map: &mut EnvironmentMap, position, (r, g, b, _): (f32, f32, f32, f32)| {
+                let i = (position.y() * map.width + position.x()) * 3;
+                map.pixels[i..i + 3].copy_from_slice(&[r, g, b]);
+            },
+        )
+        .map_err(|e| eyre!("could not read {}: {e}", path.display()))?;
+
+        let map = image.layer_data.channel_data.pixels;
+        if map.width == 0 || map.height == 0 {
+            return Err(eyre!("{} has no pixels", path.display()));
+        }
+        Ok(map)
+    }
+
+    /// Color of a pixel, counted from the top left corner
+    pub fn pixel(&self, x: usize, y: usize) -> glm::Vec3 {
+        let i = (y * self.width + x) * 3;
+        glm::make_vec3(&self.pixels[i..i + 3])
+    }
+
+    /// Color seen in a direction, from the nearest pixel
+    pub fn sample(&self, dir: &glm::Vec3) -> glm::Vec3 {
+        let uv = equirect_uv(dir);
+        let x = ((uv.x * self.width as f32) as usize).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as usize).min(self.height - 1);
+        self.pixel(x, y)
+    }
+
+    /// Upload as a mipmapped half float texture, whose smallest levels are the blurred light
+    /// the lighting pass takes from the environment
+    ///
+    /// # Safety
+    ///
+    /// Needs a current GL context; the texture is left bound to `TEXTURE_2D`.
+    pub unsafe fn upload(&self, gl: &Context) -> Result<Texture> {
+        let texture = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl_check!(
+            gl,
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGB16F as i32,
+                self.width as i32,
+                self.height as i32,
+                0,
+                glow::RGB,
+                glow::FLOAT,
+                Some(bytemuck::cast_slice(&self.pixels)),
+            )
+        );
+        gl.generate_mipmap(glow::TEXTURE_2D);
+        // Around the horizon the image wraps, while the poles are at its top and bottom edges
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_MIN_FILTER,
+            glow::LINEAR_MIPMAP_LINEAR as i32,
+        );
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+        Ok(texture)
+    }
+}
+
+/// Where a direction is seen in an equirectangular image, from 0 to 1 across it with the top row
+/// at 0, as `environment_uv` in the lighting pass looks it up
+///
+/// +Y is straight up and -Z, where the editor camera looks at first, is the middle of the image.
+pub fn equirect_uv(dir: &glm::Vec3) -> glm::Vec2 {
+    let dir = dir.normalize();
+    let u = dir.x.atan2(-dir.z) / (2.0 * std::f32::consts::PI) + 0.5;
+    let v = dir.y.clamp(-1.0, 1.0).acos() / std::f32::consts::PI;
+    glm::vec2(u, v)
+}
@@ -0,0 +1,583 @@
+use ahash::AHashMap;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, Framebuffer, HasContext, Renderbuffer, Texture};
+
+use crate::render_targets::RenderTargetPool;
+
+/// Size of a render target
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TargetSize {
+    /// Follows the size of the viewport
+    Viewport,
+    Fixed(i32, i32),
+}
+
+/// Description of a texture written by a pass
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextureDesc {
+    pub internal_format: u32,
+    pub format: u32,
+    pub ty: u32,
+    pub size: TargetSize,
+    pub filter: u32,
+    /// Sample with depth comparison and a white border, as for shadow maps
+    pub compare: bool,
+    /// Samples per pixel, where more than 1 makes a multisampled texture
+    pub samples: i32,
+}
+
+impl TextureDesc {
+    /// Viewport-sized color texture
+    pub fn color(internal_format: u32, format: u32, ty: u32) -> Self {
+        Self {
+            internal_format,
+            format,
+            ty,
+            size: TargetSize::Viewport,
+            filter: glow::NEAREST,
+            compare: false,
+            samples: 1,
+        }
+    }
+
+    /// Depth texture that is sampled with depth comparison
+    pub fn shadow_map(width: i32, height: i32) -> Self {
+        Self {
+            internal_format: glow::DEPTH_COMPONENT24,
+            format: glow::DEPTH_COMPONENT,
+            ty: glow::FLOAT,
+            size: TargetSize::Fixed(width, height),
+            // Compared samples are blended between texels, for smoother shadow edges
+            filter: glow::LINEAR,
+            compare: true,
+            samples: 1,
+        }
+    }
+
+    pub fn with_size(mut self, size: TargetSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: u32) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: i32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Texture target the texture is bound to
+    pub fn target(&self) -> u32 {
+        if self.samples > 1 { glow::TEXTURE_2D_MULTISAMPLE } else { glow::TEXTURE_2D }
+    }
+
+    fn is_depth(&self) -> bool {
+        self.format == glow::DEPTH_COMPONENT || self.format == glow::DEPTH_STENCIL
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthOutput {
+    /// Write depth to a texture declared in the graph
+    Texture(&'static str),
+    /// Depth and stencil renderbuffer owned by the pass, sized and sampled like its color outputs
+    ///
+    /// For multisampled passes it is resolved to a renderbuffer with one sample after the pass,
+    /// so it can be read back.
+    Renderbuffer,
+}
+
+/// A pass and the textures it reads and writes
+///
+/// A pass without outputs renders to the default framebuffer.
+#[derive(Debug, Clone)]
+pub struct PassDesc {
+    pub name: &'static str,
+    pub reads: Vec<&'static str>,
+    pub writes: Vec<&'static str>,
+    pub depth: Option<DepthOutput>,
+    /// Color outputs that are resolved along with the depth and stencil of a multisampled pass
+    pub resolves: Vec<&'static str>,
+}
+
+impl PassDesc {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, reads: Vec::new(), writes: Vec::new(), depth: None, resolves: Vec::new() }
+    }
+
+    pub fn reads(mut self, textures: &[&'static str]) -> Self {
+        self.reads.extend_from_slice(textures);
+        self
+    }
+
+    /// Color textures written by the pass, in attachment order
+    pub fn writes(mut self, textures: &[&'static str]) -> Self {
+        self.writes.extend_from_slice(textures);
+        self
+    }
+
+    pub fn depth(mut self, depth: DepthOutput) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Color outputs copied to textures with one sample after the pass when it is multisampled,
+    /// so they can be read back
+    ///
+    /// A sample is picked for each pixel rather than averaging them, which suits integer textures
+    /// such as IDs.
+    pub fn resolves(mut self, textures: &[&'static str]) -> Self {
+        self.resolves.extend_from_slice(textures);
+        self
+    }
+
+    fn outputs(&self) -> impl Iterator<Item = &'static str> + '_ {
+        let depth = match self.depth {
+            Some(DepthOutput::Texture(name)) => Some(name),
+            _ => None,
+        };
+        self.writes.iter().copied().chain(depth)
+    }
+}
+
+/// Where an inserted pass runs, relative to a pass of the graph
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PassPosition {
+    Before(&'static str),
+    After(&'static str),
+}
+
+#[derive(Default)]
+pub struct FrameGraphBuilder {
+    textures: Vec<(&'static str, TextureDesc)>,
+    passes: Vec<PassDesc>,
+    /// Passes that have to run before other passes, besides writing what those read
+    runs_before: Vec<(&'static str, &'static str)>,
+}
+
+impl FrameGraphBuilder {
+    pub fn texture(mut self, name: &'static str, desc: TextureDesc) -> Self {
+        self.textures.push((name, desc));
+        self
+    }
+
+    pub fn pass(mut self, pass: PassDesc) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Add a pass that runs right before or after a pass added earlier, unless the textures they
+    /// read put other passes in between
+    pub fn insert(mut self, pass: PassDesc, position: PassPosition) -> Self {
+        let (other, index, order) = match position {
+            PassPosition::Before(other) => (other, 0, (pass.name, other)),
+            PassPosition::After(other) => (other, 1, (other, pass.name)),
+        };
+        // Passes that are ready at the same time run in the order they were added
+        match self.passes.iter().position(|p| p.name == other) {
+            Some(i) => self.passes.insert(i + index, pass),
+            None => self.passes.push(pass),
+        }
+        self.runs_before.push(order);
+        self
+    }
+
+    /// Names of the passes in the order the built graph runs them
+    pub fn pass_order(&self) -> Result<Vec<&'static str>> {
+        Ok(self.order()?.into_iter().map(|i| self.passes[i].name).collect())
+    }
+
+    /// Order the passes and allocate their textures and framebuffers from a pool
+    ///
+    /// A texture goes back to the pool after the last pass that reads it, so later passes can
+    /// reuse it. Textures that no pass reads stay reserved for code outside the graph.
+    pub fn build(self, gl: &Context, viewport: (u32, u32)) -> Result<FrameGraph> {
+        let order = self.order()?;
+        let passes: Vec<_> = order.into_iter().map(|i| self.passes[i].clone()).collect();
+        let descs: AHashMap<_, _> = self.textures.into_iter().collect();
+
+        let mut last_read = AHashMap::new();
+        for (i, pass) in passes.iter().enumerate() {
+            for &name in &pass.reads {
+                last_read.insert(name, i);
+            }
+        }
+
+        let mut pool = RenderTargetPool::new(viewport);
+        let mut textures = AHashMap::new();
+        let mut compiled = Vec::with_capacity(passes.len());
+        for (i, pass) in passes.into_iter().enumerate() {
+            let size = pass.outputs().next().map_or(TargetSize::Viewport, |name| descs[name].size);
+            if let Some(name) = pass.outputs().find(|name| descs[name].size != size) {
+                return Err(eyre!("pass {:?} writes {:?} with a different size", pass.name, name));
+            }
+            let samples = pass.outputs().next().map_or(1, |name| descs[name].samples);
+            if let Some(name) = pass.outputs().find(|name| descs[name].samples != samples) {
+                return Err(eyre!("pass {:?} writes {:?} with different samples", pass.name, name));
+            }
+            if let Some(name) = pass.resolves.iter().find(|name| !pass.writes.contains(name)) {
+                return Err(eyre!(
+                    "pass {:?} resolves {:?}, which it does not write",
+                    pass.name,
+                    name
+                ));
+            }
+
+            for name in pass.outputs() {
+                let texture = pool.acquire_texture(gl, &descs[name])?;
+                textures.insert(name, (descs[name], texture));
+            }
+
+            let renderbuffer = match pass.depth {
+                Some(DepthOutput::Renderbuffer) => {
+                    Some(pool.acquire_renderbuffer(gl, size, samples)?)
+                }
+                _ => None,
+            };
+            let framebuffer = if pass.outputs().next().is_some() || renderbuffer.is_some() {
+                Some(unsafe { create_framebuffer(gl, &pass, &textures, renderbuffer)? })
+            } else {
+                None
+            };
+            let resolve = if samples > 1 && (renderbuffer.is_some() || !pass.resolves.is_empty()) {
+                let depth = match renderbuffer {
+                    Some(_) => Some(pool.acquire_renderbuffer(gl, size, 1)?),
+                    None => None,
+                };
+                let mut colors = Vec::with_capacity(pass.resolves.len());
+                for name in &pass.resolves {
+                    let texture = pool.acquire_texture(gl, &descs[name].with_samples(1))?;
+                    colors.push((attachment_index(&pass, name), texture));
+                }
+                Some(unsafe { create_resolve_framebuffer(gl, &pass, &colors, depth)? })
+            } else {
+                None
+            };
+
+            for name in &pass.reads {
+                if last_read[name] == i {
+                    pool.release_texture(textures[name].1);
+                }
+            }
+
+            compiled.push(CompiledPass {
+                desc: pass,
+                size,
+                samples,
+                framebuffer,
+                resolve,
+                enabled: true,
+            });
+        }
+
+        Ok(FrameGraph { pool, textures, passes: compiled })
+    }
+
+    /// Sort the passes so every texture is written before it is read
+    fn order(&self) -> Result<Vec<usize>> {
+        let mut writers = AHashMap::new();
+        for (i, pass) in self.passes.iter().enumerate() {
+            for name in pass.outputs() {
+                let Some((_, desc)) = self.textures.iter().find(|(n, _)| *n == name) else {
+                    return Err(eyre!("pass {:?} writes unknown texture {:?}", pass.name, name));
+                };
+                if matches!(pass.depth, Some(DepthOutput::Texture(n)) if n == name)
+                    != desc.is_depth()
+                {
+                    return Err(eyre!("texture {:?} is used as the wrong attachment type", name));
+                }
+                if writers.insert(name, i).is_some() {
+                    return Err(eyre!("texture {:?} is written by more than one pass", name));
+                }
+            }
+        }
+
+        let mut dependencies = Vec::with_capacity(self.passes.len());
+        for pass in &self.passes {
+            let mut deps = Vec::new();
+            for name in &pass.reads {
+                let Some(&writer) = writers.get(name) else {
+                    return Err(eyre!(
+                        "pass {:?} reads {:?}, which no pass writes",
+                        pass.name,
+                        name
+                    ));
+                };
+                deps.push(writer);
+            }
+            dependencies.push(deps);
+        }
+        for &(earlier, later) in &self.runs_before {
+            let find = |name| {
+                self.passes.iter().position(|p| p.name == name).ok_or_else(|| {
+                    eyre!("a pass is inserted next to {:?}, which is not in the graph", name)
+                })
+            };
+            let (earlier, later) = (find(earlier)?, find(later)?);
+            dependencies[later].push(earlier);
+        }
+
+        // Repeatedly take the first pass whose dependencies are done, keeping declaration order
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let next = (0..self.passes.len())
+                .find(|&i| !done[i] && dependencies[i].iter().all(|&d| done[d]))
+                .ok_or_else(|| eyre!("frame graph has a cycle"))?;
+            done[next] = true;
+            order.push(next);
+        }
+
+        Ok(order)
+    }
+}
+
+struct CompiledPass {
+    desc: PassDesc,
+    size: TargetSize,
+    samples: i32,
+    framebuffer: Option<Framebuffer>,
+    /// Framebuffer the multisampled depth and stencil, and the resolved color outputs, are
+    /// resolved to
+    resolve: Option<Framebuffer>,
+    enabled: bool,
+}
+
+/// Render passes in dependency order, along with the render targets they share
+pub struct FrameGraph {
+    pool: RenderTargetPool,
+    textures: AHashMap<&'static str, (TextureDesc, Texture)>,
+    passes: Vec<CompiledPass>,
+}
+
+impl FrameGraph {
+    pub fn builder() -> FrameGraphBuilder {
+        FrameGraphBuilder::default()
+    }
+
+    /// Names of the enabled passes in the order they should run
+    pub fn passes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.passes.iter().filter(|p| p.enabled).map(|p| p.desc.name)
+    }
+
+    /// Skip a pass when rendering, or run it again
+    ///
+    /// The render targets of a disabled pass are kept, so passes that read them see what it
+    /// rendered last.
+    pub fn set_enabled(&mut self, pass: &str, enabled: bool) {
+        if let Some(p) = self.passes.iter_mut().find(|p| p.desc.name == pass) {
+            p.enabled = enabled;
+        }
+    }
+
+    pub fn has_pass(&self, pass: &str) -> bool {
+        self.pass(pass).is_some()
+    }
+
+    pub fn is_enabled(&self, pass: &str) -> bool {
+        self.pass(pass).is_some_and(|p| p.enabled)
+    }
+
+    pub fn texture(&self, name: &str) -> Option<Texture> {
+        self.textures.get(name).map(|(_, texture)| *texture)
+    }
+
+    /// Framebuffer to read what a pass rendered from, or `None` if it renders to the default
+    /// framebuffer
+    ///
+    /// For a multisampled pass with a depth renderbuffer or resolved color outputs, this is the
+    /// framebuffer they are resolved to by [`end_pass`](Self::end_pass).
+    pub fn framebuffer(&self, pass: &str) -> Option<Framebuffer> {
+        self.pass(pass).and_then(|p| p.resolve.or(p.framebuffer))
+    }
+
+    /// Color attachment a texture written by a pass is read from in the
+    /// [`framebuffer`](Self::framebuffer) of the pass, or `None` if it can not be read back there
+    ///
+    /// This is also the attachment the pass draws the texture to.
+    pub fn color_attachment(&self, pass: &str, texture: &str) -> Option<u32> {
+        let p = self.pass(pass)?;
+        let readable = p.resolve.is_none() || p.desc.resolves.iter().any(|&n| n == texture);
+        let index = p.desc.writes.iter().position(|&n| n == texture)?;
+        readable.then_some(glow::COLOR_ATTACHMENT0 + index as u32)
+    }
+
+    /// Size of the render targets of a pass
+    pub fn size(&self, pass: &str) -> (i32, i32) {
+        self.pool.resolve(self.pass(pass).map_or(TargetSize::Viewport, |p| p.size))
+    }
+
+    fn pass(&self, name: &str) -> Option<&CompiledPass> {
+        self.passes.iter().find(|p| p.desc.name == name)
+    }
+
+    /// Bind the framebuffer of a pass and set the viewport to its size
+    ///
+    /// # Safety
+    ///
+    /// The graph must have been built with the given context.
+    pub unsafe fn begin_pass(&self, gl: &Context, pass: &str) {
+        let (width, height) = self.size(pass);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, self.pass(pass).and_then(|p| p.framebuffer));
+        gl.viewport(0, 0, width, height);
+    }
+
+    /// Resolve the multisampled depth and stencil of a pass, and the color outputs it resolves,
+    /// so they can be read back
+    ///
+    /// The scissor test has to be disabled, since it applies to the resolve as well.
+    ///
+    /// # Safety
+    ///
+    /// The graph must have been built with the given context.
+    pub unsafe fn end_pass(&self, gl: &Context, pass: &str) {
+        let Some(p) = self.pass(pass).filter(|p| p.resolve.is_some()) else {
+            return;
+        };
+        let (width, height) = self.size(pass);
+        let blit = |mask| {
+            gl.blit_framebuffer(0, 0, width, height, 0, 0, width, height, mask, glow::NEAREST)
+        };
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, p.framebuffer);
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, p.resolve);
+        if p.desc.depth == Some(DepthOutput::Renderbuffer) {
+            blit(glow::DEPTH_BUFFER_BIT | glow::STENCIL_BUFFER_BIT);
+        }
+        // Colors are copied one attachment at a time, to the same attachment
+        for name in &p.desc.resolves {
+            let attachment = glow::COLOR_ATTACHMENT0 + attachment_index(&p.desc, name);
+            gl.read_buffer(attachment);
+            gl.draw_buffer(attachment);
+            blit(glow::COLOR_BUFFER_BIT);
+        }
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    }
+
+    /// Samples per pixel of the render targets of a pass
+    pub fn samples(&self, pass: &str) -> i32 {
+        self.pass(pass).map_or(1, |p| p.samples)
+    }
+
+    /// Pool the graph allocates from, for render targets used outside of the graph
+    pub fn pool_mut(&mut self) -> &mut RenderTargetPool {
+        &mut self.pool
+    }
+
+    /// Reallocate every render target that follows the viewport size
+    pub fn resize(&mut self, gl: &Context, width: u32, height: u32) {
+        self.pool.resize(gl, width, height);
+    }
+
+    /// # Safety
+    ///
+    /// The textures and framebuffers are no longer valid and should not be used.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        for pass in self.passes.drain(..) {
+            for fbo in pass.framebuffer.into_iter().chain(pass.resolve) {
+                gl.delete_framebuffer(fbo);
+            }
+        }
+        self.textures.clear();
+        self.pool.destroy(gl);
+    }
+}
+
+unsafe fn create_framebuffer(
+    gl: &Context,
+    pass: &PassDesc,
+    textures: &AHashMap<&'static str, (TextureDesc, Texture)>,
+    renderbuffer: Option<Renderbuffer>,
+) -> Result<Framebuffer> {
+    let fbo = gl.create_framebuffer().map_err(|e| eyre!("could not create framebuffer: {e}"))?;
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+
+    let mut draw_buffers = Vec::with_capacity(pass.writes.len());
+    for (i, name) in pass.writes.iter().enumerate() {
+        let attachment = glow::COLOR_ATTACHMENT0 + i as u32;
+        let (desc, texture) = textures[name];
+        gl.framebuffer_texture_2d(glow::FRAMEBUFFER, attachment, desc.target(), Some(texture), 0);
+        draw_buffers.push(attachment);
+    }
+    if draw_buffers.is_empty() {
+        gl.draw_buffer(glow::NONE);
+        gl.read_buffer(glow::NONE);
+    } else {
+        gl.draw_buffers(&draw_buffers);
+    }
+
+    match pass.depth {
+        Some(DepthOutput::Texture(name)) => {
+            let (desc, texture) = textures[name];
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                desc.target(),
+                Some(texture),
+                0,
+            );
+        }
+        Some(DepthOutput::Renderbuffer) => {
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_STENCIL_ATTACHMENT,
+                glow::RENDERBUFFER,
+                renderbuffer,
+            );
+        }
+        None => {}
+    }
+
+    let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    if status != glow::FRAMEBUFFER_COMPLETE {
+        return Err(eyre!("framebuffer for pass {:?} was not completed", pass.name));
+    }
+
+    Ok(fbo)
+}
+
+/// Index of the color attachment a pass writes a texture to
+fn attachment_index(pass: &PassDesc, texture: &str) -> u32 {
+    pass.writes.iter().position(|&name| name == texture).unwrap_or_default() as u32
+}
+
+/// Framebuffer with single-sample render targets to resolve a multisampled pass into
+///
+/// The color textures are attached at the same attachments as in the pass, next to the depth and
+/// stencil renderbuffer if there is one.
+unsafe fn create_resolve_framebuffer(
+    gl: &Context,
+    pass: &PassDesc,
+    colors: &[(u32, Texture)],
+    renderbuffer: Option<Renderbuffer>,
+) -> Result<Framebuffer> {
+    let fbo = gl.create_framebuffer().map_err(|e| eyre!("could not create framebuffer: {e}"))?;
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+    for &(index, texture) in colors {
+        let attachment = glow::COLOR_ATTACHMENT0 + index;
+        let target = glow::TEXTURE_2D;
+        gl.framebuffer_texture_2d(glow::FRAMEBUFFER, attachment, target, Some(texture), 0);
+    }
+    gl.draw_buffer(glow::NONE);
+    gl.read_buffer(glow::NONE);
+    if renderbuffer.is_some() {
+        gl.framebuffer_renderbuffer(
+            glow::FRAMEBUFFER,
+            glow::DEPTH_STENCIL_ATTACHMENT,
+            glow::RENDERBUFFER,
+            renderbuffer,
+        );
+    }
+
+    let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+    gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    if status != glow::FRAMEBUFFER_COMPLETE {
+        return Err(eyre!("resolve framebuffer for pass {:?} was not completed", pass.name));
+    }
+
+    Ok(fbo)
+}
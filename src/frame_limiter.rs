@@ -0,0 +1,124 @@
+//! Vertical sync and frame rate limits of the editor window
+
+use std::ops::RangeInclusive;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Frame rates the editor can be limited to
+pub const FPS_LIMIT_RANGE: RangeInclusive<u32> = 10..=480;
+/// Frame rate while the window is in the background, when the editor idles then
+pub const UNFOCUSED_FPS: u32 = 10;
+
+/// When frames are shown on the display
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum VsyncMode {
+    /// Wait for vertical sync, so frames never tear
+    #[default]
+    On,
+    /// Show frames as soon as they are drawn
+    Off,
+    /// Wait for vertical sync, except after frames that took longer than a refresh of the display,
+    /// which are shown right away instead of waiting for the next one
+    Adaptive,
+}
+
+impl VsyncMode {
+    pub const ALL: [VsyncMode; 3] = [VsyncMode::On, VsyncMode::Off, VsyncMode::Adaptive];
+
+    /// Name used in the configuration file and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            VsyncMode::On => "on",
+            VsyncMode::Off => "off",
+            VsyncMode::Adaptive => "adaptive",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VsyncMode::On => "On",
+            VsyncMode::Off => "Off",
+            VsyncMode::Adaptive => "Adaptive",
+        }
+    }
+
+    /// Whether to wait for vertical sync before showing a frame that took `frame_time` to draw, on
+    /// a display that refreshes every `refresh_interval`
+    ///
+    /// Adaptive vsync waits when the refresh rate of the display is not known.
+    pub fn wait_for_sync(self, frame_time: Duration, refresh_interval: Option<Duration>) -> bool {
+        match self {
+            VsyncMode::On => true,
+            VsyncMode::Off => false,
+            VsyncMode::Adaptive => refresh_interval.map_or(true, |interval| frame_time <= interval),
+        }
+    }
+}
+
+/// Shortest time from the start of one frame to the start of the next, or `None` if frames are
+/// not limited
+///
+/// With `idle` set, frames are limited to [`UNFOCUSED_FPS`], or the limit if it is lower.
+pub fn frame_interval(fps_limit: Option<u32>, idle: bool) -> Option<Duration> {
+    let fps = if idle {
+        fps_limit.map_or(UNFOCUSED_FPS, |fps| fps.min(UNFOCUSED_FPS))
+    } else {
+        fps_limit?
+    };
+    Some(Duration::from_secs(1) / fps.max(1))
+}
+
+/// Keeps the game loop to the frame rate limit, and decides when frames wait for vertical sync
+///
+/// The game loop calls [`wait`](Self::wait) at the start of each frame, and
+/// [`update_vsync`](Self::update_vsync) right before swapping buffers.
+#[derive(Debug)]
+pub struct FrameLimiter {
+    frame_start: Instant,
+    /// Mode given on the command line, used until vsync is changed in the configuration
+    vsync_override: Option<VsyncMode>,
+    /// Mode in the configuration the last frame
+    configured: Option<VsyncMode>,
+    /// Whether the last frame waited for vertical sync, or `None` before the first frame
+    synced: Option<bool>,
+}
+
+impl FrameLimiter {
+    pub fn new(vsync_override: Option<VsyncMode>) -> Self {
+        Self { frame_start: Instant::now(), vsync_override, configured: None, synced: None }
+    }
+
+    /// Sleep until the next frame is due, and mark the start of it
+    pub fn wait(&mut self, fps_limit: Option<u32>, idle: bool) {
+        if let Some(interval) = frame_interval(fps_limit, idle) {
+            let next_frame = self.frame_start + interval;
+            let now = Instant::now();
+            if next_frame > now {
+                thread::sleep(next_frame - now);
+            }
+        }
+        self.frame_start = Instant::now();
+    }
+
+    /// The vsync mode in use, which is the one from the command line until the configured mode
+    /// changes
+    pub fn vsync(&mut self, configured: VsyncMode) -> VsyncMode {
+        if self.configured.is_some_and(|previous| previous != configured) {
+            self.vsync_override = None;
+        }
+        self.configured = Some(configured);
+        self.vsync_override.unwrap_or(configured)
+    }
+
+    /// Whether the frame being drawn should wait for vertical sync, if that changed since the last
+    /// frame
+    pub fn update_vsync(
+        &mut self,
+        configured: VsyncMode,
+        refresh_interval: Option<Duration>,
+    ) -> Option<bool> {
+        let mode = self.vsync(configured);
+        let synced = mode.wait_for_sync(self.frame_start.elapsed(), refresh_interval);
+        (self.synced.replace(synced) != Some(synced)).then_some(synced)
+    }
+}
@@ -1,9 +1,11 @@
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use bevy_ecs::prelude::*;
-use bevy_ecs::schedule::ExecutorKind;
 use color_eyre::Result;
 use egui_glow::EguiGlow;
 use glow::{Context, HasContext};
@@ -14,29 +16,81 @@ use glutin::prelude::*;
 use glutin::surface::SwapInterval;
 use glutin_winit::GlWindow;
 use nalgebra_glm as glm;
-use tracing::info;
+use tracing::{error, info, warn};
 use winit::dpi::PhysicalSize;
-use winit::event::{ElementState, KeyboardInput, MouseButton, WindowEvent};
-use winit::window::{CursorGrabMode, Window};
+use winit::event::{KeyboardInput, WindowEvent};
+use winit::event_loop::EventLoopProxy;
+use winit::window::Window;
 
+use crate::app::{EditorSet, SceneEditorApp, SimulationSchedule};
+use crate::asset_cache::DerivedCache;
 use crate::components::{CustomShader, Mesh, PointLight, Position, Scale, TransformBundle};
+use crate::config::{self, EditorConfig};
+use crate::console::ConsoleLog;
+use crate::editor::{UserEvent, WINDOW_TITLE};
+use crate::frame_limiter::{FrameLimiter, VsyncMode};
+use crate::gl_debug::{reset_name, GlErrorChecks, ResetStatus};
+use crate::gl_state::GlState;
+use crate::input_map::{InputMap, LEGACY_INPUT_CONFIG_PATH};
+use crate::material::MaterialLoader;
+use crate::picking::Picker;
+use crate::profiler::Profiler;
 use crate::resources::{
-    Camera, EguiGlowRes, Input, ModelLoader, RenderState, TextureLoader, Time, UiState, WinitWindow,
+    BoxSelection, Camera, CameraFlight, DebugDraw, EguiGlowRes, FixedTime, Input,
+    InspectedComponents, KeyEvent, Lighting, LookDev, ModelLoader, MouseEvent, Notifications,
+    Outliner, Placement, RenderSettings, RenderState, SceneView, ShaderGlobals, TerrainBrush,
+    TextureLoader, Time, UiState, ViewBookmarks, ViewLayout, Viewport, WinitWindow, SCENE_COLOR,
 };
-use crate::{renderer, systems, ui, WinitEvent};
+use crate::scene::Scene;
+use crate::scripting::{self, ScriptLog, ScriptRuntime};
+use crate::shader_cache::{BinaryStore, ProgramBinaryFns, ShaderCache, BINARY_DIR};
+use crate::shader_reload::{ShaderWatcher, SHADERS_DIR};
+use crate::terrain::{self, TerrainMeshes};
+use crate::updates::{UpdateChecker, CURRENT_VERSION};
+use crate::usage::{UsageLog, USAGE_LOG_PATH};
+use crate::variables::SceneVariables;
+use crate::variants::{self, SceneVariants};
+use crate::watchdog::{FrameWatchdog, StalledFrame};
+use crate::{commands, lod, renderer, systems, transform, ui, WinitEvent};
 
+/// Directories the models and textures are loaded from by default
+pub const MODELS_DIR: &str = "res/models";
+pub const TEXTURES_DIR: &str = "res/textures";
+pub const MATERIALS_DIR: &str = "res/materials";
+
+/// File the scene is saved to when the GPU is reset and the editor has to quit
+pub const RECOVERED_SCENE_PATH: &str = "recovered_scene.ron";
+
+/// How often events are checked for while the window is minimized or hidden
+const HIDDEN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[allow(clippy::too_many_arguments)]
 pub fn run_game_loop(
     gl: Arc<Context>,
     window: Arc<Window>,
     not_current_gl_context: NotCurrentContext,
     gl_config: Config,
     egui_glow: EguiGlow,
+    app: SceneEditorApp,
+    config: EditorConfig,
     event_receiver: Receiver<WinitEvent>,
+    event_loop_proxy: EventLoopProxy<UserEvent>,
 ) -> Result<()> {
+    let SceneEditorApp {
+        options,
+        registry,
+        mut world,
+        mut schedule,
+        mut simulation_schedule,
+        model_importers,
+        ..
+    } = app;
     let attrs = window.build_surface_attributes(Default::default());
     let gl_surface = unsafe { gl_config.display().create_window_surface(&gl_config, &attrs)? };
     let gl_context = not_current_gl_context.make_current(&gl_surface)?;
-    gl_surface.set_swap_interval(&gl_context, SwapInterval::Wait(NonZeroU32::new(1).unwrap()))?;
+    let mut frame_limiter = FrameLimiter::new(options.vsync);
+    let vsync = frame_limiter.vsync(config.vsync);
+    set_vsync(&gl_surface, &gl_context, vsync != VsyncMode::Off);
 
     // Draw once before loading
     unsafe {
@@ -45,63 +99,301 @@ pub fn run_game_loop(
         gl_surface.swap_buffers(&gl_context)?;
     }
 
-    let mut world = World::new();
-
-    let mut model_loader = ModelLoader::new();
-    model_loader.load_models_in_dir(&gl, "res/models")?;
+    let mut model_loader = ModelLoader::new()
+        .with_cache(DerivedCache::new(DerivedCache::DEFAULT_DIR))
+        .with_lods(&lod::LEVEL_RATIOS);
+    for (extension, importer) in model_importers {
+        model_loader.add_importer(&extension, importer);
+    }
+    model_loader.load_models_in_dir(&gl, &options.models_dir);
+    model_loader.add_missing_builtins(&gl);
     let mut texture_loader = TextureLoader::new();
-    texture_loader.load_textures_in_dir(&gl, "res/textures")?;
-
-    world.spawn((
-        Mesh::from(model_loader.get("Plane").unwrap()),
-        TransformBundle {
-            position: Position::new(0.0, -2.0, 0.0),
-            scale: Scale::new(10.0, 1.0, 10.0),
-            ..Default::default()
-        },
-    ));
-    world.spawn((
-        Mesh::from(model_loader.get("Cube").unwrap()),
-        TransformBundle { position: Position::new(5.0, 0.0, 0.0), ..Default::default() },
-    ));
-    world.spawn((
-        Mesh::from(model_loader.get("Sphere").unwrap()),
-        PointLight::new(
-            glm::vec3(0.2, 0.2, 0.2),
-            glm::vec3(1.0, 1.0, 1.0),
-            glm::vec3(1.0, 1.0, 1.0),
-            1.0,
-            0.09,
-            0.032,
-        ),
-        TransformBundle { position: Position::new(-5.0, 0.0, 0.0), ..Default::default() },
-    ));
+    texture_loader.set_quality(&gl, config.texture_quality);
+    texture_loader.load_textures_in_dir(&gl, &options.textures_dir);
+    let mut material_loader = MaterialLoader::new();
+    material_loader.load_materials_in_dir(&options.materials_dir);
+
+    if let Some(plane) = model_loader.get("Plane") {
+        world.spawn((
+            Mesh::from(plane),
+            TransformBundle {
+                position: Position::new(0.0, -2.0, 0.0),
+                scale: Scale::new(10.0, 1.0, 10.0),
+                ..Default::default()
+            },
+        ));
+    }
+    if let Some(cube) = model_loader.get("Cube") {
+        world.spawn((
+            Mesh::from(cube),
+            TransformBundle { position: Position::new(5.0, 0.0, 0.0), ..Default::default() },
+        ));
+    }
+    if let Some(sphere) = model_loader.get("Sphere") {
+        world.spawn((
+            Mesh::from(sphere),
+            PointLight::new(
+                glm::vec3(0.2, 0.2, 0.2),
+                glm::vec3(1.0, 1.0, 1.0),
+                glm::vec3(1.0, 1.0, 1.0),
+                1.0,
+                0.09,
+                0.032,
+            ),
+            TransformBundle { position: Position::new(-5.0, 0.0, 0.0), ..Default::default() },
+        ));
+    }
 
     // Make sure systems using OpenGL runs on this thread
     world.insert_non_send_resource(gl.clone());
+    world.insert_non_send_resource(event_loop_proxy);
     world.insert_resource(model_loader);
     world.insert_resource(texture_loader);
+    world.insert_resource(material_loader);
+    world.insert_resource(registry);
     world.insert_resource(WinitWindow::new(window.clone()));
     world.insert_resource(EguiGlowRes::new(egui_glow));
+    let window_size = window.inner_size();
+    world.insert_resource(Viewport::new(window_size.width, window_size.height));
     world.init_resource::<RenderState>();
+    if config.msaa_samples > 1 {
+        match world.resource_mut::<RenderState>().set_samples(&gl, config.msaa_samples) {
+            Ok(samples) => info!("rendering with {samples}× MSAA"),
+            Err(e) => warn!("could not turn on MSAA, rendering without it: {e}"),
+        }
+    }
+    // The render targets keep their GL objects when resized, so the texture is registered once
+    let scene_texture = world.resource::<RenderState>().frame_graph.texture(SCENE_COLOR).unwrap();
+    let texture =
+        world.resource_mut::<EguiGlowRes>().painter.register_native_texture(scene_texture);
+    world.insert_resource(SceneView::new(Some(texture)));
     world.init_resource::<Camera>();
+    world.init_resource::<ViewLayout>();
+    world.init_resource::<CameraFlight>();
+    world.init_resource::<ViewBookmarks>();
     world.init_resource::<UiState>();
+    world.resource_mut::<UiState>().view_only = options.view_only;
     world.init_resource::<Time>();
+    world.init_resource::<FixedTime>();
+    world.init_resource::<ShaderGlobals>();
+    world.init_resource::<SceneVariables>();
+    world.init_resource::<SceneVariants>();
+    world.init_resource::<Lighting>();
+    world.init_resource::<RenderSettings>();
+    world.init_resource::<LookDev>();
     world.init_resource::<Input>();
+    world.init_resource::<Events<KeyEvent>>();
+    world.init_resource::<Events<MouseEvent>>();
+    let config_path = config::config_path();
+    // Bindings were kept in a file of their own before there was a configuration file
+    let bindings_path =
+        if config_path.exists() { config_path } else { PathBuf::from(LEGACY_INPUT_CONFIG_PATH) };
+    world.insert_resource(InputMap::load_or_default(bindings_path));
+    let mut shader_cache = ShaderCache::default();
+    if config.shader_binaries {
+        let display = gl_config.display();
+        // The context was made current on this thread above
+        match unsafe { ProgramBinaryFns::load(&gl, |name| display.get_proc_address(name)) } {
+            Some(fns) => {
+                let driver = unsafe {
+                    let renderer = gl.get_parameter_string(glow::RENDERER);
+                    format!("{renderer} {}", gl.get_parameter_string(glow::VERSION))
+                };
+                shader_cache =
+                    shader_cache.with_binaries(BinaryStore::new(BINARY_DIR, driver), fns);
+            }
+            None => info!("the OpenGL driver can not store shader binaries"),
+        }
+    }
+    world.insert_resource(shader_cache);
+    // The context was made current on this thread above
+    let reset_status = unsafe {
+        let display = gl_config.display();
+        ResetStatus::load(|name| display.get_proc_address(name))
+    };
+    if reset_status.is_none() {
+        info!("the OpenGL driver can not tell whether the GPU was reset");
+    }
+    world.insert_resource(config);
+    world.init_resource::<InspectedComponents>();
+    world.init_resource::<Outliner>();
+    world.init_resource::<Placement>();
+    world.init_resource::<BoxSelection>();
+    world.init_resource::<TerrainBrush>();
+    world.init_resource::<TerrainMeshes>();
+    world.insert_resource(DebugDraw { light_ranges: true, ..Default::default() });
+    world.init_resource::<GlState>();
+    world.init_resource::<GlErrorChecks>();
+    world.init_resource::<Profiler>();
+    world.init_resource::<Picker>();
+    world.init_resource::<Notifications>();
+    world.init_resource::<ScriptRuntime>();
+    world.init_resource::<ScriptLog>();
+    world.init_resource::<ConsoleLog>();
+    if options.watch_shaders {
+        if cfg!(debug_assertions) {
+            info!("watching {SHADERS_DIR} for shader changes");
+            world.insert_resource(ShaderWatcher::new(SHADERS_DIR));
+        } else {
+            warn!("shader hot reload is only available in debug builds");
+        }
+    }
+
+    let mut config = world.resource_mut::<EditorConfig>();
+    let mut update_checker = UpdateChecker::default();
+    if config.check_for_updates {
+        update_checker.start();
+    }
+    let palette = config.palette.palette();
+    // Show what's new after an upgrade, but not on the first run
+    let upgraded = config.last_seen_version.as_deref() != Some(CURRENT_VERSION);
+    let first_run = config.last_seen_version.is_none();
+    if upgraded {
+        config.last_seen_version = Some(CURRENT_VERSION.to_owned());
+        if let Err(e) = config.save(config::config_path()) {
+            warn!("could not save editor configuration: {e}");
+        }
+        world.resource_mut::<UiState>().whats_new_open = !first_run;
+    }
+    world.insert_resource(palette);
+    world.insert_resource(update_checker);
+    world.init_resource::<UsageLog>();
 
-    let mut schedule = Schedule::default();
-    schedule.add_systems((
-        ui::run_ui,
-        systems::move_camera,
-        systems::spawn_object,
-        systems::select_object,
-    ));
+    let models = world.resource::<ModelLoader>();
+    let textures = world.resource::<TextureLoader>();
+    let (model_count, texture_count) = (models.keys().count(), textures.keys().count());
+    let asset_errors = models.errors().len()
+        + textures.errors().len()
+        + world.resource::<MaterialLoader>().errors().len();
+    let mut notifications = world.resource_mut::<Notifications>();
+    notifications.info(format!("Loaded {model_count} models and {texture_count} textures"));
+    if asset_errors > 0 {
+        notifications.warning(format!("{asset_errors} assets could not be loaded"));
+    }
+    world.resource_mut::<UiState>().asset_errors_open = asset_errors > 0;
 
-    let mut render_schedule = Schedule::default();
-    render_schedule.set_executor_kind(ExecutorKind::SingleThreaded);
-    render_schedule.add_systems((renderer::render, ui::paint_ui).chain());
+    if let Some(path) = &options.scene {
+        commands::load_scene(&mut world, path);
+        world.resource_mut::<UiState>().scene_path = path.display().to_string();
+    } else if world.resource::<EditorConfig>().start_screen {
+        world.resource_mut::<UiState>().start_screen_open = true;
+    }
 
+    schedule.add_systems(
+        (systems::update_input, systems::window_actions, systems::overlay_actions)
+            .chain()
+            .in_set(EditorSet::Input),
+    );
+    schedule.add_systems(
+        (
+            systems::inspect_custom_components.before(ui::run_ui),
+            systems::list_outliner_entries.before(ui::run_ui),
+            ui::run_ui,
+            systems::move_camera,
+            systems::spawn_object.run_if(systems::editing_allowed),
+            systems::select_object.run_if(systems::clicks_select),
+            systems::box_select.after(systems::select_object),
+            systems::nudge_selection.run_if(systems::editing_allowed),
+            systems::cycle_selection,
+            systems::focus_camera,
+            systems::axis_views,
+            systems::switch_views,
+            systems::camera_views,
+            systems::poll_update_check,
+            systems::track_usage,
+        )
+            .in_set(EditorSet::EditorUi),
+    );
+    schedule.add_systems(
+        (systems::sculpt_terrain.run_if(systems::editing_allowed), systems::pick_focus)
+            .in_set(EditorSet::EditorUi),
+    );
+    schedule.add_system(
+        systems::look_dev
+            .after(systems::move_camera)
+            .after(systems::focus_camera)
+            .in_set(EditorSet::EditorUi),
+    );
+    // Plugin panels are drawn into the same UI frame as the editor's own
+    schedule.add_systems(
+        (
+            ui::begin_ui.before(ui::run_ui),
+            ui::terrain_window.after(ui::run_ui).before(ui::show_plugin_panels),
+            ui::render_settings_window.after(ui::run_ui).before(ui::show_plugin_panels),
+            ui::show_plugin_panels.after(ui::run_ui),
+            ui::end_ui.after(ui::show_plugin_panels),
+        )
+            .in_set(EditorSet::EditorUi),
+    );
+    schedule.add_systems(
+        (
+            systems::run_simulation,
+            scripting::run_scripts,
+            systems::update_shader_globals,
+            systems::apply_variables,
+        )
+            .chain()
+            .in_set(EditorSet::Simulation),
+    );
+    schedule.add_systems(
+        (
+            systems::resize_scene_view,
+            systems::reload_shaders,
+            renderer::interpolate_transforms,
+            transform::update_global_transforms,
+            systems::update_scene_camera,
+            lod::update_lods,
+            terrain::update_terrain_meshes,
+            systems::apply_render_settings,
+            renderer::render,
+            renderer::draw_debug_lines,
+            systems::capture_turntable,
+            renderer::store_previous_transforms,
+            ui::paint_ui,
+        )
+            .chain()
+            .in_set(EditorSet::Render),
+    );
+    schedule.add_systems(
+        (
+            systems::clear_input,
+            Events::<KeyEvent>::update_system,
+            Events::<MouseEvent>::update_system,
+            systems::advance_time,
+        )
+            .in_set(EditorSet::Cleanup),
+    );
+
+    // Animations advance in fixed steps, so they play the same at any frame rate
+    simulation_schedule.add_systems(
+        (
+            systems::begin_fixed_step,
+            systems::animate_transforms,
+            systems::play_animation_clips,
+            systems::update_skeletons,
+        )
+            .chain()
+            .in_set(EditorSet::Simulation),
+    );
+    world.add_schedule(simulation_schedule, SimulationSchedule);
+
+    let watchdog = FrameWatchdog::default();
+    let stalled_window = window.clone();
+    watchdog.spawn(move |duration| {
+        warn!(
+            "frame has taken {:.1} s, the GPU may be stuck on a custom shader",
+            duration.as_secs_f32()
+        );
+        stalled_window.set_title(&format!("{WINDOW_TITLE} (not responding)"));
+    });
+
+    let mut focused = true;
+    let (mut minimized, mut occluded) = (false, false);
+    let mut paused_since: Option<Instant> = None;
+    let mut context_lost = false;
     'game_loop: loop {
+        let config = world.resource::<EditorConfig>();
+        frame_limiter.wait(config.fps_limit, !focused && config.idle_when_unfocused);
         for event in event_receiver.try_iter() {
             match event {
                 WinitEvent::WindowEvent(event) => {
@@ -110,67 +402,58 @@ pub fn run_game_loop(
 
                     if !event_response.consumed {
                         match event {
-                            WindowEvent::MouseInput {
-                                state, button: MouseButton::Right, ..
-                            } => {
-                                let camera_focused =
-                                    &mut world.resource_mut::<UiState>().camera_focused;
-                                match state {
-                                    ElementState::Pressed => {
-                                        window
-                                            .set_cursor_grab(CursorGrabMode::Confined)
-                                            .or_else(|_| {
-                                                window.set_cursor_grab(CursorGrabMode::Locked)
-                                            })
-                                            .unwrap();
-                                        window.set_cursor_visible(false);
-                                        *camera_focused = true;
-                                    }
-                                    ElementState::Released => {
-                                        window.set_cursor_grab(CursorGrabMode::None).unwrap();
-                                        window.set_cursor_visible(true);
-                                        *camera_focused = false;
-                                    }
-                                }
-                            }
                             WindowEvent::MouseInput { state, button, .. } => {
-                                world
-                                    .resource_mut::<Input>()
-                                    .handle_mouse_button_input(button, state);
+                                world.send_event(MouseEvent { button, state });
                             }
                             WindowEvent::CursorMoved { position, .. } => {
-                                world.resource_mut::<Input>().mouse_pos = position.into();
+                                let position =
+                                    world.resource::<SceneView>().to_local(position.into());
+                                world.resource_mut::<Input>().mouse_pos = position;
                             }
                             WindowEvent::KeyboardInput {
                                 input: KeyboardInput { state, virtual_keycode: Some(keycode), .. },
                                 ..
                             } => {
-                                world.resource_mut::<Input>().handle_keyboard_input(keycode, state);
+                                world.send_event(KeyEvent { key: keycode, state });
                             }
                             WindowEvent::Resized(size) => {
-                                resize(&gl_surface, &gl_context, &mut world, size);
+                                minimized = size.width == 0 || size.height == 0;
+                                resize(&gl_surface, &gl_context, size);
                             }
+                            WindowEvent::Focused(has_focus) => focused = has_focus,
+                            WindowEvent::Occluded(hidden) => occluded = hidden,
                             _ => (),
                         }
                     }
                 }
                 WinitEvent::ScaleFactorChanged { scale_factor, new_size } => {
+                    let pixels_per_point =
+                        world.resource::<EditorConfig>().pixels_per_point(scale_factor);
                     info!(
-                        "scale factor changed, changing egui pixels per point to {}",
-                        scale_factor
+                        "scale factor changed, setting egui pixels per point to {pixels_per_point}"
                     );
                     world
                         .resource_mut::<EguiGlowRes>()
                         .egui_ctx
-                        .set_pixels_per_point(scale_factor as f32);
+                        .set_pixels_per_point(pixels_per_point);
 
-                    resize(&gl_surface, &gl_context, &mut world, new_size);
+                    resize(&gl_surface, &gl_context, new_size);
                 }
                 WinitEvent::MouseMotion(delta) => {
                     if world.resource::<UiState>().camera_focused {
-                        world.resource_mut::<Input>().mouse_delta = delta;
+                        // Several motion events can arrive in one frame
+                        let mut input = world.resource_mut::<Input>();
+                        input.mouse_delta.0 += delta.0;
+                        input.mouse_delta.1 += delta.1;
                     }
                 }
+                #[cfg(feature = "accesskit")]
+                WinitEvent::AccessKitActionRequest(request) => {
+                    world
+                        .resource_mut::<EguiGlowRes>()
+                        .egui_winit
+                        .on_accesskit_action_request(request);
+                }
                 WinitEvent::LoopDestroyed => {
                     cleanup(&mut world);
                     break 'game_loop Ok(());
@@ -178,41 +461,133 @@ pub fn run_game_loop(
             }
         }
 
+        // Nothing can be drawn with a lost context, so only wait for the editor to quit
+        if context_lost {
+            thread::sleep(HIDDEN_POLL_INTERVAL);
+            continue;
+        }
+
+        // Nothing can be seen, so skip drawing until the window is shown again
+        if minimized || occluded {
+            if paused_since.is_none() {
+                info!("window is hidden, pausing rendering");
+                paused_since = Some(Instant::now());
+            }
+            thread::sleep(HIDDEN_POLL_INTERVAL);
+            continue;
+        }
+        if let Some(paused_since) = paused_since.take() {
+            info!("window is shown again, resuming rendering");
+            world.resource_mut::<Time>().skip(paused_since.elapsed());
+        }
+
+        watchdog.begin_frame();
         schedule.run(&mut world);
-        render_schedule.run(&mut world);
+        world.resource_mut::<Profiler>().end_frame(&gl);
 
+        let refresh_interval = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .filter(|&millihertz| millihertz > 0)
+            .map(|millihertz| Duration::from_secs(1000) / millihertz);
+        let vsync = world.resource::<EditorConfig>().vsync;
+        if let Some(wait) = frame_limiter.update_vsync(vsync, refresh_interval) {
+            set_vsync(&gl_surface, &gl_context, wait);
+        }
         gl_surface.swap_buffers(&gl_context)?;
+        // The context is current on this thread
+        if let Some(status) = reset_status.and_then(|reset| unsafe { reset.check() }) {
+            context_lost = true;
+            lost_context(&mut world, status);
+            continue;
+        }
+        if let Some(duration) = watchdog.end_frame() {
+            window.set_title(WINDOW_TITLE);
+            stalled_frame(&mut world, duration);
+        }
 
-        world.resource_mut::<Input>().update_after_frame();
-        world.resource_mut::<Time>().next_frame();
         world.clear_trackers();
     }
 }
 
+/// Save the scene and quit after the driver reset the GPU, which loses every GL object
+///
+/// The scene is kept on the CPU, so it is saved to `RECOVERED_SCENE_PATH` rather than over the
+/// scene file, which is left as it was last saved.
+fn lost_context(world: &mut World, status: u32) {
+    error!("the GPU was reset ({}), the editor has to quit", reset_name(status));
+    let scene = variants::with_base(world, Scene::from_world);
+    match scene.save(RECOVERED_SCENE_PATH) {
+        Ok(()) => error!("the scene was saved to {RECOVERED_SCENE_PATH}"),
+        Err(e) => error!("could not save the scene: {e}"),
+    }
+    // The editor quits as if the window was closed, which cleans up and saves the configuration
+    let _ = world.non_send_resource::<EventLoopProxy<UserEvent>>().send_event(UserEvent::Quit);
+}
+
+/// Draw custom shaders with the built-in shader after a frame took too long, and ask what to do
+fn stalled_frame(world: &mut World, duration: Duration) {
+    let shaders: Vec<_> = world
+        .query::<(Entity, &CustomShader)>()
+        .iter(world)
+        .filter(|(_, cs)| cs.shader.is_ok())
+        .map(|(entity, _)| entity)
+        .collect();
+    warn!("frame took {:.1} s with {} custom shaders", duration.as_secs_f32(), shaders.len());
+    if shaders.is_empty() {
+        return;
+    }
+    world.resource_mut::<RenderState>().custom_shaders = false;
+    world.resource_mut::<UiState>().stalled_frame = Some(StalledFrame { duration, shaders });
+}
+
+fn set_vsync(
+    gl_surface: &glutin::surface::Surface<glutin::surface::WindowSurface>,
+    gl_context: &glutin::context::PossiblyCurrentContext,
+    wait: bool,
+) {
+    let interval =
+        if wait { SwapInterval::Wait(NonZeroU32::new(1).unwrap()) } else { SwapInterval::DontWait };
+    if let Err(e) = gl_surface.set_swap_interval(gl_context, interval) {
+        warn!("could not set swap interval: {e}");
+    }
+}
+
 fn resize(
     gl_surface: &glutin::surface::Surface<glutin::surface::WindowSurface>,
     gl_context: &glutin::context::PossiblyCurrentContext,
-    world: &mut World,
     new_size: PhysicalSize<u32>,
 ) {
     let (width, height): (u32, u32) = new_size.into();
     if width != 0 && height != 0 {
-        // Update projection
-        world.resource_mut::<Camera>().projection =
-            Camera::perspective(new_size.width, new_size.height);
-
-        // Resize surface (no-op on most platforms, needed for compatibility)
+        // Resize surface (no-op on most platforms, needed for compatibility). The render targets
+        // follow the scene view, which is resized once the UI has been laid out.
         gl_surface.resize(gl_context, width.try_into().unwrap(), height.try_into().unwrap());
-
-        // Resize render state
-        world.resource_scope(|world, mut rs: Mut<RenderState>| {
-            let gl = world.non_send_resource::<Arc<Context>>();
-            rs.resize(gl, width, height);
-        });
     }
 }
 
 fn cleanup(world: &mut World) {
+    if world.resource::<EditorConfig>().usage_log {
+        if let Err(e) = world.resource::<UsageLog>().save(USAGE_LOG_PATH) {
+            warn!("could not save usage log: {e}");
+        }
+    }
+    let window = world.resource::<WinitWindow>();
+    let size = window.inner_size();
+    let position = window.outer_position().ok();
+    let mut config = world.resource_mut::<EditorConfig>();
+    config.window.size = Some((size.width, size.height));
+    config.window.position = position.map(|p| (p.x, p.y));
+    // Also saves the panel sizes and tabs, which change too often to save as they happen
+    let config_path = config::config_path();
+    if let Err(e) = config.save(&config_path) {
+        warn!("could not save editor configuration: {e}");
+    }
+    // Also moves bindings loaded from the old input file into the configuration file
+    if let Err(e) = world.resource::<InputMap>().save(&config_path) {
+        warn!("could not save input bindings: {e}");
+    }
+
     world.resource_mut::<EguiGlowRes>().destroy();
 
     let gl = world.non_send_resource::<Arc<Context>>().clone();
@@ -232,11 +607,10 @@ fn cleanup(world: &mut World) {
         }
     }
 
-    let mut render_state = world.resource_mut::<RenderState>();
     unsafe {
-        render_state.quad_vao.destroy(&gl);
-        render_state.depth_shader.destroy(&gl);
-        render_state.geometry_pass_shader.destroy(&gl);
-        render_state.deferred_pass_shader.destroy(&gl);
+        world.resource_mut::<RenderState>().destroy(&gl);
+        world.resource_mut::<Profiler>().destroy(&gl);
+        world.resource_mut::<Picker>().destroy(&gl);
+        world.resource_mut::<TerrainMeshes>().destroy(&gl);
     }
 }
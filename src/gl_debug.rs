@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::ffi::{c_void, CStr};
+use std::mem;
+
+use bevy_ecs::system::Resource;
+use glow::{Context, HasContext};
+use tracing::{debug, error, info, warn};
+
+/// Number of failures kept by `GlErrorChecks`
+const MAX_FAILURES: usize = 20;
+
+/// Evaluate a GL call and, in debug builds, log any GL errors it raised with the call site
+///
+/// ```ignore
+/// gl_check!(gl, gl.draw_elements(glow::TRIANGLES, len, glow::UNSIGNED_INT, 0));
+/// ```
+macro_rules! gl_check {
+    ($gl:expr, $call:expr) => {{
+        let result = $call;
+        if cfg!(debug_assertions) {
+            $crate::gl_debug::check_error(&$gl, file!(), line!(), stringify!($call));
+        }
+        result
+    }};
+}
+
+pub(crate) use gl_check;
+
+/// Log all pending GL errors, returning whether there were any
+pub fn check_error(gl: &Context, file: &str, line: u32, call: &str) -> bool {
+    let errors = pending_errors(gl);
+    for &error in &errors {
+        warn!("{} at {file}:{line} in `{call}`", error_name(error));
+    }
+    !errors.is_empty()
+}
+
+/// Take all error flags that are set, clearing them
+pub fn pending_errors(gl: &Context) -> Vec<u32> {
+    let mut errors = Vec::new();
+    loop {
+        let error = unsafe { gl.get_error() };
+        if error == glow::NO_ERROR {
+            return errors;
+        }
+        errors.push(error);
+    }
+}
+
+pub fn error_name(error: u32) -> &'static str {
+    match error {
+        glow::INVALID_ENUM => "GL_INVALID_ENUM",
+        glow::INVALID_VALUE => "GL_INVALID_VALUE",
+        glow::INVALID_OPERATION => "GL_INVALID_OPERATION",
+        glow::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        glow::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        glow::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+        glow::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+        _ => "unknown GL error",
+    }
+}
+
+type GetGraphicsResetStatus = unsafe extern "system" fn() -> u32;
+
+/// `glGetGraphicsResetStatus`, which tells whether the driver reset the GPU and lost the context,
+/// and which glow does not wrap
+#[derive(Clone, Copy)]
+pub struct ResetStatus {
+    get_graphics_reset_status: GetGraphicsResetStatus,
+}
+
+impl ResetStatus {
+    /// Look up the function with the loader the context was created with, if the driver has it
+    ///
+    /// # Safety
+    ///
+    /// The loader must return the functions of the current context.
+    pub unsafe fn load(mut loader: impl FnMut(&CStr) -> *const c_void) -> Option<Self> {
+        let mut lookup = |name: &[u8]| {
+            let function = loader(CStr::from_bytes_with_nul(name).ok()?);
+            (!function.is_null()).then_some(function)
+        };
+        // Drivers before OpenGL 4.5 only have it from `GL_ARB_robustness`
+        let function = lookup(b"glGetGraphicsResetStatus\0")
+            .or_else(|| lookup(b"glGetGraphicsResetStatusARB\0"))?;
+        Some(Self {
+            get_graphics_reset_status: mem::transmute::<*const c_void, GetGraphicsResetStatus>(
+                function,
+            ),
+        })
+    }
+
+    /// How the context was lost, or `None` while it can still be used
+    ///
+    /// # Safety
+    ///
+    /// The context the function was loaded for must be current on the calling thread.
+    pub unsafe fn check(&self) -> Option<u32> {
+        let status = (self.get_graphics_reset_status)();
+        (status != glow::NO_ERROR).then_some(status)
+    }
+}
+
+pub fn reset_name(status: u32) -> &'static str {
+    match status {
+        glow::GUILTY_CONTEXT_RESET => "GL_GUILTY_CONTEXT_RESET",
+        glow::INNOCENT_CONTEXT_RESET => "GL_INNOCENT_CONTEXT_RESET",
+        glow::UNKNOWN_CONTEXT_RESET => "GL_UNKNOWN_CONTEXT_RESET",
+        _ => "unknown reset status",
+    }
+}
+
+/// How often the renderer checks for GL errors
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorCheckMode {
+    #[default]
+    Off,
+    /// After each render pass
+    Passes,
+    /// After each draw call, which stalls the pipeline
+    DrawCalls,
+}
+
+/// Checks the GL error flags while rendering, for drivers without debug output
+#[derive(Resource, Default)]
+pub struct GlErrorChecks {
+    pub mode: ErrorCheckMode,
+    /// Most recent failures, oldest first
+    failures: VecDeque<String>,
+}
+
+impl GlErrorChecks {
+    /// Report errors raised by the given stage, if checks are enabled at the given level
+    pub fn check(&mut self, gl: &Context, stage: &str, level: ErrorCheckMode) {
+        if self.mode < level {
+            return;
+        }
+
+        for error in pending_errors(gl) {
+            let failure = format!("{} in {stage}", error_name(error));
+            warn!("{failure}");
+            if self.failures.len() == MAX_FAILURES {
+                self.failures.pop_front();
+            }
+            self.failures.push_back(failure);
+        }
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &str> {
+        self.failures.iter().map(String::as_str)
+    }
+
+    pub fn clear(&mut self) {
+        self.failures.clear();
+    }
+}
+
+/// Route messages from `GL_KHR_debug` through `tracing`, returning whether debug output is
+/// supported
+///
+/// # Safety
+///
+/// The context must be current on the calling thread.
+pub unsafe fn enable_debug_output(gl: &Context) -> bool {
+    if !gl.supports_debug() {
+        return false;
+    }
+
+    gl.enable(glow::DEBUG_OUTPUT);
+    // Report messages on the thread and in the call that caused them
+    gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+    // glow keeps a pointer to the callback on the stack, so it must not capture anything
+    gl.debug_message_callback(log_message);
+    true
+}
+
+fn log_message(source: u32, ty: u32, id: u32, severity: u32, message: &str) {
+    let source = match source {
+        glow::DEBUG_SOURCE_API => "API",
+        glow::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        glow::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        glow::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        glow::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    };
+    let ty = match ty {
+        glow::DEBUG_TYPE_ERROR => "error",
+        glow::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        glow::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        glow::DEBUG_TYPE_PORTABILITY => "portability",
+        glow::DEBUG_TYPE_PERFORMANCE => "performance",
+        glow::DEBUG_TYPE_MARKER => "marker",
+        _ => "other",
+    };
+
+    match severity {
+        glow::DEBUG_SEVERITY_HIGH => error!("GL {source} {ty} ({id}): {message}"),
+        glow::DEBUG_SEVERITY_MEDIUM => warn!("GL {source} {ty} ({id}): {message}"),
+        glow::DEBUG_SEVERITY_LOW => info!("GL {source} {ty} ({id}): {message}"),
+        _ => debug!("GL {source} {ty} ({id}): {message}"),
+    }
+}
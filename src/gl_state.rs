@@ -0,0 +1,169 @@
+use bevy_ecs::system::Resource;
+use glow::{Context, HasContext, Program, Texture};
+
+/// Number of texture units tracked by the cache
+pub const MAX_TEXTURE_UNITS: usize = 16;
+
+/// Capabilities tracked by the cache, toggled with `glEnable` and `glDisable`
+const CAPABILITIES: [u32; 5] =
+    [glow::BLEND, glow::CULL_FACE, glow::DEPTH_TEST, glow::SCISSOR_TEST, glow::STENCIL_TEST];
+
+/// Cache of the GL state set by the renderer, which skips calls that would not change anything
+///
+/// A `None` field means the state is unknown and the next call always goes through. The cache
+/// must be invalidated whenever GL state is changed without it, like when egui is painted.
+#[derive(Resource, Default)]
+pub struct GlState {
+    program: Option<Option<Program>>,
+    active_texture: Option<u32>,
+    textures: [Option<Option<Texture>>; MAX_TEXTURE_UNITS],
+    capabilities: [Option<bool>; CAPABILITIES.len()],
+    blend_func: Option<(u32, u32)>,
+    depth_func: Option<u32>,
+    depth_mask: Option<bool>,
+    cull_face: Option<u32>,
+    stencil_func: Option<(u32, i32, u32)>,
+    stencil_op: Option<(u32, u32, u32)>,
+    stencil_mask: Option<u32>,
+}
+
+/// Update a cached value, returning whether it changed
+fn update<T: PartialEq>(cached: &mut Option<T>, value: T) -> bool {
+    if cached.as_ref() == Some(&value) {
+        return false;
+    }
+    *cached = Some(value);
+    true
+}
+
+impl GlState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget all cached state, so the next calls are all passed on to GL
+    pub fn invalidate(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn use_program(&mut self, gl: &Context, program: Option<Program>) {
+        if update(&mut self.program, program) {
+            unsafe { gl.use_program(program) }
+        }
+    }
+
+    /// Bind a 2D texture to the given texture unit, starting from 0
+    pub fn bind_texture(&mut self, gl: &Context, unit: u32, texture: Option<Texture>) {
+        let Some(cached) = self.textures.get_mut(unit as usize) else {
+            // Not tracked, so bind it directly and forget which unit is active
+            self.active_texture = None;
+            unsafe {
+                gl.active_texture(glow::TEXTURE0 + unit);
+                gl.bind_texture(glow::TEXTURE_2D, texture);
+            }
+            return;
+        };
+        if !update(cached, texture) {
+            return;
+        }
+
+        unsafe {
+            if update(&mut self.active_texture, unit) {
+                gl.active_texture(glow::TEXTURE0 + unit);
+            }
+            gl.bind_texture(glow::TEXTURE_2D, texture);
+        }
+    }
+
+    /// Make a texture unit active, so the texture bound to it can be changed
+    pub fn active_texture(&mut self, gl: &Context, unit: u32) {
+        if update(&mut self.active_texture, unit) {
+            unsafe { gl.active_texture(glow::TEXTURE0 + unit) }
+        }
+    }
+
+    /// Bind a multisampled texture to the given texture unit
+    ///
+    /// Only 2D textures are cached, so this always goes through.
+    pub fn bind_texture_multisample(&mut self, gl: &Context, unit: u32, texture: Option<Texture>) {
+        self.active_texture = Some(unit);
+        unsafe {
+            gl.active_texture(glow::TEXTURE0 + unit);
+            gl.bind_texture(glow::TEXTURE_2D_MULTISAMPLE, texture);
+        }
+    }
+
+    /// Enable or disable a capability like `glow::DEPTH_TEST`
+    pub fn set_enabled(&mut self, gl: &Context, capability: u32, enabled: bool) {
+        let changed = match CAPABILITIES.iter().position(|&c| c == capability) {
+            Some(i) => update(&mut self.capabilities[i], enabled),
+            None => true,
+        };
+        if !changed {
+            return;
+        }
+
+        unsafe {
+            if enabled {
+                gl.enable(capability);
+            } else {
+                gl.disable(capability);
+            }
+        }
+    }
+
+    /// Whether a capability is enabled, asking GL when the cache does not know
+    pub fn is_enabled(&mut self, gl: &Context, capability: u32) -> bool {
+        let cached = CAPABILITIES.iter().position(|&c| c == capability);
+        if let Some(enabled) = cached.and_then(|i| self.capabilities[i]) {
+            return enabled;
+        }
+        let enabled = unsafe { gl.is_enabled(capability) };
+        if let Some(i) = cached {
+            self.capabilities[i] = Some(enabled);
+        }
+        enabled
+    }
+
+    pub fn blend_func(&mut self, gl: &Context, src: u32, dst: u32) {
+        if update(&mut self.blend_func, (src, dst)) {
+            unsafe { gl.blend_func(src, dst) }
+        }
+    }
+
+    pub fn depth_func(&mut self, gl: &Context, func: u32) {
+        if update(&mut self.depth_func, func) {
+            unsafe { gl.depth_func(func) }
+        }
+    }
+
+    pub fn depth_mask(&mut self, gl: &Context, mask: bool) {
+        if update(&mut self.depth_mask, mask) {
+            unsafe { gl.depth_mask(mask) }
+        }
+    }
+
+    pub fn cull_face(&mut self, gl: &Context, face: u32) {
+        if update(&mut self.cull_face, face) {
+            unsafe { gl.cull_face(face) }
+        }
+    }
+
+    pub fn stencil_func(&mut self, gl: &Context, func: u32, reference: i32, mask: u32) {
+        if update(&mut self.stencil_func, (func, reference, mask)) {
+            unsafe { gl.stencil_func(func, reference, mask) }
+        }
+    }
+
+    pub fn stencil_op(&mut self, gl: &Context, stencil_fail: u32, depth_fail: u32, pass: u32) {
+        if update(&mut self.stencil_op, (stencil_fail, depth_fail, pass)) {
+            unsafe { gl.stencil_op(stencil_fail, depth_fail, pass) }
+        }
+    }
+
+    pub fn stencil_mask(&mut self, gl: &Context, mask: u32) {
+        if update(&mut self.stencil_mask, mask) {
+            unsafe { gl.stencil_mask(mask) }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::ops::Range;
+
+/// Words of the language, separated by whitespace
+const KEYWORDS: &str = "\
+    attribute break case centroid const continue default discard do else false flat for highp if \
+    in inout invariant layout lowp mediump noperspective out precision return smooth struct \
+    subroutine switch true uniform varying while";
+
+const TYPES: &str = "\
+    bool bvec2 bvec3 bvec4 double dvec2 dvec3 dvec4 float int isampler2D ivec2 ivec3 ivec4 mat2 \
+    mat2x3 mat2x4 mat3 mat3x2 mat3x4 mat4 mat4x2 mat4x3 sampler1D sampler2D sampler2DArray \
+    sampler2DShadow sampler3D samplerCube uint usampler2D uvec2 uvec3 uvec4 vec2 vec3 vec4 void";
+
+/// Built-in functions, while built-in variables are recognized by their `gl_` prefix
+const BUILTINS: &str = "\
+    abs acos all any asin atan ceil clamp cos cross dFdx dFdy degrees determinant distance dot \
+    exp exp2 faceforward floor fract fwidth inverse inversesqrt length log log2 max min mix mod \
+    normalize not pow radians reflect refract round sign sin smoothstep sqrt step tan texelFetch \
+    texture textureGather textureGrad textureLod textureOffset textureProj textureSize transpose";
+
+/// What a piece of GLSL source is, for highlighting and checking custom shaders
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Comment,
+    /// A line starting with `#`, such as `#version` or `#define`
+    Preprocessor,
+    Keyword,
+    Type,
+    /// Built-in functions and `gl_` variables
+    Builtin,
+    Number,
+    Identifier,
+    /// Whitespace, operators and punctuation
+    Other,
+}
+
+/// A piece of source and the byte range it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub range: Range<usize>,
+}
+
+/// Split GLSL source into tokens that together cover all of it
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let bytes = source.as_bytes();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &source[i..];
+        let (kind, len) = if rest.starts_with("//") || (line_start && rest.starts_with('#')) {
+            let kind =
+                if rest.starts_with('#') { TokenKind::Preprocessor } else { TokenKind::Comment };
+            (kind, rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            (TokenKind::Comment, comment.find("*/").map_or(rest.len(), |end| end + 4))
+        } else if bytes[i].is_ascii_digit()
+            || (bytes[i] == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        {
+            (TokenKind::Number, number_len(rest.as_bytes()))
+        } else if bytes[i].is_ascii_alphabetic() || bytes[i] == b'_' {
+            let len = rest
+                .bytes()
+                .position(|b| !(b.is_ascii_alphanumeric() || b == b'_'))
+                .unwrap_or(rest.len());
+            (identifier_kind(&rest[..len]), len)
+        } else {
+            (TokenKind::Other, rest.chars().next().map_or(1, char::len_utf8))
+        };
+
+        let range = i..i + len;
+        if source[range.clone()].contains('\n') {
+            line_start = true;
+        } else if kind != TokenKind::Other || !source[range.clone()].trim().is_empty() {
+            line_start = false;
+        }
+        match tokens.last_mut() {
+            Some(last) if last.kind == TokenKind::Other && kind == TokenKind::Other => {
+                last.range.end = range.end;
+            }
+            _ => tokens.push(Token { kind, range }),
+        }
+        i += len;
+    }
+    tokens
+}
+
+/// Length of the number at the start of `bytes`, with suffixes such as `u` and exponents
+fn number_len(bytes: &[u8]) -> usize {
+    let mut len = 0;
+    while let Some(&b) = bytes.get(len) {
+        let exponent_sign = (b == b'+' || b == b'-')
+            && len > 0
+            && matches!(bytes[len - 1], b'e' | b'E')
+            && !bytes.starts_with(b"0x");
+        if b.is_ascii_alphanumeric() || b == b'.' || b == b'_' || exponent_sign {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+fn identifier_kind(identifier: &str) -> TokenKind {
+    let listed = |words: &str| words.split_whitespace().any(|word| word == identifier);
+    if listed(KEYWORDS) {
+        TokenKind::Keyword
+    } else if listed(TYPES) {
+        TokenKind::Type
+    } else if listed(BUILTINS) || identifier.starts_with("gl_") {
+        TokenKind::Builtin
+    } else {
+        TokenKind::Identifier
+    }
+}
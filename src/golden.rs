@@ -0,0 +1,296 @@
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+
+use crate::image_export;
+
+/// Folder the golden-image cases are kept in
+pub const GOLDEN_DIR: &str = "tests/golden";
+
+/// Folder the HTML diff report is written to when a case fails
+pub const REPORT_DIR: &str = "target/golden-report";
+
+/// Size golden images are rendered at
+pub const WIDTH: u32 = 320;
+pub const HEIGHT: u32 = 240;
+
+/// Largest possible difference in YIQ space, so deltas fall between 0 and 1
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+/// RGBA8 pixels, starting with the top row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self { width, height, pixels }
+    }
+
+    /// Read an 8-bit RGB or RGBA PNG file
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| eyre!("could not open {}: {e}", path.display()))?;
+        let mut reader = png::Decoder::new(file).read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        buffer.truncate(info.buffer_size());
+        let pixels = match (info.color_type, info.bit_depth) {
+            (png::ColorType::Rgba, png::BitDepth::Eight) => buffer,
+            (png::ColorType::Rgb, png::BitDepth::Eight) => {
+                buffer.chunks_exact(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect()
+            }
+            (color, depth) => {
+                return Err(eyre!("{} is {color:?} {depth:?}, not 8-bit RGB", path.display()));
+            }
+        };
+        Ok(Self::new(info.width, info.height, pixels))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        image_export::save_png(path, self.width, self.height, &self.pixels)
+    }
+
+    fn pixel(&self, i: usize) -> [u8; 4] {
+        let p = &self.pixels[i * 4..i * 4 + 4];
+        [p[0], p[1], p[2], p[3]]
+    }
+}
+
+/// How different a render can be from its reference and still pass
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    /// Largest [`color_delta`] of a pixel that is counted as the same
+    pub threshold: f32,
+    /// Share of the pixels that can differ, from 0 to 1
+    pub max_different: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self { threshold: 0.1, max_different: 0.001 }
+    }
+}
+
+/// How a render differs from its reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    /// Pixels with a [`color_delta`] above the threshold
+    pub different: usize,
+    pub total: usize,
+    pub max_delta: f32,
+    /// The reference faded to gray, with the different pixels in red
+    pub image: Image,
+}
+
+impl Diff {
+    /// Share of the pixels that differ, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        self.different as f64 / self.total.max(1) as f64
+    }
+
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.fraction() <= tolerance.max_different
+    }
+}
+
+/// Perceptual difference between two colors, from 0 for the same color up to 1
+///
+/// Colors are blended onto white by their alpha and compared in YIQ space, where brightness counts
+/// for more than hue, as in pixelmatch.
+pub fn color_delta(a: [u8; 4], b: [u8; 4]) -> f32 {
+    let yiq = |[r, g, b, a]: [u8; 4]| {
+        let alpha = a as f32 / 255.0;
+        let blend = |c: u8| 255.0 + (c as f32 - 255.0) * alpha;
+        let (r, g, b) = (blend(r), blend(g), blend(b));
+        (
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2,
+            r * 0.595_978 - g * 0.274_176_1 - b * 0.321_801_9,
+            r * 0.211_470_2 - g * 0.522_617_1 + b * 0.311_146_9,
+        )
+    };
+    let ((y1, i1, q1), (y2, i2, q2)) = (yiq(a), yiq(b));
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    (delta / MAX_YIQ_DELTA).sqrt().min(1.0)
+}
+
+/// Compare a render with its reference pixel by pixel, counting the pixels that look different
+pub fn compare(reference: &Image, actual: &Image, tolerance: &Tolerance) -> Result<Diff> {
+    if (reference.width, reference.height) != (actual.width, actual.height) {
+        return Err(eyre!(
+            "the render is {}x{}, but the reference is {}x{}",
+            actual.width,
+            actual.height,
+            reference.width,
+            reference.height
+        ));
+    }
+
+    let total = (reference.width * reference.height) as usize;
+    let mut different = 0;
+    let mut max_delta = 0.0f32;
+    let mut pixels = Vec::with_capacity(total * 4);
+    for i in 0..total {
+        let (expected, found) = (reference.pixel(i), actual.pixel(i));
+        let delta = color_delta(expected, found);
+        max_delta = max_delta.max(delta);
+        if delta > tolerance.threshold {
+            different += 1;
+            pixels.extend([255, 0, 0, 255]);
+        } else {
+            // Faded so the red pixels stand out
+            let [r, g, b, _] = expected;
+            let luma = (r as f32 * 0.299 + g as f32 * 0.587 + b as f32 * 0.114) as u8;
+            let gray = 255 - (255 - luma) / 4;
+            pixels.extend([gray, gray, gray, 255]);
+        }
+    }
+    let image = Image::new(reference.width, reference.height, pixels);
+    Ok(Diff { different, total, max_delta, image })
+}
+
+/// A scene in the golden folder, rendered and compared with the image next to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub name: String,
+    pub scene: PathBuf,
+    /// Approved render, which may not exist yet
+    pub reference: PathBuf,
+}
+
+/// The `.ron` scenes in a folder, by name, with a `.png` reference of the same name
+pub fn cases(dir: &Path) -> Result<Vec<Case>> {
+    let entries = fs::read_dir(dir).map_err(|e| eyre!("could not read {}: {e}", dir.display()))?;
+    let mut cases: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "ron"))
+        .filter_map(|scene| {
+            let name = scene.file_stem()?.to_string_lossy().into_owned();
+            Some(Case { reference: scene.with_extension("png"), scene, name })
+        })
+        .collect();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// What came of checking a case
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed(Diff),
+    Failed(Diff),
+    /// The case has no reference image yet
+    Missing,
+    /// The render was saved as the new reference
+    Approved,
+    /// The scene could not be rendered, or the reference could not be read
+    Error(String),
+}
+
+impl Outcome {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Outcome::Passed(_) | Outcome::Approved)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseResult {
+    pub case: Case,
+    pub outcome: Outcome,
+    pub actual: Option<Image>,
+}
+
+/// Check a render against the reference of its case, or save it as the reference when approving
+pub fn check(case: &Case, actual: Image, tolerance: &Tolerance, approve: bool) -> CaseResult {
+    let outcome = if approve {
+        match actual.save(&case.reference) {
+            Ok(()) => Outcome::Approved,
+            Err(e) => Outcome::Error(format!("could not save the reference: {e}")),
+        }
+    } else if !case.reference.exists() {
+        Outcome::Missing
+    } else {
+        let diff = Image::load(&case.reference)
+            .and_then(|reference| compare(&reference, &actual, tolerance));
+        match diff {
+            Ok(diff) if diff.passes(tolerance) => Outcome::Passed(diff),
+            Ok(diff) => Outcome::Failed(diff),
+            Err(e) => Outcome::Error(e.to_string()),
+        }
+    };
+    CaseResult { case: case.clone(), outcome, actual: Some(actual) }
+}
+
+/// Write an HTML page showing the reference, render and difference of each case that did not
+/// pass, returning the path of the page
+pub fn write_report(results: &[CaseResult], dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).map_err(|e| eyre!("could not create {}: {e}", dir.display()))?;
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Golden images</title>\n\
+         <style>body { font-family: sans-serif; } img { image-rendering: pixelated; \
+         border: 1px solid #ccc; } td { padding: 4px; vertical-align: top; }</style>\n\
+         </head>\n<body>\n<h1>Golden images</h1>\n",
+    );
+    let failed = results.iter().filter(|r| !r.outcome.is_ok()).count();
+    writeln!(html, "<p>{failed} of {} cases failed.</p>", results.len())?;
+
+    for result in results.iter().filter(|r| !r.outcome.is_ok()) {
+        let name = escape(&result.case.name);
+        writeln!(html, "<h2>{name}</h2>")?;
+        let summary = match &result.outcome {
+            Outcome::Failed(diff) => format!(
+                "{} of {} pixels differ ({:.3}%), the largest difference is {:.3}",
+                diff.different,
+                diff.total,
+                diff.fraction() * 100.0,
+                diff.max_delta
+            ),
+            Outcome::Missing => "No reference image, approve the render to add one".to_owned(),
+            Outcome::Error(e) => e.clone(),
+            Outcome::Passed(_) | Outcome::Approved => unreachable!(),
+        };
+        writeln!(html, "<p>{}</p>", escape(&summary))?;
+
+        let mut images = Vec::new();
+        if result.case.reference.exists() {
+            let file = format!("{}.reference.png", result.case.name);
+            fs::copy(&result.case.reference, dir.join(&file))?;
+            images.push(("Reference", file));
+        }
+        if let Some(actual) = &result.actual {
+            let file = format!("{}.actual.png", result.case.name);
+            actual.save(&dir.join(&file))?;
+            images.push(("Render", file));
+        }
+        if let Outcome::Failed(diff) = &result.outcome {
+            let file = format!("{}.diff.png", result.case.name);
+            diff.image.save(&dir.join(&file))?;
+            images.push(("Difference", file));
+        }
+        html.push_str("<table>\n<tr>");
+        for (label, _) in &images {
+            write!(html, "<th>{label}</th>")?;
+        }
+        html.push_str("</tr>\n<tr>");
+        for (label, file) in &images {
+            write!(html, "<td><img src=\"{}\" alt=\"{label}\"></td>", escape(file))?;
+        }
+        html.push_str("</tr>\n</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+
+    let path = dir.join("index.html");
+    fs::write(&path, html).map_err(|e| eyre!("could not write {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
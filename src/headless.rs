@@ -0,0 +1,356 @@
+#[cfg(not(target_os = "macos"))]
+use std::ffi::CString;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ExecutorKind;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext};
+#[cfg(not(target_os = "macos"))]
+use glutin::api::egl::{context::PossiblyCurrentContext, device::Device, display::Display};
+#[cfg(not(target_os = "macos"))]
+use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+#[cfg(not(target_os = "macos"))]
+use glutin::context::{ContextApi, ContextAttributesBuilder, GlProfile, Version};
+#[cfg(not(target_os = "macos"))]
+use glutin::prelude::*;
+use tracing::{info, warn};
+
+use crate::asset_cache::DerivedCache;
+use crate::gl_debug::GlErrorChecks;
+use crate::gl_state::GlState;
+use crate::golden::{self, CaseResult, Image, Outcome, Tolerance};
+use crate::material::MaterialLoader;
+use crate::palette::Palette;
+use crate::profiler::Profiler;
+use crate::resources::{
+    Camera, DebugDraw, FixedTime, Lighting, ModelLoader, RenderSettings, RenderState,
+    ShaderGlobals, TextureLoader, Time, ViewBookmarks, Viewport,
+};
+use crate::scene::Scene;
+use crate::terrain::{self, TerrainMeshes};
+use crate::variables::SceneVariables;
+use crate::variants::{self, SceneVariants};
+use crate::{editor, image_export, renderer, systems, transform};
+
+/// Frame rate animations are stepped at when rendering several frames
+const FRAME_RATE: f32 = 30.0;
+
+/// What a headless run renders, and where the images are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadlessOptions {
+    pub scene: PathBuf,
+    /// Image file for a single frame, or directory for a numbered PNG sequence
+    pub output: PathBuf,
+    pub models_dir: PathBuf,
+    pub textures_dir: PathBuf,
+    pub materials_dir: PathBuf,
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Numbered camera view saved in the scene to render from, instead of the first one
+    pub view: Option<u8>,
+    /// Scene variant to render, instead of the base scene
+    pub variant: Option<String>,
+}
+
+/// Where the golden-image cases are, and whether their renders are checked or approved
+///
+/// Cases load models, textures and materials from the `models`, `textures` and `materials`
+/// folders next to them rather than from `res`, so changing the editor's assets does not change
+/// their renders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenOptions {
+    pub dir: PathBuf,
+    /// Save the renders as the new reference images instead of comparing them
+    pub approve: bool,
+    /// Directory the HTML diff report is written to when a case fails
+    pub report: PathBuf,
+    pub tolerance: Tolerance,
+}
+
+impl Default for GoldenOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from(golden::GOLDEN_DIR),
+            approve: false,
+            report: PathBuf::from(golden::REPORT_DIR),
+            tolerance: Tolerance::default(),
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+type SurfacelessContext = PossiblyCurrentContext;
+#[cfg(target_os = "macos")]
+type SurfacelessContext = ();
+
+/// OpenGL context without a window or display server, that renders scenes one after another
+///
+/// Renders with an EGL device, such as a GPU render node or Mesa's software renderer, so it also
+/// runs on CI machines.
+pub struct HeadlessRenderer {
+    gl: Arc<Context>,
+    _gl_context: SurfacelessContext,
+}
+
+impl HeadlessRenderer {
+    pub fn new() -> Result<Self> {
+        let (gl, gl_context) = create_surfaceless_context()?;
+        info!("rendering with {}", unsafe { gl.get_parameter_string(glow::RENDERER) });
+        Ok(Self { gl: Arc::new(gl), _gl_context: gl_context })
+    }
+
+    /// Load a scene and render its frames, passing the RGBA8 pixels of each frame to `frame`
+    ///
+    /// `options.output` is not used, writing the pixels is up to `frame`.
+    pub fn render<F>(&self, options: &HeadlessOptions, mut frame: F) -> Result<()>
+    where
+        F: FnMut(u32, &[u8]) -> Result<()>,
+    {
+        let gl = &self.gl;
+        let (width, height) = (options.width, options.height);
+        let mut world = World::new();
+        let mut model_loader =
+            ModelLoader::new().with_cache(DerivedCache::new(DerivedCache::DEFAULT_DIR));
+        model_loader.load_models_in_dir(gl, &options.models_dir);
+        model_loader.add_missing_builtins(gl);
+        let mut texture_loader = TextureLoader::new();
+        texture_loader.load_textures_in_dir(gl, &options.textures_dir);
+        let mut material_loader = MaterialLoader::new();
+        material_loader.load_materials_in_dir(&options.materials_dir);
+        world.insert_non_send_resource(gl.clone());
+        world.insert_resource(model_loader);
+        world.insert_resource(texture_loader);
+        world.insert_resource(material_loader);
+        world.insert_resource(Viewport::new(width, height));
+        world.init_resource::<Camera>();
+        world.init_resource::<Time>();
+        world.init_resource::<ShaderGlobals>();
+        world.init_resource::<Lighting>();
+        world.init_resource::<RenderSettings>();
+        world.init_resource::<Palette>();
+        world.init_resource::<DebugDraw>();
+        world.init_resource::<GlState>();
+        world.init_resource::<GlErrorChecks>();
+        world.init_resource::<Profiler>();
+        world.init_resource::<TerrainMeshes>();
+
+        let result = RenderState::new(gl, (width, height)).and_then(|render_state| {
+            world.insert_resource(render_state);
+            render_frames(&mut world, options, &mut frame)
+        });
+
+        // The context outlives this render, so everything it created is cleaned up here
+        for vao in world.resource_mut::<ModelLoader>().values_mut() {
+            unsafe {
+                vao.destroy(gl);
+            }
+        }
+        unsafe {
+            if let Some(mut render_state) = world.get_resource_mut::<RenderState>() {
+                render_state.destroy(gl);
+            }
+            world.resource_mut::<Profiler>().destroy(gl);
+            world.resource_mut::<TerrainMeshes>().destroy(gl);
+        }
+        result
+    }
+
+    /// Render every golden-image case in a directory, and compare each render with its reference
+    /// or save it as the new reference
+    pub fn render_golden(&self, options: &GoldenOptions) -> Result<Vec<CaseResult>> {
+        let mut results = Vec::new();
+        for case in golden::cases(&options.dir)? {
+            let headless_options = HeadlessOptions {
+                scene: case.scene.clone(),
+                output: PathBuf::new(),
+                models_dir: options.dir.join("models"),
+                textures_dir: options.dir.join("textures"),
+                materials_dir: options.dir.join("materials"),
+                frames: 1,
+                width: golden::WIDTH,
+                height: golden::HEIGHT,
+                view: None,
+                variant: None,
+            };
+            let mut pixels = Vec::new();
+            let rendered = self.render(&headless_options, |_, frame| {
+                pixels = frame.to_vec();
+                Ok(())
+            });
+            let result = match rendered {
+                Ok(()) => {
+                    let actual = Image::new(golden::WIDTH, golden::HEIGHT, pixels);
+                    golden::check(&case, actual, &options.tolerance, options.approve)
+                }
+                Err(e) => CaseResult { case, outcome: Outcome::Error(e.to_string()), actual: None },
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
+
+/// Load a scene and render it to image files, without a window or display server
+pub fn render_headless(options: &HeadlessOptions) -> Result<()> {
+    editor::init_tracing(None)?;
+
+    let renderer = HeadlessRenderer::new()?;
+    if options.frames > 1 {
+        fs::create_dir_all(&options.output)?;
+    }
+    renderer.render(options, |frame, pixels| {
+        let path = if options.frames > 1 {
+            options.output.join(format!("frame_{frame:04}.png"))
+        } else {
+            options.output.clone()
+        };
+        image_export::save_image(&path, options.width, options.height, pixels)?;
+        info!("wrote {}", path.display());
+        Ok(())
+    })
+}
+
+/// Check the golden-image cases, printing a line per case, and write the HTML diff report if any
+/// of them fail
+pub fn check_golden(options: &GoldenOptions) -> Result<()> {
+    editor::init_tracing(None)?;
+
+    let results = HeadlessRenderer::new()?.render_golden(options)?;
+    if results.is_empty() {
+        warn!("found no golden-image cases in {}", options.dir.display());
+    }
+    for result in &results {
+        let status = match &result.outcome {
+            Outcome::Passed(_) => "ok".to_owned(),
+            Outcome::Approved => "approved".to_owned(),
+            Outcome::Failed(diff) => {
+                format!("FAILED, {:.3}% of the pixels differ", diff.fraction() * 100.0)
+            }
+            Outcome::Missing => "FAILED, no reference image".to_owned(),
+            Outcome::Error(e) => format!("FAILED, {e}"),
+        };
+        println!("{} ... {status}", result.case.name);
+    }
+
+    let failed = results.iter().filter(|r| !r.outcome.is_ok()).count();
+    if failed == 0 {
+        return Ok(());
+    }
+    let report = golden::write_report(&results, &options.report)?;
+    Err(eyre!("{failed} of {} golden-image cases failed, see {}", results.len(), report.display()))
+}
+
+/// Step the scene's animations and render each frame
+fn render_frames<F>(world: &mut World, options: &HeadlessOptions, frame: &mut F) -> Result<()>
+where
+    F: FnMut(u32, &[u8]) -> Result<()>,
+{
+    let scene = Scene::load(&options.scene)?;
+    let spawned = scene.spawn(world)?;
+    world.resource_mut::<ShaderGlobals>().params = scene.shader_params;
+    world.insert_resource(SceneVariables { values: scene.variables });
+    world.insert_resource(SceneVariants::from_scene(&scene.variants, &spawned));
+    if let Some(lighting) = scene.lighting {
+        world.insert_resource(lighting);
+    }
+    world.insert_resource(scene.render_settings);
+    if let Some(name) = &options.variant {
+        if !scene.variants.contains_key(name) {
+            return Err(eyre!("the scene has no variant {name:?}"));
+        }
+        variants::switch(world, Some(name));
+    }
+    let pose = match options.view {
+        Some(slot) => Some(
+            *scene.views.get(&slot).ok_or_else(|| eyre!("the scene has no camera view {slot}"))?,
+        ),
+        None => scene.views.values().next().copied(),
+    };
+    if let Some(pose) = pose {
+        world.resource_mut::<Camera>().set_pose(pose);
+    }
+    world.insert_resource(ViewBookmarks { views: scene.views });
+
+    // One simulation step is taken per frame, and animated entities are drawn where the step left
+    // them instead of between steps
+    let mut simulation_schedule = Schedule::default();
+    simulation_schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    simulation_schedule.add_systems(
+        (systems::animate_transforms, systems::play_animation_clips, systems::update_skeletons)
+            .chain(),
+    );
+
+    let mut schedule = Schedule::default();
+    schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    schedule.add_systems(
+        (
+            systems::update_shader_globals,
+            systems::apply_variables,
+            transform::update_global_transforms,
+            terrain::update_terrain_meshes,
+            systems::apply_render_settings,
+            renderer::render,
+            renderer::store_previous_transforms,
+        )
+            .chain(),
+    );
+
+    let (width, height) = (options.width, options.height);
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let step = Duration::from_secs_f32(1.0 / FRAME_RATE);
+    world.insert_resource(FixedTime::new(step));
+    for i in 0..options.frames {
+        if i > 0 {
+            world.resource_mut::<Time>().step(step);
+            simulation_schedule.run(world);
+        }
+        schedule.run(world);
+
+        let pixels =
+            renderer::read_scene_color(&gl, world.resource::<RenderState>(), width, height);
+        frame(i, &pixels)?;
+    }
+    Ok(())
+}
+
+/// Create an OpenGL context that is current without any surface, on the first EGL device
+#[cfg(not(target_os = "macos"))]
+fn create_surfaceless_context() -> Result<(Context, PossiblyCurrentContext)> {
+    let device = Device::query_devices()?
+        .next()
+        .ok_or_else(|| eyre!("found no EGL device to render with"))?;
+    let display = unsafe { Display::with_device(&device, None)? };
+
+    let template = ConfigTemplateBuilder::new()
+        .with_stencil_size(8)
+        .with_surface_type(ConfigSurfaceTypes::empty())
+        .build();
+    let config = unsafe { display.find_configs(template)? }
+        .next()
+        .ok_or_else(|| eyre!("found no EGL config for rendering without a surface"))?;
+    let context_attributes = ContextAttributesBuilder::new()
+        .with_profile(GlProfile::Core)
+        .with_context_api(ContextApi::OpenGl(Some(Version::new(4, 1))))
+        .build(None);
+    let gl_context = unsafe { display.create_context(&config, &context_attributes)? }
+        .make_current_surfaceless()?;
+
+    let gl = unsafe {
+        Context::from_loader_function(|s| {
+            let s = CString::new(s).expect("failed to construct C string for gl proc address");
+            display.get_proc_address(&s)
+        })
+    };
+    Ok((gl, gl_context))
+}
+
+#[cfg(target_os = "macos")]
+fn create_surfaceless_context() -> Result<(Context, ())> {
+    Err(eyre!("headless rendering needs EGL, which is not available on macOS"))
+}
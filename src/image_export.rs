@@ -0,0 +1,63 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+
+/// Save RGBA8 pixels, starting with the top row, as PNG or OpenEXR depending on the extension
+pub fn save_image(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("png") => save_png(path, width, height, pixels),
+        Some("exr") => {
+            let pixels: Vec<f32> = pixels.iter().map(|&c| c as f32 / 255.0).collect();
+            save_exr(path, width, height, &pixels)
+        }
+        _ => Err(eyre!("unsupported image format for {}, use .png or .exr", path.display())),
+    }
+}
+
+/// Save linear RGBA float pixels, starting with the top row, as PNG or OpenEXR depending on the
+/// extension
+///
+/// OpenEXR keeps colors brighter than 1, while PNG clamps them to 8 bits.
+pub fn save_hdr_image(path: &Path, width: u32, height: u32, pixels: &[f32]) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    if extension.as_deref() == Some("exr") {
+        return save_exr(path, width, height, pixels);
+    }
+    let pixels: Vec<u8> =
+        pixels.iter().map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8).collect();
+    save_image(path, width, height, &pixels)
+}
+
+pub fn save_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    write_png(BufWriter::new(File::create(path)?), width, height, pixels)
+}
+
+/// Encode RGBA8 pixels, starting with the top row, as a PNG file in memory
+pub fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    write_png(&mut png, width, height, pixels)?;
+    Ok(png)
+}
+
+fn write_png(output: impl Write, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    let mut encoder = png::Encoder::new(output, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
+
+/// Save RGBA float pixels, starting with the top row, as 32-bit float channels
+pub fn save_exr(path: &Path, width: u32, height: u32, pixels: &[f32]) -> Result<()> {
+    let (width, height) = (width as usize, height as usize);
+    exr::prelude::write_rgba_file(path, width, height, |x, y| {
+        let i = (y * width + x) * 4;
+        (pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3])
+    })?;
+    Ok(())
+}
@@ -0,0 +1,445 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::{fmt, fs};
+
+use ahash::AHashMap;
+use bevy_ecs::system::Resource;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use toml_edit::{Document, Item, Table};
+use tracing::warn;
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::config;
+use crate::resources::{Input, VIEW_SLOTS};
+
+/// Where the input bindings were kept before they moved to the editor configuration file
+pub const LEGACY_INPUT_CONFIG_PATH: &str = "input.toml";
+
+/// Something the user can do with a key or mouse button
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveFast,
+    /// Hold to capture the cursor and look around
+    LookAround,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+    Select,
+    SelectNext,
+    SelectPrevious,
+    /// Move the camera so the selection fills the view
+    FocusSelected,
+    /// Move the camera so every object is in view
+    FrameAll,
+    /// Look along a world axis with an orthographic projection
+    ViewFront,
+    ViewRight,
+    ViewTop,
+    /// Switch between perspective and orthographic projection
+    ToggleOrthographic,
+    /// Switch between one view and perspective, top, front and right views
+    ToggleQuadView,
+    /// Move the selection by the position snapping step
+    NudgeLeft,
+    NudgeRight,
+    NudgeForward,
+    NudgeBackward,
+    NudgeUp,
+    NudgeDown,
+    SpawnObject,
+    /// Save the camera pose to a numbered view
+    SaveView(u8),
+    /// Fly the camera to a numbered view
+    RecallView(u8),
+    /// Open the command palette, to search for a command and run it
+    CommandPalette,
+    /// Show the key bindings over the editor
+    ShowShortcuts,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the Preferences window
+    pub fn all() -> impl Iterator<Item = Action> {
+        const FIXED: [Action; 29] = [
+            Action::MoveForward,
+            Action::MoveBackward,
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::MoveUp,
+            Action::MoveDown,
+            Action::MoveFast,
+            Action::LookAround,
+            Action::LookLeft,
+            Action::LookRight,
+            Action::LookUp,
+            Action::LookDown,
+            Action::Select,
+            Action::SelectNext,
+            Action::SelectPrevious,
+            Action::FocusSelected,
+            Action::FrameAll,
+            Action::ViewFront,
+            Action::ViewRight,
+            Action::ViewTop,
+            Action::ToggleOrthographic,
+            Action::ToggleQuadView,
+            Action::NudgeLeft,
+            Action::NudgeRight,
+            Action::NudgeForward,
+            Action::NudgeBackward,
+            Action::NudgeUp,
+            Action::NudgeDown,
+            Action::SpawnObject,
+        ];
+        let views = (1..=VIEW_SLOTS).flat_map(|n| [Action::SaveView(n), Action::RecallView(n)]);
+        let last = [Action::CommandPalette, Action::ShowShortcuts, Action::Quit];
+        FIXED.into_iter().chain(views).chain(last)
+    }
+
+    /// Name used in the config file
+    pub fn name(self) -> Cow<'static, str> {
+        let name = match self {
+            Action::SaveView(n) => return format!("save_view_{n}").into(),
+            Action::RecallView(n) => return format!("recall_view_{n}").into(),
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveFast => "move_fast",
+            Action::LookAround => "look_around",
+            Action::LookLeft => "look_left",
+            Action::LookRight => "look_right",
+            Action::LookUp => "look_up",
+            Action::LookDown => "look_down",
+            Action::Select => "select",
+            Action::SelectNext => "select_next",
+            Action::SelectPrevious => "select_previous",
+            Action::FocusSelected => "focus_selected",
+            Action::FrameAll => "frame_all",
+            Action::ViewFront => "view_front",
+            Action::ViewRight => "view_right",
+            Action::ViewTop => "view_top",
+            Action::ToggleOrthographic => "toggle_orthographic",
+            Action::ToggleQuadView => "toggle_quad_view",
+            Action::NudgeLeft => "nudge_left",
+            Action::NudgeRight => "nudge_right",
+            Action::NudgeForward => "nudge_forward",
+            Action::NudgeBackward => "nudge_backward",
+            Action::NudgeUp => "nudge_up",
+            Action::NudgeDown => "nudge_down",
+            Action::SpawnObject => "spawn_object",
+            Action::CommandPalette => "command_palette",
+            Action::ShowShortcuts => "show_shortcuts",
+            Action::Quit => "quit",
+        };
+        name.into()
+    }
+
+    pub fn label(self) -> Cow<'static, str> {
+        let label = match self {
+            Action::SaveView(n) => return format!("Save view {n}").into(),
+            Action::RecallView(n) => return format!("Go to view {n}").into(),
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveFast => "Move faster",
+            Action::LookAround => "Look around",
+            Action::LookLeft => "Look left",
+            Action::LookRight => "Look right",
+            Action::LookUp => "Look up",
+            Action::LookDown => "Look down",
+            Action::Select => "Select object",
+            Action::SelectNext => "Select next object",
+            Action::SelectPrevious => "Select previous object",
+            Action::FocusSelected => "Focus on selection",
+            Action::FrameAll => "Frame all objects",
+            Action::ViewFront => "Front view (along -Z)",
+            Action::ViewRight => "Right view (along -X)",
+            Action::ViewTop => "Top view (along -Y)",
+            Action::ToggleOrthographic => "Toggle orthographic",
+            Action::ToggleQuadView => "Toggle quad view",
+            Action::NudgeLeft => "Nudge selection left (-X)",
+            Action::NudgeRight => "Nudge selection right (+X)",
+            Action::NudgeForward => "Nudge selection forward (-Z)",
+            Action::NudgeBackward => "Nudge selection backward (+Z)",
+            Action::NudgeUp => "Nudge selection up (+Y)",
+            Action::NudgeDown => "Nudge selection down (-Y)",
+            Action::SpawnObject => "Spawn object",
+            Action::CommandPalette => "Command palette",
+            Action::ShowShortcuts => "Keyboard shortcuts",
+            Action::Quit => "Quit",
+        };
+        label.into()
+    }
+
+    fn default_binding(self) -> Binding {
+        match self {
+            Action::MoveForward => Binding::Key(VirtualKeyCode::W),
+            Action::MoveBackward => Binding::Key(VirtualKeyCode::S),
+            Action::MoveLeft => Binding::Key(VirtualKeyCode::A),
+            Action::MoveRight => Binding::Key(VirtualKeyCode::D),
+            Action::MoveUp => Binding::Key(VirtualKeyCode::Space),
+            Action::MoveDown => Binding::Key(VirtualKeyCode::LControl),
+            Action::MoveFast => Binding::Key(VirtualKeyCode::LShift),
+            Action::LookAround => Binding::Mouse(MouseButton::Right),
+            Action::LookLeft => Binding::Key(VirtualKeyCode::J),
+            Action::LookRight => Binding::Key(VirtualKeyCode::L),
+            Action::LookUp => Binding::Key(VirtualKeyCode::I),
+            Action::LookDown => Binding::Key(VirtualKeyCode::K),
+            Action::Select => Binding::Mouse(MouseButton::Left),
+            Action::SelectNext => Binding::Key(VirtualKeyCode::RBracket),
+            Action::SelectPrevious => Binding::Key(VirtualKeyCode::LBracket),
+            Action::FocusSelected => Binding::Key(VirtualKeyCode::F),
+            Action::FrameAll => Binding::ShiftKey(VirtualKeyCode::A),
+            Action::ViewFront => Binding::Key(VirtualKeyCode::Numpad1),
+            Action::ViewRight => Binding::Key(VirtualKeyCode::Numpad3),
+            Action::ViewTop => Binding::Key(VirtualKeyCode::Numpad7),
+            Action::ToggleOrthographic => Binding::Key(VirtualKeyCode::Numpad5),
+            Action::ToggleQuadView => Binding::CtrlKey(VirtualKeyCode::Q),
+            Action::NudgeLeft => Binding::Key(VirtualKeyCode::Left),
+            Action::NudgeRight => Binding::Key(VirtualKeyCode::Right),
+            Action::NudgeForward => Binding::Key(VirtualKeyCode::Up),
+            Action::NudgeBackward => Binding::Key(VirtualKeyCode::Down),
+            Action::NudgeUp => Binding::Key(VirtualKeyCode::PageUp),
+            Action::NudgeDown => Binding::Key(VirtualKeyCode::PageDown),
+            Action::SpawnObject => Binding::Key(VirtualKeyCode::E),
+            Action::SaveView(n) => Binding::CtrlKey(digit_key(n)),
+            Action::RecallView(n) => Binding::Key(digit_key(n)),
+            Action::CommandPalette => Binding::CtrlShiftKey(VirtualKeyCode::P),
+            Action::ShowShortcuts => Binding::Key(VirtualKeyCode::F1),
+            Action::Quit => Binding::Key(VirtualKeyCode::Escape),
+        }
+    }
+}
+
+fn digit_key(n: u8) -> VirtualKeyCode {
+    use VirtualKeyCode::*;
+    [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9][n as usize % 10]
+}
+
+/// A key or mouse button bound to an action
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    /// A key pressed while either Shift key is held
+    ShiftKey(VirtualKeyCode),
+    /// A key pressed while either Control key is held
+    CtrlKey(VirtualKeyCode),
+    /// A key pressed while a Control key and a Shift key are held
+    CtrlShiftKey(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// Keys that can be named in the config file
+#[rustfmt::skip]
+const KEYS: &[VirtualKeyCode] = {
+    use VirtualKeyCode::*;
+    &[
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+        Escape, Tab, Space, Return, Back, Delete, Insert, Home, End, PageUp, PageDown,
+        Left, Right, Up, Down, LShift, RShift, LControl, RControl, LAlt, RAlt,
+        Grave, Minus, Equals, LBracket, RBracket, Semicolon, Apostrophe, Comma, Period, Slash,
+        Backslash,
+    ]
+};
+
+impl Binding {
+    pub fn parse(s: &str) -> Option<Self> {
+        let mouse = match s {
+            "MouseLeft" => Some(MouseButton::Left),
+            "MouseRight" => Some(MouseButton::Right),
+            "MouseMiddle" => Some(MouseButton::Middle),
+            _ => s.strip_prefix("Mouse").and_then(|n| n.parse().ok()).map(MouseButton::Other),
+        };
+        if let Some(button) = mouse {
+            return Some(Binding::Mouse(button));
+        }
+
+        let find_key = |s: &str| KEYS.iter().find(|key| format!("{key:?}") == s).copied();
+        if let Some(key) = s.strip_prefix("Ctrl+Shift+") {
+            find_key(key).map(Binding::CtrlShiftKey)
+        } else if let Some(key) = s.strip_prefix("Shift+") {
+            find_key(key).map(Binding::ShiftKey)
+        } else if let Some(key) = s.strip_prefix("Ctrl+") {
+            find_key(key).map(Binding::CtrlKey)
+        } else {
+            find_key(s).map(Binding::Key)
+        }
+    }
+
+    /// Whether the key or button was pressed this frame, with its modifier held
+    pub fn pressed(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.get_key_press(key),
+            Binding::ShiftKey(key) => input.shift_held() && input.get_key_press(key),
+            Binding::CtrlKey(key) => input.ctrl_held() && input.get_key_press(key),
+            Binding::CtrlShiftKey(key) => {
+                input.ctrl_held() && input.shift_held() && input.get_key_press(key)
+            }
+            Binding::Mouse(button) => input.get_mouse_button_press(button),
+        }
+    }
+
+    /// Whether the key or button was pressed this frame, or the key was repeated while held
+    pub fn repeated(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.get_key_repeat(key),
+            Binding::ShiftKey(key) => input.shift_held() && input.get_key_repeat(key),
+            Binding::CtrlKey(key) => input.ctrl_held() && input.get_key_repeat(key),
+            Binding::CtrlShiftKey(key) => {
+                input.ctrl_held() && input.shift_held() && input.get_key_repeat(key)
+            }
+            Binding::Mouse(button) => input.get_mouse_button_press(button),
+        }
+    }
+
+    /// Whether the key or button was let go of this frame
+    ///
+    /// The modifier does not have to be held any more, as it is often let go first.
+    pub fn released(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key)
+            | Binding::ShiftKey(key)
+            | Binding::CtrlKey(key)
+            | Binding::CtrlShiftKey(key) => input.get_key_release(key),
+            Binding::Mouse(button) => input.get_mouse_button_release(button),
+        }
+    }
+
+    /// Whether the key or button is held down, with its modifier
+    pub fn held(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.get_key_press_continuous(key),
+            Binding::ShiftKey(key) => input.shift_held() && input.get_key_press_continuous(key),
+            Binding::CtrlKey(key) => input.ctrl_held() && input.get_key_press_continuous(key),
+            Binding::CtrlShiftKey(key) => {
+                input.ctrl_held() && input.shift_held() && input.get_key_press_continuous(key)
+            }
+            Binding::Mouse(button) => input.get_mouse_button_press_continuous(button),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::ShiftKey(key) => write!(f, "Shift+{key:?}"),
+            Binding::CtrlKey(key) => write!(f, "Ctrl+{key:?}"),
+            Binding::CtrlShiftKey(key) => write!(f, "Ctrl+Shift+{key:?}"),
+            Binding::Mouse(MouseButton::Left) => write!(f, "MouseLeft"),
+            Binding::Mouse(MouseButton::Right) => write!(f, "MouseRight"),
+            Binding::Mouse(MouseButton::Middle) => write!(f, "MouseMiddle"),
+            Binding::Mouse(MouseButton::Other(n)) => write!(f, "Mouse{n}"),
+        }
+    }
+}
+
+/// Keys and mouse buttons bound to each action
+#[derive(Resource, Clone)]
+pub struct InputMap {
+    bindings: AHashMap<Action, Binding>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self { bindings: Action::all().map(|a| (a, a.default_binding())).collect() }
+    }
+}
+
+impl InputMap {
+    /// Load bindings from a TOML file, using the defaults for actions that are not in it
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        let document: Document =
+            source.parse().map_err(|e| eyre!("could not parse {}: {e}", path.display()))?;
+
+        let mut map = Self::default();
+        let Some(bindings) = document.get("bindings").and_then(Item::as_table_like) else {
+            return Ok(map);
+        };
+        for (name, item) in bindings.iter() {
+            let Some(action) = Action::all().find(|a| a.name() == name) else {
+                warn!("unknown action {:?} in {}", name, path.display());
+                continue;
+            };
+            match item.as_str().and_then(Binding::parse) {
+                Some(binding) => map.set(action, binding),
+                None => warn!("invalid binding for {:?} in {}", name, path.display()),
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Load bindings from a TOML file, falling back to the defaults if it is missing or invalid
+    pub fn load_or_default(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load(path).unwrap_or_else(|e| {
+            warn!("could not load input bindings, using defaults: {e}");
+            Self::default()
+        })
+    }
+
+    /// Write the bindings to the `bindings` table of a TOML file, keeping the rest of the file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut bindings = Table::new();
+        for action in Action::all() {
+            bindings[&*action.name()] = toml_edit::value(self.get(action).to_string());
+        }
+
+        config::update_document(path.as_ref(), |document| {
+            document["bindings"] = Item::Table(bindings);
+        })
+    }
+
+    pub fn get(&self, action: Action) -> Binding {
+        self.bindings.get(&action).copied().unwrap_or_else(|| action.default_binding())
+    }
+
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        self.bindings.insert(action, binding);
+    }
+
+    /// Whether the binding of the action was pressed this frame, or the action was run from the
+    /// command palette
+    pub fn pressed(&self, input: &Input, action: Action) -> bool {
+        input.triggered(action) || self.get(action).pressed(input)
+    }
+
+    /// Whether the binding of the action was pressed this frame, or its key was repeated while held
+    pub fn repeated(&self, input: &Input, action: Action) -> bool {
+        input.triggered(action) || self.get(action).repeated(input)
+    }
+
+    /// Whether the binding of the action was let go of this frame
+    pub fn released(&self, input: &Input, action: Action) -> bool {
+        self.get(action).released(input)
+    }
+
+    /// Whether the binding of the action is held down
+    pub fn held(&self, input: &Input, action: Action) -> bool {
+        self.get(action).held(input)
+    }
+}
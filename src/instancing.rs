@@ -0,0 +1,101 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use nalgebra_glm as glm;
+
+use crate::bounds::Aabb;
+use crate::components::Properties;
+use crate::mesh_cleanup::MeshData;
+
+/// Grid that relative positions, normals and texture coordinates are rounded to before hashing
+const HASH_PRECISION: f32 = 1e-3;
+
+/// How far apart matching vertices of two instances may be, relative to the size of the mesh
+const POSITION_TOLERANCE: f32 = 1e-5;
+
+/// How far apart matching normals and texture coordinates of two instances may be
+const ATTRIBUTE_TOLERANCE: f32 = 1e-4;
+
+/// What importing a model file found, and how much was shared between its meshes
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Meshes in the file
+    pub meshes: usize,
+    /// Meshes that were the same as another one, up to a translation, and share its model
+    pub instances: usize,
+    /// Vertex and index memory that was not uploaded thanks to the instances
+    pub bytes_saved: usize,
+}
+
+impl ImportReport {
+    pub fn add(&mut self, other: ImportReport) {
+        self.meshes += other.meshes;
+        self.instances += other.instances;
+        self.bytes_saved += other.bytes_saved;
+    }
+}
+
+/// A mesh of an imported file, as a model and where it was placed in the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedPart {
+    pub model: String,
+    /// Translation from the shared model to where the mesh was
+    pub offset: glm::Vec3,
+    /// Properties from the `extras` of the glTF node the mesh was in
+    pub properties: Properties,
+}
+
+/// Memory the mesh takes on the GPU, without tangents
+pub fn gpu_size(mesh: &MeshData) -> usize {
+    let vertex_size = std::mem::size_of::<glm::Vec3>() * 2 + std::mem::size_of::<glm::Vec2>();
+    mesh.vertex_count() * vertex_size + mesh.indices.len() * std::mem::size_of::<u32>()
+}
+
+/// Hash of the geometry of a mesh that is the same wherever the mesh is placed
+///
+/// Meshes with different hashes are never the same, while meshes with the same hash still have to
+/// be compared with [`instance_offset`].
+pub fn geometry_hash(mesh: &MeshData) -> u64 {
+    let origin = Aabb::from_points(&mesh.positions).min;
+    let quantize = |x: f32| (x / HASH_PRECISION).round() as i64;
+
+    let mut hasher = DefaultHasher::new();
+    mesh.vertex_count().hash(&mut hasher);
+    mesh.indices.hash(&mut hasher);
+    for ((position, normal), uv) in
+        mesh.positions.iter().zip(&mesh.normals).zip(&mesh.texture_coords)
+    {
+        (position - origin).map(quantize).as_slice().hash(&mut hasher);
+        normal.map(quantize).as_slice().hash(&mut hasher);
+        uv.map(quantize).as_slice().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Translation that moves `model` onto `mesh`, if they are the same mesh apart from where they are
+pub fn instance_offset(model: &MeshData, mesh: &MeshData) -> Option<glm::Vec3> {
+    if model.vertex_count() != mesh.vertex_count() || model.indices != mesh.indices {
+        return None;
+    }
+    let (model_bounds, mesh_bounds) =
+        (Aabb::from_points(&model.positions), Aabb::from_points(&mesh.positions));
+    let offset = mesh_bounds.min - model_bounds.min;
+    let size = (model_bounds.max - model_bounds.min).max().max(1.0);
+
+    let positions_match = model
+        .positions
+        .iter()
+        .zip(&mesh.positions)
+        .all(|(a, b)| glm::distance(&(a + offset), b) <= POSITION_TOLERANCE * size);
+    let normals_match = model
+        .normals
+        .iter()
+        .zip(&mesh.normals)
+        .all(|(a, b)| glm::distance(a, b) <= ATTRIBUTE_TOLERANCE);
+    let texture_coords_match = model
+        .texture_coords
+        .iter()
+        .zip(&mesh.texture_coords)
+        .all(|(a, b)| glm::distance(a, b) <= ATTRIBUTE_TOLERANCE);
+    (positions_match && normals_match && texture_coords_match).then_some(offset)
+}
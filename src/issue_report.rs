@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use color_eyre::Result;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::image_export;
+
+/// Lines of log output kept for issue reports
+const LOG_LINES: usize = 1000;
+
+static RECENT_LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Writes log output to stdout, and keeps the latest lines for issue reports
+///
+/// Used as the writer of the tracing subscriber, which writes each event in one go.
+#[derive(Debug, Default)]
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = io::stdout().write(buf)?;
+        let text = strip_ansi(&String::from_utf8_lossy(&buf[..written]));
+        if let Ok(mut logs) = RECENT_LOGS.lock() {
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                if logs.len() == LOG_LINES {
+                    logs.pop_front();
+                }
+                logs.push_back(line.to_owned());
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// The latest lines of log output, oldest first
+pub fn recent_logs() -> Vec<String> {
+    RECENT_LOGS.lock().map(|logs| logs.iter().cloned().collect()).unwrap_or_default()
+}
+
+/// Remove the escape sequences that color terminal output
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Sequences end with a letter, such as the `m` of `\x1b[2m`
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Everything needed to look into a problem someone ran into, written to a zip file
+#[derive(Debug, Clone, Default)]
+pub struct IssueReport {
+    /// Editor version, system, graphics driver, frame times and asset errors
+    pub diagnostics: String,
+    /// Lighting, views and debug settings the scene was rendered with
+    pub render_settings: String,
+    pub logs: Vec<String>,
+    /// Width, height and RGBA8 pixels of the scene view, starting with the top row
+    pub screenshot: Option<(u32, u32, Vec<u8>)>,
+    /// The scene in the format it is saved in
+    pub scene: Option<String>,
+}
+
+impl IssueReport {
+    /// Names of the files in the zip, in the order they are written
+    pub fn file_names(&self) -> Vec<&'static str> {
+        let mut names = vec!["diagnostics.txt", "render_settings.txt", "log.txt"];
+        if self.screenshot.is_some() {
+            names.push("screenshot.png");
+        }
+        if self.scene.is_some() {
+            names.push("scene.ron");
+        }
+        names
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file("diagnostics.txt", options)?;
+        zip.write_all(self.diagnostics.as_bytes())?;
+        zip.start_file("render_settings.txt", options)?;
+        zip.write_all(self.render_settings.as_bytes())?;
+        zip.start_file("log.txt", options)?;
+        for line in &self.logs {
+            writeln!(zip, "{line}")?;
+        }
+        if let Some((width, height, pixels)) = &self.screenshot {
+            // PNG is compressed already
+            let stored = options.compression_method(CompressionMethod::Stored);
+            zip.start_file("screenshot.png", stored)?;
+            zip.write_all(&image_export::encode_png(*width, *height, pixels)?)?;
+        }
+        if let Some(scene) = &self.scene {
+            zip.start_file("scene.ron", options)?;
+            zip.write_all(scene.as_bytes())?;
+        }
+
+        zip.finish()?.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+//! Sorting point lights into clusters of the view, so each pixel only adds up the lights that can
+//! reach it
+//!
+//! The view is split into columns and rows on screen, and into slices by depth. For perspective
+//! views the slices get deeper further from the camera, as clusters further away cover more of
+//! the scene, while orthographic views are split into slices of the same depth. Each light is added to the clusters its range overlaps, and the
+//! lighting pass looks the lights up by the cluster of the pixel.
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext, Texture};
+use nalgebra_glm as glm;
+
+use crate::bounds::Aabb;
+use crate::components::{PointLight, Position};
+use crate::gl_state::GlState;
+use crate::resources::{Camera, ProjectionMode};
+
+/// Columns, rows and depth slices the view is split into
+pub const CLUSTERS: [u32; 3] = [16, 9, 24];
+
+/// Width of the texture the light indices of the clusters are listed in
+pub const INDEX_ROW: u32 = 4096;
+
+/// Texels of each light in the light texture
+const LIGHT_TEXELS: i32 = 4;
+
+/// Depth slice of a distance in front of a camera with the given projection
+pub fn depth_slice(depth: f32, mode: ProjectionMode) -> u32 {
+    let (near, far) = (Camera::NEAR, Camera::FAR);
+    let t = match mode {
+        ProjectionMode::Perspective => (depth.max(near) / near).ln() / (far / near).ln(),
+        ProjectionMode::Orthographic => (depth - near).max(0.0) / (far - near),
+    };
+    ((t * CLUSTERS[2] as f32) as u32).min(CLUSTERS[2] - 1)
+}
+
+/// The lights of each cluster of a view, as ranges of one list of light indices
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LightClusters {
+    /// Start in `indices` and number of lights of each cluster, by column, then row, then slice
+    pub clusters: Vec<[u32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl LightClusters {
+    /// Sort lights, given as their position and range, into the clusters of a view
+    ///
+    /// Lights without a range reach every cluster.
+    pub fn assign(
+        lights: &[(glm::Vec3, Option<f32>)],
+        view: &glm::Mat4,
+        projection: &glm::Mat4,
+        mode: ProjectionMode,
+    ) -> Self {
+        let [columns, rows, slices] = CLUSTERS;
+        let mut lists = vec![Vec::new(); (columns * rows * slices) as usize];
+        let view_projection = projection * view;
+        for (i, &(position, range)) in lights.iter().enumerate() {
+            let bounds = cluster_bounds(position, range, view, &view_projection, mode);
+            let Some((min, max)) = bounds else {
+                continue;
+            };
+            for slice in min[2]..=max[2] {
+                for row in min[1]..=max[1] {
+                    for column in min[0]..=max[0] {
+                        lists[Self::index(column, row, slice)].push(i as u32);
+                    }
+                }
+            }
+        }
+
+        let mut clusters = Self::default();
+        for list in lists {
+            clusters.clusters.push([clusters.indices.len() as u32, list.len() as u32]);
+            clusters.indices.extend(list);
+        }
+        clusters
+    }
+
+    /// Index of a cluster in `clusters`
+    pub fn index(column: u32, row: u32, slice: u32) -> usize {
+        let [columns, rows, _] = CLUSTERS;
+        ((slice * rows + row) * columns + column) as usize
+    }
+
+    /// Indices of the lights that can reach a cluster
+    pub fn lights(&self, column: u32, row: u32, slice: u32) -> &[u32] {
+        let [start, count] = self.clusters[Self::index(column, row, slice)];
+        &self.indices[start as usize..(start + count) as usize]
+    }
+}
+
+/// First and last cluster on each axis a light can reach, or `None` if it is out of view
+fn cluster_bounds(
+    position: glm::Vec3,
+    range: Option<f32>,
+    view: &glm::Mat4,
+    view_projection: &glm::Mat4,
+    mode: ProjectionMode,
+) -> Option<([u32; 3], [u32; 3])> {
+    let [columns, rows, slices] = CLUSTERS;
+    let Some(range) = range else {
+        return Some(([0, 0, 0], [columns - 1, rows - 1, slices - 1]));
+    };
+    let depth = -(view * position.push(1.0)).z;
+    if depth + range < Camera::NEAR || depth - range > Camera::FAR {
+        return None;
+    }
+
+    // Corners of the box around the range that are behind a perspective camera are left out of
+    // its rectangle on screen, so a light that reaches past the camera covers the whole view
+    let behind = depth - range * 3f32.sqrt() < Camera::NEAR;
+    let (min, max) = if behind && mode == ProjectionMode::Perspective {
+        (glm::vec2(-1.0, -1.0), glm::vec2(1.0, 1.0))
+    } else {
+        let extent = glm::Vec3::repeat(range);
+        Aabb::new(position - extent, position + extent).ndc_rect(view_projection)?
+    };
+    if min.x > 1.0 || min.y > 1.0 || max.x < -1.0 || max.y < -1.0 {
+        return None;
+    }
+
+    let cell = |ndc: f32, cells: u32| {
+        ((ndc + 1.0) / 2.0 * cells as f32).floor().clamp(0.0, cells as f32 - 1.0) as u32
+    };
+    Some((
+        [cell(min.x, columns), cell(min.y, rows), depth_slice(depth - range, mode)],
+        [cell(max.x, columns), cell(max.y, rows), depth_slice(depth + range, mode)],
+    ))
+}
+
+/// Textures the lighting pass reads the lights and their clusters from
+pub struct LightClusterTextures {
+    /// Four texels per light: its position and constant attenuation, then its ambient, diffuse
+    /// and specular colors with the linear and quadratic attenuation and zero
+    pub lights: Texture,
+    /// Start and count of the light indices of each cluster, with a row per depth slice
+    pub clusters: Texture,
+    /// Light indices of the clusters, in rows of [`INDEX_ROW`]
+    pub indices: Texture,
+}
+
+impl LightClusterTextures {
+    /// # Safety
+    ///
+    /// Leaves the last texture bound.
+    pub unsafe fn new(gl: &Context) -> Result<Self> {
+        let create = || -> Result<Texture> {
+            let texture =
+                gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            // Integer textures are only complete without mipmap filtering
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
+            Ok(texture)
+        };
+        Ok(Self { lights: create()?, clusters: create()?, indices: create()? })
+    }
+
+    /// Replace the contents of the textures with the lights and the clusters they were sorted
+    /// into, and bind the textures to `first_unit` and the two units after it
+    ///
+    /// # Safety
+    ///
+    /// Leaves the index texture's unit active.
+    pub unsafe fn upload(
+        &self,
+        gl: &Context,
+        gl_state: &mut GlState,
+        first_unit: u32,
+        lights: &[(&PointLight, &Position)],
+        clusters: &LightClusters,
+    ) {
+        let mut texels: Vec<f32> = Vec::with_capacity(lights.len().max(1) * 16);
+        for &(light, &position) in lights {
+            let position: glm::Vec3 = position.into();
+            texels.extend(position.iter().chain([&light.constant]));
+            texels.extend(light.ambient.iter().chain([&light.linear]));
+            texels.extend(light.diffuse.iter().chain([&light.quadratic]));
+            texels.extend(light.specular.iter().chain([&0.0]));
+        }
+        // Textures can not be empty
+        texels.resize(texels.len().max(16), 0.0);
+        let height = (texels.len() / 16) as i32;
+        gl_state.bind_texture(gl, first_unit, Some(self.lights));
+        gl_state.active_texture(gl, first_unit);
+        let (internal, format) = (glow::RGBA32F, glow::RGBA);
+        upload(gl, internal, format, glow::FLOAT, LIGHT_TEXELS, height, &texels);
+
+        let [columns, rows, slices] = CLUSTERS;
+        let (width, height) = ((columns * rows) as i32, slices as i32);
+        let data: &[u32] = bytemuck::cast_slice(&clusters.clusters);
+        gl_state.bind_texture(gl, first_unit + 1, Some(self.clusters));
+        gl_state.active_texture(gl, first_unit + 1);
+        let (internal, format) = (glow::RG32UI, glow::RG_INTEGER);
+        upload(gl, internal, format, glow::UNSIGNED_INT, width, height, data);
+
+        let mut indices = clusters.indices.clone();
+        let rows = ((indices.len() + INDEX_ROW as usize - 1) / INDEX_ROW as usize).max(1);
+        indices.resize(rows * INDEX_ROW as usize, 0);
+        let (width, height) = (INDEX_ROW as i32, rows as i32);
+        gl_state.bind_texture(gl, first_unit + 2, Some(self.indices));
+        gl_state.active_texture(gl, first_unit + 2);
+        let (internal, format) = (glow::R32UI, glow::RED_INTEGER);
+        upload(gl, internal, format, glow::UNSIGNED_INT, width, height, &indices);
+    }
+
+    /// # Safety
+    ///
+    /// The textures are no longer valid and should not be used.
+    pub unsafe fn destroy(&self, gl: &Context) {
+        for texture in [self.lights, self.clusters, self.indices] {
+            gl.delete_texture(texture);
+        }
+    }
+}
+
+/// Fill the texture bound to the active unit
+unsafe fn upload<T: bytemuck::Pod>(
+    gl: &Context,
+    internal_format: u32,
+    format: u32,
+    ty: u32,
+    width: i32,
+    height: i32,
+    data: &[T],
+) {
+    gl.tex_image_2d(
+        glow::TEXTURE_2D,
+        0,
+        internal_format as i32,
+        width,
+        height,
+        0,
+        format,
+        ty,
+        Some(bytemuck::cast_slice(data)),
+    );
+}
@@ -0,0 +1,155 @@
+//! Simpler versions of models, drawn in place of the model when it is far from the camera
+
+use ahash::{AHashMap, AHashSet};
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+
+use crate::bounds::Aabb;
+use crate::components::{Lod, Mesh};
+use crate::entity_pool::Pooled;
+use crate::mesh_cleanup::MeshData;
+use crate::resources::ModelLoader;
+
+/// Fraction of the triangles of the model kept in each generated level, from the most detailed
+pub const LEVEL_RATIOS: [f32; 2] = [0.5, 0.2];
+
+/// Camera distance each generated level is drawn from, unless the scene sets its own
+pub const DEFAULT_DISTANCES: [f32; 2] = [15.0, 40.0];
+
+/// Models with fewer triangles than this are cheap enough to always draw in full
+pub const MIN_TRIANGLES: usize = 64;
+
+/// Most cells along the longest side of the grid that vertices are merged in
+const MAX_CELLS: u32 = 1024;
+
+/// Name of a generated level of a model, where level 0 is the model itself
+pub fn level_name(model: &str, level: usize) -> String {
+    format!("{model}.lod{level}")
+}
+
+/// Whether a name has the form of [`level_name`], which only generated levels may use
+pub fn is_level_name(name: &str) -> bool {
+    name.rsplit_once(".lod").is_some_and(|(model, level)| {
+        !model.is_empty() && !level.is_empty() && level.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// Simplify a mesh to at most `ratio` of its triangles, by merging the vertices in each cell of
+/// a grid
+///
+/// The grid is made as fine as it can be while keeping under the triangle budget. Merged vertices
+/// are placed at their average and take the texture coordinates of the first one, so textures
+/// smear a little across UV seams, which is not noticeable from the distance levels are seen at.
+pub fn simplify(mesh: &MeshData, ratio: f32) -> MeshData {
+    let target = (mesh.triangle_count() as f32 * ratio.clamp(0.0, 1.0)).floor() as usize;
+    if mesh.positions.is_empty() || target >= mesh.triangle_count() {
+        return mesh.clone();
+    }
+
+    let aabb = Aabb::from_points(&mesh.positions);
+    let longest = aabb.size().max().max(f32::EPSILON);
+    let (mut low, mut high) = (1, MAX_CELLS);
+    let mut best = cluster(mesh, &aabb, longest);
+    while low <= high {
+        let cells = (low + high) / 2;
+        let clustered = cluster(mesh, &aabb, longest / cells as f32);
+        if clustered.triangle_count() <= target {
+            best = clustered;
+            low = cells + 1;
+        } else {
+            high = cells - 1;
+        }
+    }
+    best
+}
+
+/// Merge the vertices in each cube of `cell_size`, dropping the triangles that collapse
+fn cluster(mesh: &MeshData, aabb: &Aabb, cell_size: f32) -> MeshData {
+    let mut clustered = MeshData::default();
+    let mut cells: AHashMap<[i64; 3], u32> = AHashMap::new();
+    let mut counts = Vec::new();
+    let remap: Vec<u32> = (0..mesh.vertex_count())
+        .map(|i| {
+            let cell = ((mesh.positions[i] - aabb.min) / cell_size).map(|x| x.floor() as i64);
+            let index = *cells.entry(cell.into()).or_insert_with(|| {
+                clustered.positions.push(glm::Vec3::zeros());
+                clustered.normals.push(glm::Vec3::zeros());
+                clustered.texture_coords.push(mesh.texture_coords[i]);
+                counts.push(0.0);
+                (clustered.positions.len() - 1) as u32
+            });
+            clustered.positions[index as usize] += mesh.positions[i];
+            clustered.normals[index as usize] += mesh.normals[i];
+            counts[index as usize] += 1.0;
+            index
+        })
+        .collect();
+
+    for (position, count) in clustered.positions.iter_mut().zip(&counts) {
+        *position /= *count;
+    }
+    for normal in &mut clustered.normals {
+        *normal = normal.try_normalize(f32::EPSILON).unwrap_or_else(glm::Vec3::zeros);
+    }
+
+    let mut seen = AHashSet::new();
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| remap[triangle[i] as usize]);
+        if a == b || b == c || c == a {
+            continue;
+        }
+        // The same triangle can be left by several collapsed ones, starting at any corner
+        let first = a.min(b).min(c);
+        let rotated = if first == a {
+            [a, b, c]
+        } else if first == b {
+            [b, c, a]
+        } else {
+            [c, a, b]
+        };
+        if seen.insert(rotated) {
+            clustered.indices.extend(rotated);
+        }
+    }
+    clustered
+}
+
+/// Give entities whose model has generated levels a [`Lod`], and keep its meshes up to date when
+/// the model of the entity changes
+///
+/// Distances already set on the component, such as the ones a scene was saved with, are kept.
+/// When models are loaded or removed, the levels of every entity are looked up again, so none
+/// keeps drawing a level that was replaced.
+pub fn update_lods(
+    mut commands: Commands,
+    model_loader: Option<Res<ModelLoader>>,
+    mut query: Query<(Entity, &Mesh, Option<&mut Lod>), Without<Pooled>>,
+    changed: Query<(), Changed<Mesh>>,
+) {
+    let Some(model_loader) = model_loader else {
+        return;
+    };
+    let models_changed = model_loader.is_changed();
+    for (entity, mesh, lod) in &mut query {
+        if !models_changed && !changed.contains(entity) {
+            continue;
+        }
+        let meshes: Vec<Mesh> = model_loader
+            .name_of(mesh.vao_id)
+            .map(|name| model_loader.lods(name).map(Mesh::from).collect())
+            .unwrap_or_default();
+        match lod {
+            Some(mut lod) => {
+                lod.meshes = meshes;
+                if lod.forced.is_some_and(|level| level > lod.meshes.len()) {
+                    lod.forced = None;
+                }
+            }
+            None if !meshes.is_empty() => {
+                let distances = DEFAULT_DISTANCES.to_vec();
+                commands.entity(entity).insert(Lod { distances, meshes, forced: None });
+            }
+            None => {}
+        }
+    }
+}
@@ -1,9 +1,34 @@
+use clap::Parser;
 use color_eyre::eyre::Result;
+use scene_editor::bevy_export::{self, BevyExportOptions};
+use scene_editor::registry::ComponentRegistry;
+use scene_editor::scene::Scene;
+use scene_editor::{demo, Cli, Command};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
-    scene_editor::run()?;
+    let cli = Cli::parse();
+    if let Some(Command::GenerateDemo { dir }) = &cli.command {
+        let content = demo::generate(dir)?;
+        println!("wrote the demo to {}, open it with", dir.display());
+        println!("    scene-editor {}", content.args().join(" "));
+        return Ok(());
+    }
+    if let Some(Command::ExportBevy { scene, output, module }) = &cli.command {
+        let options = BevyExportOptions { module: module.clone() };
+        bevy_export::export(&Scene::load(scene)?, output, &options)?;
+        println!("wrote {}", output.display());
+        return Ok(());
+    }
+    if let Some(options) = cli.golden_options() {
+        return scene_editor::check_golden(&options);
+    }
+    if let Some(options) = cli.headless_options() {
+        return scene_editor::render_headless(&options);
+    }
+
+    scene_editor::run_with_options(cli.startup_options(), ComponentRegistry::default())?;
 
     Ok(())
 }
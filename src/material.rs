@@ -0,0 +1,284 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::Context;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::commands;
+use crate::components::{CustomShader, CustomTexture, ShaderParams};
+use crate::resources::{AssetError, TextureLoader};
+use crate::scene::ShaderSources;
+
+/// Extension of material files
+pub const MATERIAL_EXTENSION: &str = "material";
+
+/// Textures, custom shader and uniform values that entities can share, stored in a `.material` file
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Material {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diffuse: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub specular: Option<String>,
+    /// Tangent-space normal map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<String>,
+    /// Cut out the mostly transparent parts of the diffuse texture
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub alpha_test: bool,
+    /// Shader sources, with the values of their uniforms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_shader: Option<ShaderSources>,
+}
+
+impl Material {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| eyre!("could not read material {}: {e}", path.as_ref().display()))?;
+        Self::from_ron(&contents)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path.as_ref(), self.to_ron()?)
+            .map_err(|e| eyre!("could not write material {}: {e}", path.as_ref().display()))
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self> {
+        ron::from_str(s).map_err(|e| eyre!("could not parse material: {e}"))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| eyre!("could not serialize material: {e}"))
+    }
+
+    /// The textures, custom shader and uniform values an entity has now
+    ///
+    /// Requires the `TextureLoader` resource to look up texture names.
+    pub fn from_entity(world: &World, entity: Entity) -> Self {
+        let texture_loader = world.resource::<TextureLoader>();
+        let entity = world.entity(entity);
+        let texture = entity.get::<CustomTexture>().copied().unwrap_or_default();
+        let name = |texture: Option<glow::Texture>| {
+            texture.and_then(|t| texture_loader.name_of(t)).map(str::to_owned)
+        };
+        Self {
+            diffuse: name(texture.diffuse),
+            specular: name(texture.specular),
+            normal: name(texture.normal),
+            alpha_test: texture.alpha_test,
+            custom_shader: entity.get::<CustomShader>().map(|cs| ShaderSources {
+                vert: cs.vert_source.clone(),
+                frag: cs.frag_source.clone(),
+                params: entity.get::<ShaderParams>().map(|p| p.values.clone()).unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+/// Handle to a material in the `MaterialLoader`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialId(usize);
+
+/// The material an entity takes its textures, custom shader and uniform values from
+///
+/// Scenes store only the name of the material for these entities, so changes to the material file
+/// reach every scene using it.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MaterialHandle(pub MaterialId);
+
+/// Materials by name, loaded from `.material` files
+#[derive(Resource, Default)]
+pub struct MaterialLoader {
+    materials: Vec<(String, Material)>,
+    /// Folder the materials were loaded from, that new materials are saved to
+    dir: Option<PathBuf>,
+    errors: Vec<AssetError>,
+}
+
+impl MaterialLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every material in a directory, and save new materials to it
+    ///
+    /// Materials that can not be loaded are replaced by an empty material under the same name and
+    /// listed in [`errors`](Self::errors). A missing directory is not an error, as it is created
+    /// when the first material is saved.
+    pub fn load_materials_in_dir<P>(&mut self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.dir = Some(path.as_ref().to_owned());
+        let Ok(entries) = path.as_ref().read_dir() else {
+            return;
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == MATERIAL_EXTENSION))
+            .collect();
+        // Keep the handles in the same order between runs
+        paths.sort();
+        for path in paths {
+            if let Err(e) = self.load_material(&path) {
+                self.errors.push(AssetError::new(path.display(), e));
+                if let Some(name) = path.file_stem() {
+                    self.insert(&name.to_string_lossy(), Material::default());
+                }
+            }
+        }
+    }
+
+    /// Load a material file under the name of the file
+    pub fn load_material<P>(&mut self, path: P) -> Result<MaterialId>
+    where
+        P: AsRef<Path>,
+    {
+        let name = path
+            .as_ref()
+            .file_stem()
+            .ok_or_else(|| eyre!("{} has no file name", path.as_ref().display()))?
+            .to_string_lossy()
+            .into_owned();
+        let material = Material::load(path)?;
+        Ok(self.insert(&name, material))
+    }
+
+    /// Add a material, replacing the material with the same name and keeping its handle
+    pub fn insert(&mut self, name: &str, material: Material) -> MaterialId {
+        match self.id(name) {
+            Some(id) => {
+                self.materials[id.0].1 = material;
+                id
+            }
+            None => {
+                self.materials.push((name.to_owned(), material));
+                MaterialId(self.materials.len() - 1)
+            }
+        }
+    }
+
+    /// Write a material to the folder materials are loaded from and add it
+    pub fn save(&mut self, name: &str, material: Material) -> Result<MaterialId> {
+        let dir = self.dir.as_ref().ok_or_else(|| eyre!("no folder to save materials to"))?;
+        if name.is_empty() || name.contains(['/', '\\']) {
+            return Err(eyre!("{name:?} is not a valid material name"));
+        }
+        fs::create_dir_all(dir)
+            .map_err(|e| eyre!("could not create folder {}: {e}", dir.display()))?;
+        material.save(dir.join(format!("{name}.{MATERIAL_EXTENSION}")))?;
+        Ok(self.insert(name, material))
+    }
+
+    pub fn get(&self, id: MaterialId) -> Option<&Material> {
+        self.materials.get(id.0).map(|(_, material)| material)
+    }
+
+    /// The handle of a material by name
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.materials.iter().position(|(n, _)| n == name).map(MaterialId)
+    }
+
+    /// A material by name, or an empty material put in its place if it is not loaded
+    pub fn get_or_placeholder(&mut self, name: &str) -> MaterialId {
+        if let Some(id) = self.id(name) {
+            return id;
+        }
+        let message = "material not found, showing no textures or shader instead";
+        self.errors.push(AssetError::new(name, message));
+        self.insert(name, Material::default())
+    }
+
+    pub fn name_of(&self, id: MaterialId) -> Option<&str> {
+        self.materials.get(id.0).map(|(name, _)| name.as_str())
+    }
+
+    /// Handles and names of the materials, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (MaterialId, &str)> {
+        self.materials.iter().enumerate().map(|(i, (name, _))| (MaterialId(i), name.as_str()))
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Materials that could not be loaded, oldest first
+    pub fn errors(&self) -> &[AssetError] {
+        &self.errors
+    }
+
+    pub fn clear_errors(&mut self) {
+        self.errors.clear();
+    }
+}
+
+/// Give an entity the textures, custom shader and uniform values of a material
+pub fn apply(world: &mut World, entity: Entity, id: MaterialId) {
+    let Some(material) = world.resource::<MaterialLoader>().get(id).cloned() else {
+        warn!("material {id:?} does not exist");
+        return;
+    };
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let texture = {
+        let mut texture_loader = world.resource_mut::<TextureLoader>();
+        let mut lookup =
+            |name: &Option<String>| texture_loader.get_or_placeholder(&gl, name.as_ref()?);
+        CustomTexture {
+            diffuse: lookup(&material.diffuse),
+            specular: lookup(&material.specular),
+            normal: lookup(&material.normal),
+            alpha_test: material.alpha_test,
+        }
+    };
+
+    let mut entity_mut = world.entity_mut(entity);
+    let textured =
+        texture.diffuse.is_some() || texture.specular.is_some() || texture.normal.is_some();
+    if textured || texture.alpha_test {
+        entity_mut.insert(texture);
+    } else {
+        entity_mut.remove::<CustomTexture>();
+    }
+    entity_mut.insert(MaterialHandle(id));
+
+    let Some(sources) = material.custom_shader else {
+        commands::remove_custom_shader(entity, world);
+        return;
+    };
+    if !entity_mut.contains::<CustomShader>() {
+        entity_mut.insert(CustomShader::new(&gl));
+    }
+    if let Some(mut cs) = entity_mut.get_mut::<CustomShader>() {
+        cs.vert_source = sources.vert;
+        cs.frag_source = sources.frag;
+    }
+    entity_mut.insert(ShaderParams { values: sources.params });
+    commands::load_custom_shader(entity, world);
+}
+
+/// Save the textures, custom shader and uniform values of an entity as a material, and give the
+/// material to the entity and to every entity that already has it
+pub fn save_from_entity(world: &mut World, entity: Entity, name: &str) -> Result<MaterialId> {
+    let material = Material::from_entity(world, entity);
+    let id = world.get_resource_or_insert_with(MaterialLoader::default).save(name, material)?;
+    let mut users: Vec<_> = world
+        .query::<(Entity, &MaterialHandle)>()
+        .iter(world)
+        .filter(|(_, handle)| handle.0 == id)
+        .map(|(entity, _)| entity)
+        .collect();
+    if !users.contains(&entity) {
+        users.push(entity);
+    }
+    for user in users {
+        apply(world, user, id);
+    }
+    Ok(id)
+}
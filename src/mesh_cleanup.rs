@@ -0,0 +1,355 @@
+use ahash::AHashMap;
+use nalgebra_glm as glm;
+
+/// How far apart texture coordinates of welded vertices may be, so UV seams are kept
+const UV_TOLERANCE: f32 = 1e-4;
+
+/// Smallest cell of the grid used to find vertices to weld
+const MIN_CELL_SIZE: f32 = 1e-6;
+
+/// Triangles whose area is below this fraction of their longest edge squared are degenerate
+const DEGENERATE_RATIO: f32 = 1e-6;
+
+/// Vertices and triangles of a model, kept on the CPU so it can be cleaned up in the editor
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<glm::Vec3>,
+    pub normals: Vec<glm::Vec3>,
+    pub texture_coords: Vec<glm::Vec2>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// Create mesh data, filling in zeros for missing normals and texture coordinates
+    pub fn new(
+        positions: Vec<glm::Vec3>,
+        mut normals: Vec<glm::Vec3>,
+        mut texture_coords: Vec<glm::Vec2>,
+        indices: Vec<u32>,
+    ) -> Self {
+        normals.resize(positions.len(), glm::Vec3::zeros());
+        texture_coords.resize(positions.len(), glm::Vec2::zeros());
+        Self { positions, normals, texture_coords, indices }
+    }
+
+    /// Cube from -1 to 1 on every axis, with a face of its own on each side
+    pub fn cube() -> Self {
+        let mut cube = Self::default();
+        for axis in 0..3 {
+            for sign in [1.0, -1.0] {
+                let mut normal = glm::Vec3::zeros();
+                normal[axis] = sign;
+                let u = glm::Vec3::from_fn(|i, _| if i == (axis + 1) % 3 { 1.0 } else { 0.0 });
+                let v = normal.cross(&u);
+                cube.push_quad(normal, u, v);
+            }
+        }
+        cube
+    }
+
+    /// Square from -1 to 1 on the x and z axes, facing up
+    pub fn plane() -> Self {
+        let mut plane = Self::default();
+        plane.push_quad(glm::Vec3::y(), glm::Vec3::z(), glm::Vec3::x());
+        plane.positions.iter_mut().for_each(|p| p.y = 0.0);
+        plane
+    }
+
+    /// Sphere with a radius of 1, made of `segments` around and `rings` from pole to pole
+    pub fn sphere(segments: u32, rings: u32) -> Self {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        let mut sphere = Self::default();
+        for ring in 0..=rings {
+            let v = ring as f32 / rings as f32;
+            let (sin_theta, cos_theta) = (v * std::f32::consts::PI).sin_cos();
+            for segment in 0..=segments {
+                let u = segment as f32 / segments as f32;
+                let (sin_phi, cos_phi) = (u * std::f32::consts::TAU).sin_cos();
+                let normal = glm::vec3(sin_theta * cos_phi, cos_theta, -sin_theta * sin_phi);
+                sphere.positions.push(normal);
+                sphere.normals.push(normal);
+                sphere.texture_coords.push(glm::vec2(u, 1.0 - v));
+            }
+        }
+        let row = segments + 1;
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let (a, b) = (ring * row + segment, (ring + 1) * row + segment);
+                sphere.indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+            }
+        }
+        sphere
+    }
+
+    /// Torus around the y axis, with a radius of 1 to the middle of its tube
+    pub fn torus(segments: u32, sides: u32, tube_radius: f32) -> Self {
+        let (segments, sides) = (segments.max(3), sides.max(3));
+        let mut torus = Self::default();
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin_phi, cos_phi) = (u * std::f32::consts::TAU).sin_cos();
+            let center = glm::vec3(cos_phi, 0.0, -sin_phi);
+            for side in 0..=sides {
+                let v = side as f32 / sides as f32;
+                let (sin_theta, cos_theta) = (v * std::f32::consts::TAU).sin_cos();
+                let normal = center * cos_theta + glm::Vec3::y() * sin_theta;
+                torus.positions.push(center + normal * tube_radius);
+                torus.normals.push(normal);
+                torus.texture_coords.push(glm::vec2(u, v));
+            }
+        }
+        let row = sides + 1;
+        for segment in 0..segments {
+            for side in 0..sides {
+                let (a, b) = (segment * row + side, (segment + 1) * row + side);
+                torus.indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+            }
+        }
+        torus
+    }
+
+    /// Cylinder with a radius of 1 from -1 to 1 on the y axis, closed at both ends
+    pub fn cylinder(segments: u32) -> Self {
+        let segments = segments.max(3);
+        let mut cylinder = Self::default();
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin_phi, cos_phi) = (u * std::f32::consts::TAU).sin_cos();
+            let normal = glm::vec3(cos_phi, 0.0, -sin_phi);
+            for y in [-1.0, 1.0] {
+                cylinder.positions.push(normal + glm::Vec3::y() * y);
+                cylinder.normals.push(normal);
+                cylinder.texture_coords.push(glm::vec2(u, (y + 1.0) / 2.0));
+            }
+        }
+        for segment in 0..segments {
+            let (a, b) = (segment * 2, segment * 2 + 2);
+            cylinder.indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+        }
+
+        // Each cap is a fan around its center, with vertices of its own for the flat normal
+        for y in [-1.0f32, 1.0] {
+            let normal = glm::Vec3::y() * y;
+            let center = cylinder.positions.len() as u32;
+            cylinder.positions.push(normal);
+            cylinder.normals.push(normal);
+            cylinder.texture_coords.push(glm::vec2(0.5, 0.5));
+            for segment in 0..=segments {
+                let (sin_phi, cos_phi) =
+                    (segment as f32 / segments as f32 * std::f32::consts::TAU).sin_cos();
+                cylinder.positions.push(glm::vec3(cos_phi, y, -sin_phi));
+                cylinder.normals.push(normal);
+                cylinder.texture_coords.push(glm::vec2(cos_phi + 1.0, 1.0 - sin_phi) / 2.0);
+            }
+            for segment in 0..segments {
+                let (a, b) = (center + 1 + segment, center + 2 + segment);
+                // Counter-clockwise seen from outside, so the bottom cap is wound the other way
+                let triangle = if y > 0.0 { [center, a, b] } else { [center, b, a] };
+                cylinder.indices.extend(triangle);
+            }
+        }
+        cylinder
+    }
+
+    /// Add a square facing `normal`, spanned by the unit vectors `u` and `v`
+    fn push_quad(&mut self, normal: glm::Vec3, u: glm::Vec3, v: glm::Vec3) {
+        let first = self.positions.len() as u32;
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            self.positions.push(normal + u * x + v * y);
+            self.normals.push(normal);
+            self.texture_coords.push(glm::vec2((x + 1.0) / 2.0, (y + 1.0) / 2.0));
+        }
+        self.indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+    }
+
+    /// Normal of a triangle with a length of twice its area
+    fn face_normal(&self, [a, b, c]: [usize; 3]) -> glm::Vec3 {
+        let (a, b, c) = (self.positions[a], self.positions[b], self.positions[c]);
+        (b - a).cross(&(c - a))
+    }
+
+    fn push_vertex(&mut self, from: &MeshData, index: usize, normal: glm::Vec3) -> u32 {
+        self.positions.push(from.positions[index]);
+        self.normals.push(normal);
+        self.texture_coords.push(from.texture_coords[index]);
+        (self.positions.len() - 1) as u32
+    }
+}
+
+/// A cleanup operation that can be run on the mesh of an entity
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MeshCleanup {
+    /// Merge vertices closer than the tolerance
+    Weld { tolerance: f32 },
+    /// Replace the normals, smoothing across edges sharper than the angle in degrees
+    RecomputeNormals { smoothing_angle: f32 },
+    /// Remove triangles without area
+    RemoveDegenerateTriangles,
+}
+
+impl MeshCleanup {
+    pub fn apply(&self, mesh: &MeshData) -> MeshData {
+        match *self {
+            MeshCleanup::Weld { tolerance } => weld_vertices(mesh, tolerance),
+            MeshCleanup::RecomputeNormals { smoothing_angle } => {
+                recompute_normals(mesh, smoothing_angle)
+            }
+            MeshCleanup::RemoveDegenerateTriangles => remove_degenerate_triangles(mesh),
+        }
+    }
+
+    /// Added to the model name of the cleaned up mesh
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            MeshCleanup::Weld { .. } => "welded",
+            MeshCleanup::RecomputeNormals { .. } => "normals",
+            MeshCleanup::RemoveDegenerateTriangles => "cleaned",
+        }
+    }
+}
+
+/// Settings for the mesh cleanup tools in the inspector
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CleanupSettings {
+    pub weld_tolerance: f32,
+    pub smoothing_angle: f32,
+}
+
+impl Default for CleanupSettings {
+    fn default() -> Self {
+        Self { weld_tolerance: 1e-4, smoothing_angle: 30.0 }
+    }
+}
+
+/// Merge vertices that are closer than `tolerance` and have the same texture coordinates
+///
+/// The normals of merged vertices are averaged. Vertices on either side of a UV seam are kept
+/// apart, so textures still map the same way.
+pub fn weld_vertices(mesh: &MeshData, tolerance: f32) -> MeshData {
+    let tolerance = tolerance.max(0.0);
+    let cell_size = tolerance.max(MIN_CELL_SIZE);
+    let cell = |p: &glm::Vec3| (p / cell_size).map(|x| x.floor() as i64);
+
+    let mut welded = MeshData::default();
+    let mut grid: AHashMap<[i64; 3], Vec<u32>> = AHashMap::new();
+    let mut remap = Vec::with_capacity(mesh.vertex_count());
+    for i in 0..mesh.vertex_count() {
+        let (position, uv) = (mesh.positions[i], mesh.texture_coords[i]);
+        let center = cell(&position);
+
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let key = [center.x + dx, center.y + dy, center.z + dz];
+                    let Some(candidates) = grid.get(&key) else {
+                        continue;
+                    };
+                    found = candidates.iter().copied().find(|&j| {
+                        let j = j as usize;
+                        glm::distance(&welded.positions[j], &position) <= tolerance
+                            && glm::distance(&welded.texture_coords[j], &uv) <= UV_TOLERANCE
+                    });
+                    if found.is_some() {
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        let index = match found {
+            Some(j) => {
+                welded.normals[j as usize] += mesh.normals[i];
+                j
+            }
+            None => {
+                let j = welded.push_vertex(mesh, i, mesh.normals[i]);
+                grid.entry([center.x, center.y, center.z]).or_default().push(j);
+                j
+            }
+        };
+        remap.push(index);
+    }
+
+    for normal in &mut welded.normals {
+        *normal = normal.try_normalize(f32::EPSILON).unwrap_or_else(glm::Vec3::zeros);
+    }
+    welded.indices = mesh.indices.iter().map(|&i| remap[i as usize]).collect();
+    welded
+}
+
+/// Remove triangles that repeat a vertex or have no area, and the vertices left unused
+pub fn remove_degenerate_triangles(mesh: &MeshData) -> MeshData {
+    let mut cleaned = MeshData::default();
+    let mut remap: Vec<Option<u32>> = vec![None; mesh.vertex_count()];
+    for triangle in mesh.triangles() {
+        let [a, b, c] = triangle.map(|i| mesh.positions[i]);
+        let longest_edge =
+            glm::distance2(&a, &b).max(glm::distance2(&b, &c)).max(glm::distance2(&c, &a));
+        let area = mesh.face_normal(triangle).norm();
+        if area <= DEGENERATE_RATIO * longest_edge {
+            continue;
+        }
+
+        for i in triangle {
+            let index =
+                *remap[i].get_or_insert_with(|| cleaned.push_vertex(mesh, i, mesh.normals[i]));
+            cleaned.indices.push(index);
+        }
+    }
+    cleaned
+}
+
+/// Recompute the normals from the triangles, smoothing across edges where the faces meet at
+/// less than `smoothing_angle` degrees
+///
+/// An angle of 0 gives flat shading and 180 smooths every shared vertex. Triangles that meet at
+/// the same position are smoothed together even when they do not share a vertex, and vertices
+/// are split where their triangles need different normals.
+pub fn recompute_normals(mesh: &MeshData, smoothing_angle: f32) -> MeshData {
+    let face_normals: Vec<_> = mesh.triangles().map(|t| mesh.face_normal(t)).collect();
+    let unit_normals: Vec<_> = face_normals
+        .iter()
+        .map(|n| n.try_normalize(f32::EPSILON).unwrap_or_else(glm::Vec3::zeros))
+        .collect();
+
+    let position_key = |i: usize| -> [u32; 3] { mesh.positions[i].map(f32::to_bits).into() };
+    let mut faces_at: AHashMap<_, Vec<usize>> = AHashMap::new();
+    for (face, triangle) in mesh.triangles().enumerate() {
+        for i in triangle {
+            faces_at.entry(position_key(i)).or_default().push(face);
+        }
+    }
+
+    let min_cos = smoothing_angle.clamp(0.0, 180.0).to_radians().cos() - 1e-5;
+    let mut recomputed = MeshData::default();
+    let mut split: AHashMap<(usize, [u32; 3]), u32> = AHashMap::new();
+    for (face, triangle) in mesh.triangles().enumerate() {
+        for i in triangle {
+            let sum: glm::Vec3 = faces_at[&position_key(i)]
+                .iter()
+                .filter(|&&other| unit_normals[face].dot(&unit_normals[other]) >= min_cos)
+                .map(|&other| face_normals[other])
+                .sum();
+            let normal = sum.try_normalize(f32::EPSILON).unwrap_or(mesh.normals[i]);
+
+            let index = *split
+                .entry((i, normal.map(f32::to_bits).into()))
+                .or_insert_with(|| recomputed.push_vertex(mesh, i, normal));
+            recomputed.indices.push(index);
+        }
+    }
+    recomputed
+}
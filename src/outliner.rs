@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::world::EntityRef;
+
+use crate::components::{
+    AnimationClip, CameraComponent, CustomShader, CustomTexture, FollowPath, Group, Hidden, Locked,
+    Mesh, Oscillator, Parent, PointLight, Properties, Rotator, Script, Skeleton, Tags,
+};
+use crate::entity_pool::Pooled;
+use crate::material::MaterialHandle;
+use crate::registry::ComponentRegistry;
+use crate::resources::ModelLoader;
+use crate::variables::Bindings;
+
+/// Whether an entity has a component
+type Has = fn(&EntityRef) -> bool;
+
+/// Built-in components that `has:` filters match, by the name they are written as
+const BUILT_IN: [(&str, Has); 16] = [
+    ("Group", has::<Group>),
+    ("PointLight", has::<PointLight>),
+    ("CameraComponent", has::<CameraComponent>),
+    ("CustomShader", has::<CustomShader>),
+    ("CustomTexture", has::<CustomTexture>),
+    ("Material", has::<MaterialHandle>),
+    ("Rotator", has::<Rotator>),
+    ("Oscillator", has::<Oscillator>),
+    ("FollowPath", has::<FollowPath>),
+    ("AnimationClip", has::<AnimationClip>),
+    ("Skeleton", has::<Skeleton>),
+    ("Script", has::<Script>),
+    ("Properties", has::<Properties>),
+    ("Bindings", has::<Bindings>),
+    ("Hidden", has::<Hidden>),
+    ("Locked", has::<Locked>),
+];
+
+fn has<T: Component>(entity: &EntityRef) -> bool {
+    entity.contains::<T>()
+}
+
+type EntryQuery<'a> = (
+    Entity,
+    Option<&'a Mesh>,
+    Option<&'a Group>,
+    Option<&'a Tags>,
+    Option<&'a Hidden>,
+    Option<&'a Locked>,
+    Option<&'a Parent>,
+);
+
+/// Entries by the group they are listed under, or `None` for the top level
+type EntryTree = BTreeMap<Option<Entity>, Vec<OutlinerEntry>>;
+
+/// An entity as listed in the outliner
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlinerEntry {
+    pub entity: Entity,
+    /// Name of the model, followed by the entity index to tell entities of one model apart, or the
+    /// name of a group
+    pub name: String,
+    /// Number of groups the entity is in, to indent it by
+    pub depth: usize,
+    pub tags: BTreeSet<String>,
+    pub hidden: bool,
+    pub locked: bool,
+    /// Built-in and registered components of the entity
+    pub components: Vec<&'static str>,
+}
+
+/// Every mesh entity, camera and group in the world, in the order they were spawned, with the
+/// entities in a group listed right after it
+pub fn entries(world: &mut World) -> Vec<OutlinerEntry> {
+    let mut query = world.query_filtered::<
+        EntryQuery,
+        (Or<(With<Mesh>, With<CameraComponent>, With<Group>)>, Without<Pooled>),
+    >();
+    let model_loader = world.get_resource::<ModelLoader>();
+    let mut listed: Vec<_> = query
+        .iter(world)
+        .map(|(entity, mesh, group, tags, hidden, locked, parent)| {
+            let name = match group {
+                Some(group) => group.name.clone(),
+                None => {
+                    let model = model_loader.zip(mesh).and_then(|(l, m)| l.name_of(m.vao_id));
+                    let fallback = if mesh.is_none() { "Camera" } else { "Entity" };
+                    format!("{} {}", model.unwrap_or(fallback), entity.index())
+                }
+            };
+            let entry = OutlinerEntry {
+                entity,
+                name,
+                depth: 0,
+                tags: tags.map(|t| t.values.clone()).unwrap_or_default(),
+                hidden: hidden.is_some(),
+                locked: locked.is_some(),
+                components: components(world, entity),
+            };
+            (entry, parent.map(|p| p.0))
+        })
+        .collect();
+    listed.sort_by_key(|(entry, _)| entry.entity.index());
+
+    let listed_entities: BTreeSet<_> = listed.iter().map(|(entry, _)| entry.entity).collect();
+    let mut tree = EntryTree::new();
+    for (entry, parent) in listed {
+        let parent = parent.filter(|parent| listed_entities.contains(parent));
+        tree.entry(parent).or_default().push(entry);
+    }
+    let mut entries = Vec::new();
+    add_entries(None, 0, &mut tree, &mut entries);
+    // Only entities in a cycle of parents are left
+    entries.extend(tree.into_values().flatten());
+    entries
+}
+
+/// Add the entries listed under `parent`, each followed by the entries listed under it
+fn add_entries(
+    parent: Option<Entity>,
+    depth: usize,
+    tree: &mut EntryTree,
+    entries: &mut Vec<OutlinerEntry>,
+) {
+    for mut entry in tree.remove(&parent).unwrap_or_default() {
+        entry.depth = depth;
+        let entity = entry.entity;
+        entries.push(entry);
+        add_entries(Some(entity), depth + 1, tree, entries);
+    }
+}
+
+/// Names of the built-in and registered components of an entity
+pub fn components(world: &World, entity: Entity) -> Vec<&'static str> {
+    let Some(entity_ref) = world.get_entity(entity) else {
+        return Vec::new();
+    };
+    let built_in = BUILT_IN.iter().filter(|(_, has)| has(&entity_ref)).map(|(name, _)| *name);
+    let registered = world
+        .get_resource::<ComponentRegistry>()
+        .into_iter()
+        .flat_map(|registry| registry.iter())
+        .filter(|r| r.contains(world, entity))
+        .map(|r| r.name());
+    built_in.chain(registered).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Name(String),
+    Tag(String),
+    Has(String),
+}
+
+/// Filter typed in the outliner, keeping the entities that match every term
+///
+/// Terms are separated by spaces. `tag:light` keeps entities tagged `light`, `tag:` keeps entities
+/// with any tag, `has:PointLight` keeps entities with a point light, and other terms keep entities
+/// whose name contains them. Case is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutlinerFilter {
+    terms: Vec<Term>,
+}
+
+impl OutlinerFilter {
+    pub fn parse(filter: &str) -> Self {
+        let terms = filter
+            .split_whitespace()
+            .map(|term| {
+                let term = term.to_lowercase();
+                if let Some(tag) = term.strip_prefix("tag:") {
+                    Term::Tag(tag.to_owned())
+                } else if let Some(component) = term.strip_prefix("has:") {
+                    Term::Has(component.to_owned())
+                } else {
+                    Term::Name(term)
+                }
+            })
+            .collect();
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, entry: &OutlinerEntry) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Name(part) => entry.name.to_lowercase().contains(part),
+            Term::Tag(tag) if tag.is_empty() => !entry.tags.is_empty(),
+            Term::Tag(tag) => entry.tags.iter().any(|t| t.to_lowercase() == *tag),
+            Term::Has(component) => {
+                entry.components.iter().any(|c| c.eq_ignore_ascii_case(component))
+            }
+        })
+    }
+}
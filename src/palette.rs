@@ -0,0 +1,108 @@
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+
+/// Colors used for the selection outline and debug drawing
+#[derive(Resource, Debug, Copy, Clone, PartialEq)]
+pub struct Palette {
+    /// Outline around the selected entity
+    pub selection: glm::Vec3,
+    /// Bounding boxes of unselected entities
+    pub bounds: glm::Vec3,
+    /// Bounding box of the selected entity
+    pub selected_bounds: glm::Vec3,
+    /// X, Y and Z axis colors
+    pub axes: [glm::Vec3; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        PalettePreset::Standard.palette()
+    }
+}
+
+/// Built-in palettes, including ones that stay distinguishable with common color vision
+/// deficiencies
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum PalettePreset {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 4] = [
+        PalettePreset::Standard,
+        PalettePreset::Deuteranopia,
+        PalettePreset::Protanopia,
+        PalettePreset::Tritanopia,
+    ];
+
+    /// Name used in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Standard => "standard",
+            PalettePreset::Deuteranopia => "deuteranopia",
+            PalettePreset::Protanopia => "protanopia",
+            PalettePreset::Tritanopia => "tritanopia",
+        }
+    }
+
+    /// Name shown in the UI
+    pub fn label(self) -> &'static str {
+        match self {
+            PalettePreset::Standard => "Standard",
+            PalettePreset::Deuteranopia => "Deuteranopia (red-green)",
+            PalettePreset::Protanopia => "Protanopia (red-green)",
+            PalettePreset::Tritanopia => "Tritanopia (blue-yellow)",
+        }
+    }
+
+    /// The colors for this preset. The color-blind safe presets are picked from the Okabe-Ito
+    /// palette, relying on lightness as well as hue to tell colors apart.
+    pub fn palette(self) -> Palette {
+        match self {
+            PalettePreset::Standard => Palette {
+                selection: glm::vec3(1.0, 0.5, 0.0),
+                bounds: glm::vec3(0.2, 1.0, 0.2),
+                selected_bounds: glm::vec3(1.0, 0.6, 0.0),
+                axes: [
+                    glm::vec3(0.9, 0.2, 0.2),
+                    glm::vec3(0.2, 0.8, 0.2),
+                    glm::vec3(0.2, 0.4, 1.0),
+                ],
+            },
+            PalettePreset::Deuteranopia => Palette {
+                selection: glm::vec3(0.9, 0.62, 0.0),
+                bounds: glm::vec3(0.34, 0.71, 0.91),
+                selected_bounds: glm::vec3(0.94, 0.89, 0.26),
+                axes: [
+                    glm::vec3(0.84, 0.37, 0.0),
+                    glm::vec3(0.94, 0.89, 0.26),
+                    glm::vec3(0.0, 0.45, 0.7),
+                ],
+            },
+            PalettePreset::Protanopia => Palette {
+                selection: glm::vec3(0.94, 0.89, 0.26),
+                bounds: glm::vec3(0.34, 0.71, 0.91),
+                selected_bounds: glm::vec3(0.94, 0.89, 0.26),
+                axes: [
+                    glm::vec3(0.9, 0.62, 0.0),
+                    glm::vec3(0.9, 0.9, 0.9),
+                    glm::vec3(0.0, 0.45, 0.7),
+                ],
+            },
+            PalettePreset::Tritanopia => Palette {
+                selection: glm::vec3(0.8, 0.47, 0.65),
+                bounds: glm::vec3(0.0, 0.62, 0.45),
+                selected_bounds: glm::vec3(0.84, 0.37, 0.0),
+                axes: [
+                    glm::vec3(0.84, 0.37, 0.0),
+                    glm::vec3(0.0, 0.62, 0.45),
+                    glm::vec3(0.9, 0.9, 0.9),
+                ],
+            },
+        }
+    }
+}
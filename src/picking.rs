@@ -0,0 +1,131 @@
+//! Reading the entity ID under the mouse back from the G-buffer without stalling the GPU
+
+use std::time::{Duration, Instant};
+
+use bevy_ecs::system::Resource;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Buffer, Context, Fence, Framebuffer, HasContext, PixelPackData};
+
+/// Where a pick was made, in window coordinates, and whether it adds to the selection
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PickRequest {
+    pub position: (f64, f64),
+    pub add: bool,
+}
+
+/// A finished pick, with the ID that was read where it was made
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pick {
+    pub request: PickRequest,
+    pub id: u32,
+}
+
+/// Copies entity IDs to a pixel buffer object, to be read once a fence says the copy is done
+///
+/// A pick is started with [`start`](Self::start) and taken with [`poll`](Self::poll) in a later
+/// frame, so clicking does not wait for the GPU to finish the frames in flight.
+#[derive(Resource, Default)]
+pub struct Picker {
+    buffer: Option<Buffer>,
+    pending: Option<(Fence, PickRequest, Instant)>,
+    latency: Option<Duration>,
+}
+
+impl Picker {
+    /// Copy the ID at `pixel` of a color attachment of `framebuffer` to the pixel buffer, replacing
+    /// the pick that is pending if there is one
+    ///
+    /// # Safety
+    ///
+    /// The framebuffer must have been created with the given context, and have an unsigned
+    /// integer texture at the attachment.
+    pub unsafe fn start(
+        &mut self,
+        gl: &Context,
+        framebuffer: Option<Framebuffer>,
+        attachment: u32,
+        pixel: (i32, i32),
+        request: PickRequest,
+    ) -> Result<()> {
+        let buffer = match self.buffer {
+            Some(buffer) => buffer,
+            None => {
+                let buffer =
+                    gl.create_buffer().map_err(|e| eyre!("could not create pixel buffer: {e}"))?;
+                gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(buffer));
+                gl.buffer_data_size(glow::PIXEL_PACK_BUFFER, 4, glow::STREAM_READ);
+                self.buffer = Some(buffer);
+                buffer
+            }
+        };
+        if let Some((fence, ..)) = self.pending.take() {
+            gl.delete_sync(fence);
+        }
+
+        gl.bind_framebuffer(glow::FRAMEBUFFER, framebuffer);
+        gl.read_buffer(attachment);
+        gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(buffer));
+        gl.read_pixels(
+            pixel.0,
+            pixel.1,
+            1,
+            1,
+            glow::RED_INTEGER,
+            glow::UNSIGNED_INT,
+            PixelPackData::BufferOffset(0),
+        );
+        gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+        let fence = gl
+            .fence_sync(glow::SYNC_GPU_COMMANDS_COMPLETE, 0)
+            .map_err(|e| eyre!("could not create fence: {e}"))?;
+        // Send the copy to the GPU now, or the fence might not be signaled until the next swap
+        gl.flush();
+        self.pending = Some((fence, request, Instant::now()));
+        Ok(())
+    }
+
+    /// The pending pick, once the GPU has copied its ID
+    ///
+    /// # Safety
+    ///
+    /// The context must be the one the pick was started with.
+    pub unsafe fn poll(&mut self, gl: &Context) -> Option<Pick> {
+        let (fence, request, started) = self.pending?;
+        if gl.get_sync_status(fence) != glow::SIGNALED {
+            return None;
+        }
+        gl.delete_sync(fence);
+        self.pending = None;
+
+        let mut bytes = [0; 4];
+        gl.bind_buffer(glow::PIXEL_PACK_BUFFER, self.buffer);
+        gl.get_buffer_sub_data(glow::PIXEL_PACK_BUFFER, 0, &mut bytes);
+        gl.bind_buffer(glow::PIXEL_PACK_BUFFER, None);
+        self.latency = Some(started.elapsed());
+        Some(Pick { request, id: u32::from_ne_bytes(bytes) })
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Time the last pick took from being started to being taken
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// # Safety
+    ///
+    /// The picker must not be used with the context afterwards.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        if let Some((fence, ..)) = self.pending.take() {
+            gl.delete_sync(fence);
+        }
+        if let Some(buffer) = self.buffer.take() {
+            gl.delete_buffer(buffer);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use nalgebra_glm as glm;
+
+use crate::components::Rotation;
+use crate::resources::{Camera, ViewRect};
+
+/// Depth buffer values of a pixel and its eight neighbors, in rows from the bottom up as OpenGL
+/// reads them
+pub type DepthSamples = [[f32; 3]; 3];
+
+/// Point on the surface drawn at a pixel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurfaceHit {
+    pub position: glm::Vec3,
+    /// Facing the camera
+    pub normal: glm::Vec3,
+}
+
+/// Where the surface drawn at pixel `x`, `y` of a view lies, from the depth buffer around it, or
+/// `None` if nothing is drawn there
+///
+/// The pixel is counted from the bottom left of the window, like `ViewRect`.
+pub fn surface_hit(
+    camera: &Camera,
+    rect: ViewRect,
+    x: i32,
+    y: i32,
+    depths: &DepthSamples,
+) -> Option<SurfaceHit> {
+    let depth = |dx: i32, dy: i32| depths[(dy + 1) as usize][(dx + 1) as usize];
+    if depth(0, 0) >= 1.0 {
+        return None;
+    }
+
+    let view = camera.view();
+    let viewport = glm::vec4(rect.x as f32, rect.y as f32, rect.width as f32, rect.height as f32);
+    let point = |dx: i32, dy: i32| {
+        let window = glm::vec3((x + dx) as f32 + 0.5, (y + dy) as f32 + 0.5, depth(dx, dy));
+        glm::unproject(&window, &view, &camera.projection, viewport)
+    };
+    let position = point(0, 0);
+
+    // Take the neighbor closest in depth along each axis, so the edge of an object in front or the
+    // background behind it does not bend the normal
+    let tangent = |(ax, ay): (i32, i32)| {
+        let (before, after) = (depth(-ax, -ay), depth(ax, ay));
+        if before >= 1.0 && after >= 1.0 {
+            None
+        } else if (after - depth(0, 0)).abs() <= (before - depth(0, 0)).abs() {
+            Some(point(ax, ay) - position)
+        } else {
+            Some(position - point(-ax, -ay))
+        }
+    };
+    let normal = match (tangent((1, 0)), tangent((0, 1))) {
+        (Some(right), Some(up)) => glm::cross(&right, &up),
+        _ => glm::Vec3::zeros(),
+    };
+    let normal = if normal.norm() > f32::EPSILON {
+        let normal = glm::normalize(&normal);
+        if glm::dot(&normal, &camera.front) > 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    } else {
+        -camera.front
+    };
+
+    Some(SurfaceHit { position, normal })
+}
+
+/// Rotation that turns the up axis of a model to point along `normal`
+pub fn align_to_normal(normal: glm::Vec3) -> Rotation {
+    let normal = glm::normalize(&normal);
+    let x = normal.y.clamp(-1.0, 1.0).acos();
+    // Around the Y axis the rotation only matters when the model is tilted
+    let y = if x.abs() > 1e-4 { normal.x.atan2(normal.z) } else { 0.0 };
+    Rotation::new(x.to_degrees(), y.to_degrees(), 0.0)
+}
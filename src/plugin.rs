@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+
+use crate::app::SceneEditorApp;
+
+/// A feature added to the editor, such as physics or an exporter, that can live in a crate of its
+/// own
+///
+/// Plugins are built before the editor starts, after the plugins they depend on.
+pub trait Plugin: Send + 'static {
+    /// Name other plugins give in their dependencies, which has to be unique
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Names of the plugins that have to be built before this one
+    fn dependencies(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Add the systems, resources, components, panels and model importers of the plugin
+    fn build(&self, app: &mut SceneEditorApp);
+}
+
+/// Order `plugins` so each comes after its dependencies, and otherwise in the order they were
+/// added
+///
+/// Dependencies on the plugins named in `built` are already met.
+pub fn load_order(
+    plugins: Vec<Box<dyn Plugin>>,
+    built: &[&'static str],
+) -> Result<Vec<Box<dyn Plugin>>> {
+    let mut by_name = BTreeMap::new();
+    for (i, plugin) in plugins.iter().enumerate() {
+        if by_name.insert(plugin.name(), i).is_some() || built.contains(&plugin.name()) {
+            return Err(eyre!("plugin {:?} was added twice", plugin.name()));
+        }
+    }
+
+    let mut dependencies = Vec::with_capacity(plugins.len());
+    for plugin in &plugins {
+        let indices = plugin
+            .dependencies()
+            .into_iter()
+            .filter(|name| !built.contains(name))
+            .map(|name| {
+                by_name.get(name).copied().ok_or_else(|| {
+                    eyre!("plugin {:?} depends on {name:?}, which was not added", plugin.name())
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        dependencies.push(indices);
+    }
+
+    let mut order = Vec::with_capacity(plugins.len());
+    let mut state = vec![Visit::New; plugins.len()];
+    for i in 0..plugins.len() {
+        visit(i, &dependencies, &mut state, &mut order).map_err(|cycle| {
+            eyre!("plugin {:?} depends on itself through its dependencies", plugins[cycle].name())
+        })?;
+    }
+
+    let mut plugins: Vec<_> = plugins.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|i| plugins[i].take()).collect())
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+/// Add plugin `i` to `order` after its dependencies, or return the plugin a cycle came back to
+fn visit(
+    i: usize,
+    dependencies: &[Vec<usize>],
+    state: &mut [Visit],
+    order: &mut Vec<usize>,
+) -> Result<(), usize> {
+    match state[i] {
+        Visit::Done => return Ok(()),
+        Visit::InProgress => return Err(i),
+        Visit::New => {}
+    }
+    state[i] = Visit::InProgress;
+    for &dependency in &dependencies[i] {
+        visit(dependency, dependencies, state, order)?;
+    }
+    state[i] = Visit::Done;
+    order.push(i);
+    Ok(())
+}
+
+type ShowPanel = Box<dyn FnMut(&mut egui::Ui, &mut World) + Send + Sync>;
+
+/// A panel added by a plugin, shown in a window of its own and toggled from the toolbar
+pub struct PluginPanel {
+    pub title: &'static str,
+    pub open: bool,
+    show: ShowPanel,
+}
+
+impl PluginPanel {
+    /// Draw the panel in its window, if it is open
+    pub fn show(&mut self, ctx: &egui::Context, world: &mut World) {
+        let Self { title, open, show } = self;
+        egui::Window::new(*title).open(open).show(ctx, |ui| show(ui, world));
+    }
+}
+
+/// Panels added by plugins, in the order they were added
+#[derive(Resource, Default)]
+pub struct PluginPanels {
+    panels: Vec<PluginPanel>,
+}
+
+impl PluginPanels {
+    pub fn add<F>(&mut self, title: &'static str, show: F)
+    where
+        F: FnMut(&mut egui::Ui, &mut World) + Send + Sync + 'static,
+    {
+        self.panels.push(PluginPanel { title, open: false, show: Box::new(show) });
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut PluginPanel> {
+        self.panels.iter_mut()
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use bevy_ecs::system::Resource;
+use glow::{Context, HasContext, Query};
+use tracing::warn;
+
+/// Number of frames of history kept for the frame time graph
+pub const HISTORY_LEN: usize = 240;
+
+/// Queries per pass, so results can be read a few frames later without stalling
+const QUERIES_PER_PASS: usize = 3;
+
+struct PassTimer {
+    name: &'static str,
+    queries: Vec<Query>,
+    pending: [bool; QUERIES_PER_PASS],
+    next: usize,
+    /// Whether a query was started for the current run of the pass
+    active: bool,
+    cpu_start: Option<Instant>,
+    cpu_ms: f32,
+    gpu_ms: Option<f32>,
+}
+
+impl PassTimer {
+    unsafe fn read_result(&mut self, gl: &Context, i: usize) -> bool {
+        let query = self.queries[i];
+        if gl.get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE) == 0 {
+            return false;
+        }
+        let ns = gl.get_query_parameter_u32(query, glow::QUERY_RESULT);
+        self.gpu_ms = Some(ns as f32 / 1_000_000.0);
+        self.pending[i] = false;
+        true
+    }
+}
+
+/// Timings of a render pass from the last frames
+#[derive(Debug, Copy, Clone)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub cpu_ms: f32,
+    /// `None` until the first GPU timer query result is available
+    pub gpu_ms: Option<f32>,
+}
+
+/// Counters collected while rendering a frame
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FrameStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+}
+
+/// Per-pass CPU and GPU timings, draw counters and frame time history
+#[derive(Resource)]
+pub struct Profiler {
+    passes: Vec<PassTimer>,
+    current: FrameStats,
+    last: FrameStats,
+    frame_times: VecDeque<f32>,
+    last_frame: Instant,
+    /// Disabled if the context could not create timer queries
+    gpu_timing: bool,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            current: FrameStats::default(),
+            last: FrameStats::default(),
+            frame_times: VecDeque::with_capacity(HISTORY_LEN),
+            last_frame: Instant::now(),
+            gpu_timing: true,
+        }
+    }
+
+    /// Start timing a pass, which must be ended with `end_pass` before another pass is started
+    pub fn begin_pass(&mut self, gl: &Context, name: &'static str) {
+        let gpu_timing = self.gpu_timing;
+        let i = match self.passes.iter().position(|p| p.name == name) {
+            Some(i) => i,
+            None => {
+                let queries = if gpu_timing {
+                    (0..QUERIES_PER_PASS).map(|_| unsafe { gl.create_query() }).collect()
+                } else {
+                    Ok(Vec::new())
+                };
+                let queries = queries.unwrap_or_else(|e| {
+                    warn!("could not create timer query, disabling GPU timings: {e}");
+                    self.gpu_timing = false;
+                    Vec::new()
+                });
+                self.passes.push(PassTimer {
+                    name,
+                    queries,
+                    pending: [false; QUERIES_PER_PASS],
+                    next: 0,
+                    active: false,
+                    cpu_start: None,
+                    cpu_ms: 0.0,
+                    gpu_ms: None,
+                });
+                self.passes.len() - 1
+            }
+        };
+
+        let timer = &mut self.passes[i];
+        timer.cpu_start = Some(Instant::now());
+        timer.active = false;
+        if timer.queries.is_empty() {
+            return;
+        }
+
+        unsafe {
+            // Skip GPU timing this frame rather than waiting on a query that is still in flight
+            let slot = timer.next;
+            if timer.pending[slot] && !timer.read_result(gl, slot) {
+                return;
+            }
+            gl.begin_query(glow::TIME_ELAPSED, timer.queries[slot]);
+        }
+        timer.active = true;
+    }
+
+    pub fn end_pass(&mut self, gl: &Context, name: &'static str) {
+        let Some(timer) = self.passes.iter_mut().find(|p| p.name == name) else {
+            return;
+        };
+
+        if let Some(start) = timer.cpu_start.take() {
+            timer.cpu_ms = start.elapsed().as_secs_f32() * 1000.0;
+        }
+        if timer.active {
+            unsafe {
+                gl.end_query(glow::TIME_ELAPSED);
+            }
+            timer.pending[timer.next] = true;
+            timer.next = (timer.next + 1) % QUERIES_PER_PASS;
+            timer.active = false;
+        }
+    }
+
+    /// Count a draw call with the given number of triangles
+    pub fn count_draw(&mut self, triangles: u32) {
+        self.current.draw_calls += 1;
+        self.current.triangles += triangles;
+    }
+
+    /// Collect finished GPU timings and record the frame time
+    pub fn end_frame(&mut self, gl: &Context) {
+        for timer in &mut self.passes {
+            for i in 0..timer.queries.len() {
+                if timer.pending[i] {
+                    unsafe {
+                        timer.read_result(gl, i);
+                    }
+                }
+            }
+        }
+
+        self.last = std::mem::take(&mut self.current);
+
+        let now = Instant::now();
+        if self.frame_times.len() == HISTORY_LEN {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(now.duration_since(self.last_frame).as_secs_f32() * 1000.0);
+        self.last_frame = now;
+    }
+
+    pub fn pass_timings(&self) -> impl Iterator<Item = PassTiming> + '_ {
+        self.passes.iter().map(|p| PassTiming { name: p.name, cpu_ms: p.cpu_ms, gpu_ms: p.gpu_ms })
+    }
+
+    /// Counters from the last finished frame
+    pub fn stats(&self) -> FrameStats {
+        self.last
+    }
+
+    /// Frame times in milliseconds, oldest first
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    /// # Safety
+    ///
+    /// The profiler must not be used with the context afterwards.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        for timer in self.passes.drain(..) {
+            for query in timer.queries {
+                gl.delete_query(query);
+            }
+        }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
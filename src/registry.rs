@@ -0,0 +1,280 @@
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+#[cfg(feature = "editor")]
+use ron::value::{Float, Number};
+use ron::Value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::warn;
+
+/// A component that can be edited in the inspector
+#[cfg(feature = "editor")]
+pub trait Inspect {
+    /// Draw widgets for editing the component, returning whether it was changed
+    fn inspect(&mut self, ui: &mut egui::Ui) -> bool;
+}
+
+/// Type-erased operations on a registered component
+#[derive(Copy, Clone)]
+pub struct ComponentRegistration {
+    name: &'static str,
+    contains: fn(&World, Entity) -> bool,
+    serialize: fn(&World, Entity) -> Option<Result<Value>>,
+    insert: fn(&mut World, Entity, Value) -> Result<()>,
+    insert_default: fn(&mut World, Entity),
+    remove: fn(&mut World, Entity),
+    #[cfg(feature = "editor")]
+    inspect: fn(&mut Value, &mut egui::Ui) -> Result<bool>,
+}
+
+impl ComponentRegistration {
+    fn new<T>(name: &'static str) -> Self
+    where
+        T: Component + Default + Serialize + DeserializeOwned,
+    {
+        Self {
+            name,
+            contains: |world, entity| world.get::<T>(entity).is_some(),
+            serialize: serialize::<T>,
+            insert: insert::<T>,
+            insert_default: |world, entity| {
+                if let Some(mut entity) = world.get_entity_mut(entity) {
+                    entity.insert(T::default());
+                }
+            },
+            remove: |world, entity| {
+                if let Some(mut entity) = world.get_entity_mut(entity) {
+                    entity.remove::<T>();
+                }
+            },
+            #[cfg(feature = "editor")]
+            inspect: edit::<T>,
+        }
+    }
+
+    /// Name used in scene files and the inspector
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn contains(&self, world: &World, entity: Entity) -> bool {
+        (self.contains)(world, entity)
+    }
+
+    /// Serialize the component of the entity, or `None` if it does not have one
+    pub fn serialize(&self, world: &World, entity: Entity) -> Option<Result<Value>> {
+        (self.serialize)(world, entity)
+    }
+
+    /// Deserialize the component and insert it on the entity
+    pub fn insert(&self, world: &mut World, entity: Entity, value: Value) -> Result<()> {
+        (self.insert)(world, entity, value)
+    }
+
+    pub fn insert_default(&self, world: &mut World, entity: Entity) {
+        (self.insert_default)(world, entity)
+    }
+
+    pub fn remove(&self, world: &mut World, entity: Entity) {
+        (self.remove)(world, entity)
+    }
+
+    /// Edit a serialized component, returning whether it was changed
+    ///
+    /// Components registered without an `Inspect` implementation get a widget for each field of
+    /// their serialized value. Fails if the value can not be read back as the component.
+    #[cfg(feature = "editor")]
+    pub fn inspect(&self, value: &mut Value, ui: &mut egui::Ui) -> Result<bool> {
+        (self.inspect)(value, ui)
+    }
+}
+
+/// Components from user code that are saved in scenes and shown in the inspector
+#[derive(Resource, Default, Clone)]
+pub struct ComponentRegistry {
+    registrations: Vec<ComponentRegistration>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a component under a unique name
+    pub fn register<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: Component + Default + Serialize + DeserializeOwned,
+    {
+        self.add(ComponentRegistration::new::<T>(name));
+        self
+    }
+
+    /// Register a component that can be edited in the inspector
+    #[cfg(feature = "editor")]
+    pub fn register_inspectable<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: Component + Default + Serialize + DeserializeOwned + Inspect,
+    {
+        let mut registration = ComponentRegistration::new::<T>(name);
+        registration.inspect = inspect::<T>;
+        self.add(registration);
+        self
+    }
+
+    fn add(&mut self, registration: ComponentRegistration) {
+        if let Some(existing) = self.registrations.iter_mut().find(|r| r.name == registration.name)
+        {
+            warn!("component {:?} was registered twice, replacing it", registration.name);
+            *existing = registration;
+        } else {
+            self.registrations.push(registration);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ComponentRegistration> {
+        self.registrations.iter().find(|r| r.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentRegistration> {
+        self.registrations.iter()
+    }
+
+    /// Registrations whose name contains the filter, ignoring case, sorted by name
+    pub fn search(&self, filter: &str) -> Vec<&ComponentRegistration> {
+        let filter = filter.trim().to_lowercase();
+        let mut found: Vec<_> =
+            self.iter().filter(|r| r.name.to_lowercase().contains(&filter)).collect();
+        found.sort_by_key(|r| r.name);
+        found
+    }
+}
+
+fn to_value<T: Serialize>(component: &T) -> Result<Value> {
+    let s = ron::to_string(component)?;
+    Ok(ron::from_str(&s)?)
+}
+
+fn serialize<T: Component + Serialize>(world: &World, entity: Entity) -> Option<Result<Value>> {
+    world.get::<T>(entity).map(to_value)
+}
+
+fn insert<T: Component + DeserializeOwned>(
+    world: &mut World,
+    entity: Entity,
+    value: Value,
+) -> Result<()> {
+    let component: T = value.into_rust()?;
+    world
+        .get_entity_mut(entity)
+        .ok_or_else(|| eyre!("entity {} does not exist", entity.index()))?
+        .insert(component);
+    Ok(())
+}
+
+#[cfg(feature = "editor")]
+fn inspect<T>(value: &mut Value, ui: &mut egui::Ui) -> Result<bool>
+where
+    T: Serialize + DeserializeOwned + Inspect,
+{
+    let mut component: T = value.clone().into_rust()?;
+    if !component.inspect(ui) {
+        return Ok(false);
+    }
+    *value = to_value(&component)?;
+    Ok(true)
+}
+
+/// Edit the component with the widgets of `edit_value`, keeping only changes it can be read from
+#[cfg(feature = "editor")]
+fn edit<T>(value: &mut Value, ui: &mut egui::Ui) -> Result<bool>
+where
+    T: Serialize + DeserializeOwned,
+{
+    value.clone().into_rust::<T>()?;
+    let mut edited = value.clone();
+    if !edit_value(ui, &mut edited) {
+        return Ok(false);
+    }
+    *value = to_value(&edited.into_rust::<T>()?)?;
+    Ok(true)
+}
+
+/// Draw widgets for a serialized value and each of its fields, returning whether it was changed
+///
+/// Missing options can not be filled in, as the type of their value is not known.
+#[cfg(feature = "editor")]
+pub fn edit_value(ui: &mut egui::Ui, value: &mut Value) -> bool {
+    match value {
+        Value::Bool(b) => ui.checkbox(b, "").changed(),
+        Value::Char(c) => {
+            let mut s = c.to_string();
+            let response = ui.add(egui::TextEdit::singleline(&mut s).desired_width(24.0));
+            match s.chars().last() {
+                Some(new) if response.changed() && new != *c => {
+                    *c = new;
+                    true
+                }
+                _ => false,
+            }
+        }
+        Value::String(s) => ui.text_edit_singleline(s).changed(),
+        Value::Number(Number::Integer(i)) => ui.add(egui::DragValue::new(i)).changed(),
+        Value::Number(Number::Float(f)) => {
+            let mut x = f.get();
+            let changed = ui.add(egui::DragValue::new(&mut x).speed(0.01)).changed();
+            if changed {
+                *f = Float::new(x);
+            }
+            changed
+        }
+        Value::Option(None) => {
+            ui.weak("None");
+            false
+        }
+        Value::Option(Some(inner)) => {
+            let (changed, clear) = ui
+                .horizontal(|ui| (edit_value(ui, inner), ui.small_button("Clear").clicked()))
+                .inner;
+            if clear {
+                *value = Value::Option(None);
+            }
+            changed || clear
+        }
+        Value::Seq(items) => {
+            let id = ui.next_auto_id();
+            egui::Grid::new(id)
+                .show(ui, |ui| {
+                    let mut changed = false;
+                    for (i, item) in items.iter_mut().enumerate() {
+                        ui.label(i.to_string());
+                        changed |= edit_value(ui, item);
+                        ui.end_row();
+                    }
+                    changed
+                })
+                .inner
+        }
+        Value::Map(map) => {
+            let id = ui.next_auto_id();
+            egui::Grid::new(id)
+                .show(ui, |ui| {
+                    let mut changed = false;
+                    for (key, field) in map.iter_mut() {
+                        match key {
+                            Value::String(key) => ui.label(key.as_str()),
+                            key => ui.label(ron::to_string(key).unwrap_or_default()),
+                        };
+                        changed |= edit_value(ui, field);
+                        ui.end_row();
+                    }
+                    changed
+                })
+                .inner
+        }
+        Value::Unit => {
+            ui.weak("()");
+            false
+        }
+    }
+}
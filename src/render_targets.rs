@@ -0,0 +1,194 @@
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext, Renderbuffer, Texture};
+
+use crate::frame_graph::{TargetSize, TextureDesc};
+
+struct PooledTexture {
+    desc: TextureDesc,
+    texture: Texture,
+    in_use: bool,
+}
+
+struct PooledRenderbuffer {
+    size: TargetSize,
+    samples: i32,
+    renderbuffer: Renderbuffer,
+    in_use: bool,
+}
+
+/// Render targets shared between passes, keyed by their format and size
+///
+/// Released targets are handed out again to later requests with the same description, and every
+/// viewport-sized target is reallocated in one place when the viewport is resized.
+pub struct RenderTargetPool {
+    textures: Vec<PooledTexture>,
+    renderbuffers: Vec<PooledRenderbuffer>,
+    viewport: (i32, i32),
+}
+
+impl RenderTargetPool {
+    pub fn new(viewport: (u32, u32)) -> Self {
+        Self {
+            textures: Vec::new(),
+            renderbuffers: Vec::new(),
+            viewport: (viewport.0 as i32, viewport.1 as i32),
+        }
+    }
+
+    /// Current size of a target with the given size
+    pub fn resolve(&self, size: TargetSize) -> (i32, i32) {
+        match size {
+            TargetSize::Viewport => self.viewport,
+            TargetSize::Fixed(width, height) => (width, height),
+        }
+    }
+
+    /// Get an unused texture matching the description, creating one if there is none
+    pub fn acquire_texture(&mut self, gl: &Context, desc: &TextureDesc) -> Result<Texture> {
+        if let Some(pooled) = self.textures.iter_mut().find(|t| !t.in_use && t.desc == *desc) {
+            pooled.in_use = true;
+            return Ok(pooled.texture);
+        }
+
+        let texture = unsafe { create_texture(gl, desc, self.resolve(desc.size))? };
+        self.textures.push(PooledTexture { desc: *desc, texture, in_use: true });
+        Ok(texture)
+    }
+
+    /// Return a texture to the pool so later requests can reuse it
+    pub fn release_texture(&mut self, texture: Texture) {
+        if let Some(pooled) = self.textures.iter_mut().find(|t| t.texture == texture) {
+            pooled.in_use = false;
+        }
+    }
+
+    /// Get an unused depth and stencil renderbuffer, creating one if there is none
+    pub fn acquire_renderbuffer(
+        &mut self,
+        gl: &Context,
+        size: TargetSize,
+        samples: i32,
+    ) -> Result<Renderbuffer> {
+        let matching = |r: &&mut PooledRenderbuffer| r.size == size && r.samples == samples;
+        if let Some(pooled) = self.renderbuffers.iter_mut().find(|r| !r.in_use && matching(r)) {
+            pooled.in_use = true;
+            return Ok(pooled.renderbuffer);
+        }
+
+        let renderbuffer = unsafe {
+            let rbo = gl
+                .create_renderbuffer()
+                .map_err(|e| eyre!("could not create renderbuffer: {e}"))?;
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rbo));
+            allocate_renderbuffer(gl, self.resolve(size), samples);
+            rbo
+        };
+        self.renderbuffers.push(PooledRenderbuffer { size, samples, renderbuffer, in_use: true });
+        Ok(renderbuffer)
+    }
+
+    pub fn release_renderbuffer(&mut self, renderbuffer: Renderbuffer) {
+        if let Some(pooled) = self.renderbuffers.iter_mut().find(|r| r.renderbuffer == renderbuffer)
+        {
+            pooled.in_use = false;
+        }
+    }
+
+    /// Reallocate every target that follows the viewport size
+    ///
+    /// The GL objects are kept, so framebuffers they are attached to stay valid. A width or height
+    /// of 0, as a minimized window has, is ignored since GL can not allocate targets that small.
+    pub fn resize(&mut self, gl: &Context, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.viewport = (width as i32, height as i32);
+        unsafe {
+            for pooled in &self.textures {
+                if pooled.desc.size == TargetSize::Viewport {
+                    gl.bind_texture(pooled.desc.target(), Some(pooled.texture));
+                    allocate_texture(gl, &pooled.desc, self.viewport);
+                }
+            }
+
+            for pooled in &self.renderbuffers {
+                if pooled.size == TargetSize::Viewport {
+                    gl.bind_renderbuffer(glow::RENDERBUFFER, Some(pooled.renderbuffer));
+                    allocate_renderbuffer(gl, self.viewport, pooled.samples);
+                }
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The textures and renderbuffers are no longer valid and should not be used.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        for pooled in self.textures.drain(..) {
+            gl.delete_texture(pooled.texture);
+        }
+        for pooled in self.renderbuffers.drain(..) {
+            gl.delete_renderbuffer(pooled.renderbuffer);
+        }
+    }
+}
+
+unsafe fn allocate_texture(gl: &Context, desc: &TextureDesc, (width, height): (i32, i32)) {
+    if desc.samples > 1 {
+        // Renderbuffers always have fixed sample locations, and a framebuffer needs them to match
+        let format = desc.internal_format as i32;
+        gl.tex_image_2d_multisample(desc.target(), desc.samples, format, width, height, true);
+        return;
+    }
+    gl.tex_image_2d(
+        glow::TEXTURE_2D,
+        0,
+        desc.internal_format as i32,
+        width,
+        height,
+        0,
+        desc.format,
+        desc.ty,
+        None,
+    );
+}
+
+unsafe fn allocate_renderbuffer(gl: &Context, (width, height): (i32, i32), samples: i32) {
+    if samples > 1 {
+        let format = glow::DEPTH24_STENCIL8;
+        gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, samples, format, width, height);
+    } else {
+        gl.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, width, height);
+    }
+}
+
+unsafe fn create_texture(gl: &Context, desc: &TextureDesc, size: (i32, i32)) -> Result<Texture> {
+    let texture = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
+    gl.bind_texture(desc.target(), Some(texture));
+    allocate_texture(gl, desc, size);
+    // Multisampled textures are only read with texelFetch, and have no sampler state
+    if desc.samples > 1 {
+        return Ok(texture);
+    }
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, desc.filter as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, desc.filter as i32);
+
+    if desc.compare {
+        gl.tex_parameter_i32(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_COMPARE_MODE,
+            glow::COMPARE_REF_TO_TEXTURE as i32,
+        );
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_COMPARE_FUNC, glow::LEQUAL as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_BORDER as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_BORDER as i32);
+        gl.tex_parameter_f32_slice(
+            glow::TEXTURE_2D,
+            glow::TEXTURE_BORDER_COLOR,
+            &[1.0, 1.0, 1.0, 1.0],
+        );
+    }
+
+    Ok(texture)
+}
@@ -1,269 +1,1477 @@
 use std::sync::Arc;
 
 use bevy_ecs::prelude::*;
-use glow::{Context, HasContext};
+use bevy_ecs::schedule::ExecutorKind;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext, PixelPackData};
 use nalgebra_glm as glm;
+use tracing::{error, warn};
 
 use crate::components::{
-    CustomShader, CustomTexture, Mesh, PointLight, Position, Rotation, Scale, Selected, StencilId,
+    CameraComponent, CustomShader, CustomTexture, GlobalTransform, Hidden, Interpolation, Lod,
+    Mesh, PointLight, Position, PreviousTransform, Rotation, Scale, Selected, ShaderParams,
+    Skeleton,
 };
-use crate::resources::{Camera, RenderState, WinitWindow};
+use crate::entity_pool::Pooled;
+use crate::gl_debug::{gl_check, ErrorCheckMode, GlErrorChecks};
+use crate::gl_state::GlState;
+use crate::light_clusters::{LightClusters, CLUSTERS, INDEX_ROW};
+use crate::palette::Palette;
+use crate::profiler::Profiler;
+use crate::resources::{
+    BoundsDisplay, Camera, DebugDraw, FixedTime, Lighting, Notifications, ProjectionMode,
+    RenderState, ShaderGlobals, ViewLayout, ViewPasses, ViewRect, Viewport, DEPTH_OF_FIELD_PASS,
+    ENTITY_ID, FOCUSED_COLOR, GEOMETRY_PASS, LIGHTING_PASS, LIT_COLOR, MOTION_BLUR_PASS,
+    OUTPUT_PASS, REFLECTED_COLOR, REFLECTION_PASS, SHADOW_PASS,
+};
+use crate::shader::{Shader, ShaderFeatures};
+use crate::terrain::{Terrain, TerrainChunks};
+use crate::transform::update_global_transforms;
 
-type GeometryQuery<'a> = (
+/// Components of the meshes the passes draw
+pub type GeometryQuery<'a> = (
     Entity,
     &'a Mesh,
-    &'a Position,
-    &'a Rotation,
-    &'a Scale,
+    &'a GlobalTransform,
     Option<&'a Selected>,
     Option<&'a CustomShader>,
+    Option<&'a ShaderParams>,
     Option<&'a CustomTexture>,
+    Option<&'a Skeleton>,
+    Option<&'a PreviousTransform>,
+    Option<&'a Lod>,
 );
 
+/// Components of the terrains the passes draw
+pub type TerrainQuery<'a> =
+    (Entity, &'a Terrain, &'a TerrainChunks, &'a GlobalTransform, Option<&'a PreviousTransform>);
+
+/// Entities that are drawn, leaving out pooled and hidden ones
+type Drawn = (Without<Pooled>, Without<Hidden>);
+
+pub fn model_matrix(pos: Position, rot: Rotation, scale: Scale) -> glm::Mat4 {
+    glm::translation(&pos.into()) * rot.matrix() * glm::scaling(&scale.into())
+}
+
+/// Model matrix blended from one transform to another by `t`, turning rotations the short way
+pub fn interpolated_model_matrix(
+    from: (Position, Rotation, Scale),
+    to: (Position, Rotation, Scale),
+    t: f32,
+) -> glm::Mat4 {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let ((p0, r0, s0), (p1, r1, s1)) = (from, to);
+    model_matrix(
+        Position::new(lerp(p0.x, p1.x), lerp(p0.y, p1.y), lerp(p0.z, p1.z)),
+        r0.slerp(&r1, t),
+        Scale::new(lerp(s0.x, s1.x), lerp(s0.y, s1.y), lerp(s0.z, s1.z)),
+    )
+}
+
+/// Draw animated entities between their last two simulation steps, by how far the frame is into
+/// the next step
+pub fn interpolate_transforms(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&Position, &Rotation, &Scale, &mut Interpolation)>,
+) {
+    let t = fixed_time.overstep_fraction();
+    for (&pos, &rot, &scale, mut interpolation) in &mut query {
+        let from = (interpolation.position, interpolation.rotation, interpolation.scale);
+        interpolation.model = interpolated_model_matrix(from, (pos, rot, scale), t);
+    }
+}
+
+/// Built-in shader features a mesh needs
+fn shader_features(
+    mesh: &Mesh,
+    texture: Option<&CustomTexture>,
+    skeleton: Option<&Skeleton>,
+) -> ShaderFeatures {
+    ShaderFeatures {
+        skinned: skeleton.is_some(),
+        alpha_test: texture.is_some_and(|t| t.alpha_test),
+        normal_map: mesh.has_tangents && texture.is_some_and(|t| t.normal.is_some()),
+        // Only terrains are splatted, and they are drawn on their own
+        splat: false,
+    }
+}
+
+/// Features of the shadow map shader for a mesh drawn with `features`, which only deforms and cuts
+/// out the mesh
+fn depth_features(features: ShaderFeatures) -> ShaderFeatures {
+    ShaderFeatures {
+        skinned: features.skinned,
+        alpha_test: features.alpha_test,
+        ..Default::default()
+    }
+}
+
+/// Upload the joint matrices of a skinned mesh, or disable skinning
+///
+/// # Safety
+///
+/// The shader program must be active.
+unsafe fn set_skinning(gl: &Context, shader: &Shader, skeleton: Option<&Skeleton>) {
+    shader.uniform_int(gl, "skinned", skeleton.is_some() as i32);
+    if let Some(skeleton) = skeleton {
+        shader.uniform_mat4_array(gl, "joint_matrices", &skeleton.joint_matrices);
+    }
+}
+
+/// Upload the values every custom shader can use
+///
+/// # Safety
+///
+/// The shader program must be active.
+unsafe fn set_shader_globals(
+    gl: &Context,
+    shader: &Shader,
+    globals: &ShaderGlobals,
+    camera: &Camera,
+) {
+    shader.uniform_float(gl, "time", globals.time);
+    shader.uniform_float(gl, "delta_time", globals.delta_time);
+    shader.uniform_vec2(gl, "resolution", &globals.resolution);
+    shader.uniform_vec3(gl, "camera_pos", &camera.pos);
+    shader.uniform_vec4(gl, "user_params", &globals.params);
+}
+
+/// Cameras of the visible views, where they are drawn and which passes they show, which is the
+/// whole viewport with every pass unless a `ViewLayout` says otherwise
+///
+/// The scene camera preview comes last, so it is drawn over the views, and leaves out the editor
+/// outlines and lines.
+fn visible_views<'a>(
+    camera: &'a Camera,
+    layout: Option<&'a ViewLayout>,
+    viewport: &Viewport,
+) -> Vec<(&'a Camera, ViewRect, ViewPasses)> {
+    match layout {
+        Some(layout) => {
+            let rects = layout.rects(viewport.width, viewport.height);
+            let views = layout.views(camera, viewport.width, viewport.height);
+            let mut visible: Vec<_> = rects
+                .into_iter()
+                .zip(views)
+                .map(|((view, _), (camera, rect))| (camera, rect, layout.passes(view)))
+                .collect();
+            if let Some((camera, rect)) = layout.preview(viewport.width, viewport.height) {
+                let passes = ViewPasses {
+                    outlines: false,
+                    bounding_boxes: false,
+                    ..layout.passes(layout.active())
+                };
+                visible.push((camera, rect, passes));
+            }
+            visible
+        }
+        None => {
+            let (width, height) = (viewport.width as i32, viewport.height as i32);
+            vec![(camera, ViewRect { x: 0, y: 0, width, height }, ViewPasses::default())]
+        }
+    }
+}
+
+/// Restrict drawing and clearing to the part of the viewport a view covers
+fn set_view_rect(gl: &Context, gl_state: &mut GlState, rect: ViewRect) {
+    gl_state.set_enabled(gl, glow::SCISSOR_TEST, true);
+    unsafe {
+        gl.viewport(rect.x, rect.y, rect.width, rect.height);
+        gl.scissor(rect.x, rect.y, rect.width, rect.height);
+    }
+}
+
+/// Draws a pass of the frame graph into its framebuffer, which is bound when it is called
+pub type RenderNode = fn(&mut PassContext);
+
+/// What the passes of a frame draw, and the GL state they draw with
+pub struct PassContext<'a> {
+    pub gl: &'a Context,
+    pub render_state: &'a RenderState,
+    /// Meshes that are drawn, leaving out pooled and hidden ones
+    pub geometry: &'a [GeometryQuery<'a>],
+    /// Terrains that are drawn, leaving out hidden ones
+    pub terrain: &'a [TerrainQuery<'a>],
+    /// Point lights that are not hidden, with where they are
+    pub lights: &'a [(&'a PointLight, &'a Position)],
+    /// Camera of the active view
+    pub camera: &'a Camera,
+    /// Visible views, with their view-projection matrix in the previous frame
+    pub views: &'a [(&'a Camera, ViewRect, ViewPasses, glm::Mat4)],
+    pub light_space_matrix: glm::Mat4,
+    pub lighting: Lighting,
+    pub palette: &'a Palette,
+    pub globals: &'a ShaderGlobals,
+    pub debug_draw: &'a DebugDraw,
+    pub gl_state: &'a mut GlState,
+    pub profiler: &'a mut Profiler,
+    pub error_checks: &'a mut GlErrorChecks,
+}
+
+/// Projection of the scene onto the shadow map, looking along the direction the sun shines in
+pub fn light_space_matrix(sun_direction: &glm::Vec3) -> glm::Mat4 {
+    // Any up vector works unless it is parallel to the sun, which it is at noon
+    let vertical = sun_direction.y.abs() > 0.99 * sun_direction.norm();
+    let up = if vertical { glm::Vec3::z() } else { glm::Vec3::y() };
+    glm::ortho(-15.0f32, 15.0, -10.0, 10.0, -15.0, 15.0)
+        * glm::look_at(&-sun_direction, &glm::Vec3::zeros(), &up)
+}
+
+/// Nodes of the passes in `RenderState::frame_graph`
+pub fn builtin_nodes() -> [(&'static str, RenderNode); 6] {
+    [
+        (SHADOW_PASS, shadow_node),
+        (GEOMETRY_PASS, geometry_node),
+        (LIGHTING_PASS, lighting_node),
+        (REFLECTION_PASS, reflection_node),
+        (DEPTH_OF_FIELD_PASS, depth_of_field_node),
+        (MOTION_BLUR_PASS, motion_blur_node),
+    ]
+}
+
+/// Run the enabled passes of the frame graph in dependency order, each with its node
+#[allow(clippy::too_many_arguments)]
 pub fn render(
     gl: NonSend<Arc<Context>>,
     camera: Res<Camera>,
-    render_state: Res<RenderState>,
-    window: Res<WinitWindow>,
-    geometry: Query<GeometryQuery>,
-    lights: Query<(&PointLight, &Position)>,
-    mut commands: Commands,
+    viewport: Res<Viewport>,
+    layout: Option<Res<ViewLayout>>,
+    mut render_state: ResMut<RenderState>,
+    geometry: Query<GeometryQuery, Drawn>,
+    terrain: Query<TerrainQuery, Drawn>,
+    lights: Query<(&PointLight, &Position), Without<Hidden>>,
+    lighting: Option<Res<Lighting>>,
+    palette: Res<Palette>,
+    globals: Res<ShaderGlobals>,
+    mut gl_state: ResMut<GlState>,
+    mut profiler: ResMut<Profiler>,
+    // Where errors in the passes and the built-in shaders are reported
+    (mut error_checks, mut notifications): (ResMut<GlErrorChecks>, Option<ResMut<Notifications>>),
+    debug_draw: Res<DebugDraw>,
+    mut previous_view_projections: Local<Vec<glm::Mat4>>,
 ) {
-    let window_size = window.inner_size();
+    // egui_glow and the resource loaders change GL state without going through the cache
+    gl_state.invalidate();
+    error_checks.check(&gl, "update", ErrorCheckMode::Passes);
+
+    // Compile the shader variants the meshes need before the passes borrow the render state. A
+    // variant that does not compile is a bug in the built-in shaders, reported once.
+    let mut report = |result: Result<()>| {
+        if let Err(e) = result {
+            error!("{e}");
+            if let Some(notifications) = notifications.as_deref_mut() {
+                notifications.error(format!("A built-in shader is broken: {e}"));
+            }
+        }
+    };
+    for (_, mesh, .., custom_texture, skeleton, _, lod) in &geometry {
+        let levels = lod.map_or(&[][..], |lod| lod.meshes.as_slice());
+        for mesh in std::iter::once(mesh).chain(levels) {
+            let features = shader_features(mesh, custom_texture, skeleton);
+            report(render_state.geometry_pass_shaders.prepare(&gl, features));
+            report(render_state.depth_shaders.prepare(&gl, depth_features(features)));
+        }
+    }
+    if !terrain.is_empty() {
+        let features = ShaderFeatures { splat: true, ..Default::default() };
+        report(render_state.geometry_pass_shaders.prepare(&gl, features));
+    }
+
+    // The shadow map is shared by the views, so it is only skipped when no view shows shadows
+    let visible = visible_views(&camera, layout.as_deref(), &viewport);
+    let shadows = visible.iter().any(|(.., passes)| passes.shadows);
+    render_state.frame_graph.set_enabled(SHADOW_PASS, shadows);
+    let render_state = &*render_state;
 
-    let light_space_matrix = glm::ortho(-15.0f32, 15.0, -10.0, 10.0, -15.0, 15.0)
-        * glm::look_at(
-            &glm::vec3(0.2, 0.7, 0.5),
-            &glm::vec3(0.0, 0.0, 0.0),
-            &glm::vec3(0.0, 1.0, 0.0),
+    let lighting = lighting.as_deref().copied().unwrap_or_default();
+    let light_space_matrix = light_space_matrix(&lighting.sun.direction);
+
+    let geometry: Vec<_> = geometry.iter().collect();
+    let terrain: Vec<_> = terrain.iter().collect();
+    let lights: Vec<_> = lights.iter().collect();
+    let views: Vec<_> = visible
+        .iter()
+        .enumerate()
+        .map(|(i, &(camera, rect, passes))| {
+            let vp = camera.projection * camera.view();
+            (camera, rect, passes, previous_view_projections.get(i).copied().unwrap_or(vp))
+        })
+        .collect();
+    let mut ctx = PassContext {
+        gl: &gl,
+        render_state,
+        geometry: &geometry,
+        terrain: &terrain,
+        lights: &lights,
+        camera: &camera,
+        views: &views,
+        light_space_matrix,
+        lighting,
+        palette: &palette,
+        globals: &globals,
+        debug_draw: &debug_draw,
+        gl_state: &mut gl_state,
+        profiler: &mut profiler,
+        error_checks: &mut error_checks,
+    };
+
+    for pass in render_state.frame_graph.passes() {
+        ctx.profiler.begin_pass(&gl, pass);
+        unsafe {
+            render_state.frame_graph.begin_pass(&gl, pass);
+        }
+
+        match render_state.node(pass) {
+            Some(node) => node(&mut ctx),
+            None => warn!("no renderer for pass {:?}", pass),
+        }
+        // The scissor test would limit resolving to the last view
+        ctx.gl_state.set_enabled(&gl, glow::SCISSOR_TEST, false);
+        unsafe {
+            render_state.frame_graph.end_pass(&gl, pass);
+        }
+        ctx.profiler.end_pass(&gl, pass);
+        ctx.error_checks.check(&gl, pass, ErrorCheckMode::Passes);
+    }
+
+    *previous_view_projections =
+        visible.iter().map(|(camera, ..)| camera.projection * camera.view()).collect();
+}
+
+type PreviousTransformQuery<'a> = (Entity, &'a GlobalTransform, Option<&'a mut PreviousTransform>);
+
+/// Remember the model matrix each entity was drawn with, for motion vectors in the next frame
+pub fn store_previous_transforms(mut query: Query<PreviousTransformQuery>, mut commands: Commands) {
+    for (entity, &GlobalTransform(model), previous) in &mut query {
+        match previous {
+            Some(mut previous) => previous.0 = model,
+            None => {
+                commands.entity(entity).insert(PreviousTransform(model));
+            }
+        }
+    }
+}
+
+/// Render the scene from the sun into the shadow map
+fn shadow_node(ctx: &mut PassContext) {
+    shadow_pass(
+        ctx.gl,
+        ctx.render_state,
+        ctx.geometry,
+        &ctx.light_space_matrix,
+        &ctx.camera.pos,
+        ctx.gl_state,
+        ctx.profiler,
+        ctx.error_checks,
+    );
+    terrain_shadow_pass(
+        ctx.gl,
+        ctx.render_state,
+        ctx.terrain,
+        &ctx.light_space_matrix,
+        ctx.gl_state,
+        ctx.profiler,
+        ctx.error_checks,
+    );
+}
+
+/// Fill the G-buffer for each visible view
+fn geometry_node(ctx: &mut PassContext) {
+    for &(camera, rect, passes, previous_vp) in ctx.views {
+        set_view_rect(ctx.gl, ctx.gl_state, rect);
+        geometry_pass(
+            ctx.gl,
+            camera,
+            &previous_vp,
+            ctx.render_state,
+            ctx.geometry,
+            ctx.globals,
+            passes.outlines,
+            ctx.gl_state,
+            ctx.profiler,
+            ctx.error_checks,
+        );
+        terrain_pass(
+            ctx.gl,
+            camera,
+            &previous_vp,
+            ctx.render_state,
+            ctx.terrain,
+            ctx.gl_state,
+            ctx.profiler,
+            ctx.error_checks,
         );
+    }
+}
 
-    // Shadow map pass
-    unsafe {
-        let (width, height) = render_state.shadow_map_size;
-        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(render_state.shadow_map_fbo));
-        gl.viewport(0, 0, width, height);
+/// Light the G-buffer into the scene color for each visible view
+fn lighting_node(ctx: &mut PassContext) {
+    for &(camera, rect, passes, _) in ctx.views {
+        set_view_rect(ctx.gl, ctx.gl_state, rect);
+        lighting_pass(
+            ctx.gl,
+            camera,
+            ctx.render_state,
+            ctx.lights,
+            &ctx.lighting,
+            &ctx.light_space_matrix,
+            ctx.palette,
+            &ctx.debug_draw,
+            rect,
+            passes,
+            ctx.gl_state,
+            ctx.profiler,
+        );
+    }
+}
 
-        gl.clear(glow::DEPTH_BUFFER_BIT);
+/// Copy the lit scene for each visible view, with reflections when they are enabled
+fn reflection_node(ctx: &mut PassContext) {
+    for &(camera, rect, ..) in ctx.views {
+        set_view_rect(ctx.gl, ctx.gl_state, rect);
+        reflection_pass(ctx.gl, camera, ctx.render_state, rect, ctx.gl_state, ctx.profiler);
+    }
+}
 
-        // Fix after egui_glow and prepare for shadow mapping
-        gl.enable(glow::DEPTH_TEST);
-        gl.depth_func(glow::LESS);
-        gl.enable(glow::CULL_FACE);
-        gl.cull_face(glow::BACK);
+/// Copy the reflected scene for each visible view, blurred outside the focus distance when depth
+/// of field is enabled
+fn depth_of_field_node(ctx: &mut PassContext) {
+    for &(camera, rect, ..) in ctx.views {
+        set_view_rect(ctx.gl, ctx.gl_state, rect);
+        depth_of_field_pass(ctx.gl, camera, ctx.render_state, rect, ctx.gl_state, ctx.profiler);
+    }
+}
 
-        render_state.depth_shader.activate(&gl);
-        render_state.depth_shader.uniform_mat4(&gl, "light_space_matrix", &light_space_matrix);
+/// Copy the focused scene into the scene color for each visible view, blurred along the motion
+/// of each pixel when motion blur is enabled
+fn motion_blur_node(ctx: &mut PassContext) {
+    for &(_, rect, ..) in ctx.views {
+        set_view_rect(ctx.gl, ctx.gl_state, rect);
+        motion_blur_pass(ctx.gl, ctx.render_state, rect, ctx.gl_state, ctx.profiler);
     }
+}
 
-    for (_, mesh, &pos, &rot, &scale, _, _, _) in &geometry {
-        let model = glm::translation(&pos.into())
-            * glm::rotation(rot.y.to_radians(), &glm::vec3(0.0, 1.0, 0.0))
-            * glm::rotation(rot.x.to_radians(), &glm::vec3(1.0, 0.0, 0.0))
-            * glm::rotation(rot.z.to_radians(), &glm::vec3(0.0, 0.0, 1.0))
-            * glm::scaling(&scale.into());
+#[allow(clippy::too_many_arguments)]
+fn shadow_pass(
+    gl: &Context,
+    render_state: &RenderState,
+    geometry: &[GeometryQuery],
+    light_space_matrix: &glm::Mat4,
+    eye: &glm::Vec3,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+    error_checks: &mut GlErrorChecks,
+) {
+    gl_state.set_enabled(gl, glow::SCISSOR_TEST, false);
+    gl_state.set_enabled(gl, glow::BLEND, false);
+    gl_state.set_enabled(gl, glow::STENCIL_TEST, false);
+    gl_state.set_enabled(gl, glow::DEPTH_TEST, true);
+    gl_state.depth_func(gl, glow::LESS);
+    gl_state.depth_mask(gl, true);
+    gl_state.set_enabled(gl, glow::CULL_FACE, true);
+    gl_state.cull_face(gl, glow::BACK);
+
+    unsafe {
+        gl.clear(glow::DEPTH_BUFFER_BIT);
+    }
+
+    for (_, mesh, &GlobalTransform(model), _, _, _, custom_texture, skeleton, _, lod) in
+        geometry.iter().copied()
+    {
+        // Shadows are cast by the level the main camera sees
+        let mesh = Lod::select(lod, mesh, &model, eye);
+        let features = depth_features(shader_features(mesh, custom_texture, skeleton));
+        let Some(shader) = render_state.depth_shaders.get(features) else {
+            continue;
+        };
+        gl_state.use_program(gl, Some(shader.program));
+        if features.alpha_test {
+            // Cut out where the diffuse texture is, so the holes let light through
+            let texture = custom_texture.and_then(|t| t.diffuse);
+            gl_state.bind_texture(gl, 0, Some(texture.unwrap_or(render_state.default_diffuse)));
+        }
 
         unsafe {
-            render_state.depth_shader.uniform_mat4(&gl, "model", &model);
+            shader.uniform_mat4(gl, "light_space_matrix", light_space_matrix);
+            shader.uniform_mat4(gl, "model", &model);
+            shader.uniform_int(gl, "diffuse_tx", 0);
+            set_skinning(gl, shader, skeleton);
             gl.bind_vertex_array(Some(mesh.vao_id));
-            gl.draw_elements(glow::TRIANGLES, mesh.indices_len as i32, glow::UNSIGNED_INT, 0);
+            gl_check!(
+                gl,
+                gl.draw_elements(glow::TRIANGLES, mesh.indices_len as i32, glow::UNSIGNED_INT, 0)
+            );
+            profiler.count_draw(mesh.indices_len as u32 / 3);
+            error_checks.check(gl, SHADOW_PASS, ErrorCheckMode::DrawCalls);
         }
     }
+}
 
-    // Geometry pass
-    unsafe {
-        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(render_state.g_buffer));
-        gl.viewport(0, 0, window_size.width as i32, window_size.height as i32);
+#[allow(clippy::too_many_arguments)]
+fn geometry_pass(
+    gl: &Context,
+    camera: &Camera,
+    previous_vp: &glm::Mat4,
+    render_state: &RenderState,
+    geometry: &[GeometryQuery],
+    globals: &ShaderGlobals,
+    outlines: bool,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+    error_checks: &mut GlErrorChecks,
+) {
+    gl_state.set_enabled(gl, glow::BLEND, false);
+    gl_state.set_enabled(gl, glow::DEPTH_TEST, true);
+    gl_state.depth_func(gl, glow::LESS);
+    gl_state.depth_mask(gl, true);
+    gl_state.set_enabled(gl, glow::CULL_FACE, true);
+    gl_state.cull_face(gl, glow::BACK);
+    gl_state.set_enabled(gl, glow::STENCIL_TEST, true);
+    gl_state.stencil_op(gl, glow::KEEP, glow::KEEP, glow::REPLACE);
+    gl_state.stencil_mask(gl, 0xFF);
 
+    // Draw buffer of the entity IDs, which as an integer buffer is cleared on its own
+    let id_buffer = render_state
+        .frame_graph
+        .color_attachment(GEOMETRY_PASS, ENTITY_ID)
+        .map(|attachment| attachment - glow::COLOR_ATTACHMENT0);
+    unsafe {
         gl.clear_color(0.0, 0.0, 0.0, 0.0);
-        gl.stencil_mask(0xFF);
         gl.clear_stencil(0);
         gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT | glow::STENCIL_BUFFER_BIT);
-
-        gl.disable(glow::BLEND);
-
-        gl.enable(glow::STENCIL_TEST);
-        gl.stencil_op(glow::KEEP, glow::KEEP, glow::REPLACE);
+        if let Some(id_buffer) = id_buffer {
+            gl.clear_buffer_u32_slice(glow::COLOR, id_buffer, &[0; 4]);
+        }
     }
 
-    let vp =
-        camera.projection * glm::look_at(&camera.pos, &(camera.pos + camera.front), &camera.up);
+    let vp = camera.projection * camera.view();
 
-    for (i, (entity, mesh, &pos, &rot, &scale, selected, custom_shader, custom_texture)) in
-        geometry.iter().enumerate()
+    for (
+        i,
+        (
+            entity,
+            mesh,
+            &GlobalTransform(model),
+            selected,
+            custom_shader,
+            shader_params,
+            custom_texture,
+            skeleton,
+            previous,
+            lod,
+        ),
+    ) in geometry.iter().copied().enumerate()
     {
-        let model = glm::translation(&pos.into())
-            * glm::rotation(rot.y.to_radians(), &glm::vec3(0.0, 1.0, 0.0))
-            * glm::rotation(rot.x.to_radians(), &glm::vec3(1.0, 0.0, 0.0))
-            * glm::rotation(rot.z.to_radians(), &glm::vec3(0.0, 0.0, 1.0))
-            * glm::scaling(&scale.into());
+        let mesh = Lod::select(lod, mesh, &model, &camera.pos);
+        let previous_model = previous.map_or(model, |p| p.0);
 
         let mvp = vp * model;
+        let previous_mvp = previous_vp * previous_model;
         let normal_mat = glm::mat4_to_mat3(&model.try_inverse().unwrap().transpose());
-        let id = i + 1;
+        // Only outlines use the stencil, so its 8 bits wrapping around does not matter much
+        let stencil = (i % 255 + 1) as i32;
 
-        let shader = if let Some(CustomShader { shader: Ok(shader), .. }) = custom_shader {
-            shader
-        } else {
-            &render_state.geometry_pass_shader
+        let features = shader_features(mesh, custom_texture, skeleton);
+        let Some(variant) = render_state.geometry_pass_shaders.get(features) else {
+            continue;
         };
-        shader.activate(&gl);
+        let custom = custom_shader
+            .and_then(|cs| cs.shader.as_ref().ok())
+            .filter(|_| render_state.custom_shaders);
+        let shader = custom.unwrap_or(variant);
+        gl_state.use_program(gl, Some(shader.program));
+
+        let texture = custom_texture.copied().unwrap_or_default();
+        gl_state.bind_texture(gl, 0, Some(texture.diffuse.unwrap_or(render_state.default_diffuse)));
+        gl_state.bind_texture(
+            gl,
+            1,
+            Some(texture.specular.unwrap_or(render_state.default_specular)),
+        );
+        if features.normal_map {
+            gl_state.bind_texture(gl, 2, texture.normal);
+        }
 
         unsafe {
-            let texture = custom_texture.copied().unwrap_or_default();
-            let diffuse = texture.diffuse.unwrap_or(render_state.default_diffuse);
-            let specular = texture.specular.unwrap_or(render_state.default_specular);
-            gl.active_texture(glow::TEXTURE0);
-            gl.bind_texture(glow::TEXTURE_2D, Some(diffuse));
-            gl.active_texture(glow::TEXTURE1);
-            gl.bind_texture(glow::TEXTURE_2D, Some(specular));
-            shader.uniform_int(&gl, "diffuse_tx", 0);
-            shader.uniform_int(&gl, "specular_tx", 1);
-
-            shader.uniform_mat4(&gl, "mvp", &mvp);
-            shader.uniform_mat4(&gl, "model", &model);
-            shader.uniform_mat3(&gl, "normal_mat", &normal_mat);
-            shader.uniform_float(&gl, "selected", 0.0);
-
-            gl.stencil_func(glow::ALWAYS, id as i32, 0xFF);
+            shader.uniform_int(gl, "diffuse_tx", 0);
+            shader.uniform_int(gl, "specular_tx", 1);
+            shader.uniform_int(gl, "normal_tx", 2);
+
+            shader.uniform_mat4(gl, "mvp", &mvp);
+            shader.uniform_mat4(gl, "previous_mvp", &previous_mvp);
+            shader.uniform_mat4(gl, "model", &model);
+            shader.uniform_mat3(gl, "normal_mat", &normal_mat);
+            shader.uniform_float(gl, "selected", 0.0);
+            shader.uniform_uint(gl, "entity_id", entity.index() + 1);
+            set_skinning(gl, shader, skeleton);
+            if custom.is_some() {
+                set_shader_globals(gl, shader, globals, camera);
+                for (name, value) in shader_params.iter().flat_map(|p| &p.values) {
+                    shader.uniform_value(gl, name, value);
+                }
+            }
+
+            gl_state.stencil_func(gl, glow::ALWAYS, stencil, 0xFF);
             gl.bind_vertex_array(Some(mesh.vao_id));
-            gl.draw_elements(glow::TRIANGLES, mesh.indices_len as i32, glow::UNSIGNED_INT, 0);
+            // Custom shaders may leave the entity ID unwritten, so it is drawn by the built-in
+            // shader after them
+            let custom_id_buffer = id_buffer.filter(|_| custom.is_some());
+            if let Some(id_buffer) = custom_id_buffer {
+                gl.color_mask_draw_buffer(id_buffer, false, false, false, false);
+            }
+            gl_check!(
+                gl,
+                gl.draw_elements(glow::TRIANGLES, mesh.indices_len as i32, glow::UNSIGNED_INT, 0)
+            );
+            profiler.count_draw(mesh.indices_len as u32 / 3);
+            error_checks.check(gl, GEOMETRY_PASS, ErrorCheckMode::DrawCalls);
+            if let Some(id_buffer) = custom_id_buffer {
+                // Write only the entity ID where the custom shader drew, leaving the depth and
+                // stencil as they are
+                gl_state.use_program(gl, Some(variant.program));
+                variant.uniform_mat4(gl, "mvp", &mvp);
+                variant.uniform_mat4(gl, "previous_mvp", &previous_mvp);
+                variant.uniform_mat4(gl, "model", &model);
+                variant.uniform_mat3(gl, "normal_mat", &normal_mat);
+                variant.uniform_float(gl, "selected", 0.0);
+                variant.uniform_uint(gl, "entity_id", entity.index() + 1);
+                set_skinning(gl, variant, skeleton);
+                gl.color_mask(false, false, false, false);
+                gl.color_mask_draw_buffer(id_buffer, true, true, true, true);
+                gl_state.depth_func(gl, glow::LEQUAL);
+                gl_state.depth_mask(gl, false);
+                gl_state.stencil_mask(gl, 0x00);
+                gl_check!(
+                    gl,
+                    gl.draw_elements(
+                        glow::TRIANGLES,
+                        mesh.indices_len as i32,
+                        glow::UNSIGNED_INT,
+                        0
+                    )
+                );
+                profiler.count_draw(mesh.indices_len as u32 / 3);
+                error_checks.check(gl, GEOMETRY_PASS, ErrorCheckMode::DrawCalls);
+                gl.color_mask(true, true, true, true);
+                gl_state.depth_func(gl, glow::LESS);
+                gl_state.depth_mask(gl, true);
+                gl_state.stencil_mask(gl, 0xFF);
+            }
 
-            if selected.is_some() {
-                // Redraw the object in bigger scale, with stencil testing and outline
-                let mvp = mvp
-                    * glm::scaling(
-                        &glm::Vec3::from(scale)
-                            .add_scalar(0.1)
-                            .component_div(&glm::Vec3::from(scale)),
-                    );
-
-                render_state.geometry_pass_shader.activate(&gl);
-                render_state.geometry_pass_shader.uniform_int(&gl, "diffuse_tx", 0);
-                render_state.geometry_pass_shader.uniform_int(&gl, "specular_tx", 1);
-
-                render_state.geometry_pass_shader.uniform_mat4(&gl, "mvp", &mvp);
-                render_state.geometry_pass_shader.uniform_mat4(&gl, "model", &model);
-                render_state.geometry_pass_shader.uniform_mat3(&gl, "normal_mat", &normal_mat);
-                render_state.geometry_pass_shader.uniform_float(&gl, "selected", 1.0);
-
-                // Disable writing to the stencil buffer
-                gl.stencil_mask(0x00);
+            if selected.is_some() && outlines {
+                // Redraw the object in bigger scale, with stencil testing and outline. The scale in
+                // the world, with the scale of the parents, is the length of each model axis.
+                let scale = glm::Vec3::from_fn(|axis, _| model.fixed_view::<3, 1>(0, axis).norm());
+                let outline_scale = glm::scaling(&scale.add_scalar(0.1).component_div(&scale));
+                let mvp = mvp * outline_scale;
+
+                gl_state.use_program(gl, Some(variant.program));
+                variant.uniform_int(gl, "diffuse_tx", 0);
+                variant.uniform_int(gl, "specular_tx", 1);
+                variant.uniform_int(gl, "normal_tx", 2);
+
+                variant.uniform_mat4(gl, "mvp", &mvp);
+                variant.uniform_mat4(gl, "previous_mvp", &(previous_mvp * outline_scale));
+                variant.uniform_mat4(gl, "model", &model);
+                variant.uniform_mat3(gl, "normal_mat", &normal_mat);
+                variant.uniform_float(gl, "selected", 1.0);
+                set_skinning(gl, variant, skeleton);
+
+                // Disable writing to the stencil buffer, and the entity IDs so the outline can
+                // not be picked
+                gl_state.stencil_mask(gl, 0x00);
+                if let Some(id_buffer) = id_buffer {
+                    gl.color_mask_draw_buffer(id_buffer, false, false, false, false);
+                }
                 // Pass if the fragment does not overlap with the object we're highlighting
-                gl.stencil_func(glow::NOTEQUAL, id as i32, 0xFF);
-                gl.draw_elements(glow::TRIANGLES, mesh.indices_len as i32, glow::UNSIGNED_INT, 0);
-                // Re-enable writing to the stencil buffer
-                gl.stencil_mask(0xFF);
+                gl_state.stencil_func(gl, glow::NOTEQUAL, stencil, 0xFF);
+                gl_check!(
+                    gl,
+                    gl.draw_elements(
+                        glow::TRIANGLES,
+                        mesh.indices_len as i32,
+                        glow::UNSIGNED_INT,
+                        0
+                    )
+                );
+                profiler.count_draw(mesh.indices_len as u32 / 3);
+                error_checks.check(gl, GEOMETRY_PASS, ErrorCheckMode::DrawCalls);
+                // Re-enable writing to the stencil buffer and the entity IDs
+                gl_state.stencil_mask(gl, 0xFF);
+                if let Some(id_buffer) = id_buffer {
+                    gl.color_mask_draw_buffer(id_buffer, true, true, true, true);
+                }
             }
         }
+    }
+}
 
-        commands.entity(entity).insert(StencilId(id));
+/// Draw the chunks of each terrain into the shadow map, after the meshes
+fn terrain_shadow_pass(
+    gl: &Context,
+    render_state: &RenderState,
+    terrain: &[TerrainQuery],
+    light_space_matrix: &glm::Mat4,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+    error_checks: &mut GlErrorChecks,
+) {
+    let Some(shader) = render_state.depth_shaders.get(ShaderFeatures::default()) else {
+        return;
+    };
+    gl_state.use_program(gl, Some(shader.program));
+
+    for &(_, _, chunks, &GlobalTransform(model), _) in terrain {
+        unsafe {
+            shader.uniform_mat4(gl, "light_space_matrix", light_space_matrix);
+            shader.uniform_mat4(gl, "model", &model);
+            set_skinning(gl, shader, None);
+            for mesh in &chunks.meshes {
+                gl.bind_vertex_array(Some(mesh.vao_id));
+                gl_check!(
+                    gl,
+                    gl.draw_elements(
+                        glow::TRIANGLES,
+                        mesh.indices_len as i32,
+                        glow::UNSIGNED_INT,
+                        0
+                    )
+                );
+                profiler.count_draw(mesh.indices_len as u32 / 3);
+                error_checks.check(gl, SHADOW_PASS, ErrorCheckMode::DrawCalls);
+            }
+        }
     }
+}
 
-    // Deferred lighting pass
-    unsafe {
-        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-        gl.viewport(0, 0, window_size.width as i32, window_size.height as i32);
+/// Draw the chunks of each terrain into the G-buffer, after the meshes, blending its layer
+/// textures by the weights of the vertices
+///
+/// Terrains are not outlined when selected, as the outline would cover most of the view.
+#[allow(clippy::too_many_arguments)]
+fn terrain_pass(
+    gl: &Context,
+    camera: &Camera,
+    previous_vp: &glm::Mat4,
+    render_state: &RenderState,
+    terrain: &[TerrainQuery],
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+    error_checks: &mut GlErrorChecks,
+) {
+    let features = ShaderFeatures { splat: true, ..Default::default() };
+    let Some(shader) = render_state.geometry_pass_shaders.get(features) else {
+        return;
+    };
+    gl_state.use_program(gl, Some(shader.program));
+    // Nothing is outlined against the terrain, so it leaves the stencil as it was cleared
+    gl_state.stencil_func(gl, glow::ALWAYS, 0, 0xFF);
 
-        gl.clear_color(0.0, 0.0, 0.0, 0.0);
-        gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+    let vp = camera.projection * camera.view();
+    for &(entity, terrain, chunks, &GlobalTransform(model), previous) in terrain {
+        let previous_model = previous.map_or(model, |p| p.0);
+        let normal_mat = glm::mat4_to_mat3(&model.try_inverse().unwrap().transpose());
 
-        // Disable stencil test to make sure the quad and UI are drawn correctly
-        gl.disable(glow::STENCIL_TEST);
+        gl_state.bind_texture(gl, 1, Some(render_state.default_specular));
+        for (i, layer) in terrain.layers.iter().enumerate() {
+            let texture = layer.unwrap_or(render_state.default_diffuse);
+            gl_state.bind_texture(gl, 3 + i as u32, Some(texture));
+        }
 
-        render_state.deferred_pass_shader.activate(&gl);
+        unsafe {
+            shader.uniform_int(gl, "specular_tx", 1);
+            for i in 0..terrain.layers.len() {
+                shader.uniform_int(gl, &format!("layer_tx[{i}]"), 3 + i as i32);
+            }
+            shader.uniform_mat4(gl, "mvp", &(vp * model));
+            shader.uniform_mat4(gl, "previous_mvp", &(previous_vp * previous_model));
+            shader.uniform_mat4(gl, "model", &model);
+            shader.uniform_mat3(gl, "normal_mat", &normal_mat);
+            shader.uniform_float(gl, "selected", 0.0);
+            shader.uniform_uint(gl, "entity_id", entity.index() + 1);
+            set_skinning(gl, shader, None);
 
-        gl.active_texture(glow::TEXTURE0);
-        gl.bind_texture(glow::TEXTURE_2D, Some(render_state.g_position));
-        gl.active_texture(glow::TEXTURE1);
-        gl.bind_texture(glow::TEXTURE_2D, Some(render_state.g_normal));
-        gl.active_texture(glow::TEXTURE2);
-        gl.bind_texture(glow::TEXTURE_2D, Some(render_state.g_albedo_spec));
-        gl.active_texture(glow::TEXTURE3);
-        gl.bind_texture(glow::TEXTURE_2D, Some(render_state.shadow_map));
+            for mesh in &chunks.meshes {
+                gl.bind_vertex_array(Some(mesh.vao_id));
+                gl_check!(
+                    gl,
+                    gl.draw_elements(
+                        glow::TRIANGLES,
+                        mesh.indices_len as i32,
+                        glow::UNSIGNED_INT,
+                        0
+                    )
+                );
+                profiler.count_draw(mesh.indices_len as u32 / 3);
+                error_checks.check(gl, GEOMETRY_PASS, ErrorCheckMode::DrawCalls);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn lighting_pass(
+    gl: &Context,
+    camera: &Camera,
+    render_state: &RenderState,
+    lights: &[(&PointLight, &Position)],
+    lighting: &Lighting,
+    light_space_matrix: &glm::Mat4,
+    palette: &Palette,
+    debug_draw: &DebugDraw,
+    rect: ViewRect,
+    passes: ViewPasses,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+) {
+    // Disable stencil test to make sure the quad is drawn correctly
+    gl_state.set_enabled(gl, glow::STENCIL_TEST, false);
+    gl_state.depth_mask(gl, true);
+    gl_state.use_program(gl, Some(render_state.deferred_pass_shader.program));
+
+    let frame_graph = &render_state.frame_graph;
+    let g_buffer = [(0, "g_position"), (1, "g_normal"), (2, "g_albedo_spec"), (4, "g_velocity")];
+    for (unit, name) in g_buffer {
+        if render_state.samples() > 1 {
+            gl_state.bind_texture_multisample(gl, unit, frame_graph.texture(name));
+        } else {
+            gl_state.bind_texture(gl, unit, frame_graph.texture(name));
+        }
+    }
+    gl_state.bind_texture(gl, 3, frame_graph.texture("shadow_map"));
+    if let Some(environment_map) = render_state.environment_map {
+        gl_state.bind_texture(gl, 8, Some(environment_map));
+    }
 
-        render_state.deferred_pass_shader.uniform_int(&gl, "position_tx", 0);
-        render_state.deferred_pass_shader.uniform_int(&gl, "normal_tx", 1);
-        render_state.deferred_pass_shader.uniform_int(&gl, "albedo_spec_tx", 2);
-        render_state.deferred_pass_shader.uniform_vec3(&gl, "view_pos", &camera.pos);
+    let positions: Vec<(glm::Vec3, Option<f32>)> =
+        lights.iter().map(|&(light, &position)| (position.into(), light.range())).collect();
+    let clusters =
+        LightClusters::assign(&positions, &camera.view(), &camera.projection, camera.mode);
+
+    unsafe {
+        render_state.light_clusters.upload(gl, gl_state, 5, lights, &clusters);
+
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+
+        render_state.deferred_pass_shader.uniform_int(gl, "position_tx", 0);
+        render_state.deferred_pass_shader.uniform_int(gl, "normal_tx", 1);
+        render_state.deferred_pass_shader.uniform_int(gl, "albedo_spec_tx", 2);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "view_pos", &camera.pos);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "selection_color", &palette.selection);
 
         render_state.deferred_pass_shader.uniform_mat4(
-            &gl,
+            gl,
             "light_space_matrix",
-            &light_space_matrix,
+            light_space_matrix,
         );
-        render_state.deferred_pass_shader.uniform_int(&gl, "shadow_map_tx", 3);
-
-        // TODO: Make this configurable
-        render_state.deferred_pass_shader.uniform_vec3(
-            &gl,
-            "dir_light.direction",
-            &glm::vec3(-0.2, -0.7, -0.5),
+        render_state.deferred_pass_shader.uniform_int(gl, "shadow_map_tx", 3);
+        let shadows = &render_state.shadows;
+        render_state.deferred_pass_shader.uniform_int(
+            gl,
+            "shadow_kernel_size",
+            shadows.pcf_kernel_size,
+        );
+        render_state.deferred_pass_shader.uniform_float(
+            gl,
+            "shadow_depth_bias",
+            shadows.depth_bias,
+        );
+        render_state.deferred_pass_shader.uniform_float(
+            gl,
+            "shadow_normal_offset",
+            shadows.normal_offset,
         );
-        render_state.deferred_pass_shader.uniform_vec3(
-            &gl,
-            "dir_light.ambient",
-            &glm::vec3(0.2, 0.2, 0.2),
+        render_state.deferred_pass_shader.uniform_int(gl, "velocity_tx", 4);
+        render_state.deferred_pass_shader.uniform_int(
+            gl,
+            "show_motion_vectors",
+            debug_draw.motion_vectors as i32,
         );
-        render_state.deferred_pass_shader.uniform_vec3(
-            &gl,
-            "dir_light.diffuse",
-            &glm::vec3(0.5, 0.5, 0.5),
+        render_state.deferred_pass_shader.uniform_int(
+            gl,
+            "show_light_heatmap",
+            debug_draw.light_heatmap as i32,
         );
-        render_state.deferred_pass_shader.uniform_vec3(
-            &gl,
-            "dir_light.specular",
-            &glm::vec3(1.0, 1.0, 1.0),
+        render_state.deferred_pass_shader.uniform_int(gl, "shadows_enabled", passes.shadows as i32);
+        render_state.deferred_pass_shader.uniform_int(
+            gl,
+            "lighting_enabled",
+            passes.lighting as i32,
         );
 
-        let lights_iter = lights.iter();
-        let lights_len = lights_iter.len();
-        for (i, (light, &light_pos)) in lights_iter.enumerate() {
-            render_state.deferred_pass_shader.uniform_vec3(
-                &gl,
-                &format!("point_lights[{i}].position"),
-                &light_pos.into(),
-            );
-            render_state.deferred_pass_shader.uniform_vec3(
-                &gl,
-                &format!("point_lights[{i}].ambient"),
-                &light.ambient,
-            );
-            render_state.deferred_pass_shader.uniform_vec3(
-                &gl,
-                &format!("point_lights[{i}].diffuse"),
-                &light.diffuse,
-            );
-            render_state.deferred_pass_shader.uniform_vec3(
-                &gl,
-                &format!("point_lights[{i}].specular"),
-                &light.specular,
-            );
-            render_state.deferred_pass_shader.uniform_float(
-                &gl,
-                &format!("point_lights[{i}].constant"),
-                light.constant,
-            );
-            render_state.deferred_pass_shader.uniform_float(
-                &gl,
-                &format!("point_lights[{i}].linear"),
-                light.linear,
-            );
-            render_state.deferred_pass_shader.uniform_float(
-                &gl,
-                &format!("point_lights[{i}].quadratic"),
-                light.quadratic,
-            );
+        let sun = &lighting.sun;
+        render_state.deferred_pass_shader.uniform_vec3(gl, "dir_light.direction", &sun.direction);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "dir_light.ambient", &sun.ambient);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "dir_light.diffuse", &sun.diffuse);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "dir_light.specular", &sun.specular);
+        render_state.deferred_pass_shader.uniform_float(gl, "exposure", lighting.exposure);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "background", &lighting.background);
+        render_state.deferred_pass_shader.uniform_int(gl, "sky_enabled", lighting.sky as i32);
+        render_state.deferred_pass_shader.uniform_int(
+            gl,
+            "environment_enabled",
+            render_state.environment_map.is_some() as i32,
+        );
+        render_state.deferred_pass_shader.uniform_int(gl, "environment_tx", 8);
+        let inverse_view_projection = glm::inverse(&(camera.projection * camera.view()));
+        render_state.deferred_pass_shader.uniform_mat4(
+            gl,
+            "inverse_view_projection",
+            &inverse_view_projection,
+        );
+        let fog = &lighting.fog;
+        render_state.deferred_pass_shader.uniform_int(gl, "fog.enabled", fog.enabled as i32);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "fog.color", &fog.color);
+        render_state.deferred_pass_shader.uniform_float(gl, "fog.density", fog.density);
+        render_state.deferred_pass_shader.uniform_float(gl, "fog.start", fog.start);
+        render_state.deferred_pass_shader.uniform_float(gl, "fog.height", fog.height);
+        render_state.deferred_pass_shader.uniform_float(
+            gl,
+            "fog.height_falloff",
+            fog.height_falloff,
+        );
+        render_state.deferred_pass_shader.uniform_float(gl, "sky_distance", Camera::FAR);
+
+        render_state.deferred_pass_shader.uniform_int(gl, "light_tx", 5);
+        render_state.deferred_pass_shader.uniform_int(gl, "cluster_tx", 6);
+        render_state.deferred_pass_shader.uniform_int(gl, "light_index_tx", 7);
+        render_state.deferred_pass_shader.uniform_mat4(gl, "view", &camera.view());
+        let view_rect = glm::vec4(rect.x, rect.y, rect.width, rect.height).map(|x| x as f32);
+        render_state.deferred_pass_shader.uniform_vec4(gl, "view_rect", &view_rect);
+        let [columns, rows, slices] = CLUSTERS.map(|x| x as f32);
+        let cluster_count = glm::vec3(columns, rows, slices);
+        render_state.deferred_pass_shader.uniform_vec3(gl, "cluster_count", &cluster_count);
+        let depth_range = glm::vec2(Camera::NEAR, Camera::FAR);
+        render_state.deferred_pass_shader.uniform_vec2(gl, "cluster_depth", &depth_range);
+        let linear_slices = camera.mode == ProjectionMode::Orthographic;
+        render_state.deferred_pass_shader.uniform_int(gl, "linear_slices", linear_slices as i32);
+        render_state.deferred_pass_shader.uniform_int(gl, "light_index_row", INDEX_ROW as i32);
+
+        draw_quad(gl, render_state, profiler);
+    }
+}
+
+/// How far reflected rays are traced, in world units
+const REFLECTION_DISTANCE: f32 = 20.0;
+/// Steps each reflected ray is traced in
+const REFLECTION_STEPS: i32 = 64;
+/// How far behind a surface a reflected ray can be and still hit it, in world units
+const REFLECTION_THICKNESS: f32 = 0.5;
+
+fn reflection_pass(
+    gl: &Context,
+    camera: &Camera,
+    render_state: &RenderState,
+    rect: ViewRect,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+) {
+    gl_state.set_enabled(gl, glow::STENCIL_TEST, false);
+    let shader = &render_state.reflection_pass_shader;
+    gl_state.use_program(gl, Some(shader.program));
+
+    let frame_graph = &render_state.frame_graph;
+    for (unit, name) in [(0, "g_position"), (1, "g_normal"), (2, "g_albedo_spec")] {
+        if render_state.samples() > 1 {
+            gl_state.bind_texture_multisample(gl, unit, frame_graph.texture(name));
+        } else {
+            gl_state.bind_texture(gl, unit, frame_graph.texture(name));
+        }
+    }
+    gl_state.bind_texture(gl, 3, frame_graph.texture(LIT_COLOR));
+
+    unsafe {
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+
+        shader.uniform_int(gl, "position_tx", 0);
+        shader.uniform_int(gl, "normal_tx", 1);
+        shader.uniform_int(gl, "albedo_spec_tx", 2);
+        shader.uniform_int(gl, "color_tx", 3);
+        shader.uniform_int(gl, "reflections_enabled", render_state.reflections as i32);
+        shader.uniform_vec3(gl, "view_pos", &camera.pos);
+        shader.uniform_mat4(gl, "view_projection", &(camera.projection * camera.view()));
+        let view_rect = glm::vec4(rect.x, rect.y, rect.width, rect.height).map(|x| x as f32);
+        shader.uniform_vec4(gl, "view_rect", &view_rect);
+        shader.uniform_float(gl, "max_distance", REFLECTION_DISTANCE);
+        shader.uniform_int(gl, "steps", REFLECTION_STEPS);
+        shader.uniform_float(gl, "thickness", REFLECTION_THICKNESS);
+
+        draw_quad(gl, render_state, profiler);
+    }
+}
+
+/// Largest depth of field blur radius, as a fraction of the height of the view
+const MAX_DEPTH_OF_FIELD_BLUR: f32 = 0.02;
+/// Samples per pixel of depth of field at low quality
+const DEPTH_OF_FIELD_SAMPLES: i32 = 12;
+/// Samples per pixel of motion blur at low quality
+const MOTION_BLUR_SAMPLES: i32 = 6;
+
+fn depth_of_field_pass(
+    gl: &Context,
+    camera: &Camera,
+    render_state: &RenderState,
+    rect: ViewRect,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+) {
+    gl_state.set_enabled(gl, glow::STENCIL_TEST, false);
+    let shader = &render_state.depth_of_field_shader;
+    gl_state.use_program(gl, Some(shader.program));
+
+    let frame_graph = &render_state.frame_graph;
+    for (unit, name) in [(0, "g_position"), (1, "g_normal")] {
+        if render_state.samples() > 1 {
+            gl_state.bind_texture_multisample(gl, unit, frame_graph.texture(name));
+        } else {
+            gl_state.bind_texture(gl, unit, frame_graph.texture(name));
         }
+    }
+    gl_state.bind_texture(gl, 2, frame_graph.texture(REFLECTED_COLOR));
+
+    let settings = &render_state.post_effects.depth_of_field;
+    unsafe {
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+
+        shader.uniform_int(gl, "position_tx", 0);
+        shader.uniform_int(gl, "normal_tx", 1);
+        shader.uniform_int(gl, "color_tx", 2);
+        shader.uniform_int(gl, "depth_of_field_enabled", settings.enabled as i32);
+        shader.uniform_vec3(gl, "view_pos", &camera.pos);
+        let view_rect = glm::vec4(rect.x, rect.y, rect.width, rect.height).map(|x| x as f32);
+        shader.uniform_vec4(gl, "view_rect", &view_rect);
+        shader.uniform_float(gl, "focus_distance", settings.focus_distance);
+        shader.uniform_float(gl, "aperture", settings.aperture);
+        shader.uniform_float(gl, "max_blur", MAX_DEPTH_OF_FIELD_BLUR);
+        let samples = settings.quality.samples(DEPTH_OF_FIELD_SAMPLES);
+        shader.uniform_int(gl, "samples", samples);
+
+        draw_quad(gl, render_state, profiler);
+    }
+}
 
-        render_state.deferred_pass_shader.uniform_int(&gl, "point_lights_size", lights_len as i32);
+fn motion_blur_pass(
+    gl: &Context,
+    render_state: &RenderState,
+    rect: ViewRect,
+    gl_state: &mut GlState,
+    profiler: &mut Profiler,
+) {
+    gl_state.set_enabled(gl, glow::STENCIL_TEST, false);
+    let shader = &render_state.motion_blur_shader;
+    gl_state.use_program(gl, Some(shader.program));
+
+    let frame_graph = &render_state.frame_graph;
+    if render_state.samples() > 1 {
+        gl_state.bind_texture_multisample(gl, 0, frame_graph.texture("g_velocity"));
+    } else {
+        gl_state.bind_texture(gl, 0, frame_graph.texture("g_velocity"));
+    }
+    gl_state.bind_texture(gl, 1, frame_graph.texture(FOCUSED_COLOR));
 
-        gl.bind_vertex_array(Some(render_state.quad_vao.vao_id));
+    let settings = &render_state.post_effects.motion_blur;
+    unsafe {
+        gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+
+        shader.uniform_int(gl, "velocity_tx", 0);
+        shader.uniform_int(gl, "color_tx", 1);
+        shader.uniform_int(gl, "motion_blur_enabled", settings.enabled as i32);
+        let view_rect = glm::vec4(rect.x, rect.y, rect.width, rect.height).map(|x| x as f32);
+        shader.uniform_vec4(gl, "view_rect", &view_rect);
+        shader.uniform_float(gl, "strength", settings.strength);
+        shader.uniform_int(gl, "samples", settings.quality.samples(MOTION_BLUR_SAMPLES));
+
+        draw_quad(gl, render_state, profiler);
+    }
+}
+
+/// Draw the quad that covers the view, for passes that shade every pixel
+///
+/// # Safety
+///
+/// A shader has to be in use.
+unsafe fn draw_quad(gl: &Context, render_state: &RenderState, profiler: &mut Profiler) {
+    gl.bind_vertex_array(Some(render_state.quad_vao.vao_id));
+    gl_check!(
+        gl,
         gl.draw_elements(
             glow::TRIANGLES,
             render_state.quad_vao.indices_len as i32,
             glow::UNSIGNED_INT,
             0,
+        )
+    );
+    profiler.count_draw(render_state.quad_vao.indices_len as u32 / 3);
+}
+
+type BoundsQuery<'a> = (&'a Mesh, &'a GlobalTransform, Option<&'a Selected>);
+
+/// Lines with their color, and whether a view shows them
+type LineBatch = (Vec<glm::Vec3>, glm::Vec3, fn(&ViewPasses) -> bool);
+
+/// Segments of each circle of a light range sphere
+const RANGE_SEGMENTS: usize = 48;
+
+/// Three circles around the axes, outlining a sphere as line segments
+fn sphere_lines(center: glm::Vec3, radius: f32) -> Vec<glm::Vec3> {
+    let mut lines = Vec::with_capacity(3 * RANGE_SEGMENTS * 2);
+    for axis in 0..3 {
+        let point = |i: usize| {
+            let (sin, cos) = (i as f32 / RANGE_SEGMENTS as f32 * std::f32::consts::TAU).sin_cos();
+            let mut offset = glm::Vec3::zeros();
+            offset[(axis + 1) % 3] = cos * radius;
+            offset[(axis + 2) % 3] = sin * radius;
+            center + offset
+        };
+        for i in 0..RANGE_SEGMENTS {
+            lines.extend([point(i), point(i + 1)]);
+        }
+    }
+    lines
+}
+
+/// Distance from a scene camera that its frustum is outlined to
+const FRUSTUM_GIZMO_DEPTH: f32 = 1.0;
+
+/// Edges of the frustum of a scene camera projecting onto a viewport, cut off
+/// `FRUSTUM_GIZMO_DEPTH` beyond the near plane
+fn frustum_lines(
+    component: &CameraComponent,
+    model: &glm::Mat4,
+    viewport: &Viewport,
+) -> Vec<glm::Vec3> {
+    let near = component.near.max(f32::EPSILON);
+    let gizmo = CameraComponent { near, far: near + FRUSTUM_GIZMO_DEPTH, ..*component };
+    let camera = gizmo.camera(model, viewport.width, viewport.height);
+    let inverse = glm::inverse(&(camera.projection * camera.view()));
+    let corner = |x: f32, y: f32, z: f32| {
+        let point = inverse * glm::vec4(x, y, z, 1.0);
+        point.xyz() / point.w
+    };
+    let square = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+    let mut lines = Vec::with_capacity(24);
+    for (i, &(x, y)) in square.iter().enumerate() {
+        let (next_x, next_y) = square[(i + 1) % square.len()];
+        lines.extend([corner(x, y, -1.0), corner(next_x, next_y, -1.0)]);
+        lines.extend([corner(x, y, 1.0), corner(next_x, next_y, 1.0)]);
+        lines.extend([corner(x, y, -1.0), corner(x, y, 1.0)]);
+    }
+    lines
+}
+
+/// Draw world-space bounding boxes, the range of the selected point lights and the frustums of
+/// scene cameras on top of the scene as configured in `DebugDraw`
+#[allow(clippy::too_many_arguments)]
+pub fn draw_debug_lines(
+    gl: NonSend<Arc<Context>>,
+    camera: Res<Camera>,
+    viewport: Res<Viewport>,
+    layout: Option<Res<ViewLayout>>,
+    render_state: Res<RenderState>,
+    debug_draw: Res<DebugDraw>,
+    palette: Res<Palette>,
+    mut gl_state: ResMut<GlState>,
+    mut error_checks: ResMut<GlErrorChecks>,
+    meshes: Query<BoundsQuery, Drawn>,
+    lights: Query<(&Position, &PointLight), (With<Selected>, Drawn)>,
+    cameras: Query<(&CameraComponent, &GlobalTransform, Option<&Selected>), Without<Pooled>>,
+) {
+    let mut batches: Vec<LineBatch> = Vec::new();
+    if debug_draw.bounding_boxes != BoundsDisplay::Off {
+        let mut lines = Vec::new();
+        let mut selected_lines = Vec::new();
+        for (mesh, GlobalTransform(model), selected) in &meshes {
+            let aabb = mesh.aabb.transformed(model);
+            if selected.is_some() {
+                selected_lines.extend(aabb.edges());
+            } else if debug_draw.bounding_boxes == BoundsDisplay::All {
+                lines.extend(aabb.edges());
+            }
+        }
+        batches.push((lines, palette.bounds, |passes| passes.bounding_boxes));
+        batches.push((selected_lines, palette.selected_bounds, |passes| passes.bounding_boxes));
+    }
+    if debug_draw.light_ranges {
+        for (&pos, light) in &lights {
+            let Some(range) = light.range() else {
+                continue;
+            };
+            // The hue of the light at full brightness, so dim lights still stand out
+            let color = light.diffuse / light.diffuse.max().max(f32::EPSILON);
+            batches.push((sphere_lines(pos.into(), range), color, |passes| passes.outlines));
+        }
+    }
+    let (mut frustums, mut selected_frustums) = (Vec::new(), Vec::new());
+    for (component, GlobalTransform(model), selected) in &cameras {
+        match selected {
+            // Seen from the inside when looking through it
+            Some(_) if layout.as_ref().is_some_and(|layout| layout.look_through) => {}
+            Some(_) => selected_frustums.extend(frustum_lines(component, model, &viewport)),
+            None => frustums.extend(frustum_lines(component, model, &viewport)),
+        }
+    }
+    batches.push((frustums, palette.bounds, |passes| passes.outlines));
+    batches.push((selected_frustums, palette.selected_bounds, |passes| passes.outlines));
+    if batches.iter().all(|(lines, ..)| lines.is_empty()) {
+        return;
+    }
+
+    let views = visible_views(&camera, layout.as_deref(), &viewport);
+
+    unsafe {
+        render_state.frame_graph.begin_pass(&gl, OUTPUT_PASS);
+    }
+    // Lines are drawn over everything, and the depth test is put back for the passes after this
+    let depth_test = gl_state.is_enabled(&gl, glow::DEPTH_TEST);
+    gl_state.set_enabled(&gl, glow::DEPTH_TEST, false);
+    gl_state.use_program(&gl, Some(render_state.debug_line_shader.program));
+
+    unsafe {
+        gl.bind_vertex_array(Some(render_state.debug_line_vao));
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(render_state.debug_line_vbo));
+
+        for (lines, color, shown) in batches {
+            if lines.is_empty() {
+                continue;
+            }
+            render_state.debug_line_shader.uniform_vec3(&gl, "color", &color);
+            gl_check!(
+                gl,
+                gl.buffer_data_u8_slice(
+                    glow::ARRAY_BUFFER,
+                    bytemuck::cast_slice(&lines),
+                    glow::STREAM_DRAW,
+                )
+            );
+            for &(camera, rect, passes) in &views {
+                if !shown(&passes) {
+                    continue;
+                }
+                set_view_rect(&gl, &mut gl_state, rect);
+                let vp = camera.projection * camera.view();
+                render_state.debug_line_shader.uniform_mat4(&gl, "vp", &vp);
+                gl_check!(gl, gl.draw_arrays(glow::LINES, 0, lines.len() as i32));
+            }
+        }
+    }
+    gl_state.set_enabled(&gl, glow::SCISSOR_TEST, false);
+    gl_state.set_enabled(&gl, glow::DEPTH_TEST, depth_test);
+    error_checks.check(&gl, "debug lines", ErrorCheckMode::Passes);
+}
+
+/// Copy the rendered scene to the default framebuffer
+///
+/// The editor shows the scene texture in its UI instead, so this is for applications that draw
+/// the scene straight to the window.
+pub fn present(
+    gl: NonSend<Arc<Context>>,
+    viewport: Res<Viewport>,
+    render_state: Res<RenderState>,
+    mut error_checks: ResMut<GlErrorChecks>,
+) {
+    let (width, height) = (viewport.width as i32, viewport.height as i32);
+    unsafe {
+        gl.bind_framebuffer(
+            glow::READ_FRAMEBUFFER,
+            render_state.frame_graph.framebuffer(OUTPUT_PASS),
+        );
+        gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+        gl.blit_framebuffer(
+            0,
+            0,
+            width,
+            height,
+            0,
+            0,
+            width,
+            height,
+            glow::COLOR_BUFFER_BIT,
+            glow::NEAREST,
+        );
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    }
+    error_checks.check(&gl, "present", ErrorCheckMode::Passes);
+}
+
+/// Size and quality of an image drawn by `render_offscreen`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ImageSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Samples per pixel along each axis, averaged down to the final size
+    pub supersampling: u32,
+    /// Width and height of the shadow map
+    pub shadow_map_size: i32,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        Self { width: 3840, height: 2160, supersampling: 2, shadow_map_size: 8192 }
+    }
+}
+
+/// Render the scene from the camera to an image of any size, whatever the size of the window
+///
+/// The scene is drawn with render targets of its own, without selection outlines or debug views.
+/// Returns linear RGBA float pixels, which can be brighter than 1, starting with the top row.
+pub fn render_offscreen(world: &mut World, settings: &ImageSettings) -> Result<Vec<f32>> {
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let factor = settings.supersampling.max(1);
+    let (width, height) = (settings.width * factor, settings.height * factor);
+    let max_size = unsafe {
+        let max_texture = gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE);
+        gl.get_parameter_i32(glow::MAX_RENDERBUFFER_SIZE).min(max_texture) as u32
+    };
+    if width == 0 || height == 0 {
+        return Err(eyre!("image size must not be zero"));
+    }
+    if width.max(height) > max_size || settings.shadow_map_size as u32 > max_size {
+        return Err(eyre!(
+            "{width}x{height} with a {0}x{0} shadow map is larger than the {max_size} pixels the \
+             driver supports",
+            settings.shadow_map_size
+        ));
+    }
+
+    let mut offscreen =
+        RenderState::with_shadow_map_size(&gl, (width, height), settings.shadow_map_size)?;
+    let render_state = world.remove_resource::<RenderState>();
+    if let Some(render_state) = &render_state {
+        offscreen.reflections = render_state.reflections;
+        offscreen.post_effects = render_state.post_effects;
+        offscreen.shadows = render_state.shadows;
+    }
+    let viewport = world.remove_resource::<Viewport>();
+    let layout = world.remove_resource::<ViewLayout>();
+    let debug_draw = world.remove_resource::<DebugDraw>();
+    world.insert_resource(offscreen);
+    world.insert_resource(Viewport::new(width, height));
+    world.insert_resource(DebugDraw::default());
+    let selected: Vec<_> = world.query_filtered::<Entity, With<Selected>>().iter(world).collect();
+    for &entity in &selected {
+        world.entity_mut(entity).remove::<Selected>();
+    }
+    let projection = {
+        let mut camera = world.resource_mut::<Camera>();
+        let projection = camera.projection;
+        camera.update_projection(width, height);
+        projection
+    };
+
+    let mut schedule = Schedule::default();
+    schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    schedule.add_systems((update_global_transforms, render).chain());
+    schedule.run(world);
+    let pixels = read_scene_color_hdr(&gl, world.resource::<RenderState>(), width, height);
+
+    world.resource_mut::<Camera>().projection = projection;
+    for entity in selected {
+        world.entity_mut(entity).insert(Selected);
+    }
+    if let Some(mut offscreen) = world.remove_resource::<RenderState>() {
+        unsafe {
+            offscreen.destroy(&gl);
+        }
+    }
+    world.remove_resource::<DebugDraw>();
+    if let Some(render_state) = render_state {
+        world.insert_resource(render_state);
+    }
+    if let Some(viewport) = viewport {
+        world.insert_resource(viewport);
+    }
+    if let Some(layout) = layout {
+        world.insert_resource(layout);
+    }
+    if let Some(debug_draw) = debug_draw {
+        world.insert_resource(debug_draw);
+    }
+
+    Ok(downsample(&pixels, width, height, factor))
+}
+
+/// Average each block of `factor` by `factor` pixels into one
+fn downsample(pixels: &[f32], width: u32, height: u32, factor: u32) -> Vec<f32> {
+    if factor == 1 {
+        return pixels.to_vec();
+    }
+
+    let (width, height, factor) = (width as usize, height as usize, factor as usize);
+    let (out_width, out_height) = (width / factor, height / factor);
+    let samples = (factor * factor) as f32;
+    let mut out = Vec::with_capacity(out_width * out_height * 4);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let mut sum = [0.0; 4];
+            for sy in y * factor..(y + 1) * factor {
+                for sx in x * factor..(x + 1) * factor {
+                    let i = (sy * width + sx) * 4;
+                    for (c, total) in sum.iter_mut().enumerate() {
+                        *total += pixels[i + c];
+                    }
+                }
+            }
+            out.extend(sum.map(|total| total / samples));
+        }
+    }
+    out
+}
+
+/// Read the lit scene back as RGBA8 pixels, starting with the top row
+pub fn read_scene_color(
+    gl: &Context,
+    render_state: &RenderState,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    read_scene_pixels(gl, render_state, width, height, glow::UNSIGNED_BYTE)
+}
+
+/// Read the lit scene back as linear RGBA floats, not clamped to 1, starting with the top row
+pub fn read_scene_color_hdr(
+    gl: &Context,
+    render_state: &RenderState,
+    width: u32,
+    height: u32,
+) -> Vec<f32> {
+    read_scene_pixels(gl, render_state, width, height, glow::FLOAT)
+}
+
+fn read_scene_pixels<T: bytemuck::Pod>(
+    gl: &Context,
+    render_state: &RenderState,
+    width: u32,
+    height: u32,
+    ty: u32,
+) -> Vec<T> {
+    let row_len = width as usize * 4;
+    let mut pixels = vec![T::zeroed(); row_len * height as usize];
+    unsafe {
+        gl.bind_framebuffer(
+            glow::READ_FRAMEBUFFER,
+            render_state.frame_graph.framebuffer(OUTPUT_PASS),
+        );
+        gl.read_pixels(
+            0,
+            0,
+            width as i32,
+            height as i32,
+            glow::RGBA,
+            ty,
+            PixelPackData::Slice(bytemuck::cast_slice_mut(&mut pixels)),
         );
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    }
+
+    // OpenGL reads from the bottom row up
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks_exact(row_len).rev() {
+        flipped.extend_from_slice(row);
     }
+    flipped
 }
@@ -1,60 +1,188 @@
-use std::fmt;
-use std::path::Path;
+use std::collections::{BTreeMap, VecDeque};
+#[cfg(feature = "editor")]
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use std::{fmt, fs};
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
+#[cfg(feature = "editor")]
+use bevy_ecs::entity::Entity;
 use bevy_ecs::system::Resource;
 use bevy_ecs::world::{FromWorld, World};
 use color_eyre::eyre::eyre;
 use color_eyre::Result;
+#[cfg(feature = "editor")]
 use egui_glow::EguiGlow;
-use glow::{Context, Framebuffer, HasContext, Renderbuffer, Texture};
+use glow::{Buffer, Context, HasContext, Texture, VertexArray};
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+#[cfg(feature = "editor")]
 use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+#[cfg(feature = "editor")]
 use winit::window::Window;
 use zune_png::zune_core::bit_depth::{BitDepth, ByteEndian};
 use zune_png::zune_core::colorspace::ColorSpace;
 use zune_png::zune_core::options::DecoderOptions;
 use zune_png::PngDecoder;
 
-use crate::shader::{Shader, ShaderBuilder, ShaderType};
+#[cfg(feature = "editor")]
+use crate::annotation::Markup;
+use crate::asset_cache::DerivedCache;
+#[cfg(feature = "editor")]
+use crate::bounds::Aabb;
+use crate::components::Properties;
+use crate::frame_graph::{DepthOutput, FrameGraph, PassDesc, PassPosition, TextureDesc};
+use crate::gl_debug::gl_check;
+#[cfg(feature = "editor")]
+use crate::input_map::Action;
+use crate::instancing::{self, ImportReport, ImportedPart};
+use crate::light_clusters::LightClusterTextures;
+#[cfg(feature = "editor")]
+use crate::mesh_cleanup::CleanupSettings;
+use crate::mesh_cleanup::MeshData;
+#[cfg(feature = "editor")]
+use crate::outliner::OutlinerEntry;
+#[cfg(feature = "editor")]
+use crate::renderer::ImageSettings;
+use crate::renderer::{self, RenderNode};
+#[cfg(feature = "editor")]
+use crate::scene::MergeOptions;
+use crate::shader::{Shader, ShaderBuilder, ShaderFeatures, ShaderType, ShaderVariants};
+use crate::skin::{SkinData, MAX_JOINTS};
+#[cfg(feature = "editor")]
+use crate::terrain::Brush;
+#[cfg(feature = "editor")]
+use crate::texture_import::Channel;
+use crate::texture_import::{self, TextureInfo, TextureQuality, TextureSettings};
 use crate::vao::VertexArrayObject;
+#[cfg(feature = "editor")]
+use crate::watchdog::StalledFrame;
+use crate::{lod, tangents};
+
+pub const SHADOW_PASS: &str = "shadow";
+pub const GEOMETRY_PASS: &str = "geometry";
+pub const LIGHTING_PASS: &str = "lighting";
+pub const REFLECTION_PASS: &str = "reflections";
+pub const DEPTH_OF_FIELD_PASS: &str = "depth_of_field";
+pub const MOTION_BLUR_PASS: &str = "motion_blur";
+/// Pass that writes [`SCENE_COLOR`], whose framebuffer holds the finished image
+pub const OUTPUT_PASS: &str = MOTION_BLUR_PASS;
+/// Texture the lighting pass renders to, which the reflection pass adds reflections to
+pub const LIT_COLOR: &str = "lit_color";
+/// Texture the reflection pass renders to, which the depth of field pass blurs
+pub const REFLECTED_COLOR: &str = "reflected_color";
+/// Texture the depth of field pass renders to, which the motion blur pass blurs
+pub const FOCUSED_COLOR: &str = "focused_color";
+/// Texture the lit scene is rendered to, before it is presented or shown in the UI
+pub const SCENE_COLOR: &str = "scene_color";
+/// G-buffer texture with the index of the entity drawn at each pixel plus one, where 0 is the
+/// background, used for picking
+pub const ENTITY_ID: &str = "g_entity_id";
+/// Samples per pixel the geometry pass can be multisampled with, where 1 turns MSAA off
+pub const MSAA_SAMPLES: [u32; 4] = [1, 2, 4, 8];
+
+/// Widths and heights of the shadow map a scene can be rendered with
+pub const SHADOW_MAP_SIZES: [i32; 4] = [1024, 2048, 4096, 8192];
+
+/// Widths of the square of shadow map samples that are blended for soft shadow edges, where 1
+/// turns the blending off
+pub const PCF_KERNEL_SIZES: [i32; 4] = [1, 3, 5, 7];
+
+/// Passes of the frame graph, in the order they run
+const PASSES: [&str; 6] = [
+    SHADOW_PASS,
+    GEOMETRY_PASS,
+    LIGHTING_PASS,
+    REFLECTION_PASS,
+    DEPTH_OF_FIELD_PASS,
+    MOTION_BLUR_PASS,
+];
+
+/// Passes that draw a quad over the G-buffer, by the name of their fragment shader file, in the
+/// order of [`RenderState::quad_pass_shaders_mut`]
+const QUAD_PASS_SHADERS: [(&str, &str); 4] = [
+    ("deferred_pass", crate::shader::DEFERRED_PASS_FRAG),
+    ("reflection_pass", crate::shader::REFLECTION_PASS_FRAG),
+    ("depth_of_field", crate::shader::DEPTH_OF_FIELD_FRAG),
+    ("motion_blur", crate::shader::MOTION_BLUR_FRAG),
+];
 
 #[derive(Resource)]
 pub struct RenderState {
     pub default_diffuse: Texture,
     pub default_specular: Texture,
-    pub shadow_map_fbo: Framebuffer,
-    pub shadow_map: Texture,
-    pub shadow_map_size: (i32, i32),
-    pub depth_shader: Shader,
-    pub g_buffer: Framebuffer,
-    pub g_position: Texture,
-    pub g_normal: Texture,
-    pub g_albedo_spec: Texture,
-    pub g_rbo: Renderbuffer,
-    pub geometry_pass_shader: Shader,
+    pub frame_graph: FrameGraph,
+    /// Function each pass of the frame graph is drawn with
+    nodes: AHashMap<&'static str, RenderNode>,
+    /// Variants of the shadow map shader, only keyed by whether the mesh is skinned
+    pub depth_shaders: ShaderVariants,
+    pub geometry_pass_shaders: ShaderVariants,
     pub quad_vao: VertexArrayObject,
     pub deferred_pass_shader: Shader,
+    pub reflection_pass_shader: Shader,
+    pub depth_of_field_shader: Shader,
+    pub motion_blur_shader: Shader,
+    /// Point lights and the clusters of the view they reach, read by the lighting pass
+    pub light_clusters: LightClusterTextures,
+    pub debug_line_vao: VertexArray,
+    pub debug_line_vbo: Buffer,
+    pub debug_line_shader: Shader,
+    /// Draw meshes with their custom shaders, instead of the built-in shader
+    pub custom_shaders: bool,
+    /// Reflect the scene in shiny surfaces, by tracing rays through the G-buffer
+    pub reflections: bool,
+    pub post_effects: PostEffects,
+    pub shadows: ShadowSettings,
+    /// Equirectangular HDR image drawn around the scene and lighting it in place of the ambient
+    /// light of the sun, owned by whatever set it, such as look-dev mode
+    pub environment_map: Option<Texture>,
+    shadow_map_size: i32,
+    /// Samples per pixel of the G-buffer
+    samples: i32,
+    /// Passes added to the built-in frame graph, which are added again when it is rebuilt
+    inserted_passes: Vec<InsertedPass>,
+}
+
+/// A pass added to the frame graph with [`RenderState::insert_pass`]
+struct InsertedPass {
+    pass: PassDesc,
+    position: PassPosition,
+    textures: Vec<(&'static str, TextureDesc)>,
 }
 
 impl RenderState {
+    /// Width and height of the shadow map in the editor
+    pub const SHADOW_MAP_SIZE: i32 = 4096;
+
     pub fn new(gl: &Context, window_size: (u32, u32)) -> Result<Self> {
+        Self::with_shadow_map_size(gl, window_size, Self::SHADOW_MAP_SIZE)
+    }
+
+    pub fn with_shadow_map_size(
+        gl: &Context,
+        window_size: (u32, u32),
+        shadow_map_size: i32,
+    ) -> Result<Self> {
         let default_diffuse = unsafe {
             let tex = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
             gl.bind_texture(glow::TEXTURE_2D, Some(tex));
             let pixels: [u8; 4] = [229, 229, 229, 255];
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                1,
-                1,
-                0,
-                glow::RGBA,
-                glow::UNSIGNED_BYTE,
-                Some(&pixels),
+            gl_check!(
+                gl,
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA as i32,
+                    1,
+                    1,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    Some(&pixels),
+                )
             );
             tex
         };
@@ -63,189 +191,34 @@ impl RenderState {
             let tex = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
             gl.bind_texture(glow::TEXTURE_2D, Some(tex));
             let pixels: [u8; 4] = [0; 4];
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                1,
-                1,
-                0,
-                glow::RGBA,
-                glow::UNSIGNED_BYTE,
-                Some(&pixels),
+            gl_check!(
+                gl,
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA as i32,
+                    1,
+                    1,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    Some(&pixels),
+                )
             );
             tex
         };
 
-        let shadow_map_size = (4096, 4096);
-        let (shadow_map_fbo, shadow_map) = unsafe {
-            let fbo =
-                gl.create_framebuffer().map_err(|e| eyre!("could not create framebuffer: {e}"))?;
-
-            let map = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
-            gl.bind_texture(glow::TEXTURE_2D, Some(map));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::DEPTH_COMPONENT24 as i32,
-                shadow_map_size.0,
-                shadow_map_size.1,
-                0,
-                glow::DEPTH_COMPONENT,
-                glow::FLOAT,
-                None,
-            );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_COMPARE_MODE,
-                glow::COMPARE_REF_TO_TEXTURE as i32,
-            );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_COMPARE_FUNC, glow::LEQUAL as i32);
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_WRAP_S,
-                glow::CLAMP_TO_BORDER as i32,
-            );
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_WRAP_T,
-                glow::CLAMP_TO_BORDER as i32,
-            );
-            gl.tex_parameter_f32_slice(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_BORDER_COLOR,
-                &[1.0, 1.0, 1.0, 1.0],
-            );
+        let frame_graph = Self::frame_graph(gl, window_size, shadow_map_size, 1, &[])?;
 
-            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::DEPTH_ATTACHMENT,
-                glow::TEXTURE_2D,
-                Some(map),
-                0,
-            );
-            gl.draw_buffer(glow::NONE);
-            gl.read_buffer(glow::NONE);
-            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-
-            (fbo, map)
-        };
+        let mut depth_shaders =
+            ShaderVariants::new(crate::shader::DEPTH_VERT, crate::shader::DEPTH_FRAG);
+        depth_shaders.prepare(gl, ShaderFeatures::default())?;
 
-        let depth_shader = ShaderBuilder::new(gl)
-            .add_shader_source(include_str!("../shaders/depth_vert.glsl"), ShaderType::Vertex)?
-            .add_shader_source(include_str!("../shaders/depth_frag.glsl"), ShaderType::Fragment)?
-            .link()?;
-
-        let (g_buffer, g_position, g_normal, g_albedo_spec, g_rbo) = unsafe {
-            let g_buf =
-                gl.create_framebuffer().map_err(|e| eyre!("could not create framebuffer: {e}"))?;
-            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(g_buf));
-
-            let (width, height) = window_size;
-            let width = width as i32;
-            let height = height as i32;
-            let g_pos = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
-            gl.bind_texture(glow::TEXTURE_2D, Some(g_pos));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA16F as i32,
-                width,
-                height,
-                0,
-                glow::RGBA,
-                glow::FLOAT,
-                None,
-            );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::COLOR_ATTACHMENT0,
-                glow::TEXTURE_2D,
-                Some(g_pos),
-                0,
-            );
-
-            let g_norm = gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
-            gl.bind_texture(glow::TEXTURE_2D, Some(g_norm));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA16F as i32,
-                width,
-                height,
-                0,
-                glow::RGBA,
-                glow::FLOAT,
-                None,
-            );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::COLOR_ATTACHMENT1,
-                glow::TEXTURE_2D,
-                Some(g_norm),
-                0,
-            );
-
-            let g_alb_spec =
-                gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
-            gl.bind_texture(glow::TEXTURE_2D, Some(g_alb_spec));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                width,
-                height,
-                0,
-                glow::RGBA,
-                glow::UNSIGNED_BYTE,
-                None,
-            );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
-            gl.framebuffer_texture_2d(
-                glow::FRAMEBUFFER,
-                glow::COLOR_ATTACHMENT2,
-                glow::TEXTURE_2D,
-                Some(g_alb_spec),
-                0,
-            );
-
-            gl.draw_buffers(&[
-                glow::COLOR_ATTACHMENT0,
-                glow::COLOR_ATTACHMENT1,
-                glow::COLOR_ATTACHMENT2,
-            ]);
-
-            let rbo = gl
-                .create_renderbuffer()
-                .map_err(|e| eyre!("could not create renderbuffer: {e}"))?;
-            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rbo));
-            gl.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH24_STENCIL8, width, height);
-            gl.framebuffer_renderbuffer(
-                glow::FRAMEBUFFER,
-                glow::DEPTH_STENCIL_ATTACHMENT,
-                glow::RENDERBUFFER,
-                Some(rbo),
-            );
-
-            if gl.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
-                return Err(eyre!("framebuffer was not completed"));
-            }
-
-            (g_buf, g_pos, g_norm, g_alb_spec, rbo)
-        };
-
-        let geometry_pass_shader = ShaderBuilder::new(gl)
-            .add_shader_source(crate::shader::GEOMETRY_PASS_VERT, ShaderType::Vertex)?
-            .add_shader_source(crate::shader::GEOMETRY_PASS_FRAG, ShaderType::Fragment)?
-            .link()?;
+        let mut geometry_pass_shaders = ShaderVariants::new(
+            crate::shader::GEOMETRY_PASS_VERT,
+            crate::shader::GEOMETRY_PASS_FRAG,
+        );
+        geometry_pass_shaders.prepare(gl, ShaderFeatures::default())?;
 
         let quad_vertices = [
             glm::vec3(-1.0, 1.0, 0.0),
@@ -272,94 +245,408 @@ impl RenderState {
             )
         };
 
-        let deferred_pass_shader = ShaderBuilder::new(gl)
-            .add_shader_source(crate::shader::DEFERRED_PASS_VERT, ShaderType::Vertex)?
-            .add_shader_source(crate::shader::DEFERRED_PASS_FRAG, ShaderType::Fragment)?
+        let [
+            deferred_pass_shader,
+            reflection_pass_shader,
+            depth_of_field_shader,
+            motion_blur_shader,
+        ] = quad_pass_shaders(gl, 1)?;
+
+        let light_clusters = unsafe { LightClusterTextures::new(gl)? };
+
+        // Lines are uploaded every frame, so only the layout is set up here
+        let (debug_line_vao, debug_line_vbo) = unsafe {
+            let vao = gl
+                .create_vertex_array()
+                .map_err(|e| eyre!("could not create vertex array: {e}"))?;
+            gl.bind_vertex_array(Some(vao));
+            let vbo = gl.create_buffer().map_err(|e| eyre!("could not create buffer: {e}"))?;
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo));
+            gl_check!(gl, gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, 12, 0));
+            gl.enable_vertex_attrib_array(0);
+            gl.bind_vertex_array(None);
+
+            (vao, vbo)
+        };
+
+        let debug_line_shader = ShaderBuilder::new(gl)
+            .add_shader_source(crate::shader::DEBUG_LINE_VERT, ShaderType::Vertex)?
+            .add_shader_source(crate::shader::DEBUG_LINE_FRAG, ShaderType::Fragment)?
             .link()?;
 
         Ok(Self {
             default_diffuse,
             default_specular,
-            shadow_map_fbo,
-            shadow_map,
-            shadow_map_size,
-            depth_shader,
-            g_buffer,
-            g_position,
-            g_normal,
-            g_albedo_spec,
-            g_rbo,
-            geometry_pass_shader,
+            frame_graph,
+            nodes: renderer::builtin_nodes().into_iter().collect(),
+            depth_shaders,
+            geometry_pass_shaders,
             quad_vao,
             deferred_pass_shader,
+            reflection_pass_shader,
+            depth_of_field_shader,
+            motion_blur_shader,
+            light_clusters,
+            debug_line_vao,
+            debug_line_vbo,
+            debug_line_shader,
+            custom_shaders: true,
+            reflections: false,
+            post_effects: PostEffects::default(),
+            shadows: ShadowSettings::default(),
+            environment_map: None,
+            shadow_map_size,
+            samples: 1,
+            inserted_passes: Vec::new(),
         })
     }
 
-    pub fn resize(&mut self, gl: &Context, new_width: u32, new_height: u32) {
-        let new_width = new_width as i32;
-        let new_height = new_height as i32;
-        unsafe {
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.g_position));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA16F as i32,
-                new_width,
-                new_height,
-                0,
-                glow::RGBA,
-                glow::FLOAT,
-                None,
+    /// Render passes and targets, with the G-buffer multisampled if `samples` is more than 1
+    fn frame_graph(
+        gl: &Context,
+        window_size: (u32, u32),
+        shadow_map_size: i32,
+        samples: i32,
+        inserted_passes: &[InsertedPass],
+    ) -> Result<FrameGraph> {
+        let g_buffer = |internal_format, format, ty| {
+            TextureDesc::color(internal_format, format, ty).with_samples(samples)
+        };
+        // Lit colors are kept as half floats, so light brighter than white survives until an image
+        // is exported
+        let color = TextureDesc::color(glow::RGBA16F, glow::RGBA, glow::FLOAT);
+        let mut builder = FrameGraph::builder()
+            .texture("shadow_map", TextureDesc::shadow_map(shadow_map_size, shadow_map_size))
+            .texture("g_position", g_buffer(glow::RGBA16F, glow::RGBA, glow::FLOAT))
+            .texture("g_normal", g_buffer(glow::RGBA16F, glow::RGBA, glow::FLOAT))
+            .texture("g_albedo_spec", g_buffer(glow::RGBA, glow::RGBA, glow::UNSIGNED_BYTE))
+            .texture(ENTITY_ID, g_buffer(glow::R32UI, glow::RED_INTEGER, glow::UNSIGNED_INT))
+            .pass(PassDesc::new(SHADOW_PASS).depth(DepthOutput::Texture("shadow_map")))
+            .pass(
+                PassDesc::new(GEOMETRY_PASS)
+                    .writes(&["g_position", "g_normal", "g_albedo_spec", "g_velocity", ENTITY_ID])
+                    .depth(DepthOutput::Renderbuffer)
+                    .resolves(&[ENTITY_ID]),
+            )
+            .texture("g_velocity", g_buffer(glow::RG16F, glow::RG, glow::FLOAT))
+            .texture(LIT_COLOR, color)
+            .pass(
+                PassDesc::new(LIGHTING_PASS)
+                    .reads(&["g_position", "g_normal", "g_albedo_spec", "g_velocity", "shadow_map"])
+                    .writes(&[LIT_COLOR]),
+            )
+            .texture(REFLECTED_COLOR, color)
+            .pass(
+                PassDesc::new(REFLECTION_PASS)
+                    .reads(&[LIT_COLOR, "g_position", "g_normal", "g_albedo_spec"])
+                    .writes(&[REFLECTED_COLOR]),
+            )
+            .texture(FOCUSED_COLOR, color)
+            .pass(
+                PassDesc::new(DEPTH_OF_FIELD_PASS)
+                    .reads(&[REFLECTED_COLOR, "g_position", "g_normal"])
+                    .writes(&[FOCUSED_COLOR]),
+            )
+            .texture(SCENE_COLOR, color)
+            .pass(
+                PassDesc::new(MOTION_BLUR_PASS)
+                    .reads(&[FOCUSED_COLOR, "g_velocity"])
+                    .writes(&[SCENE_COLOR]),
             );
+        for inserted in inserted_passes {
+            for &(name, desc) in &inserted.textures {
+                builder = builder.texture(name, desc);
+            }
+            builder = builder.insert(inserted.pass.clone(), inserted.position);
+        }
+        builder.build(gl, window_size)
+    }
 
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.g_normal));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA16F as i32,
-                new_width,
-                new_height,
-                0,
-                glow::RGBA,
-                glow::FLOAT,
-                None,
-            );
+    /// The function a pass is drawn with
+    pub fn node(&self, pass: &str) -> Option<RenderNode> {
+        self.nodes.get(pass).copied()
+    }
 
-            gl.bind_texture(glow::TEXTURE_2D, Some(self.g_albedo_spec));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                new_width,
-                new_height,
-                0,
-                glow::RGBA,
-                glow::UNSIGNED_BYTE,
-                None,
-            );
+    /// Draw a pass of the frame graph with another function, such as to replace a built-in pass
+    pub fn set_node(&mut self, pass: &'static str, node: RenderNode) {
+        self.nodes.insert(pass, node);
+    }
 
-            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(self.g_rbo));
-            gl.renderbuffer_storage(
-                glow::RENDERBUFFER,
-                glow::DEPTH24_STENCIL8,
-                new_width,
-                new_height,
-            );
+    /// Add a pass to the frame graph before or after one of its passes, drawn with `node`
+    ///
+    /// `textures` are the textures the pass writes, which the graph does not have yet. The render
+    /// targets are created again, as with [`set_samples`](Self::set_samples), and the pass stays in
+    /// the graph when they are created again later.
+    pub fn insert_pass(
+        &mut self,
+        gl: &Context,
+        pass: PassDesc,
+        position: PassPosition,
+        textures: &[(&'static str, TextureDesc)],
+        node: RenderNode,
+    ) -> Result<()> {
+        let name = pass.name;
+        self.inserted_passes.push(InsertedPass { pass, position, textures: textures.to_vec() });
+        let (width, height) = self.frame_graph.size(LIGHTING_PASS);
+        let size = (width as u32, height as u32);
+        match Self::frame_graph(gl, size, self.shadow_map_size, self.samples, &self.inserted_passes)
+        {
+            Ok(frame_graph) => {
+                self.replace_frame_graph(gl, frame_graph);
+                self.nodes.insert(name, node);
+                Ok(())
+            }
+            Err(e) => {
+                self.inserted_passes.pop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Samples per pixel of the G-buffer, where 1 means it is not multisampled
+    pub fn samples(&self) -> u32 {
+        self.samples as u32
+    }
+
+    /// Multisample the G-buffer with the given samples per pixel, or as many as the driver
+    /// supports, and return how many it has
+    ///
+    /// The render targets are created again, so the scene color texture is a new one. The passes
+    /// that were disabled stay disabled.
+    pub fn set_samples(&mut self, gl: &Context, samples: u32) -> Result<u32> {
+        let max_samples = unsafe {
+            let max_color = gl.get_parameter_i32(glow::MAX_COLOR_TEXTURE_SAMPLES);
+            // The entity ID texture of the G-buffer is an integer texture
+            let max_integer = gl.get_parameter_i32(glow::MAX_INTEGER_SAMPLES);
+            gl.get_parameter_i32(glow::MAX_SAMPLES).min(max_color).min(max_integer)
+        };
+        let samples = (samples as i32).clamp(1, max_samples.max(1));
+        if samples == self.samples {
+            return Ok(samples as u32);
+        }
+
+        let (width, height) = self.frame_graph.size(LIGHTING_PASS);
+        let size = (width as u32, height as u32);
+        let mut frame_graph =
+            Self::frame_graph(gl, size, self.shadow_map_size, samples, &self.inserted_passes)?;
+        let shaders = match quad_pass_shaders(gl, samples) {
+            Ok(shaders) => shaders,
+            Err(e) => {
+                unsafe { frame_graph.destroy(gl) };
+                return Err(e);
+            }
+        };
+        self.replace_frame_graph(gl, frame_graph);
+        unsafe {
+            for (old, shader) in self.quad_pass_shaders_mut().into_iter().zip(shaders) {
+                std::mem::replace(old, shader).destroy(gl);
+            }
+        }
+        self.samples = samples;
+        Ok(samples as u32)
+    }
+
+    /// Width and height of the shadow map
+    pub fn shadow_map_size(&self) -> i32 {
+        self.shadow_map_size
+    }
+
+    /// Render shadows into a shadow map with the given width and height
+    ///
+    /// The render targets are created again, so the scene color texture is a new one, as with
+    /// [`set_samples`](Self::set_samples).
+    pub fn set_shadow_map_size(&mut self, gl: &Context, size: i32) -> Result<()> {
+        if size == self.shadow_map_size {
+            return Ok(());
+        }
+        let max_size = unsafe { gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) };
+        if size < 1 || size > max_size {
+            return Err(eyre!(
+                "a {size}x{size} shadow map is not within the 1 to {max_size} pixels the driver \
+                 supports"
+            ));
+        }
+
+        let (width, height) = self.frame_graph.size(LIGHTING_PASS);
+        let window_size = (width as u32, height as u32);
+        let frame_graph =
+            Self::frame_graph(gl, window_size, size, self.samples, &self.inserted_passes)?;
+        self.replace_frame_graph(gl, frame_graph);
+        self.shadow_map_size = size;
+        Ok(())
+    }
+
+    /// Switch to a new frame graph, keeping the passes that were disabled disabled
+    fn replace_frame_graph(&mut self, gl: &Context, mut frame_graph: FrameGraph) {
+        let inserted = self.inserted_passes.iter().map(|inserted| inserted.pass.name);
+        for pass in PASSES.into_iter().chain(inserted) {
+            // A pass that was just inserted starts enabled
+            if self.frame_graph.has_pass(pass) {
+                frame_graph.set_enabled(pass, self.frame_graph.is_enabled(pass));
+            }
+        }
+        unsafe {
+            std::mem::replace(&mut self.frame_graph, frame_graph).destroy(gl);
+        }
+    }
+
+    pub fn resize(&mut self, gl: &Context, new_width: u32, new_height: u32) {
+        self.frame_graph.resize(gl, new_width, new_height);
+    }
+
+    /// Shaders of the passes that draw a quad over the G-buffer, in the order of
+    /// [`QUAD_PASS_SHADERS`]
+    fn quad_pass_shaders_mut(&mut self) -> [&mut Shader; 4] {
+        [
+            &mut self.deferred_pass_shader,
+            &mut self.reflection_pass_shader,
+            &mut self.depth_of_field_shader,
+            &mut self.motion_blur_shader,
+        ]
+    }
+
+    /// Compile the depth, geometry pass, deferred pass and post effect shaders again from the
+    /// files in `dir`, with includes from its `include` folder
+    ///
+    /// A shader that does not compile keeps its old program, and the errors of all of them are
+    /// returned together.
+    pub fn reload_shaders(&mut self, gl: &Context, dir: &Path) -> Result<()> {
+        let read = |name: &str| {
+            let path = dir.join(name);
+            fs::read_to_string(&path).map_err(|e| eyre!("could not read {}: {e}", path.display()))
+        };
+        let include_dir = dir.join("include");
+        let mut errors = Vec::new();
+
+        for (shaders, name) in
+            [(&mut self.depth_shaders, "depth"), (&mut self.geometry_pass_shaders, "geometry_pass")]
+        {
+            let result = read(&format!("{name}_vert.glsl")).and_then(|vert| {
+                let frag = read(&format!("{name}_frag.glsl"))?;
+                shaders.reload(gl, vert, frag, &include_dir)
+            });
+            if let Err(e) = result {
+                errors.push(format!("{name}: {e}"));
+            }
+        }
+
+        let samples = self.samples;
+        for ((name, _), old) in QUAD_PASS_SHADERS.into_iter().zip(self.quad_pass_shaders_mut()) {
+            let shader = read("deferred_pass_vert.glsl").and_then(|vert| {
+                let frag = read(&format!("{name}_frag.glsl"))?;
+                deferred_pass_shader(gl, &vert, &frag, Some(&include_dir), samples)
+            });
+            match shader {
+                Ok(shader) => unsafe { std::mem::replace(old, shader).destroy(gl) },
+                Err(e) => errors.push(format!("{name}: {e}")),
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(eyre!("{}", errors.join("\n"))) }
+    }
+
+    /// # Safety
+    ///
+    /// The render targets, shaders and buffers are no longer valid and should not be used.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        gl.delete_texture(self.default_diffuse);
+        gl.delete_texture(self.default_specular);
+        self.quad_vao.destroy(gl);
+        self.frame_graph.destroy(gl);
+        self.depth_shaders.destroy(gl);
+        self.geometry_pass_shaders.destroy(gl);
+        for shader in self.quad_pass_shaders_mut() {
+            shader.destroy(gl);
         }
+        self.light_clusters.destroy(gl);
+        self.debug_line_shader.destroy(gl);
+        gl.delete_buffer(self.debug_line_vbo);
+        gl.delete_vertex_array(self.debug_line_vao);
+    }
+}
+
+/// Compile the shaders of [`QUAD_PASS_SHADERS`] for a G-buffer with the given samples per pixel
+fn quad_pass_shaders(gl: &Context, samples: i32) -> Result<[Shader; 4]> {
+    let mut shaders = Vec::with_capacity(QUAD_PASS_SHADERS.len());
+    for (_, frag) in QUAD_PASS_SHADERS {
+        match deferred_pass_shader(gl, crate::shader::DEFERRED_PASS_VERT, frag, None, samples) {
+            Ok(shader) => shaders.push(shader),
+            Err(e) => {
+                for mut shader in shaders {
+                    unsafe { shader.destroy(gl) };
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(shaders.try_into().unwrap_or_else(|_| unreachable!()))
+}
+
+/// Compile a shader that reads the G-buffer, such as the lighting shader, for a G-buffer with the
+/// given samples per pixel
+fn deferred_pass_shader(
+    gl: &Context,
+    vert: &str,
+    frag: &str,
+    include_dir: Option<&Path>,
+    samples: i32,
+) -> Result<Shader> {
+    let mut builder = ShaderBuilder::new(gl);
+    if let Some(dir) = include_dir {
+        builder = builder.include_dir(dir);
     }
+    let defines = if samples > 1 { format!("#define SAMPLES {samples}\n") } else { String::new() };
+    let frag = crate::shader::insert_defines(frag, &defines);
+    builder
+        .add_shader_source(vert, ShaderType::Vertex)?
+        .add_shader_source(&frag, ShaderType::Fragment)?
+        .link()
 }
 
 impl FromWorld for RenderState {
     fn from_world(world: &mut World) -> Self {
         let gl = world.non_send_resource::<Arc<Context>>();
-        let window_size = world.resource::<WinitWindow>().inner_size().into();
-        Self::new(gl, window_size).unwrap()
+        let viewport = world.resource::<Viewport>();
+        Self::new(gl, (viewport.width, viewport.height)).unwrap()
     }
 }
 
+/// Which entities to draw bounding boxes for
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum BoundsDisplay {
+    #[default]
+    Off,
+    Selected,
+    All,
+}
+
+/// Settings for debug visualizations
+#[derive(Resource, Debug, Default, Copy, Clone)]
+pub struct DebugDraw {
+    pub bounding_boxes: BoundsDisplay,
+    /// Show the motion of each pixel since the previous frame instead of the lit scene
+    pub motion_vectors: bool,
+    /// Outline how far the selected point lights reach with a sphere
+    pub light_ranges: bool,
+    /// Color each pixel by how many point lights are added up for it
+    pub light_heatmap: bool,
+}
+
+/// How the camera projects the scene onto the screen
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ProjectionMode {
+    #[default]
+    Perspective,
+    /// Parallel projection without foreshortening, for lining objects up precisely
+    Orthographic,
+}
+
 #[derive(Resource)]
 pub struct Camera {
     pub projection: glm::Mat4,
+    pub mode: ProjectionMode,
+    /// Height of the visible area in world units when the projection is orthographic
+    pub ortho_height: f32,
 
     pub pos: glm::Vec3,
     pub front: glm::Vec3,
@@ -370,6 +657,12 @@ pub struct Camera {
 }
 
 impl Camera {
+    /// Vertical field of view
+    pub const FOV_DEGREES: f32 = 74.0;
+    /// Distances of the near and far clip planes, in both projection modes
+    pub const NEAR: f32 = 0.1;
+    pub const FAR: f32 = 350.0;
+
     pub fn new(
         projection: glm::Mat4,
         pos: glm::Vec3,
@@ -378,20 +671,22 @@ impl Camera {
         yaw: f64,
         pitch: f64,
     ) -> Self {
-        Self { projection, pos, front, up, yaw, pitch }
-    }
-
-    pub fn perspective(width: u32, height: u32) -> glm::Mat4 {
-        glm::perspective(width as f32 / height as f32, 74.0_f32.to_radians(), 0.1, 350.0)
+        Self {
+            projection,
+            mode: ProjectionMode::Perspective,
+            ortho_height: 10.0,
+            pos,
+            front,
+            up,
+            yaw,
+            pitch,
+        }
     }
-}
 
-impl FromWorld for Camera {
-    fn from_world(world: &mut World) -> Self {
-        let size = world.resource::<WinitWindow>().inner_size();
-        let projection = Self::perspective(size.width, size.height);
+    /// Perspective camera at the origin looking along -Z
+    pub fn looking_forward(width: u32, height: u32) -> Self {
         Self::new(
-            projection,
+            Self::perspective(width, height),
             glm::vec3(0.0, 0.0, 0.0),
             glm::vec3(0.0, 0.0, -1.0),
             glm::vec3(0.0, 1.0, 0.0),
@@ -399,36 +694,682 @@ impl FromWorld for Camera {
             0.0,
         )
     }
+
+    /// Orthographic camera looking at the origin from `distance` away
+    pub fn looking_at_origin(width: u32, height: u32, yaw: f64, pitch: f64, distance: f32) -> Self {
+        let mut camera = Self::looking_forward(width, height);
+        let pose = CameraPose { position: glm::Vec3::zeros(), yaw, pitch };
+        camera.set_pose(CameraPose { position: -pose.front() * distance, ..pose });
+        camera.mode = ProjectionMode::Orthographic;
+        camera.ortho_height = distance;
+        camera.update_projection(width, height);
+        camera
+    }
+
+    pub fn perspective(width: u32, height: u32) -> glm::Mat4 {
+        let aspect = width as f32 / height as f32;
+        glm::perspective(aspect, Self::FOV_DEGREES.to_radians(), Self::NEAR, Self::FAR)
+    }
+
+    pub fn orthographic(width: u32, height: u32, view_height: f32) -> glm::Mat4 {
+        let half_height = view_height * 0.5;
+        let half_width = half_height * width as f32 / height as f32;
+        glm::ortho(-half_width, half_width, -half_height, half_height, Self::NEAR, Self::FAR)
+    }
+
+    /// Rebuild the projection matrix for the current mode and viewport size
+    pub fn update_projection(&mut self, width: u32, height: u32) {
+        self.projection = match self.mode {
+            ProjectionMode::Perspective => Self::perspective(width, height),
+            ProjectionMode::Orthographic => Self::orthographic(width, height, self.ortho_height),
+        };
+    }
+
+    /// View matrix, which stays valid when looking straight up or down
+    pub fn view(&self) -> glm::Mat4 {
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        let up = glm::vec3(
+            (-yaw.cos() * pitch.sin()) as f32,
+            pitch.cos() as f32,
+            (-yaw.sin() * pitch.sin()) as f32,
+        );
+        glm::look_at(&self.pos, &(self.pos + self.front), &up)
+    }
+
+    /// Direction to the right of the camera, parallel to the ground
+    pub fn right(&self) -> glm::Vec3 {
+        let yaw = self.yaw.to_radians();
+        glm::vec3(-yaw.sin() as f32, 0.0, yaw.cos() as f32)
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        CameraPose { position: self.pos, yaw: self.yaw, pitch: self.pitch }
+    }
+
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.pos = pose.position;
+        self.yaw = pose.yaw;
+        self.pitch = pose.pitch;
+        self.front = pose.front();
+    }
+}
+
+/// Where the camera is and which way it looks, with angles in degrees
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub position: glm::Vec3,
+    pub yaw: f64,
+    pub pitch: f64,
+}
+
+impl CameraPose {
+    /// Direction the camera looks in
+    pub fn front(&self) -> glm::Vec3 {
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        glm::normalize(&glm::vec3(
+            (yaw.cos() * pitch.cos()) as f32,
+            pitch.sin() as f32,
+            (yaw.sin() * pitch.cos()) as f32,
+        ))
+    }
+
+    /// Pose part of the way from `self` to `other`, turning the shortest way around
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let yaw_delta = (other.yaw - self.yaw + 180.0).rem_euclid(360.0) - 180.0;
+        Self {
+            position: glm::lerp(&self.position, &other.position, t),
+            yaw: self.yaw + yaw_delta * t as f64,
+            pitch: self.pitch + (other.pitch - self.pitch) * t as f64,
+        }
+    }
+}
+
+/// Number of camera views that can be saved
+pub const VIEW_SLOTS: u8 = 9;
+
+/// Camera views saved to numbered slots, stored in the scene file
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ViewBookmarks {
+    pub views: BTreeMap<u8, CameraPose>,
+}
+
+impl FromWorld for Camera {
+    fn from_world(world: &mut World) -> Self {
+        let viewport = world.resource::<Viewport>();
+        Self::looking_forward(viewport.width, viewport.height)
+    }
 }
 
+/// Size of the area the scene is rendered to, in physical pixels
+#[derive(Resource, Debug, Copy, Clone)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+/// Part of the window a view is drawn to, in pixels from the bottom left corner
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ViewRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl ViewRect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// Parts of the rendering that can be switched off in a view, to narrow down where a problem is
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ViewPasses {
+    /// Shadows from the sun
+    pub shadows: bool,
+    /// Lighting, or flat colors from the textures when off
+    pub lighting: bool,
+    /// Outline around selected objects
+    pub outlines: bool,
+    /// Bounding boxes from the debug settings
+    pub bounding_boxes: bool,
+}
+
+impl Default for ViewPasses {
+    fn default() -> Self {
+        Self { shadows: true, lighting: true, outlines: true, bounding_boxes: true }
+    }
+}
+
+/// Splits the window into one view or a 2x2 grid of views with their own cameras
+///
+/// The `Camera` resource belongs to the active view, which receives input, while the other
+/// views keep their cameras here.
+#[derive(Resource)]
+pub struct ViewLayout {
+    pub quad: bool,
+    /// Show the active view through the selected scene camera instead of the editor camera
+    pub look_through: bool,
+    /// Show what the selected scene camera sees in a corner of the window
+    pub camera_preview: bool,
+    active: usize,
+    cameras: [Camera; 4],
+    passes: [ViewPasses; 4],
+    /// Camera of the selected entity with a `CameraComponent`, kept up to date by
+    /// `systems::update_scene_camera`
+    scene_camera: Option<Camera>,
+}
+
+impl ViewLayout {
+    /// Names of the views, from the top left to the bottom right of the grid
+    pub const NAMES: [&'static str; 4] = ["Perspective", "Top", "Front", "Right"];
+
+    pub fn new(width: u32, height: u32) -> Self {
+        const DISTANCE: f32 = 20.0;
+        let cameras = [
+            Camera::looking_forward(width, height),
+            Camera::looking_at_origin(width, height, -90.0, -90.0, DISTANCE),
+            Camera::looking_at_origin(width, height, -90.0, 0.0, DISTANCE),
+            Camera::looking_at_origin(width, height, 180.0, 0.0, DISTANCE),
+        ];
+        Self {
+            quad: false,
+            look_through: false,
+            camera_preview: true,
+            active: 0,
+            cameras,
+            passes: [ViewPasses::default(); 4],
+            scene_camera: None,
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Visible views and where they are drawn in a window of the given size
+    pub fn rects(&self, width: u32, height: u32) -> Vec<(usize, ViewRect)> {
+        let (width, height) = (width as i32, height as i32);
+        if !self.quad {
+            return vec![(self.active, ViewRect { x: 0, y: 0, width, height })];
+        }
+        let (left, bottom) = (width / 2, height / 2);
+        let (right, top) = (width - left, height - bottom);
+        vec![
+            (0, ViewRect { x: 0, y: bottom, width: left, height: top }),
+            (1, ViewRect { x: left, y: bottom, width: right, height: top }),
+            (2, ViewRect { x: 0, y: 0, width: left, height: bottom }),
+            (3, ViewRect { x: left, y: 0, width: right, height: bottom }),
+        ]
+    }
+
+    /// Camera and rectangle of every visible view, where `camera` is the active camera
+    pub fn views<'a>(
+        &'a self,
+        camera: &'a Camera,
+        width: u32,
+        height: u32,
+    ) -> Vec<(&'a Camera, ViewRect)> {
+        let active = match &self.scene_camera {
+            Some(scene_camera) if self.look_through => scene_camera,
+            _ => camera,
+        };
+        self.rects(width, height)
+            .into_iter()
+            .map(|(i, rect)| (if i == self.active { active } else { &self.cameras[i] }, rect))
+            .collect()
+    }
+
+    /// Scene camera and the corner of the window it is previewed in, drawn over the views
+    pub fn preview(&self, width: u32, height: u32) -> Option<(&Camera, ViewRect)> {
+        let camera = self.scene_camera.as_ref().filter(|_| self.camera_preview)?;
+        (!self.look_through).then(|| (camera, Self::preview_rect(width, height)))
+    }
+
+    /// Bottom right corner of the window, a quarter of its width and height
+    pub fn preview_rect(width: u32, height: u32) -> ViewRect {
+        const MARGIN: i32 = 8;
+        let (width, height) = (width as i32, height as i32);
+        let (preview_width, preview_height) = ((width / 4).max(1), (height / 4).max(1));
+        ViewRect {
+            x: (width - preview_width - MARGIN).max(0),
+            y: MARGIN.min(height - preview_height),
+            width: preview_width,
+            height: preview_height,
+        }
+    }
+
+    /// Size of the view the scene camera is shown in, to fit its projection to
+    pub fn scene_camera_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let active = self.rects(width, height).into_iter().find(|&(i, _)| i == self.active);
+        let rect = match active {
+            Some((_, rect)) if self.look_through => rect,
+            _ => Self::preview_rect(width, height),
+        };
+        (rect.width as u32, rect.height as u32)
+    }
+
+    pub fn scene_camera(&self) -> Option<&Camera> {
+        self.scene_camera.as_ref()
+    }
+
+    pub fn set_scene_camera(&mut self, camera: Option<Camera>) {
+        self.scene_camera = camera;
+    }
+
+    /// Passes that are drawn in a view
+    pub fn passes(&self, view: usize) -> ViewPasses {
+        self.passes[view]
+    }
+
+    pub fn passes_mut(&mut self, view: usize) -> &mut ViewPasses {
+        &mut self.passes[view]
+    }
+
+    /// Whether a point given in pixels from the top left corner of the window is on the preview
+    pub fn in_preview(&self, width: u32, height: u32, x: f64, y: f64) -> bool {
+        let (x, y) = (x as i32, height as i32 - y as i32 - 1);
+        self.preview(width, height).is_some_and(|(_, rect)| rect.contains(x, y))
+    }
+
+    /// View under a point given in pixels from the top left corner of the window
+    pub fn view_at(&self, width: u32, height: u32, x: f64, y: f64) -> Option<usize> {
+        let (x, y) = (x as i32, height as i32 - y as i32 - 1);
+        self.rects(width, height).into_iter().find(|(_, rect)| rect.contains(x, y)).map(|(i, _)| i)
+    }
+
+    /// Make another view active, swapping its camera with the one in `camera`
+    pub fn activate(&mut self, view: usize, camera: &mut Camera) {
+        std::mem::swap(camera, &mut self.cameras[self.active]);
+        std::mem::swap(camera, &mut self.cameras[view]);
+        self.active = view;
+    }
+
+    /// Rebuild the projections of the inactive cameras after the window is resized
+    pub fn update_projections(&mut self, width: u32, height: u32) {
+        for camera in &mut self.cameras {
+            camera.update_projection(width, height);
+        }
+    }
+}
+
+impl FromWorld for ViewLayout {
+    fn from_world(world: &mut World) -> Self {
+        let viewport = world.resource::<Viewport>();
+        Self::new(viewport.width, viewport.height)
+    }
+}
+
+#[cfg(feature = "editor")]
 #[derive(Resource, Default)]
 pub struct UiState {
     pub camera_focused: bool,
+    /// The scene is open for viewing, with the tools that change it turned off
+    pub view_only: bool,
     pub utilities_open: bool,
     pub performance_open: bool,
+    pub timeline_open: bool,
+    pub preferences_open: bool,
+    pub whats_new_open: bool,
+    pub start_screen_open: bool,
+    pub usage_open: bool,
+    pub outliner_open: bool,
+    /// Filter typed in the outliner, such as `tag:light` or `has:CustomShader`
+    pub outliner_filter: String,
+    pub script_open: bool,
+    pub console_open: bool,
+    /// Line being typed into the console
+    pub console_input: String,
+    /// The console shows the output of scripts instead of the log
+    pub console_scripts: bool,
+    /// The console shows debug and trace lines as well
+    pub console_debug: bool,
+    /// Script source being edited in the Script panel, and the entity it belongs to
+    pub script_draft: String,
+    pub script_draft_entity: Option<Entity>,
+    pub command_palette_open: bool,
+    /// Text typed into the command palette
+    pub palette_query: String,
+    /// Index of the highlighted command among the ones that match the query
+    pub palette_selected: usize,
+    pub shortcuts_open: bool,
+    pub asset_errors_open: bool,
+    pub backups_open: bool,
+    pub report_issue_open: bool,
+    pub terrain_open: bool,
+    pub render_settings_open: bool,
+    /// Clicking in the scene view sets the focus distance of the depth of field, instead of
+    /// selecting
+    pub picking_focus: bool,
+    pub report_path: String,
+    /// Add the scene file to the issue report
+    pub report_include_scene: bool,
+    /// Frame that took too long, while the editor asks what to do with the custom shaders
+    pub stalled_frame: Option<StalledFrame>,
+    /// Action waiting for a key or mouse button to be bound to it
+    pub rebinding: Option<Action>,
     pub editing_mode: Option<ShaderType>,
     pub selected_model: Option<String>,
     pub selected_diffuse: Option<String>,
     pub selected_specular: Option<String>,
+    pub selected_normal: Option<String>,
+    pub selected_alpha_test: bool,
+    pub scene_path: String,
+    pub lighting_path: String,
+    pub import_path: String,
+    /// Key typed in the inspector for a new custom property
+    pub new_property_key: String,
+    /// Text typed in the inspector to search the components that can be added
+    pub component_filter: String,
+    /// Tag typed in the inspector to add to the selected entity
+    pub new_tag: String,
+    /// Name typed in the Utilities panel for a new scene variable
+    pub new_variable_name: String,
+    /// Name typed in the Utilities panel for a new scene variant
+    pub new_variant_name: String,
+    /// Name typed in the inspector to save the selected entity's textures and shader as a material
+    pub new_material_name: String,
+    /// Module typed in the File menu that declares the game's components for a Bevy export
+    pub bevy_module: String,
+    /// Scene file typed in the File menu to import into the open scene, and how to fit it in
+    pub merge_path: String,
+    pub merge: MergeOptions,
+    pub render_image_open: bool,
+    pub render_image: ImageSettings,
+    pub render_image_path: String,
+    pub mesh_cleanup: CleanupSettings,
+    /// Screenshot open in the markup window
+    pub markup: Option<Markup>,
+    /// Texture open in the texture details window
+    pub texture_details: Option<TextureDetails>,
+}
+
+/// A texture read back from the GPU to be looked at, with import settings being edited
+#[cfg(feature = "editor")]
+pub struct TextureDetails {
+    pub name: String,
+    pub info: TextureInfo,
+    /// RGBA8 pixels of the top mip level, starting with the first row of the file
+    pub pixels: Vec<u8>,
+    pub channel: Channel,
+    /// Settings shown in the window, which take effect when applied
+    pub settings: TextureSettings,
+    /// Largest anisotropy the driver supports, if it supports anisotropic filtering
+    pub max_anisotropy: Option<f32>,
+    /// Editor-wide limit on the settings
+    pub quality: TextureQuality,
+    /// Preview of the chosen channel, made again when the channel changes
+    pub preview: Option<egui::TextureHandle>,
+}
+
+/// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+/// How long a toast for an error stays on screen, so there is time to read it
+const ERROR_TOAST_DURATION: Duration = Duration::from_secs(12);
+/// Notifications kept for the status bar, the oldest are dropped first
+const NOTIFICATION_CAPACITY: usize = 100;
+
+/// How a notification is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// A message shown as a toast, and then in the status bar until the next one
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub message: String,
+    pub created: Instant,
+}
+
+impl Notification {
+    /// Whether the notification is still shown as a toast
+    pub fn is_active(&self) -> bool {
+        let duration = match self.level {
+            NotificationLevel::Error => ERROR_TOAST_DURATION,
+            _ => TOAST_DURATION,
+        };
+        self.created.elapsed() < duration
+    }
+}
+
+/// Results of what the editor did, such as loading assets, compiling shaders and saving, shown as
+/// toasts in the corner of the editor and in the status bar
+#[derive(Resource, Default)]
+pub struct Notifications {
+    notifications: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: NotificationLevel, message: impl Into<String>) {
+        if self.notifications.len() == NOTIFICATION_CAPACITY {
+            self.notifications.pop_front();
+        }
+        let message = message.into();
+        self.notifications.push_back(Notification { level, message, created: Instant::now() });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Info, message);
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Success, message);
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(NotificationLevel::Error, message);
+    }
+
+    /// Notifications that are still shown as toasts, oldest first
+    pub fn active(&self) -> impl Iterator<Item = &Notification> {
+        self.notifications.iter().filter(|n| n.is_active())
+    }
+
+    /// Every notification kept, oldest first
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &Notification> {
+        self.notifications.iter()
+    }
+
+    /// The newest notification, shown in the status bar
+    pub fn latest(&self) -> Option<&Notification> {
+        self.notifications.back()
+    }
+
+    pub fn clear(&mut self) {
+        self.notifications.clear();
+    }
+}
+
+/// Smooth camera move to a new pose, started by focusing, framing or recalling a view
+#[cfg(feature = "editor")]
+#[derive(Resource, Default)]
+pub struct CameraFlight {
+    from: Option<CameraPose>,
+    to: Option<CameraPose>,
+    elapsed: f32,
+}
+
+#[cfg(feature = "editor")]
+impl CameraFlight {
+    const DURATION: f32 = 0.35;
+
+    pub fn start(&mut self, from: CameraPose, to: CameraPose) {
+        *self = Self { from: Some(from), to: Some(to), elapsed: 0.0 };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.to.is_some()
+    }
+
+    /// Advance the flight by `delta` seconds, returning the new camera pose
+    pub fn advance(&mut self, delta: f32) -> Option<CameraPose> {
+        let (from, to) = (self.from?, self.to?);
+        self.elapsed += delta;
+        let t = (self.elapsed / Self::DURATION).min(1.0);
+        if t >= 1.0 {
+            self.to = None;
+        }
+        // Ease in and out
+        let t = t * t * (3.0 - 2.0 * t);
+        Some(from.lerp(&to, t))
+    }
+}
+
+/// Registered components of the selected entity, serialized for the inspector
+#[cfg(feature = "editor")]
+#[derive(Resource, Default)]
+pub struct InspectedComponents {
+    pub components: Vec<(&'static str, ron::Value)>,
+}
+
+/// Rectangle dragged from empty space in the scene view to select everything it touches
+#[cfg(feature = "editor")]
+#[derive(Resource, Debug, Default, Copy, Clone)]
+pub struct BoxSelection {
+    /// Where the drag started, in pixels from the top left of the scene view
+    pub start: Option<(f64, f64)>,
+    /// Where the mouse is now
+    pub end: (f64, f64),
+}
+
+#[cfg(feature = "editor")]
+impl BoxSelection {
+    /// Pixels the mouse has to move before a click becomes a box
+    pub const THRESHOLD: f64 = 4.0;
+
+    /// Top left and bottom right corners, once the box has been dragged out far enough
+    pub fn rect(&self) -> Option<((f64, f64), (f64, f64))> {
+        let (start, end) = (self.start?, self.end);
+        let (width, height) = ((end.0 - start.0).abs(), (end.1 - start.1).abs());
+        (width.max(height) >= Self::THRESHOLD).then(|| {
+            ((start.0.min(end.0), start.1.min(end.1)), (start.0.max(end.0), start.1.max(end.1)))
+        })
+    }
+}
+
+/// How new objects are placed in the scene
+#[cfg(feature = "editor")]
+#[derive(Resource, Debug, Clone)]
+pub struct Placement {
+    /// Spawn objects on the surface under the mouse, instead of in front of the camera
+    pub on_surfaces: bool,
+    /// Turn objects placed on a surface so their up axis follows its normal
+    pub align_to_normal: bool,
+    /// Clicking in the scene view stamps the model onto the surface there instead of selecting
+    pub brush: bool,
+    /// Model that is spawned and stamped
+    pub model: String,
+}
+
+#[cfg(feature = "editor")]
+impl Default for Placement {
+    fn default() -> Self {
+        Self { on_surfaces: false, align_to_normal: true, brush: false, model: "Cube".to_owned() }
+    }
+}
+
+/// Brush that sculpts and paints terrains in the scene view
+#[cfg(feature = "editor")]
+#[derive(Resource, Debug, Default, Clone)]
+pub struct TerrainBrush {
+    /// Dragging over a terrain applies the brush instead of selecting
+    pub active: bool,
+    pub brush: Brush,
+    /// Whether the current stroke has reached a terrain and set the height `Flatten` moves toward
+    pub stroke_height_set: bool,
 }
 
+/// Entities listed in the outliner, updated while it is open
+#[cfg(feature = "editor")]
+#[derive(Resource, Default)]
+pub struct Outliner {
+    pub entries: Vec<OutlinerEntry>,
+}
+
+/// The egui integration, with the frame split so several systems can add to the UI
+#[cfg(feature = "editor")]
 #[derive(Resource)]
 pub struct EguiGlowRes {
     egui_glow: EguiGlow,
+    shapes: Vec<egui::epaint::ClippedShape>,
+    textures_delta: egui::TexturesDelta,
 }
 
+#[cfg(feature = "editor")]
 impl EguiGlowRes {
     pub fn new(egui_glow: EguiGlow) -> Self {
-        Self { egui_glow }
+        Self { egui_glow, shapes: Vec::new(), textures_delta: Default::default() }
+    }
+
+    /// Start a UI frame, which is drawn into until [`end_frame`](Self::end_frame)
+    pub fn begin_frame(&mut self, window: &Window) {
+        let raw_input = self.egui_glow.egui_winit.take_egui_input(window);
+        self.egui_glow.egui_ctx.begin_frame(raw_input);
+    }
+
+    /// Draw into the UI frame that has been started
+    pub fn draw(&self, f: impl FnOnce(&egui::Context)) {
+        f(&self.egui_glow.egui_ctx);
+    }
+
+    /// Finish the UI frame, keeping what was drawn for [`paint`](Self::paint)
+    pub fn end_frame(&mut self, window: &Window) {
+        let egui_glow = &mut self.egui_glow;
+        let output = egui_glow.egui_ctx.end_frame();
+        egui_glow.egui_winit.handle_platform_output(
+            window,
+            &egui_glow.egui_ctx,
+            output.platform_output,
+        );
+        self.shapes = output.shapes;
+        self.textures_delta.append(output.textures_delta);
+    }
+
+    /// Paint the last finished UI frame to the bound framebuffer
+    pub fn paint(&mut self, window: &Window) {
+        let shapes = std::mem::take(&mut self.shapes);
+        let mut textures_delta = std::mem::take(&mut self.textures_delta);
+        let egui_glow = &mut self.egui_glow;
+
+        for (id, image_delta) in textures_delta.set {
+            egui_glow.painter.set_texture(id, &image_delta);
+        }
+        let clipped_primitives = egui_glow.egui_ctx.tessellate(shapes);
+        let dimensions: [u32; 2] = window.inner_size().into();
+        egui_glow.painter.paint_primitives(
+            dimensions,
+            egui_glow.egui_ctx.pixels_per_point(),
+            &clipped_primitives,
+        );
+        for id in textures_delta.free.drain(..) {
+            egui_glow.painter.free_texture(id);
+        }
     }
 }
 
+#[cfg(feature = "editor")]
 impl std::ops::DerefMut for EguiGlowRes {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.egui_glow
     }
 }
 
+#[cfg(feature = "editor")]
 impl std::ops::Deref for EguiGlowRes {
     type Target = EguiGlow;
 
@@ -437,23 +1378,27 @@ impl std::ops::Deref for EguiGlowRes {
     }
 }
 
+#[cfg(feature = "editor")]
 #[derive(Resource)]
 pub struct WinitWindow {
     window: Arc<Window>,
 }
 
+#[cfg(feature = "editor")]
 impl WinitWindow {
     pub fn new(window: Arc<Window>) -> Self {
         Self { window }
     }
 }
 
+#[cfg(feature = "editor")]
 impl std::ops::DerefMut for WinitWindow {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.window
     }
 }
 
+#[cfg(feature = "editor")]
 impl std::ops::Deref for WinitWindow {
     type Target = Arc<Window>;
 
@@ -462,59 +1407,573 @@ impl std::ops::Deref for WinitWindow {
     }
 }
 
-#[derive(Resource)]
+/// Part of the window the scene texture is shown in, which is what is left between the UI panels
+#[cfg(feature = "editor")]
+#[derive(Resource, Debug)]
+pub struct SceneView {
+    /// Area in points, as laid out by egui
+    pub rect: egui::Rect,
+    pub pixels_per_point: f32,
+    /// The scene texture registered with the egui painter
+    pub texture: Option<egui::TextureId>,
+}
+
+#[cfg(feature = "editor")]
+impl SceneView {
+    /// A view that is laid out on the first UI frame
+    pub fn new(texture: Option<egui::TextureId>) -> Self {
+        Self { rect: egui::Rect::NOTHING, pixels_per_point: 1.0, texture }
+    }
+
+    /// Size of the view in physical pixels
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.rect.size() * self.pixels_per_point;
+        (size.x.round() as u32, size.y.round() as u32)
+    }
+
+    /// Convert a cursor position in window pixels to pixels from the top left of the view
+    pub fn to_local(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let ppp = self.pixels_per_point as f64;
+        (x - self.rect.min.x as f64 * ppp, y - self.rect.min.y as f64 * ppp)
+    }
+}
+
+/// An asset that could not be loaded, and what is used in its place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetError {
+    /// File or name of the asset
+    pub asset: String,
+    pub message: String,
+}
+
+impl AssetError {
+    pub(crate) fn new(asset: impl fmt::Display, message: impl fmt::Display) -> Self {
+        let error = Self { asset: asset.to_string(), message: message.to_string() };
+        warn!("{error}");
+        error
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.asset, self.message)
+    }
+}
+
+/// Squares along each side of the texture shown in place of a missing one
+const CHECKER_SIZE: usize = 8;
+
+/// Models the editor spawns on startup and from shortcuts, which are built in if not loaded
+pub const BUILTIN_MODELS: [&str; 3] = ["Cube", "Plane", "Sphere"];
+
+/// Reads the models in a file of a format the loader does not know, returning them by name
+pub type ModelImporter = fn(&Path) -> Result<Vec<(String, MeshData)>>;
+
+#[derive(Resource, Default)]
 pub struct ModelLoader {
     models: AHashMap<String, VertexArrayObject>,
+    skins: AHashMap<String, Arc<SkinData>>,
+    meshes: AHashMap<String, MeshData>,
+    /// Names of the generated levels of detail of each model, from the most detailed
+    lods: AHashMap<String, Vec<String>>,
+    /// Fraction of the triangles kept in each level generated for loaded models
+    lod_ratios: Vec<f32>,
+    importers: AHashMap<String, ModelImporter>,
+    cache: Option<DerivedCache>,
+    errors: Vec<AssetError>,
 }
 
 impl ModelLoader {
     pub fn new() -> Self {
-        Self { models: AHashMap::new() }
+        Self {
+            models: AHashMap::new(),
+            skins: AHashMap::new(),
+            meshes: AHashMap::new(),
+            lods: AHashMap::new(),
+            lod_ratios: Vec::new(),
+            importers: AHashMap::new(),
+            cache: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Load files with the given extension, without the dot, with `importer`
+    pub fn add_importer(&mut self, extension: &str, importer: ModelImporter) {
+        self.importers.insert(extension.to_ascii_lowercase(), importer);
+    }
+
+    /// Keep generated tangents in a cache, so they are only generated again when a model changes
+    pub fn with_cache(mut self, cache: DerivedCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Generate simpler levels of detail for the models loaded from files, keeping `ratios` of
+    /// their triangles
+    ///
+    /// Models with fewer than [`lod::MIN_TRIANGLES`] triangles and skinned models get no levels.
+    pub fn with_lods(mut self, ratios: &[f32]) -> Self {
+        self.lod_ratios = ratios.to_vec();
+        self
+    }
+
+    /// Load every model file in a directory
+    ///
+    /// Files that can not be loaded are skipped and listed in [`errors`](Self::errors), so one
+    /// broken file does not keep the others from loading.
+    pub fn load_models_in_dir<P>(&mut self, gl: &Context, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        let entries = match path.as_ref().read_dir() {
+            Ok(entries) => entries,
+            Err(e) => {
+                let message = format!("could not read the models directory: {e}");
+                self.errors.push(AssetError::new(path.as_ref().display(), message));
+                return;
+            }
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    self.errors.push(AssetError::new(path.as_ref().display(), e));
+                    continue;
+                }
+            };
+            let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+            let result = match extension.as_deref() {
+                Some("gltf" | "glb") => self.load_gltf(gl, &path),
+                Some(extension) if self.importers.contains_key(extension) => {
+                    let importer = self.importers[extension];
+                    self.load_imported(gl, &path, importer)
+                }
+                _ => self.load_model(gl, &path),
+            };
+            if let Err(e) = result {
+                self.errors.push(AssetError::new(path.display(), e));
+            }
+        }
+    }
+
+    /// Build the models in [`BUILTIN_MODELS`] that were not loaded from files
+    pub fn add_missing_builtins(&mut self, gl: &Context) {
+        for name in BUILTIN_MODELS {
+            if self.models.contains_key(name) {
+                continue;
+            }
+            let mesh = match name {
+                "Cube" => MeshData::cube(),
+                "Plane" => MeshData::plane(),
+                _ => MeshData::sphere(32, 16),
+            };
+            if self.insert(gl, name.to_owned(), mesh).is_ok() {
+                self.errors.push(AssetError::new(name, "not found, using a built-in model"));
+            }
+        }
+    }
+
+    /// A model by name, or a cube put in its place if it is not loaded
+    ///
+    /// The cube is added under the missing name, so scenes that use it are saved with the name
+    /// they had.
+    pub fn get_or_placeholder(&mut self, gl: &Context, name: &str) -> Result<&VertexArrayObject> {
+        if !self.models.contains_key(name) {
+            self.upload(gl, name.to_owned(), MeshData::cube())?;
+            self.errors.push(AssetError::new(name, "model not found, showing a cube instead"));
+        }
+        self.models.get(name).ok_or_else(|| eyre!("model {name} is not loaded"))
+    }
+
+    /// Assets that could not be loaded, oldest first
+    pub fn errors(&self) -> &[AssetError] {
+        &self.errors
+    }
+
+    pub fn clear_errors(&mut self) {
+        self.errors.clear();
+    }
+
+    pub fn load_model<P>(&mut self, gl: &Context, path: P) -> Result<()>
+    where
+        P: AsRef<Path> + fmt::Debug,
+    {
+        self.load_obj(gl, path.as_ref()).map(drop)
+    }
+
+    /// Load every model in an OBJ file, returning their names
+    ///
+    /// A model named like one that is already loaded gets a name of its own, see
+    /// [`unique_name`](Self::unique_name).
+    fn load_obj(&mut self, gl: &Context, path: &Path) -> Result<Vec<String>> {
+        let (models, _) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
+        let models = models.into_iter().fuse();
+
+        if models.len() == 0 {
+            return Err(eyre!("OBJ had no models: {}", path.display()));
+        }
+
+        let mut names = Vec::with_capacity(models.len());
+        for model in models {
+            let vertices = bytemuck::cast_slice(&model.mesh.positions);
+            let indices = &model.mesh.indices;
+            let normals = bytemuck::cast_slice(&model.mesh.normals);
+            let texture_coords = bytemuck::cast_slice(&model.mesh.texcoords);
+            let tangents =
+                self.tangents(path, &model.name, vertices, normals, texture_coords, indices);
+            let mut vao =
+                unsafe { VertexArrayObject::new(gl, vertices, indices, normals, texture_coords) };
+            if let Some(tangents) = tangents {
+                unsafe { vao.add_tangents(gl, &tangents) };
+            }
+
+            let mesh = MeshData::new(
+                vertices.to_vec(),
+                normals.to_vec(),
+                texture_coords.to_vec(),
+                indices.clone(),
+            );
+            let name = self.unique_name(&model.name);
+            self.meshes.insert(name.clone(), mesh);
+            self.models.insert(name.clone(), vao);
+            self.generate_lods(gl, &name)?;
+            names.push(name);
+        }
+
+        Ok(names)
+    }
+
+    /// Load the models an importer reads from a file
+    fn load_imported(&mut self, gl: &Context, path: &Path, importer: ModelImporter) -> Result<()> {
+        for (name, mesh) in importer(path)? {
+            let name = self.unique_name(&name);
+            self.insert(gl, name.clone(), mesh)?;
+            self.generate_lods(gl, &name)?;
+        }
+        Ok(())
+    }
+
+    /// Load every mesh primitive in a glTF file, along with its skin and animations
+    pub fn load_gltf<P>(&mut self, gl: &Context, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.load_gltf_meshes(gl, path.as_ref()).map(drop)
+    }
+
+    /// Load every mesh primitive in a glTF file, returning their names in node order with the
+    /// properties in the `extras` of their node
+    ///
+    /// A mesh used by several nodes is loaded once. A primitive named like a model that is already
+    /// loaded gets a name of its own, see [`unique_name`](Self::unique_name).
+    fn load_gltf_meshes(&mut self, gl: &Context, path: &Path) -> Result<Vec<(String, Properties)>> {
+        let (document, buffers, _) = gltf::import(path)?;
+        let file_stem = path
+            .file_stem()
+            .ok_or_else(|| eyre!("could not get file stem"))?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut names = Vec::new();
+        let mut loaded: AHashMap<(usize, usize), String> = AHashMap::new();
+        for node in document.nodes() {
+            let Some(mesh) = node.mesh() else {
+                continue;
+            };
+            let properties = node
+                .extras()
+                .as_ref()
+                .and_then(|extras| Properties::from_gltf_extras(extras.get()))
+                .unwrap_or_default();
+            let mesh_name =
+                mesh.name().map_or_else(|| format!("{file_stem}{}", mesh.index()), str::to_owned);
+            let skin = node.skin().and_then(|skin| {
+                // The shaders only hold MAX_JOINTS matrices, so a bigger skeleton would be drawn
+                // with the wrong joints
+                let joints = skin.joints().count();
+                if joints > MAX_JOINTS {
+                    warn!("{mesh_name} has {joints} joints, over {MAX_JOINTS}, so it is unskinned");
+                    return None;
+                }
+                Some(Arc::new(SkinData::from_gltf(&document, &buffers, &skin)))
+            });
+
+            for (i, primitive) in mesh.primitives().enumerate() {
+                if let Some(name) = loaded.get(&(mesh.index(), i)) {
+                    names.push((name.clone(), properties.clone()));
+                    continue;
+                }
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+                let vertices: Vec<glm::Vec3> = positions.map(Into::into).collect();
+                let len = vertices.len();
+                let indices: Vec<u32> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..len as u32).collect(),
+                };
+                let normals: Vec<glm::Vec3> = match reader.read_normals() {
+                    Some(normals) => normals.map(Into::into).collect(),
+                    None => vec![glm::Vec3::zeros(); len],
+                };
+                let texture_coords: Option<Vec<glm::Vec2>> = reader
+                    .read_tex_coords(0)
+                    .map(|coords| coords.into_f32().map(Into::into).collect());
+
+                let name = if i == 0 { mesh_name.clone() } else { format!("{mesh_name}.{i}") };
+                let tangents = match reader.read_tangents() {
+                    Some(tangents) => Some(tangents.map(Into::into).collect()),
+                    None => texture_coords.as_ref().and_then(|texture_coords| {
+                        self.tangents(path, &name, &vertices, &normals, texture_coords, &indices)
+                    }),
+                };
+                let texture_coords =
+                    texture_coords.unwrap_or_else(|| vec![glm::Vec2::zeros(); len]);
+                let joints = reader.read_joints(0);
+                let weights = reader.read_weights(0);
+                let mut skin_data = None;
+                let mut vao = match (&skin, joints, weights) {
+                    (Some(skin), Some(joints), Some(weights)) => {
+                        let joints: Vec<glm::Vec4> = joints
+                            .into_u16()
+                            .map(|j| glm::vec4(j[0] as f32, j[1] as f32, j[2] as f32, j[3] as f32))
+                            .collect();
+                        let weights: Vec<glm::Vec4> = weights.into_f32().map(Into::into).collect();
+                        skin_data = Some(skin.clone());
+                        unsafe {
+                            VertexArrayObject::new_skinned(
+                                gl,
+                                &vertices,
+                                &indices,
+                                &normals,
+                                &texture_coords,
+                                &joints,
+                                &weights,
+                            )
+                        }
+                    }
+                    _ => unsafe {
+                        VertexArrayObject::new(gl, &vertices, &indices, &normals, &texture_coords)
+                    },
+                };
+                if let Some(tangents) = tangents {
+                    unsafe { vao.add_tangents(gl, &tangents) };
+                }
+
+                let name = self.unique_name(&name);
+                if let Some(skin) = skin_data {
+                    self.skins.insert(name.clone(), skin);
+                }
+                let mesh_data = MeshData::new(vertices, normals, texture_coords, indices);
+                self.meshes.insert(name.clone(), mesh_data);
+                self.models.insert(name.clone(), vao);
+                self.generate_lods(gl, &name)?;
+                loaded.insert((mesh.index(), i), name.clone());
+                names.push((name, properties.clone()));
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Load a model file, sharing one model between meshes that only differ in where they are
+    ///
+    /// Meshes with the same geometry as an earlier mesh of the file, such as repeated bolts, are
+    /// not kept as models of their own. Instead the file is returned as a flat list of parts that
+    /// place the shared models where each mesh was. Skinned meshes are never shared.
+    pub fn import<P>(&mut self, gl: &Context, path: P) -> Result<(Vec<ImportedPart>, ImportReport)>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let names = match path.extension().and_then(|e| e.to_str()) {
+            Some("gltf" | "glb") => self.load_gltf_meshes(gl, path)?,
+            _ => self
+                .load_obj(gl, path)?
+                .into_iter()
+                .map(|name| (name, Default::default()))
+                .collect(),
+        };
+
+        let mut report = ImportReport { meshes: names.len(), ..Default::default() };
+        let mut parts = Vec::with_capacity(names.len());
+        let mut shared: AHashMap<u64, Vec<String>> = AHashMap::new();
+        for (name, properties) in names {
+            let Some(mesh) = self.meshes.get(&name) else {
+                continue;
+            };
+            if self.skins.contains_key(&name) {
+                parts.push(ImportedPart { model: name, offset: glm::Vec3::zeros(), properties });
+                continue;
+            }
+
+            let candidates = shared.entry(instancing::geometry_hash(mesh)).or_default();
+            if candidates.contains(&name) {
+                // A glTF mesh used by several nodes is only loaded once
+                report.instances += 1;
+                parts.push(ImportedPart { model: name, offset: glm::Vec3::zeros(), properties });
+                continue;
+            }
+            let instance = candidates.iter().find_map(|model| {
+                let offset = instancing::instance_offset(&self.meshes[model], mesh)?;
+                Some((model.clone(), offset))
+            });
+            let Some((model, offset)) = instance else {
+                candidates.push(name.clone());
+                parts.push(ImportedPart { model: name, offset: glm::Vec3::zeros(), properties });
+                continue;
+            };
+
+            let mesh = self.meshes.remove(&name).unwrap();
+            self.remove_lods(gl, &name);
+            if let Some(mut vao) = self.models.remove(&name) {
+                let tangent_size = std::mem::size_of::<glm::Vec4>() * mesh.vertex_count();
+                report.bytes_saved += if vao.has_tangents { tangent_size } else { 0 };
+                unsafe { vao.destroy(gl) };
+            }
+            report.instances += 1;
+            report.bytes_saved += instancing::gpu_size(&mesh);
+            parts.push(ImportedPart { model, offset, properties });
+        }
+
+        Ok((parts, report))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&VertexArrayObject> {
+        self.models.get(name)
+    }
+
+    /// Vertices and triangles of a model, as they were uploaded
+    pub fn mesh_data(&self, name: &str) -> Option<&MeshData> {
+        self.meshes.get(name)
+    }
+
+    /// Add a model built in code, such as a cleaned up mesh, with generated tangents
+    ///
+    /// Returns an error if a model with the name already exists, or if the name is one that
+    /// generated levels of detail are named with, see [`lod::level_name`].
+    pub fn insert(
+        &mut self,
+        gl: &Context,
+        name: String,
+        mesh: MeshData,
+    ) -> Result<&VertexArrayObject> {
+        if lod::is_level_name(&name) {
+            return Err(eyre!("{name} is named like a generated level of detail"));
+        }
+        self.upload(gl, name, mesh)
     }
 
-    pub fn load_models_in_dir<P>(&mut self, gl: &Context, path: P) -> Result<()>
-    where
-        P: AsRef<Path>,
-    {
-        for entry in path.as_ref().read_dir()? {
-            let entry = entry?;
-            self.load_model(gl, entry.path())?;
+    /// Add a model under a name that is not taken, with generated tangents
+    fn upload(&mut self, gl: &Context, name: String, mesh: MeshData) -> Result<&VertexArrayObject> {
+        if self.models.contains_key(&name) {
+            return Err(eyre!("a model named {name} already exists"));
         }
 
-        Ok(())
+        let MeshData { positions, normals, texture_coords, indices } = &mesh;
+        let mut vao =
+            unsafe { VertexArrayObject::new(gl, positions, indices, normals, texture_coords) };
+        if let Some(tangents) =
+            tangents::generate_tangents(positions, normals, texture_coords, indices)
+        {
+            unsafe { vao.add_tangents(gl, &tangents) };
+        }
+        self.meshes.insert(name.clone(), mesh);
+        Ok(self.models.entry(name).or_insert(vao))
     }
 
-    pub fn load_model<P>(&mut self, gl: &Context, path: P) -> Result<()>
-    where
-        P: AsRef<Path> + fmt::Debug,
-    {
-        let (models, _) = tobj::load_obj(&path, &tobj::GPU_LOAD_OPTIONS)?;
-        let models = models.into_iter().fuse();
+    /// Generated levels of detail of a model, from the most detailed, leaving out the model itself
+    pub fn lods(&self, name: &str) -> impl Iterator<Item = &VertexArrayObject> {
+        let levels = self.lods.get(name).map_or(&[][..], Vec::as_slice);
+        levels.iter().filter_map(|level| self.models.get(level))
+    }
 
-        if models.len() == 0 {
-            return Err(eyre!("OBJ had no models: {}", path.as_ref().display()));
+    /// Add the levels of detail of a model that was just loaded, if it is detailed enough
+    fn generate_lods(&mut self, gl: &Context, name: &str) -> Result<()> {
+        // Levels of a model loaded again are made from the new mesh
+        self.remove_lods(gl, name);
+        let Some(mesh) = self.meshes.get(name) else {
+            return Ok(());
+        };
+        if self.skins.contains_key(name) || mesh.triangle_count() < lod::MIN_TRIANGLES {
+            return Ok(());
         }
 
-        for model in models {
-            let vertices = bytemuck::cast_slice(&model.mesh.positions);
-            let indices = &model.mesh.indices;
-            let normals = bytemuck::cast_slice(&model.mesh.normals);
-            let texture_coords = bytemuck::cast_slice(&model.mesh.texcoords);
-            let vao =
-                unsafe { VertexArrayObject::new(gl, vertices, indices, normals, texture_coords) };
+        let mut levels = Vec::new();
+        let mut previous_triangles = mesh.triangle_count();
+        let simplified: Vec<_> =
+            self.lod_ratios.iter().map(|&ratio| lod::simplify(mesh, ratio)).collect();
+        for (i, level) in simplified.into_iter().enumerate() {
+            // A level that saves nothing over the one before it would only cost memory
+            if level.triangle_count() == 0 || level.triangle_count() >= previous_triangles {
+                break;
+            }
+            previous_triangles = level.triangle_count();
+            let level_name = lod::level_name(name, i + 1);
+            self.upload(gl, level_name.clone(), level)?;
+            levels.push(level_name);
+        }
+        if !levels.is_empty() {
+            self.lods.insert(name.to_owned(), levels);
+        }
+        Ok(())
+    }
 
-            self.models.insert(model.name, vao);
+    fn remove_lods(&mut self, gl: &Context, name: &str) {
+        for level in self.lods.remove(name).unwrap_or_default() {
+            self.meshes.remove(&level);
+            if let Some(mut vao) = self.models.remove(&level) {
+                unsafe { vao.destroy(gl) };
+            }
         }
+    }
 
-        Ok(())
+    /// A model name starting with `base` that is not taken yet, and not one that generated
+    /// levels of detail are named with
+    pub fn unique_name(&self, base: &str) -> String {
+        (1..)
+            .map(|i| if i == 1 { base.to_owned() } else { format!("{base}.{i}") })
+            .find(|name| !self.models.contains_key(name) && !lod::is_level_name(name))
+            .unwrap()
     }
 
-    pub fn get(&self, name: &str) -> Option<&VertexArrayObject> {
-        self.models.get(name)
+    /// MikkTSpace tangents for a mesh, from the cache if the model has not changed
+    fn tangents(
+        &self,
+        source: &Path,
+        mesh: &str,
+        vertices: &[glm::Vec3],
+        normals: &[glm::Vec3],
+        texture_coords: &[glm::Vec2],
+        indices: &[u32],
+    ) -> Option<Vec<glm::Vec4>> {
+        let generate = || {
+            tangents::generate_tangents(vertices, normals, texture_coords, indices)
+                .unwrap_or_default()
+        };
+        let tangents = match &self.cache {
+            Some(cache) => cache.get_or_insert_with(source, &format!("{mesh}.tangents"), generate),
+            None => generate(),
+        };
+        // Meshes without texture coordinates get no tangents, which is cached as an empty entry
+        (tangents.len() == vertices.len()).then_some(tangents)
+    }
+
+    /// Get the skin of a skinned glTF model
+    pub fn skin(&self, name: &str) -> Option<&Arc<SkinData>> {
+        self.skins.get(name)
+    }
+
+    /// Find the name of the model a VAO belongs to
+    pub fn name_of(&self, vao_id: VertexArray) -> Option<&str> {
+        self.models.iter().find(|(_, vao)| vao.vao_id == vao_id).map(|(name, _)| name.as_str())
     }
 
+    /// Names of the models, leaving out generated levels of detail
     pub fn keys(&self) -> impl Iterator<Item = &String> {
-        self.models.keys()
+        let levels: AHashSet<_> = self.lods.values().flatten().collect();
+        self.models.keys().filter(move |name| !levels.contains(name))
     }
 
     pub fn values_mut(&mut self) -> impl Iterator<Item = &mut VertexArrayObject> {
@@ -522,32 +1981,93 @@ impl ModelLoader {
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct TextureLoader {
     textures: AHashMap<String, glow::Texture>,
+    /// Textures loaded from files, without the placeholders
+    info: AHashMap<String, TextureInfo>,
+    /// Limit on the filtering the import settings ask for
+    quality: TextureQuality,
+    errors: Vec<AssetError>,
 }
 
 impl TextureLoader {
     pub fn new() -> Self {
-        Self { textures: AHashMap::new() }
+        Self::default()
+    }
+
+    pub fn quality(&self) -> TextureQuality {
+        self.quality
+    }
+
+    /// Limit the filtering of every texture, including the ones already loaded
+    pub fn set_quality(&mut self, gl: &Context, quality: TextureQuality) {
+        self.quality = quality;
+        for (name, info) in &self.info {
+            let Some(&texture) = self.textures.get(name) else {
+                continue;
+            };
+            unsafe {
+                gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                quality.limit(info.settings).apply(gl);
+            }
+        }
     }
 
-    pub fn load_textures_in_dir<P>(&mut self, gl: &Context, path: P) -> Result<()>
+    /// Load every texture in a directory, with the import settings next to it
+    ///
+    /// Textures that can not be loaded are replaced by a magenta checker under the same name and
+    /// listed in [`errors`](Self::errors).
+    pub fn load_textures_in_dir<P>(&mut self, gl: &Context, path: P)
     where
         P: AsRef<Path>,
     {
-        for entry in path.as_ref().read_dir()? {
-            let entry = entry?;
-            self.load_texture(gl, entry.path())?;
+        let entries = match path.as_ref().read_dir() {
+            Ok(entries) => entries,
+            Err(e) => {
+                let message = format!("could not read the textures directory: {e}");
+                self.errors.push(AssetError::new(path.as_ref().display(), message));
+                return;
+            }
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    self.errors.push(AssetError::new(path.as_ref().display(), e));
+                    continue;
+                }
+            };
+            if path.extension().is_some_and(|e| e == texture_import::SETTINGS_EXTENSION) {
+                continue;
+            }
+            if let Err(e) = self.load_texture(gl, &path) {
+                self.errors.push(AssetError::new(path.display(), e));
+                if let Some(name) = path.file_stem() {
+                    self.insert_placeholder(gl, &name.to_string_lossy());
+                }
+            }
         }
-
-        Ok(())
     }
 
+    /// Load a texture file under the name of the file, with the import settings next to it
+    ///
+    /// Loading a texture again keeps its handle, so entities using it show the new contents.
     pub fn load_texture<P>(&mut self, gl: &Context, path: P) -> Result<()>
     where
         P: AsRef<Path>,
     {
+        let file_stem = path
+            .as_ref()
+            .file_stem()
+            .ok_or_else(|| eyre!("could not get file stem"))?
+            .to_string_lossy()
+            .into_owned();
+        let settings = TextureSettings::load_for(path.as_ref()).unwrap_or_else(|e| {
+            self.errors.push(AssetError::new(path.as_ref().display(), e));
+            TextureSettings::default()
+        });
+
         let contents = std::fs::read(path.as_ref())?;
         let byte_endian =
             if cfg!(target_endian = "little") { ByteEndian::LE } else { ByteEndian::BE };
@@ -557,13 +2077,14 @@ impl TextureLoader {
         );
         decoder.decode_headers().map_err(|_| eyre!("could not decode PNG headers"))?;
 
-        let color_space = decoder.get_colorspace().unwrap();
-        let bit_depth = decoder.get_depth().unwrap();
-        let (source_format, source_type) = match (color_space, bit_depth) {
-            (ColorSpace::RGB, BitDepth::Eight) => (glow::RGB, glow::UNSIGNED_BYTE),
-            (ColorSpace::RGB, BitDepth::Sixteen) => (glow::RGB, glow::UNSIGNED_SHORT),
-            (ColorSpace::RGBA, BitDepth::Eight) => (glow::RGBA, glow::UNSIGNED_BYTE),
-            (ColorSpace::RGBA, BitDepth::Sixteen) => (glow::RGBA, glow::UNSIGNED_SHORT),
+        let color_space =
+            decoder.get_colorspace().ok_or_else(|| eyre!("PNG has no color space"))?;
+        let bit_depth = decoder.get_depth().ok_or_else(|| eyre!("PNG has no bit depth"))?;
+        let (source_format, source_type, format) = match (color_space, bit_depth) {
+            (ColorSpace::RGB, BitDepth::Eight) => (glow::RGB, glow::UNSIGNED_BYTE, "RGB8"),
+            (ColorSpace::RGB, BitDepth::Sixteen) => (glow::RGB, glow::UNSIGNED_SHORT, "RGB16"),
+            (ColorSpace::RGBA, BitDepth::Eight) => (glow::RGBA, glow::UNSIGNED_BYTE, "RGBA8"),
+            (ColorSpace::RGBA, BitDepth::Sixteen) => (glow::RGBA, glow::UNSIGNED_SHORT, "RGBA16"),
             _ => {
                 return Err(eyre!(
                     "invalid bit depth {:?} of image {}",
@@ -573,49 +2094,150 @@ impl TextureLoader {
             }
         };
 
-        let (width, height) = decoder.get_dimensions().unwrap();
+        let (width, height) =
+            decoder.get_dimensions().ok_or_else(|| eyre!("PNG has no dimensions"))?;
         let bytes = decoder.decode_raw().map_err(|_| eyre!("could not decode PNG image"))?;
 
         let texture = unsafe {
-            let texture =
-                gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?;
+            let texture = match self.textures.get(&file_stem) {
+                Some(&texture) => texture,
+                None => gl.create_texture().map_err(|e| eyre!("could not create texture: {e}"))?,
+            };
             gl.bind_texture(glow::TEXTURE_2D, Some(texture));
-            gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                glow::RGBA as i32,
-                width as i32,
-                height as i32,
-                0,
-                source_format,
-                source_type,
-                Some(&bytes),
-            );
-            gl.tex_parameter_i32(
-                glow::TEXTURE_2D,
-                glow::TEXTURE_MIN_FILTER,
-                glow::LINEAR_MIPMAP_NEAREST as i32,
+            gl_check!(
+                gl,
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    settings.internal_format() as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    source_format,
+                    source_type,
+                    Some(&bytes),
+                )
             );
-            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
-            gl.generate_mipmap(glow::TEXTURE_2D);
+            self.quality.limit(settings).apply(gl);
+            gl_check!(gl, gl.generate_mipmap(glow::TEXTURE_2D));
             texture
         };
 
-        let file_stem = path
-            .as_ref()
-            .file_stem()
-            .ok_or_else(|| eyre!("could not get file stem"))?
-            .to_string_lossy()
-            .into_owned();
+        let info = TextureInfo {
+            path: path.as_ref().to_owned(),
+            width: width as u32,
+            height: height as u32,
+            format: format.to_owned(),
+            mip_levels: texture_import::mip_levels(width as u32, height as u32),
+            settings,
+        };
+        self.info.insert(file_stem.clone(), info);
         self.textures.insert(file_stem, texture);
 
         Ok(())
     }
 
+    /// Load a texture from its file again, picking up changes to the file and its import settings
+    pub fn reload(&mut self, gl: &Context, name: &str) -> Result<()> {
+        let path = self.path_of(name)?;
+        self.load_texture(gl, path)
+    }
+
+    /// Save the import settings of a texture next to its file and load it again with them
+    pub fn set_settings(
+        &mut self,
+        gl: &Context,
+        name: &str,
+        settings: TextureSettings,
+    ) -> Result<()> {
+        let path = self.path_of(name)?;
+        settings.save_for(&path)?;
+        self.load_texture(gl, path)
+    }
+
+    fn path_of(&self, name: &str) -> Result<PathBuf> {
+        self.info
+            .get(name)
+            .map(|info| info.path.clone())
+            .ok_or_else(|| eyre!("texture {name} was not loaded from a file"))
+    }
+
+    /// Size, format and import settings of a texture loaded from a file
+    pub fn info(&self, name: &str) -> Option<&TextureInfo> {
+        self.info.get(name)
+    }
+
     pub fn get(&self, name: &str) -> Option<&Texture> {
         self.textures.get(name)
     }
 
+    /// A texture by name, or a magenta checker put in its place if it is not loaded
+    pub fn get_or_placeholder(&mut self, gl: &Context, name: &str) -> Option<Texture> {
+        if !self.textures.contains_key(name) {
+            self.errors.push(AssetError::new(name, "texture not found, showing a checker instead"));
+            self.insert_placeholder(gl, name);
+        }
+        self.textures.get(name).copied()
+    }
+
+    /// Add a magenta and black checker texture under `name`
+    ///
+    /// Each name gets a texture of its own, so [`name_of`](Self::name_of) still finds the name a
+    /// scene refers to.
+    fn insert_placeholder(&mut self, gl: &Context, name: &str) {
+        let pixels: Vec<u8> = (0..CHECKER_SIZE * CHECKER_SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % CHECKER_SIZE, i / CHECKER_SIZE);
+                if (x + y) % 2 == 0 { [255, 0, 255, 255] } else { [0, 0, 0, 255] }
+            })
+            .collect();
+
+        let texture = unsafe {
+            let texture = match gl.create_texture() {
+                Ok(texture) => texture,
+                Err(e) => {
+                    warn!("could not create a placeholder for texture {name}: {e}");
+                    return;
+                }
+            };
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl_check!(
+                gl,
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA as i32,
+                    CHECKER_SIZE as i32,
+                    CHECKER_SIZE as i32,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    Some(&pixels),
+                )
+            );
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+            gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+            texture
+        };
+        self.textures.insert(name.to_owned(), texture);
+    }
+
+    /// Textures that could not be loaded, oldest first
+    pub fn errors(&self) -> &[AssetError] {
+        &self.errors
+    }
+
+    pub fn clear_errors(&mut self) {
+        self.errors.clear();
+    }
+
+    /// Find the name a texture was loaded under
+    pub fn name_of(&self, texture: Texture) -> Option<&str> {
+        self.textures.iter().find(|(_, &t)| t == texture).map(|(name, _)| name.as_str())
+    }
+
     pub fn keys(&self) -> impl Iterator<Item = &String> {
         self.textures.keys()
     }
@@ -623,6 +2245,7 @@ impl TextureLoader {
 
 #[derive(Resource)]
 pub struct Time {
+    start: Instant,
     prev_frame_time: Instant,
     prev_avg_frame_time: Instant,
     frame_count: u32,
@@ -634,6 +2257,7 @@ impl Time {
     pub fn new() -> Self {
         let now = Instant::now();
         Self {
+            start: now,
             prev_frame_time: now,
             prev_avg_frame_time: now,
             frame_count: 0,
@@ -655,87 +2279,615 @@ impl Time {
         }
     }
 
+    /// Advance by a fixed step instead of the measured time, for rendering frames at a steady rate
+    pub fn step(&mut self, delta: Duration) {
+        self.delta_time = delta;
+        self.prev_frame_time += delta;
+    }
+
+    /// Leave out time the editor was paused, so the next frame does not jump ahead by it
+    pub fn skip(&mut self, paused: Duration) {
+        self.start += paused;
+        self.prev_frame_time += paused;
+        self.prev_avg_frame_time += paused;
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta_time
+    }
+
     pub fn delta_seconds(&self) -> f32 {
         self.delta_time.as_secs_f32()
     }
 
+    /// Seconds since the clock was created
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.prev_frame_time.duration_since(self.start).as_secs_f32()
+    }
+
     pub fn avg_frame_time_ms(&self) -> f32 {
         self.avg_frame_time_ms
     }
 }
 
-impl FromWorld for Time {
-    fn from_world(_world: &mut World) -> Self {
+impl Default for Time {
+    fn default() -> Self {
         Self::new()
     }
 }
 
-#[derive(Resource, Default)]
+/// Clock of the simulation, which advances in steps of the same length however long frames take
+///
+/// The time of each frame is added to an accumulator, and a step is taken for every whole step
+/// in it. What is left over tells how far the drawn frame is between the last two steps.
+#[derive(Resource, Debug)]
+pub struct FixedTime {
+    step: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTime {
+    pub const DEFAULT_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+    /// Steps taken in one frame at most, so a slow frame does not make the next one slower still
+    pub const MAX_STEPS: u32 = 8;
+
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "the fixed time step must be longer than zero");
+        Self { step, accumulator: Duration::ZERO }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Length of a step, which simulation systems advance by
+    pub fn delta_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Add the time a frame took, dropping what would take more than `MAX_STEPS` steps
+    pub fn accumulate(&mut self, delta: Duration) {
+        self.accumulator = (self.accumulator + delta).min(self.step * Self::MAX_STEPS);
+    }
+
+    /// Take a step out of the accumulator, if it holds a whole one
+    pub fn expend(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How far the time left in the accumulator is into the next step, from 0 to 1
+    pub fn overstep_fraction(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+impl Default for FixedTime {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_STEP)
+    }
+}
+
+/// Directional light shining on the whole scene, like the sun
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    pub direction: glm::Vec3,
+    pub ambient: glm::Vec3,
+    pub diffuse: glm::Vec3,
+    pub specular: glm::Vec3,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: glm::vec3(-0.2, -0.7, -0.5),
+            ambient: glm::vec3(0.2, 0.2, 0.2),
+            diffuse: glm::vec3(0.5, 0.5, 0.5),
+            specular: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Lighting that is not attached to an entity
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lighting {
+    pub sun: DirectionalLight,
+    /// Multiplier applied to the lit scene
+    pub exposure: f32,
+    /// Color shown where no object was drawn
+    pub background: glm::Vec3,
+    pub fog: Fog,
+    /// Draw a sky lit by the sun where no object was drawn, in place of the background color
+    pub sky: bool,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            sun: DirectionalLight::default(),
+            exposure: 1.0,
+            background: glm::vec3(0.4, 0.4, 1.0),
+            fog: Fog::default(),
+            sky: false,
+        }
+    }
+}
+
+/// Fog that thickens with distance from the camera and thins out with height
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fog {
+    pub enabled: bool,
+    pub color: glm::Vec3,
+    /// How much of the light is lost to fog per unit traveled, at `height`
+    pub density: f32,
+    /// Distance from the camera where the fog starts
+    pub start: f32,
+    /// Height at which the fog has its density, getting thicker below it
+    pub height: f32,
+    /// How quickly the fog thins out above `height`, or 0 for the same density at every height
+    pub height_falloff: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: glm::vec3(0.6, 0.65, 0.7),
+            density: 0.02,
+            start: 0.0,
+            height: 0.0,
+            height_falloff: 0.1,
+        }
+    }
+}
+
+/// How many samples a post effect takes for each pixel, trading speed for smoother results
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl EffectQuality {
+    pub const ALL: [Self; 3] = [Self::Low, Self::Medium, Self::High];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+        }
+    }
+
+    /// Samples per pixel of an effect that takes `low` samples at low quality
+    pub fn samples(self, low: i32) -> i32 {
+        match self {
+            Self::Low => low,
+            Self::Medium => low * 2,
+            Self::High => low * 4,
+        }
+    }
+}
+
+/// Blur of what is nearer or further than the focus distance, as seen through a camera lens
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepthOfField {
+    pub enabled: bool,
+    /// Distance from the camera that is in focus
+    pub focus_distance: f32,
+    /// Size of the lens opening, where a larger one blurs more outside the focus distance
+    pub aperture: f32,
+    pub quality: EffectQuality,
+}
+
+impl Default for DepthOfField {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            focus_distance: 10.0,
+            aperture: 0.5,
+            quality: EffectQuality::default(),
+        }
+    }
+}
+
+/// Blur along the motion of each pixel on screen, as with a camera shutter open for part of a
+/// frame
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotionBlur {
+    pub enabled: bool,
+    /// Part of the motion over a frame that is blurred, like the time the shutter is open
+    pub strength: f32,
+    pub quality: EffectQuality,
+}
+
+impl Default for MotionBlur {
+    fn default() -> Self {
+        Self { enabled: false, strength: 0.5, quality: EffectQuality::default() }
+    }
+}
+
+/// Effects applied to the lit scene before it is shown
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostEffects {
+    pub depth_of_field: DepthOfField,
+    pub motion_blur: MotionBlur,
+}
+
+/// How shadows of the sun are sampled from the shadow map
+///
+/// Settings are [`clamped`](Self::clamped) when they are loaded, as a negative kernel shadows
+/// everything and a huge one can hang the GPU.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "UncheckedShadowSettings")]
+pub struct ShadowSettings {
+    /// Width of the square of samples blended at shadow edges, one of [`PCF_KERNEL_SIZES`]
+    pub pcf_kernel_size: i32,
+    /// How far surfaces at a grazing angle to the sun are pushed toward it in the shadow map, as
+    /// a fraction of its depth range, against shadow acne
+    pub depth_bias: f32,
+    /// How far surfaces are moved along their normal before the shadow map is looked up, in
+    /// units, against shadow acne without detaching shadows from what casts them
+    pub normal_offset: f32,
+}
+
+impl ShadowSettings {
+    pub const MAX_DEPTH_BIAS: f32 = 0.1;
+    /// In units
+    pub const MAX_NORMAL_OFFSET: f32 = 1.0;
+
+    /// The settings with the kernel size that is nearest in [`PCF_KERNEL_SIZES`], and the bias
+    /// and offset within 0 and their maximum
+    pub fn clamped(self) -> Self {
+        let clamp = |value: f32, max: f32| if value.is_nan() { 0.0 } else { value.clamp(0.0, max) };
+        let distance = |size: i32| size.abs_diff(self.pcf_kernel_size);
+        Self {
+            pcf_kernel_size: PCF_KERNEL_SIZES.into_iter().min_by_key(|&s| distance(s)).unwrap(),
+            depth_bias: clamp(self.depth_bias, Self::MAX_DEPTH_BIAS),
+            normal_offset: clamp(self.normal_offset, Self::MAX_NORMAL_OFFSET),
+        }
+    }
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self { pcf_kernel_size: 3, depth_bias: 0.01, normal_offset: 0.02 }
+    }
+}
+
+/// [`ShadowSettings`] as they are written in a file, before they are clamped
+#[derive(Deserialize)]
+#[serde(default)]
+struct UncheckedShadowSettings {
+    pcf_kernel_size: i32,
+    depth_bias: f32,
+    normal_offset: f32,
+}
+
+impl Default for UncheckedShadowSettings {
+    fn default() -> Self {
+        let ShadowSettings { pcf_kernel_size, depth_bias, normal_offset } = Default::default();
+        Self { pcf_kernel_size, depth_bias, normal_offset }
+    }
+}
+
+impl From<UncheckedShadowSettings> for ShadowSettings {
+    fn from(unchecked: UncheckedShadowSettings) -> Self {
+        let UncheckedShadowSettings { pcf_kernel_size, depth_bias, normal_offset } = unchecked;
+        ShadowSettings { pcf_kernel_size, depth_bias, normal_offset }.clamped()
+    }
+}
+
+/// How a scene is rendered, saved with it so it looks the same when it is opened again
+///
+/// The sun, exposure, fog, sky and background color are saved with the scene as its
+/// [`Lighting`]. `systems::apply_render_settings` passes changes on to the [`RenderState`].
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    /// Width and height of the shadow map, one of [`SHADOW_MAP_SIZES`]
+    pub shadow_map_size: i32,
+    pub shadows: ShadowSettings,
+    /// Reflect the scene in shiny surfaces, by tracing rays through the G-buffer
+    pub reflections: bool,
+    pub post_effects: PostEffects,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            shadow_map_size: RenderState::SHADOW_MAP_SIZE,
+            shadows: ShadowSettings::default(),
+            reflections: false,
+            post_effects: PostEffects::default(),
+        }
+    }
+}
+
+/// Lighting used in look-dev mode, each against a neutral gray background but the HDRI
+#[cfg(feature = "editor")]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LookDevEnvironment {
+    #[default]
+    Studio,
+    Overcast,
+    Sunset,
+    /// The HDRI loaded in [`LookDev::hdri`], shown around the objects and lighting them along with
+    /// the studio sun
+    Hdri,
+}
+
+#[cfg(feature = "editor")]
+impl LookDevEnvironment {
+    pub const ALL: [Self; 4] = [Self::Studio, Self::Overcast, Self::Sunset, Self::Hdri];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Studio => "Studio",
+            Self::Overcast => "Overcast",
+            Self::Sunset => "Sunset",
+            Self::Hdri => "HDRI",
+        }
+    }
+
+    pub fn lighting(self) -> Lighting {
+        let sun = match self {
+            // The environment map takes the place of the ambient light
+            Self::Hdri => DirectionalLight {
+                ambient: glm::Vec3::zeros(),
+                ..Self::Studio.lighting().sun
+            },
+            Self::Studio => DirectionalLight {
+                direction: glm::vec3(-0.4, -0.8, -0.4),
+                ambient: glm::vec3(0.25, 0.25, 0.25),
+                diffuse: glm::vec3(0.8, 0.8, 0.8),
+                specular: glm::vec3(0.6, 0.6, 0.6),
+            },
+            Self::Overcast => DirectionalLight {
+                direction: glm::vec3(0.0, -1.0, -0.1),
+                ambient: glm::vec3(0.45, 0.45, 0.47),
+                diffuse: glm::vec3(0.4, 0.4, 0.42),
+                specular: glm::vec3(0.1, 0.1, 0.1),
+            },
+            Self::Sunset => DirectionalLight {
+                direction: glm::vec3(-1.0, -0.25, 0.3),
+                ambient: glm::vec3(0.18, 0.14, 0.2),
+                diffuse: glm::vec3(1.0, 0.6, 0.35),
+                specular: glm::vec3(0.8, 0.5, 0.3),
+            },
+        };
+        Lighting { sun, exposure: 1.0, background: glm::vec3(0.5, 0.5, 0.5), ..Default::default() }
+    }
+}
+
+/// Look-dev mode, which turns the camera around the selected objects to review them
+#[cfg(feature = "editor")]
+#[derive(Resource)]
+pub struct LookDev {
+    /// Whether look-dev mode should be on; `systems::look_dev` starts and stops it to match
+    pub enabled: bool,
+    pub environment: LookDevEnvironment,
+    /// Degrees per second the camera turns around the objects
+    pub speed: f32,
+    /// Number of images written for a full turn
+    pub frames: u32,
+    /// Directory the image sequence is written to
+    pub output_dir: String,
+    /// Index of the next image to write while rendering a turn
+    pub recording: Option<u32>,
+    pub session: Option<LookDevSession>,
+    /// OpenEXR file of the HDRI environment
+    pub hdri_path: String,
+    /// The HDRI environment as an environment map, once it is loaded from `hdri_path`
+    pub hdri: Option<Texture>,
+}
+
+#[cfg(feature = "editor")]
+impl Default for LookDev {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            environment: LookDevEnvironment::default(),
+            speed: 30.0,
+            frames: 72,
+            output_dir: String::from("turntable"),
+            recording: None,
+            session: None,
+            hdri_path: String::new(),
+            hdri: None,
+        }
+    }
+}
+
+/// A running look-dev session, with the editor state to restore when it ends
+#[cfg(feature = "editor")]
+pub struct LookDevSession {
+    pub bounds: Aabb,
+    /// Degrees the camera has turned since the session started
+    pub angle: f64,
+    pub entities: Vec<Entity>,
+    pub pose: CameraPose,
+    pub lighting: Lighting,
+    pub debug_draw: DebugDraw,
+    pub quad: bool,
+}
+
+/// Values passed to every custom shader as uniforms
+#[derive(Resource, Debug, Default, Copy, Clone)]
+pub struct ShaderGlobals {
+    /// Seconds since startup, as `uniform float time`
+    pub time: f32,
+    /// Seconds since the previous frame, as `uniform float delta_time`
+    pub delta_time: f32,
+    /// Viewport size in pixels, as `uniform vec2 resolution`
+    pub resolution: glm::Vec2,
+    /// Parameters set per scene, as `uniform vec4 user_params`
+    pub params: glm::Vec4,
+}
+
+/// A key was pressed or released while the editor UI did not take it
+///
+/// Sent by the window for each key event, including the repeats sent while a key is held.
+#[cfg(feature = "editor")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: VirtualKeyCode,
+    pub state: ElementState,
+}
+
+/// A mouse button was pressed or released while the editor UI did not take it
+#[cfg(feature = "editor")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub button: MouseButton,
+    pub state: ElementState,
+}
+
+/// Keys and mouse buttons as of this frame, from the `KeyEvent`s and `MouseEvent`s sent since the
+/// previous one
+#[cfg(feature = "editor")]
+#[derive(Resource, Default, Clone)]
 pub struct Input {
-    keys: AHashMap<VirtualKeyCode, HeldState>,
+    keys: Buttons<VirtualKeyCode>,
+    /// Mouse movement since the previous frame, while looking around
     pub mouse_delta: (f64, f64),
+    /// Cursor position in pixels from the top left of the scene view
     pub mouse_pos: (f64, f64),
-    mouse_buttons: AHashMap<MouseButton, HeldState>,
+    mouse_buttons: Buttons<MouseButton>,
+    /// Actions run from the command palette, which count as pressed during the next frame
+    queued_actions: AHashSet<Action>,
+    triggered_actions: AHashSet<Action>,
 }
 
-enum HeldState {
-    Pressed,
-    Held,
+/// Which buttons are held, and which were pressed, repeated or released since the previous frame
+///
+/// A button that is pressed and released within one frame still counts as pressed, and the
+/// repeated presses sent while a key is held are not counted as new presses.
+#[cfg(feature = "editor")]
+#[derive(Clone)]
+struct Buttons<T> {
+    held: AHashSet<T>,
+    pressed: AHashSet<T>,
+    repeated: AHashSet<T>,
+    released: AHashSet<T>,
 }
 
-impl Input {
-    pub fn handle_keyboard_input(&mut self, keycode: VirtualKeyCode, state: ElementState) {
-        match state {
-            ElementState::Pressed => {
-                self.keys.insert(keycode, HeldState::Pressed);
-            }
-            ElementState::Released => {
-                self.keys.remove(&keycode);
-            }
+#[cfg(feature = "editor")]
+impl<T> Default for Buttons<T> {
+    fn default() -> Self {
+        Self {
+            held: AHashSet::new(),
+            pressed: AHashSet::new(),
+            repeated: AHashSet::new(),
+            released: AHashSet::new(),
         }
     }
+}
 
-    pub fn handle_mouse_button_input(&mut self, button: MouseButton, state: ElementState) {
+#[cfg(feature = "editor")]
+impl<T: Copy + Eq + Hash> Buttons<T> {
+    fn handle(&mut self, button: T, state: ElementState) {
         match state {
+            ElementState::Pressed if self.held.insert(button) => {
+                self.pressed.insert(button);
+            }
             ElementState::Pressed => {
-                self.mouse_buttons.insert(button, HeldState::Pressed);
+                self.repeated.insert(button);
             }
             ElementState::Released => {
-                self.mouse_buttons.remove(&button);
+                self.held.remove(&button);
+                self.released.insert(button);
             }
         }
     }
 
+    fn next_frame(&mut self) {
+        self.pressed.clear();
+        self.repeated.clear();
+        self.released.clear();
+    }
+}
+
+#[cfg(feature = "editor")]
+impl Input {
+    pub fn handle_key_event(&mut self, event: KeyEvent) {
+        self.keys.handle(event.key, event.state);
+    }
+
+    pub fn handle_mouse_event(&mut self, event: MouseEvent) {
+        self.mouse_buttons.handle(event.button, event.state);
+    }
+
+    /// Run an action as if its binding was pressed, during the next frame
+    pub fn trigger(&mut self, action: Action) {
+        self.queued_actions.insert(action);
+    }
+
+    /// Whether the action was run from the command palette and counts as pressed this frame
+    pub fn triggered(&self, action: Action) -> bool {
+        self.triggered_actions.contains(&action)
+    }
+
     /// Update input state after the frame
     pub fn update_after_frame(&mut self) {
-        // Keys already existing in map are now marked as held
-        for val in self.keys.values_mut() {
-            *val = HeldState::Held;
-        }
-
-        // Same as above
-        for val in self.mouse_buttons.values_mut() {
-            *val = HeldState::Held;
-        }
+        self.keys.next_frame();
+        self.mouse_buttons.next_frame();
+        self.triggered_actions = std::mem::take(&mut self.queued_actions);
 
         // Reset mouse delta to allow camera to be held still
         self.mouse_delta = (0.0, 0.0);
     }
 
+    /// Whether the key went down this frame
     pub fn get_key_press(&self, keycode: VirtualKeyCode) -> bool {
-        matches!(self.keys.get(&keycode), Some(HeldState::Pressed))
+        self.keys.pressed.contains(&keycode)
+    }
+
+    /// Whether the key went down or was repeated this frame
+    pub fn get_key_repeat(&self, keycode: VirtualKeyCode) -> bool {
+        self.get_key_press(keycode) || self.keys.repeated.contains(&keycode)
+    }
+
+    /// Whether the key went up this frame
+    pub fn get_key_release(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys.released.contains(&keycode)
     }
 
     pub fn get_key_press_continuous(&self, keycode: VirtualKeyCode) -> bool {
-        self.keys.get(&keycode).is_some()
+        self.keys.held.contains(&keycode)
+    }
+
+    pub fn shift_held(&self) -> bool {
+        self.get_key_press_continuous(VirtualKeyCode::LShift)
+            || self.get_key_press_continuous(VirtualKeyCode::RShift)
+    }
+
+    pub fn ctrl_held(&self) -> bool {
+        self.get_key_press_continuous(VirtualKeyCode::LControl)
+            || self.get_key_press_continuous(VirtualKeyCode::RControl)
     }
 
     pub fn get_mouse_button_press(&self, button: MouseButton) -> bool {
-        matches!(self.mouse_buttons.get(&button), Some(HeldState::Pressed))
+        self.mouse_buttons.pressed.contains(&button)
+    }
+
+    pub fn get_mouse_button_release(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.released.contains(&button)
     }
 
-    #[allow(dead_code)]
     pub fn get_mouse_button_press_continuous(&self, button: MouseButton) -> bool {
-        self.mouse_buttons.get(&button).is_some()
+        self.mouse_buttons.held.contains(&button)
     }
 }
@@ -0,0 +1,997 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::Context;
+use nalgebra_glm as glm;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::components::{
+    AnimationClip, AnimationPlayer, CameraComponent, CustomShader, CustomTexture, FollowPath,
+    Group, Hidden, Locked, Lod, Mesh, Oscillator, Parent, PointLight, Position, Properties,
+    PropertyValue, Rotation, Rotator, Scale, Script, ShaderParams, Skeleton, Tags, TransformBundle,
+};
+use crate::entity_pool::Pooled;
+use crate::material::{self, MaterialHandle, MaterialLoader};
+use crate::registry::ComponentRegistry;
+use crate::renderer::model_matrix;
+#[cfg(feature = "editor")]
+use crate::resources::LookDev;
+use crate::resources::{
+    CameraPose, Lighting, ModelLoader, RenderSettings, ShaderGlobals, TextureLoader, ViewBookmarks,
+};
+use crate::shader::UniformValue;
+use crate::terrain::{Terrain, LAYERS};
+use crate::variables::{BindingTarget, Bindings, SceneVariables, VariableValue};
+use crate::variants::{SceneVariants, Variant};
+use crate::{commands, lod, transform};
+
+/// A scene as stored on disk, referring to models and textures by name
+///
+/// Scenes can also be built in code and then saved or spawned:
+///
+/// ```
+/// use scene_editor::scene::Scene;
+///
+/// let scene = Scene::new()
+///     .spawn_model("cube")
+///     .at(0.0, 1.0, 0.0)
+///     .rotated(0.0, 45.0, 0.0)
+///     .spawn_model("sphere")
+///     .scaled(2.0, 2.0, 2.0)
+///     .diffuse("bricks")
+///     .finish();
+///
+/// assert_eq!(scene.entities.len(), 2);
+/// ```
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+    /// Empty transforms that entities and other groups are placed in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<SceneGroup>,
+    /// Camera views saved to numbered slots
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub views: BTreeMap<u8, CameraPose>,
+    /// Passed to custom shaders as `user_params`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shader_params: glm::Vec4,
+    /// Named values that entity fields can be bound to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, VariableValue>,
+    /// Named variations of the scene, referring to entities by their index in `entities`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variants: BTreeMap<String, Variant>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub terrains: Vec<SceneTerrain>,
+    /// Scenes saved without lighting keep whatever lighting is there when they are loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lighting: Option<Lighting>,
+    #[serde(default, skip_serializing_if = "is_default_render_settings")]
+    pub render_settings: RenderSettings,
+}
+
+fn is_zero(v: &glm::Vec4) -> bool {
+    *v == glm::Vec4::zeros()
+}
+
+/// New names for the `names` that are taken, with the lowest number from 2 up after them that
+/// is neither taken nor one of the `names`
+fn new_names<'a>(
+    names: impl Iterator<Item = &'a String> + Clone,
+    taken: &BTreeSet<String>,
+) -> BTreeMap<String, String> {
+    let mut used: BTreeSet<_> = taken.iter().chain(names.clone()).cloned().collect();
+    names
+        .filter(|name| taken.contains(*name))
+        .map(|name| {
+            let new_name = (2..).map(|n| format!("{name} {n}")).find(|n| !used.contains(n));
+            let new_name = new_name.expect("some number is free");
+            used.insert(new_name.clone());
+            (name.clone(), new_name)
+        })
+        .collect()
+}
+
+fn is_default_render_settings(settings: &RenderSettings) -> bool {
+    *settings == RenderSettings::default()
+}
+
+/// How a scene is fitted into the open one when it is imported into it
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct MergeOptions {
+    /// Moves the entities and terrains of the imported scene
+    pub offset: glm::Vec3,
+    /// Give the variables and variants a new name when the open scene has one with the same name,
+    /// instead of sharing the open scene's variable or adding to its variant
+    pub rename_duplicates: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SceneEntity {
+    /// Model drawn for the entity, or empty for an entity that is not drawn, such as a camera
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub model: String,
+    #[serde(default)]
+    pub position: Position,
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub scale: Scale,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diffuse: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub specular: Option<String>,
+    /// Tangent-space normal map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<String>,
+    /// Cut out the mostly transparent parts of the diffuse texture
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub alpha_test: bool,
+    /// Not drawn, such as a light that is only used in some variants
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+    /// Not selected by clicking it in the scene view, such as the floor
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_shader: Option<ShaderSources>,
+    /// Material the entity takes its textures, custom shader and uniform values from, in place of
+    /// the fields above
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point_light: Option<PointLight>,
+    /// A camera of the scene, apart from the editor camera
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<CameraComponent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotator: Option<Rotator>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oscillator: Option<Oscillator>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_path: Option<FollowPath>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_clip: Option<AnimationClip>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_player: Option<AnimationPlayer>,
+    /// Camera distance each level of detail of the model is drawn from, when not the default
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lod_distances: Vec<f32>,
+    /// Custom properties, such as game-specific tags
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, PropertyValue>,
+    /// Labels for finding the entity in the outliner
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// Rhai source of the script run each frame
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// Fields that take their value from a scene variable, with the name of the variable
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bindings: BTreeMap<BindingTarget, String>,
+    /// Components registered in the `ComponentRegistry`, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, ron::Value>,
+    /// Index in `groups` of the group the entity is in, which its transform is relative to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<usize>,
+}
+
+/// A group as stored on disk, with its transform relative to the group it is in
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SceneGroup {
+    pub name: String,
+    #[serde(default)]
+    pub position: Position,
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub scale: Scale,
+    /// Index in `groups` of the group this group is in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShaderSources {
+    pub vert: String,
+    pub frag: String,
+    /// Values of the uniforms edited in the inspector
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, UniformValue>,
+}
+
+/// A terrain as stored on disk, with its heights and layer weights
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SceneTerrain {
+    #[serde(default)]
+    pub position: Position,
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub scale: Scale,
+    /// Quads along each side
+    pub quads: u32,
+    /// Length of each side
+    pub size: f32,
+    /// Height of each vertex, in rows along X from -Z to +Z
+    pub heights: Vec<f32>,
+    /// Weight of each layer at each vertex, where none means covered in the first layer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub splat: Vec<[f32; LAYERS]>,
+    /// Texture name of each layer
+    #[serde(default)]
+    pub layers: [Option<String>; LAYERS],
+    /// Not drawn, such as a terrain that is only used in some variants
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+/// Builder for one entity of a scene, created with `Scene::spawn_model`
+#[must_use]
+pub struct EntityBuilder {
+    scene: Scene,
+    entity: SceneEntity,
+}
+
+impl EntityBuilder {
+    pub fn at(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.entity.position = Position::new(x, y, z);
+        self
+    }
+
+    /// Rotation in degrees
+    pub fn rotated(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.entity.rotation = Rotation::new(x, y, z);
+        self
+    }
+
+    pub fn scaled(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.entity.scale = Scale::new(x, y, z);
+        self
+    }
+
+    pub fn diffuse(mut self, texture: impl Into<String>) -> Self {
+        self.entity.diffuse = Some(texture.into());
+        self
+    }
+
+    pub fn specular(mut self, texture: impl Into<String>) -> Self {
+        self.entity.specular = Some(texture.into());
+        self
+    }
+
+    pub fn normal_map(mut self, texture: impl Into<String>) -> Self {
+        self.entity.normal = Some(texture.into());
+        self
+    }
+
+    /// Cut out the mostly transparent parts of the diffuse texture
+    pub fn alpha_test(mut self) -> Self {
+        self.entity.alpha_test = true;
+        self
+    }
+
+    pub fn locked(mut self) -> Self {
+        self.entity.locked = true;
+        self
+    }
+
+    pub fn custom_shader(mut self, vert: impl Into<String>, frag: impl Into<String>) -> Self {
+        self.entity.custom_shader =
+            Some(ShaderSources { vert: vert.into(), frag: frag.into(), params: BTreeMap::new() });
+        self
+    }
+
+    /// Use a material by name, instead of textures and a custom shader of the entity's own
+    pub fn material(mut self, name: impl Into<String>) -> Self {
+        self.entity.material = Some(name.into());
+        self
+    }
+
+    pub fn point_light(mut self, point_light: PointLight) -> Self {
+        self.entity.point_light = Some(point_light);
+        self
+    }
+
+    pub fn camera(mut self, camera: CameraComponent) -> Self {
+        self.entity.camera = Some(camera);
+        self
+    }
+
+    pub fn rotator(mut self, rotator: Rotator) -> Self {
+        self.entity.rotator = Some(rotator);
+        self
+    }
+
+    pub fn oscillator(mut self, oscillator: Oscillator) -> Self {
+        self.entity.oscillator = Some(oscillator);
+        self
+    }
+
+    pub fn follow_path(mut self, follow_path: FollowPath) -> Self {
+        self.entity.follow_path = Some(follow_path);
+        self
+    }
+
+    pub fn animation_clip(mut self, animation_clip: AnimationClip) -> Self {
+        self.entity.animation_clip = Some(animation_clip);
+        self
+    }
+
+    pub fn animation_player(mut self, animation_player: AnimationPlayer) -> Self {
+        self.entity.animation_player = Some(animation_player);
+        self
+    }
+
+    /// Add a custom property, replacing any property with the same key
+    pub fn property(mut self, key: impl Into<String>, value: impl Into<PropertyValue>) -> Self {
+        self.entity.properties.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.entity.tags.insert(tag.into());
+        self
+    }
+
+    pub fn script(mut self, source: impl Into<String>) -> Self {
+        self.entity.script = Some(source.into());
+        self
+    }
+
+    /// Bind a field to a scene variable, replacing any binding of the same field
+    pub fn bind(mut self, target: BindingTarget, variable: impl Into<String>) -> Self {
+        self.entity.bindings.insert(target, variable.into());
+        self
+    }
+
+    /// Put the entity in a group by its index in `groups`, placing it relative to the group
+    pub fn in_group(mut self, group: usize) -> Self {
+        self.entity.group = Some(group);
+        self
+    }
+
+    /// Finish this entity and start building the next one
+    pub fn spawn_model(self, model: impl Into<String>) -> EntityBuilder {
+        self.finish().spawn_model(model)
+    }
+
+    /// Finish this entity and return the scene
+    pub fn finish(self) -> Scene {
+        let mut scene = self.scene;
+        scene.entities.push(self.entity);
+        scene
+    }
+}
+
+impl From<EntityBuilder> for Scene {
+    fn from(builder: EntityBuilder) -> Self {
+        builder.finish()
+    }
+}
+
+impl SceneEntity {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self { model: model.into(), ..Default::default() }
+    }
+}
+
+type SceneQuery<'a> = (
+    Entity,
+    Option<&'a Mesh>,
+    &'a Position,
+    &'a Rotation,
+    &'a Scale,
+    Option<&'a CustomTexture>,
+    Option<&'a CustomShader>,
+    Option<&'a ShaderParams>,
+    Option<&'a PointLight>,
+    Option<&'a Rotator>,
+    Option<&'a Oscillator>,
+    Option<&'a FollowPath>,
+    Option<&'a AnimationClip>,
+    Option<&'a AnimationPlayer>,
+    (
+        Option<&'a Properties>,
+        Option<&'a Tags>,
+        Option<&'a Script>,
+        Option<&'a Bindings>,
+        Option<&'a Hidden>,
+        Option<&'a Locked>,
+        Option<&'a MaterialHandle>,
+        Option<&'a Lod>,
+        Option<&'a CameraComponent>,
+    ),
+);
+
+type TerrainQuery<'a> =
+    (Entity, &'a Terrain, &'a Position, &'a Rotation, &'a Scale, Option<&'a Hidden>);
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scene variable, replacing any variable with the same name
+    pub fn variable(mut self, name: impl Into<String>, value: VariableValue) -> Self {
+        self.variables.insert(name.into(), value);
+        self
+    }
+
+    /// Add a group, which entities are put in by the index it gets in `groups`
+    pub fn group(mut self, group: SceneGroup) -> Self {
+        self.groups.push(group);
+        self
+    }
+
+    /// Add a variant, replacing any variant with the same name
+    pub fn variant(mut self, name: impl Into<String>, variant: Variant) -> Self {
+        self.variants.insert(name.into(), variant);
+        self
+    }
+
+    /// Start building a new entity using the given model
+    pub fn spawn_model(self, model: impl Into<String>) -> EntityBuilder {
+        EntityBuilder { scene: self, entity: SceneEntity::new(model) }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| eyre!("could not read scene {}: {e}", path.as_ref().display()))?;
+        Self::from_ron(&contents)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path.as_ref(), self.to_ron()?)
+            .map_err(|e| eyre!("could not write scene {}: {e}", path.as_ref().display()))
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self> {
+        ron::from_str(s).map_err(|e| eyre!("could not parse scene: {e}"))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| eyre!("could not serialize scene: {e}"))
+    }
+
+    /// Model matrix of an entity in the world, with the transforms of the groups it is in applied
+    pub fn world_matrix(&self, entity: &SceneEntity) -> glm::Mat4 {
+        let model = model_matrix(entity.position, entity.rotation, entity.scale);
+        // Following the parents of as many groups as there are ends a cycle of parents
+        std::iter::successors(entity.group, |&i| self.groups.get(i)?.parent)
+            .take(self.groups.len())
+            .filter_map(|i| self.groups.get(i))
+            .fold(model, |model, g| model_matrix(g.position, g.rotation, g.scale) * model)
+    }
+
+    /// Prepare the scene to be spawned into an open scene with the given variables and variants,
+    /// moving it by the offset and renaming what clashes if the options ask for it
+    ///
+    /// Renamed variables get a number after their name, and the bindings and variants that refer
+    /// to them are renamed along with them.
+    pub fn prepare_merge(
+        &mut self,
+        variables: &BTreeSet<String>,
+        variants: &BTreeSet<String>,
+        options: &MergeOptions,
+    ) {
+        let offset = options.offset;
+        let moved = |position: Position| {
+            let p = glm::Vec3::from(position) + offset;
+            Position::new(p.x, p.y, p.z)
+        };
+        // Entities and groups in a group move with it
+        for group in self.groups.iter_mut().filter(|g| g.parent.is_none()) {
+            group.position = moved(group.position);
+        }
+        for entity in self.entities.iter_mut().filter(|e| e.group.is_none()) {
+            entity.position = moved(entity.position);
+            if let Some(follow_path) = &mut entity.follow_path {
+                for point in &mut follow_path.points {
+                    *point += offset;
+                }
+            }
+        }
+        for terrain in &mut self.terrains {
+            terrain.position = moved(terrain.position);
+        }
+        if !options.rename_duplicates {
+            return;
+        }
+
+        let renamed = new_names(self.variables.keys(), variables);
+        let rename = |name: String| renamed.get(&name).cloned().unwrap_or(name);
+        self.variables =
+            std::mem::take(&mut self.variables).into_iter().map(|(n, v)| (rename(n), v)).collect();
+        for entity in &mut self.entities {
+            for name in entity.bindings.values_mut() {
+                *name = rename(std::mem::take(name));
+            }
+        }
+        for variant in self.variants.values_mut() {
+            variant.variables = std::mem::take(&mut variant.variables)
+                .into_iter()
+                .map(|(n, v)| (rename(n), v))
+                .collect();
+        }
+
+        let renamed = new_names(self.variants.keys(), variants);
+        self.variants = std::mem::take(&mut self.variants)
+            .into_iter()
+            .map(|(n, v)| (renamed.get(&n).cloned().unwrap_or(n), v))
+            .collect();
+    }
+
+    /// Capture every mesh entity, camera and terrain in the world, with the saved camera views,
+    /// shader parameters, variables, variants, lighting and render settings
+    ///
+    /// Entities and lighting are captured as they are shown, so the base scene should be shown
+    /// first with `variants::with_base`. During look-dev the lighting it replaced is saved instead.
+    ///
+    /// Requires the `ModelLoader` and `TextureLoader` resources to look up asset names.
+    pub fn from_world(world: &mut World) -> Self {
+        let mut query = world.query_filtered::<
+            SceneQuery,
+            (Or<(With<Mesh>, With<CameraComponent>)>, Without<Pooled>),
+        >();
+        let model_loader = world.resource::<ModelLoader>();
+        let texture_loader = world.resource::<TextureLoader>();
+        let material_loader = world.get_resource::<MaterialLoader>();
+
+        let mut entities: Vec<_> = query
+            .iter(world)
+            .filter_map(
+                |(
+                    entity,
+                    mesh,
+                    &position,
+                    &rotation,
+                    &scale,
+                    texture,
+                    custom_shader,
+                    shader_params,
+                    point_light,
+                    rotator,
+                    oscillator,
+                    follow_path,
+                    animation_clip,
+                    animation_player,
+                    (properties, tags, script, bindings, hidden, locked, material, lod, camera),
+                )| {
+                    // Cameras of their own are the only entities saved without a model
+                    let model = match mesh {
+                        Some(mesh) => match model_loader.name_of(mesh.vao_id) {
+                            Some(model) => model,
+                            None => {
+                                warn!("entity {} has an unknown model, skipping", entity.index());
+                                return None;
+                            }
+                        },
+                        None => "",
+                    };
+                    // Entities with a material only refer to it, so it can be changed for every
+                    // scene at once
+                    let material = material.and_then(|handle| {
+                        material_loader.as_ref()?.name_of(handle.0).map(str::to_owned)
+                    });
+                    let (texture, custom_shader) = match material {
+                        Some(_) => (CustomTexture::default(), None),
+                        None => (texture.copied().unwrap_or_default(), custom_shader),
+                    };
+
+                    let scene_entity = SceneEntity {
+                        model: model.to_owned(),
+                        position,
+                        rotation,
+                        scale,
+                        diffuse: texture
+                            .diffuse
+                            .and_then(|t| texture_loader.name_of(t))
+                            .map(str::to_owned),
+                        specular: texture
+                            .specular
+                            .and_then(|t| texture_loader.name_of(t))
+                            .map(str::to_owned),
+                        normal: texture
+                            .normal
+                            .and_then(|t| texture_loader.name_of(t))
+                            .map(str::to_owned),
+                        alpha_test: texture.alpha_test,
+                        hidden: hidden.is_some(),
+                        locked: locked.is_some(),
+                        custom_shader: custom_shader.map(|cs| ShaderSources {
+                            vert: cs.vert_source.clone(),
+                            frag: cs.frag_source.clone(),
+                            params: shader_params.map(|p| p.values.clone()).unwrap_or_default(),
+                        }),
+                        material,
+                        point_light: point_light.copied(),
+                        camera: camera.copied(),
+                        rotator: rotator.copied(),
+                        oscillator: oscillator.copied(),
+                        follow_path: follow_path.cloned(),
+                        animation_clip: animation_clip.cloned(),
+                        animation_player: animation_player.copied(),
+                        lod_distances: lod
+                            .map(|lod| lod.distances.clone())
+                            .filter(|distances| *distances != lod::DEFAULT_DISTANCES)
+                            .unwrap_or_default(),
+                        properties: properties.map(|p| p.values.clone()).unwrap_or_default(),
+                        tags: tags.map(|t| t.values.clone()).unwrap_or_default(),
+                        script: script.map(|s| s.source.clone()),
+                        bindings: bindings.map(|b| b.targets.clone()).unwrap_or_default(),
+                        components: BTreeMap::new(),
+                        group: None,
+                    };
+                    Some((entity, scene_entity))
+                },
+            )
+            .collect();
+
+        // Keep the file stable between saves
+        entities.sort_by_key(|(entity, _)| entity.index());
+
+        if let Some(registry) = world.get_resource::<ComponentRegistry>() {
+            for (entity, scene_entity) in &mut entities {
+                for registration in registry.iter() {
+                    match registration.serialize(world, *entity) {
+                        Some(Ok(value)) => {
+                            scene_entity.components.insert(registration.name().to_owned(), value);
+                        }
+                        Some(Err(e)) => {
+                            warn!("could not serialize component {:?}: {e}", registration.name());
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        let mut group_query = world
+            .query_filtered::<(Entity, &Group, &Position, &Rotation, &Scale), Without<Pooled>>();
+        let mut groups: Vec<_> = group_query
+            .iter(world)
+            .map(|(entity, group, &position, &rotation, &scale)| {
+                let name = group.name.clone();
+                (entity, SceneGroup { name, position, rotation, scale, parent: None })
+            })
+            .collect();
+        groups.sort_by_key(|(entity, _)| entity.index());
+        // Only parents that are groups are saved
+        let group_index: BTreeMap<_, _> =
+            groups.iter().enumerate().map(|(i, (entity, _))| (*entity, i)).collect();
+        let group_of = |entity: Entity| {
+            let parent = world.get::<Parent>(entity)?;
+            group_index.get(&parent.0).copied()
+        };
+        for (entity, group) in &mut groups {
+            group.parent = group_of(*entity);
+        }
+        for (entity, scene_entity) in &mut entities {
+            scene_entity.group = group_of(*entity);
+        }
+
+        let mut terrain_query = world.query_filtered::<TerrainQuery, Without<Pooled>>();
+        let texture_loader = world.resource::<TextureLoader>();
+        let mut terrains: Vec<_> = terrain_query
+            .iter(world)
+            .map(|(entity, terrain, &position, &rotation, &scale, hidden)| {
+                let layers = terrain
+                    .layers
+                    .map(|layer| layer.and_then(|t| texture_loader.name_of(t)).map(str::to_owned));
+                let scene_terrain = SceneTerrain {
+                    position,
+                    rotation,
+                    scale,
+                    quads: terrain.quads,
+                    size: terrain.size,
+                    heights: terrain.heights.clone(),
+                    splat: terrain.splat.clone(),
+                    layers,
+                    hidden: hidden.is_some(),
+                };
+                (entity, scene_terrain)
+            })
+            .collect();
+        terrains.sort_by_key(|(entity, _)| entity.index());
+
+        let views =
+            world.get_resource::<ViewBookmarks>().map(|b| b.views.clone()).unwrap_or_default();
+
+        let shader_params =
+            world.get_resource::<ShaderGlobals>().map(|g| g.params).unwrap_or_default();
+
+        let variables =
+            world.get_resource::<SceneVariables>().map(|v| v.values.clone()).unwrap_or_default();
+
+        let variants = world
+            .get_resource::<SceneVariants>()
+            .map(|v| {
+                let index: BTreeMap<_, _> =
+                    entities.iter().enumerate().map(|(i, (entity, _))| (*entity, i)).collect();
+                v.to_scene(|entity| index.get(&entity).copied())
+            })
+            .unwrap_or_default();
+
+        let lighting = world.get_resource::<Lighting>().copied();
+        // Look-dev replaces the lighting with its own until it ends, keeping that of the scene
+        #[cfg(feature = "editor")]
+        let lighting = world
+            .get_resource::<LookDev>()
+            .and_then(|look_dev| look_dev.session.as_ref())
+            .map(|session| session.lighting)
+            .or(lighting);
+        let render_settings = world.get_resource::<RenderSettings>().copied().unwrap_or_default();
+
+        Self {
+            entities: entities.into_iter().map(|(_, e)| e).collect(),
+            groups: groups.into_iter().map(|(_, g)| g).collect(),
+            views,
+            shader_params,
+            variables,
+            variants,
+            terrains: terrains.into_iter().map(|(_, t)| t).collect(),
+            lighting,
+            render_settings,
+        }
+    }
+
+    /// Spawn the entities, groups and terrains of the scene into the world, returning the spawned
+    /// entities without the groups and terrains, in the order of `entities`
+    ///
+    /// Models and textures that are not loaded are shown as a cube and a magenta checker, and
+    /// materials as no material. They are added under the missing names so the scene is saved as it
+    /// was, and listed in the errors of the loaders.
+    pub fn spawn(&self, world: &mut World) -> Result<Vec<Entity>> {
+        let gl = world.non_send_resource::<Arc<Context>>().clone();
+        let mut model_loader = world.resource_mut::<ModelLoader>();
+        let meshes = self
+            .entities
+            .iter()
+            .map(|e| {
+                if e.model.is_empty() {
+                    return Ok(None);
+                }
+                let mesh = Mesh::from(model_loader.get_or_placeholder(&gl, &e.model)?);
+                Ok(Some((mesh, model_loader.skin(&e.model).cloned())))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let terrains = self
+            .terrains
+            .iter()
+            .map(|t| Terrain::from_parts(t.quads, t.size, t.heights.clone(), t.splat.clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let groups: Vec<_> = self
+            .groups
+            .iter()
+            .map(|group| {
+                let transform = TransformBundle {
+                    position: group.position,
+                    rotation: group.rotation,
+                    scale: group.scale,
+                    ..Default::default()
+                };
+                world.spawn((Group { name: group.name.clone() }, transform)).id()
+            })
+            .collect();
+        for (group, &entity) in self.groups.iter().zip(&groups) {
+            if let Some(&parent) = group.parent.and_then(|index| groups.get(index)) {
+                world.entity_mut(entity).insert(Parent(parent));
+            }
+        }
+
+        let registry = world.get_resource::<ComponentRegistry>().cloned().unwrap_or_default();
+        let mut spawned = Vec::with_capacity(self.entities.len());
+        for (scene_entity, model) in self.entities.iter().zip(meshes) {
+            let texture = {
+                let mut texture_loader = world.resource_mut::<TextureLoader>();
+                let mut lookup =
+                    |name: &Option<String>| texture_loader.get_or_placeholder(&gl, name.as_ref()?);
+                CustomTexture {
+                    diffuse: lookup(&scene_entity.diffuse),
+                    specular: lookup(&scene_entity.specular),
+                    normal: lookup(&scene_entity.normal),
+                    alpha_test: scene_entity.alpha_test,
+                }
+            };
+
+            let mut entity = world.spawn(TransformBundle {
+                position: scene_entity.position,
+                rotation: scene_entity.rotation,
+                scale: scene_entity.scale,
+                ..Default::default()
+            });
+            let skin = model.and_then(|(mesh, skin)| {
+                entity.insert(mesh);
+                skin
+            });
+            let textured =
+                texture.diffuse.is_some() || texture.specular.is_some() || texture.normal.is_some();
+            if textured || texture.alpha_test {
+                entity.insert(texture);
+            }
+            if scene_entity.hidden {
+                entity.insert(Hidden);
+            }
+            if scene_entity.locked {
+                entity.insert(Locked);
+            }
+            if let Some(&group) = scene_entity.group.and_then(|index| groups.get(index)) {
+                entity.insert(Parent(group));
+            }
+            if let Some(point_light) = scene_entity.point_light {
+                entity.insert(point_light);
+            }
+            if let Some(camera) = scene_entity.camera {
+                entity.insert(camera);
+            }
+            if let Some(rotator) = scene_entity.rotator {
+                entity.insert(rotator);
+            }
+            if let Some(oscillator) = scene_entity.oscillator {
+                entity.insert(oscillator);
+            }
+            if let Some(follow_path) = &scene_entity.follow_path {
+                entity.insert(follow_path.clone());
+            }
+            if let Some(animation_clip) = &scene_entity.animation_clip {
+                entity.insert(animation_clip.clone());
+            }
+            if !scene_entity.lod_distances.is_empty() {
+                // The meshes are filled in by `lod::update_lods`
+                let distances = scene_entity.lod_distances.clone();
+                entity.insert(Lod { distances, ..Default::default() });
+            }
+            if !scene_entity.properties.is_empty() {
+                entity.insert(Properties { values: scene_entity.properties.clone() });
+            }
+            if !scene_entity.tags.is_empty() {
+                entity.insert(Tags { values: scene_entity.tags.clone() });
+            }
+            if let Some(source) = &scene_entity.script {
+                entity.insert(Script::new(source.clone()));
+            }
+            if !scene_entity.bindings.is_empty() {
+                entity.insert(Bindings { targets: scene_entity.bindings.clone() });
+            }
+            if let Some(skin) = skin {
+                entity.insert((
+                    Skeleton::new(skin),
+                    scene_entity.animation_player.unwrap_or_default(),
+                ));
+            }
+            let entity = entity.id();
+
+            if let Some(sources) = &scene_entity.custom_shader {
+                let mut cs = CustomShader::new(&gl);
+                cs.vert_source = sources.vert.clone();
+                cs.frag_source = sources.frag.clone();
+                let params = ShaderParams { values: sources.params.clone() };
+                world.entity_mut(entity).insert((cs, params));
+                commands::load_custom_shader(entity, world);
+            }
+
+            if let Some(name) = &scene_entity.material {
+                let mut material_loader =
+                    world.get_resource_or_insert_with(MaterialLoader::default);
+                let id = material_loader.get_or_placeholder(name);
+                material::apply(world, entity, id);
+            }
+
+            for (name, value) in &scene_entity.components {
+                let Some(registration) = registry.get(name) else {
+                    warn!("scene refers to unknown component {:?}", name);
+                    continue;
+                };
+                if let Err(e) = registration.insert(world, entity, value.clone()) {
+                    warn!("could not load component {:?}: {e}", name);
+                }
+            }
+
+            spawned.push(entity);
+        }
+
+        for (scene_terrain, mut terrain) in self.terrains.iter().zip(terrains) {
+            let mut texture_loader = world.resource_mut::<TextureLoader>();
+            let mut lookup =
+                |name: &Option<String>| texture_loader.get_or_placeholder(&gl, name.as_ref()?);
+            for (layer, name) in terrain.layers.iter_mut().zip(&scene_terrain.layers) {
+                *layer = lookup(name);
+            }
+            let mut entity = world.spawn((
+                terrain,
+                TransformBundle {
+                    position: scene_terrain.position,
+                    rotation: scene_terrain.rotation,
+                    scale: scene_terrain.scale,
+                    ..Default::default()
+                },
+            ));
+            if scene_terrain.hidden {
+                entity.insert(Hidden);
+            }
+        }
+
+        Ok(spawned)
+    }
+}
+
+/// The lighting of a scene without its other entities, so the same lights can be reused across
+/// scenes
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct LightingRig {
+    #[serde(default)]
+    pub lighting: Lighting,
+    /// Entities with a point light
+    #[serde(default)]
+    pub lights: Vec<SceneEntity>,
+}
+
+impl LightingRig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = fs::read_to_string(path.as_ref())
+            .map_err(|e| eyre!("could not read lighting {}: {e}", path.as_ref().display()))?;
+        Self::from_ron(&contents)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path.as_ref(), self.to_ron()?)
+            .map_err(|e| eyre!("could not write lighting {}: {e}", path.as_ref().display()))
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self> {
+        ron::from_str(s).map_err(|e| eyre!("could not parse lighting: {e}"))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| eyre!("could not serialize lighting: {e}"))
+    }
+
+    /// Capture the `Lighting` resource and every entity with a point light
+    ///
+    /// Requires the same resources as `Scene::from_world`.
+    pub fn from_world(world: &mut World) -> Self {
+        let scene = Scene::from_world(world);
+        // The rig has no groups, so lights in one are saved where the group puts them
+        let lights = scene
+            .entities
+            .iter()
+            .filter(|e| e.point_light.is_some())
+            .map(|e| {
+                let mut light = e.clone();
+                if light.group.take().is_some() {
+                    (light.position, light.rotation, light.scale) =
+                        transform::decompose(&scene.world_matrix(e));
+                }
+                light
+            })
+            .collect();
+        let lighting = world.get_resource::<Lighting>().copied().unwrap_or_default();
+        Self { lighting, lights }
+    }
+
+    /// Replace the lighting of the world with this rig, returning the spawned lights
+    ///
+    /// Every entity with a point light is despawned first.
+    pub fn apply(&self, world: &mut World) -> Result<Vec<Entity>> {
+        let old_lights: Vec<_> =
+            world.query_filtered::<Entity, With<PointLight>>().iter(world).collect();
+        let spawned = Scene { entities: self.lights.clone(), ..Default::default() }.spawn(world)?;
+        for entity in old_lights {
+            commands::despawn_and_destroy(entity, world);
+        }
+        world.insert_resource(self.lighting);
+        Ok(spawned)
+    }
+}
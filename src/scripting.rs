@@ -0,0 +1,335 @@
+//! Behavior written in Rhai and run each frame, for trying out ideas without rebuilding the editor
+//!
+//! A script defines an `update` function, and optionally a `start` function that runs when the
+//! script is loaded or changed. Both are called with `this` set to a map with the entity's
+//! `position`, `rotation` in degrees and `scale`, the `time` and `dt` in seconds, and `input`:
+//!
+//! ```rhai
+//! fn start() {
+//!     this.speed = 90.0;
+//! }
+//!
+//! fn update() {
+//!     if this.input.held("Space") {
+//!         this.rotation.y += this.speed * this.dt;
+//!     }
+//! }
+//! ```
+//!
+//! Other fields set on `this` are kept between frames, and when the script is changed.
+
+use std::collections::hash_map::Entry;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use ahash::AHashMap;
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT, INT};
+
+use crate::components::{Position, Rotation, Scale, Script};
+use crate::entity_pool::Pooled;
+use crate::input_map::Binding;
+use crate::resources::{Input, Time};
+
+/// Source of a script added in the Script panel
+pub const NEW_SCRIPT: &str = "\
+fn start() {
+}
+
+fn update() {
+}
+";
+
+/// Messages kept in the console, the oldest are dropped first
+const LOG_CAPACITY: usize = 500;
+/// Operations a script can take in one call before it is stopped, so a loop that never ends does
+/// not freeze the editor
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Keys and mouse buttons as of this frame, named as in the input config, such as `"W"`,
+/// `"Shift+S"` or `"MouseLeft"`
+#[derive(Clone)]
+struct ScriptInput(Arc<Input>);
+
+impl ScriptInput {
+    fn check(
+        &self,
+        name: &str,
+        f: fn(Binding, &Input) -> bool,
+    ) -> Result<bool, Box<EvalAltResult>> {
+        let binding =
+            Binding::parse(name).ok_or_else(|| format!("unknown key or mouse button {name:?}"))?;
+        Ok(f(binding, &self.0))
+    }
+}
+
+fn engine(printed: Arc<Mutex<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(move |text| printed.lock().unwrap().push(text.to_owned()));
+
+    engine
+        .register_type_with_name::<glm::Vec3>("Vec3")
+        .register_fn("vec3", |x: FLOAT, y: FLOAT, z: FLOAT| {
+            glm::vec3(x as f32, y as f32, z as f32)
+        })
+        .register_get_set(
+            "x",
+            |v: &mut glm::Vec3| v.x as FLOAT,
+            |v: &mut glm::Vec3, x: FLOAT| v.x = x as f32,
+        )
+        .register_get_set(
+            "y",
+            |v: &mut glm::Vec3| v.y as FLOAT,
+            |v: &mut glm::Vec3, y: FLOAT| v.y = y as f32,
+        )
+        .register_get_set(
+            "z",
+            |v: &mut glm::Vec3| v.z as FLOAT,
+            |v: &mut glm::Vec3, z: FLOAT| v.z = z as f32,
+        )
+        .register_fn("+", |a: glm::Vec3, b: glm::Vec3| a + b)
+        .register_fn("-", |a: glm::Vec3, b: glm::Vec3| a - b)
+        .register_fn("-", |v: glm::Vec3| -v)
+        .register_fn("*", |v: glm::Vec3, s: FLOAT| v * s as f32)
+        .register_fn("*", |s: FLOAT, v: glm::Vec3| v * s as f32)
+        .register_fn("length", |v: &mut glm::Vec3| glm::length(v) as FLOAT)
+        .register_fn("normalize", |v: &mut glm::Vec3| glm::normalize(v))
+        .register_fn("to_string", |v: &mut glm::Vec3| format!("({}, {}, {})", v.x, v.y, v.z))
+        .register_fn("to_debug", |v: &mut glm::Vec3| format!("vec3({}, {}, {})", v.x, v.y, v.z));
+
+    engine
+        .register_type_with_name::<ScriptInput>("Input")
+        .register_fn("held", |input: &mut ScriptInput, name: &str| input.check(name, Binding::held))
+        .register_fn("pressed", |input: &mut ScriptInput, name: &str| {
+            input.check(name, Binding::pressed)
+        })
+        .register_fn("released", |input: &mut ScriptInput, name: &str| {
+            input.check(name, Binding::released)
+        });
+
+    engine
+}
+
+/// A script as it was last loaded for an entity
+struct LoadedScript {
+    source: String,
+    /// `None` when the source does not compile
+    ast: Option<AST>,
+    /// Fields the script has set on `this`
+    fields: Map,
+    /// Why the script stopped, until its source is changed
+    error: Option<String>,
+    /// `start` still has to be called
+    starting: bool,
+}
+
+impl LoadedScript {
+    fn load(
+        engine: &Engine,
+        source: &str,
+        fields: Map,
+        entity: Entity,
+        log: &mut ScriptLog,
+    ) -> Self {
+        let (ast, error) = match engine.compile(source) {
+            Ok(ast) => (Some(ast), None),
+            Err(e) => {
+                let error = format!("does not compile: {e}");
+                log.push(entity, error.clone(), true);
+                (None, Some(error))
+            }
+        };
+        Self { source: source.to_owned(), ast, fields, error, starting: true }
+    }
+}
+
+/// Whether the script defines a function with the name and no parameters
+fn defines(ast: &AST, name: &str) -> bool {
+    ast.iter_functions().any(|f| f.name == name && f.params.is_empty())
+}
+
+/// Call a function of the script with `this` bound, if the script defines it
+fn call(engine: &Engine, ast: &AST, this: &mut Dynamic, name: &str) -> Result<(), String> {
+    if !defines(ast, name) {
+        return Ok(());
+    }
+    let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(this);
+    engine
+        .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, name, ())
+        .map(drop)
+        .map_err(|e| e.to_string())
+}
+
+/// Read a vector the script may have changed back from `this`
+fn field_vec3(fields: &Map, name: &str) -> Result<glm::Vec3, String> {
+    fields
+        .get(name)
+        .and_then(|value| value.clone().try_cast::<glm::Vec3>())
+        .ok_or_else(|| format!("this.{name} has to be a Vec3"))
+}
+
+/// Engine running the scripts, and the state of each script
+#[derive(Resource)]
+pub struct ScriptRuntime {
+    engine: Engine,
+    /// Text printed by the script being run
+    printed: Arc<Mutex<Vec<String>>>,
+    scripts: AHashMap<Entity, LoadedScript>,
+}
+
+impl Default for ScriptRuntime {
+    fn default() -> Self {
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        Self { engine: engine(printed.clone()), printed, scripts: AHashMap::new() }
+    }
+}
+
+impl ScriptRuntime {
+    /// Why the script of the entity does not run, if it stopped with an error
+    pub fn error(&self, entity: Entity) -> Option<&str> {
+        self.scripts.get(&entity)?.error.as_deref()
+    }
+
+    /// Load the script if it is new or has changed, and run it for one frame
+    ///
+    /// Returns the transform the script leaves the entity with, or `None` if it did not run.
+    fn run(
+        &mut self,
+        entity: Entity,
+        source: &str,
+        transform: (Position, Rotation, Scale),
+        frame: &Map,
+        log: &mut ScriptLog,
+    ) -> Option<(Position, Rotation, Scale)> {
+        let Self { engine, printed, scripts } = self;
+        let script = match scripts.entry(entity) {
+            Entry::Occupied(entry) if entry.get().source == source => entry.into_mut(),
+            // Keep the fields of the running script, so it carries on where it was
+            Entry::Occupied(entry) => {
+                let script = entry.into_mut();
+                let fields = std::mem::take(&mut script.fields);
+                *script = LoadedScript::load(engine, source, fields, entity, log);
+                script
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(LoadedScript::load(engine, source, Map::new(), entity, log))
+            }
+        };
+        let Some(ast) = script.ast.as_ref().filter(|_| script.error.is_none()) else {
+            return None;
+        };
+
+        let (position, rotation, scale) = transform;
+        let mut fields = std::mem::take(&mut script.fields);
+        fields.extend(frame.clone());
+        fields.insert("position".into(), Dynamic::from(glm::Vec3::from(position)));
+        // In degrees, as shown in the inspector
+        fields.insert("rotation".into(), Dynamic::from(rotation.euler()));
+        fields.insert("scale".into(), Dynamic::from(glm::Vec3::from(scale)));
+        fields.insert("entity".into(), Dynamic::from(entity.index() as INT));
+
+        let mut this = Dynamic::from_map(fields);
+        let start = match std::mem::take(&mut script.starting) {
+            true => call(engine, ast, &mut this, "start"),
+            false => Ok(()),
+        };
+        let result = start
+            .and_then(|()| call(engine, ast, &mut this, "update"))
+            .and_then(|()| this.try_cast::<Map>().ok_or_else(|| "this has to stay a map".into()))
+            .and_then(|fields| {
+                let position = field_vec3(&fields, "position")?;
+                let euler = field_vec3(&fields, "rotation")?;
+                let scale = field_vec3(&fields, "scale")?;
+                script.fields = fields;
+                Ok((
+                    Position::new(position.x, position.y, position.z),
+                    // Angles the script left alone keep the rotation as it was
+                    if euler == rotation.euler() { rotation } else { Rotation::from_euler(euler) },
+                    Scale::new(scale.x, scale.y, scale.z),
+                ))
+            });
+
+        for text in printed.lock().unwrap().drain(..) {
+            log.push(entity, text, false);
+        }
+        result
+            .map_err(|error| {
+                log.push(entity, error.clone(), true);
+                script.error = Some(error);
+            })
+            .ok()
+    }
+}
+
+/// A line printed by a script, or the error that stopped it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptMessage {
+    pub entity: Entity,
+    pub text: String,
+    pub is_error: bool,
+}
+
+/// Output of the scripts shown in the console, oldest first
+#[derive(Resource, Default)]
+pub struct ScriptLog {
+    messages: VecDeque<ScriptMessage>,
+}
+
+impl ScriptLog {
+    pub fn push(&mut self, entity: Entity, text: String, is_error: bool) {
+        if self.messages.len() == LOG_CAPACITY {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(ScriptMessage { entity, text, is_error });
+    }
+
+    pub fn messages(&self) -> impl DoubleEndedIterator<Item = &ScriptMessage> {
+        self.messages.iter()
+    }
+
+    /// The messages as text to copy, each after the entity that printed it
+    pub fn to_text(&self) -> String {
+        let messages = self.messages.iter();
+        messages.map(|m| format!("entity {}: {}\n", m.entity.index(), m.text)).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
+type ScriptQuery<'a> = (Entity, &'a Script, &'a mut Position, &'a mut Rotation, &'a mut Scale);
+
+/// Run the `update` function of each script, reloading the scripts whose source has changed
+pub fn run_scripts(
+    mut runtime: ResMut<ScriptRuntime>,
+    mut log: ResMut<ScriptLog>,
+    time: Res<Time>,
+    input: Res<Input>,
+    mut scripts: Query<ScriptQuery, Without<Pooled>>,
+) {
+    runtime.scripts.retain(|&entity, _| scripts.contains(entity));
+    if scripts.is_empty() {
+        return;
+    }
+
+    let frame = Map::from([
+        ("time".into(), Dynamic::from(time.elapsed_seconds() as FLOAT)),
+        ("dt".into(), Dynamic::from(time.delta_seconds() as FLOAT)),
+        ("input".into(), Dynamic::from(ScriptInput(Arc::new(input.clone())))),
+    ]);
+    for (entity, script, mut position, mut rotation, mut scale) in &mut scripts {
+        let transform = (*position, *rotation, *scale);
+        let Some((new_position, new_rotation, new_scale)) =
+            runtime.run(entity, &script.source, transform, &frame, &mut log)
+        else {
+            continue;
+        };
+        // Only mark what the script changed, so entities it leaves alone are not seen as changed
+        position.set_if_neq(new_position);
+        rotation.set_if_neq(new_rotation);
+        scale.set_if_neq(new_scale);
+    }
+}
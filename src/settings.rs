@@ -0,0 +1,281 @@
+use std::path::Path;
+
+use egui::{Color32, Stroke, Visuals};
+use toml_edit::{Item, Table};
+use tracing::warn;
+
+/// Where panels, snapping, the palette and the update and usage choices were kept before they
+/// moved to the editor configuration file
+pub const LEGACY_SETTINGS_PATH: &str = "settings.toml";
+
+/// Color scheme of the editor UI
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Dark theme with brighter text, stronger outlines and a clearly visible focus ring
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
+    /// Name used in the configuration file
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::HighContrast => "high_contrast",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn visuals(self) -> Visuals {
+        match self {
+            Theme::Dark => Visuals::dark(),
+            Theme::Light => Visuals::light(),
+            Theme::HighContrast => {
+                let mut visuals = Visuals::dark();
+                visuals.override_text_color = Some(Color32::WHITE);
+                visuals.window_fill = Color32::BLACK;
+                visuals.panel_fill = Color32::BLACK;
+                visuals.extreme_bg_color = Color32::BLACK;
+                visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+                visuals.hyperlink_color = Color32::from_rgb(0, 200, 255);
+                visuals.selection.bg_fill = Color32::from_rgb(0, 90, 200);
+                visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+
+                let widgets = &mut visuals.widgets;
+                widgets.noninteractive.bg_stroke = Stroke::new(1.0, Color32::WHITE);
+                widgets.noninteractive.fg_stroke = Stroke::new(1.0, Color32::WHITE);
+                for widget in [&mut widgets.inactive, &mut widgets.hovered, &mut widgets.active] {
+                    widget.bg_fill = Color32::BLACK;
+                    widget.weak_bg_fill = Color32::BLACK;
+                    widget.bg_stroke = Stroke::new(1.5, Color32::WHITE);
+                    widget.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+                }
+                // egui draws focused widgets with the hovered style
+                widgets.hovered.bg_stroke = Stroke::new(3.0, Color32::YELLOW);
+                widgets.active.bg_stroke = Stroke::new(3.0, Color32::YELLOW);
+                widgets.open.bg_stroke = Stroke::new(2.0, Color32::WHITE);
+                visuals
+            }
+        }
+    }
+
+    /// Visuals of the theme with selections and links in an accent color, if one is picked
+    pub fn visuals_with_accent(self, accent: Option<Color32>) -> Visuals {
+        let mut visuals = self.visuals();
+        if let Some(accent) = accent {
+            // Text on the selection has to stand out against it
+            let [r, g, b, _] = accent.to_array();
+            let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            let text = if luminance > 150.0 { Color32::BLACK } else { Color32::WHITE };
+            visuals.selection.bg_fill = accent;
+            visuals.selection.stroke.color = text;
+            visuals.hyperlink_color = accent;
+        }
+        visuals
+    }
+}
+
+/// Parse a color written as `#rrggbb`
+pub fn parse_color(hex: &str) -> Option<Color32> {
+    let hex = hex.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Write a color as `#rrggbb`, leaving out its alpha
+pub fn color_hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// Editor panels that can be docked or float in their own window
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Panel {
+    Utilities,
+    Inspector,
+    Timeline,
+    Performance,
+    Outliner,
+    Script,
+    Console,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 7] = [
+        Panel::Utilities,
+        Panel::Inspector,
+        Panel::Timeline,
+        Panel::Performance,
+        Panel::Outliner,
+        Panel::Script,
+        Panel::Console,
+    ];
+
+    /// Name used in the configuration file
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Utilities => "utilities",
+            Panel::Inspector => "inspector",
+            Panel::Timeline => "timeline",
+            Panel::Performance => "performance",
+            Panel::Outliner => "outliner",
+            Panel::Script => "script",
+            Panel::Console => "console",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Panel::Utilities => "🔧 Utilities",
+            Panel::Inspector => "🔍 Inspector",
+            Panel::Timeline => "🎞 Timeline",
+            Panel::Performance => "⏱ Performance",
+            Panel::Outliner => "🗂 Outliner",
+            Panel::Script => "📜 Script",
+            Panel::Console => "🖥 Console",
+        }
+    }
+}
+
+/// Where a panel is shown
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DockArea {
+    Left,
+    Right,
+    Bottom,
+    Floating,
+}
+
+impl DockArea {
+    pub const ALL: [DockArea; 4] =
+        [DockArea::Left, DockArea::Right, DockArea::Bottom, DockArea::Floating];
+    /// Areas along the window edges, in the order egui lays them out
+    pub const DOCKED: [DockArea; 3] = [DockArea::Bottom, DockArea::Left, DockArea::Right];
+
+    /// Name used in the configuration file
+    pub fn name(self) -> &'static str {
+        match self {
+            DockArea::Left => "left",
+            DockArea::Right => "right",
+            DockArea::Bottom => "bottom",
+            DockArea::Floating => "floating",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DockArea::Left => "Left",
+            DockArea::Right => "Right",
+            DockArea::Bottom => "Bottom",
+            DockArea::Floating => "Floating window",
+        }
+    }
+}
+
+/// Where each panel is docked, which tab is in front in each area and how large the areas are
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockLayout {
+    areas: [DockArea; Panel::ALL.len()],
+    front: [Option<Panel>; DockArea::ALL.len()],
+    /// Width of the side areas and height of the bottom area, in points
+    sizes: [f32; DockArea::ALL.len()],
+}
+
+impl Default for DockLayout {
+    fn default() -> Self {
+        Self {
+            areas: [
+                DockArea::Left,
+                DockArea::Right,
+                DockArea::Bottom,
+                DockArea::Floating,
+                DockArea::Left,
+                DockArea::Right,
+                DockArea::Bottom,
+            ],
+            front: [None; DockArea::ALL.len()],
+            sizes: [200.0, 300.0, 200.0, 0.0],
+        }
+    }
+}
+
+impl DockLayout {
+    pub fn area(&self, panel: Panel) -> DockArea {
+        self.areas[panel as usize]
+    }
+
+    /// Move a panel to another area and bring it to the front there
+    pub fn set_area(&mut self, panel: Panel, area: DockArea) {
+        self.areas[panel as usize] = area;
+        self.front[area as usize] = Some(panel);
+    }
+
+    /// Panels in an area, out of the ones that are open
+    pub fn tabs(&self, area: DockArea, open: &[Panel]) -> Vec<Panel> {
+        open.iter().copied().filter(|&panel| self.area(panel) == area).collect()
+    }
+
+    /// Tab shown in an area, out of the given tabs
+    pub fn front(&self, area: DockArea, tabs: &[Panel]) -> Option<Panel> {
+        self.front[area as usize].filter(|p| tabs.contains(p)).or_else(|| tabs.first().copied())
+    }
+
+    pub fn set_front(&mut self, area: DockArea, panel: Panel) {
+        self.front[area as usize] = Some(panel);
+    }
+
+    pub fn size(&self, area: DockArea) -> f32 {
+        self.sizes[area as usize]
+    }
+
+    pub fn set_size(&mut self, area: DockArea, size: f32) {
+        self.sizes[area as usize] = size;
+    }
+
+    pub(crate) fn load(&mut self, table: &dyn toml_edit::TableLike, path: &Path) {
+        for panel in Panel::ALL {
+            let Some(name) = table.get(panel.name()).and_then(Item::as_str) else {
+                continue;
+            };
+            match DockArea::ALL.into_iter().find(|a| a.name() == name) {
+                Some(area) => self.areas[panel as usize] = area,
+                None => warn!("unknown dock area {:?} in {}", name, path.display()),
+            }
+        }
+        for area in DockArea::DOCKED {
+            let tab = table.get(&format!("{}_tab", area.name())).and_then(Item::as_str);
+            if let Some(name) = tab {
+                self.front[area as usize] = Panel::ALL.into_iter().find(|p| p.name() == name);
+            }
+            let size = table.get(&format!("{}_size", area.name())).and_then(Item::as_float);
+            if let Some(size) = size {
+                self.sizes[area as usize] = size as f32;
+            }
+        }
+    }
+
+    pub(crate) fn save(&self) -> Table {
+        let mut table = Table::new();
+        for panel in Panel::ALL {
+            table[panel.name()] = toml_edit::value(self.area(panel).name());
+        }
+        for area in DockArea::DOCKED {
+            if let Some(panel) = self.front[area as usize] {
+                table[&format!("{}_tab", area.name())] = toml_edit::value(panel.name());
+            }
+            table[&format!("{}_size", area.name())] = toml_edit::value(self.size(area) as f64);
+        }
+        table
+    }
+}
@@ -1,61 +1,272 @@
+use std::borrow::Cow;
 use std::fmt::Display;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Weak};
 
+use ahash::AHashMap;
 use color_eyre::eyre::eyre;
 use color_eyre::Result;
 use glow::{Context, HasContext};
 use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
 pub const GEOMETRY_PASS_VERT: &str = include_str!("../shaders/geometry_pass_vert.glsl");
 pub const GEOMETRY_PASS_FRAG: &str = include_str!("../shaders/geometry_pass_frag.glsl");
+pub const DEPTH_VERT: &str = include_str!("../shaders/depth_vert.glsl");
+pub const DEPTH_FRAG: &str = include_str!("../shaders/depth_frag.glsl");
 pub const DEFERRED_PASS_VERT: &str = include_str!("../shaders/deferred_pass_vert.glsl");
 pub const DEFERRED_PASS_FRAG: &str = include_str!("../shaders/deferred_pass_frag.glsl");
+pub const REFLECTION_PASS_FRAG: &str = include_str!("../shaders/reflection_pass_frag.glsl");
+pub const DEPTH_OF_FIELD_FRAG: &str = include_str!("../shaders/depth_of_field_frag.glsl");
+pub const MOTION_BLUR_FRAG: &str = include_str!("../shaders/motion_blur_frag.glsl");
+pub const DEBUG_LINE_VERT: &str = include_str!("../shaders/debug_line_vert.glsl");
+pub const DEBUG_LINE_FRAG: &str = include_str!("../shaders/debug_line_frag.glsl");
+
+/// Folder `#include "file.glsl"` is resolved against
+pub const INCLUDE_DIR: &str = "shaders/include";
+
+/// Shader library built into the editor, used for includes that are not in the include folder
+pub const BUILTIN_INCLUDES: [(&str, &str); 2] = [
+    ("lighting.glsl", include_str!("../shaders/include/lighting.glsl")),
+    ("normal_map.glsl", include_str!("../shaders/include/normal_map.glsl")),
+];
 
 pub struct Shader {
     pub program: glow::Program,
     destroyed: bool,
+    /// Shared by every shader using the program, if it is shared
+    users: Option<Arc<()>>,
 }
 
 impl Shader {
     pub fn new(program: glow::Program) -> Self {
-        Self { program, destroyed: false }
+        Self { program, destroyed: false, users: None }
+    }
+
+    /// Share the program with other shaders, so it is only deleted when the last of them is
+    /// destroyed
+    pub fn into_shared(mut self) -> Self {
+        self.users.get_or_insert_with(Default::default);
+        self
+    }
+
+    /// Keep track of a shared program without keeping it from being deleted
+    pub fn downgrade(&self) -> Option<WeakShader> {
+        let users = Arc::downgrade(self.users.as_ref()?);
+        Some(WeakShader { program: self.program, users })
     }
 
     pub fn activate(&self, gl: &Context) {
         unsafe { gl.use_program(Some(self.program)) }
     }
 
+    /// # Safety
+    ///
+    /// The shader program is no longer valid and should not be used. A shared program stays valid
+    /// for the other shaders using it, and is deleted with the last of them.
     pub unsafe fn destroy(&mut self, gl: &Context) {
-        gl.delete_program(self.program);
+        let used_elsewhere = self.users.take().is_some_and(|users| Arc::strong_count(&users) > 1);
+        if !used_elsewhere {
+            gl.delete_program(self.program);
+        }
         self.destroyed = true;
     }
 
+    /// # Safety
+    ///
+    /// The shader program must be active.
+    pub unsafe fn uniform_vec2(&self, gl: &Context, name: &str, value: &glm::Vec2) {
+        let loc = gl.get_uniform_location(self.program, name);
+        gl.uniform_2_f32_slice(loc.as_ref(), glm::value_ptr(value));
+    }
+
+    /// # Safety
+    ///
+    /// The shader program must be active.
     pub unsafe fn uniform_vec3(&self, gl: &Context, name: &str, value: &glm::Vec3) {
         let loc = gl.get_uniform_location(self.program, name);
         gl.uniform_3_f32_slice(loc.as_ref(), glm::value_ptr(value));
     }
 
+    /// # Safety
+    ///
+    /// The shader program must be active.
+    pub unsafe fn uniform_vec4(&self, gl: &Context, name: &str, value: &glm::Vec4) {
+        let loc = gl.get_uniform_location(self.program, name);
+        gl.uniform_4_f32_slice(loc.as_ref(), glm::value_ptr(value));
+    }
+
+    /// # Safety
+    ///
+    /// The shader program must be active.
     pub unsafe fn uniform_mat3(&self, gl: &Context, name: &str, value: &glm::Mat3) {
         let loc = gl.get_uniform_location(self.program, name);
         gl.uniform_matrix_3_f32_slice(loc.as_ref(), false, glm::value_ptr(value));
     }
 
+    /// # Safety
+    ///
+    /// The shader program must be active.
     pub unsafe fn uniform_mat4(&self, gl: &Context, name: &str, value: &glm::Mat4) {
         let loc = gl.get_uniform_location(self.program, name);
         gl.uniform_matrix_4_f32_slice(loc.as_ref(), false, glm::value_ptr(value));
     }
 
+    /// # Safety
+    ///
+    /// The shader program must be active.
+    pub unsafe fn uniform_mat4_array(&self, gl: &Context, name: &str, values: &[glm::Mat4]) {
+        let loc = gl.get_uniform_location(self.program, name);
+        gl.uniform_matrix_4_f32_slice(loc.as_ref(), false, bytemuck::cast_slice(values));
+    }
+
+    /// # Safety
+    ///
+    /// The shader program must be active.
     pub unsafe fn uniform_float(&self, gl: &Context, name: &str, value: f32) {
         let loc = gl.get_uniform_location(self.program, name);
         gl.uniform_1_f32(loc.as_ref(), value);
     }
 
+    /// # Safety
+    ///
+    /// The shader program must be active.
     pub unsafe fn uniform_int(&self, gl: &Context, name: &str, value: i32) {
         let loc = gl.get_uniform_location(self.program, name);
         gl.uniform_1_i32(loc.as_ref(), value);
     }
+
+    /// # Safety
+    ///
+    /// The shader program must be active.
+    pub unsafe fn uniform_uint(&self, gl: &Context, name: &str, value: u32) {
+        let loc = gl.get_uniform_location(self.program, name);
+        gl.uniform_1_u32(loc.as_ref(), value);
+    }
+
+    /// # Safety
+    ///
+    /// The shader program must be active.
+    pub unsafe fn uniform_value(&self, gl: &Context, name: &str, value: &UniformValue) {
+        match *value {
+            UniformValue::Float(v) => self.uniform_float(gl, name, v),
+            UniformValue::Int(v) => self.uniform_int(gl, name, v),
+            UniformValue::Bool(v) => self.uniform_int(gl, name, v as i32),
+            UniformValue::Vec2(v) => self.uniform_vec2(gl, name, &v.into()),
+            UniformValue::Vec3(v) => self.uniform_vec3(gl, name, &v.into()),
+            UniformValue::Vec4(v) => self.uniform_vec4(gl, name, &v.into()),
+        }
+    }
+
+    /// Uniforms of the program that can be edited, with the values the program starts with
+    ///
+    /// Leaves out the uniforms the renderer sets, arrays, matrices and samplers.
+    pub fn editable_uniforms(&self, gl: &Context) -> Vec<(String, UniformValue)> {
+        let mut uniforms = Vec::new();
+        unsafe {
+            for index in 0..gl.get_active_uniforms(self.program) {
+                let Some(uniform) = gl.get_active_uniform(self.program, index) else {
+                    continue;
+                };
+                if uniform.size != 1 || RENDERER_UNIFORMS.contains(&uniform.name.as_str()) {
+                    continue;
+                }
+                let Some(location) = gl.get_uniform_location(self.program, &uniform.name) else {
+                    continue;
+                };
+                let mut f = [0.0; 4];
+                let mut i = [0; 1];
+                let value = match uniform.utype {
+                    glow::FLOAT => {
+                        gl.get_uniform_f32(self.program, &location, &mut f[..1]);
+                        UniformValue::Float(f[0])
+                    }
+                    glow::FLOAT_VEC2 => {
+                        gl.get_uniform_f32(self.program, &location, &mut f[..2]);
+                        UniformValue::Vec2([f[0], f[1]])
+                    }
+                    glow::FLOAT_VEC3 => {
+                        gl.get_uniform_f32(self.program, &location, &mut f[..3]);
+                        UniformValue::Vec3([f[0], f[1], f[2]])
+                    }
+                    glow::FLOAT_VEC4 => {
+                        gl.get_uniform_f32(self.program, &location, &mut f);
+                        UniformValue::Vec4(f)
+                    }
+                    glow::INT => {
+                        gl.get_uniform_i32(self.program, &location, &mut i);
+                        UniformValue::Int(i[0])
+                    }
+                    glow::BOOL => {
+                        gl.get_uniform_i32(self.program, &location, &mut i);
+                        UniformValue::Bool(i[0] != 0)
+                    }
+                    _ => continue,
+                };
+                uniforms.push((uniform.name, value));
+            }
+        }
+        uniforms
+    }
+}
+
+/// Uniforms the renderer sets on every custom shader
+const RENDERER_UNIFORMS: [&str; 15] = [
+    "camera_pos",
+    "delta_time",
+    "diffuse_tx",
+    "joint_matrices",
+    "model",
+    "mvp",
+    "normal_mat",
+    "normal_tx",
+    "previous_mvp",
+    "resolution",
+    "selected",
+    "skinned",
+    "specular_tx",
+    "time",
+    "user_params",
+];
+
+/// Value of a uniform that can be edited in the inspector
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Bool(bool),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+}
+
+impl UniformValue {
+    /// Whether both values have the same GLSL type
+    pub fn same_type(&self, other: &UniformValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// A shared program that may have been deleted, from [`Shader::downgrade`]
+#[derive(Debug, Clone)]
+pub struct WeakShader {
+    program: glow::Program,
+    users: Weak<()>,
+}
+
+impl WeakShader {
+    /// Another shader using the program, unless every shader using it was destroyed
+    pub fn upgrade(&self) -> Option<Shader> {
+        let users = self.users.upgrade()?;
+        Some(Shader { program: self.program, destroyed: false, users: Some(users) })
+    }
+
+    /// Whether a shader is still using the program
+    pub fn is_used(&self) -> bool {
+        self.users.strong_count() > 0
+    }
 }
 
 impl Drop for Shader {
@@ -66,7 +277,7 @@ impl Drop for Shader {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -84,11 +295,25 @@ impl Display for ShaderType {
 pub struct ShaderBuilder<'a> {
     gl: &'a Context,
     shaders: Vec<glow::Shader>,
+    include_dir: PathBuf,
+    /// What the compiler reported for each stage added so far
+    diagnostics: Vec<(ShaderType, Vec<ShaderDiagnostic>)>,
 }
 
 impl<'a> ShaderBuilder<'a> {
     pub fn new(gl: &'a Context) -> Self {
-        Self { gl, shaders: Vec::new() }
+        Self {
+            gl,
+            shaders: Vec::new(),
+            include_dir: PathBuf::from(INCLUDE_DIR),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Resolve `#include` against `dir` instead of [`INCLUDE_DIR`]
+    pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.include_dir = dir.into();
+        self
     }
 
     #[allow(dead_code)]
@@ -101,32 +326,18 @@ impl<'a> ShaderBuilder<'a> {
     }
 
     pub fn add_shader_source(mut self, source: &str, shader_type: ShaderType) -> Result<Self> {
-        let shader_enum = match shader_type {
-            ShaderType::Vertex => glow::VERTEX_SHADER,
-            ShaderType::Fragment => glow::FRAGMENT_SHADER,
-        };
-
-        let shader = unsafe {
-            let shader = self
-                .gl
-                .create_shader(shader_enum)
-                .map_err(|e| eyre!("could not create shader: {e}"))?;
-            self.gl.shader_source(shader, source);
-            self.gl.compile_shader(shader);
-
-            if !self.gl.get_shader_compile_status(shader) {
-                return Err(eyre!(
-                    "{shader_type} shader compilation failed:\n{}",
-                    self.gl.get_shader_info_log(shader)
-                ));
-            }
-            shader
-        };
-
+        let (shader, diagnostics) = compile_stage(self.gl, source, shader_type, &self.include_dir)?;
+        self.diagnostics.push((shader_type, diagnostics));
         self.shaders.push(shader);
         Ok(self)
     }
 
+    /// Warnings the compiler reported for the stages of `shader_type` that were added
+    pub fn diagnostics(&self, shader_type: ShaderType) -> Vec<ShaderDiagnostic> {
+        let stages = self.diagnostics.iter().filter(|(stage, _)| *stage == shader_type);
+        stages.flat_map(|(_, diagnostics)| diagnostics.iter().cloned()).collect()
+    }
+
     pub fn link(self) -> Result<Shader> {
         let program = unsafe {
             self.gl.create_program().map_err(|e| eyre!("could not create shader program: {e}"))?
@@ -142,20 +353,520 @@ impl<'a> ShaderBuilder<'a> {
             self.gl.link_program(program);
 
             if !self.gl.get_program_link_status(program) {
+                let log = self.gl.get_program_info_log(program);
                 self.gl.delete_program(program);
-                return Err(eyre!(
-                    "shader program linking failed:\n{}",
-                    self.gl.get_program_info_log(program)
-                ));
+                return Err(eyre!("shader program linking failed:\n{log}"));
+            }
+        }
+
+        Ok(Shader::new(program))
+    }
+}
+
+/// Compile one stage with its includes resolved against `include_dir`, returning the shader object
+/// and the warnings the compiler reported
+///
+/// The shader object is not deleted once it is linked, which the caller has to do.
+pub fn compile_stage(
+    gl: &Context,
+    source: &str,
+    shader_type: ShaderType,
+    include_dir: &Path,
+) -> Result<(glow::Shader, Vec<ShaderDiagnostic>)> {
+    let shader_enum = match shader_type {
+        ShaderType::Vertex => glow::VERTEX_SHADER,
+        ShaderType::Fragment => glow::FRAGMENT_SHADER,
+    };
+
+    let source = resolve_includes(source, |name| read_include(include_dir, name)).map_err(|e| {
+        CompileError {
+            shader_type,
+            diagnostics: vec![e.diagnostic()],
+            message: format!("{shader_type} shader: {e}"),
+        }
+    })?;
+
+    unsafe {
+        let shader =
+            gl.create_shader(shader_enum).map_err(|e| eyre!("could not create shader: {e}"))?;
+        gl.shader_source(shader, &source.source);
+        gl.compile_shader(shader);
+
+        let log = gl.get_shader_info_log(shader);
+        if !gl.get_shader_compile_status(shader) {
+            gl.delete_shader(shader);
+            return Err(CompileError {
+                shader_type,
+                diagnostics: source.remap(parse_info_log(&log)),
+                message: format!(
+                    "{shader_type} shader compilation failed:\n{}",
+                    source.remap_log(&log)
+                ),
             }
+            .into());
         }
+        Ok((shader, source.remap(parse_info_log(&log))))
+    }
+}
 
-        for shader in self.shaders {
+impl Drop for ShaderBuilder<'_> {
+    fn drop(&mut self) {
+        // Shaders are not needed once they are linked, or when building the program fails
+        for shader in self.shaders.drain(..) {
             unsafe {
                 self.gl.delete_shader(shader);
             }
         }
+    }
+}
 
-        Ok(Shader::new(program))
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A message from the shader compiler, with the position it points at when the driver gives one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShaderDiagnostic {
+    pub severity: Severity,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{line}:{column}: {severity}: {}", self.message)
+            }
+            (Some(line), None) => write!(f, "{line}: {severity}: {}", self.message),
+            _ => write!(f, "{severity}: {}", self.message),
+        }
+    }
+}
+
+/// Split a compiler info log into diagnostics
+///
+/// Understands the Mesa (`0:12(5): error: ...`), NVIDIA (`0(12) : error C0000: ...`) and
+/// AMD, Intel and Apple (`ERROR: 0:12: ...`) formats. Lines in any other format are kept as
+/// diagnostics without a position.
+pub fn parse_info_log(log: &str) -> Vec<ShaderDiagnostic> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_info_log_line(line).unwrap_or_else(|| unpositioned(line)))
+        .collect()
+}
+
+fn parse_info_log_line(line: &str) -> Option<ShaderDiagnostic> {
+    // AMD, Intel and Apple: severity first, then source:line
+    for (prefix, severity) in [("ERROR: ", Severity::Error), ("WARNING: ", Severity::Warning)] {
+        if let Some(rest) = line.strip_prefix(prefix) {
+            let (_, rest) = rest.split_once(':')?;
+            let (line, message) = rest.split_once(':')?;
+            return Some(ShaderDiagnostic {
+                severity,
+                line: Some(line.trim().parse().ok()?),
+                column: None,
+                message: message.trim().to_owned(),
+            });
+        }
+    }
+
+    let (position, rest) = line.split_once(": ")?;
+    let (severity, message) = rest.split_once(':')?;
+    let severity = match severity.split_whitespace().next()? {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        _ => return None,
+    };
+    let (line, column) = match position.trim().split_once('(') {
+        // Mesa: source:line(column)
+        Some((source_line, column)) if source_line.contains(':') => {
+            let (_, line) = source_line.split_once(':')?;
+            (line.parse().ok()?, Some(column.strip_suffix(')')?.parse().ok()?))
+        }
+        // NVIDIA: source(line)
+        Some((_, line)) => (line.strip_suffix(')')?.parse().ok()?, None),
+        None => return None,
+    };
+
+    Some(ShaderDiagnostic {
+        severity,
+        line: Some(line),
+        column,
+        message: message.trim().to_owned(),
+    })
+}
+
+fn unpositioned(line: &str) -> ShaderDiagnostic {
+    let severity =
+        if line.to_lowercase().contains("warning") { Severity::Warning } else { Severity::Error };
+    ShaderDiagnostic { severity, line: None, column: None, message: line.to_owned() }
+}
+
+/// Contents of an include file in `dir`, or in [`BUILTIN_INCLUDES`] when it is not there
+pub fn read_include(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok().or_else(|| {
+        BUILTIN_INCLUDES
+            .iter()
+            .find(|(file, _)| *file == name)
+            .map(|(_, source)| source.to_string())
+    })
+}
+
+/// [`resolve_includes`] against [`INCLUDE_DIR`]
+pub fn preprocess(source: &str) -> Result<Preprocessed, IncludeError> {
+    resolve_includes(source, |name| read_include(Path::new(INCLUDE_DIR), name))
+}
+
+/// Shader source with its includes pasted in, and where each of its lines came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preprocessed {
+    pub source: String,
+    /// Included files, in the order they were first included
+    pub files: Vec<String>,
+    lines: Vec<SourceLine>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct SourceLine {
+    /// Index into `files`, or `None` for the shader source itself
+    file: Option<usize>,
+    line: u32,
+    /// Line of the shader source this line is in, or the `#include` it came from
+    source_line: u32,
+}
+
+impl Preprocessed {
+    /// Point diagnostics for the preprocessed source at the lines they came from
+    ///
+    /// Diagnostics in an included file are put on the `#include` line, with the file and line in
+    /// the message.
+    pub fn remap(&self, diagnostics: Vec<ShaderDiagnostic>) -> Vec<ShaderDiagnostic> {
+        diagnostics
+            .into_iter()
+            .map(|mut diagnostic| {
+                let index = diagnostic.line.and_then(|line| line.checked_sub(1));
+                if let Some(origin) = index.and_then(|index| self.lines.get(index as usize)) {
+                    diagnostic.line = Some(origin.source_line);
+                    if let Some(file) = origin.file {
+                        diagnostic.message =
+                            format!("{}:{}: {}", self.files[file], origin.line, diagnostic.message);
+                        diagnostic.column = None;
+                    }
+                }
+                diagnostic
+            })
+            .collect()
+    }
+
+    /// A compiler info log with its positions pointed at the lines they came from
+    pub fn remap_log(&self, log: &str) -> String {
+        if self.files.is_empty() {
+            return log.to_owned();
+        }
+        let diagnostics = self.remap(parse_info_log(log));
+        diagnostics.iter().map(|d| format!("{d}\n")).collect()
+    }
+}
+
+/// An `#include` that could not be resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeError {
+    /// Line of the shader source with the `#include` the error comes from
+    pub line: u32,
+    /// Included file and line the error is in, when it is not in the shader source itself
+    pub location: Option<(String, u32)>,
+    pub message: String,
+}
+
+impl IncludeError {
+    /// The error as a diagnostic on the line of the shader source it comes from
+    pub fn diagnostic(&self) -> ShaderDiagnostic {
+        let message = match &self.location {
+            Some((file, line)) => format!("{file}:{line}: {}", self.message),
+            None => self.message.clone(),
+        };
+        ShaderDiagnostic { severity: Severity::Error, line: Some(self.line), column: None, message }
+    }
+}
+
+impl Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some((file, line)) => {
+                write!(f, "{file}:{line}: {} (included from line {})", self.message, self.line)
+            }
+            None => write!(f, "{}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// A shader stage that did not compile, with the diagnostics to show in the shader editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub shader_type: ShaderType,
+    pub diagnostics: Vec<ShaderDiagnostic>,
+    message: String,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Replace each `#include "file.glsl"` with the file, as returned by `read`
+///
+/// Included files can include other files. Each file is pasted in once, where it is first
+/// included, so shared code does not need include guards. Including a file from itself, directly
+/// or through other files, is an error.
+pub fn resolve_includes<F>(source: &str, read: F) -> Result<Preprocessed, IncludeError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut includes = Includes {
+        read: &read,
+        stack: Vec::new(),
+        out: Preprocessed { source: String::new(), files: Vec::new(), lines: Vec::new() },
+    };
+    includes.expand(source, None, None)?;
+    Ok(includes.out)
+}
+
+struct Includes<'a> {
+    read: &'a dyn Fn(&str) -> Option<String>,
+    /// Files being included, innermost last
+    stack: Vec<String>,
+    out: Preprocessed,
+}
+
+impl Includes<'_> {
+    fn expand(
+        &mut self,
+        source: &str,
+        file: Option<usize>,
+        include_line: Option<u32>,
+    ) -> Result<(), IncludeError> {
+        for (index, text) in source.lines().enumerate() {
+            let line = index as u32 + 1;
+            let source_line = include_line.unwrap_or(line);
+            let error = |message: String| IncludeError {
+                line: source_line,
+                location: file.map(|file| (self.out.files[file].clone(), line)),
+                message,
+            };
+
+            let name = match include_name(text) {
+                None => {
+                    self.out.source.push_str(text);
+                    self.out.source.push('\n');
+                    self.out.lines.push(SourceLine { file, line, source_line });
+                    continue;
+                }
+                Some(name) => name.map_err(error)?,
+            };
+
+            if let Some(start) = self.stack.iter().position(|f| *f == name) {
+                let cycle = self.stack[start..].join(" -> ");
+                return Err(error(format!("include cycle: {cycle} -> {name}")));
+            }
+            if self.out.files.contains(&name) {
+                continue;
+            }
+            let Some(included) = (self.read)(&name) else {
+                return Err(error(format!("could not find \"{name}\" to include")));
+            };
+
+            self.out.files.push(name.clone());
+            self.stack.push(name);
+            self.expand(&included, Some(self.out.files.len() - 1), Some(source_line))?;
+            self.stack.pop();
+        }
+        Ok(())
+    }
+}
+
+/// The file named by an `#include` line, or `None` if the line is not an `#include`
+fn include_name(line: &str) -> Option<Result<String, String>> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?;
+    let rest = rest.split("//").next().unwrap_or_default().trim();
+    let name = rest
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| rest.strip_prefix('<').and_then(|rest| rest.strip_suffix('>')));
+    let Some(name) = name.filter(|name| !name.is_empty()) else {
+        return Some(Err(format!("expected #include \"file\", found `{}`", line.trim())));
+    };
+    // Includes are looked up in the include folder only
+    if !Path::new(name).components().all(|c| matches!(c, Component::Normal(_))) {
+        return Some(Err(format!("\"{name}\" is not a path inside the include folder")));
+    }
+    Some(Ok(name.to_owned()))
+}
+
+/// Optional features the built-in shaders can be compiled with
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderFeatures {
+    /// Deform vertices with joint matrices (`SKINNED`)
+    pub skinned: bool,
+    /// Discard fragments where the diffuse texture is mostly transparent (`ALPHA_TEST`)
+    pub alpha_test: bool,
+    /// Perturb the normal with a tangent-space normal map (`NORMAL_MAP`)
+    pub normal_map: bool,
+    /// Blend the layer textures of a terrain by the weights of its vertices (`SPLAT`)
+    pub splat: bool,
+}
+
+impl ShaderFeatures {
+    /// Source with a `#define` for each enabled feature inserted after the `#version` line
+    ///
+    /// `VARIANT` is always defined, so shaders can tell a variant from a custom shader compiled
+    /// from the same source without any defines.
+    pub fn apply(self, source: &str) -> String {
+        let mut defines = String::from("#define VARIANT\n");
+        let features = [
+            (self.skinned, "SKINNED"),
+            (self.alpha_test, "ALPHA_TEST"),
+            (self.normal_map, "NORMAL_MAP"),
+            (self.splat, "SPLAT"),
+        ];
+        for (enabled, name) in features {
+            if enabled {
+                defines.push_str(&format!("#define {name}\n"));
+            }
+        }
+        insert_defines(source, &defines)
+    }
+}
+
+/// Source with lines of `#define`s inserted after the `#version` line, which has to come first
+pub fn insert_defines(source: &str, defines: &str) -> String {
+    match source.split_once('\n') {
+        Some((version, rest)) if version.starts_with("#version") => {
+            format!("{version}\n{defines}{rest}")
+        }
+        _ => format!("{defines}{source}"),
+    }
+}
+
+/// Variants of a built-in shader, compiled the first time each combination of features is needed
+pub struct ShaderVariants {
+    vert_source: Cow<'static, str>,
+    frag_source: Cow<'static, str>,
+    include_dir: PathBuf,
+    /// Compiled variants, or `None` for those that did not compile, so they are not tried again
+    variants: AHashMap<ShaderFeatures, Option<Shader>>,
+}
+
+impl ShaderVariants {
+    pub fn new(vert_source: &'static str, frag_source: &'static str) -> Self {
+        Self {
+            vert_source: Cow::Borrowed(vert_source),
+            frag_source: Cow::Borrowed(frag_source),
+            include_dir: PathBuf::from(INCLUDE_DIR),
+            variants: AHashMap::new(),
+        }
+    }
+
+    /// Compile the variant for `features` unless it has been compiled before
+    ///
+    /// A variant that does not compile is an error in the built-in shader, returned the first time
+    /// it is prepared. It is not compiled again until the sources are reloaded.
+    pub fn prepare(&mut self, gl: &Context, features: ShaderFeatures) -> Result<()> {
+        if self.variants.contains_key(&features) {
+            return Ok(());
+        }
+        match self.compile(gl, features, &self.vert_source, &self.frag_source) {
+            Ok(shader) => {
+                self.variants.insert(features, Some(shader));
+                Ok(())
+            }
+            Err(e) => {
+                self.variants.insert(features, None);
+                Err(eyre!("could not compile shader variant {features:?}: {e}"))
+            }
+        }
+    }
+
+    /// Replace the sources and compile again every variant that has been prepared
+    ///
+    /// If any variant does not compile, the old programs and sources are kept.
+    pub fn reload(
+        &mut self,
+        gl: &Context,
+        vert_source: String,
+        frag_source: String,
+        include_dir: &Path,
+    ) -> Result<()> {
+        let old_include_dir = std::mem::replace(&mut self.include_dir, include_dir.to_owned());
+        let mut variants = AHashMap::new();
+        let mut features: Vec<_> = self.variants.keys().copied().collect();
+        if features.is_empty() {
+            features.push(ShaderFeatures::default());
+        }
+        for features in features {
+            match self.compile(gl, features, &vert_source, &frag_source) {
+                Ok(shader) => {
+                    variants.insert(features, Some(shader));
+                }
+                Err(e) => {
+                    self.include_dir = old_include_dir;
+                    for (_, shader) in variants {
+                        if let Some(mut shader) = shader {
+                            unsafe { shader.destroy(gl) };
+                        }
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        unsafe { self.destroy(gl) };
+        self.variants = variants;
+        self.vert_source = Cow::Owned(vert_source);
+        self.frag_source = Cow::Owned(frag_source);
+        Ok(())
+    }
+
+    fn compile(
+        &self,
+        gl: &Context,
+        features: ShaderFeatures,
+        vert_source: &str,
+        frag_source: &str,
+    ) -> Result<Shader> {
+        ShaderBuilder::new(gl)
+            .include_dir(&self.include_dir)
+            .add_shader_source(&features.apply(vert_source), ShaderType::Vertex)
+            .and_then(|b| b.add_shader_source(&features.apply(frag_source), ShaderType::Fragment))
+            .and_then(ShaderBuilder::link)
+    }
+
+    /// The variant for `features`, if it has been prepared and compiled successfully
+    pub fn get(&self, features: ShaderFeatures) -> Option<&Shader> {
+        self.variants.get(&features).and_then(Option::as_ref)
+    }
+
+    /// # Safety
+    ///
+    /// The shader programs are no longer valid and should not be used.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        for (_, shader) in self.variants.drain() {
+            if let Some(mut shader) = shader {
+                shader.destroy(gl);
+            }
+        }
     }
 }
@@ -0,0 +1,391 @@
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::{c_void, CStr};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::{fs, mem};
+
+use ahash::AHashMap;
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext};
+use tracing::{debug, warn};
+
+use crate::shader::{
+    self, Shader, ShaderBuilder, ShaderDiagnostic, ShaderType, UniformValue, WeakShader,
+    INCLUDE_DIR,
+};
+
+/// Folder linked programs are kept in between runs
+pub const BINARY_DIR: &str = ".cache/shaders";
+
+/// Key of a program in the `ShaderCache`, a hash of its sources with the includes resolved
+///
+/// `None` if an include can not be resolved, in which case the program can not be linked either.
+pub fn cache_key(vert: &str, frag: &str) -> Option<u64> {
+    let vert = shader::preprocess(vert).ok()?;
+    let frag = shader::preprocess(frag).ok()?;
+    let mut hasher = DefaultHasher::new();
+    vert.source.hash(&mut hasher);
+    frag.source.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Key of one compiled stage in the `ShaderCache`, a hash of its type and source with the includes
+/// resolved
+pub fn stage_key(source: &str, shader_type: ShaderType) -> Option<u64> {
+    let source = shader::preprocess(source).ok()?;
+    let mut hasher = DefaultHasher::new();
+    shader_type.hash(&mut hasher);
+    source.source.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Linked programs stored on disk, so they can be loaded instead of compiled on the next run
+///
+/// Binaries only work with the driver that made them, so they are stored under the name of the
+/// renderer and driver version as well as the key of the program.
+#[derive(Debug, Clone)]
+pub struct BinaryStore {
+    dir: PathBuf,
+    driver: String,
+}
+
+impl BinaryStore {
+    pub fn new(dir: impl Into<PathBuf>, driver: impl Into<String>) -> Self {
+        Self { dir: dir.into(), driver: driver.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The binary format and data stored for a program
+    pub fn read(&self, key: u64) -> Option<(u32, Vec<u8>)> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        let format = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?);
+        Some((format, bytes[4..].to_vec()))
+    }
+
+    pub fn write(&self, key: u64, format: u32, data: &[u8]) -> Result<()> {
+        let path = self.path(key);
+        let bytes = [&format.to_le_bytes()[..], data].concat();
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, bytes))
+            .map_err(|e| eyre!("could not write {}: {e}", path.display()))
+    }
+
+    /// The warnings the compiler reported for the vertex and fragment shader of a stored program,
+    /// which its binary does not keep
+    pub fn read_diagnostics(&self, key: u64) -> Option<StageDiagnostics> {
+        let text = fs::read_to_string(self.path(key).with_extension("ron")).ok()?;
+        ron::from_str(&text).ok()
+    }
+
+    pub fn write_diagnostics(&self, key: u64, diagnostics: &StageDiagnostics) -> Result<()> {
+        let path = self.path(key).with_extension("ron");
+        let text = ron::to_string(diagnostics)
+            .map_err(|e| eyre!("could not serialize shader diagnostics: {e}"))?;
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&path, text))
+            .map_err(|e| eyre!("could not write {}: {e}", path.display()))
+    }
+
+    /// Forget the binary of a program, such as when the driver no longer accepts it
+    pub fn remove(&self, key: u64) {
+        let _ = fs::remove_file(self.path(key));
+        let _ = fs::remove_file(self.path(key).with_extension("ron"));
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        self.driver.hash(&mut hasher);
+        key.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.bin", hasher.finish()))
+    }
+}
+
+/// Warnings of the vertex and fragment shader of a program
+pub type StageDiagnostics = (Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>);
+
+type GetProgramiv = unsafe extern "system" fn(u32, u32, *mut i32);
+type GetProgramBinary = unsafe extern "system" fn(u32, i32, *mut i32, *mut u32, *mut c_void);
+type ProgramBinary = unsafe extern "system" fn(u32, u32, *const c_void, i32);
+type ProgramParameteri = unsafe extern "system" fn(u32, u32, i32);
+
+/// The OpenGL functions for reading and loading program binaries, which glow does not wrap
+#[derive(Clone, Copy)]
+pub struct ProgramBinaryFns {
+    get_programiv: GetProgramiv,
+    get_program_binary: GetProgramBinary,
+    program_binary: ProgramBinary,
+    program_parameteri: ProgramParameteri,
+}
+
+impl ProgramBinaryFns {
+    /// Look up the functions with the loader the context was created with, if the driver has any
+    /// binary formats
+    ///
+    /// # Safety
+    ///
+    /// The loader must return the functions of the current context, which `gl` is for.
+    pub unsafe fn load(
+        gl: &Context,
+        mut loader: impl FnMut(&CStr) -> *const c_void,
+    ) -> Option<Self> {
+        if gl.get_parameter_i32(glow::NUM_PROGRAM_BINARY_FORMATS) <= 0 {
+            return None;
+        }
+        let mut lookup = |name: &[u8]| {
+            let function = loader(CStr::from_bytes_with_nul(name).ok()?);
+            (!function.is_null()).then_some(function)
+        };
+        Some(Self {
+            get_programiv: mem::transmute::<*const c_void, GetProgramiv>(lookup(
+                b"glGetProgramiv\0",
+            )?),
+            get_program_binary: mem::transmute::<*const c_void, GetProgramBinary>(lookup(
+                b"glGetProgramBinary\0",
+            )?),
+            program_binary: mem::transmute::<*const c_void, ProgramBinary>(lookup(
+                b"glProgramBinary\0",
+            )?),
+            program_parameteri: mem::transmute::<*const c_void, ProgramParameteri>(lookup(
+                b"glProgramParameteri\0",
+            )?),
+        })
+    }
+
+    /// Ask the driver to keep the binary of a program it is about to link, which it may otherwise
+    /// not be able to give back
+    unsafe fn set_retrievable(&self, program: glow::Program) {
+        (self.program_parameteri)(
+            program.0.get(),
+            glow::PROGRAM_BINARY_RETRIEVABLE_HINT,
+            glow::TRUE as i32,
+        );
+    }
+
+    /// The binary format and data of a linked program
+    unsafe fn read(&self, program: glow::Program) -> Option<(u32, Vec<u8>)> {
+        let id = program.0.get();
+        let mut length = 0;
+        (self.get_programiv)(id, glow::PROGRAM_BINARY_LENGTH, &mut length);
+        let mut data = vec![0; usize::try_from(length).ok().filter(|&l| l > 0)?];
+        let (mut written, mut format) = (0, 0);
+        (self.get_program_binary)(id, length, &mut written, &mut format, data.as_mut_ptr().cast());
+        data.truncate(usize::try_from(written).unwrap_or(0));
+        (!data.is_empty()).then_some((format, data))
+    }
+
+    /// A program loaded from a binary, if the driver accepts it
+    unsafe fn load_program(&self, gl: &Context, format: u32, data: &[u8]) -> Option<glow::Program> {
+        let program = gl.create_program().ok()?;
+        let length = i32::try_from(data.len()).ok()?;
+        (self.program_binary)(program.0.get(), format, data.as_ptr().cast(), length);
+        if gl.get_program_link_status(program) {
+            Some(program)
+        } else {
+            gl.delete_program(program);
+            None
+        }
+    }
+}
+
+/// A program from the `ShaderCache`, with what was found out about it when it was linked
+pub struct CachedShader {
+    pub shader: Shader,
+    /// Editable uniforms with the values the program starts with
+    pub uniforms: Vec<(String, UniformValue)>,
+    pub vert_diagnostics: Vec<ShaderDiagnostic>,
+    pub frag_diagnostics: Vec<ShaderDiagnostic>,
+}
+
+struct CachedProgram {
+    shader: WeakShader,
+    uniforms: Vec<(String, UniformValue)>,
+    vert_diagnostics: Vec<ShaderDiagnostic>,
+    frag_diagnostics: Vec<ShaderDiagnostic>,
+    /// Keys of the stages it was linked from, none if it was loaded from a binary
+    stages: Vec<u64>,
+}
+
+/// A compiled stage, kept so a program whose other stage changed does not compile it again
+struct CompiledStage {
+    shader: glow::Shader,
+    diagnostics: Vec<ShaderDiagnostic>,
+}
+
+/// Linked custom shader programs by the hash of their source, shared by the entities using them
+///
+/// A program is deleted when the last entity using it destroys its shader, and linked again the
+/// next time it is needed, unless it can be loaded from the `BinaryStore`.
+#[derive(Resource, Default)]
+pub struct ShaderCache {
+    programs: AHashMap<u64, CachedProgram>,
+    /// Compiled stages of the programs, by `stage_key`
+    stages: AHashMap<u64, CompiledStage>,
+    binaries: Option<(BinaryStore, ProgramBinaryFns)>,
+}
+
+impl ShaderCache {
+    /// Store linked programs in `store`, and load them from it before compiling them
+    pub fn with_binaries(mut self, store: BinaryStore, fns: ProgramBinaryFns) -> Self {
+        self.binaries = Some((store, fns));
+        self
+    }
+
+    /// Number of programs that are in use
+    pub fn len(&self) -> usize {
+        self.programs.values().filter(|p| p.shader.is_used()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A shader using the program linked from the sources, linking it if nothing uses it yet
+    ///
+    /// Only the stages whose source changed since a program was last linked are compiled again.
+    /// The warnings of a program are stored next to its binary, so a program loaded from one still
+    /// has them.
+    pub fn get_or_link(&mut self, gl: &Context, vert: &str, frag: &str) -> Result<CachedShader> {
+        let Some(key) = cache_key(vert, frag) else {
+            // Fails with the include error
+            return link(gl, vert, frag).map(|(shader, vert_diagnostics, frag_diagnostics)| {
+                CachedShader {
+                    uniforms: shader.editable_uniforms(gl),
+                    shader,
+                    vert_diagnostics,
+                    frag_diagnostics,
+                }
+            });
+        };
+
+        if let Some(cached) = self.programs.get(&key) {
+            if let Some(shader) = cached.shader.upgrade() {
+                debug!("reusing linked program {key:016x}");
+                return Ok(CachedShader {
+                    shader,
+                    uniforms: cached.uniforms.clone(),
+                    vert_diagnostics: cached.vert_diagnostics.clone(),
+                    frag_diagnostics: cached.frag_diagnostics.clone(),
+                });
+            }
+        }
+
+        let stored = self.binaries.as_ref().and_then(|(store, fns)| {
+            let (format, data) = store.read(key)?;
+            let program = unsafe { fns.load_program(gl, format, &data) };
+            if program.is_none() {
+                debug!("stored binary of program {key:016x} was rejected, compiling it");
+                store.remove(key);
+            }
+            Some((program?, store.read_diagnostics(key).unwrap_or_default()))
+        });
+        let (shader, vert_diagnostics, frag_diagnostics, stages) = match stored {
+            Some((program, (vert_diagnostics, frag_diagnostics))) => {
+                debug!("loaded program {key:016x} from its stored binary");
+                (Shader::new(program), vert_diagnostics, frag_diagnostics, Vec::new())
+            }
+            None => {
+                let (shader, stages) = self.link_stages(gl, vert, frag)?;
+                let vert_diagnostics = self.stages[&stages[0]].diagnostics.clone();
+                let frag_diagnostics = self.stages[&stages[1]].diagnostics.clone();
+                if let Some((store, fns)) = &self.binaries {
+                    let stored = unsafe { fns.read(shader.program) }.map(|(format, data)| {
+                        store.write(key, format, &data).and_then(|_| {
+                            let diagnostics = (vert_diagnostics.clone(), frag_diagnostics.clone());
+                            store.write_diagnostics(key, &diagnostics)
+                        })
+                    });
+                    if let Some(Err(e)) = stored {
+                        warn!("could not store shader binary: {e}");
+                    }
+                }
+                (shader, vert_diagnostics, frag_diagnostics, stages)
+            }
+        };
+
+        // Read before anything sets the uniforms, so they are the values in the source
+        let uniforms = shader.editable_uniforms(gl);
+        let shader = shader.into_shared();
+        let weak = shader.downgrade().expect("shared shaders can be downgraded");
+        let cached = CachedProgram {
+            shader: weak,
+            uniforms: uniforms.clone(),
+            vert_diagnostics: vert_diagnostics.clone(),
+            frag_diagnostics: frag_diagnostics.clone(),
+            stages,
+        };
+        // Forget programs that were deleted, such as earlier versions of a shader being edited
+        self.programs.retain(|_, p| p.shader.is_used());
+        self.programs.insert(key, cached);
+        // Stages are kept only while a program linked from them is, to be linked again
+        self.stages.retain(|key, stage| {
+            let used = self.programs.values().any(|p| p.stages.contains(key));
+            if !used {
+                unsafe {
+                    gl.delete_shader(stage.shader);
+                }
+            }
+            used
+        });
+        Ok(CachedShader { shader, uniforms, vert_diagnostics, frag_diagnostics })
+    }
+
+    /// Link a program from the stages of the sources, compiling the stages that are not cached,
+    /// and return it with the keys of its vertex and fragment stage
+    fn link_stages(&mut self, gl: &Context, vert: &str, frag: &str) -> Result<(Shader, Vec<u64>)> {
+        let mut stages = Vec::new();
+        for (source, shader_type) in [(vert, ShaderType::Vertex), (frag, ShaderType::Fragment)] {
+            let key = stage_key(source, shader_type).ok_or_else(|| {
+                eyre!("could not resolve the includes of the {shader_type} shader")
+            })?;
+            if !self.stages.contains_key(&key) {
+                let (shader, diagnostics) =
+                    shader::compile_stage(gl, source, shader_type, Path::new(INCLUDE_DIR))?;
+                self.stages.insert(key, CompiledStage { shader, diagnostics });
+            } else {
+                debug!("reusing compiled {shader_type} shader {key:016x}");
+            }
+            stages.push(key);
+        }
+
+        let program =
+            unsafe { gl.create_program() }.map_err(|e| eyre!("could not create program: {e}"))?;
+        unsafe {
+            for key in &stages {
+                gl.attach_shader(program, self.stages[key].shader);
+            }
+            if let Some((_, fns)) = &self.binaries {
+                fns.set_retrievable(program);
+            }
+            gl.link_program(program);
+            // The stages are kept for the next program, which attaches them again
+            for key in &stages {
+                gl.detach_shader(program, self.stages[key].shader);
+            }
+            if !gl.get_program_link_status(program) {
+                let log = gl.get_program_info_log(program);
+                gl.delete_program(program);
+                return Err(eyre!("shader program linking failed:\n{log}"));
+            }
+        }
+        Ok((Shader::new(program), stages))
+    }
+}
+
+/// The linked program, with the warnings the compiler reported for the vertex and fragment shader
+fn link(
+    gl: &Context,
+    vert: &str,
+    frag: &str,
+) -> Result<(Shader, Vec<ShaderDiagnostic>, Vec<ShaderDiagnostic>)> {
+    let builder = ShaderBuilder::new(gl)
+        .add_shader_source(vert, ShaderType::Vertex)?
+        .add_shader_source(frag, ShaderType::Fragment)?;
+    let vert_diagnostics = builder.diagnostics(ShaderType::Vertex);
+    let frag_diagnostics = builder.diagnostics(ShaderType::Fragment);
+    Ok((builder.link()?, vert_diagnostics, frag_diagnostics))
+}
@@ -0,0 +1,343 @@
+use std::ops::Range;
+
+use ahash::AHashMap;
+use bevy_ecs::system::Resource;
+
+use crate::glsl::{tokenize, Token, TokenKind};
+use crate::shader::{Severity, ShaderDiagnostic};
+
+/// Extensions custom shaders may enable, as none of them let a shader run longer or write memory
+pub const ALLOWED_EXTENSIONS: [&str; 3] = [
+    "GL_ARB_explicit_attrib_location",
+    "GL_ARB_separate_shader_objects",
+    "GL_ARB_shading_language_420pack",
+];
+
+/// Built-in functions that read from a texture
+const TEXTURE_FETCHES: [&str; 7] = [
+    "texelFetch",
+    "texture",
+    "textureGather",
+    "textureGrad",
+    "textureLod",
+    "textureOffset",
+    "textureProj",
+];
+
+/// How much work a custom shader may do, checked before it is compiled
+///
+/// The checks are heuristics on the source rather than a guarantee, and exist so a mistake in a
+/// shader is caught before it can hang the GPU.
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShaderLimits {
+    /// Iterations of a loop, counting the iterations of the loops around it
+    pub max_loop_iterations: u64,
+    /// Texture reads per vertex or pixel, counting the iterations of the loops they are in
+    pub max_texture_fetches: u64,
+}
+
+impl Default for ShaderLimits {
+    fn default() -> Self {
+        Self { max_loop_iterations: 1024, max_texture_fetches: 64 }
+    }
+}
+
+/// A `for` loop that was checked, and how often its body runs
+struct Loop {
+    body: Range<usize>,
+    iterations: u64,
+}
+
+/// Check custom shader source against the limits
+///
+/// Returns an error for each `while` or `do` loop, each `for` loop without a constant bound or
+/// with too many iterations, too many texture reads and each extension that is not in
+/// [`ALLOWED_EXTENSIONS`]. A shader with errors should not be compiled.
+pub fn check_limits(source: &str, limits: &ShaderLimits) -> Vec<ShaderDiagnostic> {
+    let code = strip_comments(source);
+    let tokens = tokenize(&code);
+    let constants = constants(&code, &tokens);
+    let line_of = |offset: usize| code[..offset].matches('\n').count() as u32 + 1;
+    let error = |offset: usize, message: String| ShaderDiagnostic {
+        severity: Severity::Error,
+        line: Some(line_of(offset)),
+        column: None,
+        message,
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut loops: Vec<Loop> = Vec::new();
+    let mut fetches = 0;
+    for token in &tokens {
+        let text = &code[token.range.clone()];
+        let start = token.range.start;
+        // Loops are found in order, so the last one around an offset is the innermost
+        let enclosing = |offset: usize| {
+            loops.iter().rev().find(|l| l.body.contains(&offset)).map_or(1, |l| l.iterations)
+        };
+        match (token.kind, text) {
+            (TokenKind::Preprocessor, _) => {
+                let separator = |c: char| c.is_whitespace() || c == ':';
+                let words: Vec<_> = text.split(separator).filter(|w| !w.is_empty()).collect();
+                if let ["#extension", name, ..] = words[..] {
+                    if !ALLOWED_EXTENSIONS.contains(&name) {
+                        let message = format!("extension {name} is not allowed in custom shaders");
+                        diagnostics.push(error(start, message));
+                    }
+                }
+            }
+            (TokenKind::Keyword, "while" | "do") => {
+                // The `while` of a `do` loop has been reported with the `do`
+                if text == "while" && is_do_while(&code[..start]) {
+                    continue;
+                }
+                let message = format!(
+                    "{text} loops are not allowed in custom shaders, use a for loop with a \
+                     constant bound"
+                );
+                diagnostics.push(error(start, message));
+            }
+            (TokenKind::Keyword, "for") => {
+                let outer = enclosing(start);
+                match parse_for(&code, token.range.end, &constants) {
+                    Ok((iterations, body)) => {
+                        let iterations = iterations.saturating_mul(outer);
+                        if iterations > limits.max_loop_iterations {
+                            let message = format!(
+                                "loop runs {iterations} times, more than the limit of {}",
+                                limits.max_loop_iterations
+                            );
+                            diagnostics.push(error(start, message));
+                        }
+                        loops.push(Loop { body, iterations });
+                    }
+                    Err(message) => {
+                        diagnostics.push(error(start, message.to_owned()));
+                        // Count what is inside as if it ran once, to keep checking the rest
+                        if let Some(body) = for_body(&code, token.range.end) {
+                            loops.push(Loop { body, iterations: outer });
+                        }
+                    }
+                }
+            }
+            (TokenKind::Builtin, _) if TEXTURE_FETCHES.contains(&text) => {
+                if !code[token.range.end..].trim_start().starts_with('(') {
+                    continue;
+                }
+                let before = fetches;
+                fetches += enclosing(start);
+                if before <= limits.max_texture_fetches && fetches > limits.max_texture_fetches {
+                    let message = format!(
+                        "shader reads textures {fetches} or more times, more than the limit of {}",
+                        limits.max_texture_fetches
+                    );
+                    diagnostics.push(error(start, message));
+                }
+            }
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+/// Whether the source before a `while` ends with the body of a `do` loop
+fn is_do_while(before: &str) -> bool {
+    let before = before.trim_end();
+    if !before.ends_with('}') {
+        return false;
+    }
+    let Some(open) = matching_open(before) else {
+        return false;
+    };
+    before[..open].trim_end().ends_with("do")
+}
+
+/// Offset of the `{` that the `}` at the end of `code` closes
+fn matching_open(code: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in code.char_indices().rev() {
+        match c {
+            '}' => depth += 1,
+            '{' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replace comments with spaces, keeping line breaks so lines are counted the same
+fn strip_comments(source: &str) -> String {
+    tokenize(source)
+        .into_iter()
+        .map(|token| {
+            let text = &source[token.range];
+            if token.kind == TokenKind::Comment {
+                text.chars().map(|c| if c == '\n' { '\n' } else { ' ' }).collect()
+            } else {
+                text.to_owned()
+            }
+        })
+        .collect()
+}
+
+/// Values of `#define`s and `const` variables that are plain numbers
+fn constants(code: &str, tokens: &[Token]) -> AHashMap<String, f64> {
+    let mut constants = AHashMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let text = &code[token.range.clone()];
+        match token.kind {
+            TokenKind::Preprocessor => {
+                if let ["#define", name, value] = text.split_whitespace().collect::<Vec<_>>()[..] {
+                    if let Some(value) = number(value) {
+                        constants.insert(name.to_owned(), value);
+                    }
+                }
+            }
+            TokenKind::Keyword if text == "const" => {
+                let mut significant = tokens[i + 1..].iter().filter(|t| t.kind != TokenKind::Other);
+                let (Some(_), Some(name)) = (significant.next(), significant.next()) else {
+                    continue;
+                };
+                let rest = &code[name.range.end..];
+                let Some(value) = rest.trim_start().strip_prefix('=') else {
+                    continue;
+                };
+                let value = value.split(';').next().unwrap_or_default();
+                if let Some(value) = number(value.trim()) {
+                    constants.insert(code[name.range.clone()].to_owned(), value);
+                }
+            }
+            _ => {}
+        }
+    }
+    constants
+}
+
+/// A number literal, without suffixes such as `u` and `f`
+fn number(literal: &str) -> Option<f64> {
+    let literal = literal.trim_end_matches(['u', 'U', 'f', 'F']);
+    match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok().map(|n| n as f64),
+        None => literal.parse().ok(),
+    }
+}
+
+fn value(expression: &str, constants: &AHashMap<String, f64>) -> Option<f64> {
+    let expression = expression.trim();
+    let (negative, expression) = match expression.strip_prefix('-') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, expression),
+    };
+    let value = number(expression).or_else(|| constants.get(expression).copied())?;
+    Some(if negative { -value } else { value })
+}
+
+/// Offset of the `)` that closes the `(` at `open`
+fn matching_paren(code: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in code[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The header of the `for` loop whose keyword ends at `after_for`, and where it ends
+fn for_header(code: &str, after_for: usize) -> Option<(&str, usize)> {
+    let open = after_for + code[after_for..].find('(')?;
+    let close = matching_paren(code, open)?;
+    Some((&code[open + 1..close], close + 1))
+}
+
+/// The body of the `for` loop whose keyword ends at `after_for`
+fn for_body(code: &str, after_for: usize) -> Option<Range<usize>> {
+    let (_, after_header) = for_header(code, after_for)?;
+    let rest = &code[after_header..];
+    let start = after_header + (rest.len() - rest.trim_start().len());
+    if code[start..].starts_with('{') {
+        let mut depth = 0;
+        for (i, c) in code[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(start..start + i + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(start..code.len())
+    } else {
+        let end = code[start..].find(';').map_or(code.len(), |end| start + end + 1);
+        Some(start..end)
+    }
+}
+
+/// How many times a `for` loop runs, and its body, or why that is not known
+fn parse_for(
+    code: &str,
+    after_for: usize,
+    constants: &AHashMap<String, f64>,
+) -> Result<(u64, Range<usize>), &'static str> {
+    const NOT_CONSTANT: &str =
+        "loop bounds have to be constants, so the number of iterations is known";
+
+    let (header, _) = for_header(code, after_for).ok_or("for loop without a header")?;
+    let body = for_body(code, after_for).ok_or("for loop without a body")?;
+    let parts: Vec<_> = header.split(';').collect();
+    let [init, condition, step] = parts[..] else {
+        return Err("for loop header has to have an initializer, a condition and a step");
+    };
+    if condition.trim().is_empty() {
+        return Err("for loop without a condition never ends");
+    }
+
+    let start = init.split('=').nth(1).and_then(|v| value(v, constants)).ok_or(NOT_CONSTANT)?;
+    let operators = ["<=", ">=", "!=", "<", ">"];
+    let (operator, at) = operators
+        .iter()
+        .filter_map(|op| condition.find(op).map(|at| (*op, at)))
+        .min_by_key(|&(op, at)| (at, std::cmp::Reverse(op.len())))
+        .ok_or(NOT_CONSTANT)?;
+    let (left, right) = (&condition[..at], &condition[at + operator.len()..]);
+    // The bound is on either side of the comparison
+    let end = value(right, constants).or_else(|| value(left, constants)).ok_or(NOT_CONSTANT)?;
+
+    let step = step.trim();
+    let increment = if step.ends_with("++") || step.starts_with("++") {
+        1.0
+    } else if step.ends_with("--") || step.starts_with("--") {
+        -1.0
+    } else if let Some((_, by)) = step.split_once("+=") {
+        value(by, constants).ok_or(NOT_CONSTANT)?
+    } else if let Some((_, by)) = step.split_once("-=") {
+        -value(by, constants).ok_or(NOT_CONSTANT)?
+    } else {
+        return Err(NOT_CONSTANT);
+    };
+    if increment == 0.0 || (start != end && (end - start) * increment < 0.0) {
+        return Err("for loop never reaches its bound");
+    }
+
+    let inclusive = matches!(operator, "<=" | ">=");
+    let mut iterations = ((end - start) / increment).abs().ceil();
+    if inclusive && ((end - start) / increment).fract() == 0.0 {
+        iterations += 1.0;
+    }
+    Ok((iterations as u64, body))
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use bevy_ecs::prelude::*;
+
+/// Folder the built-in shaders are read from when they are reloaded
+pub const SHADERS_DIR: &str = "shaders";
+
+/// Watches the shader files in a folder and its subfolders for changes
+///
+/// Inserted as a resource to reload the built-in shaders when their files change. The folder is
+/// checked by comparing modification times, at most once every `interval`.
+#[derive(Resource, Debug)]
+pub struct ShaderWatcher {
+    dir: PathBuf,
+    modified: BTreeMap<PathBuf, SystemTime>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Start watching `dir`, treating the files that are there now as unchanged
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        let dir = dir.into();
+        Self {
+            modified: scan(&dir),
+            dir,
+            interval: Self::DEFAULT_INTERVAL,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether a shader file was changed, added or removed, if the interval has passed since the
+    /// folder was last checked
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < self.interval {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        self.check()
+    }
+
+    /// Whether a shader file was changed, added or removed since the folder was last checked
+    pub fn check(&mut self) -> bool {
+        let modified = scan(&self.dir);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+/// Modification times of the `.glsl` files in a folder and its subfolders
+fn scan(dir: &Path) -> BTreeMap<PathBuf, SystemTime> {
+    let mut modified = BTreeMap::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|e| e == "glsl") {
+                if let Ok(time) = metadata.modified() {
+                    modified.insert(path, time);
+                }
+            }
+        }
+    }
+    modified
+}
@@ -0,0 +1,215 @@
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::{Interpolation, Property};
+use nalgebra_glm as glm;
+
+/// Must match `MAX_JOINTS` in the vertex shaders
+pub const MAX_JOINTS: usize = 64;
+
+/// Joint hierarchy, bind pose and animations of a skinned glTF mesh
+pub struct SkinData {
+    pub nodes: Vec<SkinNode>,
+    /// Node indices ordered so that parents always come before their children
+    order: Vec<usize>,
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<glm::Mat4>,
+    pub animations: Vec<SkeletalAnimation>,
+}
+
+pub struct SkinNode {
+    pub parent: Option<usize>,
+    pub rest: NodeTransform,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct NodeTransform {
+    pub translation: glm::Vec3,
+    pub rotation: glm::Quat,
+    pub scale: glm::Vec3,
+}
+
+impl NodeTransform {
+    pub fn matrix(&self) -> glm::Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&self.rotation)
+            * glm::scaling(&self.scale)
+    }
+}
+
+pub struct SkeletalAnimation {
+    pub name: String,
+    pub duration: f32,
+    pub channels: Vec<Channel>,
+}
+
+pub struct Channel {
+    pub node: usize,
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+    pub step: bool,
+}
+
+pub enum ChannelValues {
+    Translation(Vec<glm::Vec3>),
+    Rotation(Vec<glm::Quat>),
+    Scale(Vec<glm::Vec3>),
+}
+
+impl Channel {
+    /// Find the keyframes surrounding the given time and how far between them it is
+    fn keyframes(&self, time: f32) -> (usize, usize, f32) {
+        let last = self.times.len() - 1;
+        let i = self.times.partition_point(|&t| t <= time);
+        if i == 0 {
+            (0, 0, 0.0)
+        } else if i > last {
+            (last, last, 0.0)
+        } else {
+            let (a, b) = (self.times[i - 1], self.times[i]);
+            let t = if self.step || b <= a { 0.0 } else { (time - a) / (b - a) };
+            (i - 1, i, t)
+        }
+    }
+
+    fn apply(&self, time: f32, transform: &mut NodeTransform) {
+        if self.times.is_empty() {
+            return;
+        }
+
+        let (a, b, t) = self.keyframes(time);
+        match &self.values {
+            ChannelValues::Translation(v) => transform.translation = glm::lerp(&v[a], &v[b], t),
+            ChannelValues::Rotation(v) => transform.rotation = glm::quat_slerp(&v[a], &v[b], t),
+            ChannelValues::Scale(v) => transform.scale = glm::lerp(&v[a], &v[b], t),
+        }
+    }
+}
+
+impl SkinData {
+    pub fn from_gltf(
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        skin: &gltf::Skin,
+    ) -> Self {
+        let mut nodes: Vec<_> = document
+            .nodes()
+            .map(|node| {
+                let (t, r, s) = node.transform().decomposed();
+                SkinNode {
+                    parent: None,
+                    rest: NodeTransform {
+                        translation: t.into(),
+                        rotation: glm::quat(r[0], r[1], r[2], r[3]),
+                        scale: s.into(),
+                    },
+                }
+            })
+            .collect();
+        let children: Vec<Vec<_>> =
+            document.nodes().map(|node| node.children().map(|c| c.index()).collect()).collect();
+        for (parent, children) in children.iter().enumerate() {
+            for &child in children {
+                nodes[child].parent = Some(parent);
+            }
+        }
+
+        let mut order = Vec::with_capacity(nodes.len());
+        let mut stack: Vec<_> = (0..nodes.len()).filter(|&i| nodes[i].parent.is_none()).collect();
+        while let Some(i) = stack.pop() {
+            order.push(i);
+            stack.extend(&children[i]);
+        }
+
+        let joints: Vec<_> = skin.joints().map(|j| j.index()).collect();
+
+        let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+        let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(glm::Mat4::from).collect(),
+            None => vec![glm::Mat4::identity(); joints.len()],
+        };
+
+        let animations = document
+            .animations()
+            .enumerate()
+            .map(|(i, animation)| {
+                let channels: Vec<_> = animation
+                    .channels()
+                    .filter_map(|channel| {
+                        let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+                        let times: Vec<f32> = reader.read_inputs()?.collect();
+                        let interpolation = channel.sampler().interpolation();
+
+                        // Cubic spline outputs are stored as (in-tangent, value, out-tangent)
+                        let values = match (reader.read_outputs()?, channel.target().property()) {
+                            (ReadOutputs::Translations(v), Property::Translation) => {
+                                ChannelValues::Translation(spline_values(
+                                    v.map(Into::into),
+                                    interpolation,
+                                ))
+                            }
+                            (ReadOutputs::Rotations(v), Property::Rotation) => {
+                                let v = v.into_f32().map(|r| glm::quat(r[0], r[1], r[2], r[3]));
+                                ChannelValues::Rotation(spline_values(v, interpolation))
+                            }
+                            (ReadOutputs::Scales(v), Property::Scale) => ChannelValues::Scale(
+                                spline_values(v.map(Into::into), interpolation),
+                            ),
+                            _ => return None,
+                        };
+
+                        Some(Channel {
+                            node: channel.target().node().index(),
+                            times,
+                            values,
+                            step: interpolation == Interpolation::Step,
+                        })
+                    })
+                    .collect();
+
+                let duration =
+                    channels.iter().filter_map(|c| c.times.last()).fold(0.0f32, |a, &b| a.max(b));
+                let name = animation.name().map_or_else(|| format!("Animation {i}"), str::to_owned);
+                SkeletalAnimation { name, duration, channels }
+            })
+            .collect();
+
+        Self { nodes, order, joints, inverse_bind_matrices, animations }
+    }
+
+    /// Compute the joint matrices for the given animation and time, or the rest pose
+    pub fn joint_matrices(&self, animation: Option<(usize, f32)>, out: &mut Vec<glm::Mat4>) {
+        let mut locals: Vec<_> = self.nodes.iter().map(|n| n.rest).collect();
+        if let Some((animation, time)) =
+            animation.and_then(|(i, t)| Some((self.animations.get(i)?, t)))
+        {
+            for channel in &animation.channels {
+                channel.apply(time, &mut locals[channel.node]);
+            }
+        }
+
+        let mut globals = vec![glm::Mat4::identity(); self.nodes.len()];
+        for &i in &self.order {
+            let local = locals[i].matrix();
+            globals[i] = match self.nodes[i].parent {
+                Some(parent) => globals[parent] * local,
+                None => local,
+            };
+        }
+
+        out.clear();
+        out.extend(
+            self.joints
+                .iter()
+                .zip(&self.inverse_bind_matrices)
+                .take(MAX_JOINTS)
+                .map(|(&joint, inverse_bind)| globals[joint] * inverse_bind),
+        );
+    }
+}
+
+fn spline_values<T>(values: impl Iterator<Item = T>, interpolation: Interpolation) -> Vec<T> {
+    if interpolation == Interpolation::CubicSpline {
+        values.skip(1).step_by(3).collect()
+    } else {
+        values.collect()
+    }
+}
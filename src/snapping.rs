@@ -0,0 +1,38 @@
+/// Steps that positions, rotations and scales are rounded to while snapping
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Snapping {
+    /// Grid size in meters, which is also the distance the selection moves with each nudge
+    pub position: f32,
+    /// Angle in degrees
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl Default for Snapping {
+    fn default() -> Self {
+        Self { position: 0.25, rotation: 15.0, scale: 0.1 }
+    }
+}
+
+impl Snapping {
+    pub fn position(&self, value: f32) -> f32 {
+        snap(value, self.position)
+    }
+
+    pub fn rotation(&self, degrees: f32) -> f32 {
+        snap(degrees, self.rotation)
+    }
+
+    pub fn scale(&self, value: f32) -> f32 {
+        snap(value, self.scale)
+    }
+}
+
+/// Round a value to the nearest multiple of a step, leaving it as is if the step is not positive
+pub fn snap(value: f32, step: f32) -> f32 {
+    if step > 0.0 {
+        (value / step).round() * step
+    } else {
+        value
+    }
+}
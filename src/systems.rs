@@ -1,119 +1,1239 @@
+use std::fs;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bevy_ecs::prelude::*;
 use glow::{Context, HasContext, PixelPackData};
 use nalgebra_glm as glm;
-use tracing::debug;
-use winit::event::{MouseButton, VirtualKeyCode};
+use tracing::{debug, info, warn};
+use winit::event::{ElementState, VirtualKeyCode};
+use winit::event_loop::EventLoopProxy;
+use winit::window::CursorGrabMode;
 
-use crate::components::{Mesh, Position, Selected, StencilId, TransformBundle};
-use crate::resources::{Camera, Input, ModelLoader, RenderState, Time, WinitWindow};
+use crate::app::SimulationSchedule;
+use crate::bounds::Aabb;
+use crate::commands::replace_scene_texture;
+use crate::components::{
+    AnimationClip, AnimationPlayer, CameraComponent, FollowPath, GlobalTransform, Hidden,
+    Interpolation, Locked, Mesh, Oscillator, PointLight, Position, Rotation, Rotator, Scale,
+    Selected, ShaderParams, Skeleton, TransformBundle,
+};
+use crate::config::{self, EditorConfig};
+use crate::editor::UserEvent;
+use crate::entity_pool::{self, Pooled};
+use crate::gl_state::GlState;
+use crate::input_map::{Action, Binding, InputMap};
+use crate::picking::{PickRequest, Picker};
+use crate::placement::{self, DepthSamples, SurfaceHit};
+use crate::registry::ComponentRegistry;
+use crate::renderer::{self, model_matrix};
+use crate::resources::{
+    BoxSelection, Camera, CameraFlight, CameraPose, DebugDraw, FixedTime, Input,
+    InspectedComponents, KeyEvent, Lighting, LookDev, LookDevEnvironment, LookDevSession,
+    ModelLoader, MouseEvent, Notifications, Outliner, Placement, ProjectionMode, RenderSettings,
+    RenderState, SceneView, ShaderGlobals, TerrainBrush, Time, UiState, ViewBookmarks, ViewLayout,
+    Viewport, WinitWindow, ENTITY_ID, GEOMETRY_PASS, VIEW_SLOTS,
+};
+use crate::shader_reload::ShaderWatcher;
+use crate::terrain::Terrain;
+use crate::updates::UpdateChecker;
+use crate::usage::UsageLog;
+use crate::variables::{self, Bindings, SceneVariables};
+use crate::{image_export, outliner};
 
-pub fn move_camera(input: Res<Input>, mut camera: ResMut<Camera>, time: Res<Time>) {
-    let front = camera.front;
-    let up = camera.up;
-    const CAMERA_SPEED: f32 = 5.0;
-    const CAMERA_SENSITIVITY: f64 = 0.3;
+/// Apply the key and mouse events sent since the previous frame to `Input`, or bind the first one
+/// to the action being rebound
+pub fn update_input(
+    mut input: ResMut<Input>,
+    mut input_map: ResMut<InputMap>,
+    mut state: ResMut<UiState>,
+    mut key_events: EventReader<KeyEvent>,
+    mut mouse_events: EventReader<MouseEvent>,
+) {
+    for &event in key_events.iter() {
+        if !rebind(&mut state, &mut input_map, &input, Binding::Key(event.key), event.state) {
+            input.handle_key_event(event);
+        }
+    }
+    for &event in mouse_events.iter() {
+        if !rebind(&mut state, &mut input_map, &input, Binding::Mouse(event.button), event.state) {
+            input.handle_mouse_event(event);
+        }
+    }
+}
 
-    let speed_modifier =
-        if input.get_key_press_continuous(VirtualKeyCode::LShift) { 3.0 } else { 1.0 };
+/// Bind a key or mouse button to the action being rebound, returning whether the press was used
+/// for it and should not reach the input state
+fn rebind(
+    state: &mut UiState,
+    input_map: &mut InputMap,
+    input: &Input,
+    binding: Binding,
+    element_state: ElementState,
+) -> bool {
+    use VirtualKeyCode::{LControl, LShift, RControl, RShift};
 
-    camera.yaw += input.mouse_delta.0 * CAMERA_SENSITIVITY;
-    camera.pitch -= input.mouse_delta.1 * CAMERA_SENSITIVITY;
-    camera.pitch = camera.pitch.clamp(-89.0, 89.0);
+    let Some(action) = state.rebinding else {
+        return false;
+    };
+    let modifier = matches!(binding, Binding::Key(LShift | RShift | LControl | RControl));
+    let new_binding = match (binding, element_state) {
+        // Wait for another key, so a modifier can be combined with it
+        (_, ElementState::Pressed) if modifier => None,
+        (_, ElementState::Released) if modifier => Some(binding),
+        (Binding::Key(key), ElementState::Pressed) if input.ctrl_held() && input.shift_held() => {
+            Some(Binding::CtrlShiftKey(key))
+        }
+        (Binding::Key(key), ElementState::Pressed) if input.ctrl_held() => {
+            Some(Binding::CtrlKey(key))
+        }
+        (Binding::Key(key), ElementState::Pressed) if input.shift_held() => {
+            Some(Binding::ShiftKey(key))
+        }
+        (_, ElementState::Pressed) => Some(binding),
+        (_, ElementState::Released) => None,
+    };
+    let Some(new_binding) = new_binding else {
+        return false;
+    };
+    state.rebinding = None;
+    input_map.set(action, new_binding);
+    if let Err(e) = input_map.save(config::config_path()) {
+        warn!("could not save input bindings: {e}");
+    }
+    element_state == ElementState::Pressed
+}
 
-    let yaw_radians = camera.yaw.to_radians();
-    let pitch_radians = camera.pitch.to_radians();
-    camera.front = glm::normalize(&glm::vec3(
-        (yaw_radians.cos() * pitch_radians.cos()) as f32,
-        pitch_radians.sin() as f32,
-        (yaw_radians.sin() * pitch_radians.cos()) as f32,
-    ));
+/// Forget the presses and releases of the frame, keeping the keys that are still held
+pub fn clear_input(mut input: ResMut<Input>) {
+    input.update_after_frame();
+}
 
-    let speed = CAMERA_SPEED * time.delta_seconds() * speed_modifier;
-    if input.get_key_press_continuous(VirtualKeyCode::W) {
-        camera.pos += speed * front;
+pub fn advance_time(mut time: ResMut<Time>) {
+    time.next_frame();
+}
+
+/// Open and close the command palette and the keyboard shortcuts
+pub fn overlay_actions(input: Res<Input>, input_map: Res<InputMap>, mut state: ResMut<UiState>) {
+    if input_map.pressed(&input, Action::CommandPalette) {
+        state.command_palette_open = !state.command_palette_open;
+        state.palette_query.clear();
+        state.palette_selected = 0;
     }
-    if input.get_key_press_continuous(VirtualKeyCode::S) {
-        camera.pos -= speed * front;
+    if input_map.pressed(&input, Action::ShowShortcuts) {
+        state.shortcuts_open = !state.shortcuts_open;
     }
-    if input.get_key_press_continuous(VirtualKeyCode::A) {
-        camera.pos -= speed * glm::normalize(&glm::cross(&front, &up));
+}
+
+/// Quit, and capture the cursor while looking around
+pub fn window_actions(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    window: Res<WinitWindow>,
+    event_loop_proxy: NonSend<EventLoopProxy<UserEvent>>,
+    mut state: ResMut<UiState>,
+) {
+    if input_map.pressed(&input, Action::Quit) {
+        // The event loop exits and sends `LoopDestroyed` back to the game loop
+        let _ = event_loop_proxy.send_event(UserEvent::Quit);
     }
-    if input.get_key_press_continuous(VirtualKeyCode::D) {
-        camera.pos += speed * glm::normalize(&glm::cross(&front, &up));
+
+    if input_map.pressed(&input, Action::LookAround) {
+        window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+            .unwrap();
+        window.set_cursor_visible(false);
+        state.camera_focused = true;
+    }
+    // Checked after the press, as both can happen in one frame
+    if input_map.released(&input, Action::LookAround) && state.camera_focused {
+        window.set_cursor_grab(CursorGrabMode::None).unwrap();
+        window.set_cursor_visible(true);
+        state.camera_focused = false;
+    }
+}
+
+pub fn move_camera(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    config: Res<EditorConfig>,
+    mut camera: ResMut<Camera>,
+    flight: Res<CameraFlight>,
+    time: Res<Time>,
+    viewport: Res<Viewport>,
+) {
+    const KEYBOARD_LOOK_SPEED: f64 = 90.0;
+
+    let prefs = config.camera;
+    let speed_modifier = if input_map.held(&input, Action::MoveFast) { 3.0 } else { 1.0 };
+
+    let invert = if prefs.invert_y { -1.0 } else { 1.0 };
+    camera.yaw += input.mouse_delta.0 * prefs.look_sensitivity;
+    camera.pitch -= input.mouse_delta.1 * prefs.look_sensitivity * invert;
+
+    let look = KEYBOARD_LOOK_SPEED * time.delta_seconds() as f64;
+    if input_map.held(&input, Action::LookLeft) {
+        camera.yaw -= look;
+    }
+    if input_map.held(&input, Action::LookRight) {
+        camera.yaw += look;
+    }
+    if input_map.held(&input, Action::LookUp) {
+        camera.pitch += look;
     }
-    if input.get_key_press_continuous(VirtualKeyCode::Space) {
+    if input_map.held(&input, Action::LookDown) {
+        camera.pitch -= look;
+    }
+    camera.pitch = camera.pitch.clamp(-90.0, 90.0);
+    camera.front = camera.pose().front();
+
+    // Moving would fight the camera flight
+    if flight.is_active() {
+        return;
+    }
+
+    let speed = prefs.move_speed * time.delta_seconds() * speed_modifier;
+    let (front, right, up) = (camera.front, camera.right(), camera.up);
+    if camera.mode == ProjectionMode::Orthographic {
+        // Moving forward does not change what an orthographic camera sees, so zoom instead
+        let zoom = match (
+            input_map.held(&input, Action::MoveForward),
+            input_map.held(&input, Action::MoveBackward),
+        ) {
+            (true, false) => -speed,
+            (false, true) => speed,
+            _ => 0.0,
+        };
+        if zoom != 0.0 {
+            camera.ortho_height = (camera.ortho_height + zoom).max(0.1);
+            camera.update_projection(viewport.width, viewport.height);
+        }
+    } else {
+        if input_map.held(&input, Action::MoveForward) {
+            camera.pos += speed * front;
+        }
+        if input_map.held(&input, Action::MoveBackward) {
+            camera.pos -= speed * front;
+        }
+    }
+    if input_map.held(&input, Action::MoveLeft) {
+        camera.pos -= speed * right;
+    }
+    if input_map.held(&input, Action::MoveRight) {
+        camera.pos += speed * right;
+    }
+    if input_map.held(&input, Action::MoveUp) {
         camera.pos += speed * up;
     }
-    if input.get_key_press_continuous(VirtualKeyCode::LControl) {
+    if input_map.held(&input, Action::MoveDown) {
         camera.pos -= speed * up;
     }
 }
 
+/// Whether the scene can be changed, which it can not in view-only mode
+pub fn editing_allowed(state: Res<UiState>) -> bool {
+    !state.view_only
+}
+
+/// Whether clicking in the scene view selects, which it does unless the placement brush stamps,
+/// the terrain brush sculpts or the focus of the depth of field is being picked
+pub fn clicks_select(
+    placement: Res<Placement>,
+    terrain_brush: Res<TerrainBrush>,
+    state: Res<UiState>,
+) -> bool {
+    !state.picking_focus && (!(placement.brush || terrain_brush.active) || state.view_only)
+}
+
+/// Spawn the placement model in front of the camera, or on the surface under the mouse when
+/// placing on surfaces. With the placement brush, clicking in the scene view stamps it as well.
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_object(
+    gl: NonSend<Arc<Context>>,
+    viewport: Res<Viewport>,
+    render_state: Res<RenderState>,
     camera: Res<Camera>,
+    layout: Res<ViewLayout>,
     input: Res<Input>,
+    input_map: Res<InputMap>,
     model_loader: Res<ModelLoader>,
+    placement: Res<Placement>,
     mut commands: Commands,
 ) {
-    if input.get_key_press(VirtualKeyCode::E) {
-        let spawn_pos = camera.pos + camera.front * 3.0;
-        let position = Position::new(spawn_pos.x, spawn_pos.y, spawn_pos.z);
+    let spawn = input_map.pressed(&input, Action::SpawnObject);
+    let stamp = placement.brush && input_map.pressed(&input, Action::Select);
+    if !spawn && !stamp {
+        return;
+    }
+
+    let Some(vao) = model_loader.get(&placement.model) else {
+        warn!("can not spawn {:?}, the model is not loaded", placement.model);
+        return;
+    };
+    let mesh = Mesh::from(vao);
+
+    let hit = (stamp || placement.on_surfaces)
+        .then(|| surface_under_mouse(&gl, &viewport, &render_state, &camera, &layout, &input))
+        .flatten();
+    let (position, rotation) = match hit {
+        Some(hit) => {
+            let (rotation, up) = if placement.align_to_normal {
+                (placement::align_to_normal(hit.normal), hit.normal)
+            } else {
+                (Rotation::default(), glm::vec3(0.0, 1.0, 0.0))
+            };
+            // Rest the bottom of the model on the surface rather than sinking its origin into it
+            (hit.position - up * mesh.aabb.min.y, rotation)
+        }
+        None if !spawn => {
+            debug!("found no surface to place {:?} on", placement.model);
+            return;
+        }
+        None => (camera.pos + camera.front * 3.0, Rotation::default()),
+    };
+    let position = Position::new(position.x, position.y, position.z);
+    debug!("spawning {:?} at {:?}", placement.model, position);
+
+    commands.add(move |world: &mut World| {
+        let transform = TransformBundle { position, rotation, ..Default::default() };
+        entity_pool::spawn(world, mesh, transform);
+    });
+}
+
+/// Sculpt or paint the terrain under the mouse while the terrain brush is active and the select
+/// button is held
+#[allow(clippy::too_many_arguments)]
+pub fn sculpt_terrain(
+    gl: NonSend<Arc<Context>>,
+    viewport: Res<Viewport>,
+    render_state: Res<RenderState>,
+    camera: Res<Camera>,
+    layout: Res<ViewLayout>,
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    time: Res<Time>,
+    mut terrain_brush: ResMut<TerrainBrush>,
+    mut terrains: Query<(&mut Terrain, &GlobalTransform), Without<Locked>>,
+) {
+    if !terrain_brush.active || !input_map.held(&input, Action::Select) {
+        return;
+    }
+    if input_map.pressed(&input, Action::Select) {
+        terrain_brush.stroke_height_set = false;
+    }
+    let Some(hit) = surface_under_mouse(&gl, &viewport, &render_state, &camera, &layout, &input)
+    else {
+        return;
+    };
+
+    // The terrain whose surface is nearest the hit, as the hit may be on something else
+    let radius = terrain_brush.brush.radius;
+    let nearest = terrains
+        .iter_mut()
+        .filter_map(|(terrain, transform)| {
+            let local = (glm::inverse(&transform.0) * hit.position.push(1.0)).xyz();
+            let scale = glm::Vec3::from_fn(|i, _| transform.0.fixed_view::<3, 1>(0, i).norm());
+            let distance = (terrain.height_at(local.x, local.z)? - local.y).abs() * scale.y;
+            (distance < radius).then_some((terrain, local, scale, distance))
+        })
+        .min_by(|a, b| a.3.total_cmp(&b.3));
+    let Some((mut terrain, local, scale, _)) = nearest else {
+        return;
+    };
+
+    // Flattening levels the terrain to the height where the stroke first reached it
+    if !terrain_brush.stroke_height_set {
+        terrain_brush.brush.height = local.y;
+        terrain_brush.stroke_height_set = true;
+    }
+    terrain.sculpt_scaled(&terrain_brush.brush, local.xz(), scale, time.delta_seconds());
+}
+
+/// Set the focus distance of the depth of field to the surface clicked in the scene view, while
+/// the focus is being picked
+#[allow(clippy::too_many_arguments)]
+pub fn pick_focus(
+    gl: NonSend<Arc<Context>>,
+    viewport: Res<Viewport>,
+    render_state: Res<RenderState>,
+    camera: Res<Camera>,
+    layout: Res<ViewLayout>,
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    mut settings: ResMut<RenderSettings>,
+    mut state: ResMut<UiState>,
+) {
+    if !state.picking_focus || !input_map.pressed(&input, Action::Select) {
+        return;
+    }
+    let Some(hit) = surface_under_mouse(&gl, &viewport, &render_state, &camera, &layout, &input)
+    else {
+        return;
+    };
+
+    let distance = glm::distance(&camera.pos, &hit.position);
+    settings.post_effects.depth_of_field.focus_distance = distance;
+    state.picking_focus = false;
+    debug!("set the focus distance to {distance:.2}");
+}
 
-        debug!("spawning a cube at {:?}", position);
+/// Follow the selected scene camera with the camera of the `ViewLayout`, for looking through it
+/// and its preview
+pub fn update_scene_camera(
+    viewport: Res<Viewport>,
+    mut layout: ResMut<ViewLayout>,
+    cameras: Query<(&CameraComponent, &GlobalTransform), (With<Selected>, Without<Pooled>)>,
+) {
+    let (width, height) = layout.scene_camera_size(viewport.width, viewport.height);
+    let camera = cameras
+        .iter()
+        .next()
+        .map(|(component, GlobalTransform(model))| component.camera(model, width, height));
+    layout.set_scene_camera(camera);
+}
 
-        let mesh = Mesh::from(model_loader.get("Cube").unwrap());
-        commands.spawn((mesh, TransformBundle { position, ..Default::default() }));
+/// Pass changed render settings on to the renderer, rebuilding the frame graph for a new shadow
+/// map size
+pub fn apply_render_settings(world: &mut World) {
+    let settings = *world.resource::<RenderSettings>();
+    let mut render_state = world.resource_mut::<RenderState>();
+    if render_state.reflections != settings.reflections {
+        render_state.reflections = settings.reflections;
+    }
+    if render_state.post_effects != settings.post_effects {
+        render_state.post_effects = settings.post_effects;
+    }
+    if render_state.shadows != settings.shadows {
+        render_state.shadows = settings.shadows;
+    }
+    let current = render_state.shadow_map_size();
+    if current == settings.shadow_map_size {
+        return;
+    }
+
+    let gl = world.non_send_resource::<Arc<Context>>().clone();
+    let size = settings.shadow_map_size;
+    match world.resource_mut::<RenderState>().set_shadow_map_size(&gl, size) {
+        Ok(()) => {
+            debug!("set the shadow map size to {size}");
+            replace_scene_texture(world);
+        }
+        Err(e) => {
+            warn!("could not set the shadow map size to {size}: {e}");
+            if let Some(mut notifications) = world.get_resource_mut::<Notifications>() {
+                notifications.error(format!("Could not use {size} shadow maps: {e}"));
+            }
+            // Keeps the failed size from being tried again every frame
+            world.resource_mut::<RenderSettings>().shadow_map_size = current;
+        }
     }
 }
 
+/// Surface under the mouse in whichever view it is over, from the depth buffer of the last frame
+fn surface_under_mouse(
+    gl: &Context,
+    viewport: &Viewport,
+    render_state: &RenderState,
+    camera: &Camera,
+    layout: &ViewLayout,
+    input: &Input,
+) -> Option<SurfaceHit> {
+    let (x, y) = input.mouse_pos;
+    let (x, y) = (x as i32, viewport.height as i32 - y as i32 - 1);
+    let views = layout.views(camera, viewport.width, viewport.height);
+    let (camera, rect) = views.into_iter().find(|(_, rect)| rect.contains(x, y))?;
+    // The neighboring pixels are needed for the normal
+    let inside = |v: i32, start: i32, len: i32| v > start && v < start + len - 1;
+    if !inside(x, rect.x, rect.width) || !inside(y, rect.y, rect.height) {
+        return None;
+    }
+
+    let mut pixels = [0u32; 9];
+    unsafe {
+        let fbo = render_state.frame_graph.framebuffer(GEOMETRY_PASS);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, fbo);
+        gl.read_pixels(
+            x - 1,
+            y - 1,
+            3,
+            3,
+            glow::DEPTH_STENCIL,
+            glow::UNSIGNED_INT_24_8,
+            PixelPackData::Slice(bytemuck::cast_slice_mut(&mut pixels)),
+        );
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    }
+    // The depth is in the upper 24 bits, above the stencil value
+    let depth = |pixel: u32| (pixel >> 8) as f32 / 0xFF_FFFF as f32;
+    let depths: DepthSamples =
+        std::array::from_fn(|row| std::array::from_fn(|col| depth(pixels[row * 3 + col])));
+    placement::surface_hit(camera, rect, x, y, &depths)
+}
+
+/// Entities that can be selected in the scene view, as the others are not drawn or are locked
+type Selectable = (Without<Pooled>, Without<Hidden>, Without<Locked>);
+
+/// Select the entity under the mouse, or start a box selection if there is none
+///
+/// With Shift held the entity is added to the selection, or taken out of it if it was selected.
+/// The entity ID under the mouse is read back without waiting for the GPU, so the selection
+/// changes a frame or so after the click.
+#[allow(clippy::too_many_arguments)]
 pub fn select_object(
     gl: NonSend<Arc<Context>>,
-    window: Res<WinitWindow>,
+    viewport: Res<Viewport>,
     input: Res<Input>,
+    input_map: Res<InputMap>,
     render_state: Res<RenderState>,
+    layout: Res<ViewLayout>,
+    mut picker: ResMut<Picker>,
+    mut box_selection: ResMut<BoxSelection>,
     already_selected: Query<Entity, With<Selected>>,
-    query: Query<(Entity, &StencilId)>,
+    query: Query<Entity, Selectable>,
     mut commands: Commands,
 ) {
-    if input.get_mouse_button_press(MouseButton::Left) {
-        for entity in &already_selected {
-            commands.entity(entity).remove::<Selected>();
+    if let Some(pick) = unsafe { picker.poll(&gl) } {
+        let PickRequest { position: (x, y), add } = pick.request;
+        if !add {
+            for entity in &already_selected {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+
+        // The ID is the index of the entity plus one, and 0 where no entity was drawn
+        let index = pick.id.checked_sub(1);
+        match index.and_then(|index| query.iter().find(|entity| entity.index() == index)) {
+            Some(entity) if add && already_selected.contains(entity) => {
+                commands.entity(entity).remove::<Selected>();
+                debug!("deselected entity {}", entity.index());
+            }
+            Some(entity) => {
+                commands.entity(entity).insert(Selected);
+                debug!("selected entity {}", entity.index());
+            }
+            None => {
+                debug!("found no object to select, starting a box selection");
+                *box_selection = BoxSelection { start: Some((x, y)), end: (x, y) };
+            }
         }
+    }
 
+    if input_map.pressed(&input, Action::Select) {
         let (x, y) = input.mouse_pos;
-        let window_height = window.inner_size().height;
-        let index = unsafe {
-            let mut bytes = [0; 4];
-            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(render_state.g_buffer));
-            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(render_state.g_rbo));
-            gl.read_pixels(
-                x as i32,
-                window_height as i32 - y as i32 - 1,
-                1,
-                1,
-                glow::DEPTH_STENCIL,
-                glow::UNSIGNED_INT_24_8,
-                PixelPackData::Slice(&mut bytes),
-            );
-            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-            let pixel = u32::from_ne_bytes(bytes);
-            (pixel & 0xFF) as usize
+        if x < 0.0 || y < 0.0 || x >= viewport.width as f64 || y >= viewport.height as f64 {
+            return;
+        }
+        // The preview shows the scene camera, which the IDs were not drawn from
+        if layout.in_preview(viewport.width, viewport.height, x, y) {
+            return;
+        }
+
+        let frame_graph = &render_state.frame_graph;
+        let Some(attachment) = frame_graph.color_attachment(GEOMETRY_PASS, ENTITY_ID) else {
+            return;
         };
+        let framebuffer = frame_graph.framebuffer(GEOMETRY_PASS);
+        let pixel = (x as i32, viewport.height as i32 - y as i32 - 1);
+        let request = PickRequest { position: (x, y), add: input.shift_held() };
+        if let Err(e) = unsafe { picker.start(&gl, framebuffer, attachment, pixel, request) } {
+            warn!("could not pick an object: {e}");
+        }
+    }
+}
 
-        let mut found = false;
-        for (entity, stencil_id) in &query {
-            if stencil_id.0 == index {
-                commands.entity(entity).insert(Selected);
-                found = true;
-                debug!("selected entity {}", entity.index());
-                break;
+type BoxQuery<'a> = (Entity, &'a Mesh, &'a GlobalTransform);
+
+/// Drag out a box selection, and select every entity whose bounds on screen touch the box when the
+/// mouse button is released
+#[allow(clippy::too_many_arguments)]
+pub fn box_select(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    viewport: Res<Viewport>,
+    camera: Res<Camera>,
+    layout: Res<ViewLayout>,
+    mut box_selection: ResMut<BoxSelection>,
+    meshes: Query<BoxQuery, Selectable>,
+    mut commands: Commands,
+) {
+    let Some(start) = box_selection.start else {
+        return;
+    };
+    box_selection.end = input.mouse_pos;
+    if input_map.held(&input, Action::Select) {
+        return;
+    }
+    let rect = box_selection.rect();
+    box_selection.start = None;
+    // Not dragged far enough to be more than a click in empty space
+    let Some((min, max)) = rect else {
+        return;
+    };
+
+    // The box selects in the view it was started in
+    let height = viewport.height as f64;
+    let (x, y) = (start.0 as i32, (height - start.1) as i32 - 1);
+    let views = layout.views(&camera, viewport.width, viewport.height);
+    let Some((camera, view)) = views.into_iter().find(|(_, rect)| rect.contains(x, y)) else {
+        return;
+    };
+    let to_ndc = |(x, y): (f64, f64)| {
+        let x = (x - view.x as f64) / view.width as f64;
+        let y = (height - y - view.y as f64) / view.height as f64;
+        glm::vec2(x as f32 * 2.0 - 1.0, y as f32 * 2.0 - 1.0)
+    };
+    // The Y axis points down in window coordinates and up in normalized device coordinates
+    let (top_left, bottom_right) = (to_ndc(min), to_ndc(max));
+    let box_min = glm::vec2(top_left.x, bottom_right.y);
+    let box_max = glm::vec2(bottom_right.x, top_left.y);
+
+    let view_projection = camera.projection * camera.view();
+    let mut count = 0;
+    for (entity, mesh, &GlobalTransform(model)) in &meshes {
+        let mvp = view_projection * model;
+        let Some((entity_min, entity_max)) = mesh.aabb.ndc_rect(&mvp) else {
+            continue;
+        };
+        let overlaps = entity_min.x <= box_max.x
+            && entity_max.x >= box_min.x
+            && entity_min.y <= box_max.y
+            && entity_max.y >= box_min.y;
+        if overlaps {
+            commands.entity(entity).insert(Selected);
+            count += 1;
+        }
+    }
+    debug!("box selected {count} entities");
+}
+
+type FocusQuery<'a> = (&'a Mesh, &'a GlobalTransform, Option<&'a Selected>);
+
+/// Fly the camera to frame the selection or every mesh in the scene
+pub fn focus_camera(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    time: Res<Time>,
+    viewport: Res<Viewport>,
+    mut camera: ResMut<Camera>,
+    mut flight: ResMut<CameraFlight>,
+    meshes: Query<FocusQuery, Without<Pooled>>,
+) {
+    let focus = input_map.pressed(&input, Action::FocusSelected);
+    let frame_all = input_map.pressed(&input, Action::FrameAll);
+    if focus || frame_all {
+        let bounds = meshes
+            .iter()
+            .filter(|(.., selected)| frame_all || selected.is_some())
+            .map(|(mesh, GlobalTransform(model), _)| mesh.aabb.transformed(model))
+            .reduce(|a, b| a.union(&b));
+        match bounds {
+            Some(bounds) => {
+                let aspect = viewport.width as f32 / viewport.height.max(1) as f32;
+                if camera.mode == ProjectionMode::Orthographic {
+                    camera.ortho_height = glm::length(&bounds.size()).max(0.2) / aspect.min(1.0);
+                    camera.update_projection(viewport.width, viewport.height);
+                }
+                let position = framing_position(&bounds, camera.front, aspect);
+                flight.start(camera.pose(), CameraPose { position, ..camera.pose() });
             }
+            None => debug!("found no objects to frame"),
+        }
+    }
+
+    if let Some(pose) = flight.advance(time.delta_seconds()) {
+        camera.set_pose(pose);
+    }
+}
+
+/// Distance in front of the camera that axis views turn around
+const AXIS_VIEW_DISTANCE: f32 = 10.0;
+
+/// Turn the camera to look along a world axis, or switch between perspective and orthographic
+pub fn axis_views(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    viewport: Res<Viewport>,
+    mut camera: ResMut<Camera>,
+    mut flight: ResMut<CameraFlight>,
+) {
+    let mut mode = camera.mode;
+    if input_map.pressed(&input, Action::ToggleOrthographic) {
+        mode = match mode {
+            ProjectionMode::Perspective => ProjectionMode::Orthographic,
+            ProjectionMode::Orthographic => ProjectionMode::Perspective,
+        };
+    }
+
+    let views = [
+        (Action::ViewFront, -90.0, 0.0),
+        (Action::ViewRight, 180.0, 0.0),
+        (Action::ViewTop, -90.0, -90.0),
+    ];
+    for (action, yaw, pitch) in views {
+        if input_map.pressed(&input, action) {
+            let pivot = camera.pos + camera.front * AXIS_VIEW_DISTANCE;
+            let mut pose = CameraPose { position: pivot, yaw, pitch };
+            pose.position -= pose.front() * AXIS_VIEW_DISTANCE;
+            flight.start(camera.pose(), pose);
+            mode = ProjectionMode::Orthographic;
+        }
+    }
+
+    if mode != camera.mode {
+        if mode == ProjectionMode::Orthographic {
+            // Show about as much as the perspective projection does at the pivot
+            let half_fov_y = (Camera::FOV_DEGREES * 0.5).to_radians();
+            camera.ortho_height = 2.0 * AXIS_VIEW_DISTANCE * half_fov_y.tan();
         }
+        camera.mode = mode;
+        camera.update_projection(viewport.width, viewport.height);
+    }
+}
+
+/// Resize the render targets and cameras when the UI gives the scene view a new size
+pub fn resize_scene_view(
+    gl: NonSend<Arc<Context>>,
+    scene_view: Res<SceneView>,
+    mut viewport: ResMut<Viewport>,
+    mut render_state: ResMut<RenderState>,
+    mut camera: ResMut<Camera>,
+    mut layout: ResMut<ViewLayout>,
+) {
+    let (width, height) = scene_view.size();
+    if width == 0 || height == 0 || (width, height) == (viewport.width, viewport.height) {
+        return;
+    }
+
+    *viewport = Viewport::new(width, height);
+    camera.update_projection(width, height);
+    layout.update_projections(width, height);
+    render_state.resize(&gl, width, height);
+}
+
+/// Compile the built-in shaders again when their files change, if a shader watcher was added
+pub fn reload_shaders(
+    gl: NonSend<Arc<Context>>,
+    watcher: Option<ResMut<ShaderWatcher>>,
+    mut render_state: ResMut<RenderState>,
+    mut gl_state: ResMut<GlState>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let Some(mut watcher) = watcher else {
+        return;
+    };
+    if !watcher.poll() {
+        return;
+    }
+
+    match render_state.reload_shaders(&gl, watcher.dir()) {
+        Ok(()) => {
+            info!("reloaded the built-in shaders");
+            notifications.success("Reloaded the built-in shaders");
+        }
+        Err(e) => {
+            warn!("could not reload the built-in shaders, keeping the old ones:\n{e}");
+            notifications.error("Built-in shaders did not compile, see the console");
+        }
+    }
+    // A new program can be given the name of one that was deleted
+    gl_state.invalidate();
+}
+
+/// Toggle the quad view, and make the view under the cursor active when clicking into it
+pub fn switch_views(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    viewport: Res<Viewport>,
+    mut layout: ResMut<ViewLayout>,
+    mut camera: ResMut<Camera>,
+    mut flight: ResMut<CameraFlight>,
+) {
+    if input_map.pressed(&input, Action::ToggleQuadView) {
+        layout.quad = !layout.quad;
+    }
+
+    let clicked =
+        input_map.pressed(&input, Action::Select) || input_map.pressed(&input, Action::LookAround);
+    if !layout.quad || !clicked {
+        return;
+    }
+    let (x, y) = input.mouse_pos;
+    if layout.in_preview(viewport.width, viewport.height, x, y) {
+        return;
+    }
+    match layout.view_at(viewport.width, viewport.height, x, y) {
+        Some(view) if view != layout.active() => {
+            layout.activate(view, &mut camera);
+            // A flight belongs to the camera that started it
+            *flight = CameraFlight::default();
+            debug!("activated the {} view", ViewLayout::NAMES[view]);
+        }
+        _ => {}
+    }
+}
+
+/// Save the camera pose to a numbered view, or fly back to a saved one
+pub fn camera_views(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    camera: Res<Camera>,
+    mut bookmarks: ResMut<ViewBookmarks>,
+    mut flight: ResMut<CameraFlight>,
+    mut notifications: ResMut<Notifications>,
+) {
+    for slot in 1..=VIEW_SLOTS {
+        if input_map.pressed(&input, Action::SaveView(slot)) {
+            bookmarks.views.insert(slot, camera.pose());
+            notifications.success(format!("Saved view {slot}"));
+            // The save binding usually includes the recall key
+            continue;
+        }
+        if input_map.pressed(&input, Action::RecallView(slot)) {
+            match bookmarks.views.get(&slot) {
+                Some(&pose) => flight.start(camera.pose(), pose),
+                None => notifications.warning(format!("View {slot} has not been saved")),
+            }
+        }
+    }
+}
+
+/// Position looking along `front` from which the whole box is in view
+fn framing_position(bounds: &Aabb, front: glm::Vec3, aspect: f32) -> glm::Vec3 {
+    // Frame the bounding sphere, so the distance does not depend on the view direction
+    let radius = (glm::length(&bounds.size()) * 0.5).max(0.1);
+    let half_fov_y = (Camera::FOV_DEGREES * 0.5).to_radians();
+    let half_fov_x = (half_fov_y.tan() * aspect).atan();
+    let distance = radius / half_fov_y.min(half_fov_x).sin();
+    bounds.center() - front * distance
+}
+
+/// Pitch of the camera while it turns around objects in look-dev mode, in degrees
+const LOOK_DEV_PITCH: f64 = -15.0;
+
+/// Start, turn and stop look-dev mode to match `LookDev::enabled`
+#[allow(clippy::too_many_arguments)]
+pub fn look_dev(
+    time: Res<Time>,
+    viewport: Res<Viewport>,
+    mut look_dev: ResMut<LookDev>,
+    mut camera: ResMut<Camera>,
+    mut lighting: ResMut<Lighting>,
+    mut debug_draw: ResMut<DebugDraw>,
+    mut layout: ResMut<ViewLayout>,
+    mut render_state: ResMut<RenderState>,
+    mut notifications: ResMut<Notifications>,
+    selected: Query<(Entity, &Mesh, &GlobalTransform), With<Selected>>,
+    mut commands: Commands,
+) {
+    let look_dev = &mut *look_dev;
+    if !look_dev.enabled {
+        look_dev.recording = None;
+        if let Some(session) = look_dev.session.take() {
+            for entity in session.entities {
+                if let Some(mut entity) = commands.get_entity(entity) {
+                    entity.insert(Selected);
+                }
+            }
+            camera.set_pose(session.pose);
+            *lighting = session.lighting;
+            *debug_draw = session.debug_draw;
+            layout.quad = session.quad;
+            render_state.environment_map = None;
+        }
+        return;
+    }
+
+    if look_dev.session.is_none() {
+        let bounds = selected
+            .iter()
+            .map(|(_, mesh, GlobalTransform(model))| mesh.aabb.transformed(model))
+            .reduce(|a, b| a.union(&b));
+        let Some(bounds) = bounds else {
+            notifications.warning("Select an object to look at in look-dev mode");
+            look_dev.enabled = false;
+            look_dev.recording = None;
+            return;
+        };
+
+        // The selection outline and bounds would show up in the images, so hide them until the
+        // session ends
+        let entities: Vec<_> = selected.iter().map(|(entity, ..)| entity).collect();
+        for &entity in &entities {
+            commands.entity(entity).remove::<Selected>();
+        }
+        look_dev.session = Some(LookDevSession {
+            bounds,
+            angle: 0.0,
+            entities,
+            pose: camera.pose(),
+            lighting: *lighting,
+            debug_draw: *debug_draw,
+            quad: layout.quad,
+        });
+        *debug_draw = DebugDraw::default();
+        layout.quad = false;
+    }
+
+    let Some(session) = &mut look_dev.session else {
+        return;
+    };
+    let hdri = look_dev.hdri.filter(|_| look_dev.environment == LookDevEnvironment::Hdri);
+    // Until an HDRI is loaded it is lit like the studio
+    *lighting = match (look_dev.environment, hdri) {
+        (LookDevEnvironment::Hdri, None) => LookDevEnvironment::Studio.lighting(),
+        (environment, _) => environment.lighting(),
+    };
+    if render_state.environment_map != hdri {
+        render_state.environment_map = hdri;
+    }
+    session.angle = match look_dev.recording {
+        // Fixed steps, so the images cover exactly one turn whatever the frame rate
+        Some(frame) => 360.0 * frame as f64 / look_dev.frames.max(1) as f64,
+        None => (session.angle + (look_dev.speed * time.delta_seconds()) as f64) % 360.0,
+    };
+
+    let mut pose = CameraPose {
+        position: camera.pos,
+        yaw: session.pose.yaw + session.angle,
+        pitch: LOOK_DEV_PITCH,
+    };
+    let aspect = viewport.width as f32 / viewport.height.max(1) as f32;
+    pose.position = framing_position(&session.bounds, pose.front(), aspect);
+    camera.set_pose(pose);
+}
+
+/// Write the scene view to the next image of the sequence while look-dev renders a turn
+pub fn capture_turntable(
+    gl: NonSend<Arc<Context>>,
+    viewport: Res<Viewport>,
+    render_state: Res<RenderState>,
+    mut look_dev: ResMut<LookDev>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let (Some(frame), Some(_)) = (look_dev.recording, &look_dev.session) else {
+        return;
+    };
+
+    let dir = PathBuf::from(&look_dev.output_dir);
+    let path = dir.join(format!("turntable_{frame:04}.png"));
+    let (width, height) = (viewport.width, viewport.height);
+    let pixels = renderer::read_scene_color(&gl, &render_state, width, height);
+    let written = fs::create_dir_all(&dir)
+        .map_err(Into::into)
+        .and_then(|_| image_export::save_png(&path, width, height, &pixels));
+    if let Err(e) = written {
+        warn!("could not write turntable image {}: {e}", path.display());
+        notifications.error(format!("Could not write {}", path.display()));
+        look_dev.recording = None;
+        return;
+    }
+
+    let written = frame + 1;
+    if written < look_dev.frames {
+        look_dev.recording = Some(written);
+    } else {
+        look_dev.recording = None;
+        notifications.success(format!("Wrote {written} images to {}", dir.display()));
+    }
+}
+
+/// Move the selected entities along the world axes by the position snapping step, repeating while
+/// the key is held
+pub fn nudge_selection(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    config: Res<EditorConfig>,
+    mut query: Query<&mut Position, With<Selected>>,
+) {
+    let step = config.snapping.position;
+    let nudges = [
+        (Action::NudgeLeft, glm::vec3(-step, 0.0, 0.0)),
+        (Action::NudgeRight, glm::vec3(step, 0.0, 0.0)),
+        (Action::NudgeUp, glm::vec3(0.0, step, 0.0)),
+        (Action::NudgeDown, glm::vec3(0.0, -step, 0.0)),
+        (Action::NudgeForward, glm::vec3(0.0, 0.0, -step)),
+        (Action::NudgeBackward, glm::vec3(0.0, 0.0, step)),
+    ];
+    let offset = nudges
+        .iter()
+        .filter(|(action, _)| input_map.repeated(&input, *action))
+        .fold(glm::Vec3::zeros(), |sum, (_, offset)| sum + offset);
+    if offset == glm::Vec3::zeros() {
+        return;
+    }
+
+    for mut position in &mut query {
+        position.x += offset.x;
+        position.y += offset.y;
+        position.z += offset.z;
+    }
+}
+
+/// Select the next or previous mesh that is shown and not locked, so objects can be selected
+/// without the mouse
+pub fn cycle_selection(
+    input: Res<Input>,
+    input_map: Res<InputMap>,
+    already_selected: Query<Entity, With<Selected>>,
+    meshes: Query<Entity, (With<Mesh>, Selectable)>,
+    mut commands: Commands,
+) {
+    let forward = input_map.pressed(&input, Action::SelectNext);
+    let backward = input_map.pressed(&input, Action::SelectPrevious);
+    if forward == backward {
+        return;
+    }
+
+    let mut entities: Vec<_> = meshes.iter().collect();
+    if entities.is_empty() {
+        return;
+    }
+    entities.sort();
+
+    let current = already_selected.iter().next();
+    let index = match current.and_then(|c| entities.iter().position(|&e| e == c)) {
+        Some(i) if forward => (i + 1) % entities.len(),
+        Some(i) => (i + entities.len() - 1) % entities.len(),
+        None if forward => 0,
+        None => entities.len() - 1,
+    };
+
+    for entity in &already_selected {
+        commands.entity(entity).remove::<Selected>();
+    }
+    commands.entity(entities[index]).insert(Selected);
+    debug!("selected entity {}", entities[index].index());
+}
+
+type AnimatedQuery<'a> = (
+    &'a mut Position,
+    &'a mut Rotation,
+    Option<&'a Rotator>,
+    Option<&'a mut Oscillator>,
+    Option<&'a mut FollowPath>,
+);
+
+type AnimatedFilter = Or<(With<Rotator>, With<Oscillator>, With<FollowPath>)>;
+
+/// Entities the simulation moves
+type Simulated = Or<(With<Rotator>, With<Oscillator>, With<FollowPath>, With<AnimationClip>)>;
+
+type NotSimulated =
+    (Without<Rotator>, Without<Oscillator>, Without<FollowPath>, Without<AnimationClip>);
+
+type SimulatedQuery<'a> =
+    (Entity, &'a Position, &'a Rotation, &'a Scale, Option<&'a mut Interpolation>);
+
+/// Take as many simulation steps as fit in the time the previous frame took
+pub fn run_simulation(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    world.resource_mut::<FixedTime>().accumulate(delta);
+    while world.resource_mut::<FixedTime>().expend() {
+        world.run_schedule(SimulationSchedule);
+    }
+}
+
+/// Remember where animated entities are before a simulation step, to draw them in between
+pub fn begin_fixed_step(
+    mut simulated: Query<SimulatedQuery, Simulated>,
+    stopped: Query<Entity, (With<Interpolation>, NotSimulated)>,
+    mut commands: Commands,
+) {
+    for (entity, &position, &rotation, &scale, interpolation) in &mut simulated {
+        match interpolation {
+            Some(mut interpolation) => {
+                interpolation.position = position;
+                interpolation.rotation = rotation;
+                interpolation.scale = scale;
+            }
+            None => {
+                let model = model_matrix(position, rotation, scale);
+                commands.entity(entity).insert(Interpolation { position, rotation, scale, model });
+            }
+        }
+    }
+    for entity in &stopped {
+        commands.entity(entity).remove::<Interpolation>();
+    }
+}
+
+pub fn animate_transforms(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<AnimatedQuery, AnimatedFilter>,
+) {
+    let dt = fixed_time.delta_seconds();
+
+    for (mut pos, mut rotation, rotator, oscillator, follow_path) in &mut query {
+        if let Some(rotator) = rotator {
+            let spin = glm::vec3(rotator.x, rotator.y, rotator.z) * dt;
+            let euler = (rotation.euler() + spin).map(|angle| angle % 360.0);
+            *rotation = Rotation::from_euler(euler);
+        }
+
+        if let Some(mut path) = follow_path {
+            path.distance += path.speed * dt;
+            if let Some(p) = path.sample(path.distance) {
+                *pos = Position::new(p.x, p.y, p.z);
+            }
+        }
+
+        // Apply the change in offset so the position can still be edited while oscillating
+        if let Some(mut oscillator) = oscillator {
+            let prev_offset = oscillator.offset_at(oscillator.elapsed);
+            oscillator.elapsed += dt;
+            let delta = oscillator.offset_at(oscillator.elapsed) - prev_offset;
+            pos.x += delta.x;
+            pos.y += delta.y;
+            pos.z += delta.z;
+        }
+    }
+}
+
+pub fn play_animation_clips(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut AnimationClip, &mut Position, &mut Rotation, &mut Scale)>,
+) {
+    for (mut clip, mut pos, mut rotation, mut scale) in &mut query {
+        if !clip.playing {
+            continue;
+        }
+
+        clip.advance(fixed_time.delta_seconds());
+
+        if let Some(keyframe) = clip.sample(clip.time) {
+            *pos = keyframe.position;
+            *rotation = keyframe.rotation;
+            *scale = keyframe.scale;
+        }
+    }
+}
+
+pub fn update_skeletons(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut Skeleton, &mut AnimationPlayer)>,
+) {
+    for (mut skeleton, mut player) in &mut query {
+        let animation = player.animation;
+        let duration = animation.and_then(|i| skeleton.skin.animations.get(i)).map(|a| a.duration);
+
+        if let (true, Some(duration)) = (player.playing, duration) {
+            player.time += fixed_time.delta_seconds() * player.speed;
+            if player.time > duration {
+                if player.looping && duration > 0.0 {
+                    player.time %= duration;
+                } else {
+                    player.time = duration;
+                    player.playing = false;
+                }
+            }
+        } else if !player.is_changed() {
+            continue;
+        }
+
+        let skeleton = &mut *skeleton;
+        let animation = animation.map(|i| (i, player.time));
+        skeleton.skin.joint_matrices(animation, &mut skeleton.joint_matrices);
+    }
+}
+
+/// Keep the values passed to custom shaders up to date
+pub fn update_shader_globals(
+    time: Res<Time>,
+    viewport: Res<Viewport>,
+    mut globals: ResMut<ShaderGlobals>,
+) {
+    globals.time = time.elapsed_seconds();
+    globals.delta_time = time.delta_seconds();
+    globals.resolution = glm::vec2(viewport.width as f32, viewport.height as f32);
+}
+
+type BoundQuery<'a> = (
+    &'a Bindings,
+    Option<&'a mut Position>,
+    Option<&'a mut Scale>,
+    Option<&'a mut PointLight>,
+    Option<&'a mut ShaderParams>,
+);
+
+/// Set the fields that are bound to scene variables to the values of the variables
+pub fn apply_variables(variables: Res<SceneVariables>, mut bound: Query<BoundQuery>) {
+    for fields in &mut bound {
+        variables::apply(&variables, fields);
+    }
+}
+
+/// Serialize the registered components of the selected entity for the inspector
+pub fn inspect_custom_components(world: &mut World) {
+    let mut query = world.query_filtered::<Entity, With<Selected>>();
+    let components = match query.get_single(world) {
+        Ok(entity) => world
+            .resource::<ComponentRegistry>()
+            .iter()
+            .filter_map(|registration| match registration.serialize(world, entity)? {
+                Ok(value) => Some((registration.name(), value)),
+                Err(e) => {
+                    warn!("could not serialize component {:?}: {e}", registration.name());
+                    None
+                }
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    world.resource_mut::<InspectedComponents>().components = components;
+}
+
+/// List the entities of the scene in the outliner while it is open
+pub fn list_outliner_entries(world: &mut World) {
+    if !world.resource::<UiState>().outliner_open {
+        return;
+    }
+    let entries = outliner::entries(world);
+    world.resource_mut::<Outliner>().entries = entries;
+}
+
+/// Tell the user when the background update check finds a newer release
+pub fn poll_update_check(
+    mut update_checker: ResMut<UpdateChecker>,
+    mut notifications: ResMut<Notifications>,
+) {
+    match update_checker.poll() {
+        Some(Ok(Some(release))) => {
+            let message = format!("Version {} is available, see Preferences", release.version);
+            notifications.info(message);
+        }
+        Some(Ok(None)) => debug!("editor is up to date"),
+        Some(Err(e)) => warn!("could not check for updates: {e}"),
+        None => {}
+    }
+}
+
+/// Record frame times and how long each tool is open, if the usage log is enabled
+pub fn track_usage(
+    config: Res<EditorConfig>,
+    state: Res<UiState>,
+    time: Res<Time>,
+    mut usage: ResMut<UsageLog>,
+) {
+    if !config.usage_log {
+        return;
+    }
+
+    let delta = Duration::from_secs_f32(time.delta_seconds());
+    usage.record_frame(delta.as_secs_f32() * 1000.0);
 
-        if !found {
-            debug!("found no object to select");
+    let tools = [
+        ("Shader editor", state.editing_mode.is_some()),
+        ("Camera", state.camera_focused),
+        ("Utilities", state.utilities_open),
+        ("Performance", state.performance_open),
+        ("Timeline", state.timeline_open),
+    ];
+    for (tool, active) in tools {
+        if active {
+            usage.track(tool, delta);
         }
     }
 }
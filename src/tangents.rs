@@ -0,0 +1,75 @@
+use bevy_mikktspace::Geometry;
+use nalgebra_glm as glm;
+
+struct IndexedMesh<'a> {
+    positions: &'a [glm::Vec3],
+    normals: &'a [glm::Vec3],
+    texture_coords: &'a [glm::Vec2],
+    indices: &'a [u32],
+    tangents: Vec<glm::Vec4>,
+}
+
+impl IndexedMesh<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        self.indices[face * 3 + vert] as usize
+    }
+}
+
+impl Geometry for IndexedMesh<'_> {
+    fn num_faces(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.positions[self.index(face, vert)].into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.normals[self.index(face, vert)].into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.texture_coords[self.index(face, vert)].into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.index(face, vert);
+        self.tangents[index] = tangent.into();
+    }
+}
+
+/// Generate MikkTSpace tangents for an indexed triangle mesh, for normal mapping
+///
+/// The handedness of the bitangent is stored in `w`. Returns `None` if the normals or texture
+/// coordinates are missing, or the tangents could not be generated.
+pub fn generate_tangents(
+    positions: &[glm::Vec3],
+    normals: &[glm::Vec3],
+    texture_coords: &[glm::Vec2],
+    indices: &[u32],
+) -> Option<Vec<glm::Vec4>> {
+    let len = positions.len();
+    if len == 0
+        || normals.len() != len
+        || texture_coords.len() != len
+        || indices.len() % 3 != 0
+    {
+        return None;
+    }
+    if indices.iter().any(|&i| i as usize >= len) {
+        return None;
+    }
+
+    let mut mesh = IndexedMesh {
+        positions,
+        normals,
+        texture_coords,
+        indices,
+        tangents: vec![glm::vec4(1.0, 0.0, 0.0, 1.0); len],
+    };
+    bevy_mikktspace::generate_tangents(&mut mesh).then_some(mesh.tangents)
+}
@@ -0,0 +1,518 @@
+//! Terrain generated from a heightmap or as a flat grid, drawn in chunks and sculpted with brushes
+//!
+//! The heights and layer weights of a terrain are kept in its [`Terrain`] component, relative to
+//! the entity. Its mesh is split into chunks of [`CHUNK_QUADS`] quads a side, so a brush stroke
+//! only uploads the chunks it touched again.
+
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, Texture};
+use nalgebra_glm as glm;
+use zune_jpeg::JpegDecoder;
+use zune_png::zune_core::bit_depth::{BitDepth, ByteEndian};
+use zune_png::zune_core::colorspace::ColorSpace;
+use zune_png::zune_core::options::DecoderOptions;
+use zune_png::PngDecoder;
+
+use crate::components::Mesh;
+use crate::mesh_cleanup::MeshData;
+use crate::vao::VertexArrayObject;
+
+/// Quads along each side of a chunk, which is uploaded again when it is sculpted
+pub const CHUNK_QUADS: u32 = 32;
+
+/// Textures a terrain blends between
+pub const LAYERS: usize = 4;
+
+/// World units each layer texture repeats over, before the terrain is scaled
+pub const TILE_SIZE: f32 = 4.0;
+
+/// Quads along each side of a new terrain
+pub const DEFAULT_QUADS: u32 = 64;
+
+/// Length of each side of a new terrain
+pub const DEFAULT_SIZE: f32 = 32.0;
+
+/// Heights and layer weights of a grid of vertices, centered on the origin of the entity
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Terrain {
+    /// Quads along each side
+    pub quads: u32,
+    /// Length of each side
+    pub size: f32,
+    /// Height of each vertex, in rows along X from -Z to +Z
+    pub heights: Vec<f32>,
+    /// Weight of each layer at each vertex, in the same order as the heights
+    pub splat: Vec<[f32; LAYERS]>,
+    /// Texture of each layer, where missing ones are drawn with the default diffuse texture
+    pub layers: [Option<Texture>; LAYERS],
+    /// Chunks whose meshes are out of date
+    dirty: Vec<usize>,
+}
+
+impl Terrain {
+    /// Terrain at height 0, covered in the first layer
+    pub fn flat(quads: u32, size: f32) -> Self {
+        let side = quads.max(1) as usize + 1;
+        Self::build(quads.max(1), size, vec![0.0; side * side], Vec::new())
+    }
+
+    /// Terrain with heights read from a heightmap, from 0 where it is black to `max_height` where
+    /// it is white
+    pub fn from_heightmap(heightmap: &Heightmap, quads: u32, size: f32, max_height: f32) -> Self {
+        let quads = quads.max(1);
+        let side = quads + 1;
+        let heights = (0..side * side)
+            .map(|i| {
+                let (u, v) = ((i % side) as f32 / quads as f32, (i / side) as f32 / quads as f32);
+                heightmap.sample(u, v) * max_height
+            })
+            .collect();
+        Self::build(quads, size, heights, Vec::new())
+    }
+
+    /// Terrain with the given heights and layer weights, as saved in a scene
+    ///
+    /// Without weights the terrain is covered in the first layer.
+    pub fn from_parts(
+        quads: u32,
+        size: f32,
+        heights: Vec<f32>,
+        splat: Vec<[f32; LAYERS]>,
+    ) -> Result<Self> {
+        let side = quads as usize + 1;
+        if quads == 0 || heights.len() != side * side {
+            return Err(eyre!(
+                "terrain of {quads} quads a side needs {} heights, not {}",
+                side * side,
+                heights.len()
+            ));
+        }
+        if !splat.is_empty() && splat.len() != heights.len() {
+            let (heights, weights) = (heights.len(), splat.len());
+            return Err(eyre!("terrain has {heights} heights but {weights} layer weights"));
+        }
+        Ok(Self::build(quads, size, heights, splat))
+    }
+
+    fn build(quads: u32, size: f32, heights: Vec<f32>, mut splat: Vec<[f32; LAYERS]>) -> Self {
+        if splat.is_empty() {
+            splat = vec![[1.0, 0.0, 0.0, 0.0]; heights.len()];
+        }
+        let mut terrain =
+            Self { quads, size, heights, splat, layers: Default::default(), dirty: Vec::new() };
+        terrain.dirty = (0..terrain.chunk_count()).collect();
+        terrain
+    }
+
+    /// Vertices along each side
+    pub fn side(&self) -> u32 {
+        self.quads + 1
+    }
+
+    /// Distance between neighboring vertices
+    pub fn spacing(&self) -> f32 {
+        self.size / self.quads as f32
+    }
+
+    fn index(&self, x: u32, z: u32) -> usize {
+        (z * self.side() + x) as usize
+    }
+
+    /// Height of a vertex, where vertices past the edge take the height of the edge
+    pub fn height(&self, x: u32, z: u32) -> f32 {
+        self.heights[self.index(x.min(self.quads), z.min(self.quads))]
+    }
+
+    /// Position of a vertex relative to the entity
+    pub fn vertex_position(&self, x: u32, z: u32) -> glm::Vec3 {
+        let half = self.size / 2.0;
+        let spacing = self.spacing();
+        glm::vec3(x as f32 * spacing - half, self.height(x, z), z as f32 * spacing - half)
+    }
+
+    /// Normal of a vertex, from the heights of its neighbors
+    pub fn normal(&self, x: u32, z: u32) -> glm::Vec3 {
+        let quads = self.quads as i64;
+        let h = |x: i64, z: i64| self.height(x.clamp(0, quads) as u32, z.clamp(0, quads) as u32);
+        let (x, z) = (x as i64, z as i64);
+        let (dx, dz) = (h(x - 1, z) - h(x + 1, z), h(x, z - 1) - h(x, z + 1));
+        glm::normalize(&glm::vec3(dx, 2.0 * self.spacing(), dz))
+    }
+
+    /// Vertex grid coordinates of a point relative to the entity, which may be fractional or
+    /// outside the grid
+    fn grid_coords(&self, x: f32, z: f32) -> glm::Vec2 {
+        glm::vec2(x, z).add_scalar(self.size / 2.0) / self.spacing()
+    }
+
+    /// Height of the surface at a point relative to the entity, interpolated between the corners
+    /// of the quad it is in, or `None` if it is outside the terrain
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let grid = self.grid_coords(x, z);
+        let quads = self.quads as f32;
+        if !(0.0..=quads).contains(&grid.x) || !(0.0..=quads).contains(&grid.y) {
+            return None;
+        }
+        let (x0, z0) = ((grid.x as u32).min(self.quads - 1), (grid.y as u32).min(self.quads - 1));
+        let (tx, tz) = (grid.x - x0 as f32, grid.y - z0 as f32);
+        let h = |dx: u32, dz: u32| self.height(x0 + dx, z0 + dz);
+        let near = glm::lerp_scalar(h(0, 0), h(1, 0), tx);
+        let far = glm::lerp_scalar(h(0, 1), h(1, 1), tx);
+        Some(glm::lerp_scalar(near, far, tz))
+    }
+
+    /// Chunks along each side, where the last ones are smaller if the quads do not divide evenly
+    pub fn chunks_per_side(&self) -> u32 {
+        (self.quads + CHUNK_QUADS - 1) / CHUNK_QUADS
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        (self.chunks_per_side() * self.chunks_per_side()) as usize
+    }
+
+    /// Mesh of a chunk, by row along X from -Z, with the layer weights of each vertex
+    ///
+    /// Chunks share the vertices along their edges, so there are no seams between them.
+    pub fn chunk_mesh(&self, chunk: usize) -> (MeshData, Vec<glm::Vec4>) {
+        let chunks = self.chunks_per_side();
+        let (cx, cz) = (chunk as u32 % chunks, chunk as u32 / chunks);
+        let (x0, x1) = (cx * CHUNK_QUADS, ((cx + 1) * CHUNK_QUADS).min(self.quads));
+        let (z0, z1) = (cz * CHUNK_QUADS, ((cz + 1) * CHUNK_QUADS).min(self.quads));
+
+        let mut mesh = MeshData::default();
+        let mut weights = Vec::new();
+        for z in z0..=z1 {
+            for x in x0..=x1 {
+                mesh.positions.push(self.vertex_position(x, z));
+                mesh.normals.push(self.normal(x, z));
+                let tile = self.spacing() / TILE_SIZE;
+                mesh.texture_coords.push(glm::vec2(x as f32, z as f32) * tile);
+                weights.push(glm::Vec4::from(self.splat[self.index(x, z)]));
+            }
+        }
+
+        let width = x1 - x0 + 1;
+        for row in 0..z1 - z0 {
+            for column in 0..x1 - x0 {
+                let a = row * width + column;
+                let (b, c) = (a + 1, a + width);
+                // Counter-clockwise seen from above
+                mesh.indices.extend([a, c, b, b, c, c + 1]);
+            }
+        }
+        (mesh, weights)
+    }
+
+    /// Take the chunks that changed since the last call, to upload them again
+    pub fn take_dirty_chunks(&mut self) -> Vec<usize> {
+        mem::take(&mut self.dirty)
+    }
+
+    /// Mark the chunks with vertices from `min` to `max` out of date, along with the chunks whose
+    /// normals depend on them
+    fn mark_dirty(&mut self, min: (u32, u32), max: (u32, u32)) {
+        let chunks = self.chunks_per_side();
+        // Vertices on the edge between two chunks are in both
+        let range = |low: u32, high: u32| {
+            (low.saturating_sub(2) / CHUNK_QUADS)..=((high + 1) / CHUNK_QUADS).min(chunks - 1)
+        };
+        for cz in range(min.1, max.1) {
+            for cx in range(min.0, max.0) {
+                let chunk = (cz * chunks + cx) as usize;
+                if !self.dirty.contains(&chunk) {
+                    self.dirty.push(chunk);
+                }
+            }
+        }
+    }
+
+    /// Apply a brush centered on a point relative to the entity, for `dt` seconds
+    pub fn sculpt(&mut self, brush: &Brush, center: glm::Vec2, dt: f32) {
+        self.sculpt_scaled(brush, center, glm::Vec3::repeat(1.0), dt);
+    }
+
+    /// Apply a brush on a terrain whose entity is scaled by `scale`, with the radius of the brush
+    /// and the height it raises or lowers in world units
+    ///
+    /// The brush stays round in the world, so it is stretched over the grid when the terrain is
+    /// scaled more along one axis than the other. The `Flatten` height is relative to the entity.
+    pub fn sculpt_scaled(&mut self, brush: &Brush, center: glm::Vec2, scale: glm::Vec3, dt: f32) {
+        let center = self.grid_coords(center.x, center.y);
+        let radius = glm::vec2(scale.x, scale.z).map(|s| brush.radius / (s * self.spacing()));
+        if !radius.iter().all(|r| r.is_finite() && *r > 0.0) || scale.y == 0.0 {
+            return;
+        }
+        let quads = self.quads as f32;
+        let (min, max) = ((center - radius).map(f32::ceil), center + radius);
+        let (min, max) = (min.map(|v| v.max(0.0)), max.map(|v| v.floor().min(quads)));
+        if min.x > max.x || min.y > max.y {
+            return;
+        }
+        let (x0, z0, x1, z1) = (min.x as u32, min.y as u32, max.x as u32, max.y as u32);
+
+        // Smoothing reads the heights from before the brush, so it does not depend on the order
+        let original = (brush.tool == BrushTool::Smooth).then(|| self.heights.clone());
+        let (side, last) = (self.side() as i64, self.quads as i64);
+        let at = |x: i64, z: i64| (z.clamp(0, last) * side + x.clamp(0, last)) as usize;
+        for z in z0..=z1 {
+            for x in x0..=x1 {
+                let distance =
+                    (glm::vec2(x as f32, z as f32) - center).component_div(&radius).norm();
+                if distance > 1.0 {
+                    continue;
+                }
+                let amount = brush.strength * (1.0 - distance * distance).powi(2) * dt;
+                let t = amount.min(1.0);
+                let i = self.index(x, z);
+                match brush.tool {
+                    BrushTool::Raise => self.heights[i] += amount / scale.y,
+                    BrushTool::Lower => self.heights[i] -= amount / scale.y,
+                    BrushTool::Smooth => {
+                        let original = original.as_deref().unwrap_or(&self.heights);
+                        let (x, z) = (x as i64, z as i64);
+                        let neighbors = [at(x - 1, z), at(x + 1, z), at(x, z - 1), at(x, z + 1)];
+                        let average = neighbors.iter().map(|&n| original[n]).sum::<f32>() / 4.0;
+                        self.heights[i] += (average - self.heights[i]) * t;
+                    }
+                    BrushTool::Flatten => self.heights[i] += (brush.height - self.heights[i]) * t,
+                    BrushTool::Paint => {
+                        let weights = &mut self.splat[i];
+                        weights[brush.layer.min(LAYERS - 1)] += t;
+                        let sum: f32 = weights.iter().sum();
+                        weights.iter_mut().for_each(|w| *w /= sum);
+                    }
+                }
+            }
+        }
+        self.mark_dirty((x0, z0), (x1, z1));
+    }
+}
+
+/// What a brush does to the terrain under it
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum BrushTool {
+    #[default]
+    Raise,
+    Lower,
+    /// Move heights toward the average of their neighbors, evening out bumps
+    Smooth,
+    /// Move heights toward the height the stroke started at
+    Flatten,
+    /// Blend in a layer texture
+    Paint,
+}
+
+impl BrushTool {
+    pub const ALL: [Self; 5] = [Self::Raise, Self::Lower, Self::Smooth, Self::Flatten, Self::Paint];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Raise => "Raise",
+            Self::Lower => "Lower",
+            Self::Smooth => "Smooth",
+            Self::Flatten => "Flatten",
+            Self::Paint => "Paint",
+        }
+    }
+}
+
+/// Round brush that fades out toward its edge
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Brush {
+    pub tool: BrushTool,
+    /// Radius in the units of the terrain
+    pub radius: f32,
+    /// Height raised or lowered per second at the center, or how fast the other tools move
+    /// toward their target, where 1 reaches it in a second
+    pub strength: f32,
+    /// Height `Flatten` moves toward
+    pub height: f32,
+    /// Layer `Paint` blends in
+    pub layer: usize,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self { tool: BrushTool::Raise, radius: 3.0, strength: 2.0, height: 0.0, layer: 1 }
+    }
+}
+
+/// Grayscale image that terrain heights are read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    pub width: usize,
+    pub height: usize,
+    /// Values from 0 to 1, in rows from the top of the image
+    pub values: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Read a PNG or JPEG image, depending on the extension, taking the first channel of each
+    /// pixel
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read(path)
+            .map_err(|e| eyre!("could not read heightmap {}: {e}", path.display()))?;
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("jpg" | "jpeg") => Self::from_jpeg(&contents),
+            _ => Self::from_png(&contents),
+        }
+        .map_err(|e| eyre!("could not load heightmap {}: {e}", path.display()))
+    }
+
+    fn from_png(contents: &[u8]) -> Result<Self> {
+        let byte_endian =
+            if cfg!(target_endian = "little") { ByteEndian::LE } else { ByteEndian::BE };
+        let mut decoder = PngDecoder::new_with_options(
+            contents,
+            DecoderOptions::new_fast().set_byte_endian(byte_endian),
+        );
+        decoder.decode_headers().map_err(|_| eyre!("could not decode PNG headers"))?;
+
+        let channels = channels(decoder.get_colorspace())?;
+        let bit_depth = decoder.get_depth().ok_or_else(|| eyre!("PNG has no bit depth"))?;
+        let (width, height) =
+            decoder.get_dimensions().ok_or_else(|| eyre!("PNG has no dimensions"))?;
+        let bytes = decoder.decode_raw().map_err(|_| eyre!("could not decode PNG image"))?;
+
+        let values = match bit_depth {
+            BitDepth::Eight => bytes.iter().step_by(channels).map(|&v| v as f32 / 255.0).collect(),
+            BitDepth::Sixteen => bytes
+                .chunks_exact(2)
+                .step_by(channels)
+                .map(|v| u16::from_ne_bytes([v[0], v[1]]) as f32 / 65535.0)
+                .collect(),
+            other => return Err(eyre!("heightmap has an unsupported bit depth {other:?}")),
+        };
+        Ok(Self { width, height, values })
+    }
+
+    /// JPEG images only have 8 bits per channel, so their heights come in 256 steps
+    fn from_jpeg(contents: &[u8]) -> Result<Self> {
+        let mut decoder = JpegDecoder::new(contents);
+        decoder.decode_headers().map_err(|e| eyre!("could not decode JPEG headers: {e:?}"))?;
+
+        let channels = channels(decoder.get_output_colorspace())?;
+        let (width, height) =
+            decoder.dimensions().ok_or_else(|| eyre!("JPEG has no dimensions"))?;
+        let bytes = decoder.decode().map_err(|e| eyre!("could not decode JPEG image: {e:?}"))?;
+        let values = bytes.iter().step_by(channels).map(|&v| v as f32 / 255.0).collect();
+        Ok(Self { width, height, values })
+    }
+
+    /// Value at texture coordinates from 0 to 1, interpolated between the nearest pixels
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let x = u.clamp(0.0, 1.0) * (self.width - 1) as f32;
+        let y = v.clamp(0.0, 1.0) * (self.height - 1) as f32;
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let value = |x: usize, y: usize| self.values[y * self.width + x];
+        let top = glm::lerp_scalar(value(x0, y0), value(x1, y0), x - x0 as f32);
+        let bottom = glm::lerp_scalar(value(x0, y1), value(x1, y1), x - x0 as f32);
+        glm::lerp_scalar(top, bottom, y - y0 as f32)
+    }
+}
+
+/// Channels of each pixel of a heightmap with the given color space
+fn channels(color_space: Option<ColorSpace>) -> Result<usize> {
+    match color_space {
+        Some(ColorSpace::Luma) => Ok(1),
+        Some(ColorSpace::LumaA) => Ok(2),
+        Some(ColorSpace::RGB) => Ok(3),
+        Some(ColorSpace::RGBA) => Ok(4),
+        other => Err(eyre!("heightmap has an unsupported color space {other:?}")),
+    }
+}
+
+/// Meshes of the chunks of a terrain, kept up to date by [`update_terrain_meshes`]
+#[derive(Component, Debug, Default, Clone)]
+pub struct TerrainChunks {
+    pub meshes: Vec<Mesh>,
+}
+
+/// Vertex arrays of the chunks of every terrain, which unlike models belong to one entity
+#[derive(Resource, Default)]
+pub struct TerrainMeshes {
+    vaos: AHashMap<Entity, Vec<VertexArrayObject>>,
+}
+
+impl TerrainMeshes {
+    /// # Safety
+    ///
+    /// The vertex arrays are no longer valid and should not be used.
+    pub unsafe fn destroy(&mut self, gl: &Context) {
+        for vao in self.vaos.values_mut().flatten() {
+            vao.destroy(gl);
+        }
+        self.vaos.clear();
+    }
+}
+
+/// # Safety
+///
+/// Leaves the new VAO bound.
+unsafe fn upload_chunk(gl: &Context, terrain: &Terrain, chunk: usize) -> VertexArrayObject {
+    let (mesh, weights) = terrain.chunk_mesh(chunk);
+    let mut vao = VertexArrayObject::new(
+        gl,
+        &mesh.positions,
+        &mesh.indices,
+        &mesh.normals,
+        &mesh.texture_coords,
+    );
+    vao.add_splat_weights(gl, &weights);
+    vao
+}
+
+/// Upload the chunks of new terrains and the chunks of sculpted terrains that changed, and destroy
+/// the chunks of removed terrains
+pub fn update_terrain_meshes(
+    gl: NonSend<Arc<Context>>,
+    mut meshes: ResMut<TerrainMeshes>,
+    mut terrains: Query<(Entity, &mut Terrain, Option<&mut TerrainChunks>), Changed<Terrain>>,
+    mut removed: RemovedComponents<Terrain>,
+    mut commands: Commands,
+) {
+    for entity in removed.iter() {
+        for mut vao in meshes.vaos.remove(&entity).into_iter().flatten() {
+            unsafe { vao.destroy(&gl) };
+        }
+    }
+
+    for (entity, mut terrain, chunks) in &mut terrains {
+        let dirty = terrain.bypass_change_detection().take_dirty_chunks();
+        let vaos = meshes.vaos.entry(entity).or_default();
+        match chunks {
+            Some(mut chunks) if vaos.len() == terrain.chunk_count() => {
+                for chunk in dirty {
+                    unsafe {
+                        vaos[chunk].destroy(&gl);
+                        vaos[chunk] = upload_chunk(&gl, &terrain, chunk);
+                    }
+                    chunks.meshes[chunk] = Mesh::from(&vaos[chunk]);
+                }
+            }
+            _ => {
+                // New terrains, and terrains that were replaced by one of another size
+                for vao in vaos.iter_mut() {
+                    unsafe { vao.destroy(&gl) };
+                }
+                *vaos = (0..terrain.chunk_count())
+                    .map(|chunk| unsafe { upload_chunk(&gl, &terrain, chunk) })
+                    .collect();
+                let chunk_meshes = vaos.iter().map(Mesh::from).collect();
+                commands.entity(entity).insert(TerrainChunks { meshes: chunk_meshes });
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::{Context, HasContext};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Extension of the import settings file kept next to a texture, as in `bricks.import`
+pub const SETTINGS_EXTENSION: &str = "import";
+
+/// How a texture file is uploaded and sampled, stored in a file next to it
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(default)]
+pub struct TextureSettings {
+    /// The file holds sRGB colors, which are turned linear when sampled
+    pub srgb: bool,
+    pub wrap: WrapMode,
+    pub filter: Filter,
+    /// Samples taken along surfaces seen at an angle, 1 to turn it off
+    pub anisotropy: f32,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self { srgb: false, wrap: WrapMode::Repeat, filter: Filter::Bilinear, anisotropy: 1.0 }
+    }
+}
+
+impl TextureSettings {
+    /// Settings file of a texture file
+    pub fn path_for(texture: &Path) -> PathBuf {
+        texture.with_extension(SETTINGS_EXTENSION)
+    }
+
+    /// Settings of a texture file, or the defaults if it has no settings file
+    pub fn load_for(texture: &Path) -> Result<Self> {
+        let path = Self::path_for(texture);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| eyre!("could not read import settings {}: {e}", path.display()))?;
+        Self::from_ron(&contents)
+    }
+
+    /// Write the settings next to a texture file, or remove the settings file if they are the
+    /// defaults
+    pub fn save_for(&self, texture: &Path) -> Result<()> {
+        let path = Self::path_for(texture);
+        if *self == Self::default() {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .map_err(|e| eyre!("could not remove {}: {e}", path.display()))?;
+            }
+            return Ok(());
+        }
+        fs::write(&path, self.to_ron()?)
+            .map_err(|e| eyre!("could not write import settings {}: {e}", path.display()))
+    }
+
+    pub fn from_ron(s: &str) -> Result<Self> {
+        ron::from_str(s).map_err(|e| eyre!("could not parse import settings: {e}"))
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| eyre!("could not serialize import settings: {e}"))
+    }
+
+    /// Internal format to upload the texture with
+    pub fn internal_format(&self) -> u32 {
+        if self.srgb { glow::SRGB8_ALPHA8 } else { glow::RGBA8 }
+    }
+
+    /// Set the wrap mode, filter and anisotropy of the bound 2D texture
+    ///
+    /// Anisotropy is left out, with a warning, when the driver does not support it.
+    ///
+    /// # Safety
+    ///
+    /// A texture must be bound to `TEXTURE_2D`.
+    pub unsafe fn apply(&self, gl: &Context) {
+        let wrap = self.wrap.gl_enum() as i32;
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap);
+        let (min, mag) = self.filter.gl_enums();
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, min as i32);
+        gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, mag as i32);
+
+        let Some(max) = max_anisotropy(gl) else {
+            if self.anisotropy > 1.0 {
+                warn!("anisotropic filtering is not supported by the driver");
+            }
+            return;
+        };
+        let anisotropy = self.anisotropy.clamp(1.0, max);
+        gl.tex_parameter_f32(glow::TEXTURE_2D, glow::TEXTURE_MAX_ANISOTROPY, anisotropy);
+    }
+}
+
+/// Largest anisotropy the driver supports, if it supports anisotropic filtering
+pub fn max_anisotropy(gl: &Context) -> Option<f32> {
+    let extensions = gl.supported_extensions();
+    let supported = extensions.contains("GL_EXT_texture_filter_anisotropic")
+        || extensions.contains("GL_ARB_texture_filter_anisotropic");
+    supported.then(|| unsafe { gl.get_parameter_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY) })
+}
+
+/// What a texture does when sampled outside of 0 to 1
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl WrapMode {
+    pub const ALL: [WrapMode; 3] =
+        [WrapMode::Repeat, WrapMode::MirroredRepeat, WrapMode::ClampToEdge];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WrapMode::Repeat => "Repeat",
+            WrapMode::MirroredRepeat => "Mirrored repeat",
+            WrapMode::ClampToEdge => "Clamp to edge",
+        }
+    }
+
+    fn gl_enum(self) -> u32 {
+        match self {
+            WrapMode::Repeat => glow::REPEAT,
+            WrapMode::MirroredRepeat => glow::MIRRORED_REPEAT,
+            WrapMode::ClampToEdge => glow::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// How texels are blended when a texture is magnified or minified, from the cheapest
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Filter {
+    /// Sharp texels, for pixel art
+    Nearest,
+    /// Blended texels, with the nearest mip level
+    Bilinear,
+    /// Blended texels, also blended between mip levels
+    Trilinear,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::Nearest, Filter::Bilinear, Filter::Trilinear];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Filter::Nearest => "Nearest",
+            Filter::Bilinear => "Bilinear",
+            Filter::Trilinear => "Trilinear",
+        }
+    }
+
+    /// Minification and magnification filters
+    fn gl_enums(self) -> (u32, u32) {
+        match self {
+            Filter::Nearest => (glow::NEAREST_MIPMAP_NEAREST, glow::NEAREST),
+            Filter::Bilinear => (glow::LINEAR_MIPMAP_NEAREST, glow::LINEAR),
+            Filter::Trilinear => (glow::LINEAR_MIPMAP_LINEAR, glow::LINEAR),
+        }
+    }
+}
+
+/// Editor-wide limit on the filtering of every texture, to trade sharpness for speed on slow GPUs
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum TextureQuality {
+    /// Bilinear filtering at most, without anisotropy
+    Low,
+    /// Anisotropy up to 4
+    Medium,
+    /// The import settings of each texture
+    #[default]
+    High,
+}
+
+impl TextureQuality {
+    pub const ALL: [TextureQuality; 3] =
+        [TextureQuality::Low, TextureQuality::Medium, TextureQuality::High];
+
+    /// Name used in the configuration file
+    pub fn name(self) -> &'static str {
+        match self {
+            TextureQuality::Low => "low",
+            TextureQuality::Medium => "medium",
+            TextureQuality::High => "high",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TextureQuality::Low => "Low",
+            TextureQuality::Medium => "Medium",
+            TextureQuality::High => "High",
+        }
+    }
+
+    /// Import settings lowered to this quality
+    pub fn limit(self, settings: TextureSettings) -> TextureSettings {
+        let (filter, anisotropy) = match self {
+            TextureQuality::Low => (settings.filter.min(Filter::Bilinear), 1.0),
+            TextureQuality::Medium => (settings.filter, settings.anisotropy.min(4.0)),
+            TextureQuality::High => (settings.filter, settings.anisotropy),
+        };
+        TextureSettings { filter, anisotropy, ..settings }
+    }
+}
+
+/// Size and format of a loaded texture, and the file and settings it was loaded with
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureInfo {
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    /// Channels and bits per channel of the file, such as `RGBA8`
+    pub format: String,
+    pub mip_levels: u32,
+    pub settings: TextureSettings,
+}
+
+/// Number of mip levels in a full chain down to 1x1
+pub fn mip_levels(width: u32, height: u32) -> u32 {
+    u32::BITS - width.max(height).max(1).leading_zeros()
+}
+
+/// Channels a texture preview can show
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Channel {
+    /// Color without alpha
+    #[default]
+    Rgb,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] =
+        [Channel::Rgb, Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Channel::Rgb => "RGB",
+            Channel::Red => "R",
+            Channel::Green => "G",
+            Channel::Blue => "B",
+            Channel::Alpha => "A",
+        }
+    }
+}
+
+/// Opaque RGBA8 pixels showing one channel of RGBA8 pixels as gray, or their color
+pub fn isolate_channel(pixels: &[u8], channel: Channel) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|p| match channel {
+            Channel::Rgb => [p[0], p[1], p[2], 255],
+            Channel::Red => [p[0], p[0], p[0], 255],
+            Channel::Green => [p[1], p[1], p[1], 255],
+            Channel::Blue => [p[2], p[2], p[2], 255],
+            Channel::Alpha => [p[3], p[3], p[3], 255],
+        })
+        .collect()
+}
+
+/// Read the top mip level of a texture as RGBA8 pixels, starting with the first row of the file
+pub fn read_pixels(gl: &Context, texture: glow::Texture, width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0; width as usize * height as usize * 4];
+    unsafe {
+        gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        gl.get_tex_image(
+            glow::TEXTURE_2D,
+            0,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(&mut pixels),
+        );
+    }
+    pixels
+}
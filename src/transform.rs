@@ -0,0 +1,198 @@
+//! World transforms of entities, from their own transform and the ones of their parents
+
+use std::collections::{HashMap, HashSet};
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+
+use crate::components::{GlobalTransform, Interpolation, Parent, Position, Rotation, Scale};
+use crate::renderer::model_matrix;
+
+/// Parents followed up from an entity at most, so a cycle of parents ends
+const MAX_DEPTH: usize = 64;
+
+type LocalQuery<'a> =
+    (&'a Position, &'a Rotation, &'a Scale, Option<&'a Interpolation>, Option<&'a Parent>);
+
+type LocalChanged = Or<(
+    Changed<Position>,
+    Changed<Rotation>,
+    Changed<Scale>,
+    Changed<Interpolation>,
+    Changed<Parent>,
+)>;
+
+/// Update the world transform of the entities whose own transform, or the transform of one of
+/// their parents, changed since the last time this ran
+///
+/// Animated entities are placed where they are drawn between simulation steps. Entities with a
+/// transform but no [`GlobalTransform`] are given one. Children of a parent that was despawned
+/// are taken out of it, keeping where they were in the world.
+#[allow(clippy::too_many_arguments)]
+pub fn update_global_transforms(
+    changed: Query<Entity, LocalChanged>,
+    missing: Query<Entity, (With<Position>, With<Rotation>, With<Scale>, Without<GlobalTransform>)>,
+    mut removed_parents: RemovedComponents<Parent>,
+    mut removed_interpolations: RemovedComponents<Interpolation>,
+    mut removed_globals: RemovedComponents<GlobalTransform>,
+    parents: Query<(Entity, &Parent)>,
+    locals: Query<LocalQuery>,
+    mut globals: Query<&mut GlobalTransform>,
+    mut commands: Commands,
+) {
+    let mut dirty: HashSet<Entity> = changed
+        .iter()
+        .chain(&missing)
+        .chain(removed_parents.iter())
+        .chain(removed_interpolations.iter())
+        .collect();
+
+    let despawned: HashSet<Entity> =
+        removed_globals.iter().filter(|&entity| locals.get(entity).is_err()).collect();
+    if !despawned.is_empty() {
+        let orphans = parents.iter().filter(|(_, parent)| despawned.contains(&parent.0));
+        for (orphan, _) in orphans {
+            // The last world transform of the child becomes its own, once it has no parent
+            let mut orphan_commands = commands.entity(orphan);
+            if let Ok(global) = globals.get(orphan) {
+                orphan_commands.insert(decompose(&global.0));
+            }
+            orphan_commands.remove::<Parent>();
+            dirty.remove(&orphan);
+        }
+    }
+    if dirty.is_empty() {
+        return;
+    }
+    // Children move with their parents, however far up the change was
+    let moved_with_parent: Vec<_> = parents
+        .iter()
+        .filter(|&(_, &Parent(parent))| has_changed_ancestor(parent, &parents, &dirty))
+        .map(|(entity, _)| entity)
+        .collect();
+    dirty.extend(moved_with_parent);
+
+    let mut computed = HashMap::new();
+    for &entity in &dirty {
+        world_matrix(entity, &locals, &globals, &dirty, &mut computed, 0);
+    }
+    for (entity, model) in computed {
+        match globals.get_mut(entity) {
+            Ok(mut global) => global.0 = model,
+            Err(_) => {
+                commands.entity(entity).insert(GlobalTransform(model));
+            }
+        }
+    }
+}
+
+/// World matrix of an entity from its own transform and the ones of its parents, for when the
+/// [`GlobalTransform`] may not be updated yet
+pub fn world_matrix_of(world: &World, entity: Entity) -> glm::Mat4 {
+    std::iter::successors(Some(entity), |&entity| world.get::<Parent>(entity).map(|p| p.0))
+        .take(MAX_DEPTH)
+        .fold(glm::Mat4::identity(), |model, entity| {
+            let local = match (
+                world.get::<Position>(entity),
+                world.get::<Rotation>(entity),
+                world.get::<Scale>(entity),
+            ) {
+                (Some(&pos), Some(&rot), Some(&scale)) => model_matrix(pos, rot, scale),
+                _ => glm::Mat4::identity(),
+            };
+            local * model
+        })
+}
+
+/// Position, rotation and scale of a model matrix, leaving out any shear from scaling a rotated
+/// entity unevenly
+pub fn decompose(model: &glm::Mat4) -> (Position, Rotation, Scale) {
+    let column = |i: usize| glm::vec4_to_vec3(&model.column(i).into_owned());
+    let (x, y, z) = (column(0), column(1), column(2));
+    let mut scale = glm::vec3(x.norm(), y.norm(), z.norm());
+    // A mirrored matrix is a rotation with one axis flipped
+    if glm::mat4_to_mat3(model).determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+    let axes = glm::Mat3::from_columns(&[x / scale.x, y / scale.y, z / scale.z]);
+    let position = column(3);
+    (
+        Position::new(position.x, position.y, position.z),
+        Rotation::from_quat(glm::mat3_to_quat(&axes)),
+        Scale::new(scale.x, scale.y, scale.z),
+    )
+}
+
+/// Give an entity another parent, or none, keeping where it is in the world
+pub fn set_parent(world: &mut World, entity: Entity, parent: Option<Entity>) {
+    let parent_model = parent.map_or_else(glm::Mat4::identity, |p| world_matrix_of(world, p));
+    let local = glm::inverse(&parent_model) * world_matrix_of(world, entity);
+    let (position, rotation, scale) = decompose(&local);
+    let mut entity = world.entity_mut(entity);
+    entity.insert((position, rotation, scale));
+    match parent {
+        Some(parent) => {
+            entity.insert(Parent(parent));
+        }
+        None => {
+            entity.remove::<Parent>();
+        }
+    }
+}
+
+/// Move the children of an entity into its own parent, or out of any parent, keeping where they
+/// are in the world
+///
+/// Called before an entity is despawned or pooled, so its children are not left with a
+/// [`Parent`] that is gone.
+pub fn unparent_children(world: &mut World, entity: Entity) {
+    let parent =
+        world.get::<Parent>(entity).map(|p| p.0).filter(|&p| world.get_entity(p).is_some());
+    let mut query = world.query::<(Entity, &Parent)>();
+    let children: Vec<_> =
+        query.iter(world).filter(|(_, p)| p.0 == entity).map(|(child, _)| child).collect();
+    for child in children {
+        set_parent(world, child, parent);
+    }
+}
+
+/// Whether `parent` or one of the parents above it changed
+fn has_changed_ancestor(
+    parent: Entity,
+    parents: &Query<(Entity, &Parent)>,
+    dirty: &HashSet<Entity>,
+) -> bool {
+    std::iter::successors(Some(parent), |&entity| parents.get(entity).ok().map(|(_, p)| p.0))
+        .take(MAX_DEPTH)
+        .any(|entity| dirty.contains(&entity))
+}
+
+/// World matrix of `entity`, computing the ones of its parents first when they changed too
+///
+/// Returns `None` for entities without a transform.
+fn world_matrix(
+    entity: Entity,
+    locals: &Query<LocalQuery>,
+    globals: &Query<&mut GlobalTransform>,
+    dirty: &HashSet<Entity>,
+    computed: &mut HashMap<Entity, glm::Mat4>,
+    depth: usize,
+) -> Option<glm::Mat4> {
+    if let Some(&model) = computed.get(&entity) {
+        return Some(model);
+    }
+    if !dirty.contains(&entity) {
+        return globals.get(entity).ok().map(|global| global.0);
+    }
+    let (&pos, &rot, &scale, interpolation, parent) = locals.get(entity).ok()?;
+    let local = interpolation.map_or_else(|| model_matrix(pos, rot, scale), |i| i.model);
+    let parent = match parent {
+        Some(&Parent(parent)) if depth < MAX_DEPTH => {
+            world_matrix(parent, locals, globals, dirty, computed, depth + 1)
+        }
+        _ => None,
+    };
+    let model = parent.map_or(local, |parent| parent * local);
+    computed.insert(entity, model);
+    Some(model)
+}
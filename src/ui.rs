@@ -1,13 +1,59 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use egui::emath::RectTransform;
+use egui::plot::{Line, Plot, PlotPoints};
+use glow::{Context, HasContext};
 use nalgebra_glm as glm;
-use tracing::warn;
+use tracing::{warn, Level};
 
-use crate::commands;
+use crate::annotation::{Annotation, Mark, Markup, MarkupTool};
+use crate::bevy_export::{BevyExportOptions, BEVY_SCENE_EXTENSION};
+use crate::code_editor::code_editor;
+use crate::command_palette::{self, PaletteCommand, PaletteEntry};
 use crate::components::{
-    CustomShader, CustomTexture, Mesh, PointLight, Position, Rotation, Scale, Selected,
+    AnimationClip, AnimationPlayer, CameraComponent, CustomShader, CustomTexture, FollowPath,
+    Hidden, Interpolation, Keyframe, Locked, Lod, Mesh, Oscillator, PointLight, Position,
+    Properties, PropertyValue, Rotation, Rotator, Scale, Script, Selected, ShaderParams, Skeleton,
+    Tags,
+};
+use crate::config::{self, EditorConfig};
+use crate::console::{self, ConsoleCommand, ConsoleLog};
+use crate::entity_pool::Pooled;
+use crate::frame_limiter::{VsyncMode, FPS_LIMIT_RANGE, UNFOCUSED_FPS};
+use crate::gl_debug::{ErrorCheckMode, GlErrorChecks};
+use crate::input_map::{Action, InputMap};
+use crate::material::{MaterialHandle, MaterialId, MaterialLoader};
+use crate::mesh_cleanup::MeshCleanup;
+use crate::outliner::{OutlinerEntry, OutlinerFilter};
+use crate::palette::{Palette, PalettePreset};
+use crate::picking::Picker;
+use crate::plugin::PluginPanels;
+use crate::profiler::Profiler;
+use crate::registry::ComponentRegistry;
+use crate::resources::{
+    BoundsDisplay, BoxSelection, Camera, CameraFlight, DebugDraw, EffectQuality, EguiGlowRes,
+    Input, InspectedComponents, Lighting, LookDev, LookDevEnvironment, ModelLoader, Notification,
+    NotificationLevel, Notifications, Outliner, Placement, RenderSettings, SceneView,
+    ShaderGlobals, ShadowSettings, TerrainBrush, TextureDetails, TextureLoader, Time, UiState,
+    ViewBookmarks, ViewLayout, ViewPasses, ViewRect, WinitWindow, MSAA_SAMPLES, PCF_KERNEL_SIZES,
+    SHADOW_MAP_SIZES, VIEW_SLOTS,
 };
-use crate::resources::{EguiGlowRes, ModelLoader, TextureLoader, Time, UiState, WinitWindow};
-use crate::shader::ShaderType;
+use crate::scripting::{ScriptLog, ScriptRuntime, NEW_SCRIPT};
+use crate::settings::{DockArea, DockLayout, Panel, Theme};
+use crate::shader::{Severity, ShaderDiagnostic, ShaderType, UniformValue};
+use crate::snapping::{self, Snapping};
+use crate::terrain::{BrushTool, Terrain, LAYERS};
+use crate::texture_import::{self, Channel, Filter, TextureQuality, WrapMode};
+use crate::updates::{self, UpdateChecker, CURRENT_VERSION};
+use crate::usage::{self, Summary, UsageLog, USAGE_LOG_PATH};
+use crate::variables::{BindingTarget, Bindings, SceneVariables, VariableValue};
+use crate::variants::{self, Field, SceneVariants, Variant};
+use crate::watchdog::StalledFrame;
+use crate::{backup, commands, image_export, shader_cache};
 
 type EntityQuery<'a> = (
     Entity,
@@ -15,92 +61,539 @@ type EntityQuery<'a> = (
     &'a mut Rotation,
     &'a mut Scale,
     Option<&'a mut CustomShader>,
-    Option<&'a PointLight>,
+    Option<&'a mut PointLight>,
+    Option<&'a mut Rotator>,
+    Option<&'a mut Oscillator>,
+    Option<&'a mut FollowPath>,
+    Option<&'a mut AnimationClip>,
+    Option<&'a Skeleton>,
+    Option<&'a mut AnimationPlayer>,
+    Option<&'a mut ShaderParams>,
+    (
+        Option<&'a mut Properties>,
+        Option<&'a mut Tags>,
+        Option<&'a mut Script>,
+        Option<&'a mut Lod>,
+        Option<&'a mut CameraComponent>,
+    ),
+    Option<&'a mut Bindings>,
 );
 
+/// Resources edited in the Preferences window
+#[derive(SystemParam)]
+pub struct Preferences<'w, 's> {
+    input_map: ResMut<'w, InputMap>,
+    config: ResMut<'w, EditorConfig>,
+    update_checker: ResMut<'w, UpdateChecker>,
+    usage: ResMut<'w, UsageLog>,
+    palette: ResMut<'w, Palette>,
+    window: Res<'w, WinitWindow>,
+    /// Theme and accent color currently set on the egui context
+    applied_theme: Local<'s, Option<(Theme, Option<egui::Color32>)>>,
+}
+
+/// Scene-wide values edited in the Utilities panel
+#[derive(SystemParam)]
+pub struct SceneParams<'w, 's> {
+    shader_globals: ResMut<'w, ShaderGlobals>,
+    lighting: ResMut<'w, Lighting>,
+    look_dev: ResMut<'w, LookDev>,
+    variables: ResMut<'w, SceneVariables>,
+    /// Changed through commands, as switching variants sets fields of many entities
+    variants: Res<'w, SceneVariants>,
+    hidden: Query<'w, 's, (), With<Hidden>>,
+    locked: Query<'w, 's, (), With<Locked>>,
+}
+
+/// Loaded assets, and the materials of entities
+#[derive(SystemParam)]
+pub struct Assets<'w, 's> {
+    model_loader: Res<'w, ModelLoader>,
+    texture_loader: Res<'w, TextureLoader>,
+    material_loader: Res<'w, MaterialLoader>,
+    material_handles: Query<'w, 's, &'static MaterialHandle>,
+}
+
+/// Resources shown in the Performance window
+#[derive(SystemParam)]
+pub struct Diagnostics<'w> {
+    time: Res<'w, Time>,
+    profiler: Res<'w, Profiler>,
+    picker: Res<'w, Picker>,
+    error_checks: ResMut<'w, GlErrorChecks>,
+}
+
+/// Registered components of the selected entity and the entities listed in the outliner
+#[derive(SystemParam)]
+pub struct Inspection<'w> {
+    registry: Res<'w, ComponentRegistry>,
+    inspected: ResMut<'w, InspectedComponents>,
+    outliner: Res<'w, Outliner>,
+}
+
+/// Log lines and the errors and output of scripts, shown in the Console and Script panels
+#[derive(SystemParam)]
+pub struct Output<'w> {
+    log: Res<'w, ConsoleLog>,
+    script_runtime: Res<'w, ScriptRuntime>,
+    script_log: ResMut<'w, ScriptLog>,
+}
+
+/// Resources used by the Views menu and the scene view
+#[derive(SystemParam)]
+pub struct CameraViews<'w> {
+    camera: Res<'w, Camera>,
+    bookmarks: ResMut<'w, ViewBookmarks>,
+    flight: ResMut<'w, CameraFlight>,
+    layout: ResMut<'w, ViewLayout>,
+    scene_view: ResMut<'w, SceneView>,
+    box_selection: Res<'w, BoxSelection>,
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn run_ui(
-    mut egui_glow: ResMut<EguiGlowRes>,
-    window: Res<WinitWindow>,
+    egui_glow: Res<EguiGlowRes>,
     mut state: ResMut<UiState>,
-    model_loader: Res<ModelLoader>,
-    texture_loader: Res<TextureLoader>,
-    time: Res<Time>,
+    assets: Assets,
+    diagnostics: Diagnostics,
+    mut views: CameraViews,
+    inspection: Inspection,
+    mut debug_draw: ResMut<DebugDraw>,
+    mut scene_params: SceneParams,
+    mut prefs: Preferences,
+    mut notifications: ResMut<Notifications>,
+    mut placement: ResMut<Placement>,
+    mut plugin_panels: ResMut<PluginPanels>,
+    mut output: Output,
     mut selected_entities: Query<EntityQuery, With<Selected>>,
-    all_mesh_entities: Query<Entity, With<Mesh>>,
+    all_mesh_entities: Query<Entity, (With<Mesh>, Without<Pooled>)>,
     mut commands: Commands,
 ) {
     // Need to reborrow for borrow checker to understand that we borrow different fields
     let state = &mut *state;
+    let Diagnostics { time, profiler, picker, mut error_checks } = diagnostics;
+    let Assets { model_loader, texture_loader, material_loader, material_handles } = assets;
+    let Inspection { registry, mut inspected, outliner } = inspection;
+    let axis_colors = prefs.palette.axes.map(color32);
+    let snapping = prefs.config.snapping;
+
+    egui_glow.draw(|ctx| {
+        let theme = (prefs.config.theme, prefs.config.accent);
+        if *prefs.applied_theme != Some(theme) {
+            ctx.set_visuals(theme.0.visuals_with_accent(theme.1));
+            *prefs.applied_theme = Some(theme);
+        }
 
-    egui_glow.run(&window, |ctx| {
-        let selected = selected_entities.get_single_mut();
+        // The inspector edits one entity, and only counts the entities of a larger selection
+        let selection_count = selected_entities.iter().count();
+        let mut selected = selected_entities.get_single_mut();
 
         match state.editing_mode {
             None => {
                 egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
                     ui.horizontal_wrapped(|ui| {
+                        let editable = !state.view_only;
+                        ui.menu_button("File", |ui| {
+                            if ui.add_enabled(editable, egui::Button::new("📄 New Scene")).clicked()
+                            {
+                                state.scene_path.clear();
+                                commands.add(commands::new_scene);
+                                ui.close_menu();
+                            }
+                            let has_recent = !prefs.config.recent_files.is_empty();
+                            ui.add_enabled_ui(has_recent, |ui| {
+                                ui.menu_button("Open Recent", |ui| {
+                                    let recent = &prefs.config.recent_files;
+                                    if recent_files(ui, recent, state, &mut commands) {
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                    if ui.button("Remove Missing Files").clicked() {
+                                        prefs.config.remove_missing_recent_files();
+                                        save_config(&prefs.config);
+                                    }
+                                    if ui.button("Clear Recent").clicked() {
+                                        prefs.config.recent_files.clear();
+                                        save_config(&prefs.config);
+                                        ui.close_menu();
+                                    }
+                                });
+                            });
+                            let has_path = !state.scene_path.is_empty();
+                            let restore = ui
+                                .add_enabled(
+                                    has_path && editable,
+                                    egui::Button::new("🕘 Restore from Backup…"),
+                                )
+                                .on_disabled_hover_text(if editable {
+                                    "Save or open a scene first"
+                                } else {
+                                    "Not available in view-only mode"
+                                });
+                            if restore.clicked() {
+                                state.backups_open = true;
+                                ui.close_menu();
+                            }
+                            ui.add_enabled_ui(editable, |ui| {
+                                ui.menu_button("Import into Current Scene", |ui| {
+                                    if merge_menu(ui, state, axis_colors, &mut commands) {
+                                        ui.close_menu();
+                                    }
+                                });
+                            });
+                            ui.add_enabled_ui(has_path, |ui| {
+                                ui.menu_button("Export Bevy Scene", |ui| {
+                                    if bevy_export_menu(ui, state, &mut commands) {
+                                        ui.close_menu();
+                                    }
+                                });
+                            });
+                            ui.separator();
+                            if ui.button("Start Screen").clicked() {
+                                state.start_screen_open = true;
+                                ui.close_menu();
+                            }
+                        });
+                        ui.menu_button("View", |ui| {
+                            ui.label("Bounding boxes");
+                            let bounds = &mut debug_draw.bounding_boxes;
+                            ui.radio_value(bounds, BoundsDisplay::Off, "Off");
+                            ui.radio_value(bounds, BoundsDisplay::Selected, "Selected");
+                            ui.radio_value(bounds, BoundsDisplay::All, "All");
+                            ui.separator();
+
+                            ui.checkbox(&mut debug_draw.motion_vectors, "Motion vectors");
+                            ui.checkbox(&mut debug_draw.light_ranges, "Light range")
+                                .on_hover_text("Show how far the selected point light reaches");
+                            ui.checkbox(&mut debug_draw.light_heatmap, "Light heatmap")
+                                .on_hover_text("Color pixels by how many point lights reach them");
+                            ui.separator();
+
+                            ui.label("GL error checks");
+                            let mode = &mut error_checks.mode;
+                            ui.radio_value(mode, ErrorCheckMode::Off, "Off");
+                            ui.radio_value(mode, ErrorCheckMode::Passes, "After each pass");
+                            ui.radio_value(mode, ErrorCheckMode::DrawCalls, "After each draw");
+                        });
+                        ui.menu_button("Views", |ui| {
+                            views_menu(ui, &mut views, &prefs.input_map, &state, &mut commands)
+                        });
+                        ui.menu_button("Snapping", |ui| {
+                            if snapping_menu(ui, &mut prefs.config.snapping) {
+                                save_config(&prefs.config);
+                            }
+                        });
+                        ui.menu_button("Placement", |ui| {
+                            placement_menu(ui, &mut placement, &model_loader)
+                        });
+                        ui.menu_button("Help", |ui| {
+                            if ui.button("🐞 Report Issue…").clicked() {
+                                state.report_issue_open = true;
+                                ui.close_menu();
+                            }
+                            if ui.button("✨ What's New").clicked() {
+                                state.whats_new_open = true;
+                                ui.close_menu();
+                            }
+                        });
+                        ui.separator();
+
                         ui.toggle_value(&mut state.utilities_open, "🔧 Utilities");
+                        ui.toggle_value(&mut state.outliner_open, "🗂 Outliner");
                         ui.toggle_value(&mut state.performance_open, "⏱ Performance");
+                        ui.toggle_value(&mut state.timeline_open, "🎞 Timeline");
+                        ui.toggle_value(&mut state.script_open, "📜 Script");
+                        ui.toggle_value(&mut state.console_open, "🖥 Console");
+                        ui.toggle_value(&mut state.terrain_open, "⛰ Terrain");
+                        ui.toggle_value(&mut state.render_settings_open, "🎨 Render Settings");
+                        for panel in plugin_panels.iter_mut() {
+                            ui.toggle_value(&mut panel.open, panel.title);
+                        }
+                        ui.toggle_value(&mut state.preferences_open, "⚙ Preferences");
+                        ui.toggle_value(&mut state.view_only, "🔒 View only").on_hover_text(
+                            "Turn off editing, to show the scene without changing it",
+                        );
+                        if !scene_params.variants.variants.is_empty() {
+                            ui.separator();
+                            variant_picker(ui, &scene_params.variants, &mut commands);
+                        }
+                        ui.separator();
+
+                        ui.add(
+                            egui::TextEdit::singleline(&mut state.scene_path)
+                                .hint_text("scene.ron")
+                                .desired_width(200.0),
+                        );
+                        let has_path = !state.scene_path.is_empty();
+                        let save =
+                            ui.add_enabled(has_path && editable, egui::Button::new("💾 Save"));
+                        if save.clicked() {
+                            let path = PathBuf::from(&state.scene_path);
+                            commands
+                                .add(move |world: &mut World| commands::save_scene(world, &path));
+                        }
+                        if ui.add_enabled(has_path, egui::Button::new("📂 Open")).clicked() {
+                            let path = PathBuf::from(&state.scene_path);
+                            commands
+                                .add(move |world: &mut World| commands::load_scene(world, &path));
+                        }
+                        ui.separator();
+                        ui.toggle_value(&mut state.render_image_open, "🖼 Render Image…");
+                        let screenshot = ui
+                            .button("📷 Screenshot")
+                            .on_hover_text("Capture the scene view to mark it up");
+                        if screenshot.clicked() {
+                            commands.add(commands::capture_screenshot);
+                        }
+
+                        let error_count = model_loader.errors().len()
+                            + texture_loader.errors().len()
+                            + material_loader.errors().len();
+                        if error_count > 0 {
+                            ui.separator();
+                            let label = match error_count {
+                                1 => "⚠ 1 asset error".to_owned(),
+                                n => format!("⚠ {n} asset errors"),
+                            };
+                            ui.toggle_value(&mut state.asset_errors_open, label);
+                        }
                     });
                 });
 
-                egui::SidePanel::left("left_panel").show_animated(
-                    ctx,
-                    state.utilities_open,
-                    |ui| {
-                        ui.heading("🔧 Utilities");
-                        if ui.button("Despawn all").clicked() {
-                            for entity in &all_mesh_entities {
-                                commands.entity(entity).add(commands::despawn_and_destroy);
+                // Shown before the docked panels, so it spans the whole width of the window
+                egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+                    let entity_count = all_mesh_entities.iter().count();
+                    let frame_time = time.avg_frame_time_ms();
+                    status_bar(ui, &notifications, entity_count, selection_count, frame_time);
+                });
+
+                let open: Vec<_> = Panel::ALL
+                    .into_iter()
+                    .filter(|&panel| match panel {
+                        Panel::Utilities => state.utilities_open,
+                        Panel::Inspector => selection_count > 0,
+                        Panel::Timeline => state.timeline_open,
+                        Panel::Performance => state.performance_open,
+                        Panel::Outliner => state.outliner_open,
+                        Panel::Script => state.script_open,
+                        Panel::Console => state.console_open,
+                    })
+                    .collect();
+
+                let mut panel_contents = |ui: &mut egui::Ui, panel: Panel| match panel {
+                    Panel::Timeline => match &mut selected {
+                        Ok((_, pos, rotation, scale, _, _, _, _, _, Some(clip), ..)) => {
+                            timeline(ui, clip, pos, rotation, scale, !state.view_only);
+                        }
+                        Ok((_, _, _, _, _, _, _, _, _, None, ..)) if state.view_only => {
+                            ui.label("The selected entity has no animation clip");
+                        }
+                        Ok((entity, _, _, _, _, _, _, _, _, None, ..)) => {
+                            if ui.button("Add animation clip").clicked() {
+                                commands.entity(*entity).insert(AnimationClip::default());
                             }
                         }
+                        Err(_) => {
+                            ui.label("Select an entity to animate it");
+                        }
                     },
-                );
+                    Panel::Utilities if state.view_only => {
+                        ui.heading("Look-dev");
+                        look_dev_controls(ui, &mut scene_params.look_dev, &mut commands);
+                    }
+                    Panel::Utilities => {
+                        if ui.button("Despawn all").clicked() {
+                            commands.add(commands::despawn_all);
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut state.import_path)
+                                    .hint_text("model.gltf")
+                                    .desired_width(120.0),
+                            );
+                            let has_path = !state.import_path.is_empty();
+                            if ui.add_enabled(has_path, egui::Button::new("Import model")).clicked()
+                            {
+                                let path = PathBuf::from(&state.import_path);
+                                commands.add(move |world: &mut World| {
+                                    commands::import_model(world, &path)
+                                });
+                            }
+                        });
+                        ui.separator();
 
-                egui::SidePanel::right("right_panel").default_width(300.0).show_animated(
-                    ctx,
-                    selected.is_ok(),
-                    |ui| {
-                        let Ok((entity, mut pos, mut rotation, mut scale, _, point_light)) = selected else {
-                            unreachable!();
+                        ui.heading("Shader parameters");
+                        ui.label(
+                            "Available to custom shaders as user_params, saved with the scene",
+                        );
+                        ui.horizontal(|ui| {
+                            let params = scene_params.shader_globals.params.iter_mut();
+                            for (component, value) in
+                                ["x:", "y:", "z:", "w:"].into_iter().zip(params)
+                            {
+                                let label = ui.label(component);
+                                ui.add(egui::DragValue::new(value).speed(0.01))
+                                    .labelled_by(label.id);
+                            }
+                        });
+                        ui.separator();
+
+                        ui.heading("Variables");
+                        ui.label("Bind fields of entities to these in the inspector");
+                        variables_editor(
+                            ui,
+                            &mut scene_params.variables,
+                            &mut state.new_variable_name,
+                            axis_colors,
+                        );
+                        ui.separator();
+
+                        ui.heading("Variants");
+                        ui.label(
+                            "Versions of the scene with other lights, visibility or materials",
+                        );
+                        variants_editor(
+                            ui,
+                            &scene_params.variants,
+                            &mut state.new_variant_name,
+                            &state.scene_path,
+                            &mut commands,
+                        );
+                        ui.separator();
+
+                        ui.heading("Textures");
+                        texture_list(ui, &texture_loader, &mut commands);
+                        ui.separator();
+
+                        ui.heading("Lighting");
+                        let lighting = &mut *scene_params.lighting;
+                        egui::Grid::new("lighting_grid").show(ui, |ui| {
+                            ui.label("Sun direction");
+                            ui.horizontal(|ui| {
+                                let direction = lighting.sun.direction.iter_mut();
+                                xyz_drag_values(ui, axis_colors, direction, 0.01);
+                            });
+                            ui.end_row();
+                            color_edit(ui, "Ambient", &mut lighting.sun.ambient);
+                            color_edit(ui, "Diffuse", &mut lighting.sun.diffuse);
+                            color_edit(ui, "Specular", &mut lighting.sun.specular);
+                            ui.label("Exposure");
+                            ui.add(egui::Slider::new(&mut lighting.exposure, 0.0..=4.0));
+                            ui.end_row();
+                            color_edit(ui, "Background", &mut lighting.background);
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut state.lighting_path)
+                                    .hint_text("lighting.ron")
+                                    .desired_width(120.0),
+                            );
+                            let has_path = !state.lighting_path.is_empty();
+                            if ui.add_enabled(has_path, egui::Button::new("Export")).clicked() {
+                                let path = PathBuf::from(&state.lighting_path);
+                                commands.add(move |world: &mut World| {
+                                    commands::export_lighting(world, &path)
+                                });
+                            }
+                            if ui.add_enabled(has_path, egui::Button::new("Import")).clicked() {
+                                let path = PathBuf::from(&state.lighting_path);
+                                commands.add(move |world: &mut World| {
+                                    commands::import_lighting(world, &path)
+                                });
+                            }
+                        });
+                        ui.separator();
+
+                        ui.heading("Environment");
+                        environment_editor(ui, &mut scene_params.lighting);
+                        ui.separator();
+
+                        ui.heading("Look-dev");
+                        look_dev_controls(ui, &mut scene_params.look_dev, &mut commands);
+                    }
+                    Panel::Inspector => {
+                        let Ok((
+                            entity,
+                            pos,
+                            rotation,
+                            scale,
+                            custom_shader,
+                            point_light,
+                            rotator,
+                            oscillator,
+                            follow_path,
+                            _,
+                            skeleton,
+                            animation_player,
+                            shader_params,
+                            (properties, tags, _, lod, camera),
+                            bindings,
+                        )) = &mut selected
+                        else {
+                            ui.label(format!("{selection_count} entities selected"));
+                            return;
                         };
+                        // Reborrow so the fields can be borrowed separately
+                        let entity = *entity;
+                        let (pos, rotation, scale) = (&mut **pos, &mut **rotation, &mut **scale);
+                        if state.view_only {
+                            let player = animation_player.as_deref_mut();
+                            view_only_inspector(
+                                ui, entity, pos, rotation, scale, *skeleton, player,
+                            );
+                            if let Some(properties) = properties.as_deref() {
+                                ui.separator();
+                                property_list(ui, properties);
+                            }
+                            if let Some(tags) = tags.as_deref() {
+                                ui.separator();
+                                tag_list(ui, tags);
+                            }
+                            return;
+                        }
 
-                        ui.heading("Inspector");
                         ui.strong(format!("Entity {}", entity.index()));
                         ui.separator();
 
                         egui::Grid::new("inspector_grid").spacing((20.0, 10.0)).show(ui, |ui| {
                             ui.label("Position");
                             ui.horizontal(|ui| {
-                                ui.label("X:");
-                                ui.add(egui::DragValue::new(&mut pos.x).speed(0.1));
-                                ui.label("Y:");
-                                ui.add(egui::DragValue::new(&mut pos.y).speed(0.1));
-                                ui.label("Z:");
-                                ui.add(egui::DragValue::new(&mut pos.z).speed(0.1));
+                                snapped_xyz_drag_values(
+                                    ui,
+                                    axis_colors,
+                                    [&mut pos.x, &mut pos.y, &mut pos.z],
+                                    0.1,
+                                    snapping.position,
+                                );
                             });
                             ui.end_row();
 
                             ui.label("Rotation");
                             ui.horizontal(|ui| {
-                                ui.label("X:");
-                                ui.add(egui::DragValue::new(&mut rotation.x).speed(1.0));
-                                ui.label("Y:");
-                                ui.add(egui::DragValue::new(&mut rotation.y).speed(1.0));
-                                ui.label("Z:");
-                                ui.add(egui::DragValue::new(&mut rotation.z).speed(1.0));
+                                let mut euler = rotation.euler();
+                                snapped_xyz_drag_values(
+                                    ui,
+                                    axis_colors,
+                                    [&mut euler.x, &mut euler.y, &mut euler.z],
+                                    1.0,
+                                    snapping.rotation,
+                                );
+                                if euler != rotation.euler() {
+                                    *rotation = Rotation::from_euler(euler);
+                                }
                             });
                             ui.end_row();
 
                             ui.label("Scale");
                             ui.horizontal(|ui| {
-                                ui.label("X:");
-                                ui.add(egui::DragValue::new(&mut scale.x).speed(0.1));
-                                ui.label("Y:");
-                                ui.add(egui::DragValue::new(&mut scale.y).speed(0.1));
-                                ui.label("Z:");
-                                ui.add(egui::DragValue::new(&mut scale.z).speed(0.1));
+                                snapped_xyz_drag_values(
+                                    ui,
+                                    axis_colors,
+                                    [&mut scale.x, &mut scale.y, &mut scale.z],
+                                    0.1,
+                                    snapping.scale,
+                                );
                             });
                             ui.end_row();
 
@@ -112,8 +605,23 @@ pub fn run_ui(
                             }
                             ui.end_row();
 
+                            let material = material_handles.get(entity).ok().map(|handle| handle.0);
+                            ui.label("Material");
+                            ui.vertical(|ui| {
+                                material_editor(
+                                    ui,
+                                    &material_loader,
+                                    material,
+                                    &mut state.new_material_name,
+                                    entity,
+                                    &mut commands,
+                                );
+                            });
+                            ui.end_row();
+
                             ui.label("Custom Shader");
                             ui.vertical(|ui| {
+                                ui.set_enabled(material.is_none());
                                 if ui.button("Edit Vertex").clicked() {
                                     state.editing_mode = Some(ShaderType::Vertex);
                                 }
@@ -123,10 +631,26 @@ pub fn run_ui(
                                 if ui.button("Reset Shaders").clicked() {
                                     commands.entity(entity).add(commands::remove_custom_shader);
                                 }
+                                if let Some(error) =
+                                    custom_shader.as_ref().and_then(|cs| cs.error.as_deref())
+                                {
+                                    shader_compile_error(ui, error, entity, &mut commands);
+                                }
                             });
                             ui.end_row();
 
-                            ui.label("Change Model");
+                            if let Some(params) =
+                                shader_params.as_mut().filter(|p| !p.values.is_empty())
+                            {
+                                ui.label("Shader Parameters");
+                                ui.vertical(|ui| {
+                                    ui.set_enabled(material.is_none());
+                                    uniform_values(ui, &mut params.values);
+                                });
+                                ui.end_row();
+                            }
+
+                            let model_label = ui.label("Change Model");
                             ui.vertical(|ui| {
                                 egui::ComboBox::from_id_source("model_select")
                                     .selected_text(match &state.selected_model {
@@ -141,13 +665,25 @@ pub fn run_ui(
                                                 name,
                                             );
                                         }
-                                    });
+                                    })
+                                    .response
+                                    .labelled_by(model_label.id);
 
                                 if ui.button("Load").clicked() {
                                     if let Some(ref name) = state.selected_model {
                                         if let Some(vao) = model_loader.get(name) {
                                             let mesh = Mesh::from(vao);
                                             commands.entity(entity).insert(mesh);
+                                            if let Some(skin) = model_loader.skin(name) {
+                                                commands.entity(entity).insert((
+                                                    Skeleton::new(skin.clone()),
+                                                    AnimationPlayer::default(),
+                                                ));
+                                            } else {
+                                                commands
+                                                    .entity(entity)
+                                                    .remove::<(Skeleton, AnimationPlayer)>();
+                                            }
                                         } else {
                                             warn!("could not load model {:?}", name);
                                         }
@@ -156,8 +692,16 @@ pub fn run_ui(
                             });
                             ui.end_row();
 
+                            let lod = lod.as_deref_mut().filter(|lod| !lod.meshes.is_empty());
+                            if let Some(lod) = lod {
+                                ui.label("Level of Detail");
+                                ui.vertical(|ui| lod_editor(ui, lod));
+                                ui.end_row();
+                            }
+
                             ui.label("Texture");
                             ui.vertical(|ui| {
+                                ui.set_enabled(material.is_none());
                                 egui::ComboBox::from_label("Diffuse")
                                     .selected_text(match &state.selected_diffuse {
                                         Some(name) => name,
@@ -198,8 +742,36 @@ pub fn run_ui(
                                         }
                                     });
 
+                                egui::ComboBox::from_label("Normal")
+                                    .selected_text(match &state.selected_normal {
+                                        Some(name) => name,
+                                        None => "None",
+                                    })
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut state.selected_normal,
+                                            None,
+                                            "None",
+                                        );
+                                        for name in texture_loader.keys() {
+                                            ui.selectable_value(
+                                                &mut state.selected_normal,
+                                                Some(name.clone()),
+                                                name,
+                                            );
+                                        }
+                                    });
+
+                                ui.checkbox(&mut state.selected_alpha_test, "Alpha test")
+                                    .on_hover_text(
+                                        "Cut out the transparent parts of the diffuse texture",
+                                    );
+
                                 if ui.button("Load").clicked() {
-                                    let mut ct = CustomTexture::default();
+                                    let mut ct = CustomTexture {
+                                        alpha_test: state.selected_alpha_test,
+                                        ..Default::default()
+                                    };
                                     if let Some(ref name) = state.selected_diffuse {
                                         if let Some(texture) = texture_loader.get(name) {
                                             ct.diffuse = Some(*texture);
@@ -214,13 +786,20 @@ pub fn run_ui(
                                             warn!("could not load texture {:?}", name);
                                         }
                                     }
+                                    if let Some(ref name) = state.selected_normal {
+                                        if let Some(texture) = texture_loader.get(name) {
+                                            ct.normal = Some(*texture);
+                                        } else {
+                                            warn!("could not load texture {:?}", name);
+                                        }
+                                    }
                                     commands.entity(entity).insert(ct);
                                 }
                             });
                             ui.end_row();
 
                             ui.label("Light");
-                            ui.horizontal(|ui| {
+                            ui.vertical(|ui| {
                                 let mut checked = point_light.is_some();
                                 if ui.checkbox(&mut checked, "Point Light").changed() {
                                     if checked {
@@ -236,62 +815,3272 @@ pub fn run_ui(
                                         commands.entity(entity).remove::<PointLight>();
                                     }
                                 }
+                                if let Some(light) = point_light {
+                                    point_light_editor(ui, light);
+                                }
                             });
                             ui.end_row();
 
-                            ui.label("Commands");
-                            if ui.button("Despawn").clicked() {
-                                commands.entity(entity).add(commands::despawn_and_destroy);
-                            }
+                            ui.label("Camera");
+                            ui.vertical(|ui| {
+                                let mut checked = camera.is_some();
+                                if ui.checkbox(&mut checked, "Scene Camera").changed() {
+                                    if checked {
+                                        commands.entity(entity).insert(CameraComponent::default());
+                                    } else {
+                                        commands.entity(entity).remove::<CameraComponent>();
+                                    }
+                                }
+                                if let Some(camera) = camera {
+                                    camera_editor(ui, camera);
+                                }
+                            });
                             ui.end_row();
-                        });
-                    },
-                );
 
-                egui::Window::new("⏱ Performance").open(&mut state.performance_open).show(ctx, |ui| {
-                    ui.label(format!("Frame time: {}", time.avg_frame_time_ms()));
-                    ui.label(format!("FPS: {}", (1000.0 / time.avg_frame_time_ms()).round()));
-                });
-            }
-            Some(editing_mode) => {
-                if let Ok((entity, _, _, _, custom_shader, _)) = selected {
-                    match custom_shader {
-                        Some(mut cs) => {
-                            egui::CentralPanel::default().show(ctx, |ui| {
-                                ui.heading(format!("Editing {editing_mode} Shader"));
-                                let response = ui.button("Save and close");
-                                ui.separator();
+                            ui.label("Rotator");
+                            ui.vertical(|ui| {
+                                let mut checked = rotator.is_some();
+                                if ui.checkbox(&mut checked, "Enabled").changed() {
+                                    if checked {
+                                        commands
+                                            .entity(entity)
+                                            .insert(Rotator::new(0.0, 45.0, 0.0));
+                                    } else {
+                                        commands.entity(entity).remove::<Rotator>();
+                                    }
+                                }
+                                if let Some(rotator) = rotator {
+                                    ui.horizontal(|ui| {
+                                        let rotator = &mut **rotator;
+                                        xyz_drag_values(
+                                            ui,
+                                            axis_colors,
+                                            [&mut rotator.x, &mut rotator.y, &mut rotator.z],
+                                            1.0,
+                                        );
+                                    });
+                                }
+                            });
+                            ui.end_row();
 
-                                egui::ScrollArea::vertical().show(ui, |ui| {
-                                    let shader_source = match editing_mode {
-                                        ShaderType::Vertex => &mut cs.vert_source,
-                                        ShaderType::Fragment => &mut cs.frag_source,
-                                    };
+                            ui.label("Oscillator");
+                            ui.vertical(|ui| {
+                                let mut checked = oscillator.is_some();
+                                if ui.checkbox(&mut checked, "Enabled").changed() {
+                                    if checked {
+                                        commands.entity(entity).insert(Oscillator::default());
+                                    } else {
+                                        commands.entity(entity).remove::<Oscillator>();
+                                    }
+                                }
+                                if let Some(oscillator) = oscillator {
+                                    ui.horizontal(|ui| {
+                                        ui.label("Axis");
+                                        xyz_drag_values(
+                                            ui,
+                                            axis_colors,
+                                            oscillator.axis.iter_mut(),
+                                            0.1,
+                                        );
+                                    });
+                                    ui.add(
+                                        egui::Slider::new(&mut oscillator.amplitude, 0.0..=10.0)
+                                            .text("Amplitude"),
+                                    );
+                                    ui.add(
+                                        egui::Slider::new(&mut oscillator.frequency, 0.0..=5.0)
+                                            .text("Frequency"),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Follow Path");
+                            ui.vertical(|ui| {
+                                let mut checked = follow_path.is_some();
+                                if ui.checkbox(&mut checked, "Enabled").changed() {
+                                    if checked {
+                                        let start = glm::vec3(pos.x, pos.y, pos.z);
+                                        commands.entity(entity).insert(FollowPath::new(
+                                            vec![start, start + glm::vec3(5.0, 0.0, 0.0)],
+                                            2.0,
+                                            true,
+                                        ));
+                                    } else {
+                                        commands.entity(entity).remove::<FollowPath>();
+                                    }
+                                }
+                                if let Some(path) = follow_path {
+                                    ui.add(
+                                        egui::Slider::new(&mut path.speed, 0.0..=20.0)
+                                            .text("Speed"),
+                                    );
+                                    ui.checkbox(&mut path.looping, "Looping");
+
+                                    let mut remove = None;
+                                    for (i, point) in path.points.iter_mut().enumerate() {
+                                        ui.horizontal(|ui| {
+                                            ui.label(format!("{i}:"));
+                                            xyz_drag_values(ui, axis_colors, point.iter_mut(), 0.1);
+                                            if icon_button(ui, "🗑", "Remove point").clicked() {
+                                                remove = Some(i);
+                                            }
+                                        });
+                                    }
+                                    if let Some(i) = remove {
+                                        path.points.remove(i);
+                                    }
+                                    if ui.button("Add current position").clicked() {
+                                        path.points.push(glm::vec3(pos.x, pos.y, pos.z));
+                                    }
+                                }
+                            });
+                            ui.end_row();
+
+                            if let (Some(skeleton), Some(player)) = (skeleton, animation_player) {
+                                let skeleton_label = ui.label("Skeleton");
+                                ui.vertical(|ui| {
+                                    let animations = &skeleton.skin.animations;
+                                    egui::ComboBox::from_id_source("animation_select")
+                                        .selected_text(
+                                            match player.animation.and_then(|i| animations.get(i)) {
+                                                Some(animation) => &animation.name,
+                                                None => "Rest pose",
+                                            },
+                                        )
+                                        .show_ui(ui, |ui| {
+                                            ui.selectable_value(
+                                                &mut player.animation,
+                                                None,
+                                                "Rest pose",
+                                            );
+                                            for (i, animation) in animations.iter().enumerate() {
+                                                ui.selectable_value(
+                                                    &mut player.animation,
+                                                    Some(i),
+                                                    &animation.name,
+                                                );
+                                            }
+                                        })
+                                        .response
+                                        .labelled_by(skeleton_label.id);
 
+                                    ui.horizontal(|ui| {
+                                        let label =
+                                            if player.playing { "⏸ Pause" } else { "▶ Play" };
+                                        if ui.button(label).clicked() {
+                                            player.playing = !player.playing;
+                                        }
+                                        if ui.button("⏹ Stop").clicked() {
+                                            player.playing = false;
+                                            player.time = 0.0;
+                                        }
+                                        ui.checkbox(&mut player.looping, "Loop");
+                                    });
                                     ui.add(
-                                        egui::TextEdit::multiline(shader_source)
-                                            .code_editor()
-                                            .desired_width(f32::INFINITY),
+                                        egui::Slider::new(&mut player.speed, 0.0..=3.0)
+                                            .text("Speed"),
                                     );
                                 });
+                                ui.end_row();
+                            }
 
-                                if response.clicked() {
-                                    state.editing_mode = None;
+                            ui.label("Bindings");
+                            let mut targets = vec![BindingTarget::Position, BindingTarget::Scale];
+                            if point_light.is_some() {
+                                targets.extend(BindingTarget::LIGHT);
+                            }
+                            if let Some(params) = shader_params.as_deref() {
+                                let uniforms = params.values.keys().cloned();
+                                targets.extend(uniforms.map(BindingTarget::Uniform));
+                            }
+                            bindings_editor(
+                                ui,
+                                bindings.as_deref_mut(),
+                                targets,
+                                &scene_params.variables,
+                                shader_params.as_deref(),
+                                entity,
+                                &mut commands,
+                            );
+                            ui.end_row();
 
-                                    commands.entity(entity).add(commands::compile_custom_shader);
+                            ui.label("Visible");
+                            let mut visible = !scene_params.hidden.contains(entity);
+                            if ui.checkbox(&mut visible, "").changed() {
+                                if visible {
+                                    commands.entity(entity).remove::<Hidden>();
+                                } else {
+                                    commands.entity(entity).insert(Hidden);
+                                }
+                            }
+                            ui.end_row();
+
+                            ui.label("Locked");
+                            let mut locked = scene_params.locked.contains(entity);
+                            let response = ui.checkbox(&mut locked, "");
+                            let response = response.on_hover_text("Not selected by clicking it");
+                            if response.changed() {
+                                if locked {
+                                    commands.entity(entity).insert(Locked);
+                                } else {
+                                    commands.entity(entity).remove::<Locked>();
+                                }
+                            }
+                            ui.end_row();
+
+                            if let Some(variant) = scene_params.variants.active_variant() {
+                                let name = scene_params.variants.active().unwrap_or_default();
+                                ui.label(format!("In {name}"));
+                                let mut fields = vec![Field::Hidden, Field::Textures];
+                                if point_light.is_some() {
+                                    fields.push(Field::PointLight);
                                 }
+                                if shader_params.is_some() {
+                                    fields.push(Field::Uniforms);
+                                }
+                                let entity = Some(entity);
+                                variant_fields(ui, variant, entity, &fields, &mut commands);
+                                ui.end_row();
+                            }
+
+                            ui.label("Properties");
+                            ui.vertical(|ui| {
+                                let new_key = &mut state.new_property_key;
+                                let properties = properties.as_deref_mut();
+                                properties_editor(ui, properties, new_key, entity, &mut commands);
                             });
-                        }
-                        None => {
-                            commands.entity(entity).add(commands::add_custom_shader);
-                        }
-                    }
-                }
-            }
-        }
-    });
-}
+                            ui.end_row();
+
+                            ui.label("Tags");
+                            ui.vertical(|ui| {
+                                let tags = tags.as_deref_mut();
+                                tags_editor(ui, tags, &mut state.new_tag, entity, &mut commands);
+                            });
+                            ui.end_row();
+
+                            for (name, value) in &mut inspected.components {
+                                let Some(&registration) = registry.get(name) else {
+                                    continue;
+                                };
 
-pub fn paint_ui(mut egui_glow: ResMut<EguiGlowRes>, window: Res<WinitWindow>) {
+                                ui.label(*name);
+                                ui.vertical(|ui| {
+                                    match registration.inspect(value, ui) {
+                                        Ok(true) => {
+                                            let value = value.clone();
+                                            commands.add(move |world: &mut World| {
+                                                if let Err(e) =
+                                                    registration.insert(world, entity, value)
+                                                {
+                                                    warn!("could not update component: {e}");
+                                                }
+                                            });
+                                        }
+                                        Ok(false) => {}
+                                        // Show what can not be edited, such as enums
+                                        Err(e) => {
+                                            let s = ron::to_string(value).unwrap_or_default();
+                                            ui.monospace(s)
+                                                .on_hover_text(format!("Can not be edited: {e}"));
+                                        }
+                                    }
+                                    if ui.button("Remove").clicked() {
+                                        commands.add(move |world: &mut World| {
+                                            registration.remove(world, entity);
+                                        });
+                                    }
+                                });
+                                ui.end_row();
+                            }
+
+                            if registry.iter().next().is_some() {
+                                ui.label("");
+                                ui.menu_button("Add Component", |ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut state.component_filter)
+                                            .hint_text("Search"),
+                                    );
+                                    let found = registry.search(&state.component_filter);
+                                    if found.is_empty() {
+                                        ui.weak("No components found");
+                                    }
+                                    for &registration in found {
+                                        let name = registration.name();
+                                        let added =
+                                            inspected.components.iter().any(|(n, _)| *n == name);
+                                        let button = egui::Button::new(name);
+                                        let response = ui
+                                            .add_enabled(!added, button)
+                                            .on_disabled_hover_text("Already added");
+                                        if response.clicked() {
+                                            commands.add(move |world: &mut World| {
+                                                registration.insert_default(world, entity);
+                                            });
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                                ui.end_row();
+                            }
+
+                            let cleanup_label = ui.label("Mesh Cleanup");
+                            ui.vertical(|ui| {
+                                let settings = &mut state.mesh_cleanup;
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::DragValue::new(&mut settings.weld_tolerance)
+                                            .speed(0.0001)
+                                            .clamp_range(0.0..=1.0)
+                                            .max_decimals(4),
+                                    )
+                                    .labelled_by(cleanup_label.id)
+                                    .on_hover_text("Largest distance between welded vertices");
+                                    if ui.button("Weld Vertices").clicked() {
+                                        let cleanup = MeshCleanup::Weld {
+                                            tolerance: settings.weld_tolerance,
+                                        };
+                                        commands.add(move |world: &mut World| {
+                                            commands::clean_up_mesh(world, entity, cleanup)
+                                        });
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::DragValue::new(&mut settings.smoothing_angle)
+                                            .clamp_range(0.0..=180.0)
+                                            .suffix("°"),
+                                    )
+                                    .on_hover_text(
+                                        "Edges sharper than this stay hard, 0° gives flat shading",
+                                    );
+                                    if ui.button("Recompute Normals").clicked() {
+                                        let cleanup = MeshCleanup::RecomputeNormals {
+                                            smoothing_angle: settings.smoothing_angle,
+                                        };
+                                        commands.add(move |world: &mut World| {
+                                            commands::clean_up_mesh(world, entity, cleanup)
+                                        });
+                                    }
+                                });
+                                if ui.button("Remove Degenerate Triangles").clicked() {
+                                    commands.add(move |world: &mut World| {
+                                        commands::clean_up_mesh(
+                                            world,
+                                            entity,
+                                            MeshCleanup::RemoveDegenerateTriangles,
+                                        )
+                                    });
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Commands");
+                            if ui.button("Despawn").clicked() {
+                                commands.entity(entity).add(commands::despawn_and_destroy);
+                            }
+                            ui.end_row();
+                        });
+                    }
+                    Panel::Performance => {
+                        ui.label(format!("Frame time: {}", time.avg_frame_time_ms()));
+                        ui.label(format!("FPS: {}", (1000.0 / time.avg_frame_time_ms()).round()));
+                        let stats = profiler.stats();
+                        ui.label(format!("Draw calls: {}", stats.draw_calls));
+                        ui.label(format!("Triangles: {}", stats.triangles));
+                        // From a click to the entity ID under it being read back from the GPU
+                        match picker.latency() {
+                            Some(latency) => ui.label(format!(
+                                "Pick latency: {:.1} ms",
+                                latency.as_secs_f32() * 1000.0
+                            )),
+                            None => ui.label("Pick latency: -"),
+                        };
+                        ui.separator();
+
+                        egui::Grid::new("pass_timings").striped(true).show(ui, |ui| {
+                            ui.strong("Pass");
+                            ui.strong("CPU (ms)");
+                            ui.strong("GPU (ms)");
+                            ui.end_row();
+                            for timing in profiler.pass_timings() {
+                                ui.label(timing.name);
+                                ui.label(format!("{:.3}", timing.cpu_ms));
+                                match timing.gpu_ms {
+                                    Some(gpu_ms) => ui.label(format!("{gpu_ms:.3}")),
+                                    None => ui.label("-"),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                        ui.separator();
+
+                        let points: PlotPoints = profiler
+                            .frame_times()
+                            .enumerate()
+                            .map(|(i, ms)| [i as f64, ms as f64])
+                            .collect();
+                        Plot::new("frame_times")
+                            .height(120.0)
+                            .include_y(0.0)
+                            .allow_drag(false)
+                            .allow_zoom(false)
+                            .allow_scroll(false)
+                            .show_x(false)
+                            .show(ui, |plot_ui| {
+                                plot_ui.line(Line::new(points).name("Frame time (ms)"))
+                            });
+
+                        if error_checks.mode != ErrorCheckMode::Off {
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                ui.strong("GL errors");
+                                if ui.small_button("Clear").clicked() {
+                                    error_checks.clear();
+                                }
+                            });
+                            let mut failures = error_checks.failures().peekable();
+                            if failures.peek().is_none() {
+                                ui.label("None");
+                            }
+                            for failure in failures {
+                                ui.colored_label(ui.visuals().warn_fg_color, failure);
+                            }
+                        }
+                    }
+                    Panel::Outliner => {
+                        let can_group = !state.view_only && selection_count > 0;
+                        ui.horizontal(|ui| {
+                            let group = ui
+                                .add_enabled(can_group, egui::Button::new("Group selected"))
+                                .on_hover_text("Put the selection in a group that moves it along");
+                            if group.clicked() {
+                                commands.add(commands::group_selected);
+                            }
+                            let ungroup = ui
+                                .add_enabled(can_group, egui::Button::new("Ungroup"))
+                                .on_hover_text("Take the entities out of the selected groups");
+                            if ungroup.clicked() {
+                                commands.add(commands::ungroup_selected);
+                            }
+                        });
+                        let selected = selected.as_ref().ok().map(|(entity, ..)| *entity);
+                        let filter = &mut state.outliner_filter;
+                        outliner_list(ui, &outliner.entries, filter, selected, &mut commands);
+                    }
+                    Panel::Script => {
+                        let Ok((entity, .., (_, _, script), _)) = &mut selected else {
+                            ui.label("Select an entity to edit its script");
+                            return;
+                        };
+                        let error = output.script_runtime.error(*entity);
+                        let script = script.as_deref_mut();
+                        script_editor(ui, *entity, script, error, state, &mut commands);
+                    }
+                    Panel::Console => console_panel(ui, &mut output, state, &mut commands),
+                };
+
+                let layout = &mut prefs.config.layout;
+                let mut layout_changed = false;
+                for area in DockArea::DOCKED {
+                    let tabs = layout.tabs(area, &open);
+                    let size = layout.size(area);
+                    let contents = |ui: &mut egui::Ui| {
+                        layout_changed |= dock_tabs(ui, area, &tabs, layout, &mut panel_contents);
+                    };
+                    let response = match area {
+                        DockArea::Left => egui::SidePanel::left("left_panel")
+                            .default_width(size)
+                            .show_animated(ctx, !tabs.is_empty(), contents),
+                        DockArea::Right => egui::SidePanel::right("right_panel")
+                            .default_width(size)
+                            .show_animated(ctx, !tabs.is_empty(), contents),
+                        _ => egui::TopBottomPanel::bottom("bottom_panel")
+                            .resizable(true)
+                            .default_height(size)
+                            .show_animated(ctx, !tabs.is_empty(), contents),
+                    };
+                    if let Some(response) = response {
+                        let rect = response.response.rect;
+                        let size =
+                            if area == DockArea::Bottom { rect.height() } else { rect.width() };
+                        layout.set_size(area, size);
+                    }
+                }
+
+                let mut closed = Vec::new();
+                for panel in layout.tabs(DockArea::Floating, &open) {
+                    let mut window_open = true;
+                    let window = egui::Window::new(panel.label());
+                    // The inspector follows the selection, so it has no close button
+                    let window = if panel == Panel::Inspector {
+                        window
+                    } else {
+                        window.open(&mut window_open)
+                    };
+                    window.show(ctx, |ui| {
+                        layout_changed |= dock_menu(ui, panel, layout);
+                        ui.separator();
+                        panel_contents(ui, panel);
+                    });
+                    if !window_open {
+                        closed.push(panel);
+                    }
+                }
+                for panel in closed {
+                    match panel {
+                        Panel::Utilities => state.utilities_open = false,
+                        Panel::Timeline => state.timeline_open = false,
+                        Panel::Performance => state.performance_open = false,
+                        Panel::Outliner => state.outliner_open = false,
+                        Panel::Script => state.script_open = false,
+                        Panel::Console => state.console_open = false,
+                        Panel::Inspector => {}
+                    }
+                }
+                if layout_changed {
+                    save_config(&prefs.config);
+                }
+
+                let mut preferences_open = state.preferences_open;
+                egui::Window::new("⚙ Preferences")
+                    .open(&mut preferences_open)
+                    .show(ctx, |ui| preferences(ui, &mut prefs, state, &mut commands));
+                state.preferences_open = preferences_open;
+
+                let mut render_image_open = state.render_image_open;
+                egui::Window::new("🖼 Render Image")
+                    .open(&mut render_image_open)
+                    .show(ctx, |ui| render_image(ui, state, &mut commands));
+                state.render_image_open = render_image_open;
+
+                if let Some(markup) = &mut state.markup {
+                    let mut open = true;
+                    egui::Window::new("✏ Markup")
+                        .open(&mut open)
+                        .default_width(800.0)
+                        .show(ctx, |ui| markup_editor(ui, markup, &mut notifications));
+                    if !open {
+                        state.markup = None;
+                    }
+                }
+
+                if let Some(details) = &mut state.texture_details {
+                    let mut open = true;
+                    egui::Window::new(format!("🖼 Texture {}", details.name))
+                        .id(egui::Id::new("texture_details"))
+                        .open(&mut open)
+                        .default_width(400.0)
+                        .show(ctx, |ui| texture_details(ui, details, &mut commands));
+                    if !open {
+                        state.texture_details = None;
+                    }
+                }
+
+                egui::Window::new("📊 Usage")
+                    .open(&mut state.usage_open)
+                    .show(ctx, |ui| usage_summary(ui, &mut prefs.usage));
+
+                let mut backups_open = state.backups_open && !state.view_only;
+                egui::Window::new("🕘 Backups")
+                    .open(&mut backups_open)
+                    .show(ctx, |ui| backups(ui, &state.scene_path, &mut commands));
+                state.backups_open = backups_open;
+
+                let mut report_issue_open = state.report_issue_open;
+                egui::Window::new("🐞 Report Issue")
+                    .open(&mut report_issue_open)
+                    .show(ctx, |ui| report_issue(ui, state, &mut commands));
+                state.report_issue_open &= report_issue_open;
+
+                egui::Window::new("⚠ Asset Errors").open(&mut state.asset_errors_open).show(
+                    ctx,
+                    |ui| {
+                        let (models, textures) = (&model_loader, &texture_loader);
+                        asset_errors(ui, models, textures, &material_loader, &mut commands)
+                    },
+                );
+
+                if let Some(stalled) = &mut state.stalled_frame {
+                    egui::Window::new("⏳ Frame Stalled")
+                        .collapsible(false)
+                        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                        .show(ctx, |ui| stalled_frame(ui, stalled, &mut commands));
+                }
+
+                let mut start_screen_open = state.start_screen_open;
+                egui::Window::new("Welcome")
+                    .open(&mut start_screen_open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| start_screen(ui, &mut prefs.config, state, &mut commands));
+                state.start_screen_open &= start_screen_open;
+
+                egui::Window::new("✨ What's New").open(&mut state.whats_new_open).show(
+                    ctx,
+                    |ui| {
+                        ui.heading(format!("Version {CURRENT_VERSION}"));
+                        match updates::changelog(CURRENT_VERSION) {
+                            Some(changes) => {
+                                for line in changes.lines() {
+                                    ui.label(line);
+                                }
+                            }
+                            None => {
+                                ui.label("No changes listed for this version.");
+                            }
+                        }
+                    },
+                );
+
+                // Laid out last, so the scene gets the space the panels leave
+                show_scene(ctx, &mut views.scene_view, &views.layout, &views.box_selection);
+            }
+            Some(editing_mode) => {
+                if let Ok((entity, _, _, _, custom_shader, ..)) = selected {
+                    match custom_shader {
+                        Some(mut cs) => {
+                            egui::CentralPanel::default().show(ctx, |ui| {
+                                ui.heading(format!("Editing {editing_mode} Shader"));
+                                let (response, compile) = ui
+                                    .horizontal(|ui| {
+                                        (ui.button("Save and close"), ui.button("Compile"))
+                                    })
+                                    .inner;
+                                let cs = &mut *cs;
+                                if let Some(error) = &cs.error {
+                                    shader_compile_error(ui, error, entity, &mut commands);
+                                }
+                                let (shader_source, diagnostics) = match editing_mode {
+                                    ShaderType::Vertex => {
+                                        (&mut cs.vert_source, &cs.vert_diagnostics)
+                                    }
+                                    ShaderType::Fragment => {
+                                        (&mut cs.frag_source, &cs.frag_diagnostics)
+                                    }
+                                };
+                                shader_diagnostics(ui, diagnostics);
+                                ui.separator();
+
+                                egui::ScrollArea::vertical().show(ui, |ui| {
+                                    code_editor(ui, shader_source, diagnostics);
+                                });
+
+                                if response.clicked() {
+                                    state.editing_mode = None;
+
+                                    commands.entity(entity).add(commands::compile_custom_shader);
+                                } else if compile.clicked() {
+                                    commands.entity(entity).add(commands::compile_custom_shader);
+                                }
+                            });
+                        }
+                        None => {
+                            commands.entity(entity).add(commands::add_custom_shader);
+                        }
+                    }
+                }
+            }
+        }
+
+        if state.command_palette_open {
+            let entries = command_palette::entries(
+                &prefs.input_map,
+                model_loader.keys().map(String::as_str),
+            );
+            if let Some(command) = command_palette_window(ctx, state, &entries) {
+                run_palette_command(command, state, &mut notifications, &mut commands);
+            }
+        }
+        keyboard_shortcuts(ctx, &mut state.shortcuts_open, &prefs.input_map);
+
+        // Above the status bar
+        egui::Area::new("toasts")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                for notification in notifications.active() {
+                    let frame = egui::Frame::popup(ui.style());
+                    frame.show(ui, |ui| notification_label(ui, notification));
+                }
+            });
+    });
+}
+
+/// Search box with the commands that match it, returning the command picked with Enter or a click
+fn command_palette_window(
+    ctx: &egui::Context,
+    state: &mut UiState,
+    entries: &[PaletteEntry],
+) -> Option<PaletteCommand> {
+    let mut picked = None;
+    let mut open = true;
+    egui::Window::new("Command palette")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            let search = egui::TextEdit::singleline(&mut state.palette_query)
+                .hint_text("Type to search commands")
+                .desired_width(f32::INFINITY);
+            let response = ui.add(search);
+            response.request_focus();
+            if response.changed() {
+                state.palette_selected = 0;
+            }
+
+            let matches = command_palette::search(entries, &state.palette_query);
+            let [up, down, enter, escape] =
+                [egui::Key::ArrowUp, egui::Key::ArrowDown, egui::Key::Enter, egui::Key::Escape]
+                    .map(|key| ui.input(|i| i.key_pressed(key)));
+            let last = matches.len().saturating_sub(1);
+            if down {
+                state.palette_selected = (state.palette_selected + 1).min(last);
+            }
+            if up {
+                state.palette_selected = state.palette_selected.saturating_sub(1);
+            }
+
+            egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                if matches.is_empty() {
+                    ui.weak("No command matches");
+                }
+                for (i, entry) in matches.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let highlighted = i == state.palette_selected;
+                        let label = ui.selectable_label(highlighted, &entry.label);
+                        if highlighted && (up || down) {
+                            label.scroll_to_me(None);
+                        }
+                        if label.clicked() {
+                            picked = Some(entry.command.clone());
+                        }
+                        if let Some(binding) = entry.binding {
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                ui.weak(binding.to_string());
+                            });
+                        }
+                    });
+                }
+            });
+
+            if enter {
+                picked = picked.or_else(|| {
+                    matches.get(state.palette_selected).map(|entry| entry.command.clone())
+                });
+            }
+            if escape {
+                open = false;
+            }
+        });
+    state.command_palette_open = open && picked.is_none();
+    picked
+}
+
+fn run_palette_command(
+    command: PaletteCommand,
+    state: &mut UiState,
+    notifications: &mut Notifications,
+    commands: &mut Commands,
+) {
+    let changes_scene = matches!(
+        command,
+        PaletteCommand::NewScene
+            | PaletteCommand::SaveScene
+            | PaletteCommand::Spawn(_)
+            | PaletteCommand::GroupSelected
+            | PaletteCommand::Ungroup
+            | PaletteCommand::AddCamera
+    );
+    if changes_scene && state.view_only {
+        notifications.warning("The scene is open for viewing only");
+        return;
+    }
+    match command {
+        PaletteCommand::Action(action) => {
+            commands.add(move |world: &mut World| world.resource_mut::<Input>().trigger(action));
+        }
+        PaletteCommand::TogglePanel(panel) => {
+            if let Some(open) = panel_open(state, panel) {
+                *open = !*open;
+            }
+        }
+        PaletteCommand::NewScene => {
+            state.scene_path.clear();
+            commands.add(commands::new_scene);
+        }
+        PaletteCommand::SaveScene if state.scene_path.is_empty() => {
+            notifications.warning("Type a file name for the scene in the toolbar to save it");
+        }
+        PaletteCommand::SaveScene => {
+            let path = PathBuf::from(&state.scene_path);
+            commands.add(move |world: &mut World| commands::save_scene(world, &path));
+        }
+        PaletteCommand::GroupSelected => commands.add(commands::group_selected),
+        PaletteCommand::Ungroup => commands.add(commands::ungroup_selected),
+        PaletteCommand::AddCamera => commands.add(commands::add_camera),
+        PaletteCommand::Spawn(model) => {
+            commands.add(move |world: &mut World| {
+                let spawn = ConsoleCommand::Spawn { model, position: Position::default() };
+                if let Err(e) = spawn.execute(world) {
+                    warn!("could not spawn from the command palette: {e}");
+                }
+            });
+        }
+    }
+}
+
+/// Whether a panel is open, for the panels that are opened and closed by hand
+fn panel_open(state: &mut UiState, panel: Panel) -> Option<&mut bool> {
+    match panel {
+        Panel::Utilities => Some(&mut state.utilities_open),
+        // Shown while something is selected
+        Panel::Inspector => None,
+        Panel::Timeline => Some(&mut state.timeline_open),
+        Panel::Performance => Some(&mut state.performance_open),
+        Panel::Outliner => Some(&mut state.outliner_open),
+        Panel::Script => Some(&mut state.script_open),
+        Panel::Console => Some(&mut state.console_open),
+    }
+}
+
+/// Every action with the key or mouse button bound to it
+fn keyboard_shortcuts(ctx: &egui::Context, open: &mut bool, input_map: &InputMap) {
+    egui::Window::new("⌨ Keyboard Shortcuts")
+        .open(open)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            let max_height = ctx.screen_rect().height() * 0.7;
+            egui::ScrollArea::vertical().max_height(max_height).show(ui, |ui| {
+                egui::Grid::new("keyboard_shortcuts").striped(true).show(ui, |ui| {
+                    for action in Action::all() {
+                        ui.label(action.label());
+                        ui.monospace(input_map.get(action).to_string());
+                        ui.end_row();
+                    }
+                });
+            });
+            ui.separator();
+            ui.weak("Bindings can be changed in Preferences");
+        });
+}
+
+/// Buttons that open the recent scenes, returning whether one was clicked
+///
+/// Scenes that no longer exist are shown but can not be clicked.
+fn recent_files(
+    ui: &mut egui::Ui,
+    recent: &[PathBuf],
+    state: &mut UiState,
+    commands: &mut Commands,
+) -> bool {
+    let mut clicked = false;
+    for file in recent {
+        let exists = file.exists();
+        let button = ui
+            .add_enabled(exists, egui::Button::new(file.display().to_string()))
+            .on_disabled_hover_text("The file was moved or deleted");
+        if button.clicked() {
+            state.scene_path = file.display().to_string();
+            let path = file.clone();
+            commands.add(move |world: &mut World| commands::load_scene(world, &path));
+            clicked = true;
+        }
+    }
+    clicked
+}
+
+/// Shown on startup, to start a new scene or go back to a recent one
+fn start_screen(
+    ui: &mut egui::Ui,
+    config: &mut EditorConfig,
+    state: &mut UiState,
+    commands: &mut Commands,
+) {
+    if ui.add_enabled(!state.view_only, egui::Button::new("📄 New Scene")).clicked() {
+        state.scene_path.clear();
+        commands.add(commands::new_scene);
+        state.start_screen_open = false;
+    }
+    ui.separator();
+
+    ui.heading("Open Recent");
+    if config.recent_files.is_empty() {
+        ui.label("Scenes you open or save are listed here");
+    } else if recent_files(ui, &config.recent_files, state, commands) {
+        state.start_screen_open = false;
+    }
+    ui.separator();
+
+    if ui.checkbox(&mut config.start_screen, "Show on startup").changed() {
+        save_config(config);
+    }
+}
+
+/// Scene file to import into the open scene, with where to put it and whether to rename its
+/// variables and variants, returning whether it was imported
+fn merge_menu(
+    ui: &mut egui::Ui,
+    state: &mut UiState,
+    axis_colors: [egui::Color32; 3],
+    commands: &mut Commands,
+) -> bool {
+    ui.add(
+        egui::TextEdit::singleline(&mut state.merge_path)
+            .hint_text("chunk.ron")
+            .desired_width(200.0),
+    );
+    ui.horizontal(|ui| {
+        ui.label("Offset");
+        xyz_drag_values(ui, axis_colors, state.merge.offset.iter_mut(), 0.1);
+    });
+    ui.checkbox(&mut state.merge.rename_duplicates, "Rename clashing variables and variants")
+        .on_hover_text("Otherwise they are shared with the ones of the same name in this scene");
+    let has_path = !state.merge_path.is_empty();
+    if !ui.add_enabled(has_path, egui::Button::new("Import")).clicked() {
+        return false;
+    }
+    let path = PathBuf::from(&state.merge_path);
+    let options = state.merge;
+    commands.add(move |world: &mut World| commands::import_scene(world, &path, &options));
+    true
+}
+
+/// Module of the game's components and a button to write the scene next to its file, returning
+/// whether it was exported
+fn bevy_export_menu(ui: &mut egui::Ui, state: &mut UiState, commands: &mut Commands) -> bool {
+    ui.label("Module of SceneModel, SceneCamera and SceneTags");
+    ui.add(
+        egui::TextEdit::singleline(&mut state.bevy_module)
+            .hint_text("my_game::level")
+            .desired_width(200.0),
+    )
+    .on_hover_text("Leave empty to write only Bevy's own components");
+    let path = Path::new(&state.scene_path).with_extension(BEVY_SCENE_EXTENSION);
+    if !ui.button(format!("Save {}", path.display())).clicked() {
+        return false;
+    }
+    let module = Some(state.bevy_module.trim()).filter(|m| !m.is_empty()).map(str::to_owned);
+    let options = BevyExportOptions { module };
+    commands.add(move |world: &mut World| commands::export_bevy_scene(world, &path, &options));
+    true
+}
+
+/// Backups of the open scene, newest first, each with a button to restore it
+fn backups(ui: &mut egui::Ui, scene_path: &str, commands: &mut Commands) {
+    if scene_path.is_empty() {
+        ui.label("Save or open a scene to see its backups");
+        return;
+    }
+    let scene = PathBuf::from(scene_path);
+    let backups = backup::list(&scene);
+    if backups.is_empty() {
+        ui.label(format!("No backups in {}", backup::backup_dir(&scene).display()));
+        return;
+    }
+
+    ui.label("Restoring replaces the scene file, which is backed up first.");
+    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+        egui::Grid::new("backups_grid").striped(true).show(ui, |ui| {
+            for backup in backups {
+                let (date, time) = backup.timestamp.split_once('_').unwrap_or_default();
+                ui.label(format!("{date} {} UTC", time.replace('-', ":")));
+                if ui.button("Restore").clicked() {
+                    let scene = scene.clone();
+                    commands.add(move |world: &mut World| {
+                        commands::restore_backup(world, &backup.path, &scene)
+                    });
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
+
+fn save_config(config: &EditorConfig) {
+    if let Err(e) = config.save(config::config_path()) {
+        warn!("could not save editor configuration: {e}");
+    }
+}
+
+fn preferences(
+    ui: &mut egui::Ui,
+    prefs: &mut Preferences,
+    state: &mut UiState,
+    commands: &mut Commands,
+) {
+    let input_map = &mut *prefs.input_map;
+    let rebinding = &mut state.rebinding;
+    ui.heading("Input bindings");
+    egui::Grid::new("input_bindings").striped(true).show(ui, |ui| {
+        for action in Action::all() {
+            ui.label(action.label());
+            if *rebinding == Some(action) {
+                if ui.button("Press a key or button...").clicked() {
+                    *rebinding = None;
+                }
+            } else if ui.button(input_map.get(action).to_string()).clicked() {
+                *rebinding = Some(action);
+            }
+            ui.end_row();
+        }
+    });
+
+    if ui.button("Reset to defaults").clicked() {
+        *input_map = InputMap::default();
+        *rebinding = None;
+        if let Err(e) = input_map.save(config::config_path()) {
+            warn!("could not save input bindings: {e}");
+        }
+    }
+    ui.separator();
+
+    ui.heading("Camera");
+    let camera = &mut prefs.config.camera;
+    let mut config_changed = false;
+    egui::Grid::new("camera_preferences").show(ui, |ui| {
+        ui.label("Move speed");
+        let speed =
+            egui::DragValue::new(&mut camera.move_speed).speed(0.1).clamp_range(0.1..=100.0);
+        config_changed |= ui.add(speed).changed();
+        ui.end_row();
+        ui.label("Look sensitivity");
+        let sensitivity =
+            egui::DragValue::new(&mut camera.look_sensitivity).speed(0.01).clamp_range(0.01..=5.0);
+        config_changed |= ui.add(sensitivity).changed();
+        ui.end_row();
+    });
+    config_changed |= ui.checkbox(&mut camera.invert_y, "Invert vertical look").changed();
+    ui.separator();
+
+    ui.heading("Appearance");
+    egui::ComboBox::from_label("Theme").selected_text(prefs.config.theme.label()).show_ui(
+        ui,
+        |ui| {
+            for theme in Theme::ALL {
+                config_changed |=
+                    ui.selectable_value(&mut prefs.config.theme, theme, theme.label()).changed();
+            }
+        },
+    );
+    ui.horizontal(|ui| {
+        let mut custom_accent = prefs.config.accent.is_some();
+        let checkbox = ui.checkbox(&mut custom_accent, "Accent color");
+        if checkbox.on_hover_text("Color of selections and links").changed() {
+            // Starts out as the color of the theme
+            let theme_accent = prefs.config.theme.visuals().selection.bg_fill;
+            prefs.config.accent = custom_accent.then_some(theme_accent);
+            config_changed = true;
+        }
+        if let Some(accent) = &mut prefs.config.accent {
+            let opaque = egui::color_picker::Alpha::Opaque;
+            let picker = egui::color_picker::color_edit_button_srgba(ui, accent, opaque);
+            config_changed |= picker.changed();
+        }
+    });
+    // Applied when the slider is let go, so the slider does not move away from the pointer
+    let mut percent = (prefs.config.ui_scale * 100.0).round();
+    let (start, end) = (*config::UI_SCALE_RANGE.start(), *config::UI_SCALE_RANGE.end());
+    let slider = egui::Slider::new(&mut percent, start * 100.0..=end * 100.0)
+        .step_by(5.0)
+        .suffix("%")
+        .text("Interface scale");
+    let response = ui.add(slider).on_hover_text(
+        "Size of the interface relative to what the display asks for, for displays where it \
+         comes out too small or too large",
+    );
+    if response.changed() {
+        prefs.config.ui_scale = percent / 100.0;
+    }
+    if response.drag_released() || (response.changed() && !response.dragged()) {
+        let pixels_per_point = prefs.config.pixels_per_point(prefs.window.scale_factor());
+        ui.ctx().set_pixels_per_point(pixels_per_point);
+        config_changed = true;
+    }
+    let previous_palette = prefs.config.palette;
+    egui::ComboBox::from_label("Colors").selected_text(prefs.config.palette.label()).show_ui(
+        ui,
+        |ui| {
+            for palette in PalettePreset::ALL {
+                ui.selectable_value(&mut prefs.config.palette, palette, palette.label());
+            }
+        },
+    );
+    if prefs.config.palette != previous_palette {
+        *prefs.palette = prefs.config.palette.palette();
+        config_changed = true;
+    }
+    ui.label("Used for the selection outline, bounding boxes and axis labels");
+    if config_changed {
+        save_config(&prefs.config);
+    }
+    ui.separator();
+
+    ui.heading("Backups");
+    let response = ui.horizontal(|ui| {
+        ui.label("Backups kept per scene");
+        ui.add(egui::DragValue::new(&mut prefs.config.backups).clamp_range(0..=100))
+    });
+    let response = response.inner.on_hover_text(format!(
+        "Copies of a scene are kept in a {} folder next to it each time it is saved. 0 turns \
+         backups off.",
+        backup::BACKUP_DIR
+    ));
+    if response.changed() {
+        save_config(&prefs.config);
+    }
+    ui.separator();
+
+    ui.heading("Rendering");
+    let mut config_changed = false;
+    let vsync = &mut prefs.config.vsync;
+    let combo = egui::ComboBox::from_label("Vsync").selected_text(vsync.label()).show_ui(ui, |ui| {
+        for mode in VsyncMode::ALL {
+            config_changed |= ui.selectable_value(vsync, mode, mode.label()).changed();
+        }
+    });
+    combo.response.on_hover_text(
+        "Wait for the display before showing each frame, so frames do not tear. Adaptive shows \
+         frames that missed a refresh right away instead of waiting for the next one.",
+    );
+    ui.horizontal(|ui| {
+        let mut limited = prefs.config.fps_limit.is_some();
+        if ui.checkbox(&mut limited, "Limit frame rate").changed() {
+            prefs.config.fps_limit = limited.then_some(60);
+            config_changed = true;
+        }
+        if let Some(fps) = &mut prefs.config.fps_limit {
+            let drag = egui::DragValue::new(fps).clamp_range(FPS_LIMIT_RANGE).suffix(" FPS");
+            config_changed |= ui.add(drag).changed();
+        }
+    });
+    let previous_samples = prefs.config.msaa_samples;
+    let msaa_label =
+        |samples: u32| if samples > 1 { format!("{samples}× MSAA") } else { "Off".to_owned() };
+    let combo = egui::ComboBox::from_label("Anti-aliasing")
+        .selected_text(msaa_label(previous_samples))
+        .show_ui(ui, |ui| {
+            for samples in MSAA_SAMPLES {
+                let label = msaa_label(samples);
+                ui.selectable_value(&mut prefs.config.msaa_samples, samples, label);
+            }
+        });
+    combo.response.on_hover_text(
+        "Smooths the edges of objects and selection outlines by lighting several samples per \
+         pixel, which takes more time on the graphics card for each sample",
+    );
+    if prefs.config.msaa_samples != previous_samples {
+        config_changed = true;
+        let samples = prefs.config.msaa_samples;
+        commands.add(move |world: &mut World| commands::set_msaa_samples(world, samples));
+    }
+    let response =
+        ui.checkbox(&mut prefs.config.idle_when_unfocused, "Slow down in the background");
+    let response = response.on_hover_text(format!(
+        "Draw at most {UNFOCUSED_FPS} frames per second while another window has focus, to save \
+         battery"
+    ));
+    config_changed |= response.changed();
+    if config_changed {
+        save_config(&prefs.config);
+    }
+    ui.separator();
+
+    ui.heading("Shaders");
+    let response = ui.checkbox(&mut prefs.config.shader_binaries, "Keep compiled shaders on disk");
+    let response = response.on_hover_text(format!(
+        "Custom shaders are stored in {} once they are compiled, and loaded from there the next \
+         time instead of being compiled again. Takes effect when the editor is started again.",
+        shader_cache::BINARY_DIR
+    ));
+    if response.changed() {
+        save_config(&prefs.config);
+    }
+    ui.separator();
+
+    ui.heading("Textures");
+    let previous_quality = prefs.config.texture_quality;
+    let combo = egui::ComboBox::from_label("Filtering quality")
+        .selected_text(previous_quality.label())
+        .show_ui(ui, |ui| {
+            for quality in TextureQuality::ALL {
+                ui.selectable_value(&mut prefs.config.texture_quality, quality, quality.label());
+            }
+        });
+    combo.response.on_hover_text(
+        "Lowers the filtering and anisotropy of every texture, for slow graphics cards. The \
+         import settings of the textures are kept.",
+    );
+    if prefs.config.texture_quality != previous_quality {
+        save_config(&prefs.config);
+        let quality = prefs.config.texture_quality;
+        commands.add(move |world: &mut World| commands::set_texture_quality(world, quality));
+    }
+    ui.separator();
+
+    ui.heading("Updates");
+    let response = ui.checkbox(&mut prefs.config.check_for_updates, "Check for updates on startup");
+    let response = response.on_hover_text(
+        "Asks GitHub for the latest release when the editor starts. Nothing about you or your \
+         scenes is sent.",
+    );
+    if response.changed() {
+        if prefs.config.check_for_updates {
+            prefs.update_checker.start();
+        }
+        save_config(&prefs.config);
+    }
+    if let Some(release) = &prefs.update_checker.available {
+        ui.hyperlink_to(format!("Version {} is available", release.version), &release.url);
+    }
+    if ui.button("What's new in this version").clicked() {
+        state.whats_new_open = true;
+    }
+    ui.separator();
+
+    ui.heading("Usage statistics");
+    let response = ui.checkbox(&mut prefs.config.usage_log, "Keep a local usage log");
+    let response = response.on_hover_text(format!(
+        "Records scenes opened, time spent in each tool and frame times to {USAGE_LOG_PATH} when \
+         the editor closes. Nothing leaves this computer."
+    ));
+    if response.changed() {
+        save_config(&prefs.config);
+    }
+    if ui.button("Show usage summary").clicked() {
+        state.usage_open = true;
+    }
+}
+
+fn usage_summary(ui: &mut egui::Ui, usage: &mut UsageLog) {
+    let session = usage.session();
+    ui.heading("This session");
+    ui.label(format!("Duration: {}", format_duration(session.duration_secs)));
+    usage_details(ui, "session", &Summary::new(std::slice::from_ref(&session)));
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        ui.heading("Previous sessions");
+        if ui.small_button("Reload").clicked() {
+            usage.history = None;
+        }
+    });
+    let history = usage.history.get_or_insert_with(|| {
+        usage::load_sessions(USAGE_LOG_PATH).unwrap_or_else(|e| {
+            warn!("could not read usage log: {e}");
+            Vec::new()
+        })
+    });
+    if history.is_empty() {
+        ui.label(format!("No sessions recorded in {USAGE_LOG_PATH}"));
+        return;
+    }
+    let summary = Summary::new(history);
+    ui.label(format!(
+        "{} sessions, {} in total",
+        summary.sessions,
+        format_duration(summary.total_secs)
+    ));
+    usage_details(ui, "history", &summary);
+}
+
+fn usage_details(ui: &mut egui::Ui, id: &str, summary: &Summary) {
+    egui::Grid::new(("usage_tools", id)).striped(true).show(ui, |ui| {
+        for (tool, secs) in &summary.tool_secs {
+            ui.label(tool);
+            ui.label(format_duration(*secs));
+            ui.end_row();
+        }
+    });
+    if !summary.scenes.is_empty() {
+        ui.label("Scenes opened:");
+        for (scene, count) in summary.scenes.iter().take(5) {
+            ui.label(format!("{scene} ({count}×)"));
+        }
+    }
+    if let Some(t) = summary.frame_times {
+        ui.label(format!(
+            "Frame time (ms): p50 {:.1}, p90 {:.1}, p99 {:.1}, max {:.1}",
+            t.p50, t.p90, t.p99, t.max
+        ));
+    }
+}
+
+fn views_menu(
+    ui: &mut egui::Ui,
+    views: &mut CameraViews,
+    input_map: &InputMap,
+    state: &UiState,
+    commands: &mut Commands,
+) {
+    let add = ui
+        .add_enabled(!state.view_only, egui::Button::new("Add camera"))
+        .on_hover_text("Add a scene camera where the editor camera is, looking the same way");
+    if add.clicked() {
+        commands.add(commands::add_camera);
+        ui.close_menu();
+    }
+    ui.checkbox(&mut views.layout.quad, "Quad view").on_hover_text(format!(
+        "Split the window into perspective, top, front and right views ({})",
+        input_map.get(Action::ToggleQuadView)
+    ));
+    let has_camera = views.layout.scene_camera().is_some();
+    let selected_camera = if has_camera { "" } else { ", once one is selected" };
+    ui.checkbox(&mut views.layout.look_through, "Look through selected camera").on_hover_text(
+        format!("Show the active view through the selected scene camera{selected_camera}"),
+    );
+    ui.checkbox(&mut views.layout.camera_preview, "Camera preview").on_hover_text(format!(
+        "Show what the selected scene camera sees in the bottom right corner{selected_camera}"
+    ));
+    let (width, height) = views.scene_view.size();
+    for (view, _) in views.layout.rects(width, height) {
+        let label = if views.layout.quad {
+            format!("{} passes", ViewLayout::NAMES[view])
+        } else {
+            "Render passes".to_owned()
+        };
+        ui.menu_button(label, |ui| pass_toggles(ui, views.layout.passes_mut(view)));
+    }
+    ui.separator();
+    for slot in 1..=VIEW_SLOTS {
+        let saved = views.bookmarks.views.get(&slot).copied();
+        ui.horizontal(|ui| {
+            let button = egui::Button::new(format!("View {slot}"))
+                .shortcut_text(input_map.get(Action::RecallView(slot)).to_string());
+            if ui.add_enabled(saved.is_some(), button).clicked() {
+                if let Some(pose) = saved {
+                    views.flight.start(views.camera.pose(), pose);
+                }
+                ui.close_menu();
+            }
+            let save = ui.small_button("Save").on_hover_text(format!(
+                "Save the current camera as view {slot} ({})",
+                input_map.get(Action::SaveView(slot))
+            ));
+            if save.clicked() {
+                views.bookmarks.views.insert(slot, views.camera.pose());
+            }
+            if saved.is_some() && icon_button(ui, "🗑", "Delete view").clicked() {
+                views.bookmarks.views.remove(&slot);
+            }
+        });
+    }
+    ui.label("Views are saved with the scene");
+}
+
+/// Checkboxes for the passes drawn in a view
+fn pass_toggles(ui: &mut egui::Ui, passes: &mut ViewPasses) {
+    ui.checkbox(&mut passes.shadows, "Shadows");
+    ui.checkbox(&mut passes.lighting, "Lighting")
+        .on_hover_text("Show the texture colors without lighting when off");
+    ui.checkbox(&mut passes.outlines, "Selection outline");
+    ui.checkbox(&mut passes.bounding_boxes, "Bounding boxes");
+    if ui.button("Reset").clicked() {
+        *passes = ViewPasses::default();
+    }
+}
+
+/// Show the scene texture in the space left between the panels
+///
+/// The scene is painted on the background layer rather than in a panel, so clicks on it are not
+/// taken by egui and still reach the editor input.
+fn show_scene(
+    ctx: &egui::Context,
+    scene_view: &mut SceneView,
+    layout: &ViewLayout,
+    box_selection: &BoxSelection,
+) {
+    scene_view.rect = ctx.available_rect();
+    scene_view.pixels_per_point = ctx.pixels_per_point();
+
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    if let Some(texture) = scene_view.texture {
+        // GL textures start at the bottom left, so flip the image vertically
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 1.0), egui::pos2(1.0, 0.0));
+        painter.image(texture, scene_view.rect, uv, egui::Color32::WHITE);
+    }
+    if layout.quad {
+        view_overlays(ctx, &painter, scene_view, layout);
+    }
+    let (width, height) = scene_view.size();
+    if let Some((_, rect)) = layout.preview(width, height) {
+        let area = view_area(scene_view, rect);
+        let visuals = &ctx.style().visuals;
+        painter.rect_stroke(area, 0.0, visuals.widgets.noninteractive.bg_stroke);
+        painter.text(
+            area.left_bottom() + egui::vec2(8.0, -8.0),
+            egui::Align2::LEFT_BOTTOM,
+            "Camera",
+            egui::FontId::proportional(14.0),
+            visuals.text_color(),
+        );
+    } else if layout.look_through && layout.scene_camera().is_some() {
+        painter.text(
+            scene_view.rect.left_top() + egui::vec2(8.0, 8.0),
+            egui::Align2::LEFT_TOP,
+            "Looking through the selected camera",
+            egui::FontId::proportional(14.0),
+            ctx.style().visuals.text_color(),
+        );
+    }
+    if let Some((min, max)) = box_selection.rect() {
+        // The box is in pixels from the top left of the view, egui uses points
+        let ppp = scene_view.pixels_per_point;
+        let to_pos =
+            |(x, y): (f64, f64)| scene_view.rect.min + egui::vec2(x as f32, y as f32) / ppp;
+        let rect = egui::Rect::from_min_max(to_pos(min), to_pos(max));
+        let selection = ctx.style().visuals.selection;
+        painter.rect(rect, 0.0, selection.bg_fill.linear_multiply(0.25), selection.stroke);
+    }
+}
+
+/// Name each view of the quad layout and outline the active one
+fn view_overlays(
+    ctx: &egui::Context,
+    painter: &egui::Painter,
+    scene_view: &SceneView,
+    layout: &ViewLayout,
+) {
+    let (width, height) = scene_view.size();
+    let visuals = &ctx.style().visuals;
+    for (view, rect) in layout.rects(width, height) {
+        let area = view_area(scene_view, rect);
+        let stroke = if view == layout.active() {
+            visuals.selection.stroke
+        } else {
+            visuals.widgets.noninteractive.bg_stroke
+        };
+        painter.rect_stroke(area.shrink(1.0), 0.0, stroke);
+        painter.text(
+            area.left_bottom() + egui::vec2(8.0, -8.0),
+            egui::Align2::LEFT_BOTTOM,
+            ViewLayout::NAMES[view],
+            egui::FontId::proportional(14.0),
+            visuals.text_color(),
+        );
+    }
+}
+
+/// Area of the scene view a view rectangle covers
+fn view_area(scene_view: &SceneView, rect: ViewRect) -> egui::Rect {
+    let ppp = scene_view.pixels_per_point;
+    let (_, height) = scene_view.size();
+    // View rectangles are in pixels from the bottom left, egui uses points from the top left
+    let top = height as i32 - rect.y - rect.height;
+    egui::Rect::from_min_size(
+        scene_view.rect.min + egui::vec2(rect.x as f32, top as f32) / ppp,
+        egui::vec2(rect.width as f32, rect.height as f32) / ppp,
+    )
+}
+
+/// Tabs for the panels docked in an area, with the one in front shown below them
+///
+/// Returns whether a panel was moved to another area.
+fn dock_tabs(
+    ui: &mut egui::Ui,
+    area: DockArea,
+    tabs: &[Panel],
+    layout: &mut DockLayout,
+    contents: &mut impl FnMut(&mut egui::Ui, Panel),
+) -> bool {
+    let Some(mut front) = layout.front(area, tabs) else {
+        return false;
+    };
+
+    let mut moved = false;
+    ui.horizontal(|ui| {
+        for &panel in tabs {
+            ui.selectable_value(&mut front, panel, panel.label());
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            moved = dock_menu(ui, front, layout);
+        });
+    });
+    layout.set_front(area, front);
+    ui.separator();
+
+    contents(ui, front);
+    moved
+}
+
+/// Menu for moving a panel to another dock area, returning whether it was moved
+fn dock_menu(ui: &mut egui::Ui, panel: Panel, layout: &mut DockLayout) -> bool {
+    let mut moved = false;
+    ui.menu_button("Move to", |ui| {
+        for area in DockArea::ALL {
+            if ui.radio(layout.area(panel) == area, area.label()).clicked() {
+                layout.set_area(panel, area);
+                moved = true;
+                ui.close_menu();
+            }
+        }
+    });
+    moved
+}
+
+/// A notification with an icon and color for its level
+fn notification_label(ui: &mut egui::Ui, notification: &Notification) -> egui::Response {
+    let (icon, color) = match notification.level {
+        NotificationLevel::Info => ("ℹ", ui.visuals().text_color()),
+        NotificationLevel::Success => ("✔", egui::Color32::from_rgb(100, 200, 100)),
+        NotificationLevel::Warning => ("⚠", ui.visuals().warn_fg_color),
+        NotificationLevel::Error => ("❌", ui.visuals().error_fg_color),
+    };
+    ui.colored_label(color, format!("{icon} {}", notification.message))
+}
+
+/// The latest notification, with the earlier ones on hover, and the entity count, selection count
+/// and frame time on the right
+fn status_bar(
+    ui: &mut egui::Ui,
+    notifications: &Notifications,
+    entity_count: usize,
+    selection_count: usize,
+    frame_time: f32,
+) {
+    ui.horizontal(|ui| {
+        match notifications.latest() {
+            Some(latest) => {
+                notification_label(ui, latest).on_hover_ui(|ui| {
+                    for notification in notifications.history().rev().take(20) {
+                        notification_label(ui, notification);
+                    }
+                });
+            }
+            None => {
+                ui.weak("Ready");
+            }
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.label(format!("{frame_time:.1} ms"));
+            ui.separator();
+            ui.label(format!("{selection_count} selected"));
+            ui.separator();
+            ui.label(format!("{entity_count} entities"));
+        });
+    });
+}
+
+/// Compiler errors and warnings for the shader being edited
+fn shader_diagnostics(ui: &mut egui::Ui, diagnostics: &[ShaderDiagnostic]) {
+    for diagnostic in diagnostics {
+        let color = match diagnostic.severity {
+            Severity::Error => ui.visuals().error_fg_color,
+            Severity::Warning => ui.visuals().warn_fg_color,
+        };
+        ui.colored_label(color, egui::RichText::new(diagnostic.to_string()).monospace());
+    }
+}
+
+/// Widgets for the uniforms of a custom shader, with color pickers for the ones named as colors
+fn uniform_values(ui: &mut egui::Ui, values: &mut BTreeMap<String, UniformValue>) {
+    egui::Grid::new("uniform_values_grid").show(ui, |ui| {
+        for (name, value) in values.iter_mut() {
+            ui.label(name.as_str());
+            let lowercase = name.to_lowercase();
+            let color = ["color", "colour", "tint"].iter().any(|word| lowercase.contains(word));
+            match value {
+                UniformValue::Float(v) => {
+                    ui.add(egui::Slider::new(v, 0.0..=1.0).clamp_to_range(false));
+                }
+                UniformValue::Int(v) => {
+                    ui.add(egui::DragValue::new(v));
+                }
+                UniformValue::Bool(v) => {
+                    ui.checkbox(v, "");
+                }
+                UniformValue::Vec3(v) if color => {
+                    ui.color_edit_button_rgb(v);
+                }
+                UniformValue::Vec4(v) if color => {
+                    ui.color_edit_button_rgba_unmultiplied(v);
+                }
+                UniformValue::Vec2(v) => drag_values(ui, v),
+                UniformValue::Vec3(v) => drag_values(ui, v),
+                UniformValue::Vec4(v) => drag_values(ui, v),
+            }
+            ui.end_row();
+        }
+    });
+}
+
+fn drag_values(ui: &mut egui::Ui, values: &mut [f32]) {
+    ui.horizontal(|ui| {
+        for value in values {
+            ui.add(egui::DragValue::new(value).speed(0.01));
+        }
+    });
+}
+
+/// Why a custom shader did not compile, with a way back to the last source that did
+fn shader_compile_error(ui: &mut egui::Ui, error: &str, entity: Entity, commands: &mut Commands) {
+    let color = ui.visuals().error_fg_color;
+    egui::CollapsingHeader::new(egui::RichText::new("⚠ Compile error").color(color))
+        .id_source(("shader_compile_error", entity))
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.colored_label(color, egui::RichText::new(error).monospace());
+            if ui.button("⟲ Revert to last working").clicked() {
+                commands.entity(entity).add(commands::revert_custom_shader);
+            }
+        });
+}
+
+/// Size, quality and file of an image rendered from the camera
+fn render_image(ui: &mut egui::Ui, state: &mut UiState, commands: &mut Commands) {
+    const PRESETS: [(&str, u32, u32); 3] =
+        [("1080p", 1920, 1080), ("4K", 3840, 2160), ("8K", 7680, 4320)];
+
+    let settings = &mut state.render_image;
+    ui.horizontal(|ui| {
+        for (name, width, height) in PRESETS {
+            if ui.button(name).clicked() {
+                settings.width = width;
+                settings.height = height;
+            }
+        }
+    });
+    egui::Grid::new("render_image_grid").show(ui, |ui| {
+        ui.label("Size");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut settings.width).clamp_range(1..=16384));
+            ui.label("×");
+            ui.add(egui::DragValue::new(&mut settings.height).clamp_range(1..=16384));
+        });
+        ui.end_row();
+        ui.label("Supersampling");
+        ui.horizontal(|ui| {
+            for factor in [1, 2, 4] {
+                ui.selectable_value(&mut settings.supersampling, factor, format!("{factor}×"));
+            }
+        });
+        ui.end_row();
+        ui.label("Shadow map");
+        ui.horizontal(|ui| {
+            for size in [4096, 8192, 16384] {
+                ui.selectable_value(&mut settings.shadow_map_size, size, size.to_string());
+            }
+        });
+        ui.end_row();
+        ui.label("File");
+        ui.add(
+            egui::TextEdit::singleline(&mut state.render_image_path)
+                .hint_text("render.png or render.exr")
+                .desired_width(160.0),
+        );
+        ui.end_row();
+    });
+
+    let has_path = !state.render_image_path.is_empty();
+    if ui.add_enabled(has_path, egui::Button::new("Render")).clicked() {
+        let path = PathBuf::from(&state.render_image_path);
+        let settings = state.render_image;
+        commands.add(move |world: &mut World| commands::render_image(world, &path, &settings));
+    }
+}
+
+/// Ask what to do with the custom shaders after a frame took too long
+fn stalled_frame(ui: &mut egui::Ui, stalled: &mut StalledFrame, commands: &mut Commands) {
+    ui.label(format!(
+        "The last frame took {:.1} s. This can happen when a custom shader runs for too long or \
+         never finishes. Until you pick what to do, objects are drawn with the built-in shader.",
+        stalled.duration.as_secs_f32()
+    ));
+    ui.separator();
+
+    stalled.shaders.retain(|&entity| {
+        ui.horizontal(|ui| {
+            ui.label(format!("Custom shader of entity {}", entity.index()));
+            let disable = ui.button("Disable").clicked();
+            if disable {
+                commands.entity(entity).add(commands::disable_custom_shader);
+            }
+            !disable
+        })
+        .inner
+    });
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        if ui.button("Disable All and Continue").clicked() {
+            for &entity in &stalled.shaders {
+                commands.entity(entity).add(commands::disable_custom_shader);
+            }
+            commands.add(commands::resume_custom_shaders);
+        }
+        let keep = ui
+            .button("Continue")
+            .on_hover_text("Draw the custom shaders that are left, which may stall again");
+        if keep.clicked() {
+            commands.add(commands::resume_custom_shaders);
+        }
+    });
+}
+
+/// What goes into an issue report and where to save it
+fn report_issue(ui: &mut egui::Ui, state: &mut UiState, commands: &mut Commands) {
+    ui.label("Saves a zip file to attach to a bug report, with:");
+    ui.label("• Editor version, system and graphics driver, frame times and asset errors");
+    ui.label("• Recent log output");
+    ui.label("• Lighting, view and debug settings");
+    ui.label("• A screenshot of the scene view");
+    ui.checkbox(&mut state.report_include_scene, "Include the scene")
+        .on_hover_text("Leave this out if the scene is not yours to share");
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        ui.label("File");
+        ui.add(
+            egui::TextEdit::singleline(&mut state.report_path)
+                .hint_text("issue_report.zip")
+                .desired_width(160.0),
+        );
+    });
+    let has_path = !state.report_path.is_empty();
+    if ui.add_enabled(has_path, egui::Button::new("Save Report")).clicked() {
+        let path = PathBuf::from(&state.report_path);
+        let include_scene = state.report_include_scene;
+        commands.add(move |world: &mut World| {
+            commands::report_issue(world, &path, include_scene);
+        });
+        state.report_issue_open = false;
+    }
+}
+
+/// Models, textures and materials that failed to load, and what is shown in their place
+fn asset_errors(
+    ui: &mut egui::Ui,
+    model_loader: &ModelLoader,
+    texture_loader: &TextureLoader,
+    material_loader: &MaterialLoader,
+    commands: &mut Commands,
+) {
+    let errors = model_loader.errors().iter();
+    let errors = errors.chain(texture_loader.errors()).chain(material_loader.errors());
+    let mut empty = true;
+    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+        for error in errors {
+            empty = false;
+            ui.horizontal(|ui| {
+                ui.strong(&error.asset);
+                ui.label(&error.message);
+            });
+        }
+    });
+    if empty {
+        ui.label("Every asset loaded.");
+        return;
+    }
+
+    ui.separator();
+    if ui.button("Clear").on_hover_text("Forget these errors, keeping the fallbacks").clicked() {
+        commands.add(|world: &mut World| {
+            world.resource_mut::<ModelLoader>().clear_errors();
+            world.resource_mut::<TextureLoader>().clear_errors();
+            world.resource_mut::<MaterialLoader>().clear_errors();
+        });
+    }
+}
+
+/// Draw arrows, boxes and text on a screenshot and save it
+fn markup_editor(ui: &mut egui::Ui, markup: &mut Markup, notifications: &mut Notifications) {
+    ui.horizontal(|ui| {
+        for tool in MarkupTool::ALL {
+            ui.selectable_value(&mut markup.tool, tool, tool.label());
+        }
+        ui.separator();
+        ui.color_edit_button_srgba(&mut markup.color);
+        match markup.tool {
+            MarkupTool::Arrow | MarkupTool::Box => {
+                let width = egui::DragValue::new(&mut markup.line_width).clamp_range(1.0..=32.0);
+                ui.add(width.suffix(" px"));
+            }
+            MarkupTool::Text => {
+                let size = egui::DragValue::new(&mut markup.text_size).clamp_range(8.0..=128.0);
+                ui.add(size.suffix(" px"));
+                ui.add(
+                    egui::TextEdit::singleline(&mut markup.text)
+                        .hint_text("Click the image to place text")
+                        .desired_width(200.0),
+                );
+            }
+        }
+        ui.separator();
+        if ui.add_enabled(!markup.marks.is_empty(), egui::Button::new("Undo")).clicked() {
+            markup.marks.pop();
+        }
+        if ui.add_enabled(!markup.marks.is_empty(), egui::Button::new("Clear")).clicked() {
+            markup.marks.clear();
+        }
+    });
+
+    let image_size = egui::vec2(markup.width as f32, markup.height as f32);
+    let texture = markup.texture.get_or_insert_with(|| {
+        let size = [markup.width as usize, markup.height as usize];
+        let image = egui::ColorImage::from_rgba_unmultiplied(size, &markup.pixels);
+        ui.ctx().load_texture("screenshot", image, egui::TextureOptions::LINEAR)
+    });
+    let scale = (ui.available_width() / image_size.x).min(1.0);
+    let (response, painter) =
+        ui.allocate_painter(image_size * scale, egui::Sense::click_and_drag());
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    painter.image(texture.id(), response.rect, uv, egui::Color32::WHITE);
+
+    let to_screen = RectTransform::from_to(
+        egui::Rect::from_min_size(egui::Pos2::ZERO, image_size),
+        response.rect,
+    );
+    let pointer = response.interact_pointer_pos().map(|pos| to_screen.inverse() * pos);
+    let mut preview = None;
+    match (markup.tool, pointer) {
+        (MarkupTool::Text, Some(pos)) if response.clicked() && !markup.text.is_empty() => {
+            markup.marks.push(Mark {
+                annotation: Annotation::Text { pos, text: markup.text.clone() },
+                color: markup.color,
+                size: markup.text_size,
+            });
+        }
+        (MarkupTool::Arrow | MarkupTool::Box, Some(pos)) => {
+            if response.drag_started() {
+                markup.drag_start = Some(pos);
+            }
+            let start = markup.drag_start.unwrap_or(pos);
+            if response.drag_released() {
+                markup.drag_start = None;
+                if start.distance(pos) > 2.0 {
+                    markup.marks.extend(markup.mark_between(start, pos));
+                }
+            } else if markup.drag_start.is_some() {
+                preview = markup.mark_between(start, pos);
+            }
+        }
+        _ => {}
+    }
+    let shapes: Vec<_> = ui.fonts(|fonts| {
+        markup.marks.iter().chain(&preview).flat_map(|mark| mark.shapes(fonts, to_screen)).collect()
+    });
+    painter.extend(shapes);
+
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut markup.path)
+                .hint_text("markup.png")
+                .desired_width(200.0),
+        );
+        if ui.add_enabled(!markup.path.is_empty(), egui::Button::new("💾 Save")).clicked() {
+            let path = PathBuf::from(&markup.path);
+            let pixels = markup.flatten();
+            match image_export::save_image(&path, markup.width, markup.height, &pixels) {
+                Ok(()) => {
+                    notifications.success(format!("Saved the screenshot to {}", path.display()));
+                }
+                Err(e) => {
+                    warn!("could not save screenshot: {e}");
+                    notifications.error(format!("Could not save the screenshot: {e}"));
+                }
+            }
+        }
+    });
+}
+
+/// Loaded textures, with buttons opening their details and import settings
+fn texture_list(ui: &mut egui::Ui, texture_loader: &TextureLoader, commands: &mut Commands) {
+    let mut names: Vec<_> = texture_loader.keys().collect();
+    names.sort();
+    egui::ScrollArea::vertical().id_source("texture_list").max_height(160.0).show(ui, |ui| {
+        for name in names {
+            ui.horizontal(|ui| {
+                let Some(info) = texture_loader.info(name) else {
+                    ui.label(name).on_hover_text("Placeholder for a texture that is missing");
+                    return;
+                };
+                let details = ui.button("🔍").on_hover_text("Show details and import settings");
+                if details.clicked() {
+                    let name = name.clone();
+                    commands.add(move |world: &mut World| commands::inspect_texture(world, &name));
+                }
+                ui.label(name);
+                ui.weak(format!("{}×{}", info.width, info.height));
+            });
+        }
+    });
+}
+
+/// Resolution, format and a preview of a texture, and its import settings
+fn texture_details(ui: &mut egui::Ui, details: &mut TextureDetails, commands: &mut Commands) {
+    let info = &details.info;
+    egui::Grid::new("texture_details_grid").show(ui, |ui| {
+        ui.label("File");
+        ui.label(info.path.display().to_string());
+        ui.end_row();
+        ui.label("Resolution");
+        ui.label(format!("{}×{}", info.width, info.height));
+        ui.end_row();
+        ui.label("Format");
+        ui.label(if info.settings.srgb {
+            format!("{} sRGB", info.format)
+        } else {
+            info.format.clone()
+        });
+        ui.end_row();
+        ui.label("Mip levels");
+        ui.label(info.mip_levels.to_string());
+        ui.end_row();
+    });
+    ui.separator();
+
+    ui.horizontal(|ui| {
+        ui.label("Channel");
+        for channel in Channel::ALL {
+            if ui.selectable_value(&mut details.channel, channel, channel.label()).changed() {
+                details.preview = None;
+            }
+        }
+    });
+    let (width, height) = (info.width, info.height);
+    let preview = details.preview.get_or_insert_with(|| {
+        let pixels = texture_import::isolate_channel(&details.pixels, details.channel);
+        let image =
+            egui::ColorImage::from_rgba_unmultiplied([width as usize, height as usize], &pixels);
+        ui.ctx().load_texture("texture_details", image, egui::TextureOptions::NEAREST)
+    });
+    let size = egui::vec2(width as f32, height as f32);
+    let scale = (ui.available_width() / size.x).min(1.0);
+    ui.image(preview.id(), size * scale);
+    ui.separator();
+
+    ui.label("Import settings, saved next to the texture file");
+    let settings = &mut details.settings;
+    egui::Grid::new("texture_import_grid").show(ui, |ui| {
+        ui.label("Color");
+        ui.checkbox(&mut settings.srgb, "sRGB")
+            .on_hover_text("The file holds colors, rather than data such as normals");
+        ui.end_row();
+        ui.label("Wrap");
+        egui::ComboBox::from_id_source("texture_wrap")
+            .selected_text(settings.wrap.label())
+            .show_ui(ui, |ui| {
+                for wrap in WrapMode::ALL {
+                    ui.selectable_value(&mut settings.wrap, wrap, wrap.label());
+                }
+            });
+        ui.end_row();
+        ui.label("Filter");
+        egui::ComboBox::from_id_source("texture_filter")
+            .selected_text(settings.filter.label())
+            .show_ui(ui, |ui| {
+                for filter in Filter::ALL {
+                    ui.selectable_value(&mut settings.filter, filter, filter.label());
+                }
+            });
+        ui.end_row();
+        ui.label("Anisotropy");
+        let max = details.max_anisotropy.unwrap_or(1.0);
+        ui.add_enabled(
+            details.max_anisotropy.is_some(),
+            egui::Slider::new(&mut settings.anisotropy, 1.0..=max),
+        )
+        .on_disabled_hover_text("Not supported by the graphics driver");
+        ui.end_row();
+    });
+    if details.quality.limit(*settings) != *settings {
+        ui.weak(format!(
+            "Lowered by the {} texture quality in Preferences",
+            details.quality.label().to_lowercase()
+        ));
+    }
+
+    ui.horizontal(|ui| {
+        let changed = details.settings != details.info.settings;
+        if ui.add_enabled(changed, egui::Button::new("Apply")).clicked() {
+            let (name, settings) = (details.name.clone(), details.settings);
+            commands.add(move |world: &mut World| {
+                commands::set_texture_settings(world, &name, settings)
+            });
+        }
+        if ui.add_enabled(changed, egui::Button::new("Revert")).clicked() {
+            details.settings = details.info.settings;
+        }
+        if ui.button("⟳ Reload").on_hover_text("Load the file and its settings again").clicked() {
+            let name = details.name.clone();
+            commands.add(move |world: &mut World| commands::reload_texture(world, &name));
+        }
+    });
+}
+
+/// Turntable and image sequence settings for look-dev mode
+fn look_dev_controls(ui: &mut egui::Ui, look_dev: &mut LookDev, commands: &mut Commands) {
+    ui.checkbox(&mut look_dev.enabled, "Turntable")
+        .on_hover_text("Turn the camera around the selection against a neutral background");
+    egui::Grid::new("look_dev_grid").show(ui, |ui| {
+        ui.label("Environment");
+        egui::ComboBox::from_id_source("look_dev_environment")
+            .selected_text(look_dev.environment.name())
+            .show_ui(ui, |ui| {
+                for environment in LookDevEnvironment::ALL {
+                    ui.selectable_value(&mut look_dev.environment, environment, environment.name());
+                }
+            });
+        ui.end_row();
+        ui.label("HDRI");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut look_dev.hdri_path)
+                    .hint_text("environment.exr")
+                    .desired_width(120.0),
+            );
+            let can_load = !look_dev.hdri_path.is_empty();
+            if ui.add_enabled(can_load, egui::Button::new("Load")).clicked() {
+                let path = PathBuf::from(&look_dev.hdri_path);
+                commands.add(move |world: &mut World| commands::load_hdri(world, &path));
+            }
+        });
+        ui.end_row();
+        ui.label("Speed");
+        ui.add(egui::Slider::new(&mut look_dev.speed, 0.0..=180.0).suffix("°/s"));
+        ui.end_row();
+        ui.label("Frames");
+        ui.add(egui::DragValue::new(&mut look_dev.frames).clamp_range(1..=3600));
+        ui.end_row();
+        ui.label("Output");
+        ui.add(
+            egui::TextEdit::singleline(&mut look_dev.output_dir)
+                .hint_text("turntable")
+                .desired_width(120.0),
+        );
+        ui.end_row();
+    });
+
+    match look_dev.recording {
+        Some(frame) => {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(frame as f32 / look_dev.frames as f32)
+                        .text(format!("{frame} / {}", look_dev.frames)),
+                );
+                if ui.button("Cancel").clicked() {
+                    look_dev.recording = None;
+                }
+            });
+        }
+        None => {
+            let can_render = !look_dev.output_dir.is_empty();
+            if ui.add_enabled(can_render, egui::Button::new("Render turn")).clicked() {
+                look_dev.enabled = true;
+                look_dev.recording = Some(0);
+            }
+        }
+    }
+}
+
+/// Labelled color picker in a grid row
+fn color_edit(ui: &mut egui::Ui, label: &str, color: &mut glm::Vec3) {
+    let label = ui.label(label);
+    let mut rgb = [color.x, color.y, color.z];
+    if ui.color_edit_button_rgb(&mut rgb).labelled_by(label.id).changed() {
+        *color = glm::Vec3::from(rgb);
+    }
+    ui.end_row();
+}
+
+/// Fog and sky of the scene
+fn environment_editor(ui: &mut egui::Ui, lighting: &mut Lighting) {
+    ui.checkbox(&mut lighting.sky, "Sky")
+        .on_hover_text("Draw a sky lit by the sun in place of the background color");
+    ui.checkbox(&mut lighting.fog.enabled, "Fog");
+    ui.add_enabled_ui(lighting.fog.enabled, |ui| {
+        let fog = &mut lighting.fog;
+        egui::Grid::new("fog_grid").show(ui, |ui| {
+            color_edit(ui, "Color", &mut fog.color);
+            ui.label("Density");
+            ui.add(egui::Slider::new(&mut fog.density, 0.0..=0.2).logarithmic(true));
+            ui.end_row();
+            ui.label("Start");
+            ui.add(egui::DragValue::new(&mut fog.start).speed(0.1).clamp_range(0.0..=f32::MAX));
+            ui.end_row();
+            ui.label("Height");
+            ui.add(egui::DragValue::new(&mut fog.height).speed(0.1));
+            ui.end_row();
+            ui.label("Height falloff").on_hover_text(
+                "How quickly the fog thins out above its height, or 0 for even fog at every height",
+            );
+            ui.add(egui::Slider::new(&mut fog.height_falloff, 0.0..=1.0));
+            ui.end_row();
+        });
+    });
+}
+
+/// Lens of a scene camera, which looks along the -Z axis of its entity
+fn camera_editor(ui: &mut egui::Ui, camera: &mut CameraComponent) {
+    ui.add(egui::Slider::new(&mut camera.fov_degrees, 10.0..=120.0).text("Field of view"));
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut camera.near).speed(0.01).clamp_range(0.01..=10.0));
+        ui.label("Near");
+        let far = egui::DragValue::new(&mut camera.far).speed(1.0).clamp_range(1.0..=10_000.0);
+        ui.add(far);
+        ui.label("Far");
+    });
+    ui.label("Look through it or preview it from the Views menu")
+        .on_hover_text("Moving and rotating the entity moves the camera");
+}
+
+/// Colors and attenuation of a point light, with how far it reaches
+///
+/// Changing the range sets the linear and quadratic attenuation to reach it.
+fn point_light_editor(ui: &mut egui::Ui, light: &mut PointLight) {
+    egui::Grid::new("point_light_grid").show(ui, |ui| {
+        color_edit(ui, "Ambient", &mut light.ambient);
+        color_edit(ui, "Diffuse", &mut light.diffuse);
+        color_edit(ui, "Specular", &mut light.specular);
+
+        let attenuation = [
+            ("Constant", &mut light.constant, 2.0),
+            ("Linear", &mut light.linear, 1.0),
+            ("Quadratic", &mut light.quadratic, 2.0),
+        ];
+        for (label, value, max) in attenuation {
+            let label = ui.label(label);
+            let slider = egui::Slider::new(value, 0.0..=max).logarithmic(true);
+            ui.add(slider).labelled_by(label.id);
+            ui.end_row();
+        }
+
+        let label = ui.label("Range");
+        match light.range() {
+            Some(mut range) => {
+                let drag = egui::DragValue::new(&mut range).speed(0.1).clamp_range(0.1..=1000.0);
+                let response = ui.add(drag).labelled_by(label.id).on_hover_text(
+                    "Where the light fades below 1/64 of its brightness, shown as a sphere around \
+                     it",
+                );
+                if response.changed() {
+                    light.set_range(range);
+                }
+            }
+            None => {
+                if ui.button("Unlimited").on_hover_text("Give the light a range").clicked() {
+                    light.set_range(10.0);
+                }
+            }
+        }
+        ui.end_row();
+    });
+}
+
+/// Level override and camera distances of the levels of detail of an entity
+fn lod_editor(ui: &mut egui::Ui, lod: &mut Lod) {
+    let labels: Vec<_> = std::iter::once("Level 0 (full)".to_owned())
+        .chain(lod.meshes.iter().enumerate().map(|(i, mesh)| {
+            format!("Level {} ({} triangles)", i + 1, mesh.indices_len / 3)
+        }))
+        .collect();
+    let selected = lod.forced.map_or("Automatic", |level| labels[level].as_str());
+    egui::ComboBox::from_label("Override")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut lod.forced, None, "Automatic");
+            for (level, label) in labels.iter().enumerate() {
+                ui.selectable_value(&mut lod.forced, Some(level), label);
+            }
+        })
+        .response
+        .on_hover_text("Draw one level at every distance, to check how it looks");
+
+    let levels = lod.meshes.len();
+    for (i, distance) in lod.distances.iter_mut().take(levels).enumerate() {
+        ui.horizontal(|ui| {
+            let label = ui.label(format!("Level {} from", i + 1));
+            let drag = egui::DragValue::new(distance).speed(0.1).clamp_range(0.0..=10000.0);
+            ui.add(drag).labelled_by(label.id);
+        });
+    }
+}
+
+/// X, Y and Z drag values, each labelled so screen readers can tell them apart
+fn xyz_drag_values<'a>(
+    ui: &mut egui::Ui,
+    colors: [egui::Color32; 3],
+    values: impl IntoIterator<Item = &'a mut f32>,
+    speed: f64,
+) {
+    for ((axis, color), value) in ["X:", "Y:", "Z:"].into_iter().zip(colors).zip(values) {
+        let label = ui.label(egui::RichText::new(axis).color(color));
+        ui.add(egui::DragValue::new(value).speed(speed)).labelled_by(label.id);
+    }
+}
+
+/// X, Y and Z drag values that are rounded to a multiple of `step` when changed while Ctrl is held
+fn snapped_xyz_drag_values<'a>(
+    ui: &mut egui::Ui,
+    colors: [egui::Color32; 3],
+    values: impl IntoIterator<Item = &'a mut f32>,
+    speed: f64,
+    step: f32,
+) {
+    let snap = ui.input(|i| i.modifiers.ctrl);
+    for ((axis, color), value) in ["X:", "Y:", "Z:"].into_iter().zip(colors).zip(values) {
+        let label = ui.label(egui::RichText::new(axis).color(color));
+        let drag = egui::DragValue::new(&mut *value).speed(speed);
+        let response = ui.add(drag).labelled_by(label.id);
+        // egui keeps the unrounded value of a drag, so small movements still add up to a step
+        if snap && response.changed() {
+            *value = snapping::snap(*value, step);
+        }
+    }
+}
+
+/// Model that is spawned and how it is placed in the scene
+fn placement_menu(ui: &mut egui::Ui, placement: &mut Placement, model_loader: &ModelLoader) {
+    let model_label = ui.label("Model");
+    egui::ComboBox::from_id_source("placement_model")
+        .selected_text(placement.model.as_str())
+        .show_ui(ui, |ui| {
+            for name in model_loader.keys() {
+                ui.selectable_value(&mut placement.model, name.clone(), name);
+            }
+        })
+        .response
+        .labelled_by(model_label.id);
+    ui.checkbox(&mut placement.on_surfaces, "Spawn on surfaces")
+        .on_hover_text("Spawn where the mouse points instead of in front of the camera");
+    ui.checkbox(&mut placement.align_to_normal, "Align to surface")
+        .on_hover_text("Turn placed objects so they stand up from the surface");
+    ui.checkbox(&mut placement.brush, "Placement brush")
+        .on_hover_text("Clicking in the scene view stamps the model instead of selecting");
+}
+
+/// Steps of position, rotation and scale snapping, returning whether any of them changed
+fn snapping_menu(ui: &mut egui::Ui, snapping: &mut Snapping) -> bool {
+    let mut changed = false;
+    egui::Grid::new("snapping_grid").show(ui, |ui| {
+        ui.label("Position");
+        let position = egui::DragValue::new(&mut snapping.position)
+            .speed(0.01)
+            .clamp_range(0.001..=100.0)
+            .suffix(" m");
+        changed |= ui.add(position).changed();
+        ui.end_row();
+        ui.label("Rotation");
+        let rotation =
+            egui::DragValue::new(&mut snapping.rotation).clamp_range(0.1..=180.0).suffix("°");
+        changed |= ui.add(rotation).changed();
+        ui.end_row();
+        ui.label("Scale");
+        let scale =
+            egui::DragValue::new(&mut snapping.scale).speed(0.01).clamp_range(0.001..=10.0);
+        changed |= ui.add(scale).changed();
+        ui.end_row();
+    });
+    ui.label("Hold Ctrl while dragging a transform in the inspector to snap it");
+    ui.label("The selection is nudged by the position step");
+    changed
+}
+
+fn color32(color: glm::Vec3) -> egui::Color32 {
+    egui::Color32::from_rgb(
+        (color.x * 255.0) as u8,
+        (color.y * 255.0) as u8,
+        (color.z * 255.0) as u8,
+    )
+}
+
+/// Button showing only an icon, with a text description for tooltips and screen readers
+fn icon_button(ui: &mut egui::Ui, icon: &str, description: &str) -> egui::Response {
+    let response = ui.small_button(icon);
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, description));
+    response.on_hover_text(description)
+}
+
+fn format_duration(secs: f32) -> String {
+    let secs = secs as u64;
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+/// Scene variables, each with a field for its value and a button to remove it, followed by a name
+/// field and buttons to add a variable
+fn variables_editor(
+    ui: &mut egui::Ui,
+    variables: &mut SceneVariables,
+    new_name: &mut String,
+    axis_colors: [egui::Color32; 3],
+) {
+    let mut remove = None;
+    egui::Grid::new("variables_grid").show(ui, |ui| {
+        for (name, value) in &mut variables.values {
+            match value {
+                VariableValue::Float(v) => {
+                    let label = ui.label(name);
+                    ui.add(egui::DragValue::new(v).speed(0.01)).labelled_by(label.id);
+                }
+                VariableValue::Color(color) => {
+                    let label = ui.label(name);
+                    let mut rgb = [color.x, color.y, color.z];
+                    if ui.color_edit_button_rgb(&mut rgb).labelled_by(label.id).changed() {
+                        *color = glm::Vec3::from(rgb);
+                    }
+                }
+                VariableValue::Vector(v) => {
+                    ui.label(name);
+                    ui.horizontal(|ui| xyz_drag_values(ui, axis_colors, v.iter_mut(), 0.1));
+                }
+            }
+            if icon_button(ui, "🗑", "Remove variable").clicked() {
+                remove = Some(name.clone());
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(name) = remove {
+        variables.values.remove(&name);
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(new_name).hint_text("name").desired_width(80.0));
+        let name = new_name.trim().to_owned();
+        let can_add = !name.is_empty() && !variables.values.contains_key(&name);
+        let mut added = None;
+        for (label, value) in [
+            ("Add float", VariableValue::Float(0.0)),
+            ("Add color", VariableValue::Color(glm::vec3(1.0, 1.0, 1.0))),
+            ("Add vector", VariableValue::Vector(glm::Vec3::zeros())),
+        ] {
+            if ui.add_enabled(can_add, egui::Button::new(label)).clicked() {
+                added = Some(value);
+            }
+        }
+        if let Some(value) = added {
+            variables.values.insert(name, value);
+            new_name.clear();
+        }
+    });
+}
+
+/// Choice of the material of an entity, and a name field to save its textures and shader as a
+/// material
+fn material_editor(
+    ui: &mut egui::Ui,
+    materials: &MaterialLoader,
+    current: Option<MaterialId>,
+    new_name: &mut String,
+    entity: Entity,
+    commands: &mut Commands,
+) {
+    let mut change = None;
+    egui::ComboBox::from_id_source("material_select")
+        .selected_text(current.and_then(|id| materials.name_of(id)).unwrap_or("None"))
+        .show_ui(ui, |ui| {
+            if ui.selectable_label(current.is_none(), "None").clicked() {
+                change = Some(None);
+            }
+            for (id, name) in materials.iter() {
+                if ui.selectable_label(current == Some(id), name).clicked() {
+                    change = Some(Some(id));
+                }
+            }
+        });
+    if let Some(material) = change.filter(|&material| material != current) {
+        commands.add(move |world: &mut World| commands::set_material(world, entity, material));
+    }
+    if current.is_some() {
+        ui.label("Textures and shader come from the material, choose None to edit them here");
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(new_name).hint_text("name").desired_width(80.0));
+        let name = new_name.trim().to_owned();
+        let can_save = !name.is_empty() && materials.dir().is_some();
+        let save = ui
+            .add_enabled(can_save, egui::Button::new("Save as material"))
+            .on_hover_text("Save the textures and shader, replacing a material with the same name");
+        if save.clicked() {
+            new_name.clear();
+            commands.add(move |world: &mut World| commands::save_material(world, entity, &name));
+        }
+    });
+}
+
+/// Choice of the variant of the scene to show
+fn variant_picker(ui: &mut egui::Ui, variants: &SceneVariants, commands: &mut Commands) {
+    let active = variants.active();
+    let label = ui.label("Variant");
+    let mut switch = None;
+    egui::ComboBox::from_id_source("variant_picker")
+        .selected_text(active.unwrap_or("Base scene"))
+        .show_ui(ui, |ui| {
+            if ui.selectable_label(active.is_none(), "Base scene").clicked() {
+                switch = Some(None);
+            }
+            for name in variants.variants.keys() {
+                if ui.selectable_label(active == Some(name), name).clicked() {
+                    switch = Some(Some(name.clone()));
+                }
+            }
+        })
+        .response
+        .labelled_by(label.id);
+    if let Some(name) = switch.filter(|name| name.as_deref() != active) {
+        commands.add(move |world: &mut World| {
+            variants::switch(world, name.as_deref());
+        });
+    }
+}
+
+/// Scene variants, each with buttons to show, export and remove it, followed by what the shown
+/// variant replaces and a name field to add a variant
+fn variants_editor(
+    ui: &mut egui::Ui,
+    variants: &SceneVariants,
+    new_name: &mut String,
+    scene_path: &str,
+    commands: &mut Commands,
+) {
+    egui::Grid::new("variants_grid").show(ui, |ui| {
+        for name in variants.variants.keys() {
+            let active = variants.active() == Some(name);
+            if ui.selectable_label(active, name).clicked() {
+                let name = (!active).then(|| name.clone());
+                commands.add(move |world: &mut World| {
+                    variants::switch(world, name.as_deref());
+                });
+            }
+            let path = (!scene_path.is_empty())
+                .then(|| variants::export_path(Path::new(scene_path), name));
+            let export = ui
+                .add_enabled(path.is_some(), egui::Button::new("Export"))
+                .on_disabled_hover_text("Enter the path of the scene first");
+            let export = match &path {
+                Some(path) => export.on_hover_text(format!("Save as {}", path.display())),
+                None => export,
+            };
+            if let (true, Some(path)) = (export.clicked(), path) {
+                let name = name.clone();
+                commands
+                    .add(move |world: &mut World| commands::export_variant(world, &name, &path));
+            }
+            if icon_button(ui, "🗑", "Remove variant").clicked() {
+                let name = name.clone();
+                commands.add(move |world: &mut World| variants::remove(world, &name));
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(variant) = variants.active_variant() {
+        let fields = [Field::Lighting, Field::Variables];
+        variant_fields(ui, variant, None, &fields, commands);
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(new_name).hint_text("name").desired_width(80.0));
+        let name = new_name.trim().to_owned();
+        let can_add = !name.is_empty() && !variants.variants.contains_key(&name);
+        if ui.add_enabled(can_add, egui::Button::new("Add variant")).clicked() {
+            new_name.clear();
+            commands.add(move |world: &mut World| variants::add(world, &name));
+        }
+    });
+}
+
+/// A checkbox for each field, to choose whether the shown variant keeps its own value of it
+fn variant_fields(
+    ui: &mut egui::Ui,
+    variant: &Variant<Entity>,
+    entity: Option<Entity>,
+    fields: &[Field],
+    commands: &mut Commands,
+) {
+    ui.horizontal_wrapped(|ui| {
+        for &field in fields {
+            let label = match field {
+                Field::Lighting => "Sun and exposure",
+                Field::Variables => "Variables",
+                Field::Hidden => "Visibility",
+                Field::PointLight => "Light",
+                Field::Textures => "Textures",
+                Field::Uniforms => "Uniforms",
+            };
+            let mut overridden = variant.overrides(entity, field);
+            let checkbox = ui
+                .checkbox(&mut overridden, label)
+                .on_hover_text("Keep a separate value in this variant");
+            if checkbox.changed() {
+                commands.add(move |world: &mut World| {
+                    variants::set_overridden(world, entity, field, overridden)
+                });
+            }
+        }
+    });
+}
+
+/// Fields of an entity that can be bound to scene variables, each with a choice of the variables
+/// that fit it
+fn bindings_editor(
+    ui: &mut egui::Ui,
+    bindings: Option<&mut Bindings>,
+    targets: Vec<BindingTarget>,
+    variables: &SceneVariables,
+    params: Option<&ShaderParams>,
+    entity: Entity,
+    commands: &mut Commands,
+) {
+    let bound = bindings.as_ref().map_or(0, |b| b.targets.len());
+    let mut change = None;
+    ui.collapsing(format!("{bound} bound"), |ui| {
+        egui::Grid::new("bindings_grid").show(ui, |ui| {
+            for target in targets {
+                let current = bindings.as_ref().and_then(|b| b.targets.get(&target));
+                let selected = match current {
+                    Some(name) if variables.values.contains_key(name) => name.clone(),
+                    Some(name) => format!("{name} (missing)"),
+                    None => "None".to_owned(),
+                };
+                let label = ui.label(target.to_string());
+                egui::ComboBox::from_id_source(&target)
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(current.is_none(), "None").clicked() {
+                            change = Some((target.clone(), None));
+                        }
+                        for (name, &value) in &variables.values {
+                            if !target.accepts(value, params) {
+                                continue;
+                            }
+                            let is_current = current == Some(name);
+                            if ui.selectable_label(is_current, name).clicked() {
+                                change = Some((target.clone(), Some(name.clone())));
+                            }
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                ui.end_row();
+            }
+        });
+    });
+
+    let Some((target, variable)) = change else {
+        return;
+    };
+    match (bindings, variable) {
+        (Some(bindings), Some(variable)) => {
+            bindings.targets.insert(target, variable);
+        }
+        (Some(bindings), None) => {
+            bindings.targets.remove(&target);
+        }
+        (None, Some(variable)) => {
+            let targets = BTreeMap::from([(target, variable)]);
+            commands.entity(entity).insert(Bindings { targets });
+        }
+        (None, None) => {}
+    }
+}
+
+/// Custom properties of an entity, each with a field for its type and a button to remove it,
+/// followed by a key field and buttons to add a property
+fn properties_editor(
+    ui: &mut egui::Ui,
+    properties: Option<&mut Properties>,
+    new_key: &mut String,
+    entity: Entity,
+    commands: &mut Commands,
+) {
+    let mut taken = false;
+    if let Some(properties) = properties {
+        let mut remove = None;
+        egui::Grid::new("properties_grid").show(ui, |ui| {
+            for (key, value) in &mut properties.values {
+                ui.label(key);
+                match value {
+                    PropertyValue::Bool(b) => ui.checkbox(b, ""),
+                    PropertyValue::Number(n) => ui.add(egui::DragValue::new(n).speed(0.1)),
+                    PropertyValue::String(s) => {
+                        ui.add(egui::TextEdit::singleline(s).desired_width(120.0))
+                    }
+                };
+                if icon_button(ui, "🗑", "Remove property").clicked() {
+                    remove = Some(key.clone());
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(key) = remove {
+            properties.values.remove(&key);
+        }
+        taken = properties.values.contains_key(new_key.trim());
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(new_key).hint_text("key").desired_width(80.0));
+        let key = new_key.trim().to_owned();
+        let can_add = !key.is_empty() && !taken;
+        let mut added = None;
+        for (label, value) in [
+            ("Add text", PropertyValue::String(String::new())),
+            ("Add number", PropertyValue::Number(0.0)),
+            ("Add flag", PropertyValue::Bool(false)),
+        ] {
+            if ui.add_enabled(can_add, egui::Button::new(label)).clicked() {
+                added = Some(value);
+            }
+        }
+        if let Some(value) = added {
+            new_key.clear();
+            commands.add(move |world: &mut World| {
+                let mut entity = world.entity_mut(entity);
+                match entity.get_mut::<Properties>() {
+                    Some(mut properties) => {
+                        properties.values.insert(key, value);
+                    }
+                    None => {
+                        entity.insert(Properties { values: BTreeMap::from([(key, value)]) });
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Tags of an entity, each with a button to remove it, followed by a field to add a tag
+fn tags_editor(
+    ui: &mut egui::Ui,
+    tags: Option<&mut Tags>,
+    new_tag: &mut String,
+    entity: Entity,
+    commands: &mut Commands,
+) {
+    let mut taken = false;
+    if let Some(tags) = tags {
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            for tag in &tags.values {
+                ui.label(tag);
+                if icon_button(ui, "✖", "Remove tag").clicked() {
+                    remove = Some(tag.clone());
+                }
+            }
+        });
+        if let Some(tag) = remove {
+            tags.values.remove(&tag);
+        }
+        taken = tags.values.contains(new_tag.trim());
+    }
+
+    ui.horizontal(|ui| {
+        let response =
+            ui.add(egui::TextEdit::singleline(new_tag).hint_text("tag").desired_width(80.0));
+        let tag = new_tag.trim().to_owned();
+        // Filters are split at spaces, so tags with spaces could not be found
+        let can_add = !tag.is_empty() && !taken && !tag.contains(char::is_whitespace);
+        let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let clicked = ui.add_enabled(can_add, egui::Button::new("Add tag")).clicked();
+        if can_add && (clicked || entered) {
+            new_tag.clear();
+            commands.add(move |world: &mut World| {
+                let mut entity = world.entity_mut(entity);
+                match entity.get_mut::<Tags>() {
+                    Some(mut tags) => {
+                        tags.values.insert(tag);
+                    }
+                    None => {
+                        entity.insert(Tags::new([tag]));
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Tags of an entity, as text
+fn tag_list(ui: &mut egui::Ui, tags: &Tags) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Tags");
+        for tag in &tags.values {
+            ui.weak(tag);
+        }
+    });
+}
+
+/// Entities of the scene that match the filter, selecting the one that is clicked
+fn outliner_list(
+    ui: &mut egui::Ui,
+    entries: &[OutlinerEntry],
+    filter: &mut String,
+    selected: Option<Entity>,
+    commands: &mut Commands,
+) {
+    ui.add(
+        egui::TextEdit::singleline(filter)
+            .hint_text("name, tag:light, has:PointLight")
+            .desired_width(f32::INFINITY),
+    );
+    let parsed = OutlinerFilter::parse(filter);
+    let shown: Vec<_> = entries.iter().filter(|entry| parsed.matches(entry)).collect();
+    if !parsed.is_empty() {
+        ui.weak(format!("{} of {} entities", shown.len(), entries.len()));
+    }
+    ui.separator();
+
+    let row_height = ui.spacing().interact_size.y;
+    egui::ScrollArea::vertical().auto_shrink([false; 2]).show_rows(
+        ui,
+        row_height,
+        shown.len(),
+        |ui, rows| {
+            for entry in &shown[rows] {
+                ui.horizontal(|ui| {
+                    let entity = entry.entity;
+                    ui.add_space(entry.depth as f32 * ui.spacing().indent);
+                    if entry.components.contains(&"Group") {
+                        ui.label("📁").on_hover_text("Group, its entities move with it");
+                    } else {
+                        outliner_toggles(ui, entry, commands);
+                    }
+
+                    let is_selected = selected == Some(entry.entity);
+                    if ui.selectable_label(is_selected, &entry.name).clicked() && !is_selected {
+                        commands.add(move |world: &mut World| commands::select(world, entity));
+                    }
+                    for tag in &entry.tags {
+                        ui.weak(format!("#{tag}"));
+                    }
+                });
+            }
+        },
+    );
+}
+
+/// Buttons to show or hide an entity and to lock it against selecting by clicking
+fn outliner_toggles(ui: &mut egui::Ui, entry: &OutlinerEntry, commands: &mut Commands) {
+    let entity = entry.entity;
+    let eye = egui::SelectableLabel::new(!entry.hidden, "👁");
+    if ui.add(eye).on_hover_text("Show in the scene").clicked() {
+        if entry.hidden {
+            commands.entity(entity).remove::<Hidden>();
+        } else {
+            commands.entity(entity).insert(Hidden);
+        }
+    }
+    let padlock = egui::SelectableLabel::new(entry.locked, "🔒");
+    if ui.add(padlock).on_hover_text("Lock against selecting by clicking").clicked() {
+        if entry.locked {
+            commands.entity(entity).remove::<Locked>();
+        } else {
+            commands.entity(entity).insert(Locked);
+        }
+    }
+}
+
+/// Source of the selected entity's script, edited as a draft that replaces the running script
+/// when applied
+fn script_editor(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    script: Option<&mut Script>,
+    error: Option<&str>,
+    state: &mut UiState,
+    commands: &mut Commands,
+) {
+    let editable = !state.view_only;
+    let Some(script) = script else {
+        if !editable {
+            ui.label("The selected entity has no script");
+        } else if ui.button("Add script").clicked() {
+            commands.entity(entity).insert(Script::new(NEW_SCRIPT));
+        }
+        return;
+    };
+    // Start over from the running script when another entity is selected
+    if state.script_draft_entity != Some(entity) {
+        state.script_draft_entity = Some(entity);
+        state.script_draft = script.source.clone();
+    }
+
+    ui.horizontal(|ui| {
+        let modified = state.script_draft != script.source;
+        let apply = ui
+            .add_enabled(modified && editable, egui::Button::new("▶ Apply"))
+            .on_hover_text("Reload the script, keeping the fields it has set on `this`");
+        if apply.clicked() {
+            script.source = state.script_draft.clone();
+        }
+        if ui.add_enabled(modified, egui::Button::new("Revert")).clicked() {
+            state.script_draft = script.source.clone();
+        }
+        if ui.add_enabled(editable, egui::Button::new("🗑 Remove script")).clicked() {
+            commands.entity(entity).remove::<Script>();
+        }
+    });
+    if let Some(error) = error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        let editor = egui::TextEdit::multiline(&mut state.script_draft)
+            .code_editor()
+            .desired_width(f32::INFINITY)
+            .desired_rows(16);
+        ui.add_enabled(editable, editor);
+    });
+}
+
+/// Log lines or the output of scripts, newest at the bottom, above a line for typing commands
+fn console_panel(
+    ui: &mut egui::Ui,
+    output: &mut Output,
+    state: &mut UiState,
+    commands: &mut Commands,
+) {
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut state.console_scripts, false, "Log");
+        ui.selectable_value(&mut state.console_scripts, true, "Scripts");
+        ui.separator();
+        if !state.console_scripts {
+            ui.checkbox(&mut state.console_debug, "Debug lines");
+        }
+        if ui.button("Clear").clicked() {
+            match state.console_scripts {
+                true => output.script_log.clear(),
+                false => output.log.clear(),
+            }
+        }
+        if ui.button("Copy").on_hover_text("Copy the lines shown to the clipboard").clicked() {
+            let text = match state.console_scripts {
+                true => output.script_log.to_text(),
+                false => output.log.to_text(state.console_debug),
+            };
+            ui.output_mut(|o| o.copied_text = text);
+        }
+    });
+    ui.separator();
+
+    ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+        let line = egui::TextEdit::singleline(&mut state.console_input)
+            .font(egui::TextStyle::Monospace)
+            .hint_text("Type a command, or help to list them")
+            .desired_width(f32::INFINITY);
+        let response = ui.add_enabled(!state.view_only, line);
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            let line = std::mem::take(&mut state.console_input);
+            if !line.trim().is_empty() {
+                commands.add(move |world: &mut World| console::run_line(world, &line));
+            }
+            response.request_focus();
+        }
+        ui.separator();
+
+        ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
+            egui::ScrollArea::vertical().auto_shrink([false; 2]).stick_to_bottom(true).show(
+                ui,
+                |ui| match state.console_scripts {
+                    true => script_messages(ui, &output.script_log, commands),
+                    false => log_lines(ui, &output.log, state.console_debug),
+                },
+            );
+        });
+    });
+}
+
+fn log_lines(ui: &mut egui::Ui, log: &ConsoleLog, debug: bool) {
+    for line in log.shown_lines(debug) {
+        let color = match line.level {
+            Level::ERROR => ui.visuals().error_fg_color,
+            Level::WARN => ui.visuals().warn_fg_color,
+            Level::INFO => ui.visuals().text_color(),
+            _ => ui.visuals().weak_text_color(),
+        };
+        ui.label(egui::RichText::new(line.text()).monospace().color(color));
+    }
+}
+
+fn script_messages(ui: &mut egui::Ui, log: &ScriptLog, commands: &mut Commands) {
+    if log.messages().next().is_none() {
+        ui.weak("Nothing printed yet");
+    }
+    for message in log.messages() {
+        ui.horizontal_wrapped(|ui| {
+            let entity = message.entity;
+            let link = ui.link(format!("entity {}", entity.index()));
+            if link.on_hover_text("Select the entity").clicked() {
+                commands.add(move |world: &mut World| commands::select(world, entity));
+            }
+            if message.is_error {
+                ui.colored_label(ui.visuals().error_fg_color, &message.text);
+            } else {
+                ui.label(&message.text);
+            }
+        });
+    }
+}
+
+/// Custom properties of an entity, as text
+fn property_list(ui: &mut egui::Ui, properties: &Properties) {
+    egui::Grid::new("property_list_grid").show(ui, |ui| {
+        for (key, value) in &properties.values {
+            ui.label(key);
+            match value {
+                PropertyValue::Bool(b) => ui.label(if *b { "Yes" } else { "No" }),
+                PropertyValue::Number(n) => ui.label(n.to_string()),
+                PropertyValue::String(s) => ui.label(s),
+            };
+            ui.end_row();
+        }
+    });
+}
+
+/// The selected entity in view-only mode, with playback of its skeletal animation
+fn view_only_inspector(
+    ui: &mut egui::Ui,
+    entity: Entity,
+    pos: &Position,
+    rotation: &Rotation,
+    scale: &Scale,
+    skeleton: Option<&Skeleton>,
+    player: Option<&mut AnimationPlayer>,
+) {
+    ui.strong(format!("Entity {}", entity.index()));
+    ui.separator();
+
+    egui::Grid::new("view_only_inspector_grid").spacing((20.0, 10.0)).show(ui, |ui| {
+        for (label, [x, y, z]) in [
+            ("Position", [pos.x, pos.y, pos.z]),
+            ("Rotation", rotation.euler().into()),
+            ("Scale", [scale.x, scale.y, scale.z]),
+        ] {
+            ui.label(label);
+            ui.label(format!("{x:.2}, {y:.2}, {z:.2}"));
+            ui.end_row();
+        }
+
+        if let (Some(skeleton), Some(player)) = (skeleton, player) {
+            let animations = &skeleton.skin.animations;
+            ui.label("Animation");
+            ui.horizontal(|ui| {
+                match player.animation.and_then(|i| animations.get(i)) {
+                    Some(animation) => ui.label(&animation.name),
+                    None => ui.label("Rest pose"),
+                };
+                if ui.button(if player.playing { "⏸ Pause" } else { "▶ Play" }).clicked() {
+                    player.playing = !player.playing;
+                }
+                if ui.button("⏹ Stop").clicked() {
+                    player.playing = false;
+                    player.time = 0.0;
+                }
+            });
+            ui.end_row();
+        }
+    });
+}
+
+/// Playback of an animation clip, and its keyframes when `editable`
+fn timeline(
+    ui: &mut egui::Ui,
+    clip: &mut AnimationClip,
+    pos: &mut Position,
+    rotation: &mut Rotation,
+    scale: &mut Scale,
+    editable: bool,
+) {
+    let mut scrubbed = false;
+
+    ui.horizontal(|ui| {
+        if ui.button(if clip.playing { "⏸ Pause" } else { "▶ Play" }).clicked() {
+            clip.playing = !clip.playing;
+            if clip.playing && clip.time >= clip.end() {
+                clip.time = 0.0;
+            }
+        }
+        if ui.button("⏹ Stop").clicked() {
+            clip.time = 0.0;
+            scrubbed = true;
+        }
+        if !editable {
+            return;
+        }
+        if ui.button("⏺ Record keyframe").clicked() {
+            clip.insert_keyframe(Keyframe {
+                time: clip.time,
+                position: *pos,
+                rotation: *rotation,
+                scale: *scale,
+            });
+        }
+        ui.separator();
+
+        egui::ComboBox::from_label("Interpolation")
+            .selected_text(format!("{:?}", clip.interpolation))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut clip.interpolation, Interpolation::Linear, "Linear");
+                ui.selectable_value(&mut clip.interpolation, Interpolation::Cubic, "Cubic");
+            });
+        ui.checkbox(&mut clip.looping, "Loop");
+        ui.label("Length:");
+        ui.add(
+            egui::DragValue::new(&mut clip.length).clamp_range(0.1..=600.0).speed(0.1).suffix(" s"),
+        );
+    });
+
+    let end = clip.end();
+    ui.spacing_mut().slider_width = (ui.available_width() - 100.0).max(100.0);
+    scrubbed |=
+        ui.add(egui::Slider::new(&mut clip.time, 0.0..=end).suffix(" s").text("Time")).changed();
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Keyframes:");
+        let mut jump_to = None;
+        let mut remove = None;
+        for (i, keyframe) in clip.keyframes.iter().enumerate() {
+            let current = (keyframe.time - clip.time).abs() < 1e-3;
+            let response = ui.selectable_label(current, format!("{:.2}", keyframe.time));
+            if response.clicked() {
+                jump_to = Some(keyframe.time);
+            }
+            if !editable {
+                continue;
+            }
+            if response.secondary_clicked() {
+                remove = Some(i);
+            }
+            response.on_hover_text("Click to jump to keyframe, right click to delete");
+        }
+
+        if let Some(time) = jump_to {
+            clip.time = time;
+            scrubbed = true;
+        }
+        if let Some(i) = remove {
+            clip.keyframes.remove(i);
+        }
+    });
+
+    // Preview the clip at the scrubbed time
+    if scrubbed {
+        clip.playing = false;
+        if let Some(keyframe) = clip.sample(clip.time) {
+            *pos = keyframe.position;
+            *rotation = keyframe.rotation;
+            *scale = keyframe.scale;
+        }
+    }
+}
+
+/// Start the UI frame that `run_ui` and the panels of plugins draw into
+pub fn begin_ui(mut egui_glow: ResMut<EguiGlowRes>, window: Res<WinitWindow>) {
+    egui_glow.begin_frame(&window);
+}
+
+/// Draw the open panels added by plugins, which can change anything in the world
+pub fn show_plugin_panels(world: &mut World) {
+    let ctx = world.resource::<EguiGlowRes>().egui_ctx.clone();
+    world.resource_scope(|world, mut panels: Mut<PluginPanels>| {
+        for panel in panels.iter_mut() {
+            panel.show(&ctx, world);
+        }
+    });
+}
+
+/// Draw the terrain window, for adding terrains and choosing the brush that sculpts them
+pub fn terrain_window(
+    egui_glow: Res<EguiGlowRes>,
+    mut state: ResMut<UiState>,
+    mut terrain_brush: ResMut<TerrainBrush>,
+    texture_loader: Res<TextureLoader>,
+    mut selected: Query<&mut Terrain, With<Selected>>,
+    mut heightmap: Local<Option<String>>,
+    mut commands: Commands,
+) {
+    let mut open = state.terrain_open && !state.view_only;
+    egui::Window::new("⛰ Terrain").open(&mut open).show(&egui_glow.egui_ctx, |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("Add flat").clicked() {
+                commands.add(|world: &mut World| commands::add_terrain(world, None));
+            }
+            let add = ui.add_enabled(heightmap.is_some(), egui::Button::new("Add from heightmap"));
+            if add.clicked() {
+                let name = heightmap.clone();
+                commands
+                    .add(move |world: &mut World| commands::add_terrain(world, name.as_deref()));
+            }
+            texture_combo(ui, "terrain_heightmap", &mut heightmap, &texture_loader);
+        });
+        ui.separator();
+
+        let TerrainBrush { active, brush, .. } = &mut *terrain_brush;
+        ui.checkbox(active, "Brush")
+            .on_hover_text("Dragging over a terrain sculpts it instead of selecting");
+        ui.horizontal(|ui| {
+            for tool in BrushTool::ALL {
+                ui.radio_value(&mut brush.tool, tool, tool.label());
+            }
+        });
+        ui.add(egui::Slider::new(&mut brush.radius, 0.5..=20.0).text("Radius"));
+        ui.add(egui::Slider::new(&mut brush.strength, 0.1..=10.0).text("Strength"));
+        if brush.tool == BrushTool::Paint {
+            ui.add(egui::Slider::new(&mut brush.layer, 0..=LAYERS - 1).text("Layer"));
+        }
+
+        let Ok(mut terrain) = selected.get_single_mut() else {
+            return;
+        };
+        ui.separator();
+        ui.label("Layers of the selected terrain");
+        for (i, layer) in terrain.layers.clone().into_iter().enumerate() {
+            let mut name = layer.and_then(|t| texture_loader.name_of(t)).map(str::to_owned);
+            ui.horizontal(|ui| {
+                ui.label(format!("{i}"));
+                if texture_combo(ui, ("terrain_layer", i), &mut name, &texture_loader) {
+                    terrain.layers[i] = name.and_then(|name| texture_loader.get(&name).copied());
+                }
+            });
+        }
+    });
+    state.terrain_open &= open;
+}
+
+/// Draw the render settings window, with the settings of how the scene is rendered that are saved
+/// with it
+pub fn render_settings_window(
+    egui_glow: Res<EguiGlowRes>,
+    mut state: ResMut<UiState>,
+    mut settings: ResMut<RenderSettings>,
+) {
+    let mut open = state.render_settings_open;
+    let settings = &mut *settings;
+    egui::Window::new("🎨 Render Settings").open(&mut open).show(&egui_glow.egui_ctx, |ui| {
+        ui.label("Saved with the scene, along with the lighting");
+        egui::ComboBox::from_label("Shadow map")
+            .selected_text(settings.shadow_map_size.to_string())
+            .show_ui(ui, |ui| {
+                for size in SHADOW_MAP_SIZES {
+                    ui.selectable_value(&mut settings.shadow_map_size, size, size.to_string());
+                }
+            });
+        let shadows = &mut settings.shadows;
+        let kernel_name = |size: i32| match size {
+            1 => "Off".to_owned(),
+            size => format!("{size}×{size}"),
+        };
+        egui::ComboBox::from_label("Shadow filtering")
+            .selected_text(kernel_name(shadows.pcf_kernel_size))
+            .show_ui(ui, |ui| {
+                for size in PCF_KERNEL_SIZES {
+                    ui.selectable_value(&mut shadows.pcf_kernel_size, size, kernel_name(size));
+                }
+            })
+            .response
+            .on_hover_text("Blends shadow map samples around each pixel, for softer shadow edges");
+        ui.horizontal(|ui| {
+            let bias = egui::DragValue::new(&mut shadows.depth_bias)
+                .speed(0.0005)
+                .clamp_range(0.0..=ShadowSettings::MAX_DEPTH_BIAS);
+            ui.add(bias);
+            ui.label("Depth bias").on_hover_text(
+                "Raise when surfaces shadow themselves in stripes, lower when shadows start \
+                 away from what casts them",
+            );
+        });
+        ui.horizontal(|ui| {
+            let offset = egui::DragValue::new(&mut shadows.normal_offset)
+                .speed(0.005)
+                .clamp_range(0.0..=ShadowSettings::MAX_NORMAL_OFFSET)
+                .suffix(" units");
+            ui.add(offset);
+            ui.label("Normal offset").on_hover_text(
+                "Moves surfaces out along their normal before they are shadowed, which removes \
+                 stripes with less depth bias",
+            );
+        });
+        ui.checkbox(&mut settings.reflections, "Screen-space reflections").on_hover_text(
+            "Reflects the scene in shiny surfaces, by tracing rays through what is already on \
+             screen, so anything off screen or hidden behind other objects is not reflected",
+        );
+        ui.separator();
+
+        let dof = &mut settings.post_effects.depth_of_field;
+        ui.checkbox(&mut dof.enabled, "Depth of field");
+        ui.add_enabled_ui(dof.enabled, |ui| {
+            ui.horizontal(|ui| {
+                let focus = egui::DragValue::new(&mut dof.focus_distance)
+                    .speed(0.1)
+                    .clamp_range(Camera::NEAR..=Camera::FAR)
+                    .suffix(" units");
+                ui.add(focus);
+                ui.label("Focus distance");
+                ui.toggle_value(&mut state.picking_focus, "🎯 Pick")
+                    .on_hover_text("Click a surface in the scene view to focus on it");
+            });
+            ui.add(egui::Slider::new(&mut dof.aperture, 0.0..=1.0).text("Aperture"));
+            quality_combo(ui, "depth_of_field_quality", &mut dof.quality);
+        });
+        ui.separator();
+
+        let blur = &mut settings.post_effects.motion_blur;
+        ui.checkbox(&mut blur.enabled, "Motion blur");
+        ui.add_enabled_ui(blur.enabled, |ui| {
+            ui.add(egui::Slider::new(&mut blur.strength, 0.0..=1.0).text("Strength"))
+                .on_hover_text("Part of the motion over a frame that is blurred");
+            quality_combo(ui, "motion_blur_quality", &mut blur.quality);
+        });
+    });
+    state.render_settings_open = open;
+    // Picking stops along with the window or the depth of field
+    state.picking_focus &= open && settings.post_effects.depth_of_field.enabled;
+}
+
+fn quality_combo(ui: &mut egui::Ui, id: &str, quality: &mut EffectQuality) {
+    egui::ComboBox::new(id, "Quality").selected_text(quality.name()).show_ui(ui, |ui| {
+        for option in EffectQuality::ALL {
+            ui.selectable_value(quality, option, option.name());
+        }
+    });
+}
+
+/// Pick a loaded texture or none, returning whether the choice changed
+fn texture_combo(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    current: &mut Option<String>,
+    texture_loader: &TextureLoader,
+) -> bool {
+    let before = current.clone();
+    let selected = current.as_deref().unwrap_or("None").to_owned();
+    egui::ComboBox::from_id_source(id).selected_text(selected).show_ui(ui, |ui| {
+        ui.selectable_value(current, None, "None");
+        for name in texture_loader.keys() {
+            ui.selectable_value(current, Some(name.clone()), name);
+        }
+    });
+    *current != before
+}
+
+pub fn end_ui(mut egui_glow: ResMut<EguiGlowRes>, window: Res<WinitWindow>) {
+    egui_glow.end_frame(&window);
+}
+
+pub fn paint_ui(
+    gl: NonSend<Arc<Context>>,
+    mut egui_glow: ResMut<EguiGlowRes>,
+    window: Res<WinitWindow>,
+    mut profiler: ResMut<Profiler>,
+    mut error_checks: ResMut<GlErrorChecks>,
+) {
+    profiler.begin_pass(&gl, "ui");
+    unsafe {
+        // The scene was drawn offscreen, so the window is only covered by what egui paints
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        gl.viewport(0, 0, window.inner_size().width as i32, window.inner_size().height as i32);
+        gl.clear_color(0.0, 0.0, 0.0, 1.0);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+    }
     egui_glow.paint(&window);
+    profiler.end_pass(&gl, "ui");
+    error_checks.check(&gl, "ui", ErrorCheckMode::Passes);
 }
@@ -0,0 +1,97 @@
+use std::process::Command;
+use std::thread::{self, JoinHandle};
+
+use bevy_ecs::system::Resource;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use serde::Deserialize;
+
+/// Latest release of the editor, from the GitHub API
+pub const RELEASES_URL: &str = "https://api.github.com/repos/amatho/scene-editor/releases/latest";
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const CHANGELOG: &str = include_str!("../CHANGELOG.md");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    #[serde(rename = "tag_name")]
+    pub version: String,
+    #[serde(rename = "html_url")]
+    pub url: String,
+}
+
+/// Checks for a newer release on a background thread
+#[derive(Resource, Default)]
+pub struct UpdateChecker {
+    task: Option<JoinHandle<Result<Release>>>,
+    /// Newer release found by the last check
+    pub available: Option<Release>,
+}
+
+impl UpdateChecker {
+    /// Start checking for a newer release, unless a check is already running
+    pub fn start(&mut self) {
+        if self.task.is_none() {
+            self.task = Some(thread::spawn(fetch_latest_release));
+        }
+    }
+
+    /// Take the result of a finished check, storing the release if it is newer
+    pub fn poll(&mut self) -> Option<Result<Option<Release>>> {
+        if !self.task.as_ref()?.is_finished() {
+            return None;
+        }
+
+        let result = match self.task.take()?.join() {
+            Ok(result) => result,
+            Err(_) => Err(eyre!("update check panicked")),
+        };
+        Some(result.map(|release| {
+            self.available = is_newer(&release.version, CURRENT_VERSION).then_some(release.clone());
+            self.available.clone()
+        }))
+    }
+}
+
+/// Uses the system `curl` so the editor does not need its own HTTP and TLS stack
+fn fetch_latest_release() -> Result<Release> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", "--max-time", "10"])
+        .args(["--header", "Accept: application/vnd.github+json"])
+        .arg(RELEASES_URL)
+        .output()
+        .map_err(|e| eyre!("could not run curl: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(eyre!("could not fetch releases: {}", stderr.trim()));
+    }
+
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Parse a version like `v1.2.3` or `1.2.3`, ignoring any pre-release suffix
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|p| p.parse().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+pub fn is_newer(version: &str, current: &str) -> bool {
+    match (parse_version(version), parse_version(current)) {
+        (Some(version), Some(current)) => version > current,
+        _ => false,
+    }
+}
+
+/// Changelog entries for a version, or `None` if the changelog has no section for it
+pub fn changelog(version: &str) -> Option<&'static str> {
+    let heading = format!("## {version}\n");
+    let start = CHANGELOG.find(&heading)? + heading.len();
+    let end = CHANGELOG[start..].find("\n## ").map_or(CHANGELOG.len(), |i| start + i);
+    Some(CHANGELOG[start..end].trim())
+}
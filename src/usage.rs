@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use bevy_ecs::system::Resource;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Local file the sessions are appended to, one per line
+pub const USAGE_LOG_PATH: &str = "usage_log.ron";
+
+/// Width of a frame time histogram bin in milliseconds
+const BIN_MS: f32 = 0.1;
+/// Frame times longer than the last bin are counted in it
+const BINS: usize = 1000;
+
+/// Frame time distribution of a session, in milliseconds
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct FrameTimes {
+    pub p50: f32,
+    pub p90: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+impl FrameTimes {
+    /// Percentiles from a histogram with `BIN_MS` wide bins, using the middle of each bin
+    fn from_histogram(histogram: &[u32], max: f32) -> Option<Self> {
+        let total: u64 = histogram.iter().map(|&n| n as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let percentile = |p: f32| {
+            let target = (total as f32 * p).ceil().max(1.0) as u64;
+            let mut count = 0;
+            let bin = histogram.iter().position(|&n| {
+                count += n as u64;
+                count >= target
+            });
+            let ms = (bin.unwrap_or(BINS - 1) as f32 + 0.5) * BIN_MS;
+            ms.min(max)
+        };
+        Some(Self { p50: percentile(0.5), p90: percentile(0.9), p99: percentile(0.99), max })
+    }
+
+    fn from_samples(samples: &[f32]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |p: f32| sorted[((sorted.len() - 1) as f32 * p).round() as usize];
+        Some(Self {
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// What happened during one run of the editor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    /// Seconds since the Unix epoch
+    pub started_at: u64,
+    pub duration_secs: f32,
+    pub scenes_opened: Vec<String>,
+    /// Seconds spent with each tool open
+    pub tool_secs: BTreeMap<String, f32>,
+    pub frame_times: Option<FrameTimes>,
+}
+
+/// Usage of the current session, which is only written to a local file
+#[derive(Resource)]
+pub struct UsageLog {
+    started_at: SystemTime,
+    started: Instant,
+    scenes_opened: Vec<String>,
+    tool_time: BTreeMap<&'static str, Duration>,
+    /// Histogram of frame times, so a long session does not keep every sample
+    frame_times: Vec<u32>,
+    max_frame_time: f32,
+    /// Sessions read from the log file for the summary, loaded when first needed
+    pub history: Option<Vec<Session>>,
+}
+
+impl UsageLog {
+    pub fn new() -> Self {
+        Self {
+            started_at: SystemTime::now(),
+            started: Instant::now(),
+            scenes_opened: Vec::new(),
+            tool_time: BTreeMap::new(),
+            frame_times: vec![0; BINS],
+            max_frame_time: 0.0,
+            history: None,
+        }
+    }
+
+    pub fn scene_opened(&mut self, path: &Path) {
+        self.scenes_opened.push(path.display().to_string());
+    }
+
+    /// Add time spent with a tool open
+    pub fn track(&mut self, tool: &'static str, time: Duration) {
+        *self.tool_time.entry(tool).or_default() += time;
+    }
+
+    pub fn record_frame(&mut self, frame_time_ms: f32) {
+        let bin = ((frame_time_ms / BIN_MS) as usize).min(BINS - 1);
+        self.frame_times[bin] += 1;
+        self.max_frame_time = self.max_frame_time.max(frame_time_ms);
+    }
+
+    pub fn session(&self) -> Session {
+        Session {
+            started_at: self
+                .started_at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            duration_secs: self.started.elapsed().as_secs_f32(),
+            scenes_opened: self.scenes_opened.clone(),
+            tool_secs: self
+                .tool_time
+                .iter()
+                .map(|(&tool, time)| (tool.to_owned(), time.as_secs_f32()))
+                .collect(),
+            frame_times: FrameTimes::from_histogram(&self.frame_times, self.max_frame_time),
+        }
+    }
+
+    /// Append the current session to the log file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", ron::to_string(&self.session())?)?;
+        Ok(())
+    }
+}
+
+impl Default for UsageLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read all sessions from the log file, skipping lines that cannot be parsed
+pub fn load_sessions(path: impl AsRef<Path>) -> Result<Vec<Session>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let source = fs::read_to_string(path)?;
+    let sessions = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| {
+            ron::from_str(line)
+                .map_err(|e| warn!("skipping line {} in {}: {e}", i + 1, path.display()))
+                .ok()
+        })
+        .collect();
+    Ok(sessions)
+}
+
+/// Totals over a number of sessions
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub sessions: usize,
+    pub total_secs: f32,
+    /// Tools by total time, longest first
+    pub tool_secs: Vec<(String, f32)>,
+    /// Scenes by the number of times they were opened, most opened first
+    pub scenes: Vec<(String, usize)>,
+    /// Median of the per-session percentiles, and the longest frame of any session
+    pub frame_times: Option<FrameTimes>,
+}
+
+impl Summary {
+    pub fn new(sessions: &[Session]) -> Self {
+        let mut tool_secs = BTreeMap::new();
+        let mut scenes = BTreeMap::new();
+        for session in sessions {
+            for (tool, secs) in &session.tool_secs {
+                *tool_secs.entry(tool.clone()).or_insert(0.0) += secs;
+            }
+            for scene in &session.scenes_opened {
+                *scenes.entry(scene.clone()).or_insert(0) += 1;
+            }
+        }
+        let mut tool_secs: Vec<_> = tool_secs.into_iter().collect();
+        tool_secs.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let mut scenes: Vec<_> = scenes.into_iter().collect();
+        scenes.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+        let frame_times: Vec<_> = sessions.iter().filter_map(|s| s.frame_times).collect();
+        let median = |f: fn(&FrameTimes) -> f32| {
+            let values: Vec<_> = frame_times.iter().map(f).collect();
+            FrameTimes::from_samples(&values).map(|t| t.p50)
+        };
+        let frame_times = median(|t| t.p50).map(|p50| FrameTimes {
+            p50,
+            p90: median(|t| t.p90).unwrap_or_default(),
+            p99: median(|t| t.p99).unwrap_or_default(),
+            max: frame_times.iter().map(|t| t.max).fold(0.0, f32::max),
+        });
+
+        Self {
+            sessions: sessions.len(),
+            total_secs: sessions.iter().map(|s| s.duration_secs).sum(),
+            tool_secs,
+            scenes,
+            frame_times,
+        }
+    }
+}
@@ -5,15 +5,25 @@ use glow::{Buffer, Context, HasContext, VertexArray};
 use nalgebra_glm as glm;
 use tracing::warn;
 
+use crate::bounds::Aabb;
+use crate::gl_debug::gl_check;
+
 #[derive(Clone)]
 pub struct VertexArrayObject {
     pub vao_id: VertexArray,
     pub indices_len: usize,
+    /// Bounds of the vertices in model space
+    pub aabb: Aabb,
+    /// Whether the VAO has tangents for normal mapping, added with `add_tangents`
+    pub has_tangents: bool,
     buffers: Box<[Buffer]>,
     destroyed: bool,
 }
 
 impl VertexArrayObject {
+    /// # Safety
+    ///
+    /// Leaves the new VAO bound, and all slices must have the same number of vertices.
     pub unsafe fn new(
         gl: &Context,
         vertices: &[glm::Vec3],
@@ -30,8 +40,63 @@ impl VertexArrayObject {
         let indices_buf = buffer_with_data(gl, glow::ELEMENT_ARRAY_BUFFER, indices);
 
         let indices_len = indices.len();
+        let aabb = Aabb::from_points(vertices);
         let buffers = Box::new([vert_buf, normal_buf, tex_buf, indices_buf]);
-        Self { vao_id, indices_len, buffers, destroyed: false }
+        Self { vao_id, indices_len, aabb, has_tangents: false, buffers, destroyed: false }
+    }
+
+    /// Create a VAO with joint indices and weights for skinning
+    ///
+    /// # Safety
+    ///
+    /// Same as [`VertexArrayObject::new`].
+    pub unsafe fn new_skinned(
+        gl: &Context,
+        vertices: &[glm::Vec3],
+        indices: &[u32],
+        normals: &[glm::Vec3],
+        texture_coords: &[glm::Vec2],
+        joints: &[glm::Vec4],
+        weights: &[glm::Vec4],
+    ) -> Self {
+        let mut vao = Self::new(gl, vertices, indices, normals, texture_coords);
+
+        let joint_buf = generate_attribute(gl, 3, 4, joints, false);
+        let weight_buf = generate_attribute(gl, 4, 4, weights, false);
+
+        let mut buffers = vao.buffers.to_vec();
+        buffers.extend([joint_buf, weight_buf]);
+        vao.buffers = buffers.into_boxed_slice();
+        vao
+    }
+
+    /// Add tangents with the handedness of the bitangent in `w`, as attribute 5
+    ///
+    /// # Safety
+    ///
+    /// Leaves the VAO bound, and there must be one tangent per vertex.
+    pub unsafe fn add_tangents(&mut self, gl: &Context, tangents: &[glm::Vec4]) {
+        gl.bind_vertex_array(Some(self.vao_id));
+        let tangent_buf = generate_attribute(gl, 5, 4, tangents, false);
+
+        let mut buffers = self.buffers.to_vec();
+        buffers.push(tangent_buf);
+        self.buffers = buffers.into_boxed_slice();
+        self.has_tangents = true;
+    }
+
+    /// Add the weight of each terrain layer, as attribute 6
+    ///
+    /// # Safety
+    ///
+    /// Leaves the VAO bound, and there must be one set of weights per vertex.
+    pub unsafe fn add_splat_weights(&mut self, gl: &Context, weights: &[glm::Vec4]) {
+        gl.bind_vertex_array(Some(self.vao_id));
+        let weight_buf = generate_attribute(gl, 6, 4, weights, false);
+
+        let mut buffers = self.buffers.to_vec();
+        buffers.push(weight_buf);
+        self.buffers = buffers.into_boxed_slice();
     }
 
     /// # Safety
@@ -58,11 +123,14 @@ impl Drop for VertexArrayObject {
 unsafe fn buffer_with_data<T: Pod>(gl: &Context, target: u32, data: &[T]) -> Buffer {
     let buffer = gl.create_buffer().unwrap();
     gl.bind_buffer(target, Some(buffer));
-    gl.buffer_data_u8_slice(target, bytemuck::cast_slice(data), glow::STATIC_DRAW);
+    gl_check!(gl, gl.buffer_data_u8_slice(target, bytemuck::cast_slice(data), glow::STATIC_DRAW));
 
     buffer
 }
 
+/// # Safety
+///
+/// A VAO must be bound for the attribute to be recorded in.
 pub unsafe fn generate_attribute<T: Pod>(
     gl: &Context,
     id: u32,
@@ -71,13 +139,16 @@ pub unsafe fn generate_attribute<T: Pod>(
     normalize: bool,
 ) -> Buffer {
     let buffer = buffer_with_data(gl, glow::ARRAY_BUFFER, data);
-    gl.vertex_attrib_pointer_f32(
-        id,
-        elements_per_entry,
-        glow::FLOAT,
-        normalize,
-        mem::size_of::<T>() as i32,
-        0,
+    gl_check!(
+        gl,
+        gl.vertex_attrib_pointer_f32(
+            id,
+            elements_per_entry,
+            glow::FLOAT,
+            normalize,
+            mem::size_of::<T>() as i32,
+            0,
+        )
     );
     gl.enable_vertex_attrib_array(id);
 
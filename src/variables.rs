@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{PointLight, Position, Scale, ShaderParams};
+use crate::shader::UniformValue;
+
+/// Value of a scene variable
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VariableValue {
+    Float(f32),
+    /// Linear RGB color
+    Color(glm::Vec3),
+    Vector(glm::Vec3),
+}
+
+impl VariableValue {
+    pub fn as_float(self) -> Option<f32> {
+        match self {
+            VariableValue::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The value of a color or vector variable
+    pub fn as_vec3(self) -> Option<glm::Vec3> {
+        match self {
+            VariableValue::Color(v) | VariableValue::Vector(v) => Some(v),
+            VariableValue::Float(_) => None,
+        }
+    }
+
+    /// The value as a uniform of the same type as `current`, if it has a matching type
+    ///
+    /// Colors can also be set to `vec4` uniforms, with an alpha of 1.
+    pub fn as_uniform(self, current: &UniformValue) -> Option<UniformValue> {
+        match (self, current) {
+            (VariableValue::Float(v), UniformValue::Float(_)) => Some(UniformValue::Float(v)),
+            (VariableValue::Color(v) | VariableValue::Vector(v), UniformValue::Vec3(_)) => {
+                Some(UniformValue::Vec3([v.x, v.y, v.z]))
+            }
+            (VariableValue::Color(v), UniformValue::Vec4(_)) => {
+                Some(UniformValue::Vec4([v.x, v.y, v.z, 1.0]))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Named values shared by the entities of a scene, stored in the scene file
+///
+/// Changing a variable updates every field that is bound to it with `Bindings`.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SceneVariables {
+    pub values: BTreeMap<String, VariableValue>,
+}
+
+/// A field of an entity that can take its value from a scene variable
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BindingTarget {
+    Position,
+    Scale,
+    LightAmbient,
+    LightDiffuse,
+    LightSpecular,
+    LightConstant,
+    LightLinear,
+    LightQuadratic,
+    /// A uniform of the entity's custom shader, by name
+    Uniform(String),
+}
+
+impl BindingTarget {
+    /// The fields of a point light, in the order they are shown in the inspector
+    pub const LIGHT: [BindingTarget; 6] = [
+        BindingTarget::LightAmbient,
+        BindingTarget::LightDiffuse,
+        BindingTarget::LightSpecular,
+        BindingTarget::LightConstant,
+        BindingTarget::LightLinear,
+        BindingTarget::LightQuadratic,
+    ];
+
+    /// Whether a variable can be bound to this field
+    ///
+    /// A uniform accepts a variable if the entity's shader has the uniform with a matching type.
+    pub fn accepts(&self, value: VariableValue, params: Option<&ShaderParams>) -> bool {
+        match self {
+            BindingTarget::LightConstant
+            | BindingTarget::LightLinear
+            | BindingTarget::LightQuadratic => value.as_float().is_some(),
+            BindingTarget::Uniform(name) => params
+                .and_then(|p| p.values.get(name))
+                .is_some_and(|current| value.as_uniform(current).is_some()),
+            _ => value.as_vec3().is_some(),
+        }
+    }
+}
+
+impl Display for BindingTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingTarget::Position => write!(f, "Position"),
+            BindingTarget::Scale => write!(f, "Scale"),
+            BindingTarget::LightAmbient => write!(f, "Light ambient"),
+            BindingTarget::LightDiffuse => write!(f, "Light diffuse"),
+            BindingTarget::LightSpecular => write!(f, "Light specular"),
+            BindingTarget::LightConstant => write!(f, "Light constant"),
+            BindingTarget::LightLinear => write!(f, "Light linear"),
+            BindingTarget::LightQuadratic => write!(f, "Light quadratic"),
+            BindingTarget::Uniform(name) => write!(f, "Uniform {name}"),
+        }
+    }
+}
+
+/// Fields of an entity bound to scene variables, with the name of the variable for each field
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct Bindings {
+    pub targets: BTreeMap<BindingTarget, String>,
+}
+
+/// Components of an entity that bindings write to
+pub type BoundFields<'a> = (
+    &'a Bindings,
+    Option<Mut<'a, Position>>,
+    Option<Mut<'a, Scale>>,
+    Option<Mut<'a, PointLight>>,
+    Option<Mut<'a, ShaderParams>>,
+);
+
+/// Set the bound fields of an entity to the values of their variables
+///
+/// Fields are only written when their value differs, so bound entities are not marked as changed
+/// every frame. Bindings to variables that do not exist or have the wrong type are left alone.
+pub fn apply(variables: &SceneVariables, fields: BoundFields) {
+    let (bindings, mut position, mut scale, mut light, mut params) = fields;
+    for (target, name) in &bindings.targets {
+        let Some(&value) = variables.values.get(name) else {
+            continue;
+        };
+        match target {
+            BindingTarget::Position => {
+                let (Some(position), Some(v)) = (&mut position, value.as_vec3()) else {
+                    continue;
+                };
+                if glm::Vec3::from(**position) != v {
+                    **position = Position::new(v.x, v.y, v.z);
+                }
+            }
+            BindingTarget::Scale => {
+                let (Some(scale), Some(v)) = (&mut scale, value.as_vec3()) else {
+                    continue;
+                };
+                if glm::Vec3::from(**scale) != v {
+                    **scale = Scale::new(v.x, v.y, v.z);
+                }
+            }
+            BindingTarget::LightAmbient
+            | BindingTarget::LightDiffuse
+            | BindingTarget::LightSpecular => {
+                let (Some(light), Some(v)) = (&mut light, value.as_vec3()) else {
+                    continue;
+                };
+                let fields = light.bypass_change_detection();
+                let field = match target {
+                    BindingTarget::LightAmbient => &mut fields.ambient,
+                    BindingTarget::LightDiffuse => &mut fields.diffuse,
+                    _ => &mut fields.specular,
+                };
+                if *field != v {
+                    *field = v;
+                    light.set_changed();
+                }
+            }
+            BindingTarget::LightConstant
+            | BindingTarget::LightLinear
+            | BindingTarget::LightQuadratic => {
+                let (Some(light), Some(v)) = (&mut light, value.as_float()) else {
+                    continue;
+                };
+                let fields = light.bypass_change_detection();
+                let field = match target {
+                    BindingTarget::LightConstant => &mut fields.constant,
+                    BindingTarget::LightLinear => &mut fields.linear,
+                    _ => &mut fields.quadratic,
+                };
+                if *field != v {
+                    *field = v;
+                    light.set_changed();
+                }
+            }
+            BindingTarget::Uniform(uniform) => {
+                let Some(params) = &mut params else {
+                    continue;
+                };
+                let Some(current) = params.values.get(uniform) else {
+                    continue;
+                };
+                if let Some(v) = value.as_uniform(current).filter(|v| v != current) {
+                    params.values.insert(uniform.clone(), v);
+                }
+            }
+        }
+    }
+}
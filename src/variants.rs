@@ -0,0 +1,442 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{CustomTexture, Hidden, PointLight, ShaderParams};
+use crate::resources::{Lighting, TextureLoader};
+use crate::scene::Scene;
+use crate::shader::UniformValue;
+use crate::variables::{SceneVariables, VariableValue};
+
+/// Textures of an entity, by name
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Textures {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diffuse: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub specular: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<String>,
+}
+
+/// Values of an entity that a variant replaces, leaving the rest as they are in the base scene
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct EntityOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub point_light: Option<PointLight>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub textures: Option<Textures>,
+    /// Values of the uniforms of the entity's custom shader
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub uniforms: BTreeMap<String, UniformValue>,
+}
+
+impl EntityOverride {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What a variant can replace, for the whole scene or for an entity
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// The sun, exposure and background
+    Lighting,
+    /// Every scene variable
+    Variables,
+    Hidden,
+    PointLight,
+    Textures,
+    /// Every uniform of the custom shader
+    Uniforms,
+}
+
+impl Field {
+    /// Fields of an entity, as opposed to the whole scene
+    pub const ENTITY: [Field; 4] =
+        [Field::Hidden, Field::PointLight, Field::Textures, Field::Uniforms];
+}
+
+/// A named variation of a scene, such as a night version, that replaces some of its values
+///
+/// Entities are referred to by their index in the scene file, or by `Entity` once the scene is
+/// spawned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Variant<K: Ord = usize> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lighting: Option<Lighting>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, VariableValue>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub entities: BTreeMap<K, EntityOverride>,
+}
+
+impl<K: Ord> Default for Variant<K> {
+    fn default() -> Self {
+        Self { lighting: None, variables: BTreeMap::new(), entities: BTreeMap::new() }
+    }
+}
+
+impl<K: Ord + Copy> Variant<K> {
+    /// Whether the variant replaces a field of the scene, or of an entity
+    pub fn overrides(&self, entity: Option<K>, field: Field) -> bool {
+        let Some(entity) = entity else {
+            return match field {
+                Field::Lighting => self.lighting.is_some(),
+                Field::Variables => !self.variables.is_empty(),
+                _ => false,
+            };
+        };
+        let Some(o) = self.entities.get(&entity) else {
+            return false;
+        };
+        match field {
+            Field::Hidden => o.hidden.is_some(),
+            Field::PointLight => o.point_light.is_some(),
+            Field::Textures => o.textures.is_some(),
+            Field::Uniforms => !o.uniforms.is_empty(),
+            Field::Lighting | Field::Variables => false,
+        }
+    }
+
+    /// The fields the variant replaces
+    pub fn fields(&self) -> Vec<(Option<K>, Field)> {
+        let scene = [Field::Lighting, Field::Variables].map(|field| (None, field));
+        let entities = self
+            .entities
+            .keys()
+            .flat_map(|&entity| Field::ENTITY.map(|field| (Some(entity), field)));
+        scene.into_iter().chain(entities).filter(|&(e, field)| self.overrides(e, field)).collect()
+    }
+
+    /// Replace the fields that `other` replaces with its values
+    fn merge(&mut self, other: Variant<K>) {
+        if other.lighting.is_some() {
+            self.lighting = other.lighting;
+        }
+        self.variables.extend(other.variables);
+        for (entity, o) in other.entities {
+            let into = self.entities.entry(entity).or_default();
+            into.hidden = o.hidden.or(into.hidden);
+            into.point_light = o.point_light.or(into.point_light);
+            into.textures = o.textures.or(into.textures.take());
+            into.uniforms.extend(o.uniforms);
+        }
+    }
+
+    /// Stop replacing a field, returning the variant with only that field
+    fn take(&mut self, entity: Option<K>, field: Field) -> Variant<K> {
+        let mut taken = Variant::default();
+        match (entity, field) {
+            (None, Field::Lighting) => taken.lighting = self.lighting.take(),
+            (None, Field::Variables) => taken.variables = std::mem::take(&mut self.variables),
+            (None, _) => {}
+            (Some(entity), field) => {
+                let Some(o) = self.entities.get_mut(&entity) else {
+                    return taken;
+                };
+                let into = taken.entities.entry(entity).or_default();
+                match field {
+                    Field::Hidden => into.hidden = o.hidden.take(),
+                    Field::PointLight => into.point_light = o.point_light.take(),
+                    Field::Textures => into.textures = o.textures.take(),
+                    Field::Uniforms => into.uniforms = std::mem::take(&mut o.uniforms),
+                    Field::Lighting | Field::Variables => {}
+                }
+                if o.is_empty() {
+                    self.entities.remove(&entity);
+                }
+            }
+        }
+        taken
+    }
+
+    /// The same variant with the entities keyed differently, leaving out the ones `key` has no key
+    /// for
+    pub fn map_entities<L: Ord>(&self, mut key: impl FnMut(K) -> Option<L>) -> Variant<L> {
+        Variant {
+            lighting: self.lighting,
+            variables: self.variables.clone(),
+            entities: self
+                .entities
+                .iter()
+                .filter_map(|(&entity, o)| Some((key(entity)?, o.clone())))
+                .collect(),
+        }
+    }
+}
+
+/// The variants of the open scene, and which of them is shown
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SceneVariants {
+    pub variants: BTreeMap<String, Variant<Entity>>,
+    active: Option<String>,
+    /// Values the active variant replaced, to put back when another variant is shown
+    base: Variant<Entity>,
+}
+
+impl SceneVariants {
+    /// The variants of a scene file, for the entities spawned from it in the same order
+    pub fn from_scene(variants: &BTreeMap<String, Variant>, spawned: &[Entity]) -> Self {
+        let variants = variants
+            .iter()
+            .map(|(name, variant)| {
+                (name.clone(), variant.map_entities(|index| spawned.get(index).copied()))
+            })
+            .collect();
+        Self { variants, ..Default::default() }
+    }
+
+    /// The variants as stored in a scene file, with entities keyed by `index`
+    pub fn to_scene(&self, index: impl Fn(Entity) -> Option<usize>) -> BTreeMap<String, Variant> {
+        self.variants
+            .iter()
+            .map(|(name, variant)| (name.clone(), variant.map_entities(&index)))
+            .collect()
+    }
+
+    /// Add the variants of an imported scene, adding the entities of those with the name of a
+    /// variant there is to that variant without replacing its lighting or variables
+    pub fn merge(&mut self, variants: BTreeMap<String, Variant<Entity>>) {
+        for (name, variant) in variants {
+            let Some(into) = self.variants.get_mut(&name) else {
+                self.variants.insert(name, variant);
+                continue;
+            };
+            into.entities.extend(variant.entities);
+            for (variable, value) in variant.variables {
+                into.variables.entry(variable).or_insert(value);
+            }
+        }
+    }
+
+    /// The variant that is shown, or `None` for the base scene
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn active_variant(&self) -> Option<&Variant<Entity>> {
+        self.variants.get(self.active.as_deref()?)
+    }
+}
+
+/// Show a variant, or the base scene for `None`, returning the variant that was shown before
+///
+/// Changes made to the fields the shown variant replaces are kept in that variant, and changes to
+/// the other fields are kept in the base scene.
+pub fn switch(world: &mut World, name: Option<&str>) -> Option<String> {
+    let mut variants = world.remove_resource::<SceneVariants>().unwrap_or_default();
+    let previous = variants.active.take();
+    if let Some(active) = &previous {
+        if let Some(variant) = variants.variants.get(active) {
+            let changed = capture(world, variant);
+            variants.variants.insert(active.clone(), changed);
+        }
+        apply(world, &std::mem::take(&mut variants.base));
+    }
+    if let Some((name, variant)) = name.and_then(|name| variants.variants.get_key_value(name)) {
+        variants.base = capture(world, variant);
+        apply(world, variant);
+        variants.active = Some(name.clone());
+    }
+    world.insert_resource(variants);
+    previous
+}
+
+/// Add an empty variant and show it, so the changes that follow can be made to it
+pub fn add(world: &mut World, name: &str) {
+    let mut variants = world.get_resource_or_insert_with(SceneVariants::default);
+    if variants.variants.contains_key(name) {
+        return;
+    }
+    variants.variants.insert(name.to_owned(), Variant::default());
+    switch(world, Some(name));
+}
+
+/// Remove a variant, showing the base scene if it was shown
+pub fn remove(world: &mut World, name: &str) {
+    let active = world.get_resource::<SceneVariants>().and_then(|v| v.active.clone());
+    if active.as_deref() == Some(name) {
+        switch(world, None);
+    }
+    if let Some(mut variants) = world.get_resource_mut::<SceneVariants>() {
+        variants.variants.remove(name);
+    }
+}
+
+/// Run `f` with the base scene shown, such as to save it, and then show the variant again
+pub fn with_base<T>(world: &mut World, f: impl FnOnce(&mut World) -> T) -> T {
+    let active = switch(world, None);
+    let result = f(world);
+    switch(world, active.as_deref());
+    result
+}
+
+/// Start or stop replacing a field in the variant that is shown
+///
+/// A field starts with the value it has in the base scene, except for `Field::Hidden`, which
+/// starts out hiding an entity that is shown in the base scene and the other way around. A field
+/// that is no longer replaced goes back to its value in the base scene.
+pub fn set_overridden(world: &mut World, entity: Option<Entity>, field: Field, overridden: bool) {
+    let mut variants = world.remove_resource::<SceneVariants>().unwrap_or_default();
+    let Some(variant) = variants.active.as_ref().and_then(|a| variants.variants.get_mut(a)) else {
+        world.insert_resource(variants);
+        return;
+    };
+    if overridden {
+        let base = current(world, entity, field);
+        let mut value = base.clone();
+        if let (Some(entity), Field::Hidden) = (entity, field) {
+            let o = value.entities.entry(entity).or_default();
+            o.hidden = Some(!o.hidden.unwrap_or_default());
+        }
+        apply(world, &value);
+        variant.merge(value);
+        variants.base.merge(base);
+    } else {
+        variant.take(entity, field);
+        let base = variants.base.take(entity, field);
+        apply(world, &base);
+    }
+    world.insert_resource(variants);
+}
+
+/// File a variant of a scene is exported to by default, such as `level_night.ron` for `level.ron`
+pub fn export_path(scene: &Path, name: &str) -> PathBuf {
+    let stem = scene.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "-_".contains(c) { c } else { '_' })
+        .collect();
+    let mut file = format!("{stem}_{name}");
+    if let Some(extension) = scene.extension() {
+        file = format!("{file}.{}", extension.to_string_lossy());
+    }
+    scene.with_file_name(file)
+}
+
+/// Save a variant as a scene of its own, with its values in place of the base values and no
+/// variants
+pub fn export(world: &mut World, name: &str, path: &Path) -> Result<()> {
+    let exists =
+        world.get_resource::<SceneVariants>().is_some_and(|v| v.variants.contains_key(name));
+    if !exists {
+        return Err(eyre!("the scene has no variant {name:?}"));
+    }
+    let active = switch(world, Some(name));
+    let mut scene = Scene::from_world(world);
+    switch(world, active.as_deref());
+    scene.variants.clear();
+    scene.save(path)
+}
+
+/// The current value of a field, as a variant replacing only that field
+fn current(world: &World, entity: Option<Entity>, field: Field) -> Variant<Entity> {
+    let mut variant = Variant::default();
+    let Some(entity) = entity else {
+        match field {
+            Field::Lighting => variant.lighting = world.get_resource::<Lighting>().copied(),
+            Field::Variables => {
+                let variables = world.get_resource::<SceneVariables>();
+                variant.variables = variables.map(|v| v.values.clone()).unwrap_or_default();
+            }
+            _ => {}
+        }
+        return variant;
+    };
+    let Some(entity_ref) = world.get_entity(entity) else {
+        return variant;
+    };
+    let o = variant.entities.entry(entity).or_default();
+    match field {
+        Field::Hidden => o.hidden = Some(entity_ref.contains::<Hidden>()),
+        Field::PointLight => o.point_light = entity_ref.get::<PointLight>().copied(),
+        Field::Textures => {
+            let texture = entity_ref.get::<CustomTexture>().copied().unwrap_or_default();
+            let loader = world.get_resource::<TextureLoader>();
+            let name = |texture: Option<glow::Texture>| Some(loader?.name_of(texture?)?.to_owned());
+            o.textures = Some(Textures {
+                diffuse: name(texture.diffuse),
+                specular: name(texture.specular),
+                normal: name(texture.normal),
+            });
+        }
+        Field::Uniforms => {
+            let params = entity_ref.get::<ShaderParams>();
+            o.uniforms = params.map(|p| p.values.clone()).unwrap_or_default();
+        }
+        Field::Lighting | Field::Variables => {}
+    }
+    variant
+}
+
+/// The current values of the fields a variant replaces
+fn capture(world: &World, like: &Variant<Entity>) -> Variant<Entity> {
+    let mut captured = Variant::default();
+    for (entity, field) in like.fields() {
+        captured.merge(current(world, entity, field));
+    }
+    captured
+}
+
+/// Set the fields a variant replaces to its values
+fn apply(world: &mut World, variant: &Variant<Entity>) {
+    if let Some(lighting) = variant.lighting {
+        world.insert_resource(lighting);
+    }
+    if !variant.variables.is_empty() {
+        let mut variables = world.get_resource_or_insert_with(SceneVariables::default);
+        variables.values.extend(variant.variables.clone());
+    }
+    for (&entity, o) in &variant.entities {
+        let textures = o.textures.as_ref().map(|textures| load_textures(world, textures));
+        let Some(mut entity) = world.get_entity_mut(entity) else {
+            continue;
+        };
+        match o.hidden {
+            Some(true) => {
+                entity.insert(Hidden);
+            }
+            Some(false) => {
+                entity.remove::<Hidden>();
+            }
+            None => {}
+        }
+        if let Some(point_light) = o.point_light {
+            entity.insert(point_light);
+        }
+        if let Some((diffuse, specular, normal)) = textures {
+            let alpha_test = entity.get::<CustomTexture>().is_some_and(|t| t.alpha_test);
+            entity.insert(CustomTexture { diffuse, specular, normal, alpha_test });
+        }
+        if let Some(mut params) = entity.get_mut::<ShaderParams>() {
+            for (name, value) in &o.uniforms {
+                // Uniforms the shader no longer has, or has with another type, are left out
+                if params.values.get(name).is_some_and(|current| current.same_type(value)) {
+                    params.values.insert(name.clone(), *value);
+                }
+            }
+        }
+    }
+}
+
+type LoadedTextures = (Option<glow::Texture>, Option<glow::Texture>, Option<glow::Texture>);
+
+fn load_textures(world: &mut World, textures: &Textures) -> LoadedTextures {
+    let Some(gl) = world.get_non_send_resource::<Arc<Context>>().cloned() else {
+        return (None, None, None);
+    };
+    let Some(mut loader) = world.get_resource_mut::<TextureLoader>() else {
+        return (None, None, None);
+    };
+    let mut lookup = |name: &Option<String>| loader.get_or_placeholder(&gl, name.as_ref()?);
+    (lookup(&textures.diffuse), lookup(&textures.specular), lookup(&textures.normal))
+}
@@ -0,0 +1,129 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use bevy_ecs::entity::Entity;
+use bevy_ecs::system::Resource;
+
+/// How long a frame can take before it is treated as stalled
+pub const STALL_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// Longest time between two checks of the watchdog thread
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Source of the current time, so tests can drive the watchdog without waiting
+type Now = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct FrameClock {
+    /// When the frame being drawn started, or `None` between frames
+    started: Option<Instant>,
+    /// Whether the watchdog thread has reported the frame being drawn
+    reported: bool,
+}
+
+/// Notices frames that take far too long, such as when a custom shader never finishes on the GPU
+///
+/// The game loop marks the start and end of each frame, while a thread started with
+/// [`spawn`](Self::spawn) reports a frame that is still not done after the threshold, so the
+/// editor does not just appear frozen.
+#[derive(Resource, Clone)]
+pub struct FrameWatchdog {
+    clock: Arc<Mutex<FrameClock>>,
+    threshold: Duration,
+    now: Now,
+}
+
+impl FrameWatchdog {
+    pub fn new(threshold: Duration) -> Self {
+        Self::with_clock(threshold, Instant::now)
+    }
+
+    /// Watchdog that reads the time from `now` instead of the system clock
+    pub fn with_clock<F>(threshold: Duration, now: F) -> Self
+    where
+        F: Fn() -> Instant + Send + Sync + 'static,
+    {
+        Self { clock: Arc::default(), threshold, now: Arc::new(now) }
+    }
+
+    pub fn begin_frame(&self) {
+        if let Ok(mut clock) = self.clock.lock() {
+            *clock = FrameClock { started: Some((self.now)()), reported: false };
+        }
+    }
+
+    /// How long the frame took, if it took longer than the threshold
+    pub fn end_frame(&self) -> Option<Duration> {
+        let started = self.clock.lock().ok()?.started.take()?;
+        Some((self.now)() - started).filter(|&duration| duration > self.threshold)
+    }
+
+    /// How long the frame being drawn has taken, if it is past the threshold already
+    pub fn stalled_for(&self) -> Option<Duration> {
+        let started = self.clock.lock().ok()?.started?;
+        Some((self.now)() - started).filter(|&duration| duration > self.threshold)
+    }
+
+    /// How long the frame being drawn has taken, the first time it is found past the threshold
+    ///
+    /// This is the check the thread from [`spawn`](Self::spawn) makes on every poll.
+    pub fn check(&self) -> Option<Duration> {
+        check(&self.clock, self.threshold, &self.now)
+    }
+
+    /// Start a thread that calls `on_stall` once for each frame that is past the threshold
+    ///
+    /// The thread stops when every clone of the watchdog is dropped.
+    pub fn spawn<F>(&self, on_stall: F) -> JoinHandle<()>
+    where
+        F: Fn(Duration) + Send + 'static,
+    {
+        let clock = Arc::downgrade(&self.clock);
+        let (threshold, now) = (self.threshold, self.now.clone());
+        let interval = (threshold / 4).min(POLL_INTERVAL);
+        thread::spawn(move || watch(clock, threshold, now, interval, on_stall))
+    }
+}
+
+impl Default for FrameWatchdog {
+    fn default() -> Self {
+        Self::new(STALL_THRESHOLD)
+    }
+}
+
+fn check(clock: &Mutex<FrameClock>, threshold: Duration, now: &Now) -> Option<Duration> {
+    let mut clock = clock.lock().ok()?;
+    let duration = now() - clock.started?;
+    if clock.reported || duration <= threshold {
+        return None;
+    }
+    clock.reported = true;
+    Some(duration)
+}
+
+fn watch<F>(
+    clock: Weak<Mutex<FrameClock>>,
+    threshold: Duration,
+    now: Now,
+    interval: Duration,
+    on_stall: F,
+) where
+    F: Fn(Duration),
+{
+    while let Some(clock) = clock.upgrade() {
+        let stalled = check(&clock, threshold, &now);
+        drop(clock);
+        if let Some(duration) = stalled {
+            on_stall(duration);
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// A frame that took too long, and the custom shaders that were drawn in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StalledFrame {
+    pub duration: Duration,
+    pub shaders: Vec<Entity>,
+}
@@ -0,0 +1,61 @@
+use nalgebra_glm as glm;
+use scene_editor::components::{FollowPath, Oscillator};
+
+use crate::common::assert_close;
+
+mod common;
+
+/// Three points making a right angle, 3 units along X and then 4 along Y
+fn corner(looping: bool) -> FollowPath {
+    let points = vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(3.0, 0.0, 0.0), glm::vec3(3.0, 4.0, 0.0)];
+    FollowPath::new(points, 1.0, looping)
+}
+
+#[test]
+fn oscillators_swing_along_their_axis() {
+    // Once every two seconds, with the axis normalized
+    let oscillator = Oscillator::new(glm::vec3(0.0, 2.0, 0.0), 1.5, 0.5);
+    assert_close(oscillator.offset_at(0.0), glm::Vec3::zeros());
+    assert_close(oscillator.offset_at(0.5), glm::vec3(0.0, 1.5, 0.0));
+    assert_close(oscillator.offset_at(1.0), glm::Vec3::zeros());
+    assert_close(oscillator.offset_at(1.5), glm::vec3(0.0, -1.5, 0.0));
+    assert_close(oscillator.offset_at(2.5), glm::vec3(0.0, 1.5, 0.0));
+
+    let still = Oscillator::new(glm::Vec3::zeros(), 1.5, 0.5);
+    assert_eq!(still.offset_at(0.5), glm::Vec3::zeros());
+}
+
+#[test]
+fn paths_are_sampled_by_distance_travelled() {
+    let path = corner(false);
+    assert_eq!(path.length(), 7.0);
+    assert_close(path.sample(1.5).unwrap(), glm::vec3(1.5, 0.0, 0.0));
+    assert_close(path.sample(5.0).unwrap(), glm::vec3(3.0, 2.0, 0.0));
+
+    // Paths that do not loop stop at their ends
+    assert_close(path.sample(-1.0).unwrap(), glm::vec3(0.0, 0.0, 0.0));
+    assert_close(path.sample(10.0).unwrap(), glm::vec3(3.0, 4.0, 0.0));
+}
+
+#[test]
+fn looping_paths_return_to_their_start() {
+    let path = corner(true);
+    // The closing segment from the last point back to the first is 5 long
+    assert_eq!(path.length(), 12.0);
+    assert_close(path.sample(9.5).unwrap(), glm::vec3(1.5, 2.0, 0.0));
+    assert_close(path.sample(13.5).unwrap(), glm::vec3(1.5, 0.0, 0.0));
+    assert_close(path.sample(-1.0).unwrap(), glm::vec3(0.6, 0.8, 0.0));
+
+    // Two points are not closed into a loop, so the path starts over from its first point
+    let line = FollowPath::new(vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(2.0, 0.0, 0.0)], 1.0, true);
+    assert_eq!(line.length(), 2.0);
+    assert_close(line.sample(3.0).unwrap(), glm::vec3(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn paths_without_length_stay_on_their_first_point() {
+    assert_eq!(FollowPath::new(Vec::new(), 1.0, true).sample(1.0), None);
+
+    let point = FollowPath::new(vec![glm::vec3(1.0, 2.0, 3.0)], 1.0, false);
+    assert_eq!(point.sample(5.0), Some(glm::vec3(1.0, 2.0, 3.0)));
+}
@@ -0,0 +1,48 @@
+use scene_editor::components::{AnimationClip, Keyframe, Position, Rotation, Scale};
+
+fn keyframe(time: f32, x: f32) -> Keyframe {
+    Keyframe {
+        time,
+        position: Position::new(x, 0.0, 0.0),
+        rotation: Rotation::default(),
+        scale: Scale::default(),
+    }
+}
+
+fn clip(length: f32, looping: bool) -> AnimationClip {
+    AnimationClip {
+        keyframes: vec![keyframe(0.0, 0.0), keyframe(1.0, 2.0)],
+        looping,
+        length,
+        playing: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn trailing_time_holds_the_last_keyframe_before_looping() {
+    let mut clip = clip(3.0, true);
+    clip.advance(2.0);
+    assert_eq!(clip.time, 2.0);
+    assert_eq!(clip.sample(clip.time).unwrap().position, Position::new(2.0, 0.0, 0.0));
+
+    clip.advance(1.5);
+    assert!((clip.time - 0.5).abs() < 1e-5);
+    assert!(clip.playing);
+}
+
+#[test]
+fn playback_stops_at_the_length_without_looping() {
+    let mut clip = clip(3.0, false);
+    clip.advance(4.0);
+    assert_eq!(clip.time, 3.0);
+    assert!(!clip.playing);
+}
+
+#[test]
+fn keyframes_past_the_length_extend_it() {
+    let mut clip = clip(0.5, true);
+    assert_eq!(clip.end(), 1.0);
+    clip.advance(0.75);
+    assert_eq!(clip.time, 0.75);
+}
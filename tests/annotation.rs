@@ -0,0 +1,52 @@
+#![cfg(feature = "editor")]
+
+use egui::{pos2, Color32, Rect};
+use scene_editor::annotation::{draw_marks, Annotation, Mark};
+
+const SIZE: u32 = 64;
+
+fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let i = (y * SIZE + x) as usize * 4;
+    pixels[i..i + 4].try_into().unwrap()
+}
+
+fn black_image() -> Vec<u8> {
+    [0, 0, 0, 255].repeat((SIZE * SIZE) as usize)
+}
+
+#[test]
+fn box_is_drawn_along_its_edges() {
+    let mark = Mark {
+        annotation: Annotation::Box {
+            rect: Rect::from_min_max(pos2(16.0, 16.0), pos2(48.0, 48.0)),
+        },
+        color: Color32::RED,
+        size: 4.0,
+    };
+
+    let pixels = draw_marks(&black_image(), SIZE, SIZE, &[mark]);
+
+    assert_eq!(pixel(&pixels, 32, 16), [255, 0, 0, 255]);
+    assert_eq!(pixel(&pixels, 48, 32), [255, 0, 0, 255]);
+    assert_eq!(pixel(&pixels, 32, 32), [0, 0, 0, 255]);
+    assert_eq!(pixel(&pixels, 4, 4), [0, 0, 0, 255]);
+}
+
+#[test]
+fn text_is_drawn_where_it_is_placed() {
+    let mark = Mark {
+        annotation: Annotation::Text { pos: pos2(4.0, 4.0), text: "Bug".to_owned() },
+        color: Color32::WHITE,
+        size: 24.0,
+    };
+
+    let pixels = draw_marks(&black_image(), SIZE, SIZE, &[mark]);
+
+    let lit = |x0: u32, x1: u32, y0: u32, y1: u32| {
+        (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .any(|(x, y)| pixel(&pixels, x, y)[0] > 128)
+    };
+    assert!(lit(4, 40, 4, 30));
+    assert!(!lit(0, SIZE, 40, SIZE));
+}
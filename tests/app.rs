@@ -0,0 +1,63 @@
+#![cfg(feature = "editor")]
+
+use bevy_ecs::prelude::*;
+use scene_editor::EditorSet;
+
+#[derive(Resource, Default)]
+struct Ran(Vec<EditorSet>);
+
+#[derive(Component)]
+struct Spawned;
+
+fn record(set: EditorSet) -> impl FnMut(ResMut<Ran>) {
+    move |mut ran| ran.0.push(set)
+}
+
+#[test]
+fn sets_run_in_the_order_of_a_frame() {
+    let mut world = World::new();
+    world.init_resource::<Ran>();
+    let mut schedule = Schedule::default();
+    EditorSet::configure(&mut schedule);
+    // Added in reverse, so only the set order puts them right
+    for set in [
+        EditorSet::Cleanup,
+        EditorSet::Render,
+        EditorSet::Simulation,
+        EditorSet::EditorUi,
+        EditorSet::Input,
+    ] {
+        schedule.add_system(record(set).in_set(set));
+    }
+
+    schedule.run(&mut world);
+    assert_eq!(
+        world.resource::<Ran>().0,
+        [
+            EditorSet::Input,
+            EditorSet::EditorUi,
+            EditorSet::Simulation,
+            EditorSet::Render,
+            EditorSet::Cleanup,
+        ]
+    );
+}
+
+fn spawn(mut commands: Commands) {
+    commands.spawn(Spawned);
+}
+
+fn expect_spawned(spawned: Query<&Spawned>) {
+    assert_eq!(spawned.iter().count(), 1);
+}
+
+#[test]
+fn commands_are_applied_before_the_next_set() {
+    let mut world = World::new();
+    let mut schedule = Schedule::default();
+    EditorSet::configure(&mut schedule);
+    schedule.add_system(spawn.in_set(EditorSet::EditorUi));
+    schedule.add_system(expect_spawned.in_set(EditorSet::Render));
+
+    schedule.run(&mut world);
+}
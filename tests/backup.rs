@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+
+use scene_editor::backup::{self, BACKUP_DIR};
+
+use crate::common::temp_dir;
+
+mod common;
+
+fn temp_scene(name: &str) -> PathBuf {
+    temp_dir("backup", name).join("level.ron")
+}
+
+#[test]
+fn timestamps_are_utc_dates() {
+    let at = |secs| backup::timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+    assert_eq!(at(0), "1970-01-01_00-00-00");
+    assert_eq!(at(951_782_400), "2000-02-29_00-00-00");
+    assert_eq!(at(1_792_228_102), "2026-10-17_09-08-22");
+}
+
+#[test]
+fn new_scenes_are_not_backed_up() {
+    let scene = temp_scene("new");
+    assert_eq!(backup::back_up(&scene, 5).unwrap(), None);
+    assert!(!scene.with_file_name(BACKUP_DIR).exists());
+}
+
+#[test]
+fn oldest_backups_are_removed() {
+    let scene = temp_scene("rotation");
+    let dir = backup::backup_dir(&scene);
+    fs::create_dir_all(&dir).unwrap();
+    for day in 1..=4 {
+        fs::write(dir.join(format!("level.2020-01-0{day}_12-00-00.ron")), "old").unwrap();
+    }
+    // Not backups of this scene
+    fs::write(dir.join("level.old.ron"), "").unwrap();
+    fs::write(dir.join("other.2020-01-01_12-00-00.ron"), "").unwrap();
+    fs::write(&scene, "current").unwrap();
+
+    let made = backup::back_up(&scene, 3).unwrap().unwrap();
+
+    let backups = backup::list(&scene);
+    let timestamps: Vec<_> = backups.iter().map(|b| b.timestamp.as_str()).collect();
+    assert_eq!(timestamps.len(), 3);
+    assert_eq!(backups[0].path, made);
+    assert_eq!(&timestamps[1..], ["2020-01-04_12-00-00", "2020-01-03_12-00-00"]);
+    assert_eq!(fs::read_to_string(made).unwrap(), "current");
+    assert!(dir.join("level.old.ron").exists());
+    assert!(dir.join("other.2020-01-01_12-00-00.ron").exists());
+}
+
+#[test]
+fn restoring_backs_up_the_scene_first() {
+    let scene = temp_scene("restore");
+    let dir = backup::backup_dir(&scene);
+    fs::create_dir_all(&dir).unwrap();
+    let old = dir.join("level.2020-01-01_12-00-00.ron");
+    fs::write(&old, "old").unwrap();
+    fs::write(&scene, "broken").unwrap();
+
+    backup::restore(&old, &scene, 1).unwrap();
+
+    assert_eq!(fs::read_to_string(&scene).unwrap(), "old");
+    let backups = backup::list(&scene);
+    assert_eq!(backups.len(), 1);
+    assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "broken");
+}
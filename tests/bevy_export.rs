@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use nalgebra_glm as glm;
+use scene_editor::bevy_export::{to_ron, BevyExportOptions};
+use scene_editor::components::{CameraComponent, PointLight, PropertyValue};
+use scene_editor::scene::Scene;
+use serde::Deserialize;
+
+/// The fields Bevy's `SceneDeserializer` reads, refusing any others
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BevyScene {
+    resources: BTreeMap<String, ron::Value>,
+    entities: BTreeMap<u32, BevyEntity>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BevyEntity {
+    components: BTreeMap<String, ron::Value>,
+}
+
+fn scene() -> Scene {
+    let mut scene: Scene = Scene::new()
+        .spawn_model("cube")
+        .at(1.0, 2.0, 3.0)
+        .tag("pickup")
+        .spawn_model("lamp")
+        .point_light(PointLight::new(
+            glm::vec3(0.1, 0.1, 0.1),
+            glm::vec3(0.5, 0.25, 0.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            1.0,
+            0.09,
+            0.032,
+        ))
+        .spawn_model("camera")
+        .camera(CameraComponent::default())
+        .into();
+    scene.entities[2].hidden = true;
+    scene.entities[0].properties.insert("points".to_owned(), PropertyValue::Number(10.0));
+    scene
+}
+
+/// The exported scene without whitespace, as the pretty printer breaks up structs over lines
+fn export(options: &BevyExportOptions) -> String {
+    to_ron(&scene(), options).unwrap().chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn with_module(module: &str) -> BevyExportOptions {
+    BevyExportOptions { module: Some(module.to_owned()) }
+}
+
+#[test]
+fn writes_bevy_components() {
+    let ron = export(&BevyExportOptions::default());
+    assert!(ron.contains("entities"));
+    assert_eq!(ron.matches("\"bevy_transform::components::transform::Transform\"").count(), 3);
+    assert!(ron.contains("translation:(x:1.0,y:2.0,z:3.0"), "{ron}");
+    assert!(ron.contains("rotation:(0.0,0.0,0.0,1.0"), "{ron}");
+    assert_eq!(ron.matches("\"bevy_render::view::visibility::Visibility\"").count(), 3);
+    assert_eq!(ron.matches("Inherited").count(), 2);
+    assert_eq!(ron.matches("Hidden").count(), 1);
+    assert_eq!(ron.matches("\"bevy_pbr::light::PointLight\"").count(), 1);
+    // The brightest channel of the diffuse color sets the intensity
+    assert!(ron.contains("Rgba(red:1.0,green:0.5,blue:0.0,alpha:1.0"), "{ron}");
+    assert!(ron.contains("intensity:400.0"), "{ron}");
+}
+
+#[test]
+fn follows_the_bevy_scene_format() {
+    let ron = to_ron(&scene(), &with_module("my_game::level")).unwrap();
+    let scene: BevyScene = ron::from_str(&ron).unwrap();
+    assert!(scene.resources.is_empty());
+    assert_eq!(scene.entities.keys().copied().collect::<Vec<_>>(), [0, 1, 2]);
+    for entity in scene.entities.values() {
+        for name in [
+            "bevy_transform::components::transform::Transform",
+            "bevy_transform::components::global_transform::GlobalTransform",
+            "bevy_render::view::visibility::Visibility",
+            "bevy_render::view::visibility::ComputedVisibility",
+        ] {
+            assert!(entity.components.contains_key(name), "{name} is missing in {ron}");
+        }
+    }
+    let light = &scene.entities[&1].components;
+    assert!(light.contains_key("bevy_pbr::bundle::CubemapVisibleEntities"), "{ron}");
+    assert!(light.contains_key("bevy_render::primitives::CubemapFrusta"), "{ron}");
+}
+
+#[test]
+fn global_transforms_are_affine_matrices() {
+    let ron = export(&BevyExportOptions::default());
+    let global = "\"bevy_transform::components::global_transform::GlobalTransform\":((matrix3:(\
+        x_axis:(x:1.0,y:0.0,z:0.0),y_axis:(x:0.0,y:1.0,z:0.0),z_axis:(x:0.0,y:0.0,z:1.0)),\
+        translation:(x:1.0,y:2.0,z:3.0)))";
+    assert!(ron.contains(global), "{ron}");
+}
+
+#[test]
+fn game_components_need_a_module() {
+    let ron = export(&BevyExportOptions::default());
+    assert!(!ron.contains("SceneModel"));
+    assert!(!ron.contains("SceneCamera"));
+    assert!(!ron.contains("SceneTags"));
+    assert!(!ron.contains("SceneProperties"));
+
+    let ron = export(&with_module("my_game::level"));
+    assert_eq!(ron.matches("\"my_game::level::SceneModel\"").count(), 3);
+    assert!(ron.contains("model:\"lamp\""), "{ron}");
+    assert_eq!(ron.matches("\"my_game::level::SceneCamera\"").count(), 1);
+    assert!(ron.contains("near:0.1"), "{ron}");
+    assert_eq!(ron.matches("\"my_game::level::SceneTags\"").count(), 1);
+    assert!(ron.contains("\"pickup\""), "{ron}");
+    assert_eq!(ron.matches("\"my_game::level::SceneProperties\"").count(), 1);
+    assert!(ron.contains("properties:{\"points\":Number(10.0)}"), "{ron}");
+}
+
+#[test]
+fn rejects_invalid_modules() {
+    for module in ["", "my game", "my_game::", "::level", "1st::level", "my-game"] {
+        assert!(to_ron(&scene(), &with_module(module)).is_err(), "{module:?} should be rejected");
+    }
+    assert!(to_ron(&scene(), &with_module("_game::level2")).is_ok());
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "editor")]
+
+use nalgebra_glm as glm;
+use scene_editor::bounds::Aabb;
+use scene_editor::resources::{BoxSelection, Camera};
+
+fn unit_box_at(center: glm::Vec3) -> Aabb {
+    Aabb::new(center - glm::vec3(0.5, 0.5, 0.5), center + glm::vec3(0.5, 0.5, 0.5))
+}
+
+#[test]
+fn boxes_in_front_of_the_camera_cover_part_of_the_screen() {
+    let camera = Camera::looking_forward(100, 100);
+    let mvp = camera.projection * camera.view();
+
+    let (min, max) = unit_box_at(glm::vec3(0.0, 0.0, -5.0)).ndc_rect(&mvp).unwrap();
+    assert!(min.x < 0.0 && min.y < 0.0 && max.x > 0.0 && max.y > 0.0);
+    assert!(max.x < 1.0 && max.y < 1.0);
+
+    // To the right of the camera the box is on the right half of the screen
+    let (min, _) = unit_box_at(glm::vec3(3.0, 0.0, -5.0)).ndc_rect(&mvp).unwrap();
+    assert!(min.x > 0.0);
+}
+
+#[test]
+fn boxes_behind_the_camera_are_not_on_the_screen() {
+    let camera = Camera::looking_forward(100, 100);
+    let mvp = camera.projection * camera.view();
+
+    assert_eq!(unit_box_at(glm::vec3(0.0, 0.0, 5.0)).ndc_rect(&mvp), None);
+}
+
+#[test]
+fn boxes_through_the_near_plane_are_clipped_to_it() {
+    let camera = Camera::looking_forward(100, 100);
+    let mvp = camera.projection * camera.view();
+
+    // A long box reaching from behind the camera to the right of the view still covers the right
+    // edge of the screen, where only its corners in front would leave it out of view
+    let wall = Aabb::new(glm::vec3(1.0, -0.5, -20.0), glm::vec3(2.0, 0.5, 20.0));
+    let (min, max) = wall.ndc_rect(&mvp).unwrap();
+    assert!(min.x < 1.0 && max.x > 1.0, "{min} {max}");
+    assert!(min.y < 0.0 && max.y > 0.0);
+
+    // The camera is inside this box, which covers the whole screen
+    let (min, max) = unit_box_at(camera.pos).ndc_rect(&mvp).unwrap();
+    assert!(min.x <= -1.0 && min.y <= -1.0 && max.x >= 1.0 && max.y >= 1.0, "{min} {max}");
+}
+
+#[test]
+fn clicks_do_not_become_boxes() {
+    let mut selection = BoxSelection { start: Some((10.0, 20.0)), end: (12.0, 21.0) };
+    assert_eq!(selection.rect(), None);
+
+    // Dragged up and to the left, the corners are still top left and bottom right
+    selection.end = (2.0, 5.0);
+    assert_eq!(selection.rect(), Some(((2.0, 5.0), (10.0, 20.0))));
+
+    assert_eq!(BoxSelection::default().rect(), None);
+}
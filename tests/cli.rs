@@ -0,0 +1,158 @@
+#![cfg(feature = "editor")]
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use scene_editor::frame_limiter::VsyncMode;
+use scene_editor::{Cli, Command};
+
+#[test]
+fn defaults_match_the_res_directories() {
+    let options = Cli::try_parse_from(["scene-editor"]).unwrap().startup_options();
+
+    assert_eq!(options.scene, None);
+    assert_eq!(options.models_dir, PathBuf::from("res/models"));
+    assert_eq!(options.textures_dir, PathBuf::from("res/textures"));
+    assert_eq!(options.window_size, None);
+    assert_eq!(options.vsync, None);
+    assert!(!options.watch_shaders);
+    assert!(!options.view_only);
+}
+
+#[test]
+fn parses_scene_dirs_size_vsync_and_shader_watching() {
+    let cli = Cli::try_parse_from([
+        "scene-editor",
+        "level.ron",
+        "--models",
+        "assets/models",
+        "--textures",
+        "assets/textures",
+        "--width",
+        "1280",
+        "--height",
+        "720",
+        "--vsync",
+        "off",
+        "--watch-shaders",
+    ])
+    .unwrap();
+    let options = cli.startup_options();
+
+    assert_eq!(options.scene, Some(PathBuf::from("level.ron")));
+    assert_eq!(options.models_dir, PathBuf::from("assets/models"));
+    assert_eq!(options.textures_dir, PathBuf::from("assets/textures"));
+    assert_eq!(options.window_size, Some((1280, 720)));
+    assert_eq!(options.vsync, Some(VsyncMode::Off));
+    assert!(options.watch_shaders);
+    assert!(cli.headless_options().is_none());
+}
+
+#[test]
+fn opens_scenes_for_viewing_only() {
+    let cli = Cli::try_parse_from(["scene-editor", "level.ron", "--view-only"]).unwrap();
+    let options = cli.startup_options();
+
+    assert_eq!(options.scene, Some(PathBuf::from("level.ron")));
+    assert!(options.view_only);
+}
+
+#[test]
+fn headless_renders_a_full_hd_image_by_default() {
+    let cli = Cli::try_parse_from(["scene-editor", "--headless", "level.ron"]).unwrap();
+    let options = cli.headless_options().unwrap();
+
+    assert_eq!(options.scene, PathBuf::from("level.ron"));
+    assert_eq!(options.output, PathBuf::from("render.png"));
+    assert_eq!(options.frames, 1);
+    assert_eq!((options.width, options.height), (1920, 1080));
+    assert_eq!(options.view, None);
+    assert_eq!(options.variant, None);
+}
+
+#[test]
+fn headless_options() {
+    let cli = Cli::try_parse_from([
+        "scene-editor",
+        "--headless",
+        "level.ron",
+        "--output",
+        "frames",
+        "--frames",
+        "24",
+        "--view",
+        "3",
+        "--variant",
+        "night",
+    ])
+    .unwrap();
+    let options = cli.headless_options().unwrap();
+
+    assert_eq!(options.output, PathBuf::from("frames"));
+    assert_eq!(options.frames, 24);
+    assert_eq!(options.view, Some(3));
+    assert_eq!(options.variant.as_deref(), Some("night"));
+}
+
+#[test]
+fn golden_options() {
+    let cli = Cli::try_parse_from(["scene-editor"]).unwrap();
+    assert!(cli.golden_options().is_none());
+
+    let options =
+        Cli::try_parse_from(["scene-editor", "--golden"]).unwrap().golden_options().unwrap();
+    assert_eq!(options.dir, PathBuf::from("tests/golden"));
+    assert_eq!(options.report, PathBuf::from("target/golden-report"));
+    assert!(!options.approve);
+
+    let cli = Cli::try_parse_from(["scene-editor", "--golden", "goldens", "--approve"]).unwrap();
+    let options = cli.golden_options().unwrap();
+    assert_eq!(options.dir, PathBuf::from("goldens"));
+    assert!(options.approve);
+}
+
+#[test]
+fn generates_the_demo() {
+    let cli = Cli::try_parse_from(["scene-editor", "generate-demo", "demo"]).unwrap();
+    assert_eq!(cli.command, Some(Command::GenerateDemo { dir: PathBuf::from("demo") }));
+    assert!(Cli::try_parse_from(["scene-editor"]).unwrap().command.is_none());
+}
+
+#[test]
+fn exports_to_bevy() {
+    let args = ["scene-editor", "export-bevy", "level.ron", "level.scn.ron"];
+    let cli = Cli::try_parse_from(args).unwrap();
+    let Some(Command::ExportBevy { scene, output, module }) = cli.command else {
+        panic!("expected export-bevy, got {:?}", cli.command);
+    };
+    assert_eq!((scene, output), (PathBuf::from("level.ron"), PathBuf::from("level.scn.ron")));
+    assert_eq!(module, None);
+
+    let args = ["scene-editor", "export-bevy", "a.ron", "a.scn.ron", "--module", "game::level"];
+    let cli = Cli::try_parse_from(args).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Command::ExportBevy { module: Some(m), .. }) if m == "game::level"
+    ));
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    for args in [
+        &["scene-editor", "--headless"][..],
+        &["scene-editor", "--width", "640"],
+        &["scene-editor", "--vsync", "maybe"],
+        &["scene-editor", "level.ron", "--frames", "2"],
+        &["scene-editor", "--headless", "level.ron", "--frames", "0"],
+        &["scene-editor", "--headless", "level.ron", "--watch-shaders"],
+        &["scene-editor", "--headless", "level.ron", "--view-only"],
+        &["scene-editor", "level.ron", "--variant", "night"],
+        &["scene-editor", "--approve"],
+        &["scene-editor", "--golden", "--headless", "level.ron"],
+        &["scene-editor", "generate-demo"],
+        &["scene-editor", "export-bevy", "level.ron"],
+        &["scene-editor", "--headless", "generate-demo", "demo"],
+    ] {
+        assert!(Cli::try_parse_from(args).is_err(), "{args:?} should be rejected");
+    }
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "editor")]
+
+use scene_editor::command_palette::{self, PaletteCommand};
+use scene_editor::input_map::{Action, Binding, InputMap};
+use scene_editor::resources::Input;
+use winit::event::VirtualKeyCode;
+
+#[test]
+fn queries_match_characters_in_order() {
+    assert!(command_palette::fuzzy_score("tqv", "Toggle quad view").is_some());
+    assert!(command_palette::fuzzy_score("TOGGLE", "Toggle quad view").is_some());
+    assert!(command_palette::fuzzy_score("spawn cube", "Spawn Cube").is_some());
+    assert_eq!(command_palette::fuzzy_score("vq", "Toggle quad view"), None);
+    assert_eq!(command_palette::fuzzy_score("x", "Toggle quad view"), None);
+    assert_eq!(command_palette::fuzzy_score("", "Quit"), Some(0));
+}
+
+#[test]
+fn word_starts_and_runs_rank_higher() {
+    let entries = command_palette::entries(&InputMap::default(), ["Sphere", "Cube"]);
+    let labels = |query| -> Vec<String> {
+        let matches = command_palette::search(&entries, query);
+        matches.into_iter().map(|entry| entry.label.clone()).collect()
+    };
+
+    assert_eq!(labels("quad")[0], "Toggle quad view");
+    assert_eq!(labels("spc")[0], "Spawn Cube");
+    assert_eq!(labels("frame")[0], "Frame all objects");
+    assert!(labels("zzz").is_empty());
+    assert_eq!(labels("").len(), entries.len());
+}
+
+#[test]
+fn entries_cover_actions_panels_and_models() {
+    let entries = command_palette::entries(&InputMap::default(), ["Cube"]);
+    let command = |label: &str| {
+        let entry = entries.iter().find(|entry| entry.label == label);
+        entry.map(|entry| (entry.command.clone(), entry.binding))
+    };
+
+    assert_eq!(
+        command("Toggle quad view"),
+        Some((
+            PaletteCommand::Action(Action::ToggleQuadView),
+            Some(Binding::CtrlKey(VirtualKeyCode::Q))
+        ))
+    );
+    assert_eq!(command("Spawn Cube"), Some((PaletteCommand::Spawn("Cube".to_owned()), None)));
+    assert_eq!(command("Save scene"), Some((PaletteCommand::SaveScene, None)));
+    // Held actions do nothing when run once
+    assert_eq!(command("Move forward"), None);
+    assert_eq!(command("Command palette"), None);
+}
+
+#[test]
+fn triggered_actions_count_as_pressed_for_one_frame() {
+    let input_map = InputMap::default();
+    let mut input = Input::default();
+    input.trigger(Action::FrameAll);
+    assert!(!input_map.pressed(&input, Action::FrameAll));
+
+    input.update_after_frame();
+    assert!(input_map.pressed(&input, Action::FrameAll));
+    assert!(input_map.repeated(&input, Action::FrameAll));
+    assert!(!input_map.pressed(&input, Action::FocusSelected));
+
+    input.update_after_frame();
+    assert!(!input_map.pressed(&input, Action::FrameAll));
+}
+
+#[test]
+fn ctrl_shift_bindings_round_trip() {
+    let binding = InputMap::default().get(Action::CommandPalette);
+    assert_eq!(binding, Binding::CtrlShiftKey(VirtualKeyCode::P));
+    assert_eq!(binding.to_string(), "Ctrl+Shift+P");
+    assert_eq!(Binding::parse("Ctrl+Shift+P"), Some(binding));
+    assert_eq!(Binding::parse("Ctrl+P"), Some(Binding::CtrlKey(VirtualKeyCode::P)));
+}
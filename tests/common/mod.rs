@@ -0,0 +1,57 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use nalgebra_glm as glm;
+use scene_editor::components::Mesh;
+
+/// A cube that was never uploaded, for tests that do not draw
+pub fn mesh() -> Mesh {
+    mesh_with(1, 12)
+}
+
+/// A mesh with the given vertex array name and number of triangles, for tests that do not draw
+pub fn mesh_with(vao: u32, triangles: usize) -> Mesh {
+    Mesh {
+        vao_id: glow::NativeVertexArray(NonZeroU32::new(vao).unwrap()),
+        indices_len: triangles * 3,
+        aabb: Default::default(),
+        has_tangents: false,
+    }
+}
+
+/// An empty folder for one test of a suite, with what an earlier run left in it removed
+pub fn temp_dir(suite: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("scene_editor_{suite}")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Whether two vectors or matrices differ by less than `1e-4` in every component
+pub fn close<const R: usize, const C: usize>(
+    a: glm::TMat<f32, R, C>,
+    b: glm::TMat<f32, R, C>,
+) -> bool {
+    (a - b).abs().max() < 1e-4
+}
+
+/// Panic unless two vectors or matrices differ by less than `1e-4` in every component
+pub fn assert_close<const R: usize, const C: usize>(
+    a: glm::TMat<f32, R, C>,
+    b: glm::TMat<f32, R, C>,
+) {
+    assert_close_within(a, b, 1e-4);
+}
+
+/// Panic unless two vectors or matrices differ by less than `tolerance` in every component
+pub fn assert_close_within<const R: usize, const C: usize>(
+    a: glm::TMat<f32, R, C>,
+    b: glm::TMat<f32, R, C>,
+    tolerance: f32,
+) {
+    assert!((a - b).abs().max() < tolerance, "{a:?} is not close to {b:?}");
+}
@@ -0,0 +1,180 @@
+use bevy_ecs::prelude::*;
+use scene_editor::components::{CameraComponent, TransformBundle};
+use scene_editor::registry::ComponentRegistry;
+use scene_editor::resources::{ModelLoader, TextureLoader};
+use scene_editor::scene::{Scene, SceneEntity};
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Default, Debug, PartialEq, Serialize, Deserialize)]
+struct Health {
+    current: f32,
+    max: f32,
+}
+
+#[derive(Component, Default, Debug, PartialEq, Serialize, Deserialize)]
+struct Tag(String);
+
+#[derive(Component, Default, Debug, PartialEq, Serialize, Deserialize)]
+enum Team {
+    #[default]
+    Red,
+    Blue,
+}
+
+fn registry() -> ComponentRegistry {
+    let mut registry = ComponentRegistry::new();
+    registry.register::<Health>("Health").register::<Tag>("Tag");
+    registry
+}
+
+#[test]
+fn registered_component_round_trips_between_entities() {
+    let registry = registry();
+    let mut world = World::new();
+    let source = world.spawn(Health { current: 3.0, max: 10.0 }).id();
+    let target = world.spawn_empty().id();
+
+    let health = registry.get("Health").unwrap();
+    let value = health.serialize(&world, source).unwrap().unwrap();
+    health.insert(&mut world, target, value).unwrap();
+
+    assert_eq!(world.get::<Health>(target), Some(&Health { current: 3.0, max: 10.0 }));
+    assert!(registry.get("Tag").unwrap().serialize(&world, source).is_none());
+}
+
+#[test]
+fn insert_default_and_remove() {
+    let registry = registry();
+    let mut world = World::new();
+    let entity = world.spawn_empty().id();
+
+    let tag = registry.get("Tag").unwrap();
+    tag.insert_default(&mut world, entity);
+    assert_eq!(world.get::<Tag>(entity), Some(&Tag::default()));
+
+    tag.remove(&mut world, entity);
+    assert!(world.get::<Tag>(entity).is_none());
+}
+
+#[test]
+fn registering_twice_replaces_the_registration() {
+    let mut registry = registry();
+    registry.register::<Health>("Health");
+
+    let names: Vec<_> = registry.iter().map(|r| r.name()).collect();
+    assert_eq!(names, ["Health", "Tag"]);
+}
+
+#[test]
+fn search_is_sorted_and_ignores_case() {
+    let mut registry = registry();
+    registry.register::<Team>("Team");
+
+    let names = |filter| registry.search(filter).iter().map(|r| r.name()).collect::<Vec<_>>();
+    assert_eq!(names(""), ["Health", "Tag", "Team"]);
+    assert_eq!(names(" tA"), ["Tag"]);
+    assert_eq!(names("e"), ["Health", "Team"]);
+    assert!(names("mana").is_empty());
+}
+
+/// Run the inspector of a component for a frame with no input
+#[cfg(feature = "editor")]
+fn inspect(registry: &ComponentRegistry, name: &str, value: &mut ron::Value) -> bool {
+    let registration = registry.get(name).unwrap();
+    let mut result = None;
+    let ctx = egui::Context::default();
+    let _ = ctx.run(Default::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            result = Some(registration.inspect(value, ui));
+        });
+    });
+    result.unwrap().unwrap()
+}
+
+#[cfg(feature = "editor")]
+#[test]
+fn components_without_inspect_get_field_widgets() {
+    let registry = registry();
+    let mut world = World::new();
+    let entity = world.spawn((Health { current: 3.0, max: 10.0 }, Tag("boss".to_owned()))).id();
+
+    for name in ["Health", "Tag"] {
+        let registration = registry.get(name).unwrap();
+        let mut value = registration.serialize(&world, entity).unwrap().unwrap();
+        let before = value.clone();
+        assert!(!inspect(&registry, name, &mut value));
+        assert_eq!(value, before);
+    }
+}
+
+#[cfg(feature = "editor")]
+#[test]
+fn components_that_can_not_be_read_back_are_not_edited() {
+    let mut registry = ComponentRegistry::new();
+    registry.register::<Team>("Team");
+    let mut world = World::new();
+    let entity = world.spawn(Team::Blue).id();
+
+    // Enum variants are lost in a `ron::Value`
+    let team = registry.get("Team").unwrap();
+    let mut value = team.serialize(&world, entity).unwrap().unwrap();
+    let ctx = egui::Context::default();
+    let _ = ctx.run(Default::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            assert!(team.inspect(&mut value, ui).is_err());
+        });
+    });
+}
+
+#[test]
+fn scene_components_survive_ron_round_trip() {
+    let registry = registry();
+    let mut world = World::new();
+    let source = world.spawn((Health { current: 1.0, max: 2.0 }, Tag("boss".to_owned()))).id();
+
+    let mut entity = SceneEntity::new("cube");
+    for registration in registry.iter() {
+        let value = registration.serialize(&world, source).unwrap().unwrap();
+        entity.components.insert(registration.name().to_owned(), value);
+    }
+    let scene = Scene { entities: vec![entity], ..Default::default() };
+
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    let target = world.spawn_empty().id();
+    for (name, value) in &loaded.entities[0].components {
+        registry.get(name).unwrap().insert(&mut world, target, value.clone()).unwrap();
+    }
+
+    assert_eq!(world.get::<Health>(target), Some(&Health { current: 1.0, max: 2.0 }));
+    assert_eq!(world.get::<Tag>(target), Some(&Tag("boss".to_owned())));
+}
+
+#[test]
+fn registered_components_are_saved_with_the_scene() {
+    let mut world = World::new();
+    world.init_resource::<ModelLoader>();
+    world.init_resource::<TextureLoader>();
+    world.insert_resource(registry());
+    // Cameras are the only entities saved without a model, so no GL context is needed
+    world.spawn((
+        TransformBundle::default(),
+        CameraComponent::default(),
+        Health { current: 4.0, max: 8.0 },
+        Tag("scout".to_owned()),
+        Team::Blue,
+    ));
+
+    let scene = Scene::from_world(&mut world);
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    let components = &loaded.entities[0].components;
+    // Unregistered components are left out of the scene
+    assert_eq!(components.keys().collect::<Vec<_>>(), ["Health", "Tag"]);
+
+    let registry = world.resource::<ComponentRegistry>().clone();
+    let target = world.spawn_empty().id();
+    for (name, value) in components {
+        registry.get(name).unwrap().insert(&mut world, target, value.clone()).unwrap();
+    }
+    assert_eq!(world.get::<Health>(target), Some(&Health { current: 4.0, max: 8.0 }));
+    assert_eq!(world.get::<Tag>(target), Some(&Tag("scout".to_owned())));
+}
@@ -0,0 +1,211 @@
+#![cfg(feature = "editor")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use scene_editor::config::{EditorConfig, MAX_RECENT_FILES};
+use scene_editor::frame_limiter::VsyncMode;
+use scene_editor::palette::PalettePreset;
+use scene_editor::settings::{DockArea, DockLayout, Panel};
+use scene_editor::texture_import::TextureQuality;
+
+use crate::common::temp_dir;
+
+mod common;
+
+fn temp_path(name: &str) -> PathBuf {
+    temp_dir("config", name).join("config.toml")
+}
+
+#[test]
+fn missing_file_gives_defaults() {
+    let config = EditorConfig::load(temp_path("missing")).unwrap();
+
+    assert_eq!(config, EditorConfig::default());
+    assert_eq!(config.vsync, VsyncMode::On);
+    assert_eq!(config.fps_limit, None);
+}
+
+#[test]
+fn saved_config_loads_the_same() {
+    let path = temp_path("round_trip");
+    let mut config = EditorConfig::default();
+    config.window.size = Some((1600, 900));
+    config.window.position = Some((-20, 40));
+    config.vsync = VsyncMode::Adaptive;
+    config.fps_limit = Some(144);
+    config.idle_when_unfocused = false;
+    config.msaa_samples = 4;
+    config.camera.move_speed = 12.5;
+    config.camera.invert_y = true;
+    config.recent_files = vec![PathBuf::from("/scenes/b.ron"), PathBuf::from("/scenes/a.ron")];
+    config.start_screen = false;
+    config.backups = 3;
+    config.shader_binaries = false;
+    config.texture_quality = TextureQuality::Medium;
+    config.ui_scale = 1.5;
+    config.accent = Some(egui::Color32::from_rgb(230, 120, 20));
+    config.check_for_updates = true;
+    config.last_seen_version = Some("0.1.0".to_owned());
+    config.usage_log = true;
+    config.snapping.rotation = 5.0;
+    config.palette = PalettePreset::Tritanopia;
+    config.layout.set_area(Panel::Console, DockArea::Floating);
+    config.layout.set_size(DockArea::Left, 250.0);
+
+    config.save(&path).unwrap();
+
+    assert_eq!(EditorConfig::load(&path).unwrap(), config);
+}
+
+#[test]
+fn docked_panels_are_tabs_of_their_area() {
+    let mut layout = DockLayout::default();
+    let left = layout.tabs(DockArea::Left, &Panel::ALL);
+    assert_eq!(left, [Panel::Utilities, Panel::Outliner]);
+    assert_eq!(layout.front(DockArea::Left, &left), Some(Panel::Utilities));
+
+    layout.set_front(DockArea::Left, Panel::Outliner);
+    assert_eq!(layout.front(DockArea::Left, &left), Some(Panel::Outliner));
+    // Closing the tab in front shows the first tab that is still open
+    assert_eq!(layout.front(DockArea::Left, &[Panel::Utilities]), Some(Panel::Utilities));
+    assert_eq!(layout.front(DockArea::Left, &[]), None);
+
+    // Moved panels come to the front of their new area
+    layout.set_area(Panel::Console, DockArea::Left);
+    let left = layout.tabs(DockArea::Left, &Panel::ALL);
+    assert_eq!(left, [Panel::Utilities, Panel::Outliner, Panel::Console]);
+    assert_eq!(layout.front(DockArea::Left, &left), Some(Panel::Console));
+    assert!(!layout.tabs(DockArea::Bottom, &Panel::ALL).contains(&Panel::Console));
+}
+
+#[test]
+fn unknown_dock_areas_are_ignored() {
+    let path = temp_path("dock_layout");
+    fs::write(
+        &path,
+        "[layout]\nconsole = \"top\"\ninspector = \"left\"\nleft_tab = \"inspector\"\n\
+         left_size = 320.0\n",
+    )
+    .unwrap();
+
+    let layout = EditorConfig::load(&path).unwrap().layout;
+    assert_eq!(layout.area(Panel::Console), DockLayout::default().area(Panel::Console));
+    assert_eq!(layout.area(Panel::Inspector), DockArea::Left);
+    let left = layout.tabs(DockArea::Left, &Panel::ALL);
+    assert_eq!(layout.front(DockArea::Left, &left), Some(Panel::Inspector));
+    assert_eq!(layout.size(DockArea::Left), 320.0);
+}
+
+#[test]
+fn ui_scale_is_kept_in_range() {
+    let path = temp_path("ui_scale");
+    fs::write(&path, "[appearance]\nui_scale = 5.0\n").unwrap();
+    assert_eq!(EditorConfig::load(&path).unwrap().ui_scale, 2.0);
+
+    fs::write(&path, "[appearance]\nui_scale = 0.1\n").unwrap();
+    let config = EditorConfig::load(&path).unwrap();
+    assert_eq!(config.ui_scale, 0.75);
+    assert_eq!(config.pixels_per_point(2.0), 1.5);
+}
+
+#[test]
+fn accent_colors_are_hex() {
+    let path = temp_path("accent");
+    fs::write(&path, "[appearance]\naccent = \"#1e90ff\"\n").unwrap();
+    let accent = EditorConfig::load(&path).unwrap().accent;
+    assert_eq!(accent, Some(egui::Color32::from_rgb(0x1e, 0x90, 0xff)));
+
+    fs::write(&path, "[appearance]\naccent = \"blue\"\n").unwrap();
+    assert_eq!(EditorConfig::load(&path).unwrap().accent, None);
+}
+
+#[test]
+fn saving_keeps_other_tables() {
+    let path = temp_path("other_tables");
+    fs::write(&path, "[bindings]\nquit = \"Escape\"\n").unwrap();
+
+    EditorConfig::default().save(&path).unwrap();
+
+    let source = fs::read_to_string(&path).unwrap();
+    assert!(source.contains("[bindings]\nquit = \"Escape\""), "{source}");
+    assert!(source.contains("[window]"), "{source}");
+}
+
+#[test]
+fn recent_files_are_unique_and_limited() {
+    let mut config = EditorConfig::default();
+    for i in 0..MAX_RECENT_FILES + 2 {
+        config.add_recent_file(Path::new(&format!("/missing/{i}.ron")));
+    }
+    config.add_recent_file(Path::new("/missing/5.ron"));
+
+    assert_eq!(config.recent_files.len(), MAX_RECENT_FILES);
+    assert_eq!(config.recent_files[0], PathBuf::from("/missing/5.ron"));
+    assert_eq!(config.recent_files[1], PathBuf::from("/missing/11.ron"));
+    assert_eq!(config.recent_files.iter().filter(|f| f.ends_with("5.ron")).count(), 1);
+}
+
+#[test]
+fn missing_recent_files_are_removed() {
+    let path = temp_path("missing_recent");
+    let scene = path.with_file_name("scene.ron");
+    fs::write(&scene, "").unwrap();
+    let mut config = EditorConfig::default();
+    config.add_recent_file(&scene);
+    config.add_recent_file(&path.with_file_name("deleted.ron"));
+
+    config.remove_missing_recent_files();
+
+    assert_eq!(config.recent_files, [fs::canonicalize(&scene).unwrap()]);
+}
+
+#[test]
+fn vsync_can_be_a_boolean_or_a_mode() {
+    let path = temp_path("vsync");
+    fs::write(&path, "[window]\nvsync = false\n").unwrap();
+    assert_eq!(EditorConfig::load(&path).unwrap().vsync, VsyncMode::Off);
+
+    fs::write(&path, "[window]\nvsync = \"adaptive\"\nfps_limit = 1000\n").unwrap();
+    let config = EditorConfig::load(&path).unwrap();
+    assert_eq!(config.vsync, VsyncMode::Adaptive);
+    assert_eq!(config.fps_limit, Some(480));
+
+    fs::write(&path, "[window]\nvsync = \"sometimes\"\n").unwrap();
+    assert_eq!(EditorConfig::load(&path).unwrap().vsync, VsyncMode::On);
+}
+
+#[test]
+fn only_supported_msaa_samples_are_loaded() {
+    let path = temp_path("msaa");
+    fs::write(&path, "[rendering]\nmsaa_samples = 8\n").unwrap();
+    assert_eq!(EditorConfig::load(&path).unwrap().msaa_samples, 8);
+
+    fs::write(&path, "[rendering]\nmsaa_samples = 3\n").unwrap();
+    assert_eq!(EditorConfig::load(&path).unwrap().msaa_samples, 1);
+}
+
+#[test]
+fn settings_are_moved_from_the_old_settings_file_once() {
+    let path = temp_path("legacy_settings");
+    let legacy = path.with_file_name("settings.toml");
+    fs::write(
+        &legacy,
+        "[updates]\ncheck_for_updates = true\n\n[editing]\nsnap_increment = 0.5\n\n\
+         [appearance]\npalette = \"protanopia\"\n\n[layout]\nconsole = \"floating\"\n",
+    )
+    .unwrap();
+    fs::write(&path, "[appearance]\ntheme = \"light\"\n").unwrap();
+
+    let mut config = EditorConfig::load_or_migrate(&path, &legacy);
+    assert!(config.check_for_updates);
+    assert_eq!(config.snapping.position, 0.5);
+    assert_eq!(config.palette, PalettePreset::Protanopia);
+    assert_eq!(config.layout.area(Panel::Console), DockArea::Floating);
+    assert_eq!(config.theme.name(), "light");
+
+    // Once the configuration has the settings, the old file is not read again
+    config.check_for_updates = false;
+    config.save(&path).unwrap();
+    assert!(!EditorConfig::load_or_migrate(&path, &legacy).check_for_updates);
+}
@@ -0,0 +1,136 @@
+#![cfg(feature = "editor")]
+
+use bevy_ecs::prelude::*;
+use scene_editor::components::Position;
+use scene_editor::console::{self, ConsoleCommand, ConsoleLog, LogLine, CONSOLE_TARGET};
+use scene_editor::resources::{Lighting, ModelLoader};
+use tracing::Level;
+use tracing_subscriber::layer::SubscriberExt;
+
+fn messages(log: &ConsoleLog) -> Vec<String> {
+    log.lines().into_iter().map(|line| line.message).collect()
+}
+
+#[test]
+fn commands_are_parsed_from_words() {
+    assert_eq!(
+        ConsoleCommand::parse("spawn cube 0 1 0"),
+        Ok(ConsoleCommand::Spawn {
+            model: "cube".to_owned(),
+            position: Position::new(0.0, 1.0, 0.0),
+        })
+    );
+    assert_eq!(
+        ConsoleCommand::parse("  spawn   sphere "),
+        Ok(ConsoleCommand::Spawn { model: "sphere".to_owned(), position: Position::default() })
+    );
+    assert_eq!(ConsoleCommand::parse("despawn 12"), Ok(ConsoleCommand::Despawn(12)));
+    assert_eq!(
+        ConsoleCommand::parse("set camera.speed 20"),
+        Ok(ConsoleCommand::Set { name: "camera.speed".to_owned(), value: 20.0 })
+    );
+
+    assert!(ConsoleCommand::parse("despawn twelve").unwrap_err().contains("not a number"));
+    assert!(ConsoleCommand::parse("spawn cube 0 1").unwrap_err().contains("wrong arguments"));
+    assert!(ConsoleCommand::parse("teleport 0 0 0").unwrap_err().contains("unknown command"));
+}
+
+#[test]
+fn lines_echo_the_command_and_its_result() {
+    let mut world = World::new();
+    world.insert_resource(Lighting::default());
+    world.init_resource::<ConsoleLog>();
+
+    console::run_line(&mut world, "set lighting.exposure 2.5");
+    console::run_line(&mut world, "get lighting.exposure");
+    console::run_line(&mut world, "set sun.size 2");
+
+    assert_eq!(world.resource::<Lighting>().exposure, 2.5);
+    let log = world.resource::<ConsoleLog>();
+    assert_eq!(
+        messages(log),
+        [
+            "> set lighting.exposure 2.5",
+            "lighting.exposure = 2.5",
+            "> get lighting.exposure",
+            "lighting.exposure = 2.5",
+            "> set sun.size 2",
+            "there is no setting \"sun.size\"",
+        ]
+    );
+    let levels: Vec<_> = log.lines().into_iter().map(|line| line.level).collect();
+    assert_eq!(levels.last(), Some(&Level::WARN));
+    assert!(log.lines().iter().all(|line| line.target == CONSOLE_TARGET));
+}
+
+#[test]
+fn unknown_models_and_entities_are_reported() {
+    let mut world = World::new();
+    world.init_resource::<ModelLoader>();
+    world.init_resource::<ConsoleLog>();
+    let empty = world.spawn_empty().id();
+
+    console::run_line(&mut world, "spawn teapot");
+    console::run_line(&mut world, &format!("despawn {}", empty.index()));
+
+    assert_eq!(
+        messages(world.resource::<ConsoleLog>()),
+        [
+            "> spawn teapot".to_owned(),
+            "no model is called \"teapot\"".to_owned(),
+            format!("> despawn {}", empty.index()),
+            format!("there is no entity {}", empty.index()),
+        ]
+    );
+    assert!(world.get_entity(empty).is_some());
+}
+
+#[test]
+fn the_layer_keeps_log_events_with_their_fields() {
+    let log = ConsoleLog::default();
+    let subscriber = tracing_subscriber::registry().with(log.layer());
+    tracing::subscriber::with_default(subscriber, || {
+        let path = "res/models/cube.obj";
+        tracing::warn!(target: "scene_editor::resources", path, "could not load {}", "cube");
+    });
+
+    let lines = log.lines();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].level, Level::WARN);
+    assert_eq!(lines[0].target, "scene_editor::resources");
+    assert_eq!(lines[0].message, "could not load cube path=\"res/models/cube.obj\"");
+}
+
+#[test]
+fn shown_lines_are_copied_as_text() {
+    let log = ConsoleLog::default();
+    log.reply(Level::INFO, "spawned entity 3");
+    log.push(LogLine {
+        level: Level::WARN,
+        target: "scene_editor::resources".to_owned(),
+        message: "could not load cube".to_owned(),
+    });
+    log.push(LogLine {
+        level: Level::DEBUG,
+        target: "scene_editor::renderer".to_owned(),
+        message: "compiled variant".to_owned(),
+    });
+
+    assert_eq!(
+        log.to_text(false),
+        "INFO spawned entity 3\nWARN scene_editor::resources: could not load cube\n"
+    );
+    assert!(log.to_text(true).ends_with("DEBUG scene_editor::renderer: compiled variant\n"));
+    assert_eq!(log.shown_lines(false).len(), 2);
+}
+
+#[test]
+fn clear_empties_the_log() {
+    let mut world = World::new();
+    world.init_resource::<ConsoleLog>();
+    console::run_line(&mut world, "help");
+    assert!(!world.resource::<ConsoleLog>().lines().is_empty());
+
+    console::run_line(&mut world, "clear");
+    assert!(world.resource::<ConsoleLog>().lines().is_empty());
+}
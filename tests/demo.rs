@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use scene_editor::demo::{self, DemoContent};
+use scene_editor::golden::Image;
+use scene_editor::material::Material;
+use scene_editor::scene::Scene;
+use scene_editor::shader_limits::{check_limits, ShaderLimits};
+use scene_editor::texture_import::{Filter, TextureSettings};
+
+use crate::common::temp_dir;
+
+mod common;
+
+fn generate(name: &str) -> DemoContent {
+    demo::generate(&temp_dir("demo", name)).unwrap()
+}
+
+/// Names of the files in a folder with an extension, without it
+fn names(dir: &PathBuf, extension: &str) -> BTreeSet<String> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == extension))
+        .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn the_scene_only_uses_generated_assets() {
+    let demo = generate("assets");
+    let scene = Scene::load(&demo.scene).unwrap();
+    assert!(scene.views.contains_key(&1));
+    assert!(scene.entities.iter().any(|e| e.point_light.is_some()));
+
+    let mut models = BTreeSet::new();
+    for path in fs::read_dir(&demo.models_dir).unwrap() {
+        let (loaded, _) = tobj::load_obj(path.unwrap().path(), &tobj::GPU_LOAD_OPTIONS).unwrap();
+        for model in loaded {
+            assert!(!model.mesh.indices.is_empty(), "{} has no triangles", model.name);
+            assert_eq!(model.mesh.normals.len(), model.mesh.positions.len());
+            models.insert(model.name);
+        }
+    }
+    let textures = names(&demo.textures_dir, "png");
+    let materials = names(&demo.materials_dir, "material");
+
+    for entity in &scene.entities {
+        assert!(models.contains(&entity.model), "{} is not generated", entity.model);
+        for texture in [&entity.diffuse, &entity.specular, &entity.normal].into_iter().flatten() {
+            assert!(textures.contains(texture), "{texture} is not generated");
+        }
+        if let Some(material) = &entity.material {
+            assert!(materials.contains(material), "{material} is not generated");
+        }
+    }
+    for name in &materials {
+        let material = Material::load(demo.materials_dir.join(format!("{name}.material"))).unwrap();
+        for texture in [material.diffuse, material.specular, material.normal].into_iter().flatten()
+        {
+            assert!(textures.contains(&texture), "{texture} is not generated");
+        }
+    }
+}
+
+#[test]
+fn textures_and_their_settings_can_be_read() {
+    let demo = generate("textures");
+    for name in names(&demo.textures_dir, "png") {
+        let image = Image::load(&demo.textures_dir.join(format!("{name}.png"))).unwrap();
+        assert_eq!((image.width, image.height), (256, 256), "{name}");
+    }
+    let settings = TextureSettings::load_for(&demo.textures_dir.join("tiles.png")).unwrap();
+    assert_eq!(settings.filter, Filter::Trilinear);
+}
+
+#[test]
+fn the_custom_shader_is_within_the_limits() {
+    let demo = generate("shader");
+    let glow = Material::load(demo.materials_dir.join("glow.material")).unwrap();
+    let shader = glow.custom_shader.unwrap();
+    for source in [&shader.vert, &shader.frag] {
+        assert_eq!(check_limits(source, &ShaderLimits::default()), []);
+    }
+}
+
+#[test]
+fn generating_again_gives_the_same_content() {
+    let (first, second) = (generate("first"), generate("second"));
+    assert_eq!(fs::read(&first.scene).unwrap(), fs::read(&second.scene).unwrap());
+    for (a, b) in
+        [(&first.textures_dir, &second.textures_dir), (&first.models_dir, &second.models_dir)]
+    {
+        for entry in fs::read_dir(a).unwrap() {
+            let path = entry.unwrap().path();
+            let other = b.join(path.file_name().unwrap());
+            assert_eq!(fs::read(&path).unwrap(), fs::read(&other).unwrap(), "{}", path.display());
+        }
+    }
+    assert_eq!(first.args()[1..3], ["--models".to_owned(), first.models_dir.display().to_string()]);
+}
@@ -0,0 +1,79 @@
+use bevy_ecs::prelude::*;
+use scene_editor::commands;
+use scene_editor::components::{Mesh, Position, Selected, TransformBundle};
+use scene_editor::entity_pool::{self, EntityPool, Pooled};
+
+use crate::common::mesh;
+
+mod common;
+
+fn at(x: f32) -> TransformBundle {
+    TransformBundle { position: Position::new(x, 0.0, 0.0), ..Default::default() }
+}
+
+#[test]
+fn released_entities_are_reused() {
+    let mut world = World::new();
+    let first = entity_pool::spawn(&mut world, mesh(), at(1.0));
+    entity_pool::release(first, &mut world);
+    assert_eq!(world.resource::<EntityPool>().len(), 1);
+    assert!(world.entity(first).contains::<Pooled>());
+
+    let second = entity_pool::spawn(&mut world, mesh(), at(2.0));
+    assert_eq!(second, first);
+    assert!(!world.entity(second).contains::<Pooled>());
+    assert_eq!(world.entity(second).get::<Position>().unwrap().x, 2.0);
+    assert!(world.resource::<EntityPool>().is_empty());
+}
+
+#[test]
+fn reserved_entities_are_hidden_until_spawned() {
+    let mut world = World::new();
+    entity_pool::reserve(&mut world, mesh(), 100);
+    let visible = |world: &mut World| {
+        world.query_filtered::<(), (With<Mesh>, Without<Pooled>)>().iter(world).count()
+    };
+    assert_eq!(visible(&mut world), 0);
+    assert_eq!(world.entities().len(), 100);
+
+    for i in 0..10 {
+        entity_pool::spawn(&mut world, mesh(), at(i as f32));
+    }
+    assert_eq!(visible(&mut world), 10);
+    assert_eq!(world.entities().len(), 100);
+    assert_eq!(world.resource::<EntityPool>().len(), 90);
+}
+
+#[test]
+fn entities_with_other_components_are_despawned() {
+    let mut world = World::new();
+    let entity = entity_pool::spawn(&mut world, mesh(), at(0.0));
+    world.entity_mut(entity).insert(Selected);
+    entity_pool::release(entity, &mut world);
+    assert!(world.get_entity(entity).is_none());
+    assert!(world.resource::<EntityPool>().is_empty());
+}
+
+#[test]
+fn pooled_entities_despawned_elsewhere_are_skipped() {
+    let mut world = World::new();
+    entity_pool::reserve(&mut world, mesh(), 2);
+    let pooled: Vec<_> = world.query_filtered::<Entity, With<Pooled>>().iter(&world).collect();
+    world.despawn(pooled[0]);
+
+    let spawned = entity_pool::spawn(&mut world, mesh(), at(0.0));
+    assert_eq!(spawned, pooled[1]);
+    let spawned = entity_pool::spawn(&mut world, mesh(), at(0.0));
+    assert!(!pooled.contains(&spawned));
+}
+
+#[test]
+fn a_new_scene_empties_the_pool() {
+    let mut world = World::new();
+    entity_pool::reserve(&mut world, mesh(), 3);
+    entity_pool::spawn(&mut world, mesh(), at(0.0));
+
+    commands::new_scene(&mut world);
+    assert!(world.resource::<EntityPool>().is_empty());
+    assert_eq!(world.entities().len(), 0);
+}
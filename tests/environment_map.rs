@@ -0,0 +1,47 @@
+use nalgebra_glm as glm;
+use scene_editor::environment_map::{equirect_uv, EnvironmentMap};
+
+use crate::common::{close, temp_dir};
+
+mod common;
+
+/// A 4x2 HDRI with a bright sky in the top row and the column index in green
+fn write_hdri(name: &str) -> std::path::PathBuf {
+    let path = temp_dir("environment_map", name).join("sky.exr");
+    exr::prelude::write_rgba_file(&path, 4, 2, |x, y| {
+        let sky = if y == 0 { 8.0 } else { 0.5 };
+        (sky, x as f32, 0.0, 1.0)
+    })
+    .unwrap();
+    path
+}
+
+#[test]
+fn loads_colors_brighter_than_white() {
+    let map = EnvironmentMap::load(&write_hdri("load")).unwrap();
+    assert_eq!((map.width, map.height), (4, 2));
+    assert_eq!(map.pixels.len(), 4 * 2 * 3);
+    assert_eq!(map.pixel(3, 0), glm::vec3(8.0, 3.0, 0.0));
+    assert_eq!(map.pixel(1, 1), glm::vec3(0.5, 1.0, 0.0));
+}
+
+#[test]
+fn missing_files_are_errors() {
+    let dir = temp_dir("environment_map", "missing");
+    assert!(EnvironmentMap::load(&dir.join("missing.exr")).is_err());
+}
+
+#[test]
+fn directions_wrap_around_the_image() {
+    assert!(close(equirect_uv(&glm::vec3(0.0, 0.0, -1.0)), glm::vec2(0.5, 0.5)));
+    assert!(close(equirect_uv(&glm::vec3(1.0, 0.0, 0.0)), glm::vec2(0.75, 0.5)));
+    assert!(close(equirect_uv(&glm::vec3(-1.0, 0.0, 0.0)), glm::vec2(0.25, 0.5)));
+    assert!(close(equirect_uv(&glm::vec3(0.0, 2.0, 0.0)), glm::vec2(0.5, 0.0)));
+    assert!(equirect_uv(&glm::vec3(0.0, -1.0, 0.0)).y > 0.999);
+
+    let map = EnvironmentMap::load(&write_hdri("sample")).unwrap();
+    assert_eq!(map.sample(&glm::vec3(0.0, 1.0, -0.1)).x, 8.0);
+    assert_eq!(map.sample(&glm::vec3(0.0, -1.0, -0.1)).x, 0.5);
+    assert_eq!(map.sample(&glm::vec3(0.1, -0.1, -1.0)).y, 2.0);
+    assert_eq!(map.sample(&glm::vec3(1.0, 0.1, 0.0)).y, 3.0);
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use scene_editor::components::{Position, Rotation, Scale};
+use scene_editor::renderer::{interpolated_model_matrix, model_matrix};
+use scene_editor::resources::{FixedTime, Time};
+
+use crate::common::assert_close;
+
+mod common;
+
+#[test]
+fn steps_are_taken_for_whole_steps_of_accumulated_time() {
+    let mut fixed_time = FixedTime::new(Duration::from_millis(10));
+    fixed_time.accumulate(Duration::from_millis(25));
+
+    assert!(fixed_time.expend());
+    assert!(fixed_time.expend());
+    assert!(!fixed_time.expend());
+    assert!((fixed_time.overstep_fraction() - 0.5).abs() < 1e-4);
+
+    // The time left over carries on to the next frame
+    fixed_time.accumulate(Duration::from_millis(5));
+    assert!(fixed_time.expend());
+    assert!(!fixed_time.expend());
+}
+
+#[test]
+fn a_slow_frame_takes_a_limited_number_of_steps() {
+    let mut fixed_time = FixedTime::new(Duration::from_millis(10));
+    fixed_time.accumulate(Duration::from_secs(5));
+
+    let mut steps = 0;
+    while fixed_time.expend() {
+        steps += 1;
+    }
+    assert_eq!(steps, FixedTime::MAX_STEPS);
+}
+
+#[test]
+fn paused_time_is_left_out() {
+    let mut time = Time::new();
+    std::thread::sleep(Duration::from_millis(50));
+    time.skip(Duration::from_millis(50));
+    time.next_frame();
+
+    assert!(time.delta() < Duration::from_millis(40), "{:?}", time.delta());
+    assert!(time.elapsed_seconds() < 0.04, "{}", time.elapsed_seconds());
+}
+
+#[test]
+fn interpolation_blends_between_the_last_two_steps() {
+    let from = (Position::new(0.0, 0.0, 0.0), Rotation::default(), Scale::default());
+    let to =
+        (Position::new(2.0, 4.0, 0.0), Rotation::new(0.0, 90.0, 0.0), Scale::new(3.0, 1.0, 1.0));
+
+    assert_close(interpolated_model_matrix(from, to, 0.0), model_matrix(from.0, from.1, from.2));
+    assert_close(interpolated_model_matrix(from, to, 1.0), model_matrix(to.0, to.1, to.2));
+    let (position, rotation) = (Position::new(1.0, 2.0, 0.0), Rotation::new(0.0, 45.0, 0.0));
+    let halfway = model_matrix(position, rotation, Scale::new(2.0, 1.0, 1.0));
+    assert_close(interpolated_model_matrix(from, to, 0.5), halfway);
+}
+
+#[test]
+fn rotations_are_blended_the_short_way_around() {
+    let from = (Position::default(), Rotation::new(0.0, 350.0, 0.0), Scale::default());
+    let to = (Position::default(), Rotation::new(0.0, 10.0, 0.0), Scale::default());
+
+    let halfway = model_matrix(Position::default(), Rotation::default(), Scale::default());
+    assert_close(interpolated_model_matrix(from, to, 0.5), halfway);
+}
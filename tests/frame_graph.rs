@@ -0,0 +1,136 @@
+use scene_editor::frame_graph::{
+    DepthOutput, FrameGraph, FrameGraphBuilder, PassDesc, PassPosition, TextureDesc,
+};
+
+fn color() -> TextureDesc {
+    TextureDesc::color(glow::RGBA8, glow::RGBA, glow::UNSIGNED_BYTE)
+}
+
+/// Shadows, a G-buffer, lighting and one post effect, as the built-in frame graph has
+fn deferred() -> FrameGraphBuilder {
+    FrameGraph::builder()
+        .texture("shadow_map", TextureDesc::shadow_map(1024, 1024))
+        .texture("g_normal", color())
+        .texture("lit", color())
+        .texture("scene", color())
+        .pass(PassDesc::new("shadow").depth(DepthOutput::Texture("shadow_map")))
+        .pass(PassDesc::new("geometry").writes(&["g_normal"]).depth(DepthOutput::Renderbuffer))
+        .pass(PassDesc::new("lighting").reads(&["g_normal", "shadow_map"]).writes(&["lit"]))
+        .pass(PassDesc::new("post").reads(&["lit"]).writes(&["scene"]))
+}
+
+#[test]
+fn inserted_passes_run_right_after_their_pass() {
+    let builder = deferred().texture("outlined", color()).insert(
+        PassDesc::new("outline").reads(&["g_normal"]).writes(&["outlined"]),
+        PassPosition::After("geometry"),
+    );
+    assert_eq!(
+        builder.pass_order().unwrap(),
+        ["shadow", "geometry", "outline", "lighting", "post"]
+    );
+}
+
+#[test]
+fn inserted_passes_run_right_before_their_pass() {
+    let builder = deferred().insert(PassDesc::new("clear"), PassPosition::Before("lighting"));
+    assert_eq!(builder.pass_order().unwrap(), ["shadow", "geometry", "clear", "lighting", "post"]);
+
+    // Even a pass that reads nothing is kept after what it is inserted after
+    let builder = deferred().insert(PassDesc::new("overlay"), PassPosition::After("post"));
+    assert_eq!(
+        builder.pass_order().unwrap(),
+        ["shadow", "geometry", "lighting", "post", "overlay"]
+    );
+}
+
+#[test]
+fn inserted_passes_still_wait_for_what_they_read() {
+    // The lit color it reads is only written after the geometry pass, so it can not run before it
+    let builder = deferred().texture("bloom", color()).insert(
+        PassDesc::new("bloom").reads(&["lit"]).writes(&["bloom"]),
+        PassPosition::Before("geometry"),
+    );
+    assert!(builder.pass_order().is_err());
+
+    let builder = deferred().texture("bloom", color()).insert(
+        PassDesc::new("bloom").reads(&["lit"]).writes(&["bloom"]),
+        PassPosition::Before("post"),
+    );
+    assert_eq!(builder.pass_order().unwrap(), ["shadow", "geometry", "lighting", "bloom", "post"]);
+}
+
+#[test]
+fn passes_inserted_next_to_the_same_pass_keep_their_order() {
+    let builder = deferred()
+        .insert(PassDesc::new("first"), PassPosition::After("lighting"))
+        .insert(PassDesc::new("second"), PassPosition::After("first"));
+    assert_eq!(
+        builder.pass_order().unwrap(),
+        ["shadow", "geometry", "lighting", "first", "second", "post"]
+    );
+}
+
+#[test]
+fn passes_can_not_be_inserted_next_to_unknown_passes() {
+    let builder = deferred().insert(PassDesc::new("ssao"), PassPosition::After("missing"));
+    let error = builder.pass_order().unwrap_err();
+    assert!(error.to_string().contains("missing"), "{error}");
+}
+
+#[test]
+fn passes_are_sorted_so_textures_are_written_before_they_are_read() {
+    let builder = FrameGraph::builder()
+        .texture("shadow_map", TextureDesc::shadow_map(1024, 1024))
+        .texture("g_normal", color())
+        .texture("lit", color())
+        .texture("scene", color())
+        .pass(PassDesc::new("post").reads(&["lit"]).writes(&["scene"]))
+        .pass(PassDesc::new("lighting").reads(&["g_normal", "shadow_map"]).writes(&["lit"]))
+        .pass(PassDesc::new("geometry").writes(&["g_normal"]).depth(DepthOutput::Renderbuffer))
+        .pass(PassDesc::new("shadow").depth(DepthOutput::Texture("shadow_map")));
+    assert_eq!(builder.pass_order().unwrap(), ["geometry", "shadow", "lighting", "post"]);
+}
+
+#[test]
+fn independent_passes_keep_the_order_they_are_added_in() {
+    assert_eq!(deferred().pass_order().unwrap(), ["shadow", "geometry", "lighting", "post"]);
+
+    let builder = FrameGraph::builder()
+        .texture("a", color())
+        .texture("b", color())
+        .pass(PassDesc::new("second").writes(&["b"]))
+        .pass(PassDesc::new("first").writes(&["a"]));
+    assert_eq!(builder.pass_order().unwrap(), ["second", "first"]);
+}
+
+#[test]
+fn textures_must_be_written_by_exactly_one_pass() {
+    let unwritten =
+        deferred().texture("bloom", color()).pass(PassDesc::new("glow").reads(&["bloom"]));
+    let error = unwritten.pass_order().unwrap_err();
+    assert!(error.to_string().contains("bloom"), "{error}");
+
+    let unknown = deferred().pass(PassDesc::new("glow").writes(&["bloom"]));
+    assert!(unknown.pass_order().is_err());
+
+    let twice = deferred().pass(PassDesc::new("overlay").writes(&["scene"]));
+    assert!(twice.pass_order().is_err());
+}
+
+#[test]
+fn cycles_and_wrong_attachments_are_errors() {
+    let cycle = FrameGraph::builder()
+        .texture("a", color())
+        .texture("b", color())
+        .pass(PassDesc::new("ping").reads(&["b"]).writes(&["a"]))
+        .pass(PassDesc::new("pong").reads(&["a"]).writes(&["b"]));
+    let error = cycle.pass_order().unwrap_err();
+    assert!(error.to_string().contains("cycle"), "{error}");
+
+    // A shadow map is a depth texture, so it can not be written as a color output
+    let builder = FrameGraph::builder()
+        .texture("shadow_map", TextureDesc::shadow_map(1024, 1024))
+        .pass(PassDesc::new("shadow").writes(&["shadow_map"]));
+    assert!(builder.pass_order().is_err());
+}
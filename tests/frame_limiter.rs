@@ -0,0 +1,50 @@
+#![cfg(feature = "editor")]
+
+use std::time::Duration;
+
+use scene_editor::frame_limiter::{self, FrameLimiter, VsyncMode, UNFOCUSED_FPS};
+
+#[test]
+fn frame_rate_limits_and_idling() {
+    assert_eq!(frame_limiter::frame_interval(None, false), None);
+    assert_eq!(frame_limiter::frame_interval(Some(50), false), Some(Duration::from_millis(20)));
+
+    let idle = Duration::from_secs(1) / UNFOCUSED_FPS;
+    assert_eq!(frame_limiter::frame_interval(None, true), Some(idle));
+    assert_eq!(frame_limiter::frame_interval(Some(144), true), Some(idle));
+    // A limit below the idle frame rate is kept
+    assert_eq!(frame_limiter::frame_interval(Some(5), true), Some(Duration::from_millis(200)));
+}
+
+#[test]
+fn adaptive_vsync_skips_sync_for_slow_frames() {
+    let refresh = Some(Duration::from_micros(16_667));
+    let fast = Duration::from_millis(5);
+    let slow = Duration::from_millis(25);
+
+    assert!(VsyncMode::On.wait_for_sync(slow, refresh));
+    assert!(!VsyncMode::Off.wait_for_sync(fast, refresh));
+    assert!(VsyncMode::Adaptive.wait_for_sync(fast, refresh));
+    assert!(!VsyncMode::Adaptive.wait_for_sync(slow, refresh));
+    assert!(VsyncMode::Adaptive.wait_for_sync(slow, None));
+}
+
+#[test]
+fn swap_interval_is_only_set_when_it_changes() {
+    let mut limiter = FrameLimiter::new(None);
+
+    assert_eq!(limiter.update_vsync(VsyncMode::On, None), Some(true));
+    assert_eq!(limiter.update_vsync(VsyncMode::On, None), None);
+    assert_eq!(limiter.update_vsync(VsyncMode::Off, None), Some(false));
+    assert_eq!(limiter.update_vsync(VsyncMode::Off, None), None);
+}
+
+#[test]
+fn command_line_vsync_applies_until_the_configuration_changes() {
+    let mut limiter = FrameLimiter::new(Some(VsyncMode::Off));
+
+    assert_eq!(limiter.vsync(VsyncMode::On), VsyncMode::Off);
+    assert_eq!(limiter.vsync(VsyncMode::On), VsyncMode::Off);
+    assert_eq!(limiter.vsync(VsyncMode::Adaptive), VsyncMode::Adaptive);
+    assert_eq!(limiter.vsync(VsyncMode::On), VsyncMode::On);
+}
@@ -0,0 +1,57 @@
+use scene_editor::glsl::{tokenize, TokenKind};
+
+/// The text and kind of each token that is not whitespace or punctuation
+fn significant(source: &str) -> Vec<(&str, TokenKind)> {
+    tokenize(source)
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Other)
+        .map(|t| (&source[t.range], t.kind))
+        .collect()
+}
+
+#[test]
+fn tokens_cover_the_whole_source() {
+    let source =
+        "#version 330 core\nuniform vec4 user_params; // ✓\n/* block\ncomment */ void main() {}\n";
+    let tokens = tokenize(source);
+    assert_eq!(tokens.first().unwrap().range.start, 0);
+    assert_eq!(tokens.last().unwrap().range.end, source.len());
+    assert!(tokens.windows(2).all(|w| w[0].range.end == w[1].range.start));
+}
+
+#[test]
+fn glsl_is_classified() {
+    let source = "#define SCALE 2.0\nout vec4 color;\nvoid main() {\n    \
+                  color = texture(tx, uv) * 1.5e-2; // tint\n    gl_Position = vec4(0.0);\n}";
+    assert_eq!(
+        significant(source),
+        vec![
+            ("#define SCALE 2.0", TokenKind::Preprocessor),
+            ("out", TokenKind::Keyword),
+            ("vec4", TokenKind::Type),
+            ("color", TokenKind::Identifier),
+            ("void", TokenKind::Type),
+            ("main", TokenKind::Identifier),
+            ("color", TokenKind::Identifier),
+            ("texture", TokenKind::Builtin),
+            ("tx", TokenKind::Identifier),
+            ("uv", TokenKind::Identifier),
+            ("1.5e-2", TokenKind::Number),
+            ("// tint", TokenKind::Comment),
+            ("gl_Position", TokenKind::Builtin),
+            ("vec4", TokenKind::Type),
+            ("0.0", TokenKind::Number),
+        ]
+    );
+}
+
+#[test]
+fn hash_inside_a_line_is_not_a_directive() {
+    let source = "float x = 1.0; #pragma\n  #ifdef SKINNED\n";
+    let directives: Vec<_> = significant(source)
+        .into_iter()
+        .filter(|(_, kind)| *kind == TokenKind::Preprocessor)
+        .map(|(text, _)| text)
+        .collect();
+    assert_eq!(directives, vec!["#ifdef SKINNED"]);
+}
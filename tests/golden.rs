@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::Path;
+
+use scene_editor::golden::{self, Case, CaseResult, Image, Outcome, Tolerance};
+
+use crate::common::temp_dir;
+
+mod common;
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> Image {
+    Image::new(width, height, color.repeat((width * height) as usize))
+}
+
+fn case(dir: &Path, name: &str) -> Case {
+    Case {
+        name: name.to_owned(),
+        scene: dir.join(format!("{name}.ron")),
+        reference: dir.join(format!("{name}.png")),
+    }
+}
+
+/// Renders every case in tests/golden and compares it with its approved image
+///
+/// Needs an EGL device to render with, so it only runs with `cargo test -- --ignored`. Run
+/// `scene-editor --golden --approve` to accept intended changes to the renders.
+#[cfg(feature = "editor")]
+#[test]
+#[ignore = "needs a GPU to render with"]
+fn renders_match_the_golden_images() {
+    use scene_editor::{GoldenOptions, HeadlessRenderer};
+
+    let renderer = HeadlessRenderer::new().unwrap();
+    let options = GoldenOptions::default();
+    let results = renderer.render_golden(&options).unwrap();
+    assert!(!results.is_empty(), "found no cases in {}", options.dir.display());
+    let failed: Vec<_> =
+        results.iter().filter(|r| !r.outcome.is_ok()).map(|r| r.case.name.as_str()).collect();
+    if !failed.is_empty() {
+        let report = golden::write_report(&results, &options.report).unwrap();
+        panic!("golden images differ for {failed:?}, see {}", report.display());
+    }
+}
+
+#[test]
+fn color_delta_is_perceptual() {
+    let black = [0, 0, 0, 255];
+    let white = [255, 255, 255, 255];
+    assert_eq!(golden::color_delta(black, black), 0.0);
+    assert!(golden::color_delta(black, white) > 0.9);
+    // Transparent pixels are blended onto white
+    assert!(golden::color_delta([0, 0, 0, 0], white) < 1e-3);
+    // A change in brightness stands out more than the same change in blue
+    let gray = [128, 128, 128, 255];
+    assert!(
+        golden::color_delta(gray, [148, 148, 148, 255])
+            > golden::color_delta(gray, [128, 128, 148, 255])
+    );
+}
+
+#[test]
+fn small_differences_are_tolerated() {
+    let reference = solid(10, 10, [100, 150, 200, 255]);
+    let mut actual = reference.clone();
+    // Rounding noise on every pixel, as between GPU drivers
+    for value in actual.pixels.iter_mut().step_by(4) {
+        *value += 2;
+    }
+    let tolerance = Tolerance::default();
+    let diff = golden::compare(&reference, &actual, &tolerance).unwrap();
+    assert_eq!(diff.different, 0);
+    assert!(diff.max_delta > 0.0 && diff.passes(&tolerance));
+
+    // One pixel of a hundred is clearly wrong
+    actual.pixels[..4].copy_from_slice(&[255, 0, 0, 255]);
+    let diff = golden::compare(&reference, &actual, &tolerance).unwrap();
+    assert_eq!((diff.different, diff.total), (1, 100));
+    assert!(!diff.passes(&tolerance));
+    assert!(diff.passes(&Tolerance { max_different: 0.01, ..tolerance }));
+    assert_eq!(&diff.image.pixels[..4], &[255, 0, 0, 255]);
+    assert_ne!(&diff.image.pixels[4..8], &[255, 0, 0, 255]);
+}
+
+#[test]
+fn images_of_different_sizes_do_not_compare() {
+    let error = golden::compare(&solid(4, 4, [0; 4]), &solid(4, 3, [0; 4]), &Tolerance::default())
+        .unwrap_err();
+    assert!(error.to_string().contains("4x3"), "{error}");
+}
+
+#[test]
+fn cases_are_scenes_with_a_reference_of_the_same_name() {
+    let dir = temp_dir("golden", "cases");
+    for file in ["b.ron", "a.ron", "a.png", "notes.txt"] {
+        fs::write(dir.join(file), "").unwrap();
+    }
+    let cases = golden::cases(&dir).unwrap();
+    assert_eq!(cases, vec![case(&dir, "a"), case(&dir, "b")]);
+    assert!(golden::cases(&dir.join("missing")).is_err());
+}
+
+#[test]
+fn approving_saves_the_reference() {
+    let dir = temp_dir("golden", "approve");
+    let case = case(&dir, "cube");
+    let tolerance = Tolerance::default();
+    let render = solid(8, 6, [10, 20, 30, 255]);
+
+    let result = golden::check(&case, render.clone(), &tolerance, false);
+    assert_eq!(result.outcome, Outcome::Missing);
+    let result = golden::check(&case, render.clone(), &tolerance, true);
+    assert_eq!(result.outcome, Outcome::Approved);
+    assert_eq!(Image::load(&case.reference).unwrap(), render);
+    let result = golden::check(&case, render, &tolerance, false);
+    assert!(matches!(result.outcome, Outcome::Passed(_)), "{:?}", result.outcome);
+}
+
+#[test]
+fn the_report_shows_failed_cases() {
+    let dir = temp_dir("golden", "report");
+    let tolerance = Tolerance::default();
+    let reference = solid(4, 4, [0, 0, 0, 255]);
+    let passing = case(&dir, "passing");
+    let failing = case(&dir, "cube & sphere");
+    reference.save(&passing.reference).unwrap();
+    reference.save(&failing.reference).unwrap();
+    let results = [
+        golden::check(&passing, reference.clone(), &tolerance, false),
+        golden::check(&failing, solid(4, 4, [255, 255, 255, 255]), &tolerance, false),
+        CaseResult {
+            case: case(&dir, "broken"),
+            outcome: Outcome::Error("no such model".to_owned()),
+            actual: None,
+        },
+    ];
+
+    let report = golden::write_report(&results, &dir.join("report")).unwrap();
+    let html = fs::read_to_string(&report).unwrap();
+    assert!(html.contains("2 of 3 cases failed"), "{html}");
+    assert!(html.contains("<h2>cube &amp; sphere</h2>"), "{html}");
+    assert!(html.contains("no such model"), "{html}");
+    assert!(!html.contains("<h2>passing</h2>"), "{html}");
+    for file in ["reference", "actual", "diff"] {
+        let file = dir.join("report").join(format!("cube & sphere.{file}.png"));
+        assert!(file.exists(), "{}", file.display());
+    }
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "editor")]
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use scene_editor::components::{
+    Group, Mesh, Parent, Position, Rotation, Scale, Selected, TransformBundle,
+};
+use scene_editor::entity_pool::Pooled;
+use scene_editor::scene::{MergeOptions, Scene, SceneGroup};
+use scene_editor::transform::{self, world_matrix_of};
+use scene_editor::{commands, outliner};
+
+use crate::common::{close, mesh};
+
+mod common;
+
+fn origin(world: &World, entity: Entity) -> glm::Vec3 {
+    glm::vec4_to_vec3(&world_matrix_of(world, entity).column(3).into_owned())
+}
+
+fn spawn_at(world: &mut World, x: f32, y: f32, z: f32) -> Entity {
+    let transform = TransformBundle {
+        position: Position::new(x, y, z),
+        rotation: Rotation::new(0.0, 30.0, 0.0),
+        ..Default::default()
+    };
+    world.spawn((mesh(), transform, Selected)).id()
+}
+
+#[test]
+fn decomposing_gives_back_the_transform() {
+    let (position, rotation, scale) =
+        (Position::new(1.0, -2.0, 3.0), Rotation::new(10.0, 20.0, 30.0), Scale::new(2.0, 1.0, 0.5));
+    let model = scene_editor::renderer::model_matrix(position, rotation, scale);
+    let (p, r, s) = transform::decompose(&model);
+    assert!(close(scene_editor::renderer::model_matrix(p, r, s), model));
+}
+
+#[test]
+fn grouping_keeps_entities_in_place() {
+    let mut world = World::new();
+    let a = spawn_at(&mut world, 1.0, 0.0, 0.0);
+    let b = spawn_at(&mut world, 3.0, 2.0, 0.0);
+    let before = [world_matrix_of(&world, a), world_matrix_of(&world, b)];
+
+    commands::group_selected(&mut world);
+    let mut groups = world.query_filtered::<(Entity, &Group), With<Selected>>();
+    let (group, name) = groups.single(&world);
+    assert_eq!(name.name, "Group 1");
+    assert_eq!(origin(&world, group), glm::vec3(2.0, 1.0, 0.0));
+    assert_eq!(world.get::<Parent>(a), Some(&Parent(group)));
+    assert_eq!(world.get::<Parent>(b), Some(&Parent(group)));
+    assert!(world.get::<Selected>(a).is_none());
+    assert!(close(world_matrix_of(&world, a), before[0]));
+    assert!(close(world_matrix_of(&world, b), before[1]));
+
+    // Turning the group turns what is in it around the group
+    *world.get_mut::<Rotation>(group).unwrap() = Rotation::new(0.0, 180.0, 0.0);
+    assert!(glm::distance(&origin(&world, a), &glm::vec3(3.0, 0.0, 0.0)) < 1e-4);
+    *world.get_mut::<Rotation>(group).unwrap() = Rotation::default();
+
+    commands::ungroup_selected(&mut world);
+    assert!(world.get_entity(group).is_none());
+    assert!(world.get::<Parent>(a).is_none());
+    assert!(world.get::<Selected>(a).is_some() && world.get::<Selected>(b).is_some());
+    assert!(close(world_matrix_of(&world, a), before[0]));
+}
+
+#[test]
+fn groups_nest_in_the_group_of_the_selection() {
+    let mut world = World::new();
+    let a = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    let b = spawn_at(&mut world, 2.0, 0.0, 0.0);
+    commands::group_selected(&mut world);
+    let outer = world.query_filtered::<Entity, With<Group>>().single(&world);
+
+    world.entity_mut(outer).remove::<Selected>();
+    world.entity_mut(a).insert(Selected);
+    commands::group_selected(&mut world);
+    let inner = world.query_filtered::<Entity, (With<Group>, With<Selected>)>().single(&world);
+    assert_eq!(world.get::<Group>(inner).unwrap().name, "Group 2");
+    assert_eq!(world.get::<Parent>(inner), Some(&Parent(outer)));
+    assert_eq!(world.get::<Parent>(a), Some(&Parent(inner)));
+    assert_eq!(world.get::<Parent>(b), Some(&Parent(outer)));
+
+    // Groups are listed before what is in them, indented by how deep they are
+    let entries = outliner::entries(&mut world);
+    let listed: Vec<_> = entries.iter().map(|e| (e.entity, e.depth)).collect();
+    assert_eq!(listed, [(outer, 0), (b, 1), (inner, 1), (a, 2)]);
+    assert_eq!(entries[0].name, "Group 1");
+    assert!(entries[0].components.contains(&"Group"));
+
+    // Taking the inner group apart puts its entities in the outer one
+    commands::ungroup_selected(&mut world);
+    assert_eq!(world.get::<Parent>(a), Some(&Parent(outer)));
+}
+
+#[test]
+fn despawning_a_group_keeps_its_members_in_place() {
+    let mut world = World::new();
+    let a = spawn_at(&mut world, 0.0, 0.0, 0.0);
+    let b = spawn_at(&mut world, 2.0, 0.0, 0.0);
+    commands::group_selected(&mut world);
+    let outer = world.query_filtered::<Entity, With<Group>>().single(&world);
+    world.entity_mut(outer).remove::<Selected>();
+    world.entity_mut(a).insert(Selected);
+    commands::group_selected(&mut world);
+    let inner = world.query_filtered::<Entity, (With<Group>, With<Selected>)>().single(&world);
+    *world.get_mut::<Position>(outer).unwrap() = Position::new(0.0, 5.0, 0.0);
+    let before = world_matrix_of(&world, a);
+
+    // The members of a despawned group go into the group it was in
+    commands::despawn_and_destroy(inner, &mut world);
+    assert_eq!(world.get::<Parent>(a), Some(&Parent(outer)));
+    assert!(close(world_matrix_of(&world, a), before));
+
+    commands::despawn_all(&mut world);
+    assert_eq!(world.query::<&Group>().iter(&world).count(), 0);
+    let mut meshes = world.query_filtered::<Entity, (With<Mesh>, Without<Pooled>)>();
+    assert_eq!(meshes.iter(&world).count(), 0);
+    for entity in [a, b] {
+        assert!(world.get::<Parent>(entity).is_none());
+    }
+}
+
+#[test]
+fn scenes_place_entities_relative_to_their_groups() {
+    let scene = Scene::new()
+        .group(SceneGroup {
+            name: "room".to_owned(),
+            position: Position::new(10.0, 0.0, 0.0),
+            ..Default::default()
+        })
+        .group(SceneGroup {
+            name: "shelf".to_owned(),
+            position: Position::new(0.0, 2.0, 0.0),
+            parent: Some(0),
+            ..Default::default()
+        })
+        .spawn_model("book")
+        .at(0.0, 0.0, 1.0)
+        .in_group(1)
+        .finish();
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    assert_eq!(loaded, scene);
+
+    let book = &loaded.entities[0];
+    let origin = glm::vec4_to_vec3(&loaded.world_matrix(book).column(3).into_owned());
+    assert_eq!(origin, glm::vec3(10.0, 2.0, 1.0));
+
+    // Only what is not in a group moves when merged, the rest moves along
+    let mut merged = loaded.clone();
+    let options = MergeOptions { offset: glm::vec3(0.0, 0.0, 5.0), ..Default::default() };
+    merged.prepare_merge(&Default::default(), &Default::default(), &options);
+    assert_eq!(merged.groups[0].position, Position::new(10.0, 0.0, 5.0));
+    assert_eq!(merged.groups[1].position, loaded.groups[1].position);
+    assert_eq!(merged.entities[0].position, book.position);
+}
+
+#[test]
+fn a_cycle_of_groups_does_not_hang() {
+    let group =
+        |parent| SceneGroup { name: "loop".to_owned(), parent: Some(parent), ..Default::default() };
+    let scene =
+        Scene::new().group(group(1)).group(group(0)).spawn_model("cube").in_group(0).finish();
+    let model = scene.world_matrix(&scene.entities[0]);
+    assert_eq!(model, glm::Mat4::identity());
+}
@@ -0,0 +1,51 @@
+use std::fs::File;
+
+use scene_editor::image_export::{save_hdr_image, save_image};
+
+#[test]
+fn png_keeps_pixels_and_row_order() {
+    let path = std::env::temp_dir().join("scene_editor_image_export.png");
+    let pixels = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 10, 20, 30, 40];
+    save_image(&path, 2, 2, &pixels).unwrap();
+
+    let mut reader = png::Decoder::new(File::open(&path).unwrap()).read_info().unwrap();
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut decoded).unwrap();
+    assert_eq!((info.width, info.height), (2, 2));
+    assert_eq!(&decoded[..info.buffer_size()], &pixels);
+}
+
+#[test]
+fn unknown_extension_is_rejected() {
+    let path = std::env::temp_dir().join("scene_editor_image_export.bmp");
+    assert!(save_image(&path, 1, 1, &[0; 4]).is_err());
+}
+
+#[test]
+fn exr_keeps_colors_brighter_than_white() {
+    let path = std::env::temp_dir().join("scene_editor_image_export_hdr.exr");
+    let pixels = [4.5, 0.25, 0.0, 1.0, 0.0, 1.5, 16.0, 1.0];
+    save_hdr_image(&path, 2, 1, &pixels).unwrap();
+
+    let image = exr::prelude::read_first_rgba_layer_from_file(
+        &path,
+        |resolution, _| vec![0.0; resolution.width() * resolution.height() * 4],
+        |decoded: &mut Vec<f32>, position, (r, g, b, a): (f32, f32, f32, f32)| {
+            let i = position.x() * 4;
+            decoded[i..i + 4].copy_from_slice(&[r, g, b, a]);
+        },
+    )
+    .unwrap();
+    assert_eq!(image.layer_data.channel_data.pixels, pixels);
+}
+
+#[test]
+fn hdr_png_clamps_to_eight_bits() {
+    let path = std::env::temp_dir().join("scene_editor_image_export_hdr.png");
+    save_hdr_image(&path, 1, 1, &[4.5, 0.5, -1.0, 1.0]).unwrap();
+
+    let mut reader = png::Decoder::new(File::open(&path).unwrap()).read_info().unwrap();
+    let mut decoded = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut decoded).unwrap();
+    assert_eq!(&decoded[..4], &[255, 128, 0, 255]);
+}
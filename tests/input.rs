@@ -0,0 +1,68 @@
+#![cfg(feature = "editor")]
+
+use scene_editor::resources::{Input, KeyEvent, MouseEvent};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode};
+
+fn key(key: VirtualKeyCode, state: ElementState) -> KeyEvent {
+    KeyEvent { key, state }
+}
+
+#[test]
+fn press_and_release_in_one_frame_is_not_missed() {
+    let mut input = Input::default();
+    input.handle_key_event(key(VirtualKeyCode::E, ElementState::Pressed));
+    input.handle_key_event(key(VirtualKeyCode::E, ElementState::Released));
+
+    assert!(input.get_key_press(VirtualKeyCode::E));
+    assert!(input.get_key_release(VirtualKeyCode::E));
+    assert!(!input.get_key_press_continuous(VirtualKeyCode::E));
+
+    input.update_after_frame();
+    assert!(!input.get_key_press(VirtualKeyCode::E));
+    assert!(!input.get_key_release(VirtualKeyCode::E));
+}
+
+#[test]
+fn repeats_while_held_are_not_new_presses() {
+    let mut input = Input::default();
+    input.handle_key_event(key(VirtualKeyCode::Left, ElementState::Pressed));
+    input.update_after_frame();
+
+    input.handle_key_event(key(VirtualKeyCode::Left, ElementState::Pressed));
+    input.handle_key_event(key(VirtualKeyCode::Left, ElementState::Pressed));
+    assert!(!input.get_key_press(VirtualKeyCode::Left));
+    assert!(input.get_key_repeat(VirtualKeyCode::Left));
+    assert!(input.get_key_press_continuous(VirtualKeyCode::Left));
+
+    input.update_after_frame();
+    assert!(!input.get_key_repeat(VirtualKeyCode::Left));
+    assert!(input.get_key_press_continuous(VirtualKeyCode::Left));
+}
+
+#[test]
+fn mouse_buttons_are_held_until_released() {
+    let mut input = Input::default();
+    let press = MouseEvent { button: MouseButton::Left, state: ElementState::Pressed };
+    input.handle_mouse_event(press);
+    assert!(input.get_mouse_button_press(MouseButton::Left));
+
+    input.update_after_frame();
+    assert!(!input.get_mouse_button_press(MouseButton::Left));
+    assert!(input.get_mouse_button_press_continuous(MouseButton::Left));
+
+    let release = MouseEvent { button: MouseButton::Left, state: ElementState::Released };
+    input.handle_mouse_event(release);
+    assert!(input.get_mouse_button_release(MouseButton::Left));
+    assert!(!input.get_mouse_button_press_continuous(MouseButton::Left));
+}
+
+#[test]
+fn modifiers_are_tracked_like_other_keys() {
+    let mut input = Input::default();
+    input.handle_key_event(key(VirtualKeyCode::RShift, ElementState::Pressed));
+    assert!(input.shift_held());
+    assert!(!input.ctrl_held());
+
+    input.handle_key_event(key(VirtualKeyCode::RShift, ElementState::Released));
+    assert!(!input.shift_held());
+}
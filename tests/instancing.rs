@@ -0,0 +1,44 @@
+use nalgebra_glm as glm;
+use scene_editor::instancing::{geometry_hash, gpu_size, instance_offset};
+use scene_editor::mesh_cleanup::MeshData;
+
+/// A unit triangle with its first corner at `origin`
+fn triangle_at(origin: glm::Vec3) -> MeshData {
+    let positions = [glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0)];
+    let positions = positions.iter().map(|p| p + origin).collect();
+    let texture_coords = vec![glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0)];
+    MeshData::new(positions, vec![glm::Vec3::z(); 3], texture_coords, vec![0, 1, 2])
+}
+
+#[test]
+fn translated_meshes_are_instances() {
+    let model = triangle_at(glm::Vec3::zeros());
+    let moved = triangle_at(glm::vec3(2.0, -3.0, 0.5));
+
+    assert_eq!(geometry_hash(&model), geometry_hash(&moved));
+    assert_eq!(instance_offset(&model, &moved), Some(glm::vec3(2.0, -3.0, 0.5)));
+}
+
+#[test]
+fn different_meshes_are_not_instances() {
+    let model = triangle_at(glm::Vec3::zeros());
+
+    let mut scaled = model.clone();
+    scaled.positions[1].x = 2.0;
+    assert_ne!(geometry_hash(&model), geometry_hash(&scaled));
+    assert_eq!(instance_offset(&model, &scaled), None);
+
+    let mut flipped = model.clone();
+    flipped.indices = vec![0, 2, 1];
+    assert_eq!(instance_offset(&model, &flipped), None);
+
+    let mut remapped = model.clone();
+    remapped.texture_coords[2] = glm::vec2(0.5, 1.0);
+    assert_eq!(instance_offset(&model, &remapped), None);
+}
+
+#[test]
+fn gpu_size_counts_vertices_and_indices() {
+    // Position, normal and texture coordinates are 8 floats per vertex
+    assert_eq!(gpu_size(&triangle_at(glm::Vec3::zeros())), 3 * 32 + 3 * 4);
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "editor")]
+
+use std::fs::File;
+use std::io::Read;
+
+use scene_editor::issue_report::IssueReport;
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Vec<u8> {
+    let mut contents = Vec::new();
+    archive.by_name(name).unwrap().read_to_end(&mut contents).unwrap();
+    contents
+}
+
+#[test]
+fn report_is_saved_with_every_part() {
+    let report = IssueReport {
+        diagnostics: "Renderer: test".to_owned(),
+        render_settings: "Quad view: false".to_owned(),
+        logs: vec!["first".to_owned(), "second".to_owned()],
+        screenshot: Some((2, 1, vec![255, 0, 0, 255, 0, 0, 255, 255])),
+        scene: Some("(entities: [])".to_owned()),
+    };
+    let path = std::env::temp_dir().join("scene_editor_issue_report.zip");
+    report.save(&path).unwrap();
+
+    let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+    let mut names: Vec<_> = archive.file_names().collect();
+    names.sort_unstable();
+    let mut expected = report.file_names();
+    expected.sort_unstable();
+    assert_eq!(names, expected);
+
+    assert_eq!(read_entry(&mut archive, "diagnostics.txt"), b"Renderer: test");
+    assert_eq!(read_entry(&mut archive, "log.txt"), b"first\nsecond\n");
+    assert_eq!(read_entry(&mut archive, "scene.ron"), b"(entities: [])");
+    assert!(read_entry(&mut archive, "screenshot.png").starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn scene_is_left_out_unless_included() {
+    let report = IssueReport { logs: vec!["only line".to_owned()], ..Default::default() };
+    let path = std::env::temp_dir().join("scene_editor_issue_report_without_scene.zip");
+    report.save(&path).unwrap();
+
+    let archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+    assert_eq!(archive.len(), 3);
+    assert!(archive.file_names().all(|name| name != "scene.ron" && name != "screenshot.png"));
+}
@@ -0,0 +1,107 @@
+use nalgebra_glm as glm;
+use scene_editor::light_clusters::{depth_slice, LightClusters, CLUSTERS};
+use scene_editor::resources::{Camera, ProjectionMode};
+
+/// Sort lights into the clusters of a camera at the origin looking along -Z
+fn assign(lights: &[(glm::Vec3, Option<f32>)]) -> LightClusters {
+    let camera = Camera::looking_forward(1600, 900);
+    LightClusters::assign(lights, &camera.view(), &camera.projection, camera.mode)
+}
+
+#[test]
+fn lights_are_only_in_the_clusters_they_reach() {
+    let clusters = assign(&[(glm::vec3(0.0, 0.0, -10.0), Some(2.0))]);
+    let [columns, rows, slices] = CLUSTERS;
+
+    let slice = depth_slice(10.0, ProjectionMode::Perspective);
+    assert_eq!(clusters.lights(columns / 2, rows / 2, slice), [0]);
+    assert!(clusters.lights(0, 0, slice).is_empty());
+    assert!(clusters.lights(columns / 2, rows / 2, 0).is_empty());
+    assert!(clusters.lights(columns / 2, rows / 2, slices - 1).is_empty());
+}
+
+#[test]
+fn lights_out_of_view_are_culled() {
+    let clusters = assign(&[
+        // Behind the camera
+        (glm::vec3(0.0, 0.0, 10.0), Some(2.0)),
+        // Off to the side
+        (glm::vec3(100.0, 0.0, -10.0), Some(2.0)),
+        // Past the far plane
+        (glm::vec3(0.0, 0.0, -400.0), Some(2.0)),
+    ]);
+    assert!(clusters.indices.is_empty());
+}
+
+#[test]
+fn lights_without_a_range_are_in_every_cluster() {
+    let clusters = assign(&[(glm::vec3(0.0, 0.0, 10.0), None)]);
+    let [columns, rows, slices] = CLUSTERS;
+
+    assert_eq!(clusters.clusters.len(), (columns * rows * slices) as usize);
+    assert!(clusters.clusters.iter().all(|&[_, count]| count == 1));
+}
+
+#[test]
+fn lights_around_the_camera_cover_the_whole_view() {
+    let clusters = assign(&[(glm::vec3(0.0, 0.0, -1.0), Some(5.0))]);
+    let [columns, rows, _] = CLUSTERS;
+
+    for row in 0..rows {
+        for column in 0..columns {
+            assert_eq!(clusters.lights(column, row, 0), [0], "{column}, {row}");
+        }
+    }
+}
+
+#[test]
+fn clusters_list_their_lights_in_order() {
+    let lights = [(glm::vec3(0.0, 0.0, -10.0), Some(2.0)), (glm::vec3(0.5, 0.0, -10.0), Some(2.0))];
+    let clusters = assign(&lights);
+    let [columns, rows, _] = CLUSTERS;
+
+    assert_eq!(
+        clusters.lights(columns / 2, rows / 2, depth_slice(10.0, ProjectionMode::Perspective)),
+        [0, 1]
+    );
+}
+
+#[test]
+fn depth_slices_grow_with_distance() {
+    let [_, _, slices] = CLUSTERS;
+    assert_eq!(depth_slice(0.0, ProjectionMode::Perspective), 0);
+    assert_eq!(depth_slice(Camera::NEAR, ProjectionMode::Perspective), 0);
+    assert_eq!(depth_slice(Camera::FAR, ProjectionMode::Perspective), slices - 1);
+    assert_eq!(depth_slice(Camera::FAR * 2.0, ProjectionMode::Perspective), slices - 1);
+
+    let depths = (1..1000).map(|i| i as f32 * 0.5);
+    let slices: Vec<u32> =
+        depths.map(|depth| depth_slice(depth, ProjectionMode::Perspective)).collect();
+    assert!(slices.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn orthographic_depth_slices_are_all_as_deep() {
+    let [_, _, slices] = CLUSTERS;
+    let ortho = ProjectionMode::Orthographic;
+    assert_eq!(depth_slice(0.0, ortho), 0);
+    assert_eq!(depth_slice(Camera::FAR, ortho), slices - 1);
+
+    let depth = (Camera::FAR - Camera::NEAR) / slices as f32;
+    let middle = |slice: u32| Camera::NEAR + depth * (slice as f32 + 0.5);
+    for slice in 0..slices {
+        assert_eq!(depth_slice(middle(slice), ortho), slice);
+    }
+    // Close to the camera perspective slices are much thinner
+    assert!(depth_slice(10.0, ProjectionMode::Perspective) > depth_slice(10.0, ortho) + 5);
+}
+
+#[test]
+fn orthographic_lights_near_the_camera_only_cover_their_clusters() {
+    let camera = Camera::looking_at_origin(1600, 900, -90.0, 0.0, 10.0);
+    let light = (camera.pos + camera.front * 0.5, Some(1.0));
+    let clusters = LightClusters::assign(&[light], &camera.view(), &camera.projection, camera.mode);
+    let [columns, rows, _] = CLUSTERS;
+    assert_eq!(clusters.lights(columns / 2, rows / 2, 0), [0]);
+    assert!(clusters.lights(0, 0, 0).is_empty());
+}
@@ -0,0 +1,61 @@
+use nalgebra_glm as glm;
+use scene_editor::components::PointLight;
+use scene_editor::renderer::light_space_matrix;
+use scene_editor::resources::{Fog, Lighting};
+use scene_editor::scene::{LightingRig, Scene};
+
+#[test]
+fn lighting_rig_round_trips_through_ron() {
+    let light = PointLight::new(
+        glm::vec3(0.1, 0.1, 0.1),
+        glm::vec3(1.0, 0.9, 0.8),
+        glm::vec3(1.0, 1.0, 1.0),
+        1.0,
+        0.09,
+        0.032,
+    );
+    let scene = Scene::new().spawn_model("sphere").at(0.0, 4.0, 0.0).point_light(light).finish();
+    let mut rig = LightingRig { lighting: Lighting::default(), lights: scene.entities };
+    rig.lighting.exposure = 1.5;
+
+    let loaded = LightingRig::from_ron(&rig.to_ron().unwrap()).unwrap();
+    assert_eq!(loaded.lighting, rig.lighting);
+    assert_eq!(loaded.lights.len(), 1);
+    assert_eq!(loaded.lights[0].point_light.unwrap().diffuse, light.diffuse);
+}
+
+#[test]
+fn missing_lighting_uses_defaults() {
+    let rig = LightingRig::from_ron("(lights: [])").unwrap();
+    assert_eq!(rig.lighting, Lighting::default());
+}
+
+#[test]
+fn fog_and_sky_are_kept_with_the_lighting() {
+    let mut lighting = Lighting { sky: true, ..Default::default() };
+    lighting.fog.enabled = true;
+    lighting.fog.density = 0.05;
+    lighting.fog.height_falloff = 0.0;
+    let rig = LightingRig { lighting, lights: Vec::new() };
+    assert_eq!(LightingRig::from_ron(&rig.to_ron().unwrap()).unwrap().lighting, lighting);
+
+    // Rigs from before fog keep the rest of their settings
+    let rig = LightingRig::from_ron("(lighting: (exposure: 2.0, fog: (enabled: true)))").unwrap();
+    assert_eq!(rig.lighting.exposure, 2.0);
+    assert!(rig.lighting.fog.enabled && !rig.lighting.sky);
+    assert_eq!(rig.lighting.fog.density, Fog::default().density);
+}
+
+#[test]
+fn shadows_are_projected_for_a_sun_straight_above() {
+    let noon = [glm::vec3(0.0, -1.0, 0.0), glm::vec3(0.0, 2.0, 0.0)];
+    for direction in noon.into_iter().chain([glm::vec3(0.3, -1.0, 0.2)]) {
+        let matrix = light_space_matrix(&direction);
+        assert!(matrix.iter().all(|v| v.is_finite()), "{direction:?}");
+        // Points along the sun direction only move in depth
+        let near = matrix * glm::vec4(0.0, 0.0, 0.0, 1.0);
+        let far = matrix * (direction.normalize() * 5.0).push(1.0);
+        assert!((near.xy() - far.xy()).norm() < 1e-4, "{direction:?}");
+        assert!(far.z > near.z, "{direction:?}");
+    }
+}
@@ -0,0 +1,93 @@
+use nalgebra_glm as glm;
+use scene_editor::components::Lod;
+use scene_editor::lod;
+use scene_editor::mesh_cleanup::MeshData;
+
+use crate::common::mesh_with;
+
+mod common;
+
+fn two_levels() -> Lod {
+    Lod {
+        distances: vec![10.0, 30.0],
+        meshes: vec![mesh_with(2, 100), mesh_with(3, 20)],
+        forced: None,
+    }
+}
+
+#[test]
+fn simplified_meshes_keep_under_the_ratio() {
+    let sphere = MeshData::sphere(32, 16);
+
+    for ratio in lod::LEVEL_RATIOS {
+        let simplified = lod::simplify(&sphere, ratio);
+        let budget = (sphere.triangle_count() as f32 * ratio) as usize;
+        assert!(simplified.triangle_count() <= budget, "{ratio}");
+        // The grid is made as fine as the budget allows, so most of it is used
+        assert!(simplified.triangle_count() > budget / 2, "{ratio}");
+        assert!(simplified.indices.iter().all(|&i| (i as usize) < simplified.vertex_count()));
+    }
+}
+
+#[test]
+fn simplified_meshes_keep_their_shape() {
+    let sphere = MeshData::sphere(32, 16);
+    let simplified = lod::simplify(&sphere, 0.2);
+
+    for position in &simplified.positions {
+        assert!((glm::length(position) - 1.0).abs() < 0.2, "{position:?} is off the sphere");
+    }
+    for normal in &simplified.normals {
+        assert!((glm::length(normal) - 1.0).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn meshes_within_the_ratio_are_unchanged() {
+    let cube = MeshData::cube();
+    assert_eq!(lod::simplify(&cube, 1.0), cube);
+}
+
+#[test]
+fn levels_are_picked_by_distance() {
+    let lod = two_levels();
+
+    assert_eq!(lod.level(5.0), 0);
+    assert_eq!(lod.level(10.0), 1);
+    assert_eq!(lod.level(29.0), 1);
+    assert_eq!(lod.level(100.0), 2);
+}
+
+#[test]
+fn forced_level_is_drawn_at_every_distance() {
+    let lod = Lod { forced: Some(2), ..two_levels() };
+    assert_eq!(lod.level(0.0), 2);
+
+    // Levels that are not there fall back to the least detailed one
+    let lod = Lod { forced: Some(5), ..two_levels() };
+    assert_eq!(lod.level(0.0), 2);
+}
+
+#[test]
+fn select_measures_from_the_center_of_the_mesh() {
+    let lod = two_levels();
+    let base = mesh_with(1, 200);
+    let model = glm::translation(&glm::vec3(0.0, 0.0, -20.0));
+
+    let near = Lod::select(Some(&lod), &base, &glm::Mat4::identity(), &glm::Vec3::zeros());
+    assert_eq!(near.vao_id, base.vao_id);
+    let far = Lod::select(Some(&lod), &base, &model, &glm::Vec3::zeros());
+    assert_eq!(far.vao_id, lod.meshes[0].vao_id);
+    assert_eq!(Lod::select(None, &base, &model, &glm::Vec3::zeros()).vao_id, base.vao_id);
+}
+
+#[test]
+fn level_names_are_reserved() {
+    assert!(lod::is_level_name(&lod::level_name("rock", 1)));
+    assert!(lod::is_level_name("tree.lod12"));
+    assert!(!lod::is_level_name("rock"));
+    assert!(!lod::is_level_name("rock.lod"));
+    assert!(!lod::is_level_name(".lod1"));
+    assert!(!lod::is_level_name("rock.lod1.2"));
+    assert!(!lod::is_level_name("rock.lodge"));
+}
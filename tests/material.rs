@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use scene_editor::material::{Material, MaterialLoader};
+use scene_editor::scene::{Scene, ShaderSources};
+use scene_editor::shader::UniformValue;
+
+use crate::common::temp_dir;
+
+mod common;
+
+fn bricks() -> Material {
+    Material {
+        diffuse: Some("bricks".to_owned()),
+        normal: Some("bricks_normal".to_owned()),
+        custom_shader: Some(ShaderSources {
+            vert: "#version 410 core\n".to_owned(),
+            frag: "#version 410 core\n".to_owned(),
+            params: BTreeMap::from([("tint".to_owned(), UniformValue::Vec3([1.0, 0.5, 0.5]))]),
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn materials_round_trip_through_files() {
+    let ron = bricks().to_ron().unwrap();
+    assert!(!ron.contains("specular"), "{ron}");
+    assert!(!ron.contains("alpha_test"), "{ron}");
+    assert_eq!(Material::from_ron(&ron).unwrap(), bricks());
+
+    let material = Material::from_ron("(diffuse: Some(\"leaves\"), alpha_test: true)").unwrap();
+    assert_eq!(material.diffuse.as_deref(), Some("leaves"));
+    assert!(material.alpha_test && material.custom_shader.is_none());
+}
+
+#[test]
+fn loader_reads_every_material_in_a_folder() {
+    let dir = temp_dir("material", "load");
+    bricks().save(dir.join("bricks.material")).unwrap();
+    fs::write(dir.join("broken.material"), "(diffuse: Some(").unwrap();
+    fs::write(dir.join("notes.txt"), "not a material").unwrap();
+
+    let mut loader = MaterialLoader::new();
+    loader.load_materials_in_dir(&dir);
+
+    let names: Vec<_> = loader.iter().map(|(_, name)| name).collect();
+    assert_eq!(names, ["bricks", "broken"]);
+    assert_eq!(loader.get(loader.id("bricks").unwrap()), Some(&bricks()));
+    // Broken files are kept under their name, so scenes using them are saved as they were
+    assert_eq!(loader.get(loader.id("broken").unwrap()), Some(&Material::default()));
+    assert_eq!(loader.errors().len(), 1);
+    assert!(loader.errors()[0].asset.ends_with("broken.material"));
+    assert_eq!(loader.dir(), Some(dir.as_path()));
+}
+
+#[test]
+fn missing_materials_are_replaced_and_reported() {
+    let mut loader = MaterialLoader::new();
+    loader.load_materials_in_dir(temp_dir("material", "missing"));
+    assert!(loader.errors().is_empty());
+
+    let id = loader.get_or_placeholder("marble");
+    assert_eq!(loader.name_of(id), Some("marble"));
+    assert_eq!(loader.get_or_placeholder("marble"), id);
+    assert_eq!(loader.errors().len(), 1);
+}
+
+#[test]
+fn saving_a_material_keeps_its_handle() {
+    let dir = temp_dir("material", "save");
+    let mut loader = MaterialLoader::new();
+    assert!(loader.save("bricks", bricks()).is_err(), "there is no folder to save to yet");
+
+    loader.load_materials_in_dir(&dir);
+    let id = loader.save("bricks", Material::default()).unwrap();
+    assert_eq!(loader.save("bricks", bricks()).unwrap(), id);
+    assert_eq!(loader.get(id), Some(&bricks()));
+    assert_eq!(Material::load(dir.join("bricks.material")).unwrap(), bricks());
+    assert!(loader.save("../bricks", bricks()).is_err());
+}
+
+#[test]
+fn scenes_refer_to_materials_by_name() {
+    let scene = Scene::new().spawn_model("wall").material("bricks").spawn_model("cube").finish();
+
+    let ron = scene.to_ron().unwrap();
+    assert_eq!(ron.matches("material").count(), 1, "{ron}");
+
+    let loaded = Scene::from_ron(&ron).unwrap();
+    assert_eq!(loaded.entities[0].material.as_deref(), Some("bricks"));
+    assert_eq!(loaded.entities[1].material, None);
+}
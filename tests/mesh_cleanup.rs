@@ -0,0 +1,132 @@
+use nalgebra_glm as glm;
+use scene_editor::mesh_cleanup::{
+    recompute_normals, remove_degenerate_triangles, weld_vertices, MeshData,
+};
+
+/// Two triangles of a unit quad that do not share their vertices
+fn split_quad() -> MeshData {
+    let positions = vec![
+        glm::vec3(0.0, 0.0, 0.0),
+        glm::vec3(1.0, 0.0, 0.0),
+        glm::vec3(1.0, 1.0, 0.0),
+        glm::vec3(0.0, 0.0, 0.0),
+        glm::vec3(1.0, 1.0, 0.00001),
+        glm::vec3(0.0, 1.0, 0.0),
+    ];
+    let texture_coords = positions.iter().map(|p| glm::vec2(p.x, p.y)).collect();
+    MeshData::new(positions, vec![glm::Vec3::z(); 6], texture_coords, (0..6).collect())
+}
+
+/// Two quads that meet at a right angle along the x axis, sharing the vertices of the edge
+fn folded_quads() -> MeshData {
+    let positions = vec![
+        glm::vec3(0.0, 0.0, 0.0),
+        glm::vec3(1.0, 0.0, 0.0),
+        glm::vec3(1.0, 0.0, -1.0),
+        glm::vec3(0.0, 0.0, -1.0),
+        glm::vec3(1.0, 1.0, 0.0),
+        glm::vec3(0.0, 1.0, 0.0),
+    ];
+    let indices = vec![0, 1, 2, 0, 2, 3, 0, 1, 4, 0, 4, 5];
+    MeshData::new(positions, Vec::new(), Vec::new(), indices)
+}
+
+#[test]
+fn weld_merges_close_vertices() {
+    let welded = weld_vertices(&split_quad(), 0.001);
+
+    assert_eq!(welded.vertex_count(), 4);
+    assert_eq!(welded.indices, [0, 1, 2, 0, 2, 3]);
+    assert_eq!(welded.normals[0], glm::Vec3::z());
+}
+
+#[test]
+fn weld_keeps_vertices_outside_tolerance_and_uv_seams() {
+    assert_eq!(weld_vertices(&split_quad(), 0.0).vertex_count(), 5);
+
+    let mut seam = split_quad();
+    seam.texture_coords[3] = glm::vec2(0.5, 0.5);
+    assert_eq!(weld_vertices(&seam, 0.001).vertex_count(), 5);
+}
+
+#[test]
+fn degenerate_triangles_and_unused_vertices_are_removed() {
+    let mut mesh = weld_vertices(&split_quad(), 0.001);
+    mesh.positions.push(glm::vec3(2.0, 0.0, 0.0));
+    mesh.normals.push(glm::Vec3::z());
+    mesh.texture_coords.push(glm::Vec2::zeros());
+    // A triangle repeating a vertex, and one with all vertices on a line
+    mesh.indices.extend([0, 0, 2, 0, 1, 4]);
+
+    let cleaned = remove_degenerate_triangles(&mesh);
+
+    assert_eq!(cleaned.triangle_count(), 2);
+    assert_eq!(cleaned.vertex_count(), 4);
+}
+
+#[test]
+fn sharp_edges_are_split_below_the_smoothing_angle() {
+    let flat = recompute_normals(&folded_quads(), 30.0);
+
+    // The two vertices on the fold get one copy for each side
+    assert_eq!(flat.vertex_count(), 8);
+    assert_eq!(flat.triangle_count(), 4);
+    for (i, normal) in flat.indices.iter().map(|&i| flat.normals[i as usize]).enumerate() {
+        let expected = if i < 6 { glm::Vec3::y() } else { glm::Vec3::z() };
+        assert!(glm::distance(&normal, &expected) < 1e-6, "{normal:?} should be {expected:?}");
+    }
+}
+
+#[test]
+fn edges_within_the_smoothing_angle_are_smoothed() {
+    let smooth = recompute_normals(&folded_quads(), 180.0);
+
+    assert_eq!(smooth.vertex_count(), 6);
+    let diagonal = glm::vec3(0.0, 1.0, 1.0).normalize();
+    assert!(glm::distance(&smooth.normals[0], &diagonal) < 1e-6);
+    assert!(glm::distance(&smooth.normals[1], &diagonal) < 1e-6);
+}
+
+/// Whether every triangle faces the way the normals of its corners point
+fn faces_along_normals(mesh: &MeshData) -> bool {
+    mesh.indices.chunks_exact(3).all(|t| {
+        let [a, b, c] = [0, 1, 2].map(|i| t[i] as usize);
+        let (pa, pb, pc) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+        let face = glm::cross(&(pb - pa), &(pc - pa));
+        [a, b, c].iter().all(|&i| glm::dot(&face, &mesh.normals[i]) > 0.0)
+    })
+}
+
+#[test]
+fn builtin_cube_has_a_quad_per_side() {
+    let cube = MeshData::cube();
+    assert_eq!(cube.vertex_count(), 24);
+    assert_eq!(cube.triangle_count(), 12);
+    assert!(cube.positions.iter().all(|p| p.abs() == glm::vec3(1.0, 1.0, 1.0)));
+    assert!(faces_along_normals(&cube));
+}
+
+#[test]
+fn builtin_plane_faces_up() {
+    let plane = MeshData::plane();
+    assert_eq!(plane.triangle_count(), 2);
+    assert!(plane.normals.iter().all(|n| *n == glm::Vec3::y()));
+    assert!(plane.positions.iter().all(|p| p.y == 0.0));
+    assert!(faces_along_normals(&plane));
+}
+
+#[test]
+fn builtin_sphere_has_unit_radius() {
+    let sphere = MeshData::sphere(16, 8);
+    assert!(sphere.positions.iter().all(|p| (glm::length(p) - 1.0).abs() < 1e-5));
+    assert!(sphere.positions.iter().zip(&sphere.normals).all(|(p, n)| glm::distance(p, n) < 1e-5));
+    let area: f32 = sphere
+        .indices
+        .chunks_exact(3)
+        .map(|t| {
+            let [a, b, c] = [0, 1, 2].map(|i| sphere.positions[t[i] as usize]);
+            glm::length(&glm::cross(&(b - a), &(c - a))) / 2.0
+        })
+        .sum();
+    assert!((area - 4.0 * std::f32::consts::PI).abs() < 0.5);
+}
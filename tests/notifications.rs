@@ -0,0 +1,56 @@
+use bevy_ecs::prelude::*;
+use scene_editor::commands;
+use scene_editor::resources::{NotificationLevel, Notifications};
+
+fn levels_and_messages(notifications: &Notifications) -> Vec<(NotificationLevel, &str)> {
+    notifications.history().map(|n| (n.level, n.message.as_str())).collect()
+}
+
+#[test]
+fn new_notifications_are_shown_as_toasts_and_in_the_status_bar() {
+    let mut notifications = Notifications::default();
+    assert!(notifications.latest().is_none());
+
+    notifications.info("Loaded 3 models and 2 textures");
+    notifications.error("Could not load the scene");
+    notifications.success("Saved level.ron");
+
+    assert_eq!(
+        levels_and_messages(&notifications),
+        [
+            (NotificationLevel::Info, "Loaded 3 models and 2 textures"),
+            (NotificationLevel::Error, "Could not load the scene"),
+            (NotificationLevel::Success, "Saved level.ron"),
+        ]
+    );
+    assert_eq!(notifications.active().count(), 3);
+    assert_eq!(notifications.latest().unwrap().message, "Saved level.ron");
+
+    notifications.clear();
+    assert!(notifications.latest().is_none());
+}
+
+#[test]
+fn only_the_newest_notifications_are_kept() {
+    let mut notifications = Notifications::default();
+    for i in 0..150 {
+        notifications.warning(format!("warning {i}"));
+    }
+
+    assert_eq!(notifications.history().count(), 100);
+    assert_eq!(notifications.history().next().unwrap().message, "warning 50");
+    assert_eq!(notifications.latest().unwrap().message, "warning 149");
+}
+
+#[test]
+fn scenes_that_can_not_be_loaded_are_notified() {
+    let mut world = World::new();
+    world.init_resource::<Notifications>();
+    let path = std::env::temp_dir().join("scene_editor_notifications").join("missing.ron");
+
+    commands::load_scene(&mut world, &path);
+
+    let latest = world.resource::<Notifications>().latest().cloned().unwrap();
+    assert_eq!(latest.level, NotificationLevel::Error);
+    assert!(latest.message.starts_with("Could not load the scene"), "{}", latest.message);
+}
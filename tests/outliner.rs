@@ -0,0 +1,137 @@
+use std::collections::BTreeSet;
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use scene_editor::components::{Hidden, Locked, PointLight, Rotator, Tags};
+use scene_editor::entity_pool::Pooled;
+use scene_editor::outliner::{self, OutlinerEntry, OutlinerFilter};
+use scene_editor::registry::ComponentRegistry;
+use scene_editor::scene::Scene;
+use serde::{Deserialize, Serialize};
+
+mod common;
+
+#[derive(Component, Default, Serialize, Deserialize)]
+struct Health(f32);
+
+fn entry(index: u32, name: &str, tags: &[&str], components: &[&'static str]) -> OutlinerEntry {
+    OutlinerEntry {
+        entity: Entity::from_raw(index),
+        name: name.to_owned(),
+        depth: 0,
+        tags: tags.iter().map(|&t| t.to_owned()).collect(),
+        hidden: components.contains(&"Hidden"),
+        locked: components.contains(&"Locked"),
+        components: components.to_vec(),
+    }
+}
+
+fn entries() -> Vec<OutlinerEntry> {
+    vec![
+        entry(0, "Plane 0", &["floor", "background"], &[]),
+        entry(1, "Sphere 1", &["Light"], &["PointLight"]),
+        entry(2, "Sphere 2", &[], &["CustomShader", "Rotator"]),
+        entry(3, "Cube 3", &["background"], &["Health"]),
+    ]
+}
+
+fn matching(filter: &str) -> Vec<u32> {
+    let filter = OutlinerFilter::parse(filter);
+    entries().iter().filter(|e| filter.matches(e)).map(|e| e.entity.index()).collect()
+}
+
+#[test]
+fn empty_filter_matches_everything() {
+    assert!(OutlinerFilter::parse("  ").is_empty());
+    assert_eq!(matching(""), [0, 1, 2, 3]);
+}
+
+#[test]
+fn names_match_by_substring() {
+    assert_eq!(matching("sphere"), [1, 2]);
+    assert_eq!(matching("E 3"), [3]);
+    assert_eq!(matching("cone"), [] as [u32; 0]);
+}
+
+#[test]
+fn tags_and_components_match_whole_names_ignoring_case() {
+    assert_eq!(matching("tag:light"), [1]);
+    assert_eq!(matching("tag:back"), [] as [u32; 0]);
+    assert_eq!(matching("tag:"), [0, 1, 3]);
+    assert_eq!(matching("has:customshader"), [2]);
+    assert_eq!(matching("has:Health"), [3]);
+}
+
+#[test]
+fn every_term_must_match() {
+    assert_eq!(matching("tag:background cube"), [3]);
+    assert_eq!(matching("sphere has:Rotator"), [2]);
+    assert_eq!(matching("tag:light has:Rotator"), [] as [u32; 0]);
+}
+
+#[test]
+fn components_lists_built_in_and_registered_components() {
+    let mut world = World::new();
+    let mut registry = ComponentRegistry::new();
+    registry.register::<Health>("Health");
+    world.insert_resource(registry);
+
+    let light = world
+        .spawn((
+            PointLight::new(
+                glm::vec3(1.0, 1.0, 1.0),
+                glm::vec3(1.0, 1.0, 1.0),
+                glm::vec3(1.0, 1.0, 1.0),
+                1.0,
+                0.0,
+                0.0,
+            ),
+            Hidden,
+            Health(3.0),
+        ))
+        .id();
+    let spinner = world.spawn((Rotator::new(0.0, 1.0, 0.0), Tags::new(["spinning"]))).id();
+
+    assert_eq!(outliner::components(&world, light), ["PointLight", "Hidden", "Health"]);
+    assert_eq!(outliner::components(&world, spinner), ["Rotator"]);
+}
+
+#[test]
+fn entries_list_meshes_with_their_flags() {
+    let mut world = World::new();
+    let mesh = common::mesh();
+    let floor = world.spawn((mesh, Locked, Tags::new(["floor"]))).id();
+    let ghost = world.spawn((mesh, Hidden)).id();
+    world.spawn((mesh, Pooled));
+    world.spawn(Rotator::new(0.0, 1.0, 0.0));
+
+    let entries = outliner::entries(&mut world);
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].entity, floor);
+    assert!(entries[0].locked && !entries[0].hidden);
+    assert_eq!(entries[0].components, ["Locked"]);
+    assert!(entries[0].tags.contains("floor"));
+    assert_eq!(entries[1].entity, ghost);
+    assert!(entries[1].hidden && !entries[1].locked);
+    assert_eq!(entries[1].name, format!("Entity {}", ghost.index()));
+}
+
+#[test]
+fn hidden_and_locked_are_saved_in_scenes() {
+    let mut scene = Scene::new().spawn_model("plane").locked().finish();
+    scene.entities[0].hidden = true;
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+
+    assert!(loaded.entities[0].locked);
+    assert!(loaded.entities[0].hidden);
+    assert!(!Scene::from_ron("(entities: [(model: \"cube\")])").unwrap().entities[0].locked);
+}
+
+#[test]
+fn tags_are_saved_in_scenes() {
+    let scene = Scene::new().spawn_model("cube").tag("prop").tag("crate").finish();
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+
+    let expected: BTreeSet<_> = ["crate", "prop"].map(str::to_owned).into();
+    assert_eq!(loaded.entities[0].tags, expected);
+}
@@ -0,0 +1,92 @@
+use nalgebra_glm as glm;
+use scene_editor::components::{Position, Scale};
+use scene_editor::placement::{self, DepthSamples};
+use scene_editor::renderer::model_matrix;
+use scene_editor::resources::{Camera, ViewRect};
+
+use crate::common::assert_close_within;
+
+mod common;
+
+const RECT: ViewRect = ViewRect { x: 0, y: 0, width: 100, height: 100 };
+
+/// Depth buffer values around a pixel for a scene where every pixel shows `depth_at`
+fn depths(x: i32, y: i32, depth_at: impl Fn(i32, i32) -> f32) -> DepthSamples {
+    std::array::from_fn(|row| {
+        std::array::from_fn(|col| depth_at(x + col as i32 - 1, y + row as i32 - 1))
+    })
+}
+
+/// Depth of the point where a pixel's ray meets the plane through `point` with `normal`
+fn plane_depth(camera: &Camera, point: glm::Vec3, normal: glm::Vec3, x: i32, y: i32) -> f32 {
+    let viewport = glm::vec4(0.0, 0.0, 100.0, 100.0);
+    let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+    let near = glm::unproject(&glm::vec3(x, y, 0.0), &camera.view(), &camera.projection, viewport);
+    let far = glm::unproject(&glm::vec3(x, y, 1.0), &camera.view(), &camera.projection, viewport);
+    let t = glm::dot(&(point - near), &normal) / glm::dot(&(far - near), &normal);
+    glm::project(&glm::lerp(&near, &far, t), &camera.view(), &camera.projection, viewport).z
+}
+
+#[test]
+fn wall_in_front_of_the_camera_faces_it() {
+    let camera = Camera::looking_forward(100, 100);
+    let (point, normal) = (glm::vec3(0.0, 0.0, -5.0), glm::vec3(0.0, 0.0, 1.0));
+    let samples = depths(50, 50, |x, y| plane_depth(&camera, point, normal, x, y));
+
+    let hit = placement::surface_hit(&camera, RECT, 50, 50, &samples).unwrap();
+    // Half a pixel off the center of the view
+    assert!((hit.position.z + 5.0).abs() < 1e-2);
+    assert!(hit.position.x.abs() < 0.1 && hit.position.y.abs() < 0.1);
+    assert_close_within(hit.normal, normal, 1e-2);
+}
+
+#[test]
+fn floor_seen_from_above_points_up() {
+    let camera = Camera::looking_at_origin(100, 100, -90.0, -90.0, 20.0);
+    let (point, normal) = (glm::vec3(0.0, 2.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+    let samples = depths(30, 70, |x, y| plane_depth(&camera, point, normal, x, y));
+
+    let hit = placement::surface_hit(&camera, RECT, 30, 70, &samples).unwrap();
+    assert!((hit.position.y - 2.0).abs() < 1e-2);
+    assert_close_within(hit.normal, normal, 1e-2);
+}
+
+#[test]
+fn background_next_to_an_edge_does_not_bend_the_normal() {
+    let camera = Camera::looking_forward(100, 100);
+    let (point, normal) = (glm::vec3(0.0, 0.0, -5.0), glm::vec3(0.0, 0.0, 1.0));
+    // Nothing is drawn to the right of the pixel or above it
+    let samples = depths(50, 50, |x, y| {
+        if x > 50 || y > 50 {
+            1.0
+        } else {
+            plane_depth(&camera, point, normal, x, y)
+        }
+    });
+
+    let hit = placement::surface_hit(&camera, RECT, 50, 50, &samples).unwrap();
+    assert_close_within(hit.normal, normal, 1e-2);
+}
+
+#[test]
+fn nothing_is_hit_where_only_the_background_is_drawn() {
+    let camera = Camera::looking_forward(100, 100);
+    assert_eq!(placement::surface_hit(&camera, RECT, 50, 50, &[[1.0; 3]; 3]), None);
+}
+
+#[test]
+fn aligned_models_point_their_up_axis_along_the_normal() {
+    let normals = [
+        glm::vec3(0.0, 1.0, 0.0),
+        glm::vec3(1.0, 0.0, 0.0),
+        glm::vec3(0.0, 0.0, -1.0),
+        glm::vec3(0.0, -1.0, 0.0),
+        glm::normalize(&glm::vec3(-1.0, 2.0, 3.0)),
+    ];
+    for normal in normals {
+        let rotation = placement::align_to_normal(normal);
+        let matrix = model_matrix(Position::default(), rotation, Scale::default());
+        let up = glm::vec4_to_vec3(&(matrix * glm::vec4(0.0, 1.0, 0.0, 0.0)));
+        assert_close_within(up, normal, 1e-2);
+    }
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "editor")]
+
+use bevy_ecs::prelude::*;
+use scene_editor::plugin::load_order;
+use scene_editor::{Plugin, SceneEditorApp};
+
+struct Named {
+    name: &'static str,
+    dependencies: Vec<&'static str>,
+}
+
+impl Plugin for Named {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        self.dependencies.clone()
+    }
+
+    fn build(&self, _app: &mut SceneEditorApp) {}
+}
+
+fn plugin(name: &'static str, dependencies: &[&'static str]) -> Box<dyn Plugin> {
+    Box::new(Named { name, dependencies: dependencies.to_vec() })
+}
+
+fn names(plugins: &[Box<dyn Plugin>]) -> Vec<&'static str> {
+    plugins.iter().map(|p| p.name()).collect()
+}
+
+#[test]
+fn plugins_come_after_their_dependencies() {
+    let plugins = vec![
+        plugin("exporter", &["terrain"]),
+        plugin("terrain", &["physics"]),
+        plugin("physics", &[]),
+        plugin("audio", &[]),
+    ];
+
+    let ordered = load_order(plugins, &[]).unwrap();
+    assert_eq!(names(&ordered), ["physics", "terrain", "exporter", "audio"]);
+}
+
+#[test]
+fn dependencies_that_were_built_before_are_met() {
+    let ordered = load_order(vec![plugin("terrain", &["physics"])], &["physics"]).unwrap();
+    assert_eq!(names(&ordered), ["terrain"]);
+}
+
+#[test]
+fn missing_and_circular_dependencies_are_errors() {
+    assert!(load_order(vec![plugin("terrain", &["physics"])], &[]).is_err());
+    let cycle = vec![plugin("a", &["b"]), plugin("b", &["a"])];
+    assert!(load_order(cycle, &[]).is_err());
+    let twice = vec![plugin("a", &[]), plugin("a", &[])];
+    assert!(load_order(twice, &[]).is_err());
+}
+
+#[derive(Resource, Default)]
+struct Built(Vec<&'static str>);
+
+fn record(app: &mut SceneEditorApp, name: &'static str) {
+    app.world_mut().get_resource_or_insert_with(Built::default).0.push(name);
+}
+
+struct Physics;
+
+impl Plugin for Physics {
+    fn name(&self) -> &'static str {
+        "physics"
+    }
+
+    fn build(&self, app: &mut SceneEditorApp) {
+        record(app, "physics");
+    }
+}
+
+struct Vehicles;
+
+impl Plugin for Vehicles {
+    fn dependencies(&self) -> Vec<&'static str> {
+        vec!["physics"]
+    }
+
+    fn build(&self, app: &mut SceneEditorApp) {
+        record(app, "vehicles");
+        app.add_plugin(Trailers);
+    }
+}
+
+struct Trailers;
+
+impl Plugin for Trailers {
+    fn dependencies(&self) -> Vec<&'static str> {
+        vec!["physics"]
+    }
+
+    fn build(&self, app: &mut SceneEditorApp) {
+        record(app, "trailers");
+    }
+}
+
+#[test]
+fn plugins_added_while_building_are_built_too() {
+    let mut app = SceneEditorApp::new();
+    app.add_plugin(Vehicles).add_plugin(Physics);
+
+    app.build_plugins().unwrap();
+    assert_eq!(app.world().resource::<Built>().0, ["physics", "vehicles", "trailers"]);
+}
+
+#[test]
+fn building_fails_when_a_dependency_is_missing() {
+    let mut app = SceneEditorApp::new();
+    app.add_plugin(Vehicles);
+
+    assert!(app.build_plugins().is_err());
+}
@@ -0,0 +1,45 @@
+use nalgebra_glm as glm;
+use scene_editor::components::{PointLight, LIGHT_CUTOFF};
+
+fn white(constant: f32, linear: f32, quadratic: f32) -> PointLight {
+    let white = glm::vec3(1.0, 1.0, 1.0);
+    PointLight::new(white * 0.1, white, white, constant, linear, quadratic)
+}
+
+fn attenuation(light: &PointLight, distance: f32) -> f32 {
+    1.0 / (light.constant + light.linear * distance + light.quadratic * distance * distance)
+}
+
+#[test]
+fn the_range_is_where_the_light_fades_out() {
+    let light = white(1.0, 0.09, 0.032);
+    let range = light.range().unwrap();
+    assert!((range - 43.0).abs() < 0.1, "{range}");
+    assert!((attenuation(&light, range) - LIGHT_CUTOFF).abs() < 1e-5);
+
+    // A dimmer light fades out sooner
+    let dim = PointLight { diffuse: glm::vec3(0.25, 0.1, 0.0), ..light };
+    assert!(dim.range().unwrap() < range);
+}
+
+#[test]
+fn setting_the_range_keeps_the_constant_and_colors() {
+    for range in [0.5, 10.0, 200.0] {
+        let mut light = white(1.0, 0.09, 0.032);
+        light.set_range(range);
+        let found = light.range().unwrap();
+        assert!((found - range).abs() < range * 1e-4, "{found} for {range}");
+        assert_eq!(light.constant, 1.0);
+        assert_eq!(light.diffuse, glm::vec3(1.0, 1.0, 1.0));
+        assert!(light.linear > 0.0 && light.quadratic > 0.0);
+    }
+}
+
+#[test]
+fn lights_without_falloff_have_no_range() {
+    assert_eq!(white(1.0, 0.0, 0.0).range(), None);
+    assert_eq!(white(1.0, 0.5, 0.0).range(), Some(126.0));
+    // Too dim to ever be above the cutoff
+    let dark = PointLight { diffuse: glm::Vec3::zeros(), ..white(1.0, 0.09, 0.032) };
+    assert_eq!(dark.range(), Some(0.0));
+}
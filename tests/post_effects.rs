@@ -0,0 +1,16 @@
+use scene_editor::resources::{EffectQuality, PostEffects};
+
+#[test]
+fn higher_quality_takes_more_samples() {
+    let samples = EffectQuality::ALL.map(|quality| quality.samples(6));
+    assert_eq!(samples, [6, 12, 24]);
+    assert_eq!(EffectQuality::default(), EffectQuality::Medium);
+}
+
+#[test]
+fn post_effects_are_off_by_default() {
+    let effects = PostEffects::default();
+    assert!(!effects.depth_of_field.enabled);
+    assert!(!effects.motion_blur.enabled);
+    assert!(effects.depth_of_field.focus_distance > 0.0);
+}
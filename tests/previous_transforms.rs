@@ -0,0 +1,24 @@
+use bevy_ecs::prelude::*;
+use scene_editor::components::{Position, PreviousTransform, Rotation, Scale, TransformBundle};
+use scene_editor::renderer::{model_matrix, store_previous_transforms};
+use scene_editor::transform::update_global_transforms;
+
+#[test]
+fn previous_transform_lags_one_frame_behind() {
+    let mut world = World::new();
+    let entity = world.spawn(TransformBundle::default()).id();
+    let mut schedule = Schedule::default();
+    schedule.add_systems((update_global_transforms, store_previous_transforms).chain());
+
+    schedule.run(&mut world);
+    let start = model_matrix(Position::default(), Rotation::default(), Scale::default());
+    assert_eq!(world.get::<PreviousTransform>(entity), Some(&PreviousTransform(start)));
+
+    world.get_mut::<Position>(entity).unwrap().x = 2.0;
+    // Drawing would happen here, still seeing the matrix from the previous frame
+    assert_eq!(world.get::<PreviousTransform>(entity).unwrap().0, start);
+
+    schedule.run(&mut world);
+    let moved = model_matrix(Position::new(2.0, 0.0, 0.0), Rotation::default(), Scale::default());
+    assert_eq!(world.get::<PreviousTransform>(entity).unwrap().0, moved);
+}
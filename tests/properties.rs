@@ -0,0 +1,56 @@
+use scene_editor::components::{Properties, PropertyValue};
+use scene_editor::scene::Scene;
+
+#[test]
+fn properties_round_trip_through_scene_files() {
+    let scene = Scene::new()
+        .spawn_model("cube")
+        .property("team", "red")
+        .property("hp", 100.0)
+        .property("boss", true)
+        .spawn_model("sphere")
+        .finish();
+
+    let ron = scene.to_ron().unwrap();
+    assert!(ron.contains("\"team\": \"red\""), "{ron}");
+    assert!(ron.contains("\"boss\": true"), "{ron}");
+    // Entities without properties leave them out of the file
+    assert_eq!(ron.matches("properties").count(), 1, "{ron}");
+
+    let loaded = Scene::from_ron(&ron).unwrap();
+    let cube = &loaded.entities[0].properties;
+    assert_eq!(cube["team"], PropertyValue::String("red".to_owned()));
+    assert_eq!(cube["hp"], PropertyValue::Number(100.0));
+    assert_eq!(cube["boss"], PropertyValue::Bool(true));
+    assert!(loaded.entities[1].properties.is_empty());
+}
+
+#[test]
+fn whole_numbers_in_scene_files_are_numbers() {
+    let ron = r#"(entities: [(model: "cube", properties: {"hp": 100, "name": "crate"})])"#;
+    let scene = Scene::from_ron(ron).unwrap();
+    let properties = &scene.entities[0].properties;
+    assert_eq!(properties["hp"], PropertyValue::Number(100.0));
+    assert_eq!(properties["name"], PropertyValue::String("crate".to_owned()));
+}
+
+#[test]
+fn gltf_extras_become_properties() {
+    let extras = r#"{"spawn": "enemy", "weight": 2.5, "static": false, "skip": null,
+        "tags": ["a", "b"], "nested": {"x": 1}}"#;
+    let properties = Properties::from_gltf_extras(extras).unwrap();
+    let values = &properties.values;
+    assert_eq!(values["spawn"], PropertyValue::String("enemy".to_owned()));
+    assert_eq!(values["weight"], PropertyValue::Number(2.5));
+    assert_eq!(values["static"], PropertyValue::Bool(false));
+    assert!(!values.contains_key("skip"));
+    assert_eq!(values["tags"], PropertyValue::String(r#"["a","b"]"#.to_owned()));
+    assert_eq!(values["nested"], PropertyValue::String(r#"{"x":1}"#.to_owned()));
+}
+
+#[test]
+fn extras_that_are_not_objects_are_ignored() {
+    assert!(Properties::from_gltf_extras("[1, 2]").is_none());
+    assert!(Properties::from_gltf_extras("\"text\"").is_none());
+    assert!(Properties::from_gltf_extras("not json").is_none());
+}
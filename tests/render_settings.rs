@@ -0,0 +1,71 @@
+use scene_editor::resources::{
+    EffectQuality, Lighting, RenderSettings, RenderState, ShadowSettings, PCF_KERNEL_SIZES,
+};
+use scene_editor::scene::Scene;
+
+#[test]
+fn render_settings_round_trip_through_scenes() {
+    let mut render_settings = RenderSettings { shadow_map_size: 2048, ..Default::default() };
+    render_settings.reflections = true;
+    render_settings.post_effects.motion_blur.enabled = true;
+    render_settings.post_effects.depth_of_field.quality = EffectQuality::High;
+    let lighting = Lighting { exposure: 2.0, ..Default::default() };
+    let scene = Scene { lighting: Some(lighting), render_settings, ..Default::default() };
+
+    let parsed = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    assert_eq!(parsed.render_settings, render_settings);
+    assert_eq!(parsed.lighting, Some(lighting));
+}
+
+#[test]
+fn scenes_without_render_settings_load_the_defaults() {
+    let scene = Scene::from_ron("(entities: [])").unwrap();
+    assert_eq!(scene.render_settings, RenderSettings::default());
+    assert_eq!(scene.render_settings.shadow_map_size, RenderState::SHADOW_MAP_SIZE);
+    // The lighting is left as it is when a scene without it is loaded
+    assert_eq!(scene.lighting, None);
+}
+
+#[test]
+fn default_render_settings_are_not_saved() {
+    let ron = Scene::default().to_ron().unwrap();
+    assert!(!ron.contains("render_settings"));
+    assert!(!ron.contains("lighting"));
+}
+
+#[test]
+fn default_shadows_are_filtered_and_biased() {
+    let shadows = ShadowSettings::default();
+    assert!(PCF_KERNEL_SIZES.contains(&shadows.pcf_kernel_size));
+    assert!(shadows.pcf_kernel_size > 1);
+    assert!(shadows.depth_bias > 0.0);
+    assert!(shadows.normal_offset > 0.0);
+    // Scenes saved before the shadow settings existed load the defaults
+    let settings: RenderSettings = ron::from_str("(shadow_map_size: 2048)").unwrap();
+    assert_eq!(settings.shadows, shadows);
+}
+
+#[test]
+fn loaded_shadow_settings_are_clamped() {
+    let load = |shadows: &str| {
+        ron::from_str::<RenderSettings>(&format!("(shadows: {shadows})")).unwrap().shadows
+    };
+    // A negative kernel would shadow everything, and a huge one could hang the GPU
+    assert_eq!(load("(pcf_kernel_size: -3)").pcf_kernel_size, 1);
+    assert_eq!(load("(pcf_kernel_size: 1000000)").pcf_kernel_size, 7);
+    assert_eq!(load("(pcf_kernel_size: 4)").pcf_kernel_size, 3);
+    assert_eq!(load("(pcf_kernel_size: 5)").pcf_kernel_size, 5);
+
+    let shadows = load("(depth_bias: -1.0, normal_offset: 50.0)");
+    assert_eq!(shadows.depth_bias, 0.0);
+    assert_eq!(shadows.normal_offset, ShadowSettings::MAX_NORMAL_OFFSET);
+    assert_eq!(load("(depth_bias: 2.0)").depth_bias, ShadowSettings::MAX_DEPTH_BIAS);
+    // Fields that are left out still get their defaults
+    assert_eq!(
+        load("(depth_bias: 2.0)").pcf_kernel_size,
+        ShadowSettings::default().pcf_kernel_size
+    );
+
+    let shadows = ShadowSettings::default();
+    assert_eq!(shadows.clamped(), shadows);
+}
@@ -0,0 +1,50 @@
+use nalgebra_glm as glm;
+use scene_editor::components::Rotation;
+
+use crate::common::assert_close;
+
+mod common;
+
+/// Rotation around the Y axis, then X, then Z, as Euler angles have always been applied
+fn yxz(x: f32, y: f32, z: f32) -> glm::Mat4 {
+    glm::rotation(y.to_radians(), &glm::Vec3::y())
+        * glm::rotation(x.to_radians(), &glm::Vec3::x())
+        * glm::rotation(z.to_radians(), &glm::Vec3::z())
+}
+
+#[test]
+fn euler_angles_are_applied_in_y_x_z_order() {
+    let rotation = Rotation::new(30.0, 45.0, -60.0);
+
+    assert_close(rotation.matrix(), yxz(30.0, 45.0, -60.0));
+    // The angles that were set are kept, even past a full turn
+    assert_eq!(Rotation::new(0.0, 370.0, 0.0).euler(), glm::vec3(0.0, 370.0, 0.0));
+}
+
+#[test]
+fn quaternions_convert_back_to_the_same_rotation() {
+    for (x, y, z) in [(30.0, 45.0, -60.0), (-80.0, 170.0, 10.0), (90.0, 30.0, 20.0)] {
+        let quat = Rotation::new(x, y, z).quat();
+        let euler = Rotation::from_quat(quat).euler();
+
+        assert_close(yxz(euler.x, euler.y, euler.z), yxz(x, y, z));
+    }
+}
+
+#[test]
+fn rotations_are_saved_as_euler_angles() {
+    let rotation = Rotation::new(10.0, 20.0, 30.0);
+
+    let saved = ron::to_string(&rotation).unwrap();
+    assert_eq!(saved, "(x:10.0,y:20.0,z:30.0)");
+    assert_eq!(ron::from_str::<Rotation>(&saved).unwrap(), rotation);
+}
+
+#[test]
+fn slerp_blends_equal_rotations_and_turns_the_short_way() {
+    let from = Rotation::new(0.0, 10.0, 0.0);
+
+    assert_close(from.slerp(&from, 0.5).matrix(), from.matrix());
+    let halfway = from.slerp(&Rotation::new(0.0, 300.0, 0.0), 0.5);
+    assert_close(halfway.matrix(), yxz(0.0, -25.0, 0.0));
+}
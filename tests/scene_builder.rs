@@ -0,0 +1,123 @@
+use nalgebra_glm as glm;
+use scene_editor::components::{AnimationClip, FollowPath, PointLight, Rotator};
+use scene_editor::resources::CameraPose;
+use scene_editor::scene::{Scene, SceneEntity};
+
+#[test]
+fn builder_sets_entity_fields() {
+    let scene = Scene::new()
+        .spawn_model("cube")
+        .at(1.0, 2.0, 3.0)
+        .rotated(0.0, 90.0, 0.0)
+        .scaled(2.0, 2.0, 2.0)
+        .diffuse("bricks")
+        .specular("bricks_specular")
+        .rotator(Rotator::new(0.0, 45.0, 0.0))
+        .finish();
+
+    assert_eq!(scene.entities.len(), 1);
+    let cube = &scene.entities[0];
+    assert_eq!(cube.model, "cube");
+    assert_eq!((cube.position.x, cube.position.y, cube.position.z), (1.0, 2.0, 3.0));
+    assert_eq!(cube.rotation.euler().y, 90.0);
+    assert_eq!((cube.scale.x, cube.scale.y, cube.scale.z), (2.0, 2.0, 2.0));
+    assert_eq!(cube.diffuse.as_deref(), Some("bricks"));
+    assert_eq!(cube.specular.as_deref(), Some("bricks_specular"));
+    assert_eq!(cube.rotator.map(|r| r.y), Some(45.0));
+    assert!(cube.point_light.is_none());
+}
+
+#[test]
+fn builder_chains_entities_in_order() {
+    let scene: Scene = Scene::new()
+        .spawn_model("cube")
+        .spawn_model("sphere")
+        .at(0.0, 5.0, 0.0)
+        .spawn_model("plane")
+        .into();
+
+    let models: Vec<_> = scene.entities.iter().map(|e| e.model.as_str()).collect();
+    assert_eq!(models, ["cube", "sphere", "plane"]);
+    assert_eq!(scene.entities[0].position.y, 0.0);
+    assert_eq!(scene.entities[1].position.y, 5.0);
+    assert_eq!(scene.entities[2].scale.x, 1.0);
+}
+
+#[test]
+fn builder_extends_existing_scene() {
+    let mut scene = Scene::new().spawn_model("cube").finish();
+    scene.entities.push(SceneEntity::new("sphere"));
+    let scene = scene.spawn_model("plane").finish();
+
+    let models: Vec<_> = scene.entities.iter().map(|e| e.model.as_str()).collect();
+    assert_eq!(models, ["cube", "sphere", "plane"]);
+}
+
+#[test]
+fn built_scene_round_trips_through_ron() {
+    let light = PointLight::new(
+        glm::vec3(0.2, 0.2, 0.2),
+        glm::vec3(1.0, 1.0, 1.0),
+        glm::vec3(1.0, 1.0, 1.0),
+        1.0,
+        0.09,
+        0.032,
+    );
+    let scene = Scene::new()
+        .spawn_model("lamp")
+        .at(0.0, 3.0, 0.0)
+        .point_light(light)
+        .spawn_model("cube")
+        .custom_shader("vert source", "frag source")
+        .follow_path(FollowPath::new(
+            vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0)],
+            2.0,
+            true,
+        ))
+        .animation_clip(AnimationClip::default())
+        .finish();
+
+    let ron = scene.to_ron().unwrap();
+    let loaded = Scene::from_ron(&ron).unwrap();
+
+    assert_eq!(loaded.entities.len(), 2);
+    let lamp = &loaded.entities[0];
+    assert_eq!(lamp.model, "lamp");
+    assert_eq!(lamp.position.y, 3.0);
+    assert_eq!(lamp.point_light.map(|l| l.linear), Some(0.09));
+
+    let cube = &loaded.entities[1];
+    let shader = cube.custom_shader.as_ref().unwrap();
+    assert_eq!((shader.vert.as_str(), shader.frag.as_str()), ("vert source", "frag source"));
+    assert_eq!(cube.follow_path.as_ref().map(|p| p.points.len()), Some(2));
+    assert!(cube.animation_clip.is_some());
+    assert!(cube.point_light.is_none());
+}
+
+#[test]
+fn saved_views_round_trip_through_ron() {
+    let mut scene = Scene::new().spawn_model("cube").finish();
+    let pose = CameraPose { position: glm::vec3(1.0, 2.0, 3.0), yaw: -45.0, pitch: 10.0 };
+    scene.views.insert(3, pose);
+
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    assert_eq!(loaded.views.len(), 1);
+    assert_eq!(loaded.views.get(&3), Some(&pose));
+
+    // Scenes without views leave the field out
+    let ron = Scene::new().spawn_model("cube").finish().to_ron().unwrap();
+    assert!(!ron.contains("views"));
+}
+
+#[test]
+fn shader_params_round_trip_through_ron() {
+    let mut scene = Scene::new().spawn_model("cube").finish();
+    scene.shader_params = glm::vec4(0.5, 1.0, -2.0, 0.25);
+
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    assert_eq!(loaded.shader_params, scene.shader_params);
+
+    // Unused parameters are left out
+    let ron = Scene::new().spawn_model("cube").finish().to_ron().unwrap();
+    assert!(!ron.contains("shader_params"));
+}
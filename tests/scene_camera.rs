@@ -0,0 +1,73 @@
+use nalgebra_glm as glm;
+use scene_editor::components::{CameraComponent, Position, Rotation, Scale};
+use scene_editor::renderer::model_matrix;
+use scene_editor::resources::{Camera, ViewLayout};
+use scene_editor::scene::Scene;
+
+use crate::common::close;
+
+mod common;
+
+#[test]
+fn scene_cameras_look_along_the_entity() {
+    let component = CameraComponent::default();
+    let model = model_matrix(Position::new(1.0, 2.0, 3.0), Rotation::default(), Scale::default());
+    let camera = component.camera(&model, 800, 600);
+    assert!(close(camera.pos, glm::vec3(1.0, 2.0, 3.0)));
+    assert!(close(camera.front, glm::vec3(0.0, 0.0, -1.0)));
+    // Unrotated, it sees what the editor camera sees from the same place
+    let editor = Camera::looking_forward(800, 600);
+    assert!(close(camera.front, editor.front));
+    assert_eq!(camera.projection, editor.projection);
+
+    let turned = model_matrix(Position::default(), Rotation::new(0.0, 90.0, 0.0), Scale::default());
+    let camera = component.camera(&turned, 800, 600);
+    assert!(close(camera.front, glm::vec3(-1.0, 0.0, 0.0)), "{:?}", camera.front);
+}
+
+#[test]
+fn looking_through_replaces_the_active_camera() {
+    let mut layout = ViewLayout::new(800, 600);
+    let editor = Camera::looking_forward(800, 600);
+    let model = model_matrix(Position::new(0.0, 5.0, 0.0), Rotation::default(), Scale::default());
+    layout.set_scene_camera(Some(CameraComponent::default().camera(&model, 800, 600)));
+
+    // The scene camera is previewed until it is looked through
+    assert_eq!(layout.views(&editor, 800, 600)[0].0.pos, editor.pos);
+    let (preview, rect) = layout.preview(800, 600).unwrap();
+    assert_eq!(preview.pos.y, 5.0);
+    assert_eq!(rect, ViewLayout::preview_rect(800, 600));
+    // Clicks on the preview are measured from the top left corner of the window
+    assert!(layout.in_preview(800, 600, 790.0 - rect.width as f64 / 2.0, 590.0));
+    assert!(!layout.in_preview(800, 600, 10.0, 10.0));
+
+    layout.look_through = true;
+    assert_eq!(layout.views(&editor, 800, 600)[0].0.pos.y, 5.0);
+    assert!(layout.preview(800, 600).is_none());
+
+    // Without a selected scene camera the editor camera is used
+    layout.set_scene_camera(None);
+    assert_eq!(layout.views(&editor, 800, 600)[0].0.pos, editor.pos);
+}
+
+#[test]
+fn the_preview_fits_in_the_bottom_right_corner() {
+    for (width, height) in [(800, 600), (1920, 1080), (40, 30)] {
+        let rect = ViewLayout::preview_rect(width, height);
+        let (width, height) = (width as i32, height as i32);
+        assert!(rect.width > 0 && rect.height > 0);
+        assert!(rect.x >= width / 2 && rect.x + rect.width <= width);
+        assert!(rect.y >= 0 && rect.y + rect.height <= height / 2);
+    }
+}
+
+#[test]
+fn cameras_without_a_model_survive_ron_round_trip() {
+    let scene = Scene::new().spawn_model("").camera(CameraComponent::default()).finish();
+
+    let ron = scene.to_ron().unwrap();
+    assert!(!ron.contains("model:"), "{ron}");
+    let loaded = Scene::from_ron(&ron).unwrap();
+    assert_eq!(loaded.entities[0].model, "");
+    assert!(loaded.entities[0].camera.is_some());
+}
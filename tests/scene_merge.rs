@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use scene_editor::components::FollowPath;
+use scene_editor::scene::{MergeOptions, Scene};
+use scene_editor::variables::{BindingTarget, VariableValue};
+use scene_editor::variants::{EntityOverride, SceneVariants, Variant};
+
+fn names(names: &[&str]) -> BTreeSet<String> {
+    names.iter().map(|&n| n.to_owned()).collect()
+}
+
+fn chunk() -> Scene {
+    let mut night = Variant::default();
+    night.variables.insert("brightness".to_owned(), VariableValue::Float(0.2));
+    Scene::new()
+        .variable("brightness", VariableValue::Float(1.0))
+        .variable("brightness 2", VariableValue::Float(0.5))
+        .variant("night", night)
+        .spawn_model("lamp")
+        .at(1.0, 0.0, 0.0)
+        .follow_path(FollowPath::new(vec![glm::vec3(0.0, 0.0, 0.0)], 1.0, false))
+        .bind(BindingTarget::LightConstant, "brightness")
+        .into()
+}
+
+#[test]
+fn merging_moves_the_scene_by_the_offset() {
+    let mut scene = chunk();
+    let options = MergeOptions { offset: glm::vec3(10.0, 0.0, -5.0), ..Default::default() };
+    scene.prepare_merge(&names(&["brightness"]), &names(&["night"]), &options);
+    let lamp = &scene.entities[0];
+    assert_eq!((lamp.position.x, lamp.position.y, lamp.position.z), (11.0, 0.0, -5.0));
+    assert_eq!(lamp.follow_path.as_ref().unwrap().points, [glm::vec3(10.0, 0.0, -5.0)]);
+    // Without renaming, names are shared with the open scene
+    assert_eq!(lamp.bindings[&BindingTarget::LightConstant], "brightness");
+    assert!(scene.variants.contains_key("night"));
+}
+
+#[test]
+fn merging_renames_clashing_variables_and_variants() {
+    let mut scene = chunk();
+    let options = MergeOptions { rename_duplicates: true, ..Default::default() };
+    scene.prepare_merge(&names(&["brightness", "fog"]), &names(&["night", "night 2"]), &options);
+
+    // The next number is neither in the open scene nor the imported one
+    let variables: Vec<_> = scene.variables.keys().map(String::as_str).collect();
+    assert_eq!(variables, ["brightness 2", "brightness 3"]);
+    assert_eq!(scene.variables["brightness 3"], VariableValue::Float(1.0));
+    assert_eq!(scene.entities[0].bindings[&BindingTarget::LightConstant], "brightness 3");
+
+    let variants: Vec<_> = scene.variants.keys().map(String::as_str).collect();
+    assert_eq!(variants, ["night 3"]);
+    let night = &scene.variants["night 3"];
+    assert_eq!(night.variables.keys().collect::<Vec<_>>(), ["brightness 3"]);
+
+    // Names that do not clash are kept
+    let mut scene = chunk();
+    scene.prepare_merge(&names(&[]), &names(&[]), &options);
+    assert!(scene.variables.contains_key("brightness"));
+    assert!(scene.variants.contains_key("night"));
+}
+
+#[test]
+fn merged_variants_add_to_variants_with_the_same_name() {
+    let mut world = World::new();
+    let (open, imported) = (world.spawn_empty().id(), world.spawn_empty().id());
+    let hide = EntityOverride { hidden: Some(true), ..Default::default() };
+
+    let mut night = Variant::default();
+    night.entities.insert(open, hide.clone());
+    night.variables.insert("brightness".to_owned(), VariableValue::Float(0.2));
+    let mut variants = SceneVariants::default();
+    variants.variants.insert("night".to_owned(), night);
+
+    let mut imported_night = Variant::default();
+    imported_night.entities.insert(imported, hide.clone());
+    imported_night.variables.insert("brightness".to_owned(), VariableValue::Float(0.9));
+    imported_night.variables.insert("fog".to_owned(), VariableValue::Float(0.5));
+    let imported_variants =
+        [("night".to_owned(), imported_night), ("day".to_owned(), Variant::default())];
+    variants.merge(imported_variants.into_iter().collect());
+
+    assert_eq!(variants.variants.len(), 2);
+    let night = &variants.variants["night"];
+    assert_eq!(night.entities.keys().copied().collect::<Vec<_>>(), [open, imported]);
+    // The open scene's value wins
+    assert_eq!(night.variables["brightness"], VariableValue::Float(0.2));
+    assert_eq!(night.variables["fog"], VariableValue::Float(0.5));
+}
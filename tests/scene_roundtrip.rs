@@ -0,0 +1,500 @@
+use std::collections::BTreeMap;
+
+use nalgebra_glm as glm;
+use proptest::collection::{btree_map, btree_set, vec};
+use proptest::option;
+use proptest::prelude::*;
+use scene_editor::components::{
+    AnimationClip, AnimationPlayer, CameraComponent, FollowPath, Interpolation, Keyframe,
+    Oscillator, PointLight, Position, PropertyValue, Rotation, Rotator, Scale,
+};
+use scene_editor::material::Material;
+use scene_editor::resources::{
+    CameraPose, DepthOfField, DirectionalLight, EffectQuality, Fog, Lighting, MotionBlur,
+    PostEffects, RenderSettings, ShadowSettings, PCF_KERNEL_SIZES, SHADOW_MAP_SIZES,
+};
+use scene_editor::scene::{
+    LightingRig, Scene, SceneEntity, SceneGroup, SceneTerrain, ShaderSources,
+};
+use scene_editor::shader::UniformValue;
+use scene_editor::texture_import::TextureSettings;
+use scene_editor::variables::{BindingTarget, VariableValue};
+use scene_editor::variants::{EntityOverride, Textures, Variant};
+
+/// Any finite value, from subnormals to the largest floats
+fn float() -> impl Strategy<Value = f32> {
+    prop::num::f32::NORMAL | prop::num::f32::SUBNORMAL | prop::num::f32::ZERO
+}
+
+fn vec3() -> impl Strategy<Value = glm::Vec3> {
+    (float(), float(), float()).prop_map(|(x, y, z)| glm::vec3(x, y, z))
+}
+
+/// Names and text with quotes, escapes and non-ASCII characters
+fn name() -> impl Strategy<Value = String> {
+    "\\PC{0,12}"
+}
+
+fn position() -> impl Strategy<Value = Position> {
+    (float(), float(), float()).prop_map(|(x, y, z)| Position::new(x, y, z))
+}
+
+fn rotation() -> impl Strategy<Value = Rotation> {
+    (float(), float(), float()).prop_map(|(x, y, z)| Rotation::new(x, y, z))
+}
+
+fn scale() -> impl Strategy<Value = Scale> {
+    (float(), float(), float()).prop_map(|(x, y, z)| Scale::new(x, y, z))
+}
+
+fn point_light() -> impl Strategy<Value = PointLight> {
+    (vec3(), vec3(), vec3(), float(), float(), float())
+        .prop_map(|(a, d, s, c, l, q)| PointLight::new(a, d, s, c, l, q))
+}
+
+fn uniform() -> impl Strategy<Value = UniformValue> {
+    prop_oneof![
+        float().prop_map(UniformValue::Float),
+        any::<i32>().prop_map(UniformValue::Int),
+        any::<bool>().prop_map(UniformValue::Bool),
+        [float(), float()].prop_map(UniformValue::Vec2),
+        [float(), float(), float()].prop_map(UniformValue::Vec3),
+        [float(), float(), float(), float()].prop_map(UniformValue::Vec4),
+    ]
+}
+
+fn shader() -> impl Strategy<Value = ShaderSources> {
+    (name(), name(), btree_map(name(), uniform(), 0..4))
+        .prop_map(|(vert, frag, params)| ShaderSources { vert, frag, params })
+}
+
+fn keyframe() -> impl Strategy<Value = Keyframe> {
+    (float(), position(), rotation(), scale())
+        .prop_map(|(time, position, rotation, scale)| Keyframe { time, position, rotation, scale })
+}
+
+fn animation_clip() -> impl Strategy<Value = AnimationClip> {
+    let interpolation = prop_oneof![Just(Interpolation::Linear), Just(Interpolation::Cubic)];
+    (vec(keyframe(), 0..4), interpolation, any::<bool>(), float()).prop_map(
+        |(keyframes, interpolation, looping, length)| AnimationClip {
+            keyframes,
+            interpolation,
+            looping,
+            length,
+            ..Default::default()
+        },
+    )
+}
+
+fn animation_player() -> impl Strategy<Value = AnimationPlayer> {
+    (option::of(any::<usize>()), float(), any::<bool>()).prop_map(|(animation, speed, looping)| {
+        AnimationPlayer { animation, speed, looping, ..Default::default() }
+    })
+}
+
+fn property() -> impl Strategy<Value = PropertyValue> {
+    prop_oneof![
+        any::<bool>().prop_map(PropertyValue::Bool),
+        (prop::num::f64::NORMAL | prop::num::f64::ZERO).prop_map(PropertyValue::Number),
+        name().prop_map(PropertyValue::String),
+    ]
+}
+
+fn binding() -> impl Strategy<Value = BindingTarget> {
+    prop_oneof![
+        Just(BindingTarget::Position),
+        Just(BindingTarget::Scale),
+        Just(BindingTarget::LightAmbient),
+        Just(BindingTarget::LightDiffuse),
+        Just(BindingTarget::LightSpecular),
+        Just(BindingTarget::LightConstant),
+        Just(BindingTarget::LightLinear),
+        Just(BindingTarget::LightQuadratic),
+        name().prop_map(BindingTarget::Uniform),
+    ]
+}
+
+/// Values of registered components, which are stored as plain RON values
+fn component_value() -> impl Strategy<Value = ron::Value> {
+    let leaf = prop_oneof![
+        Just(ron::Value::Unit),
+        any::<bool>().prop_map(ron::Value::Bool),
+        any::<char>().prop_map(ron::Value::Char),
+        (-1_000_000_000i64..1_000_000_000).prop_map(|n| ron::Value::Number(n.into())),
+        name().prop_map(ron::Value::String),
+    ];
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(ron::Value::Seq),
+            option::of(inner.clone()).prop_map(|v| ron::Value::Option(v.map(Box::new))),
+            btree_map(name(), inner, 0..4).prop_map(|fields| {
+                ron::Value::Map(
+                    fields.into_iter().map(|(k, v)| (ron::Value::String(k), v)).collect(),
+                )
+            }),
+        ]
+    })
+}
+
+prop_compose! {
+    fn entity()(
+        model in name(),
+        (position, rotation, scale) in (position(), rotation(), scale()),
+        (diffuse, specular, normal) in (option::of(name()), option::of(name()), option::of(name())),
+        (alpha_test, hidden, locked) in (any::<bool>(), any::<bool>(), any::<bool>()),
+        custom_shader in option::of(shader()),
+        material in option::of(name()),
+        point_light in option::of(point_light()),
+        camera in option::of((float(), float(), float())
+            .prop_map(|(fov_degrees, near, far)| CameraComponent { fov_degrees, near, far })),
+        rotator in option::of((float(), float(), float())
+            .prop_map(|(x, y, z)| Rotator::new(x, y, z))),
+        oscillator in option::of((vec3(), float(), float())
+            .prop_map(|(a, m, f)| Oscillator::new(a, m, f))),
+        follow_path in option::of((vec(vec3(), 0..4), float(), any::<bool>())
+            .prop_map(|(points, speed, looping)| FollowPath::new(points, speed, looping))),
+        animation_clip in option::of(animation_clip()),
+        animation_player in option::of(animation_player()),
+        lod_distances in vec(float(), 0..3),
+        properties in btree_map(name(), property(), 0..4),
+        tags in btree_set(name(), 0..4),
+        script in option::of("\\PC{0,40}"),
+        bindings in btree_map(binding(), name(), 0..4),
+        components in btree_map(name(), component_value(), 0..3),
+        group in option::of(0..3usize),
+    ) -> SceneEntity {
+        SceneEntity {
+            model,
+            position,
+            rotation,
+            scale,
+            diffuse,
+            specular,
+            normal,
+            alpha_test,
+            hidden,
+            locked,
+            custom_shader,
+            material,
+            point_light,
+            camera,
+            rotator,
+            oscillator,
+            follow_path,
+            animation_clip,
+            animation_player,
+            lod_distances,
+            properties,
+            tags,
+            script,
+            bindings,
+            components,
+            group,
+        }
+    }
+}
+
+fn group() -> impl Strategy<Value = SceneGroup> {
+    (name(), position(), rotation(), scale(), option::of(0..3usize)).prop_map(
+        |(name, position, rotation, scale, parent)| SceneGroup {
+            name,
+            position,
+            rotation,
+            scale,
+            parent,
+        },
+    )
+}
+
+fn variable() -> impl Strategy<Value = VariableValue> {
+    prop_oneof![
+        float().prop_map(VariableValue::Float),
+        vec3().prop_map(VariableValue::Color),
+        vec3().prop_map(VariableValue::Vector),
+    ]
+}
+
+fn fog() -> impl Strategy<Value = Fog> {
+    (any::<bool>(), vec3(), float(), float(), float(), float()).prop_map(
+        |(enabled, color, density, start, height, height_falloff)| Fog {
+            enabled,
+            color,
+            density,
+            start,
+            height,
+            height_falloff,
+        },
+    )
+}
+
+fn lighting() -> impl Strategy<Value = Lighting> {
+    (vec3(), vec3(), vec3(), vec3(), float(), vec3(), fog(), any::<bool>()).prop_map(
+        |(direction, ambient, diffuse, specular, exposure, background, fog, sky)| Lighting {
+            sun: DirectionalLight { direction, ambient, diffuse, specular },
+            exposure,
+            background,
+            fog,
+            sky,
+        },
+    )
+}
+
+fn render_settings() -> impl Strategy<Value = RenderSettings> {
+    let quality = || prop::sample::select(EffectQuality::ALL.to_vec());
+    let depth_of_field = (any::<bool>(), float(), float(), quality()).prop_map(
+        |(enabled, focus_distance, aperture, quality)| DepthOfField {
+            enabled,
+            focus_distance,
+            aperture,
+            quality,
+        },
+    );
+    let motion_blur = (any::<bool>(), float(), quality())
+        .prop_map(|(enabled, strength, quality)| MotionBlur { enabled, strength, quality });
+    let shadows = (prop::sample::select(PCF_KERNEL_SIZES.to_vec()), float(), float()).prop_map(
+        |(pcf_kernel_size, depth_bias, normal_offset)| ShadowSettings {
+            pcf_kernel_size,
+            depth_bias,
+            normal_offset,
+        },
+    );
+    let shadow_map_size = prop::sample::select(SHADOW_MAP_SIZES.to_vec());
+    (shadow_map_size, shadows, any::<bool>(), depth_of_field, motion_blur).prop_map(
+        |(shadow_map_size, shadows, reflections, depth_of_field, motion_blur)| RenderSettings {
+            shadow_map_size,
+            shadows,
+            reflections,
+            post_effects: PostEffects { depth_of_field, motion_blur },
+        },
+    )
+}
+
+fn entity_override() -> impl Strategy<Value = EntityOverride> {
+    let textures = (option::of(name()), option::of(name()), option::of(name()))
+        .prop_map(|(diffuse, specular, normal)| Textures { diffuse, specular, normal });
+    (
+        option::of(any::<bool>()),
+        option::of(point_light()),
+        option::of(textures),
+        btree_map(name(), uniform(), 0..3),
+    )
+        .prop_map(|(hidden, point_light, textures, uniforms)| EntityOverride {
+            hidden,
+            point_light,
+            textures,
+            uniforms,
+        })
+}
+
+fn variant() -> impl Strategy<Value = Variant> {
+    (
+        option::of(lighting()),
+        btree_map(name(), variable(), 0..3),
+        btree_map(0..8usize, entity_override(), 0..3),
+    )
+        .prop_map(|(lighting, variables, entities)| Variant { lighting, variables, entities })
+}
+
+fn terrain() -> impl Strategy<Value = SceneTerrain> {
+    let layers = [option::of(name()), option::of(name()), option::of(name()), option::of(name())];
+    let flags = (any::<bool>(), any::<bool>());
+    (position(), rotation(), scale(), 1..3u32, float(), flags, layers).prop_flat_map(
+        |(position, rotation, scale, quads, size, (painted, hidden), layers)| {
+            let vertices = ((quads + 1) * (quads + 1)) as usize;
+            let splat = vec([float(), float(), float(), float()], vertices * painted as usize);
+            (vec(float(), vertices), splat).prop_map(move |(heights, splat)| SceneTerrain {
+                position,
+                rotation,
+                scale,
+                quads,
+                size,
+                heights,
+                splat,
+                layers: layers.clone(),
+                hidden,
+            })
+        },
+    )
+}
+
+fn camera_pose() -> impl Strategy<Value = CameraPose> {
+    let angle = prop::num::f64::NORMAL | prop::num::f64::ZERO;
+    (vec3(), angle, angle).prop_map(|(position, yaw, pitch)| CameraPose { position, yaw, pitch })
+}
+
+prop_compose! {
+    fn scene()(
+        entities in vec(entity(), 0..4),
+        groups in vec(group(), 0..3),
+        views in btree_map(any::<u8>(), camera_pose(), 0..3),
+        shader_params in (float(), float(), float(), float())
+            .prop_map(|(x, y, z, w)| glm::vec4(x, y, z, w)),
+        variables in btree_map(name(), variable(), 0..3),
+        variants in btree_map(name(), variant(), 0..2),
+        terrains in vec(terrain(), 0..2),
+        lighting in option::of(lighting()),
+        render_settings in render_settings(),
+    ) -> Scene {
+        Scene {
+            entities,
+            groups,
+            views,
+            shader_params,
+            variables,
+            variants,
+            terrains,
+            lighting,
+            render_settings,
+        }
+    }
+}
+
+/// Ways a saved scene gets corrupted: truncated, with bytes replaced, or with text spliced in
+#[derive(Debug, Clone)]
+enum Corruption {
+    Truncate(usize),
+    Replace(usize, char),
+    Insert(usize, String),
+    Remove(usize, usize),
+}
+
+fn corruption() -> impl Strategy<Value = Corruption> {
+    prop_oneof![
+        any::<usize>().prop_map(Corruption::Truncate),
+        (any::<usize>(), any::<char>()).prop_map(|(at, c)| Corruption::Replace(at, c)),
+        (any::<usize>(), "[(){}\\[\\]:,\"'\\\\0-9a-zA-Z.eE+-]{1,8}")
+            .prop_map(|(at, s)| Corruption::Insert(at, s)),
+        (any::<usize>(), 1..32usize).prop_map(|(at, len)| Corruption::Remove(at, len)),
+    ]
+}
+
+fn corrupt(text: &str, corruptions: &[Corruption]) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    for corruption in corruptions {
+        let at = |i: usize| i % (chars.len() + 1);
+        match corruption {
+            Corruption::Truncate(i) => chars.truncate(at(*i)),
+            Corruption::Replace(i, c) => {
+                if !chars.is_empty() {
+                    let i = i % chars.len();
+                    chars[i] = *c;
+                }
+            }
+            Corruption::Insert(i, s) => {
+                let i = at(*i);
+                chars.splice(i..i, s.chars());
+            }
+            Corruption::Remove(i, len) => {
+                let i = at(*i);
+                chars.drain(i..(i + len).min(chars.len()));
+            }
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// A scene using every kind of component, to corrupt
+fn sample_scene() -> Scene {
+    let mut scene = Scene::new()
+        .spawn_model("cube")
+        .at(1.0, 2.0, 3.0)
+        .diffuse("bricks")
+        .rotator(Rotator::new(0.0, 45.0, 0.0))
+        .spawn_model("sphere")
+        .point_light(PointLight::new(
+            glm::vec3(0.1, 0.1, 0.1),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            1.0,
+            0.09,
+            0.032,
+        ))
+        .follow_path(FollowPath::new(
+            vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0)],
+            2.0,
+            true,
+        ))
+        .finish();
+    let cube = &mut scene.entities[0];
+    cube.properties.insert("tag".to_owned(), PropertyValue::String("crate".to_owned()));
+    cube.tags.insert("props".to_owned());
+    cube.script = Some("fn update() {\n    this.rotation.y += 90.0 * this.dt;\n}\n".to_owned());
+    cube.components.insert("Health".to_owned(), ron::Value::Number(100.into()));
+    cube.animation_clip = Some(AnimationClip::default());
+    scene.variables.insert("tint".to_owned(), VariableValue::Color(glm::vec3(1.0, 0.5, 0.0)));
+    scene.variants.insert(
+        "night".to_owned(),
+        Variant {
+            entities: BTreeMap::from([(
+                1,
+                EntityOverride { hidden: Some(true), ..Default::default() },
+            )]),
+            ..Default::default()
+        },
+    );
+    scene
+}
+
+proptest! {
+    #[test]
+    fn scenes_round_trip(scene in scene()) {
+        let ron = scene.to_ron().unwrap();
+        let parsed = Scene::from_ron(&ron).map_err(|e| TestCaseError::fail(format!("{e}\n{ron}")))?;
+        prop_assert_eq!(parsed, scene);
+    }
+
+    #[test]
+    fn lighting_rigs_round_trip(lighting in lighting(), lights in vec(entity(), 0..3)) {
+        let rig = LightingRig { lighting, lights };
+        let ron = rig.to_ron().unwrap();
+        let parsed = LightingRig::from_ron(&ron)
+            .map_err(|e| TestCaseError::fail(format!("{e}\n{ron}")))?;
+        prop_assert_eq!(parsed.lighting, rig.lighting);
+        prop_assert_eq!(parsed.lights, rig.lights);
+    }
+
+    #[test]
+    fn materials_round_trip(
+        (diffuse, specular, normal) in (option::of(name()), option::of(name()), option::of(name())),
+        alpha_test in any::<bool>(),
+        custom_shader in option::of(shader()),
+    ) {
+        let material = Material { diffuse, specular, normal, alpha_test, custom_shader };
+        let ron = material.to_ron().unwrap();
+        prop_assert_eq!(Material::from_ron(&ron).unwrap(), material);
+    }
+
+    #[test]
+    fn corrupt_scenes_do_not_panic(corruptions in vec(corruption(), 1..4)) {
+        let text = corrupt(&sample_scene().to_ron().unwrap(), &corruptions);
+        // Either outcome is fine, as long as parsing does not panic
+        let _ = Scene::from_ron(&text);
+    }
+
+    #[test]
+    fn arbitrary_text_does_not_panic(text in "\\PC*") {
+        let _ = Scene::from_ron(&text);
+        let _ = LightingRig::from_ron(&text);
+        let _ = Material::from_ron(&text);
+        let _ = TextureSettings::from_ron(&text);
+    }
+
+    #[test]
+    fn arbitrary_ron_does_not_panic(text in "[(){}\\[\\]:,\"' 0-9a-z_.+-]{0,64}") {
+        let _ = Scene::from_ron(&text);
+        let _ = LightingRig::from_ron(&text);
+    }
+}
+
+#[test]
+fn truncated_scenes_are_errors() {
+    let text = sample_scene().to_ron().unwrap();
+    for (end, _) in text.char_indices().filter(|&(end, _)| end > 0) {
+        assert!(
+            Scene::from_ron(&text[..end]).is_err(),
+            "parsed the first {end} bytes:\n{}",
+            &text[..end]
+        );
+    }
+    assert_eq!(Scene::from_ron(&text).unwrap(), sample_scene());
+}
@@ -0,0 +1,125 @@
+#![cfg(feature = "editor")]
+
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use scene_editor::components::{Position, Rotation, Scale, Script};
+use scene_editor::resources::{Input, KeyEvent, Time};
+use scene_editor::scripting::{run_scripts, ScriptLog, ScriptRuntime};
+use winit::event::{ElementState, VirtualKeyCode};
+
+fn setup() -> (World, Schedule) {
+    let mut world = World::new();
+    world.init_resource::<ScriptRuntime>();
+    world.init_resource::<ScriptLog>();
+    world.init_resource::<Input>();
+    let mut time = Time::new();
+    time.step(Duration::from_millis(500));
+    world.insert_resource(time);
+    let mut schedule = Schedule::default();
+    schedule.add_system(run_scripts);
+    (world, schedule)
+}
+
+fn spawn(world: &mut World, source: &str) -> Entity {
+    let transform = (Position::default(), Rotation::default(), Scale::default());
+    world.spawn((Script::new(source), transform)).id()
+}
+
+fn log(world: &World) -> Vec<(String, bool)> {
+    let log = world.resource::<ScriptLog>();
+    log.messages().map(|m| (m.text.clone(), m.is_error)).collect()
+}
+
+#[test]
+fn update_moves_the_entity_while_a_key_is_held() {
+    let (mut world, mut schedule) = setup();
+    let source = r#"
+        fn update() {
+            if this.input.held("W") {
+                this.position.x += 2.0 * this.dt;
+            }
+            this.rotation.y += 90.0 * this.dt;
+        }
+    "#;
+    let entity = spawn(&mut world, source);
+
+    schedule.run(&mut world);
+    world
+        .resource_mut::<Input>()
+        .handle_key_event(KeyEvent { key: VirtualKeyCode::W, state: ElementState::Pressed });
+    schedule.run(&mut world);
+
+    assert_eq!(*world.get::<Position>(entity).unwrap(), Position::new(1.0, 0.0, 0.0));
+    assert_eq!(*world.get::<Rotation>(entity).unwrap(), Rotation::new(0.0, 90.0, 0.0));
+    assert!(log(&world).is_empty());
+}
+
+#[test]
+fn fields_are_kept_between_frames_and_when_the_script_changes() {
+    let (mut world, mut schedule) = setup();
+    let entity = spawn(
+        &mut world,
+        "fn start() { this.count = 0; } fn update() { this.count += 1; print(this.count); }",
+    );
+    schedule.run(&mut world);
+    schedule.run(&mut world);
+
+    world.get_mut::<Script>(entity).unwrap().source =
+        "fn update() { this.count += 10; print(this.count); }".to_owned();
+    schedule.run(&mut world);
+
+    let printed = ["1", "2", "12"].map(|text| (text.to_owned(), false));
+    assert_eq!(log(&world), printed);
+}
+
+#[test]
+fn a_failing_script_stops_until_it_is_changed() {
+    let (mut world, mut schedule) = setup();
+    let entity = spawn(&mut world, r#"fn update() { this.input.held("NoSuchKey"); }"#);
+    schedule.run(&mut world);
+    schedule.run(&mut world);
+
+    let messages = log(&world);
+    assert_eq!(messages.len(), 1, "the error is reported once: {messages:?}");
+    assert!(messages[0].0.contains("NoSuchKey") && messages[0].1);
+    assert!(world.resource::<ScriptRuntime>().error(entity).is_some());
+
+    world.get_mut::<Script>(entity).unwrap().source = "fn update() {}".to_owned();
+    schedule.run(&mut world);
+    assert_eq!(world.resource::<ScriptRuntime>().error(entity), None);
+}
+
+#[test]
+fn compile_errors_are_reported() {
+    let (mut world, mut schedule) = setup();
+    let entity = spawn(&mut world, "fn update( {");
+    schedule.run(&mut world);
+
+    let messages = log(&world);
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].0.starts_with("does not compile") && messages[0].1);
+    assert_eq!(*world.get::<Position>(entity).unwrap(), Position::default());
+}
+
+#[test]
+fn scripts_that_never_finish_are_stopped() {
+    let (mut world, mut schedule) = setup();
+    let entity = spawn(&mut world, "fn update() { loop {} }");
+    schedule.run(&mut world);
+
+    assert!(world.resource::<ScriptRuntime>().error(entity).is_some());
+}
+
+#[test]
+fn printed_messages_are_copied_with_their_entity() {
+    let mut log = ScriptLog::default();
+    let entity = World::new().spawn_empty().id();
+    log.push(entity, "hello".to_owned(), false);
+    log.push(entity, "no function update".to_owned(), true);
+    let index = entity.index();
+    assert_eq!(
+        log.to_text(),
+        format!("entity {index}: hello\nentity {index}: no function update\n")
+    );
+}
@@ -0,0 +1,92 @@
+use std::fs;
+
+use scene_editor::shader::{Severity, ShaderDiagnostic, ShaderType};
+use scene_editor::shader_cache::{cache_key, stage_key, BinaryStore};
+
+use crate::common::temp_dir;
+
+mod common;
+
+const VERT: &str = "#version 410 core\nvoid main() { gl_Position = vec4(0.0); }\n";
+const FRAG: &str = "#version 410 core\nout vec4 color;\nvoid main() { color = vec4(1.0); }\n";
+
+#[test]
+fn identical_sources_share_a_key() {
+    assert_eq!(cache_key(VERT, FRAG), cache_key(VERT, FRAG));
+    assert!(cache_key(VERT, FRAG).is_some());
+
+    let brighter = FRAG.replace("1.0", "2.0");
+    assert_ne!(cache_key(VERT, FRAG), cache_key(VERT, &brighter));
+    // The stages are hashed separately, so swapping them is a different program
+    assert_ne!(cache_key(VERT, FRAG), cache_key(FRAG, VERT));
+}
+
+#[test]
+fn stages_are_keyed_on_their_own() {
+    let brighter = FRAG.replace("1.0", "2.0");
+    assert_ne!(stage_key(FRAG, ShaderType::Fragment), stage_key(&brighter, ShaderType::Fragment));
+    // The same source compiled as another stage is another shader
+    assert_ne!(stage_key(FRAG, ShaderType::Fragment), stage_key(FRAG, ShaderType::Vertex));
+    assert_eq!(stage_key(VERT, ShaderType::Vertex), stage_key(VERT, ShaderType::Vertex));
+    assert!(stage_key("#include \"missing.glsl\"\n", ShaderType::Vertex).is_none());
+}
+
+#[test]
+fn sources_with_unresolved_includes_have_no_key() {
+    let frag = "#version 410 core\n#include \"lighting.glsl\"\nvoid main() {}\n";
+    assert!(cache_key(VERT, frag).is_some());
+    assert!(cache_key(VERT, "#include \"missing.glsl\"\n").is_none());
+}
+
+#[test]
+fn stored_binaries_are_read_back() {
+    let dir = temp_dir("shader_cache", "round_trip");
+    let store = BinaryStore::new(&dir, "Mesa 23.1");
+    assert!(store.read(7).is_none());
+
+    store.write(7, 0x8740, &[1, 2, 3, 4, 5]).unwrap();
+    assert_eq!(store.read(7), Some((0x8740, vec![1, 2, 3, 4, 5])));
+    assert!(store.read(8).is_none());
+
+    store.remove(7);
+    assert!(store.read(7).is_none());
+}
+
+#[test]
+fn diagnostics_are_stored_with_binaries() {
+    let dir = temp_dir("shader_cache", "diagnostics");
+    let store = BinaryStore::new(&dir, "Mesa 23.1");
+    let warning = ShaderDiagnostic {
+        severity: Severity::Warning,
+        line: Some(3),
+        column: None,
+        message: "unused variable".to_owned(),
+    };
+    store.write(7, 1, &[1]).unwrap();
+    assert!(store.read_diagnostics(7).is_none());
+
+    store.write_diagnostics(7, &(Vec::new(), vec![warning.clone()])).unwrap();
+    assert_eq!(store.read_diagnostics(7), Some((Vec::new(), vec![warning])));
+    assert_eq!(store.read(7), Some((1, vec![1])));
+
+    store.remove(7);
+    assert!(store.read_diagnostics(7).is_none());
+}
+
+#[test]
+fn binaries_are_kept_per_driver() {
+    let dir = temp_dir("shader_cache", "per_driver");
+    BinaryStore::new(&dir, "Mesa 23.1").write(7, 1, &[1]).unwrap();
+    assert!(BinaryStore::new(&dir, "Mesa 23.2").read(7).is_none());
+    assert_eq!(BinaryStore::new(&dir, "Mesa 23.1").read(7), Some((1, vec![1])));
+}
+
+#[test]
+fn truncated_binaries_are_ignored() {
+    let dir = temp_dir("shader_cache", "truncated");
+    let store = BinaryStore::new(&dir, "Mesa 23.1");
+    store.write(7, 1, &[]).unwrap();
+    let file = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    fs::write(&file, [1, 2]).unwrap();
+    assert!(store.read(7).is_none());
+}
@@ -0,0 +1,40 @@
+use scene_editor::shader::{parse_info_log, Severity, ShaderDiagnostic};
+
+#[test]
+fn parses_driver_log_formats() {
+    let log = "0:12(5): error: `foo' undeclared\n\
+               0(7) : warning C7050: \"bar\" might be used before being initialized\n\
+               ERROR: 0:3: 'baz' : undeclared identifier\n";
+
+    assert_eq!(
+        parse_info_log(log),
+        [
+            ShaderDiagnostic {
+                severity: Severity::Error,
+                line: Some(12),
+                column: Some(5),
+                message: "`foo' undeclared".to_owned(),
+            },
+            ShaderDiagnostic {
+                severity: Severity::Warning,
+                line: Some(7),
+                column: None,
+                message: "\"bar\" might be used before being initialized".to_owned(),
+            },
+            ShaderDiagnostic {
+                severity: Severity::Error,
+                line: Some(3),
+                column: None,
+                message: "'baz' : undeclared identifier".to_owned(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn keeps_unknown_lines_without_a_position() {
+    let diagnostics = parse_info_log("ERROR: 1 compilation errors.  No code generated.\n\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, None);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
@@ -0,0 +1,33 @@
+use scene_editor::shader::{self, ShaderFeatures};
+
+#[test]
+fn defines_go_after_the_version_line() {
+    let features =
+        ShaderFeatures { skinned: true, alpha_test: false, normal_map: false, splat: false };
+    let source = features.apply("#version 410 core\nvoid main() {}\n");
+
+    let lines: Vec<_> = source.lines().collect();
+    assert_eq!(
+        lines,
+        ["#version 410 core", "#define VARIANT", "#define SKINNED", "void main() {}"]
+    );
+}
+
+#[test]
+fn default_features_only_define_variant() {
+    let source = ShaderFeatures::default().apply("#version 410 core\n");
+    assert!(source.contains("#define VARIANT"));
+    assert!(!source.contains("SKINNED"));
+    assert!(!source.contains("ALPHA_TEST"));
+}
+
+#[test]
+fn the_lighting_shader_can_be_multisampled() {
+    let source = shader::insert_defines(shader::DEFERRED_PASS_FRAG, "#define SAMPLES 4\n");
+    let mut lines = source.lines();
+    assert!(lines.next().unwrap().starts_with("#version"));
+    assert_eq!(lines.next(), Some("#define SAMPLES 4"));
+    assert!(source.contains("sampler2DMS"));
+
+    assert_eq!(shader::insert_defines("void main() {}", ""), "void main() {}");
+}
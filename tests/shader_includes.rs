@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use scene_editor::shader::{
+    self, read_include, resolve_includes, IncludeError, Severity, ShaderDiagnostic,
+};
+
+fn library(name: &str) -> Option<String> {
+    let source = match name {
+        "a.glsl" => "float a() { return 1.0; }",
+        "b.glsl" => "#include \"a.glsl\"\nfloat b() { return a(); }",
+        "loop_a.glsl" => "#include \"loop_b.glsl\"",
+        "loop_b.glsl" => "// comment\n#include \"loop_a.glsl\"",
+        "broken.glsl" => "float broken() {}\n#include \"missing.glsl\"",
+        _ => return None,
+    };
+    Some(source.to_owned())
+}
+
+fn error(line: u32, message: &str) -> ShaderDiagnostic {
+    ShaderDiagnostic {
+        severity: Severity::Error,
+        line: Some(line),
+        column: None,
+        message: message.to_owned(),
+    }
+}
+
+#[test]
+fn includes_are_pasted_in_once() {
+    let source =
+        "#version 410 core\n#include \"b.glsl\"\n  # include <a.glsl> // again\nvoid main() {}\n";
+    let resolved = resolve_includes(source, library).unwrap();
+    assert_eq!(
+        resolved.source,
+        "#version 410 core\nfloat a() { return 1.0; }\nfloat b() { return a(); }\nvoid main() {}\n"
+    );
+    assert_eq!(resolved.files, ["b.glsl", "a.glsl"]);
+}
+
+#[test]
+fn compiler_messages_point_at_the_include() {
+    let source = "#version 410 core\n#include \"b.glsl\"\nvoid main() { x; }\n";
+    let resolved = resolve_includes(source, library).unwrap();
+    let remapped = resolved.remap(vec![error(2, "a broke"), error(3, "b broke"), error(4, "x")]);
+    assert_eq!(
+        remapped,
+        [error(2, "a.glsl:1: a broke"), error(2, "b.glsl:2: b broke"), error(3, "x")]
+    );
+}
+
+#[test]
+fn include_cycles_are_errors() {
+    let error = resolve_includes("\n#include \"loop_a.glsl\"", library).unwrap_err();
+    assert_eq!(
+        error,
+        IncludeError {
+            line: 2,
+            location: Some(("loop_b.glsl".to_owned(), 2)),
+            message: "include cycle: loop_a.glsl -> loop_b.glsl -> loop_a.glsl".to_owned(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "loop_b.glsl:2: include cycle: loop_a.glsl -> loop_b.glsl -> loop_a.glsl \
+         (included from line 2)"
+    );
+}
+
+#[test]
+fn missing_includes_are_reported_where_they_are_included() {
+    let error = resolve_includes("#include \"broken.glsl\"", library).unwrap_err();
+    assert_eq!(error.line, 1);
+    assert_eq!(error.location, Some(("broken.glsl".to_owned(), 2)));
+    assert_eq!(
+        error.diagnostic(),
+        self::error(1, "broken.glsl:2: could not find \"missing.glsl\" to include")
+    );
+
+    let error = resolve_includes("#include lighting.glsl", library).unwrap_err();
+    assert_eq!(error.to_string(), "1: expected #include \"file\", found `#include lighting.glsl`");
+}
+
+#[test]
+fn includes_stay_in_the_include_folder() {
+    for name in ["../secret.glsl", "/etc/passwd"] {
+        let error = resolve_includes(&format!("#include \"{name}\""), library).unwrap_err();
+        assert!(error.message.contains("not a path inside the include folder"), "{name}");
+    }
+}
+
+#[test]
+fn builtin_shaders_use_the_library() {
+    let missing = Path::new("no/such/folder");
+    assert!(read_include(missing, "lighting.glsl").unwrap().contains("calculate_point_light"));
+    assert_eq!(read_include(missing, "missing.glsl"), None);
+
+    for source in [shader::GEOMETRY_PASS_FRAG, shader::DEFERRED_PASS_FRAG] {
+        let resolved = resolve_includes(source, |name| read_include(missing, name)).unwrap();
+        assert!(!resolved.files.is_empty());
+        assert!(!resolved.source.contains("#include"));
+    }
+}
@@ -0,0 +1,78 @@
+use scene_editor::shader::{GEOMETRY_PASS_FRAG, GEOMETRY_PASS_VERT};
+use scene_editor::shader_limits::{check_limits, ShaderLimits};
+
+fn lines_with_errors(source: &str) -> Vec<u32> {
+    check_limits(source, &ShaderLimits::default()).iter().filter_map(|d| d.line).collect()
+}
+
+#[test]
+fn default_shaders_are_within_limits() {
+    assert!(lines_with_errors(GEOMETRY_PASS_VERT).is_empty());
+    assert!(lines_with_errors(GEOMETRY_PASS_FRAG).is_empty());
+}
+
+#[test]
+fn constant_loops_are_allowed() {
+    let source = "#define SAMPLES 16\n\
+                  const int TAPS = 4;\n\
+                  void main() {\n\
+                      for (int i = 0; i < SAMPLES; i++) {\n\
+                          for (int j = TAPS; j >= 1; j -= 1) { sum += texture(tex, uv); }\n\
+                      }\n\
+                  }\n";
+    assert!(lines_with_errors(source).is_empty());
+}
+
+#[test]
+fn while_and_do_loops_are_rejected() {
+    let source = "void main() {\n\
+                      while (x < 1.0) { x += 0.1; }\n\
+                      do { x -= 0.1; } while (x > 0.0);\n\
+                  }\n";
+    assert_eq!(lines_with_errors(source), [2, 3]);
+}
+
+#[test]
+fn loops_without_constant_bounds_are_rejected() {
+    let source = "uniform int count;\n\
+                  void main() {\n\
+                      for (int i = 0; i < count; i++) {}\n\
+                      for (int i = 0; ; i++) {}\n\
+                  }\n";
+    assert_eq!(lines_with_errors(source), [3, 4]);
+}
+
+#[test]
+fn nested_loops_count_towards_the_limit() {
+    let source = "void main() {\n\
+                      for (int i = 0; i < 64; i++) {\n\
+                          for (int j = 0; j < 64; j++) {}\n\
+                      }\n\
+                  }\n";
+    assert_eq!(lines_with_errors(source), [3]);
+
+    let limits = ShaderLimits { max_loop_iterations: 4096, ..Default::default() };
+    assert!(check_limits(source, &limits).is_empty());
+}
+
+#[test]
+fn texture_reads_in_loops_count_towards_the_limit() {
+    let source = "void main() {\n\
+                      // texture(tex, uv) in a comment does not count\n\
+                      for (int i = 0; i < 100; i++) {\n\
+                          color += texture(tex, uv + offsets[i]);\n\
+                      }\n\
+                  }\n";
+    assert_eq!(lines_with_errors(source), [4]);
+}
+
+#[test]
+fn only_allowed_extensions_can_be_enabled() {
+    let source = "#version 410 core\n\
+                  #extension GL_ARB_separate_shader_objects : enable\n\
+                  #extension GL_ARB_shader_image_load_store : require\n";
+    let diagnostics = check_limits(source, &ShaderLimits::default());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, Some(3));
+    assert!(diagnostics[0].message.contains("GL_ARB_shader_image_load_store"));
+}
@@ -0,0 +1,64 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use scene_editor::shader_reload::ShaderWatcher;
+
+use crate::common::temp_dir;
+
+mod common;
+
+/// A shader folder with one shader and one include, as the watcher expects
+fn shader_dir(name: &str) -> PathBuf {
+    let dir = temp_dir("shader_reload", name);
+    fs::create_dir_all(dir.join("include")).unwrap();
+    fs::write(dir.join("depth_vert.glsl"), "void main() {}").unwrap();
+    fs::write(dir.join("include").join("lighting.glsl"), "").unwrap();
+    dir
+}
+
+fn touch(path: PathBuf) {
+    let later = SystemTime::now() + Duration::from_secs(10);
+    File::options().write(true).open(path).unwrap().set_modified(later).unwrap();
+}
+
+#[test]
+fn changed_shader_files_are_noticed_once() {
+    let dir = shader_dir("changed");
+    let mut watcher = ShaderWatcher::new(&dir);
+    assert!(!watcher.check());
+
+    touch(dir.join("depth_vert.glsl"));
+    assert!(watcher.check());
+    assert!(!watcher.check());
+
+    touch(dir.join("include").join("lighting.glsl"));
+    assert!(watcher.check());
+}
+
+#[test]
+fn added_and_removed_shader_files_are_noticed() {
+    let dir = shader_dir("added");
+    let mut watcher = ShaderWatcher::new(&dir);
+
+    fs::write(dir.join("include").join("shadows.glsl"), "").unwrap();
+    assert!(watcher.check());
+    fs::remove_file(dir.join("depth_vert.glsl")).unwrap();
+    assert!(watcher.check());
+
+    // Only shader files are watched
+    fs::write(dir.join("notes.txt"), "").unwrap();
+    assert!(!watcher.check());
+}
+
+#[test]
+fn polling_waits_for_the_interval() {
+    let dir = shader_dir("interval");
+    let mut watcher = ShaderWatcher::new(&dir).with_interval(Duration::from_secs(3600));
+    touch(dir.join("depth_vert.glsl"));
+    assert!(!watcher.poll());
+
+    let mut watcher = ShaderWatcher::new(&dir).with_interval(Duration::ZERO);
+    touch(dir.join("depth_vert.glsl"));
+    assert!(watcher.poll());
+}
@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use scene_editor::components::ShaderParams;
+use scene_editor::scene::Scene;
+use scene_editor::shader::UniformValue;
+
+#[test]
+fn sync_keeps_values_of_unchanged_uniforms() {
+    let mut params = ShaderParams::default();
+    params.sync(vec![
+        ("roughness".to_owned(), UniformValue::Float(0.5)),
+        ("tint".to_owned(), UniformValue::Vec3([1.0, 1.0, 1.0])),
+        ("steps".to_owned(), UniformValue::Int(4)),
+    ]);
+    params.values.insert("roughness".to_owned(), UniformValue::Float(0.8));
+    params.values.insert("tint".to_owned(), UniformValue::Vec3([1.0, 0.0, 0.0]));
+
+    // `tint` became a vec4 and `steps` was removed from the shader
+    params.sync(vec![
+        ("roughness".to_owned(), UniformValue::Float(0.5)),
+        ("tint".to_owned(), UniformValue::Vec4([1.0, 1.0, 1.0, 1.0])),
+        ("wireframe".to_owned(), UniformValue::Bool(false)),
+    ]);
+
+    let expected = BTreeMap::from([
+        ("roughness".to_owned(), UniformValue::Float(0.8)),
+        ("tint".to_owned(), UniformValue::Vec4([1.0, 1.0, 1.0, 1.0])),
+        ("wireframe".to_owned(), UniformValue::Bool(false)),
+    ]);
+    assert_eq!(params.values, expected);
+}
+
+#[test]
+fn uniform_values_round_trip_through_ron() {
+    let mut scene: Scene =
+        Scene::new().spawn_model("cube").custom_shader("vert source", "frag source").into();
+    let params = &mut scene.entities[0].custom_shader.as_mut().unwrap().params;
+    params.insert("tint".to_owned(), UniformValue::Vec3([0.2, 0.4, 0.6]));
+    params.insert("steps".to_owned(), UniformValue::Int(8));
+
+    let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+    let shader = loaded.entities[0].custom_shader.as_ref().unwrap();
+    assert_eq!(shader.params.get("tint"), Some(&UniformValue::Vec3([0.2, 0.4, 0.6])));
+    assert_eq!(shader.params.get("steps"), Some(&UniformValue::Int(8)));
+}
+
+#[test]
+fn scenes_without_uniform_values_still_load() {
+    let ron = r#"(entities: [(model: "cube", custom_shader: Some((vert: "v", frag: "f")))])"#;
+    let scene = Scene::from_ron(ron).unwrap();
+    assert!(scene.entities[0].custom_shader.as_ref().unwrap().params.is_empty());
+}
@@ -0,0 +1,25 @@
+use scene_editor::snapping::{self, Snapping};
+
+#[test]
+fn values_round_to_the_nearest_step() {
+    assert_eq!(snapping::snap(0.3, 0.5), 0.5);
+    assert_eq!(snapping::snap(0.2, 0.5), 0.0);
+    assert_eq!(snapping::snap(-1.3, 0.5), -1.5);
+    assert_eq!(snapping::snap(2.0, 0.5), 2.0);
+}
+
+#[test]
+fn steps_that_are_not_positive_leave_values_as_they_are() {
+    assert_eq!(snapping::snap(0.3, 0.0), 0.3);
+    assert_eq!(snapping::snap(0.3, -1.0), 0.3);
+}
+
+#[test]
+fn each_kind_of_transform_has_its_own_step() {
+    let snapping = Snapping { position: 0.5, rotation: 15.0, scale: 0.25 };
+
+    assert_eq!(snapping.position(1.2), 1.0);
+    assert_eq!(snapping.rotation(50.0), 45.0);
+    assert_eq!(snapping.rotation(-8.0), -15.0);
+    assert_eq!(snapping.scale(1.1), 1.0);
+}
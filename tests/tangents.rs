@@ -0,0 +1,52 @@
+use nalgebra_glm as glm;
+use scene_editor::asset_cache::DerivedCache;
+use scene_editor::tangents::generate_tangents;
+
+#[test]
+fn tangents_follow_the_u_direction() {
+    // A quad facing +Z with u along +X and v along +Y
+    let positions = [
+        glm::vec3(0.0, 0.0, 0.0),
+        glm::vec3(1.0, 0.0, 0.0),
+        glm::vec3(1.0, 1.0, 0.0),
+        glm::vec3(0.0, 1.0, 0.0),
+    ];
+    let normals = [glm::vec3(0.0, 0.0, 1.0); 4];
+    let texture_coords =
+        [glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(1.0, 1.0), glm::vec2(0.0, 1.0)];
+    let indices = [0, 1, 2, 0, 2, 3];
+
+    let tangents = generate_tangents(&positions, &normals, &texture_coords, &indices).unwrap();
+    assert_eq!(tangents.len(), 4);
+    for tangent in tangents {
+        assert!(glm::distance(&tangent.xyz(), &glm::vec3(1.0, 0.0, 0.0)) < 1e-4);
+        assert_eq!(tangent.w, 1.0);
+    }
+}
+
+#[test]
+fn missing_texture_coordinates_give_no_tangents() {
+    let positions = [glm::Vec3::zeros(); 3];
+    let normals = [glm::vec3(0.0, 0.0, 1.0); 3];
+    assert!(generate_tangents(&positions, &normals, &[], &[0, 1, 2]).is_none());
+}
+
+#[test]
+fn cached_data_is_reused_until_the_source_changes() {
+    let dir = std::env::temp_dir().join("scene_editor_derived_cache");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("model.obj");
+    std::fs::write(&source, "v 0 0 0").unwrap();
+    let cache = DerivedCache::new(dir.join("cache"));
+
+    let first =
+        cache.get_or_insert_with(&source, "tangents", || vec![glm::vec4(1.0, 0.0, 0.0, 1.0)]);
+    let cached: Vec<glm::Vec4> =
+        cache.get_or_insert_with(&source, "tangents", || panic!("should be cached"));
+    assert_eq!(cached, first);
+
+    std::fs::write(&source, "v 0 0 0\nv 1 0 0").unwrap();
+    let changed = cache.get_or_insert_with(&source, "tangents", Vec::<glm::Vec4>::new);
+    assert!(changed.is_empty());
+}
@@ -0,0 +1,215 @@
+use nalgebra_glm as glm;
+use scene_editor::scene::{Scene, SceneTerrain};
+use scene_editor::terrain::{Brush, BrushTool, Heightmap, Terrain, CHUNK_QUADS};
+
+fn brush(tool: BrushTool) -> Brush {
+    Brush { tool, radius: 2.0, strength: 1.0, height: 0.0, layer: 1 }
+}
+
+/// Terrain of 8 quads a side, one unit apart, with its dirty chunks taken
+fn small_terrain() -> Terrain {
+    let mut terrain = Terrain::flat(8, 8.0);
+    terrain.take_dirty_chunks();
+    terrain
+}
+
+#[test]
+fn flat_terrains_have_a_vertex_per_grid_point() {
+    let terrain = Terrain::flat(70, 35.0);
+    assert_eq!(terrain.side(), 71);
+    assert_eq!(terrain.heights.len(), 71 * 71);
+    assert!(terrain.heights.iter().all(|&h| h == 0.0));
+    assert!(terrain.splat.iter().all(|&w| w == [1.0, 0.0, 0.0, 0.0]));
+    assert_eq!(terrain.spacing(), 0.5);
+
+    // 70 quads need three chunks a side, the last one smaller
+    assert_eq!(terrain.chunks_per_side(), 3);
+    assert_eq!(terrain.chunk_count(), 9);
+}
+
+#[test]
+fn new_terrains_have_every_chunk_dirty() {
+    let mut terrain = Terrain::flat(CHUNK_QUADS * 2, 16.0);
+    let mut dirty = terrain.take_dirty_chunks();
+    dirty.sort();
+    assert_eq!(dirty, [0, 1, 2, 3]);
+    assert!(terrain.take_dirty_chunks().is_empty());
+}
+
+#[test]
+fn chunks_share_the_vertices_along_their_edges() {
+    let terrain = Terrain::flat(CHUNK_QUADS + 4, 10.0);
+
+    let (first, weights) = terrain.chunk_mesh(0);
+    let side = CHUNK_QUADS as usize + 1;
+    assert_eq!(first.vertex_count(), side * side);
+    assert_eq!(weights.len(), first.vertex_count());
+    assert_eq!(first.triangle_count(), (CHUNK_QUADS * CHUNK_QUADS * 2) as usize);
+
+    // The chunk to the right is 4 quads wide, and starts where the first one ends
+    let (second, _) = terrain.chunk_mesh(1);
+    assert_eq!(second.vertex_count(), 5 * side);
+    assert_eq!(second.positions[0], first.positions[side - 1]);
+    assert!(second.indices.iter().all(|&i| (i as usize) < second.vertex_count()));
+}
+
+#[test]
+fn chunk_triangles_face_up() {
+    let terrain = Terrain::flat(4, 4.0);
+    let (mesh, _) = terrain.chunk_mesh(0);
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+        assert!(glm::cross(&(b - a), &(c - a)).y > 0.0);
+    }
+}
+
+#[test]
+fn heights_are_interpolated_inside_the_terrain() {
+    let heights = vec![0.0, 2.0, 2.0, 4.0];
+    let terrain = Terrain::from_parts(1, 2.0, heights, Vec::new()).unwrap();
+
+    assert_eq!(terrain.height_at(-1.0, -1.0), Some(0.0));
+    assert_eq!(terrain.height_at(1.0, 1.0), Some(4.0));
+    assert_eq!(terrain.height_at(0.0, 0.0), Some(2.0));
+    assert_eq!(terrain.height_at(0.0, -1.0), Some(1.0));
+    assert_eq!(terrain.height_at(1.5, 0.0), None);
+    assert_eq!(terrain.height_at(0.0, -1.1), None);
+}
+
+#[test]
+fn raising_and_lowering_fade_out_toward_the_edge() {
+    let mut terrain = small_terrain();
+    terrain.sculpt(&brush(BrushTool::Raise), glm::vec2(0.0, 0.0), 0.5);
+
+    let center = terrain.height(4, 4);
+    assert_eq!(center, 0.5);
+    assert!(terrain.height(5, 4) > 0.0 && terrain.height(5, 4) < center);
+    assert_eq!(terrain.height(6, 4), 0.0);
+    assert_eq!(terrain.height(0, 0), 0.0);
+
+    terrain.sculpt(&brush(BrushTool::Lower), glm::vec2(0.0, 0.0), 1.0);
+    assert_eq!(terrain.height(4, 4), -0.5);
+}
+
+#[test]
+fn scaled_terrains_are_sculpted_in_world_units() {
+    let mut terrain = small_terrain();
+    // Twice as wide along X and four times as tall, so the brush covers fewer columns than rows
+    let scale = glm::vec3(2.0, 4.0, 1.0);
+    terrain.sculpt_scaled(&brush(BrushTool::Raise), glm::vec2(0.0, 0.0), scale, 0.5);
+
+    assert_eq!(terrain.height(4, 4), 0.125);
+    assert_eq!(terrain.height(5, 4), 0.0);
+    assert!(terrain.height(4, 5) > 0.0);
+    assert_eq!(terrain.height(4, 6), 0.0);
+
+    // A flattened scale leaves the terrain alone
+    terrain.sculpt_scaled(&brush(BrushTool::Raise), glm::vec2(0.0, 0.0), glm::Vec3::zeros(), 1.0);
+    assert_eq!(terrain.height(4, 4), 0.125);
+}
+
+#[test]
+fn flattening_moves_toward_the_brush_height() {
+    let heights = vec![1.0; 9 * 9];
+    let mut terrain = Terrain::from_parts(8, 8.0, heights, Vec::new()).unwrap();
+    let flatten = Brush { height: 3.0, strength: 0.5, ..brush(BrushTool::Flatten) };
+    terrain.sculpt(&flatten, glm::vec2(0.0, 0.0), 1.0);
+    assert_eq!(terrain.height(4, 4), 2.0);
+
+    // Brushes never overshoot their target
+    let flatten = Brush { strength: 100.0, ..flatten };
+    terrain.sculpt(&flatten, glm::vec2(0.0, 0.0), 1.0);
+    assert_eq!(terrain.height(4, 4), 3.0);
+}
+
+#[test]
+fn smoothing_evens_out_bumps() {
+    let mut heights = vec![0.0; 9 * 9];
+    heights[4 * 9 + 4] = 4.0;
+    let mut terrain = Terrain::from_parts(8, 8.0, heights, Vec::new()).unwrap();
+    let smooth = Brush { strength: 100.0, ..brush(BrushTool::Smooth) };
+    terrain.sculpt(&smooth, glm::vec2(0.0, 0.0), 1.0);
+
+    assert_eq!(terrain.height(4, 4), 0.0);
+    // The neighbors are smoothed from the heights before the brush, so they take in the bump
+    assert!(terrain.height(5, 4) > 0.0);
+}
+
+#[test]
+fn painting_keeps_the_weights_summing_to_one() {
+    let mut terrain = small_terrain();
+    let paint = Brush { layer: 2, ..brush(BrushTool::Paint) };
+    for _ in 0..5 {
+        terrain.sculpt(&paint, glm::vec2(0.0, 0.0), 0.3);
+    }
+
+    let center = terrain.splat[4 * 9 + 4];
+    assert!(center[2] > center[0]);
+    for weights in &terrain.splat {
+        assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+    }
+    assert_eq!(terrain.splat[0], [1.0, 0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn sculpting_only_dirties_the_chunks_it_touches() {
+    let mut terrain = Terrain::flat(CHUNK_QUADS * 2, CHUNK_QUADS as f32 * 2.0);
+    terrain.take_dirty_chunks();
+
+    // Near the -X, -Z corner, inside the first chunk
+    let corner = -(CHUNK_QUADS as f32) + 5.0;
+    terrain.sculpt(&brush(BrushTool::Raise), glm::vec2(corner, corner), 0.1);
+    assert_eq!(terrain.take_dirty_chunks(), [0]);
+
+    // On the edge between the chunks, where every chunk shares the vertices or their normals
+    terrain.sculpt(&brush(BrushTool::Raise), glm::vec2(0.0, 0.0), 0.1);
+    let mut dirty = terrain.take_dirty_chunks();
+    dirty.sort();
+    assert_eq!(dirty, [0, 1, 2, 3]);
+
+    // Outside the terrain
+    terrain.sculpt(&brush(BrushTool::Raise), glm::vec2(500.0, 0.0), 0.1);
+    assert!(terrain.take_dirty_chunks().is_empty());
+}
+
+#[test]
+fn parts_that_do_not_fit_the_grid_are_errors() {
+    assert!(Terrain::from_parts(2, 1.0, vec![0.0; 8], Vec::new()).is_err());
+    assert!(Terrain::from_parts(0, 1.0, vec![0.0], Vec::new()).is_err());
+    assert!(Terrain::from_parts(1, 1.0, vec![0.0; 4], vec![[1.0, 0.0, 0.0, 0.0]; 3]).is_err());
+    assert!(Terrain::from_parts(1, 1.0, vec![0.0; 4], vec![[0.0, 1.0, 0.0, 0.0]; 4]).is_ok());
+}
+
+#[test]
+fn heightmaps_are_sampled_between_pixels() {
+    let heightmap = Heightmap { width: 2, height: 2, values: vec![0.0, 1.0, 0.5, 0.5] };
+    assert_eq!(heightmap.sample(0.0, 0.0), 0.0);
+    assert_eq!(heightmap.sample(1.0, 0.0), 1.0);
+    assert_eq!(heightmap.sample(0.5, 0.0), 0.5);
+    assert_eq!(heightmap.sample(0.0, 1.0), 0.5);
+    assert_eq!(heightmap.sample(-1.0, 2.0), 0.5);
+
+    let terrain = Terrain::from_heightmap(&heightmap, 2, 4.0, 10.0);
+    assert_eq!(terrain.height(0, 0), 0.0);
+    assert_eq!(terrain.height(2, 0), 10.0);
+    assert_eq!(terrain.height(1, 0), 5.0);
+}
+
+#[test]
+fn terrains_round_trip_through_scenes() {
+    let terrain = SceneTerrain {
+        quads: 1,
+        size: 4.0,
+        heights: vec![0.0, 1.5, -2.0, 3.25],
+        splat: vec![[0.5, 0.5, 0.0, 0.0]; 4],
+        layers: [Some("grass".to_owned()), None, Some("rock".to_owned()), None],
+        hidden: true,
+        ..Default::default()
+    };
+    let scene = Scene { terrains: vec![terrain], ..Default::default() };
+
+    let ron = scene.to_ron().unwrap();
+    assert_eq!(Scene::from_ron(&ron).unwrap(), scene);
+    // Scenes without terrains are saved as before
+    assert!(!Scene::default().to_ron().unwrap().contains("terrains"));
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use scene_editor::texture_import::{
+    self, Channel, Filter, TextureQuality, TextureSettings, WrapMode,
+};
+
+use crate::common::temp_dir;
+
+mod common;
+
+fn pixel_art() -> TextureSettings {
+    TextureSettings {
+        srgb: true,
+        wrap: WrapMode::ClampToEdge,
+        filter: Filter::Nearest,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn settings_are_kept_next_to_the_texture() {
+    let path = Path::new("res/textures/bricks.png");
+    assert_eq!(TextureSettings::path_for(path), Path::new("res/textures/bricks.import"));
+}
+
+#[test]
+fn settings_round_trip_through_files() {
+    let dir = temp_dir("texture_import", "round_trip");
+    let texture = dir.join("sprite.png");
+    assert_eq!(TextureSettings::load_for(&texture).unwrap(), TextureSettings::default());
+
+    pixel_art().save_for(&texture).unwrap();
+    assert!(dir.join("sprite.import").exists());
+    assert_eq!(TextureSettings::load_for(&texture).unwrap(), pixel_art());
+
+    // Going back to the defaults removes the file
+    TextureSettings::default().save_for(&texture).unwrap();
+    assert!(!dir.join("sprite.import").exists());
+    assert_eq!(TextureSettings::load_for(&texture).unwrap(), TextureSettings::default());
+}
+
+#[test]
+fn missing_settings_take_the_defaults() {
+    let settings = TextureSettings::from_ron("(srgb: true, anisotropy: 8.0)").unwrap();
+    assert!(settings.srgb);
+    assert_eq!(settings.anisotropy, 8.0);
+    assert_eq!(settings.wrap, WrapMode::Repeat);
+    assert_eq!(settings.filter, Filter::Bilinear);
+
+    assert!(TextureSettings::from_ron("(wrap: Sideways)").is_err());
+}
+
+#[test]
+fn quality_limits_the_import_settings() {
+    let sharp =
+        TextureSettings { filter: Filter::Trilinear, anisotropy: 16.0, ..Default::default() };
+    assert_eq!(TextureQuality::High.limit(sharp), sharp);
+    assert_eq!(TextureQuality::Medium.limit(sharp), TextureSettings { anisotropy: 4.0, ..sharp });
+    assert_eq!(
+        TextureQuality::Low.limit(sharp),
+        TextureSettings { filter: Filter::Bilinear, anisotropy: 1.0, ..sharp }
+    );
+
+    // Pixel art stays sharp, and the other settings are kept
+    assert_eq!(TextureQuality::Low.limit(pixel_art()), pixel_art());
+}
+
+#[test]
+fn mip_chains_go_down_to_one_texel() {
+    assert_eq!(texture_import::mip_levels(1, 1), 1);
+    assert_eq!(texture_import::mip_levels(1024, 1024), 11);
+    assert_eq!(texture_import::mip_levels(1000, 16), 10);
+    assert_eq!(texture_import::mip_levels(0, 0), 1);
+}
+
+#[test]
+fn channels_are_shown_as_gray() {
+    let pixels = [10, 20, 30, 40, 50, 60, 70, 80];
+    assert_eq!(
+        texture_import::isolate_channel(&pixels, Channel::Rgb),
+        [10, 20, 30, 255, 50, 60, 70, 255]
+    );
+    assert_eq!(
+        texture_import::isolate_channel(&pixels, Channel::Green),
+        [20, 20, 20, 255, 60, 60, 60, 255]
+    );
+    assert_eq!(
+        texture_import::isolate_channel(&pixels, Channel::Alpha),
+        [40, 40, 40, 255, 80, 80, 80, 255]
+    );
+}
@@ -0,0 +1,84 @@
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use scene_editor::components::{GlobalTransform, Parent, Position, Scale, TransformBundle};
+use scene_editor::transform::update_global_transforms;
+
+fn at(x: f32, y: f32, z: f32) -> TransformBundle {
+    TransformBundle { position: Position::new(x, y, z), ..Default::default() }
+}
+
+fn origin(world: &World, entity: Entity) -> glm::Vec3 {
+    let model = world.get::<GlobalTransform>(entity).unwrap().0;
+    glm::vec4_to_vec3(&(model * glm::vec4(0.0, 0.0, 0.0, 1.0)))
+}
+
+fn setup() -> (World, Schedule) {
+    let mut schedule = Schedule::default();
+    schedule.add_system(update_global_transforms);
+    (World::new(), schedule)
+}
+
+#[test]
+fn children_follow_their_parents() {
+    let (mut world, mut schedule) = setup();
+    let parent = world.spawn(at(1.0, 0.0, 0.0)).id();
+    let child = world.spawn((at(0.0, 2.0, 0.0), Parent(parent))).id();
+    let grandchild = world.spawn((at(0.0, 0.0, 3.0), Parent(child))).id();
+
+    schedule.run(&mut world);
+    assert_eq!(origin(&world, grandchild), glm::vec3(1.0, 2.0, 3.0));
+
+    // Only the parent changed, and the entities below it move along
+    world.get_mut::<Position>(parent).unwrap().x = 5.0;
+    *world.get_mut::<Scale>(parent).unwrap() = Scale::new(2.0, 2.0, 2.0);
+    schedule.run(&mut world);
+    assert_eq!(origin(&world, child), glm::vec3(5.0, 4.0, 0.0));
+    assert_eq!(origin(&world, grandchild), glm::vec3(5.0, 4.0, 6.0));
+
+    world.entity_mut(child).remove::<Parent>();
+    schedule.run(&mut world);
+    assert_eq!(origin(&world, grandchild), glm::vec3(0.0, 2.0, 3.0));
+}
+
+#[test]
+fn children_of_despawned_parents_stay_in_place() {
+    let (mut world, mut schedule) = setup();
+    let parent = world.spawn(at(1.0, 0.0, 0.0)).id();
+    let child = world.spawn((at(0.0, 2.0, 0.0), Parent(parent))).id();
+    let grandchild = world.spawn((at(0.0, 0.0, 3.0), Parent(child))).id();
+    schedule.run(&mut world);
+
+    world.despawn(parent);
+    schedule.run(&mut world);
+    assert!(world.get::<Parent>(child).is_none());
+    assert_eq!(world.get::<Position>(child), Some(&Position::new(1.0, 2.0, 0.0)));
+    assert_eq!(world.get::<Parent>(grandchild), Some(&Parent(child)));
+
+    // Moving the child afterwards moves it from where it was left
+    world.get_mut::<Position>(child).unwrap().y = 4.0;
+    schedule.run(&mut world);
+    assert_eq!(origin(&world, child), glm::vec3(1.0, 4.0, 0.0));
+    assert_eq!(origin(&world, grandchild), glm::vec3(1.0, 4.0, 3.0));
+}
+
+#[test]
+fn unchanged_entities_are_left_alone() {
+    let (mut world, mut schedule) = setup();
+    let entity = world.spawn(at(1.0, 0.0, 0.0)).id();
+    schedule.run(&mut world);
+    world.clear_trackers();
+
+    schedule.run(&mut world);
+    assert!(!world.get_mut::<GlobalTransform>(entity).unwrap().is_changed());
+}
+
+#[test]
+fn a_cycle_of_parents_does_not_hang() {
+    let (mut world, mut schedule) = setup();
+    let a = world.spawn(at(1.0, 0.0, 0.0)).id();
+    let b = world.spawn((at(1.0, 0.0, 0.0), Parent(a))).id();
+    world.entity_mut(a).insert(Parent(b));
+
+    schedule.run(&mut world);
+    assert!(origin(&world, a).x >= 1.0);
+}
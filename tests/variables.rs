@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use scene_editor::components::{PointLight, Position, Scale, ShaderParams};
+use scene_editor::scene::Scene;
+use scene_editor::shader::UniformValue;
+use scene_editor::variables::{self, BindingTarget, Bindings, SceneVariables, VariableValue};
+
+fn light() -> PointLight {
+    PointLight::new(glm::Vec3::zeros(), glm::Vec3::zeros(), glm::Vec3::zeros(), 1.0, 0.1, 0.01)
+}
+
+fn apply(world: &mut World) {
+    let mut query = world.query::<(
+        &Bindings,
+        Option<&mut Position>,
+        Option<&mut Scale>,
+        Option<&mut PointLight>,
+        Option<&mut ShaderParams>,
+    )>();
+    world.resource_scope(|world, variables: Mut<SceneVariables>| {
+        for fields in query.iter_mut(world) {
+            variables::apply(&variables, fields);
+        }
+    });
+}
+
+#[test]
+fn variables_and_bindings_round_trip_through_scene_files() {
+    let accent = VariableValue::Color(glm::vec3(1.0, 0.5, 0.0));
+    let scene = Scene::new()
+        .variable("accent_color", accent)
+        .variable("falloff", VariableValue::Float(0.2))
+        .spawn_model("lamp")
+        .point_light(light())
+        .bind(BindingTarget::LightDiffuse, "accent_color")
+        .bind(BindingTarget::Uniform("tint".to_owned()), "accent_color")
+        .spawn_model("cube")
+        .finish();
+
+    let ron = scene.to_ron().unwrap();
+    assert!(ron.contains("\"accent_color\": Color("), "{ron}");
+    assert!(ron.contains("Uniform(\"tint\"): \"accent_color\""), "{ron}");
+    assert_eq!(ron.matches("bindings").count(), 1, "{ron}");
+
+    let loaded = Scene::from_ron(&ron).unwrap();
+    assert_eq!(loaded.variables["accent_color"], accent);
+    assert_eq!(loaded.variables["falloff"], VariableValue::Float(0.2));
+    assert_eq!(loaded.entities[0].bindings, scene.entities[0].bindings);
+    assert!(loaded.entities[1].bindings.is_empty());
+}
+
+#[test]
+fn changing_a_variable_updates_every_bound_field() {
+    let mut world = World::new();
+    let accent = glm::vec3(1.0, 0.5, 0.0);
+    world.insert_resource(SceneVariables {
+        values: BTreeMap::from([
+            ("accent_color".to_owned(), VariableValue::Color(accent)),
+            ("falloff".to_owned(), VariableValue::Float(0.2)),
+            ("offset".to_owned(), VariableValue::Vector(glm::vec3(1.0, 2.0, 3.0))),
+        ]),
+    });
+    let lamp = world
+        .spawn((
+            Position::default(),
+            light(),
+            Bindings {
+                targets: BTreeMap::from([
+                    (BindingTarget::LightDiffuse, "accent_color".to_owned()),
+                    (BindingTarget::LightLinear, "falloff".to_owned()),
+                    (BindingTarget::Position, "offset".to_owned()),
+                ]),
+            },
+        ))
+        .id();
+    let material = world
+        .spawn((
+            ShaderParams {
+                values: BTreeMap::from([
+                    ("tint".to_owned(), UniformValue::Vec4([0.0; 4])),
+                    ("rim".to_owned(), UniformValue::Vec3([0.0; 3])),
+                ]),
+            },
+            Bindings {
+                targets: BTreeMap::from([
+                    (BindingTarget::Uniform("tint".to_owned()), "accent_color".to_owned()),
+                    (BindingTarget::Uniform("rim".to_owned()), "accent_color".to_owned()),
+                ]),
+            },
+        ))
+        .id();
+
+    apply(&mut world);
+    let light = world.get::<PointLight>(lamp).unwrap();
+    assert_eq!((light.diffuse, light.linear), (accent, 0.2));
+    assert_eq!(glm::Vec3::from(*world.get::<Position>(lamp).unwrap()), glm::vec3(1.0, 2.0, 3.0));
+    let params = &world.get::<ShaderParams>(material).unwrap().values;
+    assert_eq!(params["tint"], UniformValue::Vec4([1.0, 0.5, 0.0, 1.0]));
+    assert_eq!(params["rim"], UniformValue::Vec3([1.0, 0.5, 0.0]));
+
+    let green = glm::vec3(0.0, 1.0, 0.0);
+    world
+        .resource_mut::<SceneVariables>()
+        .values
+        .insert("accent_color".to_owned(), VariableValue::Color(green));
+    apply(&mut world);
+    assert_eq!(world.get::<PointLight>(lamp).unwrap().diffuse, green);
+    let params = &world.get::<ShaderParams>(material).unwrap().values;
+    assert_eq!(params["tint"], UniformValue::Vec4([0.0, 1.0, 0.0, 1.0]));
+}
+
+#[test]
+fn bindings_of_the_wrong_type_or_to_missing_variables_are_ignored() {
+    let mut world = World::new();
+    world.insert_resource(SceneVariables {
+        values: BTreeMap::from([("falloff".to_owned(), VariableValue::Float(0.2))]),
+    });
+    let lamp = world
+        .spawn((
+            light(),
+            Bindings {
+                targets: BTreeMap::from([
+                    (BindingTarget::LightDiffuse, "falloff".to_owned()),
+                    (BindingTarget::LightLinear, "removed".to_owned()),
+                ]),
+            },
+        ))
+        .id();
+
+    apply(&mut world);
+    let light = world.get::<PointLight>(lamp).unwrap();
+    assert_eq!((light.diffuse, light.linear), (glm::Vec3::zeros(), 0.1));
+}
+
+#[test]
+fn uniforms_accept_variables_of_their_type() {
+    let params = ShaderParams {
+        values: BTreeMap::from([
+            ("strength".to_owned(), UniformValue::Float(0.0)),
+            ("tint".to_owned(), UniformValue::Vec4([0.0; 4])),
+        ]),
+    };
+    let strength = BindingTarget::Uniform("strength".to_owned());
+    let tint = BindingTarget::Uniform("tint".to_owned());
+    let color = VariableValue::Color(glm::vec3(1.0, 0.0, 0.0));
+    let vector = VariableValue::Vector(glm::vec3(1.0, 0.0, 0.0));
+
+    assert!(strength.accepts(VariableValue::Float(1.0), Some(&params)));
+    assert!(!strength.accepts(color, Some(&params)));
+    assert!(tint.accepts(color, Some(&params)));
+    assert!(!tint.accepts(vector, Some(&params)));
+    assert!(!tint.accepts(color, None));
+    assert!(BindingTarget::LightQuadratic.accepts(VariableValue::Float(1.0), None));
+    assert!(!BindingTarget::Scale.accepts(VariableValue::Float(1.0), None));
+}
+
+#[test]
+fn bindings_are_written_by_field_name() {
+    let ron = r#"(
+        variables: {"accent_color": Color((1.0, 0.5, 0.0))},
+        entities: [
+            (
+                model: "lamp",
+                bindings: {LightDiffuse: "accent_color", Uniform("tint"): "accent_color"},
+            ),
+        ],
+    )"#;
+    let scene = Scene::from_ron(ron).unwrap();
+    assert_eq!(scene.variables["accent_color"], VariableValue::Color(glm::vec3(1.0, 0.5, 0.0)));
+    let bindings = &scene.entities[0].bindings;
+    assert_eq!(bindings[&BindingTarget::LightDiffuse], "accent_color");
+    assert_eq!(bindings[&BindingTarget::Uniform("tint".to_owned())], "accent_color");
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use bevy_ecs::prelude::*;
+use nalgebra_glm as glm;
+use scene_editor::components::{Hidden, PointLight, ShaderParams};
+use scene_editor::resources::Lighting;
+use scene_editor::scene::Scene;
+use scene_editor::shader::UniformValue;
+use scene_editor::variables::{SceneVariables, VariableValue};
+use scene_editor::variants::{self, EntityOverride, Field, SceneVariants, Variant};
+
+fn light(diffuse: f32) -> PointLight {
+    let diffuse = glm::vec3(diffuse, diffuse, diffuse);
+    PointLight::new(glm::Vec3::zeros(), diffuse, glm::Vec3::zeros(), 1.0, 0.1, 0.01)
+}
+
+fn night() -> Variant<Entity> {
+    Variant {
+        lighting: Some(Lighting { exposure: 0.25, ..Default::default() }),
+        ..Default::default()
+    }
+}
+
+/// A lamp that is only lit at night, and a sign with a `glow` uniform
+fn world() -> (World, Entity, Entity) {
+    let mut world = World::new();
+    world.insert_resource(Lighting::default());
+    world.insert_resource(SceneVariables {
+        values: BTreeMap::from([("sky".to_owned(), VariableValue::Float(1.0))]),
+    });
+    let lamp = world.spawn((light(0.0), Hidden)).id();
+    let sign = world
+        .spawn(ShaderParams {
+            values: BTreeMap::from([("glow".to_owned(), UniformValue::Float(0.0))]),
+        })
+        .id();
+
+    let mut variant = night();
+    variant.variables.insert("sky".to_owned(), VariableValue::Float(0.1));
+    variant.entities.insert(
+        lamp,
+        EntityOverride { hidden: Some(false), point_light: Some(light(1.0)), ..Default::default() },
+    );
+    variant.entities.insert(
+        sign,
+        EntityOverride {
+            uniforms: BTreeMap::from([("glow".to_owned(), UniformValue::Float(2.0))]),
+            ..Default::default()
+        },
+    );
+    let mut variants = SceneVariants::default();
+    variants.variants.insert("night".to_owned(), variant);
+    world.insert_resource(variants);
+    (world, lamp, sign)
+}
+
+fn glow(world: &World, sign: Entity) -> UniformValue {
+    world.get::<ShaderParams>(sign).unwrap().values["glow"]
+}
+
+#[test]
+fn variants_round_trip_through_scene_files() {
+    let lamp = EntityOverride { hidden: Some(false), ..Default::default() };
+    let variant = Variant {
+        variables: BTreeMap::from([("sky".to_owned(), VariableValue::Float(0.1))]),
+        entities: BTreeMap::from([(1, lamp)]),
+        ..Default::default()
+    };
+    let mut scene = Scene::new()
+        .variant("night", variant.clone())
+        .spawn_model("cube")
+        .spawn_model("lamp")
+        .point_light(light(0.0))
+        .finish();
+    scene.entities[1].hidden = true;
+
+    let ron = scene.to_ron().unwrap();
+    assert!(ron.contains("hidden: true"), "{ron}");
+    assert!(!ron.contains("lighting"), "{ron}");
+
+    let loaded = Scene::from_ron(&ron).unwrap();
+    assert_eq!(loaded.variants["night"], variant);
+    assert!(!loaded.entities[0].hidden);
+    assert!(loaded.entities[1].hidden);
+}
+
+#[test]
+fn switching_applies_a_variant_and_puts_the_base_back() {
+    let (mut world, lamp, sign) = world();
+
+    assert_eq!(variants::switch(&mut world, Some("night")), None);
+    assert_eq!(world.resource::<SceneVariants>().active(), Some("night"));
+    assert_eq!(world.resource::<Lighting>().exposure, 0.25);
+    assert_eq!(world.resource::<SceneVariables>().values["sky"], VariableValue::Float(0.1));
+    assert!(world.get::<Hidden>(lamp).is_none());
+    assert_eq!(*world.get::<PointLight>(lamp).unwrap(), light(1.0));
+    assert_eq!(glow(&world, sign), UniformValue::Float(2.0));
+
+    assert_eq!(variants::switch(&mut world, None).as_deref(), Some("night"));
+    assert_eq!(world.resource::<SceneVariants>().active(), None);
+    assert_eq!(world.resource::<Lighting>().exposure, 1.0);
+    assert_eq!(world.resource::<SceneVariables>().values["sky"], VariableValue::Float(1.0));
+    assert!(world.get::<Hidden>(lamp).is_some());
+    assert_eq!(*world.get::<PointLight>(lamp).unwrap(), light(0.0));
+    assert_eq!(glow(&world, sign), UniformValue::Float(0.0));
+}
+
+#[test]
+fn edits_are_kept_in_the_variant_that_replaces_the_field() {
+    let (mut world, lamp, sign) = world();
+    variants::switch(&mut world, Some("night"));
+
+    // Replaced by the variant
+    *world.get_mut::<PointLight>(lamp).unwrap() = light(0.5);
+    // Not replaced, so it is an edit of the base scene
+    world.entity_mut(sign).insert(Hidden);
+
+    variants::switch(&mut world, None);
+    assert_eq!(*world.get::<PointLight>(lamp).unwrap(), light(0.0));
+    assert!(world.get::<Hidden>(sign).is_some());
+
+    let saved = variants::with_base(&mut world, |world| {
+        world.resource::<SceneVariants>().variants["night"].entities[&lamp].point_light
+    });
+    assert_eq!(saved, Some(light(0.5)));
+
+    variants::switch(&mut world, Some("night"));
+    assert_eq!(*world.get::<PointLight>(lamp).unwrap(), light(0.5));
+    assert!(world.get::<Hidden>(sign).is_some());
+}
+
+#[test]
+fn overriding_a_field_starts_from_the_base_value() {
+    let (mut world, lamp, sign) = world();
+    variants::switch(&mut world, Some("night"));
+
+    // Hiding is flipped, so overriding it makes a difference right away
+    variants::set_overridden(&mut world, Some(sign), Field::Hidden, true);
+    assert!(world.get::<Hidden>(sign).is_some());
+    let variant = world.resource::<SceneVariants>().active_variant().unwrap();
+    assert!(variant.overrides(Some(sign), Field::Hidden));
+
+    // Other fields keep their value until they are edited
+    variants::set_overridden(&mut world, None, Field::Lighting, false);
+    assert_eq!(world.resource::<Lighting>().exposure, 1.0);
+    variants::set_overridden(&mut world, None, Field::Lighting, true);
+    assert_eq!(world.resource::<Lighting>().exposure, 1.0);
+
+    variants::set_overridden(&mut world, Some(lamp), Field::PointLight, false);
+    assert_eq!(*world.get::<PointLight>(lamp).unwrap(), light(0.0));
+
+    variants::set_overridden(&mut world, Some(sign), Field::Hidden, false);
+    assert!(world.get::<Hidden>(sign).is_none());
+    let variant = world.resource::<SceneVariants>().active_variant().unwrap();
+    assert!(!variant.overrides(Some(sign), Field::Hidden));
+    assert!(variant.overrides(Some(sign), Field::Uniforms));
+
+    variants::switch(&mut world, None);
+    assert!(world.get::<Hidden>(sign).is_none());
+    assert_eq!(*world.get::<PointLight>(lamp).unwrap(), light(0.0));
+}
+
+#[test]
+fn removing_the_shown_variant_shows_the_base_scene() {
+    let (mut world, lamp, _) = world();
+    variants::add(&mut world, "dusk");
+    assert_eq!(world.resource::<SceneVariants>().active(), Some("dusk"));
+
+    variants::switch(&mut world, Some("night"));
+    variants::remove(&mut world, "night");
+    let variants = world.resource::<SceneVariants>();
+    assert_eq!(variants.active(), None);
+    assert_eq!(variants.variants.keys().collect::<Vec<_>>(), ["dusk"]);
+    assert!(world.get::<Hidden>(lamp).is_some());
+}
+
+#[test]
+fn scene_files_refer_to_entities_by_index() {
+    let spawned = [Entity::from_raw(7), Entity::from_raw(3)];
+    let lamp = EntityOverride { hidden: Some(true), ..Default::default() };
+    let stored = BTreeMap::from([(
+        "night".to_owned(),
+        Variant { entities: BTreeMap::from([(1, lamp.clone()), (5, lamp)]), ..Default::default() },
+    )]);
+
+    let variants = SceneVariants::from_scene(&stored, &spawned);
+    let entities = &variants.variants["night"].entities;
+    assert_eq!(entities.keys().copied().collect::<Vec<_>>(), [Entity::from_raw(3)]);
+
+    let index = |entity: Entity| spawned.iter().position(|&e| e == entity);
+    let saved = variants.to_scene(index);
+    assert_eq!(saved["night"].entities.keys().copied().collect::<Vec<_>>(), [1]);
+}
+
+#[test]
+fn variants_are_exported_next_to_the_scene() {
+    assert_eq!(
+        variants::export_path(Path::new("scenes/level.ron"), "night"),
+        PathBuf::from("scenes/level_night.ron")
+    );
+    assert_eq!(
+        variants::export_path(Path::new("level.ron"), "late night/rain"),
+        PathBuf::from("level_late_night_rain.ron")
+    );
+}
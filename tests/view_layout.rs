@@ -0,0 +1,93 @@
+use nalgebra_glm as glm;
+use scene_editor::resources::{
+    Camera, CameraPose, ProjectionMode, ViewLayout, ViewPasses, ViewRect,
+};
+
+use crate::common::assert_close;
+
+mod common;
+
+#[test]
+fn single_view_covers_the_window() {
+    let layout = ViewLayout::new(800, 600);
+    let rects = layout.rects(800, 600);
+    assert_eq!(rects, vec![(0, ViewRect { x: 0, y: 0, width: 800, height: 600 })]);
+}
+
+#[test]
+fn quad_view_splits_the_window_without_gaps() {
+    let mut layout = ViewLayout::new(801, 601);
+    layout.quad = true;
+
+    let rects = layout.rects(801, 601);
+    assert_eq!(rects.len(), 4);
+    let area: i32 = rects.iter().map(|(_, r)| r.width * r.height).sum();
+    assert_eq!(area, 801 * 601);
+
+    // The cursor is measured from the top left, so the perspective view is under (0, 0)
+    assert_eq!(layout.view_at(801, 601, 0.0, 0.0), Some(0));
+    assert_eq!(layout.view_at(801, 601, 800.0, 0.0), Some(1));
+    assert_eq!(layout.view_at(801, 601, 0.0, 600.0), Some(2));
+    assert_eq!(layout.view_at(801, 601, 800.0, 600.0), Some(3));
+}
+
+#[test]
+fn activating_a_view_swaps_cameras() {
+    let mut layout = ViewLayout::new(800, 600);
+    layout.quad = true;
+    let mut camera = Camera::looking_forward(800, 600);
+    camera.pos.x = 5.0;
+
+    layout.activate(1, &mut camera);
+    assert_eq!(layout.active(), 1);
+    assert_eq!(camera.mode, ProjectionMode::Orthographic);
+
+    // Going back restores the perspective camera where it was left
+    layout.activate(0, &mut camera);
+    assert_eq!(camera.mode, ProjectionMode::Perspective);
+    assert_eq!(camera.pos.x, 5.0);
+}
+
+#[test]
+fn views_have_their_own_passes() {
+    let mut layout = ViewLayout::new(800, 600);
+    layout.passes_mut(1).shadows = false;
+
+    assert!(!layout.passes(1).shadows);
+    assert_eq!(layout.passes(0), ViewPasses::default());
+}
+
+#[test]
+fn orthographic_views_do_not_shrink_with_distance() {
+    let mut camera = Camera::looking_forward(800, 400);
+    camera.mode = ProjectionMode::Orthographic;
+    camera.ortho_height = 10.0;
+    camera.update_projection(800, 400);
+
+    // The view is 10 units tall and twice as wide, however far away the corner is
+    for depth in [1.0, 50.0, 300.0] {
+        let corner = camera.projection * camera.view() * glm::vec4(10.0, 5.0, -depth, 1.0);
+        assert_close(corner.xy() / corner.w, glm::vec2(1.0, 1.0));
+    }
+
+    camera.mode = ProjectionMode::Perspective;
+    camera.update_projection(800, 400);
+    assert_eq!(camera.projection, Camera::perspective(800, 400));
+}
+
+#[test]
+fn axis_views_look_along_the_world_axes() {
+    let front =
+        |yaw: f64, pitch: f64| CameraPose { position: glm::Vec3::zeros(), yaw, pitch }.front();
+    assert_close(front(-90.0, 0.0), glm::vec3(0.0, 0.0, -1.0));
+    assert_close(front(180.0, 0.0), glm::vec3(-1.0, 0.0, 0.0));
+    assert_close(front(-90.0, -90.0), glm::vec3(0.0, -1.0, 0.0));
+
+    // Looking straight down still gives a view with -Z at the top of the screen
+    let camera = Camera::looking_at_origin(800, 600, -90.0, -90.0, 10.0);
+    assert_close(camera.pos, glm::vec3(0.0, 10.0, 0.0));
+    let view = camera.view();
+    assert!(view.iter().all(|v| v.is_finite()));
+    assert_close((view * glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz(), glm::vec3(0.0, 0.0, -10.0));
+    assert!((view * glm::vec4(0.0, 0.0, -1.0, 1.0)).y > 0.0);
+}
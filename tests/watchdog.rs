@@ -0,0 +1,58 @@
+#![cfg(feature = "editor")]
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use scene_editor::watchdog::FrameWatchdog;
+
+/// Watchdog with a clock that only moves when the returned function is called
+fn fake_watchdog(threshold: Duration) -> (FrameWatchdog, impl Fn(Duration)) {
+    let time = Arc::new(Mutex::new(Instant::now()));
+    let clock = time.clone();
+    let watchdog = FrameWatchdog::with_clock(threshold, move || *clock.lock().unwrap());
+    (watchdog, move |duration| *time.lock().unwrap() += duration)
+}
+
+#[test]
+fn only_frames_past_the_threshold_are_stalled() {
+    let (watchdog, advance) = fake_watchdog(Duration::from_secs(2));
+    watchdog.begin_frame();
+    advance(Duration::from_secs(2));
+    assert_eq!(watchdog.stalled_for(), None);
+    assert_eq!(watchdog.end_frame(), None);
+
+    watchdog.begin_frame();
+    advance(Duration::from_secs(3));
+    assert_eq!(watchdog.stalled_for(), Some(Duration::from_secs(3)));
+    assert_eq!(watchdog.end_frame(), Some(Duration::from_secs(3)));
+    // Between frames nothing is stalled
+    assert_eq!(watchdog.stalled_for(), None);
+    assert_eq!(watchdog.end_frame(), None);
+}
+
+#[test]
+fn stalled_frame_is_reported_once() {
+    let (watchdog, advance) = fake_watchdog(Duration::from_secs(2));
+    watchdog.begin_frame();
+    advance(Duration::from_secs(1));
+    assert_eq!(watchdog.check(), None);
+    advance(Duration::from_secs(2));
+    assert_eq!(watchdog.check(), Some(Duration::from_secs(3)));
+    advance(Duration::from_secs(1));
+    assert_eq!(watchdog.check(), None);
+    watchdog.end_frame();
+
+    // A new frame can be reported again, but not once it is done
+    watchdog.begin_frame();
+    advance(Duration::from_secs(5));
+    watchdog.end_frame();
+    assert_eq!(watchdog.check(), None);
+}
+
+#[test]
+fn watchdog_thread_stops_with_the_watchdog() {
+    let (watchdog, _) = fake_watchdog(Duration::from_secs(2));
+    let thread = watchdog.spawn(|_| panic!("no frame was drawn"));
+    drop(watchdog);
+    thread.join().unwrap();
+}